@@ -0,0 +1,20 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::Settings;
+
+/// Identifies Idleview settings backups, so unrelated JSON isn't restored by mistake
+pub const BACKUP_FORMAT: &str = "idleview-settings";
+
+/// Bumped when the backup layout changes in a way older apps can't read
+pub const BACKUP_VERSION: u32 = 1;
+
+/// Full settings plus where and when they were saved
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct SettingsBackup {
+    pub format: String,
+    pub version: u32,
+    pub created_at: u64,       // Unix timestamp in milliseconds
+    pub app_version: String,   // Idleview version that wrote the backup
+    pub settings: Settings,
+}
@@ -0,0 +1,47 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// One occurrence of a calendar event
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct CalendarEvent {
+    pub title: String,
+    pub start: u64,  // Unix timestamp in milliseconds
+    pub end: u64,  // Unix timestamp in milliseconds, exclusive
+    pub all_day: bool,  // start and end are local midnights
+    #[serde(default)]
+    pub location: Option<String>,
+    pub calendar: String,  // Name of the feed it came from
+}
+
+/// Response of GET /api/calendar
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct CalendarReport {
+    pub events: Vec<CalendarEvent>,  // Upcoming and ongoing, soonest first
+    pub fetched_at: Option<u64>,  // Unix timestamp in milliseconds, None before the first refresh
+    #[serde(default)]
+    pub errors: Vec<String>,  // Feeds that failed in the last refresh, with why
+}
+
+/// Code to enter at Google's verification page, from POST /api/calendar/google/connect
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct GoogleDeviceCode {
+    pub user_code: String,
+    pub verification_url: String,
+    pub expires_in: u64,  // Seconds
+}
+
+/// Response of GET /api/calendar/google
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct GoogleCalendarStatus {
+    pub connected: bool,
+    #[serde(default)]
+    pub pending: Option<GoogleDeviceCode>,  // Waiting for the code to be entered
+    #[serde(default)]
+    pub last_error: Option<String>,
+}
+
+/// Body of PUT /api/calendar/caldav/:name/password; an empty password removes it
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct CalDavPasswordRequest {
+    pub password: String,
+}
@@ -0,0 +1,493 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::VecDeque;
+
+use crate::backup::SettingsBackup;
+use crate::calendar::{CalDavPasswordRequest, CalendarReport, GoogleCalendarStatus, GoogleDeviceCode};
+use crate::display::{
+    Brightness, BrightnessRequest, DisplayPower, DisplaySleep, MonitorInfo, NightMode, PowerState, Presence,
+};
+use crate::events::{EventEnvelope, SseParser};
+use crate::health::Health;
+use crate::history::SettingsHistoryEntry;
+use crate::market::{CryptoReport, StocksReport};
+use crate::message::{MessageRequest, QueuedMessage};
+use crate::music::{MediaDevice, NowPlaying, SpotifyAuthorization, SpotifyCallbackRequest, SpotifyStatus};
+use crate::news::NewsReport;
+use crate::pairing::{PairedDevice, PairingResult, PairingStarted};
+use crate::photo::CurrentPhoto;
+use crate::quote::Quote;
+use crate::settings::{Alarm, ProfilesSettings, Settings, SettingsDefaults};
+use crate::sports::SportsReport;
+use crate::sync::SyncStatus;
+use crate::system::{Connectivity, SystemStatus, UpdateInfo, WatchdogStatus};
+use crate::tasks::TasksReport;
+use crate::timer::{Timer, TimerRequest};
+use crate::weather::WeatherReport;
+use crate::{API_VERSION, API_VERSION_HEADER};
+
+/// Minimal client for the Idleview HTTP API
+#[derive(Clone)]
+pub struct IdleviewClient {
+    base_url: String,
+    http: reqwest::Client,
+    token: Option<String>,
+}
+
+impl IdleviewClient {
+    /// Create a client for a frame, e.g. `IdleviewClient::new("http://192.168.1.20:8737")`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_http_client(base_url, reqwest::Client::new())
+    }
+
+    pub fn with_http_client(base_url: impl Into<String>, http: reqwest::Client) -> Self {
+        let base_url = base_url.into().trim_end_matches('/').to_string();
+        Self { base_url, http, token: None }
+    }
+
+    /// Send the frame's API token, needed for every request that changes something
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/api/v{}{}", self.base_url, API_VERSION, path)
+    }
+
+    async fn execute(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response, String> {
+        let request = request.header(API_VERSION_HEADER, API_VERSION.to_string());
+        let request = match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        };
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {}", e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(format!("Idleview API error ({}): {}", status, error_text));
+        }
+        Ok(response)
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: reqwest::RequestBuilder) -> Result<T, String> {
+        self.execute(request)
+            .await?
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse response: {}", e))
+    }
+
+    async fn post_json<B: Serialize, T: DeserializeOwned>(&self, path: &str, body: &B) -> Result<T, String> {
+        self.send(self.http.post(self.url(path)).json(body)).await
+    }
+
+    /// GET /api/v1/settings
+    pub async fn settings(&self) -> Result<Settings, String> {
+        self.send(self.http.get(self.url("/settings"))).await
+    }
+
+    /// PUT /api/v1/settings
+    pub async fn update_settings(&self, settings: &Settings) -> Result<Settings, String> {
+        self.send(self.http.put(self.url("/settings")).json(settings)).await
+    }
+
+    /// PATCH /api/v1/settings with a partial settings document
+    pub async fn patch_settings(&self, updates: &serde_json::Value) -> Result<Settings, String> {
+        self.send(self.http.patch(self.url("/settings")).json(updates)).await
+    }
+
+    /// GET /api/v1/settings/defaults
+    pub async fn settings_defaults(&self) -> Result<SettingsDefaults, String> {
+        self.send(self.http.get(self.url("/settings/defaults"))).await
+    }
+
+    /// POST /api/v1/auth/rotate, returning the new token. This client keeps using the old one.
+    pub async fn rotate_token(&self) -> Result<String, String> {
+        let response: serde_json::Value = self.post_json("/auth/rotate", &json!({})).await?;
+        response["token"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| "Response has no token".to_string())
+    }
+
+    /// POST /api/v1/pair/start, making the frame show a PIN
+    pub async fn start_pairing(&self) -> Result<PairingStarted, String> {
+        self.post_json("/pair/start", &json!({})).await
+    }
+
+    /// POST /api/v1/pair with the PIN shown on the frame. Use the returned token with `with_token`.
+    pub async fn pair(&self, pin: &str, name: Option<&str>) -> Result<PairingResult, String> {
+        self.post_json("/pair", &json!({ "pin": pin, "name": name })).await
+    }
+
+    /// GET /api/v1/pair
+    pub async fn paired_devices(&self) -> Result<Vec<PairedDevice>, String> {
+        self.send(self.http.get(self.url("/pair"))).await
+    }
+
+    /// DELETE /api/v1/pair/:id
+    pub async fn unpair(&self, id: &str) -> Result<(), String> {
+        let path = format!("/pair/{}", urlencoding::encode(id));
+        self.execute(self.http.delete(self.url(&path))).await.map(|_| ())
+    }
+
+    /// GET /api/v1/settings/schema
+    pub async fn settings_schema(&self) -> Result<serde_json::Value, String> {
+        self.send(self.http.get(self.url("/settings/schema"))).await
+    }
+
+    /// GET /api/v1/settings/:section, e.g. "units"
+    pub async fn settings_section(&self, section: &str) -> Result<serde_json::Value, String> {
+        let path = format!("/settings/{}", urlencoding::encode(section));
+        self.send(self.http.get(self.url(&path))).await
+    }
+
+    /// PATCH /api/v1/settings/:section with a partial section
+    pub async fn patch_settings_section(
+        &self,
+        section: &str,
+        updates: &serde_json::Value,
+    ) -> Result<serde_json::Value, String> {
+        let path = format!("/settings/{}", urlencoding::encode(section));
+        self.send(self.http.patch(self.url(&path)).json(updates)).await
+    }
+
+    /// POST /api/v1/settings/reset
+    pub async fn reset_settings(&self) -> Result<Settings, String> {
+        self.post_json("/settings/reset", &json!({})).await
+    }
+
+    /// GET /api/v1/settings/backup
+    pub async fn backup_settings(&self) -> Result<SettingsBackup, String> {
+        self.send(self.http.get(self.url("/settings/backup"))).await
+    }
+
+    /// POST /api/v1/settings/restore
+    pub async fn restore_settings(&self, backup: &SettingsBackup) -> Result<Settings, String> {
+        self.post_json("/settings/restore", backup).await
+    }
+
+    /// GET /api/v1/settings/history
+    pub async fn settings_history(&self) -> Result<Vec<SettingsHistoryEntry>, String> {
+        self.send(self.http.get(self.url("/settings/history"))).await
+    }
+
+    /// GET /api/v1/settings/profiles
+    pub async fn profiles(&self) -> Result<ProfilesSettings, String> {
+        self.send(self.http.get(self.url("/settings/profiles"))).await
+    }
+
+    /// POST /api/v1/settings/profiles/:name/activate
+    pub async fn activate_profile(&self, name: &str) -> Result<Settings, String> {
+        let path = format!("/settings/profiles/{}/activate", urlencoding::encode(name));
+        self.post_json(&path, &json!({})).await
+    }
+
+    /// GET /api/v1/alarms
+    pub async fn alarms(&self) -> Result<Vec<Alarm>, String> {
+        self.send(self.http.get(self.url("/alarms"))).await
+    }
+
+    /// POST /api/v1/alarms; the returned alarm carries its assigned id
+    pub async fn create_alarm(&self, alarm: &Alarm) -> Result<Alarm, String> {
+        self.post_json("/alarms", alarm).await
+    }
+
+    /// PUT /api/v1/alarms/:id
+    pub async fn update_alarm(&self, id: u32, alarm: &Alarm) -> Result<Alarm, String> {
+        let path = format!("/alarms/{}", id);
+        self.send(self.http.put(self.url(&path)).json(alarm)).await
+    }
+
+    /// DELETE /api/v1/alarms/:id
+    pub async fn delete_alarm(&self, id: u32) -> Result<(), String> {
+        let path = format!("/alarms/{}", id);
+        self.execute(self.http.delete(self.url(&path))).await.map(|_| ())
+    }
+
+    /// GET /api/v1/weather
+    pub async fn weather(&self) -> Result<WeatherReport, String> {
+        self.send(self.http.get(self.url("/weather"))).await
+    }
+
+    /// GET /api/v1/photo/current
+    pub async fn current_photo(&self) -> Result<Option<CurrentPhoto>, String> {
+        self.send(self.http.get(self.url("/photo/current"))).await
+    }
+
+    /// POST /api/v1/photo/current
+    pub async fn set_current_photo(&self, photo: &CurrentPhoto) -> Result<CurrentPhoto, String> {
+        self.post_json("/photo/current", photo).await
+    }
+
+    /// POST /api/v1/photo/next
+    pub async fn next_photo(&self) -> Result<(), String> {
+        self.execute(self.http.post(self.url("/photo/next"))).await.map(|_| ())
+    }
+
+    /// POST /api/v1/refresh
+    pub async fn refresh(&self) -> Result<(), String> {
+        self.execute(self.http.post(self.url("/refresh"))).await.map(|_| ())
+    }
+
+    /// POST /api/v1/location/active, `None` switches back to auto-detection
+    pub async fn set_active_location(&self, name: Option<&str>) -> Result<Settings, String> {
+        self.post_json("/location/active", &json!({ "name": name })).await
+    }
+
+    /// POST /api/v1/scenes/:name/activate
+    pub async fn activate_scene(&self, name: &str) -> Result<Settings, String> {
+        let path = format!("/scenes/{}/activate", urlencoding::encode(name));
+        self.post_json(&path, &json!({})).await
+    }
+
+    /// GET /api/v1/system
+    pub async fn system_status(&self) -> Result<SystemStatus, String> {
+        self.send(self.http.get(self.url("/system"))).await
+    }
+
+    /// POST /api/v1/message
+    pub async fn send_message(&self, message: &MessageRequest) -> Result<QueuedMessage, String> {
+        self.post_json("/message", message).await
+    }
+
+    /// GET /api/v1/timer
+    pub async fn timer(&self) -> Result<Option<Timer>, String> {
+        self.send(self.http.get(self.url("/timer"))).await
+    }
+
+    /// POST /api/v1/timer
+    pub async fn start_timer(&self, timer: &TimerRequest) -> Result<Timer, String> {
+        self.post_json("/timer", timer).await
+    }
+
+    /// DELETE /api/v1/timer
+    pub async fn cancel_timer(&self) -> Result<(), String> {
+        self.execute(self.http.delete(self.url("/timer"))).await.map(|_| ())
+    }
+
+    /// GET /api/v1/display/power
+    pub async fn display_power(&self) -> Result<DisplayPower, String> {
+        self.send(self.http.get(self.url("/display/power"))).await
+    }
+
+    /// POST /api/v1/display/power
+    pub async fn set_display_power(&self, power: PowerState) -> Result<DisplayPower, String> {
+        self.post_json("/display/power", &DisplayPower { power }).await
+    }
+
+    /// GET /api/v1/display/brightness
+    pub async fn brightness(&self) -> Result<Brightness, String> {
+        self.send(self.http.get(self.url("/display/brightness"))).await
+    }
+
+    /// PUT /api/v1/display/brightness
+    pub async fn set_brightness(&self, percent: u8) -> Result<Brightness, String> {
+        self.send(self.http.put(self.url("/display/brightness")).json(&BrightnessRequest { percent })).await
+    }
+
+    /// GET /api/v1/display/night
+    pub async fn night_mode(&self) -> Result<NightMode, String> {
+        self.send(self.http.get(self.url("/display/night"))).await
+    }
+
+    /// GET /api/v1/display/sleep
+    pub async fn display_sleep(&self) -> Result<DisplaySleep, String> {
+        self.send(self.http.get(self.url("/display/sleep"))).await
+    }
+
+    /// GET /api/v1/monitors
+    pub async fn monitors(&self) -> Result<Vec<MonitorInfo>, String> {
+        self.send(self.http.get(self.url("/monitors"))).await
+    }
+
+    /// GET /api/v1/presence
+    pub async fn presence(&self) -> Result<Presence, String> {
+        self.send(self.http.get(self.url("/presence"))).await
+    }
+
+    /// POST /api/v1/presence - Report motion, waking the screen
+    pub async fn report_motion(&self) -> Result<Presence, String> {
+        self.send(self.http.post(self.url("/presence"))).await
+    }
+
+    /// GET /api/v1/screenshot, as PNG bytes; `scale` between 0 and 1 downscales it
+    pub async fn screenshot(&self, scale: Option<f32>) -> Result<Vec<u8>, String> {
+        let mut request = self.http.get(self.url("/screenshot"));
+        if let Some(scale) = scale {
+            request = request.query(&[("scale", scale)]);
+        }
+        let bytes = self
+            .execute(request)
+            .await?
+            .bytes()
+            .await
+            .map_err(|e| format!("Failed to read response: {}", e))?;
+        Ok(bytes.to_vec())
+    }
+
+    /// POST /api/v1/system/reboot
+    pub async fn reboot(&self) -> Result<(), String> {
+        self.execute(self.http.post(self.url("/system/reboot"))).await.map(|_| ())
+    }
+
+    /// POST /api/v1/system/shutdown
+    pub async fn shutdown(&self) -> Result<(), String> {
+        self.execute(self.http.post(self.url("/system/shutdown"))).await.map(|_| ())
+    }
+
+    /// POST /api/v1/system/check-update
+    pub async fn check_update(&self) -> Result<UpdateInfo, String> {
+        self.send(self.http.post(self.url("/system/check-update"))).await
+    }
+
+    /// GET /api/v1/connectivity
+    pub async fn connectivity(&self) -> Result<Connectivity, String> {
+        self.send(self.http.get(self.url("/connectivity"))).await
+    }
+
+    /// GET /api/v1/watchdog
+    pub async fn watchdog(&self) -> Result<WatchdogStatus, String> {
+        self.send(self.http.get(self.url("/watchdog"))).await
+    }
+
+    /// GET /api/v1/health. An unhealthy frame answers 503, which is returned as
+    /// an error like any other failed request.
+    pub async fn health(&self) -> Result<Health, String> {
+        self.send(self.http.get(self.url("/health"))).await
+    }
+
+    /// GET /api/v1/calendar
+    pub async fn calendar(&self) -> Result<CalendarReport, String> {
+        self.send(self.http.get(self.url("/calendar"))).await
+    }
+
+    /// GET /api/v1/calendar/google
+    pub async fn google_calendar_status(&self) -> Result<GoogleCalendarStatus, String> {
+        self.send(self.http.get(self.url("/calendar/google"))).await
+    }
+
+    /// POST /api/v1/calendar/google/connect
+    pub async fn connect_google_calendar(&self) -> Result<GoogleDeviceCode, String> {
+        self.send(self.http.post(self.url("/calendar/google/connect"))).await
+    }
+
+    /// DELETE /api/v1/calendar/google
+    pub async fn disconnect_google_calendar(&self) -> Result<(), String> {
+        self.execute(self.http.delete(self.url("/calendar/google"))).await.map(|_| ())
+    }
+
+    /// PUT /api/v1/calendar/caldav/:name/password
+    pub async fn set_caldav_password(&self, name: &str, password: &str) -> Result<(), String> {
+        let path = format!("/calendar/caldav/{}/password", urlencoding::encode(name));
+        let request = CalDavPasswordRequest { password: password.to_string() };
+        self.execute(self.http.put(self.url(&path)).json(&request)).await.map(|_| ())
+    }
+
+    /// GET /api/v1/quote
+    pub async fn quote(&self) -> Result<Quote, String> {
+        self.send(self.http.get(self.url("/quote"))).await
+    }
+
+    /// GET /api/v1/news
+    pub async fn news(&self) -> Result<NewsReport, String> {
+        self.send(self.http.get(self.url("/news"))).await
+    }
+
+    /// GET /api/v1/stocks
+    pub async fn stocks(&self) -> Result<StocksReport, String> {
+        self.send(self.http.get(self.url("/stocks"))).await
+    }
+
+    /// GET /api/v1/crypto
+    pub async fn crypto(&self) -> Result<CryptoReport, String> {
+        self.send(self.http.get(self.url("/crypto"))).await
+    }
+
+    /// GET /api/v1/sports
+    pub async fn sports(&self) -> Result<SportsReport, String> {
+        self.send(self.http.get(self.url("/sports"))).await
+    }
+
+    /// GET /api/v1/tasks
+    pub async fn tasks(&self) -> Result<TasksReport, String> {
+        self.send(self.http.get(self.url("/tasks"))).await
+    }
+
+    /// GET /api/v1/now-playing
+    pub async fn now_playing(&self) -> Result<Option<NowPlaying>, String> {
+        self.send(self.http.get(self.url("/now-playing"))).await
+    }
+
+    /// GET /api/v1/media/devices
+    pub async fn media_devices(&self) -> Result<Vec<MediaDevice>, String> {
+        self.send(self.http.get(self.url("/media/devices"))).await
+    }
+
+    /// GET /api/v1/spotify
+    pub async fn spotify_status(&self) -> Result<SpotifyStatus, String> {
+        self.send(self.http.get(self.url("/spotify"))).await
+    }
+
+    /// POST /api/v1/spotify/connect
+    pub async fn connect_spotify(&self) -> Result<SpotifyAuthorization, String> {
+        self.send(self.http.post(self.url("/spotify/connect"))).await
+    }
+
+    /// POST /api/v1/spotify/callback
+    pub async fn finish_spotify_connect(&self, redirected_to: &str) -> Result<(), String> {
+        let body = SpotifyCallbackRequest { url: redirected_to.to_string() };
+        self.execute(self.http.post(self.url("/spotify/callback")).json(&body)).await.map(|_| ())
+    }
+
+    /// DELETE /api/v1/spotify
+    pub async fn disconnect_spotify(&self) -> Result<(), String> {
+        self.execute(self.http.delete(self.url("/spotify"))).await.map(|_| ())
+    }
+
+    /// GET /api/v1/sync
+    pub async fn sync_status(&self) -> Result<SyncStatus, String> {
+        self.send(self.http.get(self.url("/sync"))).await
+    }
+
+    /// GET /api/v1/events, as a stream of event envelopes
+    pub async fn events(&self) -> Result<EventStream, String> {
+        let response = self
+            .execute(self.http.get(self.url("/events")).header("Accept", "text/event-stream"))
+            .await?;
+        Ok(EventStream {
+            response,
+            parser: SseParser::default(),
+            pending: VecDeque::new(),
+        })
+    }
+}
+
+/// Events pushed by a frame, read one at a time
+pub struct EventStream {
+    response: reqwest::Response,
+    parser: SseParser,
+    pending: VecDeque<String>,
+}
+
+impl EventStream {
+    /// The next event, or None once the frame closes the stream
+    pub async fn next(&mut self) -> Option<Result<EventEnvelope, String>> {
+        loop {
+            if let Some(data) = self.pending.pop_front() {
+                return Some(serde_json::from_str(&data).map_err(|e| format!("Failed to parse event: {}", e)));
+            }
+            match self.response.chunk().await {
+                Ok(Some(chunk)) => self.pending.extend(self.parser.push(&chunk)),
+                Ok(None) => return None,
+                Err(e) => return Some(Err(format!("Failed to read events: {}", e))),
+            }
+        }
+    }
+}
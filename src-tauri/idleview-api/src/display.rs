@@ -0,0 +1,92 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::BurnInRefresh;
+
+/// Whether the screen is lit
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PowerState {
+    On,
+    Off,
+}
+
+/// Body of POST /api/display/power and response of GET /api/display/power
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayPower {
+    pub power: PowerState,
+}
+
+/// How the brightness is applied: the screen's backlight, or a dimming
+/// overlay in the frame when there is no backlight it can control
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BrightnessMethod {
+    Backlight,
+    Software,
+}
+
+/// Response of GET and PUT /api/display/brightness
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+pub struct Brightness {
+    pub percent: u8,
+    pub method: BrightnessMethod,
+}
+
+/// Body of PUT /api/display/brightness
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+pub struct BrightnessRequest {
+    pub percent: u8,  // 0-100
+}
+
+/// Whether night mode is on, sent with `night-mode-changed` and by
+/// GET /api/display/night
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NightMode {
+    pub active: bool,
+    pub dark_theme: bool,  // Whether the frame should switch to its dark theme
+}
+
+/// Whether the sleep schedule has the screen off, sent with
+/// `display-sleep-changed` and by GET /api/display/sleep
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Default)]
+pub struct DisplaySleep {
+    pub asleep: bool,
+    pub wakes_at: Option<String>,  // "HH:MM" while asleep
+}
+
+/// Whether someone is in the room, sent with `presence-changed` and by
+/// GET and POST /api/presence
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Presence {
+    pub present: bool,
+    pub last_motion: Option<u64>,  // Unix timestamp in milliseconds
+}
+
+/// How far the frame is moved from its place, in pixels; sent with
+/// `pixel-shift` while burn-in protection is on
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PixelShift {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// A burn-in refresh cycle the frame should run now, sent with `screen-refresh`
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+pub struct ScreenRefresh {
+    pub mode: BurnInRefresh,
+    pub duration: u64,  // Seconds
+}
+
+/// A monitor attached to the machine, listed by GET /api/monitors
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq)]
+pub struct MonitorInfo {
+    pub name: Option<String>,  // e.g. "HDMI-A-1"; None when the system doesn't say
+    pub width: u32,  // Physical pixels
+    pub height: u32,
+    pub x: i32,  // Position on the desktop, in physical pixels
+    pub y: i32,
+    pub scale_factor: f64,
+    pub primary: bool,
+    pub window: Option<String>,  // Label of the frame window showing on it
+}
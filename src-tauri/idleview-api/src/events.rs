@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::calendar::CalendarEvent;
+use crate::display::{DisplayPower, DisplaySleep, NightMode, PixelShift, Presence, ScreenRefresh};
+use crate::message::Message;
+use crate::music::NowPlaying;
+use crate::news::Headline;
+use crate::pairing::{PairedDevice, PairingPin};
+use crate::photo::CurrentPhoto;
+use crate::settings::{Alarm, Settings};
+use crate::sync::SyncStatus;
+use crate::system::{BatteryStatus, Connectivity, DiskStatus, UpdateInfo};
+use crate::timer::Timer;
+use crate::weather::WeatherData;
+
+/// Bumped only for breaking changes to the envelope or existing payloads.
+/// New event types and new payload fields do not change the version.
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// Event types and their payloads
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "payload", rename_all = "kebab-case")]
+pub enum ServerEvent {
+    SettingsUpdated(Box<Settings>),
+    PhotoUpdated(CurrentPhoto),
+    /// The frame should skip to a new photo, asked over the API or MQTT
+    RefreshPhoto,
+    LocationChanged { active: Option<String> },
+    WeatherUpdated(Box<WeatherData>),
+    DisplayPowerChanged(DisplayPower),
+    /// Weather alerts that weren't raised by the previous weather update
+    WeatherAlert { alerts: Vec<String> },
+    /// The CPU reached `webhooks.cpu_temperature_limit`
+    CpuTemperatureHigh { celsius: f32 },
+    /// Free space on a disk the frame writes to fell below `webhooks.low_disk_limit`
+    DiskSpaceLow(DiskStatus),
+    /// The battery dropped to `display.low_battery_warning` percent while discharging
+    BatteryLow(BatteryStatus),
+    /// The internet connection went away or came back
+    ConnectivityChanged(Connectivity),
+    /// Another track started, playback paused or resumed, or the music stopped (None)
+    NowPlayingChanged(Option<NowPlaying>),
+    /// An alarm's time came on one of its days
+    AlarmFired(Alarm),
+    /// Every second while a timer runs
+    TimerTick(Timer),
+    TimerFinished(Timer),
+    /// A timer was stopped before the end, or replaced by a new one
+    TimerCancelled(Timer),
+    /// Night mode started or ended
+    NightModeChanged(NightMode),
+    /// The sleep schedule turned the screen off, or it woke up
+    DisplaySleepChanged(DisplaySleep),
+    /// Someone came into the room, or nobody moved for `display.presence.idle_timeout`
+    PresenceChanged(Presence),
+    /// Burn-in protection moved the frame
+    PixelShift(PixelShift),
+    /// Burn-in protection wants a refresh cycle over the whole screen
+    ScreenRefresh(ScreenRefresh),
+    /// GitHub has a newer release than the one running
+    UpdateAvailable(UpdateInfo),
+    /// The frame started or stopped following the lead
+    SyncStatus(SyncStatus),
+    /// The lead's photo, for a following frame to show
+    SyncPhoto(CurrentPhoto),
+    /// A queued message comes up on the frame, for its `duration`
+    ShowMessage(Message),
+    /// The news feeds were refreshed, or turned off (no headlines), newest first
+    NewsUpdated { headlines: Vec<Headline> },
+    /// The calendars were refreshed: the upcoming events, for the frame only
+    CalendarUpdated(Vec<CalendarEvent>),
+    /// A device asked to pair: the PIN to show, for the frame only
+    PairingPin(PairingPin),
+    /// A device redeemed the PIN and got its token
+    PairingComplete(PairedDevice),
+    /// Any event type this version of the crate doesn't know about
+    #[serde(skip)]
+    Unknown,
+}
+
+impl ServerEvent {
+    /// The `type` field, also used as the Tauri event name
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            ServerEvent::SettingsUpdated(_) => "settings-updated",
+            ServerEvent::PhotoUpdated(_) => "photo-updated",
+            ServerEvent::RefreshPhoto => "refresh-photo",
+            ServerEvent::LocationChanged { .. } => "location-changed",
+            ServerEvent::WeatherUpdated(_) => "weather-updated",
+            ServerEvent::DisplayPowerChanged(_) => "display-power-changed",
+            ServerEvent::WeatherAlert { .. } => "weather-alert",
+            ServerEvent::CpuTemperatureHigh { .. } => "cpu-temperature-high",
+            ServerEvent::DiskSpaceLow(_) => "disk-space-low",
+            ServerEvent::BatteryLow(_) => "battery-low",
+            ServerEvent::ConnectivityChanged(_) => "connectivity-changed",
+            ServerEvent::NowPlayingChanged(_) => "now-playing-changed",
+            ServerEvent::AlarmFired(_) => "alarm-fired",
+            ServerEvent::TimerTick(_) => "timer-tick",
+            ServerEvent::TimerFinished(_) => "timer-finished",
+            ServerEvent::TimerCancelled(_) => "timer-cancelled",
+            ServerEvent::NightModeChanged(_) => "night-mode-changed",
+            ServerEvent::DisplaySleepChanged(_) => "display-sleep-changed",
+            ServerEvent::PresenceChanged(_) => "presence-changed",
+            ServerEvent::PixelShift(_) => "pixel-shift",
+            ServerEvent::ScreenRefresh(_) => "screen-refresh",
+            ServerEvent::UpdateAvailable(_) => "update-available",
+            ServerEvent::SyncStatus(_) => "sync-status",
+            ServerEvent::SyncPhoto(_) => "sync-photo",
+            ServerEvent::ShowMessage(_) => "show-message",
+            ServerEvent::NewsUpdated { .. } => "news-updated",
+            ServerEvent::CalendarUpdated(_) => "calendar-updated",
+            ServerEvent::PairingPin(_) => "pairing-pin",
+            ServerEvent::PairingComplete(_) => "pairing-complete",
+            ServerEvent::Unknown => "unknown",
+        }
+    }
+
+    /// Whether the payload is only for the frame's own screen. Like the reads
+    /// that need a token, it's kept off event streams and webhooks.
+    pub fn is_private(&self) -> bool {
+        matches!(self, ServerEvent::CalendarUpdated(_) | ServerEvent::PairingPin(_))
+    }
+}
+
+/// Envelope wrapping every event sent to the webview, SSE clients and other consumers:
+/// `{"version": 1, "type": "...", "timestamp": ..., "source": "...", "payload": ...}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "RawEnvelope")]
+pub struct EventEnvelope {
+    pub version: u32,
+    pub timestamp: u64,  // Unix timestamp in milliseconds
+    pub source: String,  // Subsystem that raised the event, e.g. "http"
+    #[serde(flatten)]
+    pub event: ServerEvent,
+}
+
+impl EventEnvelope {
+    pub fn new(event: ServerEvent, source: impl Into<String>) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+
+        EventEnvelope {
+            version: EVENT_SCHEMA_VERSION,
+            timestamp,
+            source: source.into(),
+            event,
+        }
+    }
+}
+
+/// Envelope as received, before the payload is matched to a known event type
+#[derive(Deserialize)]
+struct RawEnvelope {
+    version: u32,
+    timestamp: u64,
+    source: String,
+    #[serde(rename = "type")]
+    event_type: String,
+    #[serde(default)]
+    payload: serde_json::Value,
+}
+
+impl From<RawEnvelope> for EventEnvelope {
+    fn from(raw: RawEnvelope) -> Self {
+        let tagged = serde_json::json!({ "type": raw.event_type, "payload": raw.payload });
+        EventEnvelope {
+            version: raw.version,
+            timestamp: raw.timestamp,
+            source: raw.source,
+            event: serde_json::from_value(tagged).unwrap_or(ServerEvent::Unknown),
+        }
+    }
+}
+
+/// Splits a Server-Sent Events stream into the data of each event, as bytes
+/// arrive. Comments (the server's keep-alives) and other fields are skipped.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    /// Feed received bytes, returning the data of every event they complete
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(bytes);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(self.data.join("\n"));
+                    self.data.clear();
+                }
+            } else if let Some(value) = line.strip_prefix("data:") {
+                self.data.push(value.strip_prefix(' ').unwrap_or(value).to_string());
+            }
+        }
+        events
+    }
+}
@@ -0,0 +1,40 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Overall verdict, also used per provider
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Healthy,
+    Degraded,  // Working, but something needs attention
+    Unhealthy,  // Worth power-cycling the frame
+}
+
+/// Last outcome of calls to one external provider
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct ProviderStatus {
+    pub name: String,  // "location", "weather" or "photos"
+    pub status: HealthStatus,
+    pub last_success: Option<u64>,  // Unix timestamp in milliseconds
+    pub last_error: Option<String>,  // Error of the last call, if it failed
+}
+
+/// The config directory, where settings and tokens are written
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct DiskCacheHealth {
+    pub writable: bool,
+    pub available: Option<u64>,  // Free bytes on its disk
+}
+
+/// Response of GET /api/health
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct Health {
+    pub status: HealthStatus,
+    pub service: String,
+    pub app_version: String,
+    pub uptime: u64,  // Seconds since the app started
+    pub last_weather_fetch: Option<u64>,  // Unix timestamp in milliseconds
+    pub last_photo_fetch: Option<u64>,  // Unix timestamp in milliseconds
+    pub providers: Vec<ProviderStatus>,
+    pub disk_cache: DiskCacheHealth,
+}
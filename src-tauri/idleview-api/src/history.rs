@@ -0,0 +1,20 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// One settings mutation in the audit log
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct SettingsHistoryEntry {
+    pub timestamp: u64,  // Unix timestamp in milliseconds
+    pub source: String,  // "tauri", "http", "file", "owntracks", "schedule", ...
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,  // Address of the HTTP client that made the change
+    pub changes: Vec<SettingChange>,
+}
+
+/// A single changed value, addressed by its dotted path (e.g. "units.temperature_unit")
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq)]
+pub struct SettingChange {
+    pub key: String,
+    pub old: serde_json::Value,
+    pub new: serde_json::Value,
+}
@@ -0,0 +1,57 @@
+//! Types shared between the Idleview app and tools talking to its HTTP API,
+//! plus a small client for that API.
+
+pub mod backup;
+pub mod calendar;
+pub mod client;
+pub mod display;
+pub mod events;
+pub mod health;
+pub mod history;
+pub mod location;
+pub mod market;
+pub mod message;
+pub mod music;
+pub mod news;
+pub mod pairing;
+pub mod photo;
+pub mod quote;
+pub mod settings;
+pub mod sports;
+pub mod sync;
+pub mod system;
+pub mod tasks;
+pub mod timer;
+pub mod weather;
+
+pub use backup::SettingsBackup;
+pub use calendar::{CalDavPasswordRequest, CalendarEvent, CalendarReport, GoogleCalendarStatus, GoogleDeviceCode};
+pub use client::{EventStream, IdleviewClient};
+pub use display::{
+    Brightness, DisplayPower, DisplaySleep, MonitorInfo, NightMode, PixelShift, PowerState, Presence, ScreenRefresh,
+};
+pub use events::{EventEnvelope, ServerEvent};
+pub use health::{Health, HealthStatus};
+pub use history::{SettingChange, SettingsHistoryEntry};
+pub use location::Location;
+pub use market::{CryptoPrice, CryptoReport, StockQuote, StocksReport};
+pub use message::{Message, MessageRequest, QueuedMessage};
+pub use music::{MediaDevice, MediaDeviceKind, NowPlaying, NowPlayingSource, SpotifyAuthorization, SpotifyStatus};
+pub use news::{Headline, NewsReport};
+pub use pairing::{PairedDevice, PairingResult};
+pub use photo::CurrentPhoto;
+pub use quote::Quote;
+pub use settings::Settings;
+pub use sports::{MatchStatus, SportsMatch, SportsReport};
+pub use sync::SyncStatus;
+pub use system::{BatteryStatus, ClockStatus, Connectivity, SystemStatus, UpdateInfo, WatchdogIncident, WatchdogStatus};
+pub use tasks::{Task, TaskSource, TasksReport};
+pub use timer::{Timer, TimerRequest};
+pub use weather::{WeatherData, WeatherReport};
+
+/// Version of the HTTP API, served under `/api/v1`
+pub const API_VERSION: u32 = 1;
+
+/// Request and response header carrying the API version. A request naming a
+/// version the server doesn't speak is refused instead of half-working.
+pub const API_VERSION_HEADER: &str = "X-API-Version";
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+use crate::settings::SavedLocation;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Location {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub city: Option<String>,
+    pub country: Option<String>,
+    pub source: String,  // IP provider ("ip-api", "ipwhois", "ipinfo"), "geoclue", "gps", "saved" or "cache"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elevation: Option<f64>,  // Meters above sea level, from the Open-Meteo elevation API
+}
+
+impl From<&SavedLocation> for Location {
+    fn from(saved: &SavedLocation) -> Self {
+        Location {
+            latitude: saved.latitude,
+            longitude: saved.longitude,
+            city: Some(saved.name.clone()),
+            country: None,
+            source: "saved".to_string(),
+            elevation: None,
+        }
+    }
+}
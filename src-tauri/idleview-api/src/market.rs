@@ -0,0 +1,41 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Last price of a stock and how it moved since the previous close
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq)]
+pub struct StockQuote {
+    pub symbol: String,
+    pub price: f64,
+    pub change: f64,  // Since the previous close, in the stock's currency
+    pub change_percent: f64,
+    #[serde(default)]
+    pub currency: Option<String>,  // ISO code, when the provider gives one
+}
+
+/// Response of GET /api/stocks
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct StocksReport {
+    pub quotes: Vec<StockQuote>,  // In the order of stocks.symbols
+    pub fetched_at: Option<u64>,  // Unix timestamp in milliseconds, None before the first request
+    #[serde(default)]
+    pub errors: Vec<String>,  // Symbols that failed in the last request, with why; their last known quote is kept
+}
+
+/// Price of a coin and its change over the last 24 hours
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq)]
+pub struct CryptoPrice {
+    pub coin: String,  // CoinGecko coin ID
+    pub price: f64,
+    #[serde(default)]
+    pub change_24h_percent: Option<f64>,
+}
+
+/// Response of GET /api/crypto
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct CryptoReport {
+    pub prices: Vec<CryptoPrice>,  // In the order of crypto.coins
+    pub currency: String,  // What the prices are in, e.g. "usd"
+    pub fetched_at: Option<u64>,  // Unix timestamp in milliseconds, None before the first request
+    #[serde(default)]
+    pub error: Option<String>,  // Why the last request failed; the prices are then the last known ones
+}
@@ -0,0 +1,45 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Higher priorities are shown before anything already waiting
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MessagePriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+fn default_duration() -> u32 {
+    10
+}
+
+/// Body of POST /api/message
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct MessageRequest {
+    pub text: String,
+    #[serde(default = "default_duration")]
+    pub duration: u32,  // Seconds on screen
+    #[serde(default)]
+    pub priority: MessagePriority,
+    #[serde(default)]
+    pub sound: bool,  // Play a chime when it appears
+}
+
+/// A message as sent to the frame
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct Message {
+    pub id: u64,
+    pub text: String,
+    pub duration: u32,
+    pub priority: MessagePriority,
+    pub sound: bool,
+}
+
+/// Response of POST /api/message
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct QueuedMessage {
+    pub id: u64,
+    pub position: usize,  // Messages shown before this one; 0 when it's next
+}
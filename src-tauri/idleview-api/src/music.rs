@@ -0,0 +1,83 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Where the track on the frame comes from
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NowPlayingSource {
+    Spotify,
+    LastFm,
+    Chromecast,
+    Sonos,
+}
+
+impl NowPlayingSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NowPlayingSource::Spotify => "spotify",
+            NowPlayingSource::LastFm => "lastfm",
+            NowPlayingSource::Chromecast => "chromecast",
+            NowPlayingSource::Sonos => "sonos",
+        }
+    }
+}
+
+/// The track playing right now, from GET /api/now-playing and the
+/// `now-playing-changed` event
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq)]
+pub struct NowPlaying {
+    pub title: String,
+    pub artist: String,  // Every artist, comma separated
+    #[serde(default)]
+    pub album: Option<String>,
+    #[serde(default)]
+    pub album_art_url: Option<String>,  // Largest cover the service has
+    pub is_playing: bool,  // False while paused, or for a Last.fm scrobble that has finished
+    #[serde(default)]
+    pub progress_ms: Option<u64>,
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+    pub source: NowPlayingSource,
+    #[serde(default)]
+    pub device: Option<String>,  // Chromecast or Sonos speaker it plays on
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaDeviceKind {
+    Chromecast,
+    Sonos,
+}
+
+/// A Chromecast or Sonos speaker found on the local network, from GET
+/// /api/media/devices
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct MediaDevice {
+    pub name: String,  // As set in the Google Home or Sonos app; what music.media.devices lists
+    pub kind: MediaDeviceKind,
+    pub address: String,  // IP address and port
+}
+
+/// Response of POST /api/spotify/connect: the page to approve the frame at
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct SpotifyAuthorization {
+    pub authorize_url: String,
+    pub redirect_uri: String,  // Must be listed in the Spotify app's settings
+    pub expires_in: u64,  // Seconds to finish in
+}
+
+/// Body of POST /api/spotify/callback, for when the browser couldn't reach
+/// the frame after approving: the address Spotify redirected to
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct SpotifyCallbackRequest {
+    pub url: String,
+}
+
+/// Response of GET /api/spotify
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct SpotifyStatus {
+    pub connected: bool,
+    pub authorizing: bool,  // Waiting for the approval from POST /api/spotify/connect
+    #[serde(default)]
+    pub last_error: Option<String>,
+}
@@ -0,0 +1,22 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// One headline from a news feed
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct Headline {
+    pub title: String,
+    #[serde(default)]
+    pub link: Option<String>,
+    #[serde(default)]
+    pub published: Option<u64>,  // Unix timestamp in milliseconds, when the feed gives one
+    pub feed: String,  // Name of the feed it came from
+}
+
+/// Response of GET /api/news
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct NewsReport {
+    pub headlines: Vec<Headline>,  // Newest first
+    pub fetched_at: Option<u64>,  // Unix timestamp in milliseconds, None before the first refresh
+    #[serde(default)]
+    pub errors: Vec<String>,  // Feeds that failed in the last refresh, with why
+}
@@ -0,0 +1,38 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Body of POST /api/pair
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PairRequest {
+    pub pin: String,  // The 6 digits shown on the frame
+    #[serde(default)]
+    pub name: Option<String>,  // e.g. "Ana's phone"
+}
+
+/// A device paired with the frame. Its token is only returned once, when pairing.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PairedDevice {
+    pub id: String,
+    pub name: String,
+    pub paired_at: u64,  // Unix time in milliseconds
+}
+
+/// Response of POST /api/pair
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PairingResult {
+    pub device: PairedDevice,
+    pub token: String,  // Send as a Bearer header
+}
+
+/// Response of POST /api/pair/start
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PairingStarted {
+    pub expires_in: u64,  // Seconds the PIN stays valid
+}
+
+/// The PIN the frame shows while pairing
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PairingPin {
+    pub pin: String,
+    pub expires_in: u64,  // Seconds the PIN stays valid
+}
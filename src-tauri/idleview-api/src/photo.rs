@@ -0,0 +1,14 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Current photo information
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CurrentPhoto {
+    pub url: String,
+    pub author: String,
+    pub author_url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,  // Search query the photo was found with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fetched_at: Option<u64>,  // Unix timestamp in milliseconds
+}
@@ -0,0 +1,14 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::QuoteSource;
+
+/// Response of GET /api/quote
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct Quote {
+    pub text: String,
+    #[serde(default)]
+    pub author: Option<String>,
+    pub source: QuoteSource,  // Where it came from; bundled when the remote API failed
+    pub date: String,  // YYYY-MM-DD, the day it is the quote of
+}
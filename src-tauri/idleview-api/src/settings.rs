@@ -0,0 +1,1754 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct Settings {
+    pub units: UnitsSettings,
+    #[serde(default = "default_language")]
+    pub language: String,  // BCP-47 tag used for dates, day names and weather labels, e.g. "ro-RO"
+    pub display: DisplaySettings,
+    pub photos: PhotosSettings,
+    #[serde(default)]
+    pub location: LocationSettings,
+    #[serde(default)]
+    pub weather: WeatherSettings,
+    #[serde(default)]
+    pub scenes: ScenesSettings,
+    #[serde(default)]
+    pub profiles: ProfilesSettings,
+    #[serde(default)]
+    pub server: ServerSettings,
+    #[serde(default)]
+    pub sync: SyncSettings,
+    #[serde(default)]
+    pub webhooks: WebhooksSettings,
+    #[serde(default)]
+    pub mqtt: MqttSettings,
+    #[serde(default)]
+    pub calendar: CalendarSettings,
+    #[serde(default)]
+    pub quotes: QuotesSettings,
+    #[serde(default)]
+    pub news: NewsSettings,
+    #[serde(default)]
+    pub stocks: StocksSettings,
+    #[serde(default)]
+    pub crypto: CryptoSettings,
+    #[serde(default)]
+    pub music: MusicSettings,
+    #[serde(default)]
+    pub sports: SportsSettings,
+    #[serde(default)]
+    pub tasks: TasksSettings,
+    #[serde(default)]
+    pub countdowns: CountdownsSettings,
+    #[serde(default)]
+    pub clock: ClockSettings,
+    #[serde(default)]
+    pub alarms: AlarmsSettings,
+    #[serde(default)]
+    pub monitors: MonitorsSettings,
+    #[serde(default)]
+    pub watchdog: WatchdogSettings,
+    #[serde(default)]
+    pub connectivity: ConnectivitySettings,
+}
+
+fn default_language() -> String {
+    "en".to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct UnitsSettings {
+    pub temperature_unit: TemperatureUnit,
+    pub time_format: TimeFormat,
+    pub date_format: DateFormat,
+    pub wind_speed_unit: WindSpeedUnit,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TemperatureUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeFormat {
+    #[default]
+    #[serde(rename = "24h")]
+    H24,
+    #[serde(rename = "12h")]
+    H12,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DateFormat {
+    Mdy,
+    #[default]
+    Dmy,
+    Ymd,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum WindSpeedUnit {
+    #[default]
+    Kmh,
+    Mph,
+    Ms,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DebugPosition {
+    Left,
+    #[default]
+    Right,
+}
+
+impl TemperatureUnit {
+    pub const VALUES: &'static [&'static str] = &["celsius", "fahrenheit"];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "celsius",
+            TemperatureUnit::Fahrenheit => "fahrenheit",
+        }
+    }
+}
+
+impl TimeFormat {
+    pub const VALUES: &'static [&'static str] = &["24h", "12h"];
+}
+
+impl DateFormat {
+    pub const VALUES: &'static [&'static str] = &["mdy", "dmy", "ymd"];
+}
+
+impl WindSpeedUnit {
+    pub const VALUES: &'static [&'static str] = &["kmh", "mph", "ms"];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WindSpeedUnit::Kmh => "kmh",
+            WindSpeedUnit::Mph => "mph",
+            WindSpeedUnit::Ms => "ms",
+        }
+    }
+
+    /// Label shown next to wind speeds
+    pub fn label(&self) -> &'static str {
+        match self {
+            WindSpeedUnit::Kmh => "km/h",
+            WindSpeedUnit::Mph => "mph",
+            WindSpeedUnit::Ms => "m/s",
+        }
+    }
+}
+
+impl DebugPosition {
+    pub const VALUES: &'static [&'static str] = &["left", "right"];
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct DisplaySettings {
+    pub show_humidity_wind: bool,
+    pub show_precipitation_cloudiness: bool,
+    pub show_sunrise_sunset: bool,
+    pub show_cpu_temp: bool,
+    #[serde(default)]
+    pub show_cpu_usage: bool,  // CPU utilization and load average, next to the CPU temperature
+    #[serde(default)]
+    pub show_memory: bool,  // Memory used by the machine and the app
+    #[serde(default)]
+    pub show_wifi: bool,  // Wi-Fi signal strength in the corner
+    #[serde(default)]
+    pub show_battery: bool,  // Battery charge in the corner, on a laptop or tablet
+    #[serde(default = "default_low_battery_warning")]
+    pub low_battery_warning: u8,  // Warn on screen at this charge while discharging, 0 = never
+    #[serde(default)]
+    pub show_debug: bool,
+    #[serde(default)]
+    pub debug_position: DebugPosition,
+    #[serde(default = "default_degradation_order")]
+    pub degradation_order: Vec<String>,  // Widget ids, dropped first to last in degraded mode
+    #[serde(default = "default_degraded_drop_count")]
+    pub degraded_drop_count: usize,  // How many widgets from the order to drop in degraded mode
+    #[serde(default)]
+    pub greeting: GreetingSettings,
+    #[serde(default)]
+    pub world_clocks: Vec<WorldClock>,
+    #[serde(default)]
+    pub night_mode: NightModeSettings,
+    #[serde(default)]
+    pub sleep: DisplaySleepSettings,
+    #[serde(default)]
+    pub presence: PresenceSettings,
+    #[serde(default)]
+    pub burn_in: BurnInSettings,
+}
+
+fn default_low_battery_warning() -> u8 {
+    15
+}
+
+fn default_degradation_order() -> Vec<String> {
+    [
+        "cpu_temp",
+        "cpu_usage",
+        "memory",
+        "wifi",
+        "battery",
+        "sunrise_sunset",
+        "precipitation_cloudiness",
+        "humidity_wind",
+    ]
+    .iter()
+        .map(|id| id.to_string())
+        .collect()
+}
+
+fn default_degraded_drop_count() -> usize {
+    2
+}
+
+/// A clock showing the time somewhere else, e.g. where family lives
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct WorldClock {
+    pub timezone: String,  // IANA name, e.g. "America/Toronto"
+    #[serde(default)]
+    pub label: Option<String>,  // e.g. "Ana"; None = the city of the timezone
+}
+
+/// Dimmer, darker frame at night, e.g. in a bedroom
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct NightModeSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_night_start")]
+    pub start: String,  // "HH:MM" local time
+    #[serde(default = "default_night_end")]
+    pub end: String,  // "HH:MM", may be earlier than start to wrap past midnight
+    #[serde(default)]
+    pub follow_sun: bool,  // From sunset to sunrise instead, once the weather has them
+    #[serde(default = "default_night_brightness")]
+    pub brightness: u8,  // Percent while it's night
+    #[serde(default = "default_true")]
+    pub dark_theme: bool,
+    #[serde(default = "default_night_photo_interval_factor")]
+    pub photo_interval_factor: u64,  // Photos change this many times less often at night; 1 keeps the interval
+}
+
+impl Default for NightModeSettings {
+    fn default() -> Self {
+        NightModeSettings {
+            enabled: false,
+            start: default_night_start(),
+            end: default_night_end(),
+            follow_sun: false,
+            brightness: default_night_brightness(),
+            dark_theme: true,
+            photo_interval_factor: default_night_photo_interval_factor(),
+        }
+    }
+}
+
+fn default_night_start() -> String {
+    "23:00".to_string()
+}
+
+fn default_night_end() -> String {
+    "07:00".to_string()
+}
+
+fn default_night_brightness() -> u8 {
+    10
+}
+
+fn default_night_photo_interval_factor() -> u64 {
+    1
+}
+
+/// Hours the screen is off, with polling paused until it wakes
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct DisplaySleepSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_sleep_start")]
+    pub start: String,  // "HH:MM" local time
+    #[serde(default = "default_sleep_end")]
+    pub end: String,  // "HH:MM", may be earlier than start to wrap past midnight
+}
+
+impl Default for DisplaySleepSettings {
+    fn default() -> Self {
+        DisplaySleepSettings {
+            enabled: false,
+            start: default_sleep_start(),
+            end: default_sleep_end(),
+        }
+    }
+}
+
+/// Waking the screen when someone comes into the room and turning it off
+/// once nobody has moved for a while
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct PresenceSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_presence_idle_timeout")]
+    pub idle_timeout: u64,  // Seconds without motion before the screen turns off
+    #[serde(default)]
+    pub gpio_pin: Option<u32>,  // Sysfs GPIO number of a PIR sensor; None = only POST /api/presence
+}
+
+impl Default for PresenceSettings {
+    fn default() -> Self {
+        PresenceSettings {
+            enabled: false,
+            idle_timeout: default_presence_idle_timeout(),
+            gpio_pin: None,
+        }
+    }
+}
+
+fn default_presence_idle_timeout() -> u64 {
+    600
+}
+
+/// How a burn-in refresh cycle covers the screen
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BurnInRefresh {
+    Dim,  // Fade to black
+    #[default]
+    Invert,  // Show the inverted frame, wearing the pixels the frame keeps lit
+}
+
+impl BurnInRefresh {
+    pub const VALUES: &'static [&'static str] = &["dim", "invert"];
+}
+
+/// Protection for OLED and plasma screens, where static clock digits and
+/// widgets ghost after a few months: the frame moves a few pixels now and
+/// then, and a short full-screen refresh runs every so often
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct BurnInSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_shift_interval")]
+    pub shift_interval: u64,  // Seconds between pixel shifts
+    #[serde(default = "default_max_shift")]
+    pub max_shift: u32,  // Pixels the frame moves at most from its place, each way
+    #[serde(default = "default_refresh_interval")]
+    pub refresh_interval: u64,  // Minutes between refresh cycles; 0 = none
+    #[serde(default = "default_refresh_duration")]
+    pub refresh_duration: u64,  // Seconds a refresh cycle lasts
+    #[serde(default)]
+    pub refresh_mode: BurnInRefresh,
+}
+
+impl Default for BurnInSettings {
+    fn default() -> Self {
+        BurnInSettings {
+            enabled: false,
+            shift_interval: default_shift_interval(),
+            max_shift: default_max_shift(),
+            refresh_interval: default_refresh_interval(),
+            refresh_duration: default_refresh_duration(),
+            refresh_mode: BurnInRefresh::default(),
+        }
+    }
+}
+
+fn default_shift_interval() -> u64 {
+    120
+}
+
+fn default_max_shift() -> u32 {
+    6
+}
+
+fn default_refresh_interval() -> u64 {
+    60
+}
+
+fn default_refresh_duration() -> u64 {
+    10
+}
+
+fn default_sleep_start() -> String {
+    "00:00".to_string()
+}
+
+fn default_sleep_end() -> String {
+    "06:30".to_string()
+}
+
+/// The greeting above the clock ("Good morning — cold and clear, 2°C")
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct GreetingSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_greeting_template")]
+    pub template: String,  // Placeholders: {greeting}, {name}, {conditions}, {feel}, {sky}, {temperature}
+    #[serde(default)]
+    pub name: Option<String>,  // e.g. "Radu", for templates like "{greeting}, {name}"
+}
+
+impl Default for GreetingSettings {
+    fn default() -> Self {
+        GreetingSettings {
+            enabled: false,
+            template: default_greeting_template(),
+            name: None,
+        }
+    }
+}
+
+fn default_greeting_template() -> String {
+    "{greeting} — {conditions}, {temperature}".to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct PhotosSettings {
+    pub refresh_interval: u64,  // in minutes
+    #[serde(deserialize_with = "deserialize_quality")]
+    #[schemars(schema_with = "quality_schema")]
+    pub photo_quality: String,  // Accepts both "85" string or 85 number
+    #[serde(default = "default_enable_festive")]
+    pub enable_festive_queries: bool,  // Enable holiday/festive photo themes
+    #[serde(default)]
+    pub quality_schedule: Vec<QualityWindow>,  // Time-of-day overrides, first matching window wins
+    #[serde(default)]
+    pub provider: PhotoProvider,
+    #[serde(default)]
+    pub api_keys: PhotoApiKeys,
+    #[serde(default)]
+    pub collections: Vec<String>,  // Collection IDs of the provider; photos come only from these when set
+    #[serde(default)]
+    pub query: PhotoQuerySettings,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PhotoProvider {
+    #[default]
+    Unsplash,
+    Pexels,
+}
+
+impl PhotoProvider {
+    pub const VALUES: &'static [&'static str] = &["unsplash", "pexels"];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PhotoProvider::Unsplash => "unsplash",
+            PhotoProvider::Pexels => "pexels",
+        }
+    }
+}
+
+/// Photo provider keys, redacted from API reads and events
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct PhotoApiKeys {
+    #[serde(default)]
+    pub unsplash: Option<String>,  // None = UNSPLASH_ACCESS_KEY from the environment or build
+    #[serde(default)]
+    pub pexels: Option<String>,  // Required for the Pexels provider
+}
+
+/// How the photo search query is built
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct PhotoQuerySettings {
+    #[serde(default)]
+    pub fixed: Option<String>,  // Always search for this, ignoring weather, season and scenes
+    #[serde(default)]
+    pub extra_keywords: Option<String>,  // Appended to every generated query, e.g. "mountains"
+    #[serde(default)]
+    pub orientation: PhotoOrientation,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PhotoOrientation {
+    #[default]
+    Landscape,
+    Portrait,
+    Squarish,
+}
+
+impl PhotoOrientation {
+    pub const VALUES: &'static [&'static str] = &["landscape", "portrait", "squarish"];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PhotoOrientation::Landscape => "landscape",
+            PhotoOrientation::Portrait => "portrait",
+            PhotoOrientation::Squarish => "squarish",
+        }
+    }
+}
+
+/// Photo quality and resolution used during part of the day
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct QualityWindow {
+    pub start: String,  // "HH:MM" local time
+    pub end: String,    // "HH:MM", may be earlier than start to wrap past midnight
+    #[serde(deserialize_with = "deserialize_quality")]
+    #[schemars(schema_with = "quality_schema")]
+    pub quality: String,  // Same format as photo_quality
+    #[serde(default = "default_resolution_scale")]
+    pub resolution_scale: f64,  // Multiplier for the requested width and height
+}
+
+fn default_enable_festive() -> bool {
+    true
+}
+
+fn default_resolution_scale() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct WeatherSettings {
+    #[serde(default)]
+    pub provider: WeatherProvider,
+    #[serde(default)]
+    pub api_keys: WeatherApiKeys,
+    #[serde(default = "default_weather_refresh_interval")]
+    pub refresh_interval: u64,  // in minutes
+    #[serde(default)]
+    pub alerts: WeatherAlertSettings,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum WeatherProvider {
+    #[default]
+    OpenMeteo,
+    OpenWeatherMap,
+}
+
+impl WeatherProvider {
+    pub const VALUES: &'static [&'static str] = &["openmeteo", "openweathermap"];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WeatherProvider::OpenMeteo => "openmeteo",
+            WeatherProvider::OpenWeatherMap => "openweathermap",
+        }
+    }
+}
+
+/// Weather provider keys, redacted from API reads and events
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct WeatherApiKeys {
+    #[serde(default)]
+    pub openmeteo: Option<String>,  // Commercial plan key, None = free API
+    #[serde(default)]
+    pub openweathermap: Option<String>,  // Required for the OpenWeatherMap provider
+}
+
+/// Conditions flagged next to the current weather
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct WeatherAlertSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_true")]
+    pub wind: bool,  // Wind of 50 km/h or more
+    #[serde(default = "default_true")]
+    pub precipitation: bool,  // Heavy rain (4 mm or more) or snow
+    #[serde(default = "default_true")]
+    pub frost: bool,  // 0 °C or below
+}
+
+impl Default for WeatherAlertSettings {
+    fn default() -> Self {
+        WeatherAlertSettings {
+            enabled: false,
+            wind: true,
+            precipitation: true,
+            frost: true,
+        }
+    }
+}
+
+impl Default for WeatherSettings {
+    fn default() -> Self {
+        WeatherSettings {
+            provider: WeatherProvider::OpenMeteo,
+            api_keys: WeatherApiKeys::default(),
+            refresh_interval: default_weather_refresh_interval(),
+            alerts: WeatherAlertSettings::default(),
+        }
+    }
+}
+
+fn default_weather_refresh_interval() -> u64 {
+    15
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct LocationSettings {
+    #[serde(default)]
+    pub mode: LocationMode,
+    #[serde(default)]
+    pub latitude: Option<f64>,  // Manual mode coordinates
+    #[serde(default)]
+    pub longitude: Option<f64>,
+    #[serde(default)]
+    pub label: Option<String>,  // Place name shown in manual mode
+    #[serde(default)]
+    pub timezone: Option<String>,  // IANA name, e.g. "Europe/Bucharest"; None = from coordinates
+    #[serde(default)]
+    pub saved: Vec<SavedLocation>,
+    #[serde(default)]
+    pub active: Option<String>,  // Name of the active saved location, None = auto-detect
+    #[serde(default)]
+    pub rotate: bool,  // Cycle through saved locations instead of using `active`
+    #[serde(default = "default_rotation_interval")]
+    pub rotation_interval: u64,  // in minutes
+    #[serde(default = "default_location_source")]
+    pub source: String,  // "ip", "geoclue" or "gps" (the latter two Linux only)
+    #[serde(default = "default_gps_device")]
+    pub gps_device: String,  // Serial NMEA device used when source is "gps"
+    #[serde(default)]
+    pub owntracks: OwnTracksSettings,
+}
+
+/// Where the location comes from
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LocationMode {
+    #[default]
+    Auto,    // Saved locations, then the detection `source`
+    Manual,  // Fixed `latitude`/`longitude`
+}
+
+impl LocationMode {
+    pub const VALUES: &'static [&'static str] = &["auto", "manual"];
+}
+
+/// Follow a phone's OwnTracks regions over MQTT. Entering a region whose
+/// name matches a saved location (case-insensitive) makes it active.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct OwnTracksSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_mqtt_host")]
+    pub host: String,
+    #[serde(default = "default_mqtt_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,  // Broker password; redacted from API reads and events
+    #[serde(default = "default_owntracks_topic")]
+    pub topic: String,  // Subscription filter
+    #[serde(default)]
+    pub device: String,  // Topic of the phone to follow, e.g. "owntracks/alice/pixel"; empty = any
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct SavedLocation {
+    pub name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+fn default_rotation_interval() -> u64 {
+    5
+}
+
+fn default_location_source() -> String {
+    "ip".to_string()
+}
+
+fn default_gps_device() -> String {
+    "/dev/ttyACM0".to_string()
+}
+
+fn default_mqtt_host() -> String {
+    "localhost".to_string()
+}
+
+fn default_mqtt_port() -> u16 {
+    1883
+}
+
+fn default_owntracks_topic() -> String {
+    "owntracks/#".to_string()
+}
+
+impl Default for OwnTracksSettings {
+    fn default() -> Self {
+        OwnTracksSettings {
+            enabled: false,
+            host: default_mqtt_host(),
+            port: default_mqtt_port(),
+            username: None,
+            password: None,
+            topic: default_owntracks_topic(),
+            device: String::new(),
+        }
+    }
+}
+
+impl Default for LocationSettings {
+    fn default() -> Self {
+        LocationSettings {
+            mode: LocationMode::Auto,
+            latitude: None,
+            longitude: None,
+            label: None,
+            timezone: None,
+            saved: Vec::new(),
+            active: None,
+            rotate: false,
+            rotation_interval: default_rotation_interval(),
+            source: default_location_source(),
+            gps_device: default_gps_device(),
+            owntracks: OwnTracksSettings::default(),
+        }
+    }
+}
+
+/// The HTTP control server. Changes restart its listener.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct ServerSettings {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "default_server_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub bind: ServerBind,
+    #[serde(default)]
+    pub tls: TlsSettings,
+    #[serde(default)]
+    pub cors_origins: Vec<String>,  // Browser origins allowed to call the API ("*" = any), empty = origins on the local network
+    #[serde(default)]
+    pub rate_limit: RateLimitSettings,
+    #[serde(default)]
+    pub access_log: AccessLogLevel,
+    #[serde(default = "default_true")]
+    pub compression: bool,  // gzip/brotli for clients that accept it
+    #[serde(default)]
+    pub allow_power_control: bool,  // Allow rebooting and shutting down the machine over the API
+}
+
+/// Checking the system clock against NTP. A Pi has no battery-backed clock,
+/// so after booting offline it can be hours off until it syncs.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct ClockSettings {
+    #[serde(default = "default_true")]
+    pub check_drift: bool,
+    #[serde(default = "default_ntp_server")]
+    pub ntp_server: String,  // Host name or address, with an optional ":port"
+    #[serde(default = "default_clock_max_drift")]
+    pub max_drift: u64,  // Seconds off before the clock is reported as drifted
+}
+
+impl Default for ClockSettings {
+    fn default() -> Self {
+        ClockSettings {
+            check_drift: true,
+            ntp_server: default_ntp_server(),
+            max_drift: default_clock_max_drift(),
+        }
+    }
+}
+
+/// Recovering a frame whose webview froze or crashed, noticed by its
+/// heartbeats stopping
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct WatchdogSettings {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "default_watchdog_timeout")]
+    pub timeout: u64,  // Seconds without a heartbeat before the frame is reloaded
+}
+
+impl Default for WatchdogSettings {
+    fn default() -> Self {
+        WatchdogSettings {
+            enabled: true,
+            timeout: default_watchdog_timeout(),
+        }
+    }
+}
+
+fn default_watchdog_timeout() -> u64 {
+    90
+}
+
+/// Checking the internet connection, so the frame knows when to stop retrying
+/// and keep showing what it has
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct ConnectivitySettings {
+    #[serde(default = "default_connectivity_check_url")]
+    pub check_url: String,  // Any HTTP answer from it counts as online
+    #[serde(default = "default_connectivity_interval")]
+    pub interval: u64,  // Seconds between checks while online
+}
+
+impl Default for ConnectivitySettings {
+    fn default() -> Self {
+        ConnectivitySettings {
+            check_url: default_connectivity_check_url(),
+            interval: default_connectivity_interval(),
+        }
+    }
+}
+
+fn default_connectivity_check_url() -> String {
+    "http://connectivitycheck.gstatic.com/generate_204".to_string()
+}
+
+fn default_connectivity_interval() -> u64 {
+    30
+}
+
+fn default_ntp_server() -> String {
+    "pool.ntp.org".to_string()
+}
+
+fn default_clock_max_drift() -> u64 {
+    10
+}
+
+/// Alarms and chimes raised at a time of day, as `alarm-fired` events
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Default)]
+pub struct AlarmsSettings {
+    #[serde(default)]
+    pub list: Vec<Alarm>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct Alarm {
+    #[serde(default)]
+    pub id: u32,  // Assigned when created through the API
+    pub time: String,  // "HH:MM" local time
+    #[serde(default)]
+    pub days: Vec<AlarmDay>,  // Empty for every day, e.g. a daily chime
+    #[serde(default)]
+    pub label: String,  // Shown on the frame when it fires
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AlarmDay {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+/// Frames spanning more than one screen, e.g. a dual-screen desk
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Default)]
+pub struct MonitorsSettings {
+    #[serde(default)]
+    pub window_per_monitor: bool,  // Open a frame on every monitor at startup, not only the first
+    #[serde(default)]
+    pub overrides: Vec<MonitorOverride>,
+}
+
+/// Settings that differ on one monitor, such as its own photo query
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq)]
+pub struct MonitorOverride {
+    pub monitor: String,  // Name the system gives the monitor, as listed by GET /api/monitors
+    #[serde(default)]
+    pub settings: serde_json::Value,  // Partial settings merged in for the frame on that monitor
+}
+
+/// HTTPS for the control server
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Default)]
+pub struct TlsSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub cert_path: Option<String>,  // PEM certificate chain, None = self-signed certificate generated on first use
+    #[serde(default)]
+    pub key_path: Option<String>,  // PEM private key for cert_path
+}
+
+/// Which HTTP requests are written to the access log
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessLogLevel {
+    Off,
+    Errors,  // Failed requests only
+    #[default]
+    Changes,  // Requests that change something, and failed ones
+    All,
+}
+
+impl AccessLogLevel {
+    pub const VALUES: &'static [&'static str] = &["off", "errors", "changes", "all"];
+}
+
+/// Showing the same photo on several frames: one leads, the others follow it
+/// over the LAN API
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct SyncSettings {
+    #[serde(default)]
+    pub role: SyncRole,
+    #[serde(default)]
+    pub lead_url: Option<String>,  // e.g. "http://192.168.1.20:8737", None = find the lead over mDNS
+    #[serde(default = "default_true")]
+    pub settings: bool,  // Take the lead's settings too, not just its photo
+    #[serde(default)]
+    pub local_sections: Vec<String>,  // Sections kept as set on this frame, besides server and sync
+}
+
+impl Default for SyncSettings {
+    fn default() -> Self {
+        SyncSettings {
+            role: SyncRole::Off,
+            lead_url: None,
+            settings: true,
+            local_sections: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncRole {
+    #[default]
+    Off,
+    Lead,  // Other frames may follow this one
+    Follow,  // Show the lead's photo and take its settings
+}
+
+impl SyncRole {
+    pub const VALUES: &'static [&'static str] = &["off", "lead", "follow"];
+}
+
+/// Quote of the day, shown in a corner of the frame
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct QuotesSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub source: QuoteSource,
+    #[serde(default = "default_quotes_url")]
+    pub url: String,  // Remote API: ZenQuotes' format, or an object with "content", "quote" or "text" and "author"
+}
+
+impl Default for QuotesSettings {
+    fn default() -> Self {
+        QuotesSettings {
+            enabled: false,
+            source: QuoteSource::Bundled,
+            url: default_quotes_url(),
+        }
+    }
+}
+
+fn default_quotes_url() -> String {
+    "https://zenquotes.io/api/today".to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum QuoteSource {
+    #[default]
+    Bundled,  // The offline set shipped with the app
+    Remote,  // quotes.url, falling back to the bundled set when it can't be reached
+}
+
+impl QuoteSource {
+    pub const VALUES: &'static [&'static str] = &["bundled", "remote"];
+}
+
+/// Headlines from RSS and Atom feeds, shown one at a time at the top of the frame
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct NewsSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub feeds: Vec<NewsFeed>,
+    #[serde(default = "default_news_refresh_interval")]
+    pub refresh_interval: u64,  // Minutes
+    #[serde(default = "default_news_max_headlines")]
+    pub max_headlines: usize,  // Across every feed, newest first
+}
+
+impl Default for NewsSettings {
+    fn default() -> Self {
+        NewsSettings {
+            enabled: false,
+            feeds: Vec::new(),
+            refresh_interval: default_news_refresh_interval(),
+            max_headlines: default_news_max_headlines(),
+        }
+    }
+}
+
+fn default_news_refresh_interval() -> u64 {
+    30
+}
+
+fn default_news_max_headlines() -> usize {
+    20
+}
+
+/// An RSS or Atom feed
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct NewsFeed {
+    pub name: String,
+    pub url: String,
+    #[serde(default = "default_news_feed_limit")]
+    pub limit: usize,  // Headlines taken from this feed
+}
+
+fn default_news_feed_limit() -> usize {
+    5
+}
+
+/// Stock prices for the symbols given, from Yahoo Finance or Finnhub
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct StocksSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub provider: StockProvider,
+    #[serde(default)]
+    pub api_keys: StockApiKeys,
+    #[serde(default)]
+    pub symbols: Vec<String>,  // Ticker symbols as the provider knows them, e.g. "AAPL", "VOD.L"
+    #[serde(default = "default_stocks_refresh_interval")]
+    pub refresh_interval: u64,  // Minutes between requests to the provider
+}
+
+impl Default for StocksSettings {
+    fn default() -> Self {
+        StocksSettings {
+            enabled: false,
+            provider: StockProvider::Yahoo,
+            api_keys: StockApiKeys::default(),
+            symbols: Vec::new(),
+            refresh_interval: default_stocks_refresh_interval(),
+        }
+    }
+}
+
+fn default_stocks_refresh_interval() -> u64 {
+    15
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StockProvider {
+    #[default]
+    Yahoo,  // No key needed
+    Finnhub,
+}
+
+impl StockProvider {
+    pub const VALUES: &'static [&'static str] = &["yahoo", "finnhub"];
+}
+
+/// Stock quote provider keys, redacted from API reads and events
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Default)]
+pub struct StockApiKeys {
+    #[serde(default)]
+    pub finnhub: Option<String>,  // Required for the Finnhub provider
+}
+
+/// Cryptocurrency prices from CoinGecko, shown in a corner of the frame
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct CryptoSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_crypto_coins")]
+    pub coins: Vec<String>,  // CoinGecko coin IDs, e.g. "bitcoin", "ethereum"
+    #[serde(default = "default_crypto_currency")]
+    pub currency: String,  // Currency to price them in, e.g. "usd", "eur"
+    #[serde(default = "default_crypto_refresh_interval")]
+    pub refresh_interval: u64,  // Minutes between requests to CoinGecko
+}
+
+impl Default for CryptoSettings {
+    fn default() -> Self {
+        CryptoSettings {
+            enabled: false,
+            coins: default_crypto_coins(),
+            currency: default_crypto_currency(),
+            refresh_interval: default_crypto_refresh_interval(),
+        }
+    }
+}
+
+fn default_crypto_coins() -> Vec<String> {
+    vec!["bitcoin".to_string(), "ethereum".to_string()]
+}
+
+fn default_crypto_currency() -> String {
+    "usd".to_string()
+}
+
+fn default_crypto_refresh_interval() -> u64 {
+    5
+}
+
+/// What's playing, and the album-art view the frame switches to meanwhile
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct MusicSettings {
+    #[serde(default = "default_true")]
+    pub album_art_view: bool,  // Show the cover instead of the photo while music plays
+    #[serde(default)]
+    pub spotify: SpotifySettings,
+    #[serde(default)]
+    pub lastfm: LastFmSettings,
+    #[serde(default)]
+    pub media: MediaSettings,
+}
+
+impl Default for MusicSettings {
+    fn default() -> Self {
+        MusicSettings {
+            album_art_view: true,
+            spotify: SpotifySettings::default(),
+            lastfm: LastFmSettings::default(),
+            media: MediaSettings::default(),
+        }
+    }
+}
+
+/// Spotify, connected with OAuth (PKCE, so no client secret) from an app
+/// created at developer.spotify.com
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct SpotifySettings {
+    #[serde(default)]
+    pub client_id: Option<String>,
+    #[serde(default)]
+    pub redirect_uri: Option<String>,  // None = http://127.0.0.1:<server.port>/api/v1/spotify/callback
+    #[serde(default = "default_spotify_poll_interval")]
+    pub poll_interval: u64,  // Seconds between asks for the current track
+}
+
+impl Default for SpotifySettings {
+    fn default() -> Self {
+        SpotifySettings {
+            client_id: None,
+            redirect_uri: None,
+            poll_interval: default_spotify_poll_interval(),
+        }
+    }
+}
+
+fn default_spotify_poll_interval() -> u64 {
+    5
+}
+
+/// Last.fm's latest scrobble, for music played anywhere that scrobbles.
+/// Spotify's track wins while both are playing.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct LastFmSettings {
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub api_key: Option<String>,  // From last.fm/api/account/create; redacted from API reads
+    #[serde(default = "default_lastfm_poll_interval")]
+    pub poll_interval: u64,  // Seconds between asks for the recent tracks
+}
+
+impl Default for LastFmSettings {
+    fn default() -> Self {
+        LastFmSettings {
+            username: None,
+            api_key: None,
+            poll_interval: default_lastfm_poll_interval(),
+        }
+    }
+}
+
+fn default_lastfm_poll_interval() -> u64 {
+    15
+}
+
+/// Chromecasts and Sonos speakers on the local network, found over mDNS and
+/// asked what they play, whatever app or service plays it
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct MediaSettings {
+    #[serde(default)]
+    pub chromecast: bool,
+    #[serde(default)]
+    pub sonos: bool,
+    #[serde(default)]
+    pub devices: Vec<String>,  // Names of the devices to follow, as in GET /api/media/devices; empty = all found
+    #[serde(default = "default_media_poll_interval")]
+    pub poll_interval: u64,  // Seconds between asks for what each device plays
+}
+
+impl Default for MediaSettings {
+    fn default() -> Self {
+        MediaSettings {
+            chromecast: false,
+            sonos: false,
+            devices: Vec::new(),
+            poll_interval: default_media_poll_interval(),
+        }
+    }
+}
+
+fn default_media_poll_interval() -> u64 {
+    5
+}
+
+/// Fixtures and scores of the teams and leagues followed, from TheSportsDB or
+/// football-data.org
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct SportsSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub provider: SportsProvider,
+    #[serde(default)]
+    pub api_keys: SportsApiKeys,
+    #[serde(default)]
+    pub teams: Vec<String>,  // Team IDs as the provider knows them, e.g. "133604" (TheSportsDB) or "57" (football-data.org)
+    #[serde(default)]
+    pub leagues: Vec<String>,  // League IDs, e.g. "4328" (TheSportsDB), or competition codes like "PL" (football-data.org)
+    #[serde(default = "default_sports_max_matches")]
+    pub max_matches: usize,  // Upcoming fixtures, and results, listed at most
+    #[serde(default = "default_sports_refresh_interval")]
+    pub refresh_interval: u64,  // Minutes between requests; every minute while a match is on
+    #[serde(default)]
+    pub match_day_keywords: Option<String>,  // Added to the photo query on days a followed team plays, e.g. "stadium"
+}
+
+impl Default for SportsSettings {
+    fn default() -> Self {
+        SportsSettings {
+            enabled: false,
+            provider: SportsProvider::TheSportsDb,
+            api_keys: SportsApiKeys::default(),
+            teams: Vec::new(),
+            leagues: Vec::new(),
+            max_matches: default_sports_max_matches(),
+            refresh_interval: default_sports_refresh_interval(),
+            match_day_keywords: None,
+        }
+    }
+}
+
+fn default_sports_max_matches() -> usize {
+    5
+}
+
+fn default_sports_refresh_interval() -> u64 {
+    30
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SportsProvider {
+    #[default]
+    #[serde(rename = "thesportsdb")]
+    TheSportsDb,  // Works with its free key, any sport
+    #[serde(rename = "football-data")]
+    FootballData,  // Football only, with a free key from football-data.org
+}
+
+impl SportsProvider {
+    pub const VALUES: &'static [&'static str] = &["thesportsdb", "football-data"];
+}
+
+/// Sports provider keys, redacted from API reads and events
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Default)]
+pub struct SportsApiKeys {
+    #[serde(default)]
+    pub thesportsdb: Option<String>,  // None = the free key
+    #[serde(default)]
+    pub football_data: Option<String>,  // Required for the football-data provider
+}
+
+/// Today's tasks from Todoist and/or a local file, e.g. a grocery list on a
+/// kitchen frame
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct TasksSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub todoist_token: Option<String>,  // API token from Todoist's Integrations settings; None = no Todoist
+    #[serde(default = "default_todoist_filter")]
+    pub todoist_filter: String,  // Todoist filter query of the tasks to show
+    #[serde(default)]
+    pub file: Option<String>,  // Text file with a task per line; relative paths are next to settings.json
+    #[serde(default = "default_tasks_refresh_interval")]
+    pub refresh_interval: u64,  // Minutes between requests to Todoist; the file is read every time
+}
+
+impl Default for TasksSettings {
+    fn default() -> Self {
+        TasksSettings {
+            enabled: false,
+            todoist_token: None,
+            todoist_filter: default_todoist_filter(),
+            file: None,
+            refresh_interval: default_tasks_refresh_interval(),
+        }
+    }
+}
+
+fn default_todoist_filter() -> String {
+    "today | overdue".to_string()
+}
+
+fn default_tasks_refresh_interval() -> u64 {
+    5
+}
+
+/// Days left until the user's own events, such as "Vacation" or "Exam"
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct CountdownsSettings {
+    #[serde(default)]
+    pub events: Vec<CountdownEvent>,
+    #[serde(default = "default_countdown_imminent_days")]
+    pub imminent_days: u32,  // Events this many days away or fewer are flagged as imminent
+}
+
+impl Default for CountdownsSettings {
+    fn default() -> Self {
+        CountdownsSettings {
+            events: Vec::new(),
+            imminent_days: default_countdown_imminent_days(),
+        }
+    }
+}
+
+fn default_countdown_imminent_days() -> u32 {
+    7
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct CountdownEvent {
+    pub name: String,
+    pub date: String,  // YYYY-MM-DD
+    #[serde(default)]
+    pub yearly: bool,  // Birthdays and anniversaries: counts to the next one instead of ending
+    #[serde(default)]
+    pub photo_keywords: Option<String>,  // Photo query while the event is imminent, e.g. "beach"; needs photos.enable_festive_queries
+}
+
+/// Upcoming events shown on the frame; calendar keywords in scenes match them too
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct CalendarSettings {
+    #[serde(default)]
+    pub feeds: Vec<CalendarFeed>,
+    #[serde(default = "default_calendar_refresh_interval")]
+    pub refresh_interval: u64,  // Minutes
+    #[serde(default = "default_calendar_days_ahead")]
+    pub days_ahead: u32,  // How far ahead to look for events
+    #[serde(default = "default_calendar_max_events")]
+    pub max_events: usize,
+    #[serde(default)]
+    pub google: GoogleCalendarSettings,
+    #[serde(default)]
+    pub caldav: Vec<CalDavCalendar>,
+}
+
+impl Default for CalendarSettings {
+    fn default() -> Self {
+        CalendarSettings {
+            feeds: Vec::new(),
+            refresh_interval: default_calendar_refresh_interval(),
+            days_ahead: default_calendar_days_ahead(),
+            max_events: default_calendar_max_events(),
+            google: GoogleCalendarSettings::default(),
+            caldav: Vec::new(),
+        }
+    }
+}
+
+fn default_calendar_refresh_interval() -> u64 {
+    15
+}
+
+fn default_calendar_days_ahead() -> u32 {
+    7
+}
+
+fn default_calendar_max_events() -> usize {
+    10
+}
+
+/// An iCal feed, e.g. a calendar's "secret address in iCal format"
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct CalendarFeed {
+    pub name: String,
+    pub url: String,  // http(s):// or webcal://; redacted from API reads, as it works as the calendar's password
+}
+
+/// A calendar on a CalDAV server (Nextcloud, Fastmail, Radicale). The
+/// password is kept out of the settings, set with
+/// PUT /api/calendar/caldav/:name/password.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct CalDavCalendar {
+    pub name: String,
+    pub url: String,  // The calendar collection, e.g. https://cloud.example.com/remote.php/dav/calendars/me/personal/
+    pub username: String,
+}
+
+/// Google Calendar, connected with the OAuth device flow. The client comes
+/// from a Google Cloud project, as an OAuth client of type "TV and Limited
+/// Input devices".
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct GoogleCalendarSettings {
+    #[serde(default)]
+    pub client_id: Option<String>,
+    #[serde(default)]
+    pub client_secret: Option<String>,  // Redacted from API reads and events
+    #[serde(default = "default_google_calendars")]
+    pub calendars: Vec<String>,  // Calendar IDs, "primary" is the account's own
+}
+
+impl Default for GoogleCalendarSettings {
+    fn default() -> Self {
+        GoogleCalendarSettings {
+            client_id: None,
+            client_secret: None,
+            calendars: default_google_calendars(),
+        }
+    }
+}
+
+fn default_google_calendars() -> Vec<String> {
+    vec!["primary".to_string()]
+}
+
+/// Smart-home integration: state published to an MQTT broker, commands read from it
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct MqttSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_mqtt_host")]
+    pub host: String,
+    #[serde(default = "default_mqtt_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,  // Redacted from API reads and events
+    #[serde(default = "default_mqtt_base_topic")]
+    pub base_topic: String,  // State goes to <base_topic>/..., commands are read from <base_topic>/command/...
+}
+
+impl Default for MqttSettings {
+    fn default() -> Self {
+        MqttSettings {
+            enabled: false,
+            host: default_mqtt_host(),
+            port: default_mqtt_port(),
+            username: None,
+            password: None,
+            base_topic: default_mqtt_base_topic(),
+        }
+    }
+}
+
+fn default_mqtt_base_topic() -> String {
+    "idleview".to_string()
+}
+
+/// URLs the frame POSTs its events to, for automations without MQTT
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct WebhooksSettings {
+    #[serde(default)]
+    pub list: Vec<Webhook>,
+    #[serde(default = "default_cpu_temperature_limit")]
+    pub cpu_temperature_limit: f32,  // °C at which cpu-temperature-high is sent
+    #[serde(default = "default_low_disk_limit")]
+    pub low_disk_limit: u64,  // MB free below which disk-space-low is sent and caches are trimmed
+}
+
+impl Default for WebhooksSettings {
+    fn default() -> Self {
+        WebhooksSettings {
+            list: Vec::new(),
+            cpu_temperature_limit: default_cpu_temperature_limit(),
+            low_disk_limit: default_low_disk_limit(),
+        }
+    }
+}
+
+fn default_cpu_temperature_limit() -> f32 {
+    80.0
+}
+
+fn default_low_disk_limit() -> u64 {
+    200
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct Webhook {
+    pub url: String,
+    #[serde(default)]
+    pub events: Vec<String>,  // Event types to send, e.g. "photo-updated", empty = all of them
+    #[serde(default)]
+    pub secret: Option<String>,  // Signs each body with HMAC-SHA256, sent in X-Idleview-Signature; redacted from API reads
+}
+
+/// Requests allowed per client IP, refilled evenly over the minute
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct RateLimitSettings {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "default_reads_per_minute")]
+    pub reads_per_minute: u32,  // GET requests
+    #[serde(default = "default_writes_per_minute")]
+    pub writes_per_minute: u32,  // Requests that change something, each may cost a disk write
+}
+
+impl Default for RateLimitSettings {
+    fn default() -> Self {
+        RateLimitSettings {
+            enabled: true,
+            reads_per_minute: default_reads_per_minute(),
+            writes_per_minute: default_writes_per_minute(),
+        }
+    }
+}
+
+fn default_reads_per_minute() -> u32 {
+    300
+}
+
+fn default_writes_per_minute() -> u32 {
+    30
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        ServerSettings {
+            enabled: true,
+            port: default_server_port(),
+            bind: ServerBind::All,
+            tls: TlsSettings::default(),
+            cors_origins: Vec::new(),
+            rate_limit: RateLimitSettings::default(),
+            access_log: AccessLogLevel::Changes,
+            compression: true,
+            allow_power_control: false,
+        }
+    }
+}
+
+fn default_server_port() -> u16 {
+    8737
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ServerBind {
+    #[default]
+    All,  // Reachable from the local network
+    Localhost,  // Only from this machine
+}
+
+impl ServerBind {
+    pub const VALUES: &'static [&'static str] = &["all", "localhost"];
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct ScenesSettings {
+    #[serde(default)]
+    pub list: Vec<Scene>,
+    #[serde(default)]
+    pub active: Option<String>,  // Name of the active scene, None = no scene
+    #[serde(default)]
+    pub scheduled: bool,  // Active scene was switched on by a calendar keyword
+    #[serde(default)]
+    pub revert: serde_json::Value,  // Settings restored when a scheduled scene ends
+}
+
+/// A named bundle of photo queries and settings overrides
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct Scene {
+    pub name: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub query_templates: Vec<String>,  // e.g. "{season} fireplace", placeholders: {season}, {time_of_day}
+    #[serde(default)]
+    pub settings: serde_json::Value,  // Partial settings merged in when the scene is activated
+    #[serde(default)]
+    pub calendar_keywords: Vec<String>,  // Event titles containing any of these activate the scene
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct ProfilesSettings {
+    #[serde(default)]
+    pub list: Vec<Profile>,
+    #[serde(default)]
+    pub active: Option<String>,  // Name of the active profile, None = no profile
+    #[serde(default)]
+    pub schedule: Vec<ProfileWindow>,  // Time-of-day switching, first matching window wins
+}
+
+/// A named set of settings overrides, e.g. "day", "night" or "guest"
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct Profile {
+    pub name: String,
+    #[serde(default)]
+    pub settings: serde_json::Value,  // Partial settings merged in when the profile is activated
+}
+
+/// Profile switched on automatically during part of the day
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct ProfileWindow {
+    pub start: String,  // "HH:MM" local time
+    pub end: String,    // "HH:MM", may be earlier than start to wrap past midnight
+    pub profile: String,
+}
+
+// Custom deserializer to handle both string and number
+fn deserialize_quality<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::{self, Visitor};
+    use std::fmt;
+
+    struct QualityVisitor;
+
+    impl<'de> Visitor<'de> for QualityVisitor {
+        type Value = String;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a string or number")
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<String, E>
+        where
+            E: de::Error,
+        {
+            Ok(value.to_string())
+        }
+
+        fn visit_u64<E>(self, value: u64) -> Result<String, E>
+        where
+            E: de::Error,
+        {
+            Ok(value.to_string())
+        }
+
+        fn visit_i64<E>(self, value: i64) -> Result<String, E>
+        where
+            E: de::Error,
+        {
+            Ok(value.to_string())
+        }
+    }
+
+    deserializer.deserialize_any(QualityVisitor)
+}
+
+// Schema counterpart of deserialize_quality
+fn quality_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+    use schemars::schema::{InstanceType, Metadata, Schema, SchemaObject, SingleOrVec};
+
+    Schema::Object(SchemaObject {
+        instance_type: Some(SingleOrVec::Vec(vec![InstanceType::String, InstanceType::Integer])),
+        metadata: Some(Box::new(Metadata {
+            description: Some("JPEG quality 1-100, or one of the legacy values low, medium, high, maximum".to_string()),
+            ..Default::default()
+        })),
+        ..Default::default()
+    })
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            units: UnitsSettings {
+                temperature_unit: TemperatureUnit::Celsius,
+                time_format: TimeFormat::H24,
+                date_format: DateFormat::Dmy,
+                wind_speed_unit: WindSpeedUnit::Kmh,
+            },
+            language: default_language(),
+            display: DisplaySettings {
+                show_humidity_wind: true,
+                show_precipitation_cloudiness: true,
+                show_sunrise_sunset: true,
+                show_cpu_temp: false,
+                show_cpu_usage: false,
+                show_memory: false,
+                show_wifi: false,
+                show_battery: false,
+                low_battery_warning: default_low_battery_warning(),
+                show_debug: false,
+                debug_position: DebugPosition::Right,
+                degradation_order: default_degradation_order(),
+                degraded_drop_count: default_degraded_drop_count(),
+                greeting: GreetingSettings::default(),
+                world_clocks: Vec::new(),
+                night_mode: NightModeSettings::default(),
+                sleep: DisplaySleepSettings::default(),
+                presence: PresenceSettings::default(),
+                burn_in: BurnInSettings::default(),
+            },
+            photos: PhotosSettings {
+                refresh_interval: 30,
+                photo_quality: "80".to_string(),
+                enable_festive_queries: true,
+                quality_schedule: Vec::new(),
+                provider: PhotoProvider::Unsplash,
+                api_keys: PhotoApiKeys::default(),
+                collections: Vec::new(),
+                query: PhotoQuerySettings::default(),
+            },
+            location: LocationSettings::default(),
+            weather: WeatherSettings::default(),
+            scenes: ScenesSettings::default(),
+            profiles: ProfilesSettings::default(),
+            server: ServerSettings::default(),
+            sync: SyncSettings::default(),
+            webhooks: WebhooksSettings::default(),
+            mqtt: MqttSettings::default(),
+            calendar: CalendarSettings::default(),
+            quotes: QuotesSettings::default(),
+            news: NewsSettings::default(),
+            stocks: StocksSettings::default(),
+            crypto: CryptoSettings::default(),
+            music: MusicSettings::default(),
+            sports: SportsSettings::default(),
+            tasks: TasksSettings::default(),
+            countdowns: CountdownsSettings::default(),
+            clock: ClockSettings::default(),
+            alarms: AlarmsSettings::default(),
+            monitors: MonitorsSettings::default(),
+            watchdog: WatchdogSettings::default(),
+            connectivity: ConnectivitySettings::default(),
+        }
+    }
+}
+
+/// Default settings plus the allowed values of every enum-like field,
+/// so clients can render dropdowns and "reset" buttons without hardcoding them
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct SettingsDefaults {
+    pub defaults: Settings,
+    pub options: std::collections::BTreeMap<String, Vec<String>>,  // Keyed by dotted path, e.g. "units.time_format"
+}
+
+/// JSON Schema of the settings document, for generating forms and validating
+/// payloads client-side
+pub fn settings_schema() -> serde_json::Value {
+    serde_json::to_value(schemars::schema_for!(Settings)).unwrap_or_default()
+}
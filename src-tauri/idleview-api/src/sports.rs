@@ -0,0 +1,38 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchStatus {
+    Scheduled,
+    Live,  // Under way, including half time
+    Finished,
+    Postponed,  // Or cancelled, suspended
+}
+
+/// A fixture of a followed team or league, with its score once under way
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq)]
+pub struct SportsMatch {
+    pub id: String,  // The provider's ID of the match
+    #[serde(default)]
+    pub league: Option<String>,
+    pub home_team: String,
+    pub away_team: String,
+    #[serde(default)]
+    pub home_score: Option<u32>,
+    #[serde(default)]
+    pub away_score: Option<u32>,
+    pub starts_at: Option<u64>,  // Unix timestamp in milliseconds, None when not set yet
+    pub status: MatchStatus,
+}
+
+/// Response of GET /api/sports
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct SportsReport {
+    pub live: Vec<SportsMatch>,  // Kicked off first, first
+    pub upcoming: Vec<SportsMatch>,  // Soonest first, at most sports.max_matches
+    pub results: Vec<SportsMatch>,  // Latest first, at most sports.max_matches
+    pub fetched_at: Option<u64>,  // Unix timestamp in milliseconds, None before the first request
+    #[serde(default)]
+    pub errors: Vec<String>,  // Teams or leagues that failed in the last request, with why
+}
@@ -0,0 +1,14 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::SyncRole;
+
+/// Response of GET /api/sync
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct SyncStatus {
+    pub role: SyncRole,
+    pub lead: Option<String>,  // Base URL of the lead being followed
+    pub connected: bool,  // Receiving the lead's events
+    pub last_sync: Option<u64>,  // Unix timestamp in milliseconds of the last change taken from the lead
+    pub last_error: Option<String>,  // Why following the lead last failed
+}
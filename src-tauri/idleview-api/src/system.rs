@@ -0,0 +1,150 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Response of GET /api/system
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct SystemStatus {
+    pub app_version: String,
+    pub uptime: u64,  // Seconds since the machine booted
+    pub cpu_temperature: Option<f32>,  // °C, None when the machine doesn't report one
+    #[serde(default)]
+    pub gpu_temperature: Option<f32>,  // °C, from vcgencmd on a Pi or a graphics card's hwmon sensor
+    #[serde(default)]
+    pub cpu_usage: Option<CpuUsage>,
+    #[serde(default)]
+    pub wifi: Option<WifiSignal>,  // None when not on Wi-Fi, or not on Linux
+    #[serde(default)]
+    pub battery: Option<BatteryStatus>,  // None without a battery
+    pub memory: MemoryStatus,
+    pub disk: Option<DiskStatus>,  // Disk holding the settings, None when it can't be found
+    #[serde(default)]
+    pub cache_disk: Option<DiskStatus>,  // Disk holding the webview's photo cache, when it's another one
+    #[serde(default)]
+    pub update: Option<UpdateInfo>,  // Result of the last update check, None before the first one
+    #[serde(default)]
+    pub clock: Option<ClockStatus>,  // Last comparison with NTP, None before the first answer
+}
+
+/// How far the system clock is from NTP time
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct ClockStatus {
+    pub offset_ms: i64,  // System clock minus NTP time: positive = ahead, negative = behind
+    pub drifted: bool,  // Off by more than clock.max_drift
+    pub server: String,
+    pub checked_at: u64,  // Unix timestamp in milliseconds, by the system clock
+}
+
+/// CPU utilization since the previous reading, in percent
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq)]
+pub struct CpuUsage {
+    pub total: f32,  // All cores together, 0-100
+    pub cores: Vec<f32>,  // One per logical core, 0-100
+    pub load_average: LoadAverage,
+}
+
+/// Processes running or waiting over the last 1, 5 and 15 minutes; zeros on Windows
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Default)]
+pub struct LoadAverage {
+    pub one: f64,
+    pub five: f64,
+    pub fifteen: f64,
+}
+
+/// Strength of the Wi-Fi link
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct WifiSignal {
+    pub interface: String,  // e.g. "wlan0"
+    pub quality: u8,  // Link quality, 0-100
+    pub signal_dbm: Option<i32>,  // RSSI, e.g. -56; None when the driver reports no dBm value
+}
+
+/// Whether the battery is charging
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BatteryState {
+    Charging,
+    Discharging,
+    Full,
+    Empty,
+    Unknown,
+}
+
+/// Charge of a laptop or tablet battery
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq)]
+pub struct BatteryStatus {
+    pub percent: f32,
+    pub state: BatteryState,
+    pub time_remaining: Option<u64>,  // Seconds until empty, or until full while charging
+}
+
+/// Memory in bytes
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct MemoryStatus {
+    pub total: u64,
+    pub used: u64,
+    pub available: u64,
+    #[serde(default)]
+    pub swap_total: u64,
+    #[serde(default)]
+    pub swap_used: u64,
+    #[serde(default)]
+    pub app: Option<u64>,  // Resident memory of the app and its webview processes
+}
+
+/// Disk space in bytes
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct DiskStatus {
+    pub mount_point: String,
+    pub total: u64,
+    pub available: u64,
+}
+
+/// Response of GET /api/connectivity
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+pub struct Connectivity {
+    pub online: bool,
+    pub last_online: Option<u64>,  // Unix timestamp in milliseconds of the last answered check
+    pub last_change: Option<u64>,  // When it last went offline or came back, None if it never did
+}
+
+/// Response of POST /api/system/check-update
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct UpdateInfo {
+    pub current: String,
+    pub latest: String,  // Version of the latest GitHub release
+    pub update_available: bool,
+    pub url: String,  // Release page
+    pub checked_at: u64,  // Unix timestamp in milliseconds
+}
+
+/// What the watchdog did about a frame that stopped sending heartbeats
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchdogAction {
+    Reload,  // Reloaded the page
+    Recreate,  // Closed the window and opened a new one
+    Restart,  // Restarted the app
+}
+
+/// A frame the watchdog had to recover
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct WatchdogIncident {
+    pub window: String,  // Window label, "main" or "monitor-N"
+    pub action: WatchdogAction,
+    pub silent_for: u64,  // Seconds since its last heartbeat
+    pub at: u64,  // Unix timestamp in milliseconds
+}
+
+/// Response of GET /api/watchdog
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Default)]
+pub struct WatchdogStatus {
+    pub windows: Vec<FrameHeartbeat>,
+    pub incidents: Vec<WatchdogIncident>,  // Most recent last
+}
+
+/// When a frame window last said it's alive
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct FrameHeartbeat {
+    pub window: String,
+    pub last_heartbeat: u64,  // Unix timestamp in milliseconds
+}
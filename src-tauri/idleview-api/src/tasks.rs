@@ -0,0 +1,29 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskSource {
+    Todoist,
+    File,
+}
+
+/// A task due today or earlier, or one without a date such as a grocery item
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct Task {
+    pub content: String,
+    #[serde(default)]
+    pub due: Option<String>,  // YYYY-MM-DD, None for tasks without a date
+    #[serde(default)]
+    pub overdue: bool,  // Due before today
+    pub source: TaskSource,
+}
+
+/// Response of GET /api/tasks
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct TasksReport {
+    pub tasks: Vec<Task>,  // Todoist's first, in its order, then the file's
+    pub fetched_at: Option<u64>,  // Unix timestamp in milliseconds of the last Todoist request, None before the first
+    #[serde(default)]
+    pub errors: Vec<String>,  // Why Todoist or the file couldn't be read; Todoist's last tasks are kept
+}
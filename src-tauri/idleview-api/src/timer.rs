@@ -0,0 +1,20 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Body of POST /api/timer
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct TimerRequest {
+    pub duration: u32,  // Seconds
+    #[serde(default)]
+    pub label: String,  // e.g. "Pasta"
+}
+
+/// The timer counting down on the frame
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub struct Timer {
+    pub id: u64,
+    pub label: String,
+    pub duration: u32,  // Seconds it was set for
+    pub remaining: u32,  // Seconds left, rounded up; 0 once it has finished
+    pub ends_at: u64,  // Unix timestamp in milliseconds
+}
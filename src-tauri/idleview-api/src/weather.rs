@@ -0,0 +1,29 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct WeatherData {
+    pub temperature: f64,
+    pub temperature_unit: String,
+    pub humidity: f64,
+    pub wind_speed: f64,
+    pub wind_speed_unit: String,
+    pub wind_speed_label: String,
+    pub cloudcover: f64,
+    pub rain: f64,
+    pub snowfall: f64,
+    pub sunrise: String,
+    pub sunset: String,
+    pub timezone: String,
+    #[serde(default)]
+    pub alerts: Vec<String>,  // e.g. "Strong wind", empty unless weather alerts are enabled
+}
+
+/// Response of GET /api/weather
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct WeatherReport {
+    pub weather: WeatherData,
+    pub fetched_at: u64,  // Unix timestamp in milliseconds
+    #[serde(default)]
+    pub stale: bool,  // Older than the refresh interval because refreshing failed
+}
@@ -0,0 +1,92 @@
+//! HTTP access log kept in the config directory.
+//!
+//! One JSON object per line, so it can be read with `jq` on a headless frame.
+//! The file is rotated by size and a few old ones are kept.
+
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::settings_manager::{self, AccessLogLevel};
+
+const LOG_FILE: &str = "access.log";
+/// Size at which the log is rotated
+pub const MAX_LOG_BYTES: u64 = 1024 * 1024;
+/// Rotated logs kept next to the current one (access.log.1 is the newest)
+const KEEP_ROTATED: usize = 3;
+
+/// Serializes appends so rotation never races a write
+static LOG_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccessLogEntry {
+    pub timestamp: u64,  // Unix timestamp in milliseconds
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub latency_ms: u64,
+    pub client: String,
+}
+
+/// Whether a request is logged at the given verbosity
+pub fn should_log(level: AccessLogLevel, read_only: bool, status: u16) -> bool {
+    let failed = status >= 400;
+    match level {
+        AccessLogLevel::Off => false,
+        AccessLogLevel::Errors => failed,
+        AccessLogLevel::Changes => failed || !read_only,
+        AccessLogLevel::All => true,
+    }
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+/// Append a line to `path`, first rotating it if the line would take it past `max_bytes`
+pub fn append_line(path: &Path, line: &str, max_bytes: u64) -> Result<(), String> {
+    let _guard = LOG_LOCK
+        .lock()
+        .map_err(|e| format!("Failed to lock access log: {}", e))?;
+
+    let size = fs::metadata(path).map(|meta| meta.len()).unwrap_or(0);
+    if size > 0 && size + line.len() as u64 + 1 > max_bytes {
+        for index in (1..KEEP_ROTATED).rev() {
+            let _ = fs::rename(rotated_path(path, index), rotated_path(path, index + 1));
+        }
+        fs::rename(path, rotated_path(path, 1))
+            .map_err(|e| format!("Failed to rotate {}: {}", path.display(), e))?;
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Delete the rotated logs next to `path`, returning how many there were
+pub fn remove_rotated(path: &Path) -> Result<usize, String> {
+    let _guard = LOG_LOCK
+        .lock()
+        .map_err(|e| format!("Failed to lock access log: {}", e))?;
+    Ok((1..=KEEP_ROTATED).filter(|index| fs::remove_file(rotated_path(path, *index)).is_ok()).count())
+}
+
+/// Delete the rotated access logs in the config directory, to free space
+pub fn trim() -> Result<usize, String> {
+    remove_rotated(&settings_manager::get_config_file_path(LOG_FILE)?)
+}
+
+/// Write an entry to the access log in the config directory
+pub fn record(entry: &AccessLogEntry) -> Result<(), String> {
+    let line = serde_json::to_string(entry)
+        .map_err(|e| format!("Failed to serialize access log entry: {}", e))?;
+    settings_manager::ensure_settings_dir()?;
+    append_line(&settings_manager::get_config_file_path(LOG_FILE)?, &line, MAX_LOG_BYTES)
+}
@@ -0,0 +1,105 @@
+//! Alarms and daily chimes kept in `alarms.list`. The scheduler wakes at the
+//! start of every minute and raises `alarm-fired` for each enabled alarm set
+//! to that time on that day; the frame shows the label and plays the chime,
+//! while webhooks and event stream clients get the same event.
+//!
+//! Minutes the clock skips over, e.g. when NTP moves it forward after an
+//! offline boot, don't fire their alarms late.
+
+use chrono::{Datelike, Local, NaiveDateTime, NaiveTime, Timelike, Weekday};
+use serde_json::json;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use idleview_api::ServerEvent;
+
+use crate::http_server::AppState;
+use crate::settings_manager::{Alarm, AlarmDay, AlarmsSettings, Settings, SettingsManager};
+
+/// Slept past each minute boundary, so a timer firing a little early doesn't
+/// land on the previous minute
+const MINUTE_MARGIN: Duration = Duration::from_millis(250);
+
+pub fn find_alarm(alarms: &AlarmsSettings, id: u32) -> Option<&Alarm> {
+    alarms.list.iter().find(|alarm| alarm.id == id)
+}
+
+/// The id a new alarm gets: one past the highest in use
+pub fn next_id(alarms: &AlarmsSettings) -> u32 {
+    alarms.list.iter().map(|alarm| alarm.id).max().unwrap_or(0) + 1
+}
+
+/// Add an alarm, or replace the one with the same id
+pub fn save_alarm(manager: &SettingsManager, alarm: Alarm) -> Result<Settings, String> {
+    let mut list = manager.get()?.alarms.list;
+    match list.iter_mut().find(|existing| existing.id == alarm.id) {
+        Some(existing) => *existing = alarm,
+        None => list.push(alarm),
+    }
+    manager.update_partial(json!({ "alarms": { "list": list } }))
+}
+
+pub fn delete_alarm(manager: &SettingsManager, id: u32) -> Result<Settings, String> {
+    let list: Vec<Alarm> = manager.get()?.alarms.list.into_iter().filter(|alarm| alarm.id != id).collect();
+    manager.update_partial(json!({ "alarms": { "list": list } }))
+}
+
+fn alarm_day(weekday: Weekday) -> AlarmDay {
+    match weekday {
+        Weekday::Mon => AlarmDay::Mon,
+        Weekday::Tue => AlarmDay::Tue,
+        Weekday::Wed => AlarmDay::Wed,
+        Weekday::Thu => AlarmDay::Thu,
+        Weekday::Fri => AlarmDay::Fri,
+        Weekday::Sat => AlarmDay::Sat,
+        Weekday::Sun => AlarmDay::Sun,
+    }
+}
+
+/// Enabled alarms set to the minute of `now` on its day of the week
+pub fn due_alarms(alarms: &[Alarm], now: NaiveDateTime) -> Vec<&Alarm> {
+    let day = alarm_day(now.weekday());
+    alarms
+        .iter()
+        .filter(|alarm| alarm.enabled && (alarm.days.is_empty() || alarm.days.contains(&day)))
+        .filter(|alarm| {
+            NaiveTime::parse_from_str(alarm.time.trim(), "%H:%M")
+                .is_ok_and(|time| time.hour() == now.hour() && time.minute() == now.minute())
+        })
+        .collect()
+}
+
+/// How long until the next minute starts
+fn until_next_minute(now: NaiveDateTime) -> Duration {
+    let into_minute = Duration::from_secs(u64::from(now.second())) + Duration::from_nanos(u64::from(now.nanosecond()));
+    Duration::from_secs(60).saturating_sub(into_minute) + MINUTE_MARGIN
+}
+
+/// Fire alarms at the start of their minute for the lifetime of the server
+pub fn spawn_scheduler(state: AppState) {
+    tokio::spawn(async move {
+        let mut last_minute = None;
+        loop {
+            tokio::time::sleep(until_next_minute(Local::now().naive_local())).await;
+            let now = Local::now().naive_local();
+            let minute = now.with_second(0).and_then(|now| now.with_nanosecond(0));
+            // Waking twice in one minute mustn't fire its alarms twice
+            if minute == last_minute {
+                continue;
+            }
+            last_minute = minute;
+
+            let alarms = match state.settings_manager.get() {
+                Ok(settings) => settings.alarms.list,
+                Err(e) => {
+                    warn!("Alarms: failed to read settings: {}", e);
+                    continue;
+                }
+            };
+            for alarm in due_alarms(&alarms, now) {
+                info!("⏰ Alarm {} ({}) fired: {}", alarm.id, alarm.time, alarm.label);
+                state.publish_from(ServerEvent::AlarmFired(alarm.clone()), "alarms");
+            }
+        }
+    });
+}
@@ -0,0 +1,104 @@
+//! API token guarding the mutating HTTP endpoints.
+//!
+//! The token is generated on first use and kept in the config directory, so
+//! it survives restarts until it is rotated. Tokens of paired devices (see
+//! `pairing`) are accepted too.
+
+use rand::Rng;
+use std::fs;
+use std::sync::{OnceLock, RwLock};
+
+use crate::pairing;
+use crate::settings_manager;
+
+static API_TOKEN: OnceLock<RwLock<String>> = OnceLock::new();
+
+const TOKEN_FILE: &str = "api_token";
+
+/// A new random token: 32 bytes, hex encoded
+pub(crate) fn generate_token() -> String {
+    let bytes: [u8; 32] = rand::rng().random();
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Write a file in the config directory that only the current user can read
+pub(crate) fn write_secret_file(file_name: &str, contents: &str) -> Result<(), String> {
+    settings_manager::ensure_settings_dir()?;
+    let path = settings_manager::get_config_file_path(file_name)?;
+    settings_manager::write_file_atomic(&path, contents.as_bytes())?;
+
+    // Only the user running the frame should be able to read it
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))
+            .map_err(|e| format!("Failed to restrict permissions of {}: {}", file_name, e))?;
+    }
+    Ok(())
+}
+
+fn load_or_create_token() -> Result<String, String> {
+    let path = settings_manager::get_config_file_path(TOKEN_FILE)?;
+    if let Ok(token) = fs::read_to_string(&path) {
+        let token = token.trim();
+        if !token.is_empty() {
+            return Ok(token.to_string());
+        }
+    }
+
+    let token = generate_token();
+    write_secret_file(TOKEN_FILE, &token)?;
+    Ok(token)
+}
+
+fn token_lock() -> Result<&'static RwLock<String>, String> {
+    if let Some(lock) = API_TOKEN.get() {
+        return Ok(lock);
+    }
+    let token = load_or_create_token()?;
+    Ok(API_TOKEN.get_or_init(|| RwLock::new(token)))
+}
+
+/// The current API token, created on first use
+pub fn api_token() -> Result<String, String> {
+    token_lock()?
+        .read()
+        .map(|token| token.clone())
+        .map_err(|e| format!("Failed to read API token: {}", e))
+}
+
+/// Replace the API token. Clients using the old one are rejected from now on.
+pub fn rotate_api_token() -> Result<String, String> {
+    let lock = token_lock()?;
+    let token = generate_token();
+    write_secret_file(TOKEN_FILE, &token)?;
+    *lock
+        .write()
+        .map_err(|e| format!("Failed to update API token: {}", e))? = token.clone();
+    Ok(token)
+}
+
+/// Compare every byte so the time taken doesn't reveal how much matched
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// The token of a `Bearer <token>` Authorization header
+fn bearer_token(authorization: Option<&str>) -> Option<&str> {
+    authorization
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// Whether an Authorization header carries `Bearer <token>`
+pub fn bearer_matches(authorization: Option<&str>, token: &str) -> bool {
+    bearer_token(authorization).is_some_and(|presented| constant_time_eq(presented.as_bytes(), token.as_bytes()))
+}
+
+/// Whether an Authorization header carries the API token or a paired device's token
+pub fn is_authorized(authorization: Option<&str>) -> Result<bool, String> {
+    if bearer_matches(authorization, &api_token()?) {
+        return Ok(true);
+    }
+    Ok(bearer_token(authorization).is_some_and(pairing::is_device_token))
+}
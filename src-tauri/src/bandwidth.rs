@@ -0,0 +1,111 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+
+static MONITOR: OnceLock<Mutex<BandwidthMonitor>> = OnceLock::new();
+
+/// Number of recent photo downloads averaged before switching modes
+const SAMPLE_WINDOW: usize = 3;
+/// Switch to degraded mode when average throughput drops below this
+const DEGRADE_BELOW_KBPS: f64 = 250.0;
+/// Return to normal once average throughput climbs above this
+const RECOVER_ABOVE_KBPS: f64 = 600.0;
+
+/// Photo quality cap and resolution scale while degraded
+pub const DEGRADED_MAX_QUALITY: u32 = 60;
+pub const DEGRADED_RESOLUTION_SCALE: f64 = 0.5;
+/// Refresh interval multiplier while degraded
+pub const DEGRADED_INTERVAL_FACTOR: u64 = 2;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct NetworkMode {
+    pub mode: String,                   // "normal" or "degraded"
+    pub throughput_kbps: Option<f64>,   // Average over recent photo downloads
+}
+
+/// Tracks photo download throughput with hysteresis so the mode doesn't flap
+#[derive(Debug, Default)]
+pub struct BandwidthMonitor {
+    samples: VecDeque<f64>,
+    degraded: bool,
+}
+
+impl BandwidthMonitor {
+    /// Record one download; returns true when the mode changed
+    pub fn record(&mut self, bytes: u64, duration_ms: u64) -> bool {
+        if bytes == 0 || duration_ms == 0 {
+            return false;
+        }
+
+        let kbps = bytes as f64 / 1024.0 / (duration_ms as f64 / 1000.0);
+        self.samples.push_back(kbps);
+        while self.samples.len() > SAMPLE_WINDOW {
+            self.samples.pop_front();
+        }
+
+        // Only sustained conditions count, so wait for a full window
+        let Some(average) = self.average().filter(|_| self.samples.len() == SAMPLE_WINDOW) else {
+            return false;
+        };
+
+        let was_degraded = self.degraded;
+        if !self.degraded && average < DEGRADE_BELOW_KBPS {
+            self.degraded = true;
+        } else if self.degraded && average > RECOVER_ABOVE_KBPS {
+            self.degraded = false;
+        }
+        was_degraded != self.degraded
+    }
+
+    pub fn average(&self) -> Option<f64> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        Some(self.samples.iter().sum::<f64>() / self.samples.len() as f64)
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.degraded
+    }
+
+    pub fn mode(&self) -> NetworkMode {
+        NetworkMode {
+            mode: if self.degraded { "degraded" } else { "normal" }.to_string(),
+            throughput_kbps: self.average(),
+        }
+    }
+}
+
+fn monitor() -> &'static Mutex<BandwidthMonitor> {
+    MONITOR.get_or_init(|| Mutex::new(BandwidthMonitor::default()))
+}
+
+/// Record a completed photo download reported by the frontend
+pub fn record_photo_download_impl(bytes: u64, duration_ms: u64) -> NetworkMode {
+    match monitor().lock() {
+        Ok(mut monitor) => {
+            if monitor.record(bytes, duration_ms) {
+                eprintln!("Photo pipeline switched to {} mode", monitor.mode().mode);
+            }
+            monitor.mode()
+        }
+        Err(_) => current_mode(),
+    }
+}
+
+pub fn current_mode() -> NetworkMode {
+    monitor()
+        .lock()
+        .map(|monitor| monitor.mode())
+        .unwrap_or(NetworkMode {
+            mode: "normal".to_string(),
+            throughput_kbps: None,
+        })
+}
+
+pub fn is_degraded() -> bool {
+    monitor()
+        .lock()
+        .map(|monitor| monitor.is_degraded())
+        .unwrap_or(false)
+}
@@ -0,0 +1,101 @@
+//! Battery, for frames running on a laptop or tablet. The charge, whether
+//! it's charging and the time left come from the first battery the system
+//! reports; a desktop or a Pi simply has none, and the battery is left out
+//! of `GET /api/system`.
+//!
+//! Once the charge drops to `display.low_battery_warning` percent while
+//! discharging, `battery-low` goes out and the frame shows a warning until
+//! it's plugged in.
+
+use std::time::Duration;
+use tracing::{debug, warn};
+
+pub use idleview_api::system::{BatteryState, BatteryStatus};
+use idleview_api::ServerEvent;
+use starship_battery::units::ratio::percent;
+use starship_battery::units::time::second;
+
+use crate::http_server::AppState;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How far the charge has to climb back above the limit before it can be reported low again
+pub const LOW_BATTERY_HYSTERESIS: f32 = 5.0;
+
+/// Whether the battery is low enough to warn about; 0 turns the warning off
+pub fn is_low(battery: &BatteryStatus, limit: u8) -> bool {
+    limit > 0 && battery.state == BatteryState::Discharging && battery.percent <= f32::from(limit)
+}
+
+/// Whether the battery newly runs low. `low` carries the state between
+/// checks, so one discharge is reported once.
+pub fn battery_crossed(battery: &BatteryStatus, limit: u8, low: &mut bool) -> bool {
+    if *low {
+        *low = battery.state == BatteryState::Discharging
+            && battery.percent < f32::from(limit) + LOW_BATTERY_HYSTERESIS;
+        return false;
+    }
+    *low = is_low(battery, limit);
+    *low
+}
+
+fn state_of(state: starship_battery::State) -> BatteryState {
+    match state {
+        starship_battery::State::Charging => BatteryState::Charging,
+        starship_battery::State::Discharging => BatteryState::Discharging,
+        starship_battery::State::Full => BatteryState::Full,
+        starship_battery::State::Empty => BatteryState::Empty,
+        _ => BatteryState::Unknown,
+    }
+}
+
+fn read() -> Result<Option<BatteryStatus>, starship_battery::Error> {
+    let manager = starship_battery::Manager::new()?;
+    let Some(battery) = manager.batteries()?.next().transpose()? else {
+        return Ok(None);
+    };
+    let state = state_of(battery.state());
+    let time_remaining = match state {
+        BatteryState::Charging => battery.time_to_full(),
+        BatteryState::Discharging => battery.time_to_empty(),
+        _ => None,
+    };
+    Ok(Some(BatteryStatus {
+        percent: battery.state_of_charge().get::<percent>().clamp(0.0, 100.0),
+        state,
+        time_remaining: time_remaining.map(|time| time.get::<second>().max(0.0) as u64),
+    }))
+}
+
+/// The first battery's charge, None without a battery
+pub fn status() -> Option<BatteryStatus> {
+    read().unwrap_or_else(|e| {
+        debug!("No battery status: {}", e);
+        None
+    })
+}
+
+/// Check the battery for the lifetime of the server
+pub fn spawn_monitor(state: AppState) {
+    tokio::spawn(async move {
+        let mut low = false;
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let Ok(Some(battery)) = tokio::task::spawn_blocking(status).await else {
+                continue;
+            };
+            let limit = match state.settings_manager.get() {
+                Ok(settings) => settings.display.low_battery_warning,
+                Err(e) => {
+                    warn!("Failed to read the low battery warning: {}", e);
+                    continue;
+                }
+            };
+            if battery_crossed(&battery, limit, &mut low) {
+                warn!("🪫 Battery at {:.0}%, at or below the {}% warning", battery.percent, limit);
+                state.publish_from(ServerEvent::BatteryLow(battery), "battery");
+            }
+        }
+    });
+}
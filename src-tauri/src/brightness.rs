@@ -0,0 +1,116 @@
+//! Screen brightness, through the Linux sysfs backlight when there is one
+//! the app may write to, and otherwise by dimming the frame itself.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter};
+use tracing::warn;
+
+pub use idleview_api::display::{Brightness, BrightnessMethod, BrightnessRequest};
+
+/// Where Linux lists backlight devices
+pub const BACKLIGHT_ROOT: &str = "/sys/class/backlight";
+
+/// Tauri event telling the frontend how far to dim itself
+pub const SOFTWARE_BRIGHTNESS_EVENT: &str = "software-brightness";
+
+/// Brightness of the dimming overlay, for when there is no usable backlight
+static SOFTWARE_PERCENT: Mutex<u8> = Mutex::new(100);
+
+fn read_number(path: &Path) -> Result<u32, String> {
+    std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .trim()
+        .parse()
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+/// The first backlight device under `root` with a usable maximum
+pub fn find_backlight(root: &Path) -> Option<PathBuf> {
+    let mut devices: Vec<PathBuf> = std::fs::read_dir(root)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    devices.sort();
+    devices
+        .into_iter()
+        .find(|device| read_number(&device.join("max_brightness")).is_ok_and(|max| max > 0))
+}
+
+/// A raw backlight level as a percentage of `max`
+pub fn to_percent(raw: u32, max: u32) -> u8 {
+    if max == 0 {
+        return 0;
+    }
+    ((raw.min(max) as f64 / max as f64) * 100.0).round() as u8
+}
+
+/// A percentage as a raw backlight level out of `max`
+pub fn to_raw(percent: u8, max: u32) -> u32 {
+    ((percent.min(100) as f64 / 100.0) * max as f64).round() as u32
+}
+
+/// Current brightness of a backlight device, in percent
+pub fn backlight_percent(device: &Path) -> Result<u8, String> {
+    let max = read_number(&device.join("max_brightness"))?;
+    let raw = read_number(&device.join("brightness"))?;
+    Ok(to_percent(raw, max))
+}
+
+/// Set a backlight device to `percent`
+pub fn set_backlight_percent(device: &Path, percent: u8) -> Result<(), String> {
+    let max = read_number(&device.join("max_brightness"))?;
+    let path = device.join("brightness");
+    std::fs::write(&path, to_raw(percent, max).to_string())
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn software_percent() -> u8 {
+    SOFTWARE_PERCENT.lock().map(|percent| *percent).unwrap_or(100)
+}
+
+/// Dim the frame to `percent`, telling the frontend when it changes
+fn set_software_percent(app: &AppHandle, percent: u8) -> Result<(), String> {
+    if let Ok(mut current) = SOFTWARE_PERCENT.lock() {
+        if *current == percent {
+            return Ok(());
+        }
+        *current = percent;
+    }
+    let brightness = Brightness { percent, method: BrightnessMethod::Software };
+    app.emit(SOFTWARE_BRIGHTNESS_EVENT, brightness)
+        .map_err(|e| format!("Failed to dim the frame: {}", e))
+}
+
+/// The brightness the screen is at
+pub fn brightness() -> Brightness {
+    let software = software_percent();
+    let backlight = find_backlight(Path::new(BACKLIGHT_ROOT)).and_then(|device| backlight_percent(&device).ok());
+    match backlight {
+        // While the frame is dimmed, the backlight level isn't what the user sees
+        Some(percent) if software == 100 => Brightness { percent, method: BrightnessMethod::Backlight },
+        _ => Brightness { percent: software, method: BrightnessMethod::Software },
+    }
+}
+
+/// Set the brightness, dimming the frame when the backlight can't be written
+pub fn set_brightness(app: &AppHandle, percent: u8) -> Result<Brightness, String> {
+    if percent > 100 {
+        return Err(format!("Brightness must be between 0 and 100, got {}", percent));
+    }
+
+    if let Some(device) = find_backlight(Path::new(BACKLIGHT_ROOT)) {
+        match set_backlight_percent(&device, percent) {
+            Ok(()) => {
+                // Undo any earlier software dimming
+                set_software_percent(app, 100)?;
+                return Ok(Brightness { percent, method: BrightnessMethod::Backlight });
+            }
+            // Usually a missing udev rule giving the user write access
+            Err(e) => warn!("Brightness: {}, dimming in software instead", e),
+        }
+    }
+
+    set_software_percent(app, percent)?;
+    Ok(Brightness { percent, method: BrightnessMethod::Software })
+}
@@ -0,0 +1,102 @@
+//! Burn-in protection for OLED and plasma screens, where the clock digits
+//! and widgets that never move ghost after a few months. Every
+//! `display.burn_in.shift_interval` seconds the frame is moved a few pixels,
+//! walking rings around its place so no pixel stays lit the same way; every
+//! `refresh_interval` minutes the frame is asked to dim or invert the whole
+//! screen for `refresh_duration` seconds.
+//!
+//! Nothing runs during sleep hours, with the screen off.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+pub use idleview_api::display::{PixelShift, ScreenRefresh};
+use idleview_api::ServerEvent;
+
+use crate::display_sleep;
+use crate::http_server::AppState;
+
+/// Between looks at the settings while protection is off
+const OFF_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Directions around the frame's place, one step per shift
+const ORBIT: [(i32, i32); 8] = [(-1, -1), (0, -1), (1, -1), (1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0)];
+
+static SHIFT: Mutex<PixelShift> = Mutex::new(PixelShift { x: 0, y: 0 });
+
+/// Offset for the `step`th shift: once round the orbit at one pixel, then at
+/// two and so on up to `max_shift`, then from one again
+pub fn pixel_shift(step: u64, max_shift: u32) -> PixelShift {
+    if max_shift == 0 {
+        return PixelShift::default();
+    }
+    let orbit = ORBIT.len() as u64;
+    let (x, y) = ORBIT[(step % orbit) as usize];
+    let radius = (1 + step / orbit % u64::from(max_shift)) as i32;
+    PixelShift { x: x * radius, y: y * radius }
+}
+
+/// Whether a refresh cycle is due, `since` the last one
+pub fn refresh_due(since: Duration, refresh_interval: u64) -> bool {
+    refresh_interval > 0 && since >= Duration::from_secs(refresh_interval * 60)
+}
+
+/// Where the frame is moved to right now
+pub fn current_shift() -> PixelShift {
+    SHIFT.lock().map(|shift| *shift).unwrap_or_default()
+}
+
+fn set_shift(state: &AppState, shift: PixelShift) {
+    let Ok(mut current) = SHIFT.lock() else { return };
+    if *current == shift {
+        return;
+    }
+    *current = shift;
+    drop(current);
+    state.publish_from(ServerEvent::PixelShift(shift), "burn-in");
+}
+
+/// Shift the frame and schedule refresh cycles for the lifetime of the server
+pub fn spawn_scheduler(state: AppState) {
+    tokio::spawn(async move {
+        let mut step = 0u64;
+        let mut last_refresh = Instant::now();
+        loop {
+            let settings = match state.settings_manager.get() {
+                Ok(settings) => settings.display.burn_in,
+                Err(e) => {
+                    warn!("Burn-in: failed to read settings: {}", e);
+                    tokio::time::sleep(OFF_CHECK_INTERVAL).await;
+                    continue;
+                }
+            };
+            if !settings.enabled {
+                // Turned off: put the frame back in its place
+                set_shift(&state, PixelShift::default());
+                last_refresh = Instant::now();
+                tokio::time::sleep(OFF_CHECK_INTERVAL).await;
+                continue;
+            }
+
+            tokio::time::sleep(Duration::from_secs(settings.shift_interval.max(1))).await;
+            if display_sleep::is_asleep() {
+                continue;
+            }
+            step += 1;
+            set_shift(&state, pixel_shift(step, settings.max_shift));
+
+            if refresh_due(last_refresh.elapsed(), settings.refresh_interval) {
+                info!("🧽 Burn-in refresh ({:?}) for {}s", settings.refresh_mode, settings.refresh_duration);
+                last_refresh = Instant::now();
+                state.publish_from(
+                    ServerEvent::ScreenRefresh(ScreenRefresh {
+                        mode: settings.refresh_mode,
+                        duration: settings.refresh_duration,
+                    }),
+                    "burn-in",
+                );
+            }
+        }
+    });
+}
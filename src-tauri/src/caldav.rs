@@ -0,0 +1,165 @@
+//! CalDAV calendars (Nextcloud, Fastmail, Radicale), for self-hosted
+//! calendars that shouldn't be published as an iCal link.
+//!
+//! Each calendar collection is asked for the events in the window with a
+//! `calendar-query` REPORT, and the iCal objects it returns go through the
+//! same parser as the feeds. Passwords (app passwords, ideally) are kept in
+//! their own file in the config directory, readable only by the frame's user,
+//! so they never show up in the settings, backups or the API.
+
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+pub use idleview_api::calendar::CalDavPasswordRequest;
+
+use crate::auth;
+use crate::calendar::{self, CalendarEvent};
+use crate::settings_manager::{self, CalDavCalendar};
+
+const PASSWORDS_FILE: &str = "caldav_passwords.json";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Passwords by calendar name
+static PASSWORDS: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+
+fn passwords() -> &'static Mutex<HashMap<String, String>> {
+    PASSWORDS.get_or_init(|| {
+        let passwords = settings_manager::get_config_file_path(PASSWORDS_FILE)
+            .ok()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Mutex::new(passwords)
+    })
+}
+
+/// The stored password of a calendar
+pub fn password(name: &str) -> Option<String> {
+    passwords().lock().ok()?.get(name).cloned()
+}
+
+/// Store a calendar's password, or remove it when empty
+pub fn set_password(name: &str, password: &str) -> Result<(), String> {
+    let mut passwords = passwords()
+        .lock()
+        .map_err(|e| format!("Failed to lock CalDAV passwords: {}", e))?;
+    if password.is_empty() {
+        passwords.remove(name);
+    } else {
+        passwords.insert(name.to_string(), password.to_string());
+    }
+    let json = serde_json::to_string_pretty(&*passwords)
+        .map_err(|e| format!("Failed to serialize CalDAV passwords: {}", e))?;
+    auth::write_secret_file(PASSWORDS_FILE, &json)
+}
+
+/// REPORT body asking for the events overlapping the window
+pub fn calendar_query(now: u64, window_end: u64) -> String {
+    let utc = |ms: u64| {
+        DateTime::<Utc>::from_timestamp_millis(ms as i64)
+            .unwrap_or_default()
+            .format("%Y%m%dT%H%M%SZ")
+            .to_string()
+    };
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><c:calendar-data/></d:prop>
+  <c:filter>
+    <c:comp-filter name="VCALENDAR">
+      <c:comp-filter name="VEVENT">
+        <c:time-range start="{}" end="{}"/>
+      </c:comp-filter>
+    </c:comp-filter>
+  </c:filter>
+</c:calendar-query>"#,
+        utc(now),
+        utc(window_end)
+    )
+}
+
+fn unescape_xml(text: &str) -> String {
+    if let Some(cdata) = text.trim().strip_prefix("<![CDATA[").and_then(|rest| rest.strip_suffix("]]>")) {
+        return cdata.to_string();
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#13;", "\r")
+        .replace("&#xD;", "\r")
+        .replace("&amp;", "&")
+}
+
+/// The iCal objects in a multistatus response, whatever prefix the server
+/// gives the CalDAV namespace
+pub fn calendar_data(multistatus: &str) -> Vec<String> {
+    let mut objects = Vec::new();
+    let mut rest = multistatus;
+    while let Some(open) = rest.find('<') {
+        rest = &rest[open + 1..];
+        let Some(tag_end) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[..tag_end];
+        rest = &rest[tag_end + 1..];
+        let name = tag.split_whitespace().next().unwrap_or_default();
+        let local_name = name.rsplit(':').next().unwrap_or(name);
+        if name.starts_with('/') || local_name != "calendar-data" || tag.ends_with('/') {
+            continue;
+        }
+        let closing = format!("</{}>", name);
+        let Some(end) = rest.find(&closing) else {
+            break;
+        };
+        objects.push(unescape_xml(&rest[..end]));
+        rest = &rest[end + closing.len()..];
+    }
+    objects
+}
+
+/// Events of one CalDAV calendar between `now` and `window_end`, in milliseconds
+pub async fn fetch_events_from(
+    client: &reqwest::Client,
+    calendar: &CalDavCalendar,
+    password: Option<&str>,
+    now: u64,
+    window_end: u64,
+) -> Result<Vec<CalendarEvent>, String> {
+    let report = reqwest::Method::from_bytes(b"REPORT").map_err(|e| format!("Invalid method: {}", e))?;
+    let body = client
+        .request(report, &calendar.url)
+        .basic_auth(&calendar.username, password)
+        .header("Depth", "1")
+        .header(reqwest::header::CONTENT_TYPE, "application/xml; charset=utf-8")
+        .body(calendar_query(now, window_end))
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to query CalDAV calendar {}: {}", calendar.name, e))?
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read CalDAV calendar {}: {}", calendar.name, e))?;
+
+    Ok(calendar_data(&body)
+        .iter()
+        .flat_map(|object| calendar::parse_ical(object, &calendar.name, now, window_end))
+        .collect())
+}
+
+/// Events of one configured calendar, with its stored password
+pub async fn upcoming_events(
+    client: &reqwest::Client,
+    calendar: &CalDavCalendar,
+    now: u64,
+    window_end: u64,
+) -> Result<Vec<CalendarEvent>, String> {
+    let password = password(&calendar.name)
+        .ok_or_else(|| format!("No password set for CalDAV calendar {}", calendar.name))?;
+    fetch_events_from(client, calendar, Some(&password), now, window_end).await
+}
@@ -0,0 +1,555 @@
+//! Upcoming events from iCal feeds, for the frame's agenda and for scenes
+//! with calendar keywords.
+//!
+//! The parser covers what shared family calendars use: timed and all-day
+//! events, DURATION, EXDATE, cancelled events, moved occurrences
+//! (RECURRENCE-ID) and RRULE with FREQ, INTERVAL, COUNT, UNTIL and weekly
+//! BYDAY. Times with a TZID are taken as the frame's local time. Events of
+//! CalDAV calendars (see `caldav`) and a connected Google account (see
+//! `google_calendar`) are merged in.
+
+use chrono::{
+    Datelike, Duration as ChronoDuration, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday,
+};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+pub use idleview_api::calendar::{CalendarEvent, CalendarReport};
+use idleview_api::ServerEvent;
+
+use crate::caldav;
+use crate::display_sleep;
+use crate::google_calendar;
+use crate::health;
+use crate::http_server::AppState;
+use crate::scenes;
+use crate::settings_manager::{CalendarFeed, CalendarSettings};
+
+/// How often the refresher wakes to see whether a refresh is due and which
+/// events are happening, for scenes with calendar keywords
+const TICK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Occurrences expanded per recurring event before giving up
+const MAX_OCCURRENCES: u32 = 5000;
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+static REFRESH: Notify = Notify::const_new();
+
+static REPORT: Mutex<CalendarReport> = Mutex::new(CalendarReport {
+    events: Vec::new(),
+    fetched_at: None,
+    errors: Vec::new(),
+});
+
+/// A DATE or DATE-TIME value as written in the feed
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum IcalTime {
+    Utc(NaiveDateTime),
+    Local(NaiveDateTime),  // Floating, or with a TZID
+    Date(NaiveDate),
+}
+
+impl IcalTime {
+    fn naive(&self) -> NaiveDateTime {
+        match self {
+            IcalTime::Utc(time) | IcalTime::Local(time) => *time,
+            IcalTime::Date(date) => date.and_time(NaiveTime::MIN),
+        }
+    }
+
+    /// The same kind of value at another time
+    fn with_naive(&self, naive: NaiveDateTime) -> IcalTime {
+        match self {
+            IcalTime::Utc(_) => IcalTime::Utc(naive),
+            IcalTime::Local(_) => IcalTime::Local(naive),
+            IcalTime::Date(_) => IcalTime::Date(naive.date()),
+        }
+    }
+
+    fn timestamp_ms(&self) -> Option<u64> {
+        let ms = match self {
+            IcalTime::Utc(time) => Utc.from_utc_datetime(time).timestamp_millis(),
+            _ => Local.from_local_datetime(&self.naive()).earliest()?.timestamp_millis(),
+        };
+        u64::try_from(ms).ok()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Debug)]
+struct RecurrenceRule {
+    frequency: Frequency,
+    interval: u32,
+    count: Option<u32>,
+    until: Option<IcalTime>,
+    by_day: Vec<Weekday>,
+}
+
+#[derive(Debug, Default)]
+struct RawEvent {
+    uid: String,
+    summary: String,
+    location: Option<String>,
+    start: Option<IcalTime>,
+    end: Option<IcalTime>,
+    duration: Option<ChronoDuration>,
+    rule: Option<RecurrenceRule>,
+    exdates: Vec<IcalTime>,
+    recurrence_id: Option<IcalTime>,
+    cancelled: bool,
+}
+
+/// Join folded lines back together
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        if let Some(rest) = line.strip_prefix([' ', '\t']) {
+            if let Some(last) = lines.last_mut() {
+                last.push_str(rest);
+                continue;
+            }
+        }
+        lines.push(line.to_string());
+    }
+    lines
+}
+
+/// Split a content line into its name, parameters and value. Colons inside
+/// quoted parameters (like ALTREP URLs) don't end the name.
+fn split_line(line: &str) -> Option<(String, &str, &str)> {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ':' if !quoted => {
+                let head = &line[..i];
+                let (name, params) = head.split_once(';').unwrap_or((head, ""));
+                return Some((name.to_ascii_uppercase(), params, &line[i + 1..]));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn unescape(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n' | 'N') => text.push('\n'),
+                Some(other) => text.push(other),
+                None => {}
+            },
+            _ => text.push(c),
+        }
+    }
+    text
+}
+
+fn parse_time(value: &str) -> Option<IcalTime> {
+    let value = value.trim();
+    if value.len() == 8 {
+        return NaiveDate::parse_from_str(value, "%Y%m%d").ok().map(IcalTime::Date);
+    }
+    match value.strip_suffix('Z') {
+        Some(utc) => NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok().map(IcalTime::Utc),
+        None => NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok().map(IcalTime::Local),
+    }
+}
+
+/// A DURATION like "PT1H30M" or "P1D"
+fn parse_duration(value: &str) -> Option<ChronoDuration> {
+    let value = value.trim();
+    let rest = value.strip_prefix('+').unwrap_or(value).strip_prefix('P')?;
+    let mut total = ChronoDuration::zero();
+    let mut number = String::new();
+    for c in rest.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {}
+            'W' | 'D' | 'H' | 'M' | 'S' => {
+                let n: i64 = number.parse().ok()?;
+                number.clear();
+                total += match c {
+                    'W' => ChronoDuration::weeks(n),
+                    'D' => ChronoDuration::days(n),
+                    'H' => ChronoDuration::hours(n),
+                    'M' => ChronoDuration::minutes(n),
+                    _ => ChronoDuration::seconds(n),
+                };
+            }
+            _ => return None,
+        }
+    }
+    Some(total)
+}
+
+fn parse_weekday(code: &str) -> Option<Weekday> {
+    // Ordinals like "2TU" only make sense for monthly rules, which aren't expanded by day
+    match code.get(code.len().checked_sub(2)?..)? {
+        "MO" => Some(Weekday::Mon),
+        "TU" => Some(Weekday::Tue),
+        "WE" => Some(Weekday::Wed),
+        "TH" => Some(Weekday::Thu),
+        "FR" => Some(Weekday::Fri),
+        "SA" => Some(Weekday::Sat),
+        "SU" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+/// An RRULE this parser can expand, or None
+fn parse_rule(value: &str) -> Option<RecurrenceRule> {
+    let mut rule = RecurrenceRule {
+        frequency: Frequency::Daily,
+        interval: 1,
+        count: None,
+        until: None,
+        by_day: Vec::new(),
+    };
+    let mut frequency = None;
+    for part in value.split(';') {
+        let (key, value) = part.split_once('=')?;
+        match key.to_ascii_uppercase().as_str() {
+            "FREQ" => {
+                frequency = Some(match value {
+                    "DAILY" => Frequency::Daily,
+                    "WEEKLY" => Frequency::Weekly,
+                    "MONTHLY" => Frequency::Monthly,
+                    "YEARLY" => Frequency::Yearly,
+                    _ => return None,
+                })
+            }
+            "INTERVAL" => rule.interval = value.parse().ok().filter(|interval| *interval > 0)?,
+            "COUNT" => rule.count = Some(value.parse().ok()?),
+            "UNTIL" => rule.until = Some(parse_time(value)?),
+            "BYDAY" => rule.by_day = value.split(',').map(parse_weekday).collect::<Option<_>>()?,
+            // Usually just repeats what DTSTART says
+            "BYMONTH" | "BYMONTHDAY" | "WKST" => {}
+            _ => return None,
+        }
+    }
+    rule.frequency = frequency?;
+    if !rule.by_day.is_empty() && rule.frequency != Frequency::Weekly {
+        return None;
+    }
+    Some(rule)
+}
+
+/// Start times of a recurring event, from its first occurrence until `window_end`
+fn expand(start: IcalTime, rule: &RecurrenceRule, window_end: u64) -> Vec<IcalTime> {
+    let until = rule.until.and_then(|until| until.timestamp_ms());
+    let base = start.naive();
+    let mut by_day = rule.by_day.clone();
+    by_day.sort_by_key(|day| day.num_days_from_monday());
+
+    let mut occurrences = Vec::new();
+    for n in 0..MAX_OCCURRENCES {
+        let step = n * rule.interval;
+        let candidates: Vec<NaiveDateTime> = match rule.frequency {
+            Frequency::Daily => vec![base + ChronoDuration::days(step as i64)],
+            Frequency::Weekly if !by_day.is_empty() => {
+                let week = base + ChronoDuration::weeks(step as i64)
+                    - ChronoDuration::days(base.weekday().num_days_from_monday() as i64);
+                by_day
+                    .iter()
+                    .map(|day| week + ChronoDuration::days(day.num_days_from_monday() as i64))
+                    .filter(|candidate| *candidate >= base)
+                    .collect()
+            }
+            Frequency::Weekly => vec![base + ChronoDuration::weeks(step as i64)],
+            Frequency::Monthly => base.checked_add_months(Months::new(step)).into_iter().collect(),
+            Frequency::Yearly => base.checked_add_months(Months::new(step * 12)).into_iter().collect(),
+        };
+        for candidate in candidates {
+            let occurrence = start.with_naive(candidate);
+            let Some(timestamp) = occurrence.timestamp_ms() else {
+                continue;
+            };
+            let counted_out = rule.count.is_some_and(|count| occurrences.len() as u32 >= count);
+            if counted_out || until.is_some_and(|until| timestamp > until) || timestamp >= window_end {
+                return occurrences;
+            }
+            occurrences.push(occurrence);
+        }
+    }
+    occurrences
+}
+
+fn parse_events(text: &str) -> Vec<RawEvent> {
+    let mut events = Vec::new();
+    let mut current: Option<RawEvent> = None;
+    // Nested components (alarms) have their own DTSTART and SUMMARY
+    let mut nested = 0;
+
+    for line in unfold(text) {
+        let Some((name, params, value)) = split_line(&line) else {
+            continue;
+        };
+        match (name.as_str(), value.trim().to_ascii_uppercase().as_str()) {
+            ("BEGIN", "VEVENT") => current = Some(RawEvent::default()),
+            ("END", "VEVENT") => events.extend(current.take()),
+            ("BEGIN", _) if current.is_some() => nested += 1,
+            ("END", _) if current.is_some() => nested -= 1,
+            _ => {}
+        }
+        let Some(event) = current.as_mut().filter(|_| nested == 0) else {
+            continue;
+        };
+        let date_only = params.to_ascii_uppercase().contains("VALUE=DATE") && !params.contains("DATE-TIME");
+        let time = || if date_only { parse_time(value.get(..8)?) } else { parse_time(value) };
+        match name.as_str() {
+            "UID" => event.uid = value.to_string(),
+            "SUMMARY" => event.summary = unescape(value),
+            "LOCATION" => event.location = Some(unescape(value)).filter(|location| !location.is_empty()),
+            "DTSTART" => event.start = time(),
+            "DTEND" => event.end = time(),
+            "DURATION" => event.duration = parse_duration(value),
+            "RRULE" => event.rule = parse_rule(value),
+            "EXDATE" => event.exdates.extend(value.split(',').filter_map(parse_time)),
+            "RECURRENCE-ID" => event.recurrence_id = time(),
+            "STATUS" => event.cancelled = value.eq_ignore_ascii_case("CANCELLED"),
+            _ => {}
+        }
+    }
+    events
+}
+
+/// Occurrences of the feed's events that haven't ended at `now` and start
+/// before `window_end`, all timestamps in milliseconds
+pub fn parse_ical(text: &str, calendar: &str, now: u64, window_end: u64) -> Vec<CalendarEvent> {
+    let raw_events = parse_events(text);
+
+    // Occurrences moved or cancelled individually, not to be expanded from their series
+    let overridden: HashSet<(String, u64)> = raw_events
+        .iter()
+        .filter_map(|event| Some((event.uid.clone(), event.recurrence_id?.timestamp_ms()?)))
+        .collect();
+
+    let mut events = Vec::new();
+    for event in raw_events.iter().filter(|event| !event.cancelled) {
+        let Some(start) = event.start else {
+            continue;
+        };
+        let all_day = matches!(start, IcalTime::Date(_));
+        let length = match (event.end, event.duration) {
+            (Some(end), _) => end.naive() - start.naive(),
+            (None, Some(duration)) => duration,
+            (None, None) if all_day => ChronoDuration::days(1),
+            (None, None) => ChronoDuration::zero(),
+        };
+        let length_ms = length.num_milliseconds().max(0) as u64;
+
+        let excluded: HashSet<u64> = event.exdates.iter().filter_map(IcalTime::timestamp_ms).collect();
+        let starts = match (&event.rule, event.recurrence_id) {
+            (Some(rule), None) => expand(start, rule, window_end),
+            _ => vec![start],
+        };
+        for occurrence in starts {
+            let Some(start_ms) = occurrence.timestamp_ms() else {
+                continue;
+            };
+            let moved = event.recurrence_id.is_none() && overridden.contains(&(event.uid.clone(), start_ms));
+            if moved || excluded.contains(&start_ms) {
+                continue;
+            }
+            let end_ms = start_ms + length_ms;
+            let upcoming = end_ms > now || start_ms >= now;
+            if upcoming && start_ms < window_end {
+                events.push(CalendarEvent {
+                    title: event.summary.clone(),
+                    start: start_ms,
+                    end: end_ms,
+                    all_day,
+                    location: event.location.clone(),
+                    calendar: calendar.to_string(),
+                });
+            }
+        }
+    }
+    events
+}
+
+/// Events from every feed, soonest first, at most `max`
+pub fn merge_events(mut events: Vec<CalendarEvent>, max: usize) -> Vec<CalendarEvent> {
+    events.sort_by(|a, b| a.start.cmp(&b.start).then_with(|| a.title.cmp(&b.title)));
+    events.truncate(max);
+    events
+}
+
+/// Titles of the events going on at `now`
+pub fn current_titles(events: &[CalendarEvent], now: u64) -> Vec<String> {
+    events
+        .iter()
+        .filter(|event| event.start <= now && now < event.end)
+        .map(|event| event.title.clone())
+        .collect()
+}
+
+/// webcal:// is http(s) with a hint for calendar apps
+fn feed_url(url: &str) -> String {
+    match url.strip_prefix("webcal://") {
+        Some(rest) => format!("https://{}", rest),
+        None => url.to_string(),
+    }
+}
+
+/// Download a feed's iCal text
+pub async fn fetch_feed(client: &reqwest::Client, feed: &CalendarFeed) -> Result<String, String> {
+    client
+        .get(feed_url(&feed.url))
+        .timeout(Duration::from_secs(20))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to fetch calendar {}: {}", feed.name, e))?
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read calendar {}: {}", feed.name, e))
+}
+
+fn now_ms() -> u64 {
+    chrono::Utc::now().timestamp_millis() as u64
+}
+
+/// Now and the end of the days to look ahead, in milliseconds
+pub(crate) fn window(settings: &CalendarSettings) -> (u64, u64) {
+    let now = now_ms();
+    (now, now + settings.days_ahead as u64 * DAY_MS)
+}
+
+/// Fetch every feed and merge their upcoming events. Feeds that fail are
+/// listed in `errors` rather than failing the rest.
+pub async fn fetch_report(client: &reqwest::Client, settings: &CalendarSettings) -> CalendarReport {
+    let (now, window_end) = window(settings);
+    let mut events = Vec::new();
+    let mut errors = Vec::new();
+    for feed in &settings.feeds {
+        match fetch_feed(client, feed).await {
+            Ok(text) => events.extend(parse_ical(&text, &feed.name, now, window_end)),
+            Err(e) => errors.push(e),
+        }
+    }
+    CalendarReport {
+        events: merge_events(events, settings.max_events),
+        fetched_at: Some(now),
+        errors,
+    }
+}
+
+/// The last refresh, without the events that have ended since
+pub fn report() -> CalendarReport {
+    let mut report = REPORT.lock().map(|report| report.clone()).unwrap_or_default();
+    let now = now_ms();
+    report.events.retain(|event| event.end > now || event.start >= now);
+    report
+}
+
+/// Refresh now rather than at the next interval, e.g. once an account is connected
+pub fn request_refresh() {
+    REFRESH.notify_one();
+}
+
+async fn refresh(state: &AppState, settings: &CalendarSettings) {
+    let client = crate::http_client();
+    let mut report = fetch_report(client, settings).await;
+    let (now, window_end) = window(settings);
+    let mut results = Vec::new();
+    for calendar in &settings.caldav {
+        results.push(caldav::upcoming_events(client, calendar, now, window_end).await);
+    }
+    results.push(google_calendar::upcoming_events(client, settings).await);
+    for result in results {
+        match result {
+            Ok(events) => report.events.extend(events),
+            Err(e) => report.errors.push(e),
+        }
+    }
+    report.events = merge_events(std::mem::take(&mut report.events), settings.max_events);
+    for error in &report.errors {
+        warn!("Calendar: {}", error);
+    }
+    health::record::<()>(
+        "calendar",
+        &if report.errors.is_empty() { Ok(()) } else { Err(report.errors.join("; ")) },
+    );
+    info!("Calendar: {} upcoming events", report.events.len());
+    state.publish_from(ServerEvent::CalendarUpdated(report.events.clone()), "calendar");
+    if let Ok(mut current) = REPORT.lock() {
+        *current = report;
+    }
+}
+
+/// Activate or revert scenes whose calendar keywords match what's on now
+fn sync_scenes(state: &AppState) {
+    let titles = current_titles(&report().events, now_ms());
+    match scenes::sync_scheduled_scene(&state.settings_manager, &titles) {
+        Ok(Some(settings)) => {
+            match &settings.scenes.active {
+                Some(scene) => info!("Calendar: scene {} now active", scene),
+                None => info!("Calendar: scheduled scene reverted"),
+            }
+            state.publish_from(ServerEvent::SettingsUpdated(Box::new(settings)), "calendar");
+        }
+        Ok(None) => {}
+        Err(e) => warn!("Calendar: failed to sync scenes: {}", e),
+    }
+}
+
+/// Refresh the feeds on their interval, and straight away when they change,
+/// for the lifetime of the server
+pub fn spawn_refresher(state: AppState) {
+    tokio::spawn(async move {
+        let mut refreshed: Option<(Instant, CalendarSettings)> = None;
+        let mut interval = tokio::time::interval(TICK_INTERVAL);
+        loop {
+            let requested = tokio::select! {
+                _ = interval.tick() => false,
+                _ = REFRESH.notified() => true,
+            };
+            // Paused while the screen sleeps, catching up when it wakes
+            if display_sleep::is_asleep() && !requested {
+                continue;
+            }
+            let settings = match state.settings_manager.get() {
+                Ok(settings) => settings.calendar,
+                Err(e) => {
+                    warn!("Calendar: failed to read settings: {}", e);
+                    continue;
+                }
+            };
+            let due = requested
+                || match &refreshed {
+                    Some((at, used)) => {
+                        *used != settings || at.elapsed() >= Duration::from_secs(settings.refresh_interval * 60)
+                    }
+                    None => true,
+                };
+            let sources = !settings.feeds.is_empty() || !settings.caldav.is_empty() || google_calendar::connected();
+            if due && sources {
+                refresh(&state, &settings).await;
+                refreshed = Some((Instant::now(), settings));
+            } else if due {
+                // Every calendar removed: forget their events
+                if let Ok(mut current) = REPORT.lock() {
+                    *current = CalendarReport::default();
+                }
+                refreshed = Some((Instant::now(), settings));
+            }
+            sync_scenes(&state);
+        }
+    });
+}
@@ -0,0 +1,131 @@
+//! Connectivity: a HEAD request to `connectivity.check_url` every
+//! `connectivity.interval` seconds tells whether the frame is online. Two
+//! checks in a row have to fail before it counts as offline, so one dropped
+//! request doesn't flap the state; while offline it checks more often to
+//! notice the connection coming back.
+//!
+//! `connectivity-changed` goes out on every transition. The frame stops
+//! retrying fetches and keeps its cached photo while offline, and catches up
+//! as soon as the connection is back.
+
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{info, warn};
+
+pub use idleview_api::system::Connectivity;
+use idleview_api::ServerEvent;
+
+use crate::http_server::AppState;
+
+/// How long a check may take before it counts as failed
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Between checks after one failed or while offline
+const RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Failed checks in a row before the frame counts as offline
+pub const FAILURES_BEFORE_OFFLINE: u32 = 2;
+
+/// Online or offline, from the checks so far
+#[derive(Debug, Clone)]
+pub struct ConnectivityTracker {
+    status: Connectivity,
+    failures: u32,
+}
+
+impl Default for ConnectivityTracker {
+    /// Until a check says otherwise the frame counts as online, so nothing
+    /// waits for the first one
+    fn default() -> Self {
+        ConnectivityTracker {
+            status: Connectivity { online: true, last_online: None, last_change: None },
+            failures: 0,
+        }
+    }
+}
+
+impl ConnectivityTracker {
+    /// A check at `now_ms` (Unix timestamp) was answered or not; true when
+    /// it takes the frame offline or back online
+    pub fn record(&mut self, reachable: bool, now_ms: u64) -> bool {
+        if reachable {
+            self.failures = 0;
+            self.status.last_online = Some(now_ms);
+        } else {
+            self.failures = self.failures.saturating_add(1);
+        }
+        let online = reachable || (self.status.online && self.failures < FAILURES_BEFORE_OFFLINE);
+        if online == self.status.online {
+            return false;
+        }
+        self.status.online = online;
+        self.status.last_change = Some(now_ms);
+        true
+    }
+
+    /// Whether the next check should come sooner than the interval
+    pub fn retrying(&self) -> bool {
+        self.failures > 0
+    }
+
+    pub fn status(&self) -> Connectivity {
+        self.status
+    }
+}
+
+static TRACKER: Mutex<Option<ConnectivityTracker>> = Mutex::new(None);
+
+fn now_ms() -> u64 {
+    chrono::Utc::now().timestamp_millis().max(0) as u64
+}
+
+/// Whether the frame is online, as of the last check
+pub fn status() -> Connectivity {
+    TRACKER
+        .lock()
+        .ok()
+        .and_then(|tracker| tracker.as_ref().map(ConnectivityTracker::status))
+        .unwrap_or_else(|| ConnectivityTracker::default().status())
+}
+
+/// Whether `url` answers at all; an error status is still an answer
+async fn probe(url: &str) -> bool {
+    crate::http_client().head(url).timeout(PROBE_TIMEOUT).send().await.is_ok()
+}
+
+/// Check the connection for the lifetime of the server
+pub fn spawn_checker(state: AppState) {
+    tokio::spawn(async move {
+        loop {
+            let settings = match state.settings_manager.get() {
+                Ok(settings) => settings.connectivity,
+                Err(e) => {
+                    warn!("Connectivity: failed to read settings: {}", e);
+                    tokio::time::sleep(RETRY_INTERVAL).await;
+                    continue;
+                }
+            };
+
+            let reachable = probe(settings.check_url.trim()).await;
+            let recorded = TRACKER.lock().ok().map(|mut tracker| {
+                let tracker = tracker.get_or_insert_with(ConnectivityTracker::default);
+                (tracker.record(reachable, now_ms()), tracker.retrying(), tracker.status())
+            });
+            let Some((changed, retrying, status)) = recorded else {
+                tokio::time::sleep(RETRY_INTERVAL).await;
+                continue;
+            };
+            if changed {
+                if status.online {
+                    info!("🌐 Back online");
+                } else {
+                    warn!("🌐 Offline: {} doesn't answer", settings.check_url.trim());
+                }
+                state.publish_from(ServerEvent::ConnectivityChanged(status), "connectivity");
+            }
+
+            let next = if retrying { RETRY_INTERVAL } else { Duration::from_secs(settings.interval.max(1)) };
+            tokio::time::sleep(next).await;
+        }
+    });
+}
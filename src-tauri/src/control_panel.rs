@@ -0,0 +1,177 @@
+//! The web control panel, compiled into the executable so installs don't
+//! depend on where the bundler put its resources. Debug builds read the
+//! files from `idleview-control/` on each request instead, so edits show up
+//! without a rebuild.
+//!
+//! Files are served with an ETag and Last-Modified so phones revalidate
+//! instead of downloading the panel again. Files whose name or `?v=` query
+//! carries their content hash never change and may be cached for good.
+
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use rust_embed::RustEmbed;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+#[derive(RustEmbed)]
+#[folder = "idleview-control/"]
+struct Assets;
+
+/// For files that can never change under the same URL
+pub const CACHE_IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// For everything else: keep a copy but check the ETag before using it
+pub const CACHE_REVALIDATE: &str = "no-cache";
+
+/// An embedded file, ready to serve
+pub struct Asset {
+    pub content_type: String,
+    pub data: Vec<u8>,
+    pub etag: String,
+    pub last_modified: Option<u64>,  // Unix timestamp in seconds
+}
+
+/// The asset a request path maps to: directories serve their `index.html`
+pub fn asset_path(request_path: &str) -> String {
+    let path = request_path.trim_start_matches('/');
+    if path.is_empty() || path.ends_with('/') {
+        format!("{}index.html", path)
+    } else {
+        path.to_string()
+    }
+}
+
+/// Whether the bundler put a content hash in the file name, like `index-CCkbbDEr.js`
+pub fn is_fingerprinted(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let stem = name.split('.').next().unwrap_or(name);
+    stem.rsplit_once('-').is_some_and(|(_, hash)| {
+        hash.len() == 8 && hash.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// Short content hash of an embedded file, used for ETags and `?v=` versions
+pub fn version(path: &str) -> Option<String> {
+    let file = Assets::get(path)?;
+    Some(file.metadata.sha256_hash()[..8].iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Add `?v=<hash>` to `src` and `href` references to embedded files the
+/// bundler didn't fingerprint, so browsers fetch them again once they change
+pub fn version_references(html: &str) -> String {
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = ["src=\"/", "href=\"/"]
+        .iter()
+        .filter_map(|attribute| rest.find(attribute).map(|index| index + attribute.len()))
+        .min()
+    {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find('"').unwrap_or(rest.len());
+        let path = &rest[..end];
+        result.push_str(path);
+        if !path.contains('?') && !is_fingerprinted(path) {
+            if let Some(version) = version(path) {
+                result.push_str(&format!("?v={}", version));
+            }
+        }
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// An embedded file with its content type and validators
+pub fn asset(path: &str) -> Option<Asset> {
+    let file = Assets::get(path)?;
+    let content_type = file.metadata.mimetype().to_string();
+    let last_modified = file.metadata.last_modified();
+
+    if content_type == "text/html" {
+        // The page changes when anything it references does
+        let html = version_references(&String::from_utf8_lossy(&file.data));
+        let mut hasher = DefaultHasher::new();
+        html.hash(&mut hasher);
+        return Some(Asset {
+            content_type,
+            data: html.into_bytes(),
+            etag: format!("\"{:016x}\"", hasher.finish()),
+            last_modified,
+        });
+    }
+
+    Some(Asset {
+        content_type,
+        etag: format!("\"{}\"", version(path)?),
+        data: file.data.into_owned(),
+        last_modified,
+    })
+}
+
+/// How long a response may be cached, given the `v` query it was requested with
+pub fn cache_control(path: &str, requested_version: Option<&str>) -> &'static str {
+    let versioned = requested_version.is_some_and(|requested| version(path).as_deref() == Some(requested));
+    if is_fingerprinted(path) || versioned {
+        CACHE_IMMUTABLE
+    } else {
+        CACHE_REVALIDATE
+    }
+}
+
+fn http_date(timestamp: u64) -> Option<String> {
+    DateTime::<Utc>::from_timestamp(timestamp as i64, 0)
+        .map(|date| date.format("%a, %d %b %Y %H:%M:%S GMT").to_string())
+}
+
+/// Whether the browser's copy is still current. If-None-Match wins over
+/// If-Modified-Since, as in RFC 9110.
+pub fn not_modified(headers: &HeaderMap, etag: &str, last_modified: Option<u64>) -> bool {
+    if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH).and_then(|value| value.to_str().ok()) {
+        return if_none_match
+            .split(',')
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == "*" || tag == etag);
+    }
+    let since = headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| DateTime::parse_from_rfc2822(&value.replace("GMT", "+0000")).ok());
+    match (since, last_modified) {
+        (Some(since), Some(modified)) => modified as i64 <= since.timestamp(),
+        _ => false,
+    }
+}
+
+/// Serve a control panel file for any path no API route matched
+pub async fn serve(uri: Uri, headers: HeaderMap) -> Response {
+    let path = asset_path(uri.path());
+    let Some(asset) = asset(&path) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let requested_version = uri
+        .query()
+        .and_then(|query| query.split('&').find_map(|pair| pair.strip_prefix("v=")));
+    let mut response = if not_modified(&headers, &asset.etag, asset.last_modified) {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        ([(header::CONTENT_TYPE, asset.content_type)], Body::from(asset.data)).into_response()
+    };
+
+    let response_headers = response.headers_mut();
+    if let Ok(value) = asset.etag.parse() {
+        response_headers.insert(header::ETAG, value);
+    }
+    if let Some(value) = asset.last_modified.and_then(http_date).and_then(|date| date.parse().ok()) {
+        response_headers.insert(header::LAST_MODIFIED, value);
+    }
+    response_headers.insert(
+        header::CACHE_CONTROL,
+        header::HeaderValue::from_static(cache_control(&path, requested_version)),
+    );
+    response
+}
@@ -0,0 +1,69 @@
+//! Days left until the user's own events ("Vacation", "Exam"), kept in
+//! `countdowns.events`. One-off events drop off the day after; yearly ones
+//! count to their next date. Events with photo keywords can steer the photo
+//! query while they are imminent, the way the built-in holidays do.
+
+use chrono::{Datelike, NaiveDate};
+use serde::Serialize;
+
+use crate::settings_manager::{CountdownEvent, CountdownsSettings};
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct Countdown {
+    pub name: String,
+    pub date: String,  // YYYY-MM-DD of the occurrence counted to
+    pub days_remaining: i64,  // 0 on the day itself
+    pub imminent: bool,  // Within countdowns.imminent_days
+}
+
+/// `date` in `year`; February 29th falls on the 28th in other years
+fn in_year(date: NaiveDate, year: i32) -> NaiveDate {
+    date.with_year(year)
+        .or_else(|| NaiveDate::from_ymd_opt(year, date.month(), 28))
+        .unwrap_or(date)
+}
+
+/// The day `event` next happens on, from `today`; None once a one-off event
+/// has passed or when its date doesn't parse
+fn next_date(event: &CountdownEvent, today: NaiveDate) -> Option<NaiveDate> {
+    let date = NaiveDate::parse_from_str(event.date.trim(), "%Y-%m-%d").ok()?;
+    if !event.yearly {
+        return (date >= today).then_some(date);
+    }
+    let this_year = in_year(date, today.year());
+    Some(if this_year >= today { this_year } else { in_year(date, today.year() + 1) })
+}
+
+/// The events still to come, soonest first
+pub fn countdowns_impl(settings: &CountdownsSettings, today: NaiveDate) -> Vec<Countdown> {
+    let mut countdowns: Vec<Countdown> = settings
+        .events
+        .iter()
+        .filter_map(|event| {
+            let date = next_date(event, today)?;
+            let days_remaining = (date - today).num_days();
+            Some(Countdown {
+                name: event.name.clone(),
+                date: date.format("%Y-%m-%d").to_string(),
+                days_remaining,
+                imminent: days_remaining <= i64::from(settings.imminent_days),
+            })
+        })
+        .collect();
+    countdowns.sort_by_key(|countdown| countdown.days_remaining);
+    countdowns
+}
+
+/// Photo keywords of the soonest imminent event that has some
+pub fn photo_keywords(settings: &CountdownsSettings, today: NaiveDate) -> Option<String> {
+    settings
+        .events
+        .iter()
+        .filter_map(|event| {
+            let keywords = event.photo_keywords.as_deref().map(str::trim).filter(|k| !k.is_empty())?;
+            let days = (next_date(event, today)? - today).num_days();
+            (days <= i64::from(settings.imminent_days)).then_some((days, keywords))
+        })
+        .min_by_key(|(days, _)| *days)
+        .map(|(_, keywords)| keywords.to_string())
+}
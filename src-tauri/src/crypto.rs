@@ -0,0 +1,128 @@
+//! Cryptocurrency prices from CoinGecko's free API, for a corner of a desk
+//! display.
+//!
+//! All coins come from one request, made at most once per
+//! `refresh_interval` and never more than once a minute, which keeps well
+//! inside the free tier's limits. When a request fails the last prices are
+//! kept, with the error next to them.
+
+use serde_json::Value;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+pub use idleview_api::market::{CryptoPrice, CryptoReport};
+
+use crate::health;
+use crate::settings_manager::CryptoSettings;
+
+pub const COINGECKO_URL: &str = "https://api.coingecko.com/api/v3/simple/price";
+
+/// Shortest time between two requests, whatever the settings say
+const MIN_INTERVAL: Duration = Duration::from_secs(60);
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Cached {
+    at: Instant,
+    settings: CryptoSettings,
+    report: CryptoReport,
+}
+
+/// Held while fetching, so callers arriving meanwhile wait for that request
+static CACHE: Mutex<Option<Cached>> = Mutex::const_new(None);
+
+/// Prices of `coins` in a simple/price answer, in order. Coins CoinGecko
+/// doesn't know are missing from the answer and left out.
+pub fn parse_prices(json: &str, coins: &[String], currency: &str) -> Result<Vec<CryptoPrice>, String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| format!("Failed to parse prices: {}", e))?;
+    let currency = currency.to_lowercase();
+    let change_key = format!("{}_24h_change", currency);
+    Ok(coins
+        .iter()
+        .filter_map(|coin| {
+            let prices = value.get(coin.as_str())?;
+            Some(CryptoPrice {
+                coin: coin.clone(),
+                price: prices.get(&currency).and_then(Value::as_f64)?,
+                change_24h_percent: prices.get(&change_key).and_then(Value::as_f64),
+            })
+        })
+        .collect())
+}
+
+/// Prices of the configured coins from the simple/price endpoint at `url`
+pub async fn fetch_prices_from(
+    client: &reqwest::Client,
+    url: &str,
+    settings: &CryptoSettings,
+) -> Result<Vec<CryptoPrice>, String> {
+    let body = client
+        .get(url)
+        .query(&[
+            ("ids", settings.coins.join(",").as_str()),
+            ("vs_currencies", settings.currency.as_str()),
+            ("include_24hr_change", "true"),
+        ])
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to fetch prices: {}", e))?
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read prices: {}", e))?;
+    let prices = parse_prices(&body, &settings.coins, &settings.currency)?;
+    if prices.is_empty() {
+        return Err(format!("No prices for {} in {}", settings.coins.join(", "), settings.currency));
+    }
+    Ok(prices)
+}
+
+/// The prices, from the cache unless the interval has passed or the
+/// settings changed
+pub async fn report(settings: &CryptoSettings) -> CryptoReport {
+    if !settings.enabled || settings.coins.is_empty() {
+        return CryptoReport::default();
+    }
+    let mut cache = CACHE.lock().await;
+    let interval = Duration::from_secs(settings.refresh_interval * 60).max(MIN_INTERVAL);
+    let fresh = |cached: &Cached| {
+        let age = cached.at.elapsed();
+        // Changed settings wait out the shortest interval too
+        age < MIN_INTERVAL || (age < interval && cached.settings == *settings)
+    };
+    if let Some(cached) = cache.as_ref().filter(|cached| fresh(cached)) {
+        return cached.report.clone();
+    }
+
+    let result = fetch_prices_from(crate::http_client(), COINGECKO_URL, settings).await;
+    health::record("crypto", &result);
+    let report = match result {
+        Ok(prices) => CryptoReport {
+            prices,
+            currency: settings.currency.clone(),
+            fetched_at: Some(chrono::Utc::now().timestamp_millis() as u64),
+            error: None,
+        },
+        Err(e) => {
+            warn!("Crypto: {}", e);
+            // Last prices only when they are still what was asked for
+            let last = cache
+                .take()
+                .map(|cached| cached.report)
+                .filter(|report| report.currency == settings.currency);
+            CryptoReport {
+                error: Some(e),
+                currency: settings.currency.clone(),
+                ..last.unwrap_or_default()
+            }
+        }
+    };
+    *cache = Some(Cached {
+        at: Instant::now(),
+        settings: settings.clone(),
+        report: report.clone(),
+    });
+    report
+}
@@ -0,0 +1,78 @@
+//! Zeroconf discovery of the control panel.
+//!
+//! The frame advertises `_idleview._tcp.local.` and answers for
+//! `idleview.local`, so the companion web UI and phones can find it without
+//! knowing its IP. The advertisement follows the server settings.
+
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+use tracing::{info, warn};
+
+use crate::http_server::{self, AppState};
+use crate::settings_manager::{ServerBind, ServerSettings};
+
+pub const SERVICE_TYPE: &str = "_idleview._tcp.local.";
+const INSTANCE_NAME: &str = "Idleview";
+const HOST_NAME: &str = "idleview.local.";
+
+/// The service to advertise for the server settings, or None when the server
+/// can't be reached from the network
+pub fn service_info(server: &ServerSettings) -> Result<Option<ServiceInfo>, String> {
+    if !server.enabled || server.bind == ServerBind::Localhost {
+        return Ok(None);
+    }
+
+    let scheme = if server.tls.enabled { "https" } else { "http" };
+    let properties = [
+        ("scheme", scheme),
+        ("path", "/"),
+        ("api", "/api"),
+        ("version", env!("CARGO_PKG_VERSION")),
+    ];
+    ServiceInfo::new(SERVICE_TYPE, INSTANCE_NAME, HOST_NAME, (), server.port, &properties[..])
+        .map(|info| Some(info.enable_addr_auto()))
+        .map_err(|e| format!("Failed to build mDNS service: {}", e))
+}
+
+/// Advertise the control panel over mDNS, re-announcing it whenever the
+/// server settings change
+pub fn spawn_advertiser(state: AppState) {
+    let daemon = match ServiceDaemon::new() {
+        Ok(daemon) => daemon,
+        Err(e) => {
+            warn!("mDNS: failed to start: {}", e);
+            return;
+        }
+    };
+    let mut changes = state.settings_manager.subscribe();
+
+    tokio::spawn(async move {
+        loop {
+            let server = changes.borrow_and_update().server.clone();
+            let registered = match service_info(&server) {
+                Ok(Some(service)) => {
+                    let fullname = service.get_fullname().to_string();
+                    match daemon.register(service) {
+                        Ok(()) => {
+                            info!("📣 Advertising {} on port {} (idleview.local)", SERVICE_TYPE, server.port);
+                            Some(fullname)
+                        }
+                        Err(e) => {
+                            warn!("mDNS: failed to advertise: {}", e);
+                            None
+                        }
+                    }
+                }
+                Ok(None) => None,
+                Err(e) => {
+                    warn!("mDNS: {}", e);
+                    None
+                }
+            };
+
+            http_server::server_settings_changed(&mut changes, &server).await;
+            if let Some(fullname) = registered {
+                let _ = daemon.unregister(&fullname);
+            }
+        }
+    });
+}
@@ -0,0 +1,111 @@
+//! Low disk space. SD cards fill up silently, and then settings stop saving
+//! and photos stop loading all at once. Every few minutes the free space on
+//! the disks holding the settings and the webview's photo cache is compared
+//! with `webhooks.low_disk_limit`; when it drops below, `disk-space-low` goes
+//! out and the frame trims what it can fetch or do without: the webview's
+//! cached photos and the rotated access logs.
+
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tracing::{info, warn};
+
+pub use idleview_api::system::DiskStatus;
+use idleview_api::ServerEvent;
+
+use crate::access_log;
+use crate::http_server::AppState;
+use crate::system;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// How far free space has to climb back above the limit before it can be reported low again
+pub const LOW_DISK_HYSTERESIS_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Where the webview keeps its cache, set when the monitor starts
+static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Whether free space newly drops below the limit. `low` carries the state
+/// between checks, so one shortage is reported and trimmed once.
+pub fn space_crossed(available: u64, limit: u64, low: &mut bool) -> bool {
+    if *low {
+        *low = available < limit.saturating_add(LOW_DISK_HYSTERESIS_BYTES);
+        return false;
+    }
+    *low = available < limit;
+    *low
+}
+
+/// WebView2 keeps its whole profile under the local data dir, WebKit its
+/// cache under the cache dir
+fn webview_cache_dir(app: &AppHandle) -> Option<PathBuf> {
+    #[cfg(target_os = "windows")]
+    {
+        app.path().app_local_data_dir().ok()
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        app.path().app_cache_dir().ok()
+    }
+}
+
+/// Space on the disk holding the webview's photo cache, when it isn't the
+/// one holding the settings
+pub fn cache_disk() -> Option<DiskStatus> {
+    let disk = system::disk_containing(CACHE_DIR.get()?)?;
+    let settings_disk = system::disk_status();
+    (settings_disk.map(|settings_disk| settings_disk.mount_point) != Some(disk.mount_point.clone())).then_some(disk)
+}
+
+/// Drop the webview's cached photos, which it fetches again, and the old access logs
+fn trim_caches(app: &AppHandle) {
+    for (label, window) in app.webview_windows() {
+        if let Err(e) = window.clear_all_browsing_data() {
+            warn!("Low disk space: failed to clear the cache of frame {}: {}", label, e);
+        }
+    }
+    match access_log::trim() {
+        Ok(0) => {}
+        Ok(removed) => info!("💾 Removed {} rotated access logs", removed),
+        Err(e) => warn!("Low disk space: {}", e),
+    }
+}
+
+/// Watch free space and trim caches when it runs low, for the lifetime of the server
+pub fn spawn_monitor(state: AppState) {
+    if let Some(dir) = webview_cache_dir(&state.app_handle) {
+        let _ = CACHE_DIR.set(dir);
+    }
+    tokio::spawn(async move {
+        let mut low = false;
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let limit = match state.settings_manager.get() {
+                Ok(settings) => settings.webhooks.low_disk_limit.saturating_mul(1024 * 1024),
+                Err(e) => {
+                    warn!("Failed to read the low disk space limit: {}", e);
+                    continue;
+                }
+            };
+            // The fuller of the two, when the cache is on a disk of its own
+            let disks = tokio::task::spawn_blocking(|| [system::disk_status(), cache_disk()]).await;
+            let Some(disk) = disks.into_iter().flatten().flatten().min_by_key(|disk| disk.available) else {
+                continue;
+            };
+
+            if space_crossed(disk.available, limit, &mut low) {
+                warn!(
+                    "Only {} MB free on {}, below the {} MB limit; trimming caches",
+                    disk.available / (1024 * 1024),
+                    disk.mount_point,
+                    limit / (1024 * 1024)
+                );
+                trim_caches(&state.app_handle);
+                state.publish_from(ServerEvent::DiskSpaceLow(disk), "system");
+            }
+        }
+    });
+}
@@ -0,0 +1,167 @@
+//! Turning the attached screen on and off, for bedtime from the control
+//! panel or an automation.
+//!
+//! Linux goes through wlr-randr on Wayland, xset DPMS on X11 and vcgencmd on
+//! a Raspberry Pi console; macOS through pmset and Windows through the
+//! monitor power system command.
+
+use std::process::Command;
+use std::sync::Mutex;
+
+pub use idleview_api::display::{DisplayPower, PowerState};
+
+/// Last state set through the API, for platforms that can't be asked
+static LAST_SET: Mutex<Option<PowerState>> = Mutex::new(None);
+
+/// Run a command, returning its stdout when it succeeds
+fn run(program: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| format!("{}: {}", program, e))?;
+    if !output.status.success() {
+        return Err(format!("{}: {}", program, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Monitor state from `xset q`
+pub fn parse_xset_query(output: &str) -> Option<PowerState> {
+    output.lines().find_map(|line| match line.trim() {
+        "Monitor is On" => Some(PowerState::On),
+        "Monitor is Off" | "Monitor is in Standby" | "Monitor is in Suspend" => Some(PowerState::Off),
+        _ => None,
+    })
+}
+
+/// Outputs from `wlr-randr` and whether each one is enabled
+pub fn parse_wlr_randr(output: &str) -> Vec<(String, bool)> {
+    let mut outputs: Vec<(String, bool)> = Vec::new();
+    for line in output.lines() {
+        if !line.starts_with(char::is_whitespace) {
+            if let Some(name) = line.split_whitespace().next() {
+                outputs.push((name.to_string(), false));
+            }
+        } else if let Some(enabled) = line.trim().strip_prefix("Enabled:") {
+            if let Some(last) = outputs.last_mut() {
+                last.1 = enabled.trim() == "yes";
+            }
+        }
+    }
+    outputs
+}
+
+/// Screen state from `vcgencmd display_power`
+pub fn parse_vcgencmd(output: &str) -> Option<PowerState> {
+    match output.trim().strip_prefix("display_power=")? {
+        "1" => Some(PowerState::On),
+        "0" => Some(PowerState::Off),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn on_wayland() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+#[cfg(target_os = "linux")]
+fn on_x11() -> bool {
+    std::env::var_os("DISPLAY").is_some()
+}
+
+#[cfg(target_os = "linux")]
+fn query() -> Result<PowerState, String> {
+    if on_wayland() {
+        let outputs = parse_wlr_randr(&run("wlr-randr", &[])?);
+        return match outputs.iter().any(|(_, enabled)| *enabled) {
+            true => Ok(PowerState::On),
+            false if outputs.is_empty() => Err("wlr-randr reported no outputs".to_string()),
+            false => Ok(PowerState::Off),
+        };
+    }
+    if on_x11() {
+        return parse_xset_query(&run("xset", &["q"])?)
+            .ok_or_else(|| "xset reported no monitor state; is DPMS enabled?".to_string());
+    }
+    parse_vcgencmd(&run("vcgencmd", &["display_power"])?)
+        .ok_or_else(|| "vcgencmd reported no display state".to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn apply(power: PowerState) -> Result<(), String> {
+    let on = power == PowerState::On;
+    if on_wayland() {
+        let outputs = parse_wlr_randr(&run("wlr-randr", &[])?);
+        if outputs.is_empty() {
+            return Err("wlr-randr reported no outputs".to_string());
+        }
+        for (name, _) in outputs {
+            run("wlr-randr", &["--output", &name, if on { "--on" } else { "--off" }])?;
+        }
+        return Ok(());
+    }
+    if on_x11() {
+        return run("xset", &["dpms", "force", if on { "on" } else { "off" }]).map(|_| ());
+    }
+    run("vcgencmd", &["display_power", if on { "1" } else { "0" }]).map(|_| ())
+}
+
+#[cfg(target_os = "macos")]
+fn query() -> Result<PowerState, String> {
+    Err("macOS doesn't report display power".to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn apply(power: PowerState) -> Result<(), String> {
+    match power {
+        PowerState::Off => run("pmset", &["displaysleepnow"]),
+        // Declaring user activity wakes the display
+        PowerState::On => run("caffeinate", &["-u", "-t", "1"]),
+    }
+    .map(|_| ())
+}
+
+#[cfg(target_os = "windows")]
+fn query() -> Result<PowerState, String> {
+    Err("Windows doesn't report display power".to_string())
+}
+
+#[cfg(target_os = "windows")]
+fn apply(power: PowerState) -> Result<(), String> {
+    // SC_MONITORPOWER: 2 turns monitors off, -1 turns them back on
+    let value = if power == PowerState::On { -1 } else { 2 };
+    let script = format!(
+        "Add-Type -Namespace Idleview -Name Monitor -MemberDefinition \
+         '[DllImport(\"user32.dll\")] public static extern int SendMessage(int hWnd, int hMsg, int wParam, int lParam);'; \
+         [Idleview.Monitor]::SendMessage(0xFFFF, 0x0112, 0xF170, {})",
+        value
+    );
+    run("powershell", &["-NoProfile", "-NonInteractive", "-Command", &script]).map(|_| ())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn query() -> Result<PowerState, String> {
+    Err("Display power is not supported on this platform".to_string())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn apply(_power: PowerState) -> Result<(), String> {
+    Err("Display power is not supported on this platform".to_string())
+}
+
+/// Whether the screen is on, falling back to the last state set when the
+/// platform can't tell (and assuming on before anything was set)
+pub fn power_state() -> PowerState {
+    query().unwrap_or_else(|_| LAST_SET.lock().ok().and_then(|last| *last).unwrap_or(PowerState::On))
+}
+
+/// Turn the screen on or off
+pub fn set_power(power: PowerState) -> Result<(), String> {
+    let name = if power == PowerState::On { "on" } else { "off" };
+    apply(power).map_err(|e| format!("Failed to turn display {}: {}", name, e))?;
+    if let Ok(mut last) = LAST_SET.lock() {
+        *last = Some(power);
+    }
+    Ok(())
+}
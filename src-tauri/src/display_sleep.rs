@@ -0,0 +1,99 @@
+//! Sleep hours: between `display.sleep.start` and `end` the screen is turned
+//! off and the background refreshers (calendar, news, sports, music) pause,
+//! since nobody sees what they'd fetch. At the end of the window the screen
+//! comes back on and they catch up.
+//!
+//! Turning the screen on through the API during the window wakes it until
+//! the window ends; it isn't put back to sleep the next time round.
+
+use chrono::{Local, NaiveTime};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tracing::{info, warn};
+
+pub use idleview_api::display::{DisplayPower, DisplaySleep, PowerState};
+use idleview_api::ServerEvent;
+
+use crate::display_power;
+use crate::http_server::AppState;
+use crate::in_time_window;
+use crate::settings_manager::DisplaySleepSettings;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often paused pollers look again whether the screen woke up
+pub const PAUSED_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+static ASLEEP: AtomicBool = AtomicBool::new(false);
+
+/// Woken through the API during the current window; cleared when it ends
+static WOKEN: AtomicBool = AtomicBool::new(false);
+
+/// Whether the schedule has the screen off; pollers skip their work meanwhile
+pub fn is_asleep() -> bool {
+    ASLEEP.load(Ordering::Relaxed)
+}
+
+/// Whether `time` falls in the sleep window
+pub fn in_sleep_window(settings: &DisplaySleepSettings, time: NaiveTime) -> bool {
+    settings.enabled && in_time_window(&settings.start, &settings.end, time)
+}
+
+pub fn status(settings: &DisplaySleepSettings) -> DisplaySleep {
+    let asleep = is_asleep();
+    DisplaySleep { asleep, wakes_at: asleep.then(|| settings.end.clone()) }
+}
+
+/// Keep the screen awake for the rest of the window after it was turned on
+/// by hand
+pub fn woken_by_hand(state: &AppState) {
+    if ASLEEP.swap(false, Ordering::Relaxed) {
+        WOKEN.store(true, Ordering::Relaxed);
+        info!("🌅 Display woken before the end of its sleep hours");
+        state.publish_from(ServerEvent::DisplaySleepChanged(DisplaySleep::default()), "display-sleep");
+    }
+}
+
+async fn set_power(state: &AppState, power: PowerState) {
+    match tokio::task::spawn_blocking(move || display_power::set_power(power)).await {
+        Ok(Ok(())) => state.publish_from(ServerEvent::DisplayPowerChanged(DisplayPower { power }), "display-sleep"),
+        Ok(Err(e)) => warn!("Display sleep: {}", e),
+        Err(e) => warn!("Display sleep: failed to set display power: {}", e),
+    }
+}
+
+/// Put the screen to sleep and wake it on schedule for the lifetime of the server
+pub fn spawn_scheduler(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let settings = match state.settings_manager.get() {
+                Ok(settings) => settings.display.sleep,
+                Err(e) => {
+                    warn!("Display sleep: failed to read settings: {}", e);
+                    continue;
+                }
+            };
+            let in_window = in_sleep_window(&settings, Local::now().time());
+            if !in_window {
+                WOKEN.store(false, Ordering::Relaxed);
+            }
+            let asleep = in_window && !WOKEN.load(Ordering::Relaxed);
+            if asleep == is_asleep() {
+                continue;
+            }
+
+            if asleep {
+                info!("💤 Display sleeping until {}", settings.end);
+                ASLEEP.store(true, Ordering::Relaxed);
+                set_power(&state, PowerState::Off).await;
+            } else {
+                info!("🌅 Display waking up");
+                set_power(&state, PowerState::On).await;
+                ASLEEP.store(false, Ordering::Relaxed);
+            }
+            state.publish_from(ServerEvent::DisplaySleepChanged(status(&settings)), "display-sleep");
+        }
+    });
+}
@@ -0,0 +1,115 @@
+use serde::Serialize;
+
+use crate::settings_manager::DisplaySettings;
+
+/// Widgets the frame can show, and the data each one needs
+pub const WIDGETS: &[(&str, DataSource)] = &[
+    ("weather", DataSource::Weather),
+    ("humidity_wind", DataSource::Weather),
+    ("precipitation_cloudiness", DataSource::Weather),
+    ("sunrise_sunset", DataSource::Weather),
+    ("cpu_temp", DataSource::CpuTemp),
+    ("cpu_usage", DataSource::Always),
+    ("memory", DataSource::Always),
+    ("wifi", DataSource::Wifi),
+    ("battery", DataSource::Battery),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataSource {
+    Weather,
+    CpuTemp,
+    Wifi,
+    Battery,
+    Always,  // Read from the machine, there on every platform
+}
+
+/// Which data the frontend currently has
+#[derive(Debug, Clone, Copy)]
+pub struct DataAvailability {
+    pub weather: bool,
+    pub cpu_temp: bool,
+    pub wifi: bool,
+    pub battery: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct WidgetState {
+    pub id: String,
+    pub visible: bool,
+    pub reason: Option<String>,  // Why it's hidden: "disabled", "unavailable" or "degraded"
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DisplayState {
+    pub mode: String,  // "normal" or "degraded"
+    pub widgets: Vec<WidgetState>,
+}
+
+fn enabled(display: &DisplaySettings, id: &str) -> bool {
+    match id {
+        "humidity_wind" => display.show_humidity_wind,
+        "precipitation_cloudiness" => display.show_precipitation_cloudiness,
+        "sunrise_sunset" => display.show_sunrise_sunset,
+        "cpu_temp" => display.show_cpu_temp,
+        "cpu_usage" => display.show_cpu_usage,
+        "memory" => display.show_memory,
+        "wifi" => display.show_wifi,
+        "battery" => display.show_battery,
+        _ => true,
+    }
+}
+
+/// Decide which widgets to show. Widgets without data are hidden rather than
+/// showing placeholders; in degraded mode the first `degraded_drop_count`
+/// still-visible widgets of `degradation_order` are dropped as well. Widgets
+/// not listed in the order are never dropped for degradation.
+pub fn resolve_display_state_impl(
+    display: &DisplaySettings,
+    available: DataAvailability,
+    degraded: bool,
+) -> DisplayState {
+    let mut widgets: Vec<WidgetState> = WIDGETS
+        .iter()
+        .map(|(id, source)| {
+            let has_data = match source {
+                DataSource::Weather => available.weather,
+                DataSource::CpuTemp => available.cpu_temp,
+                DataSource::Wifi => available.wifi,
+                DataSource::Battery => available.battery,
+                DataSource::Always => true,
+            };
+            let reason = if !enabled(display, id) {
+                Some("disabled")
+            } else if !has_data {
+                Some("unavailable")
+            } else {
+                None
+            };
+            WidgetState {
+                id: id.to_string(),
+                visible: reason.is_none(),
+                reason: reason.map(str::to_string),
+            }
+        })
+        .collect();
+
+    if degraded {
+        let mut remaining = display.degraded_drop_count;
+        for id in &display.degradation_order {
+            if remaining == 0 {
+                break;
+            }
+            if let Some(widget) = widgets.iter_mut().find(|w| &w.id == id && w.visible) {
+                widget.visible = false;
+                widget.reason = Some("degraded".to_string());
+                remaining -= 1;
+            }
+        }
+    }
+
+    DisplayState {
+        mode: if degraded { "degraded" } else { "normal" }.to_string(),
+        widgets,
+    }
+}
@@ -0,0 +1,186 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::Notify;
+
+use idleview_api::ServerEvent;
+
+/// Events queued per client before old ones start being dropped
+pub const CLIENT_QUEUE_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventPriority {
+    /// Dropped first when a client falls behind (periodic refreshes, ticks)
+    Low,
+    /// State changes a client must not miss unless its queue is full of them
+    Normal,
+}
+
+impl EventPriority {
+    pub fn of(event: &ServerEvent) -> Self {
+        match event {
+            ServerEvent::WeatherUpdated(_) | ServerEvent::TimerTick(_) | ServerEvent::Unknown => EventPriority::Low,
+            _ => EventPriority::Normal,
+        }
+    }
+}
+
+/// Per-client delivery statistics, exposed via `GET /api/server/clients`
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ClientStats {
+    pub id: u64,
+    pub transport: String,  // "sse", "websocket", "webhook" or "mqtt"
+    pub connected_at: u64,  // Unix timestamp in milliseconds
+    pub queued: usize,      // Events waiting to be sent, i.e. the current lag
+    pub max_queued: usize,
+    pub delivered: u64,
+    pub dropped: u64,
+}
+
+struct ClientQueue {
+    events: VecDeque<(EventPriority, Arc<str>)>,
+    stats: ClientStats,
+}
+
+struct Client {
+    queue: Mutex<ClientQueue>,
+    notify: Notify,
+}
+
+/// Fan-out of serialized events to many subscribers with bounded per-client queues.
+/// Publishing never waits on clients, so a slow dashboard can't delay the frame.
+pub struct EventHub {
+    clients: Mutex<HashMap<u64, Arc<Client>>>,
+    next_id: AtomicU64,
+    capacity: usize,
+}
+
+impl EventHub {
+    pub fn new(capacity: usize) -> Arc<Self> {
+        Arc::new(EventHub {
+            clients: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            capacity: capacity.max(1),
+        })
+    }
+
+    /// Queue an event for every connected client
+    pub fn publish(&self, data: String, priority: EventPriority) {
+        let data: Arc<str> = Arc::from(data);
+        let clients: Vec<Arc<Client>> = match self.clients.lock() {
+            Ok(clients) => clients.values().cloned().collect(),
+            Err(_) => return,
+        };
+
+        for client in clients {
+            if let Ok(mut queue) = client.queue.lock() {
+                if queue.events.len() >= self.capacity {
+                    // Drop the oldest low-priority event, or the oldest event if there is none
+                    let index = queue
+                        .events
+                        .iter()
+                        .position(|(p, _)| *p == EventPriority::Low)
+                        .unwrap_or(0);
+                    queue.events.remove(index);
+                    queue.stats.dropped += 1;
+                }
+                queue.events.push_back((priority, data.clone()));
+                queue.stats.queued = queue.events.len();
+                queue.stats.max_queued = queue.stats.max_queued.max(queue.stats.queued);
+            }
+            client.notify.notify_one();
+        }
+    }
+
+    /// Register a new client; it is removed again when the subscription is dropped
+    pub fn subscribe(self: &Arc<Self>, transport: &str) -> Subscription {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let connected_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+
+        let client = Arc::new(Client {
+            queue: Mutex::new(ClientQueue {
+                events: VecDeque::new(),
+                stats: ClientStats {
+                    id,
+                    transport: transport.to_string(),
+                    connected_at,
+                    queued: 0,
+                    max_queued: 0,
+                    delivered: 0,
+                    dropped: 0,
+                },
+            }),
+            notify: Notify::new(),
+        });
+
+        if let Ok(mut clients) = self.clients.lock() {
+            clients.insert(id, client.clone());
+        }
+
+        Subscription {
+            id,
+            client,
+            hub: self.clone(),
+        }
+    }
+
+    pub fn client_stats(&self) -> Vec<ClientStats> {
+        let clients = match self.clients.lock() {
+            Ok(clients) => clients.values().cloned().collect::<Vec<_>>(),
+            Err(_) => return Vec::new(),
+        };
+
+        let mut stats: Vec<ClientStats> = clients
+            .iter()
+            .filter_map(|client| client.queue.lock().ok().map(|queue| queue.stats.clone()))
+            .collect();
+        stats.sort_by_key(|s| s.id);
+        stats
+    }
+}
+
+/// A connected client's end of the hub
+pub struct Subscription {
+    id: u64,
+    client: Arc<Client>,
+    hub: Arc<EventHub>,
+}
+
+impl Subscription {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Take the next queued event without waiting
+    pub fn try_recv(&self) -> Option<Arc<str>> {
+        let mut queue = self.client.queue.lock().ok()?;
+        let (_, data) = queue.events.pop_front()?;
+        queue.stats.queued = queue.events.len();
+        queue.stats.delivered += 1;
+        Some(data)
+    }
+
+    /// Wait for the next event
+    pub async fn recv(&self) -> Arc<str> {
+        loop {
+            if let Some(data) = self.try_recv() {
+                return data;
+            }
+            self.client.notify.notified().await;
+        }
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Ok(mut clients) = self.hub.clients.lock() {
+            clients.remove(&self.id);
+        }
+    }
+}
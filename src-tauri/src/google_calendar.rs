@@ -0,0 +1,492 @@
+//! Google Calendar, connected with the OAuth device flow.
+//!
+//! The frame asks Google for a short code and shows it; the owner enters it
+//! at google.com/device on their phone. The tokens Google hands back are kept
+//! in the config directory and refreshed as they expire. Events are fetched
+//! with the calendar feeds and merged into the same report.
+
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+pub use idleview_api::calendar::{GoogleCalendarStatus, GoogleDeviceCode};
+
+use crate::auth;
+use crate::calendar::{self, CalendarEvent};
+use crate::http_server::AppState;
+use crate::messages::{MessagePriority, MessageRequest};
+use crate::settings_manager::{self, CalendarSettings, GoogleCalendarSettings};
+
+pub const DEVICE_CODE_URL: &str = "https://oauth2.googleapis.com/device/code";
+pub const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+pub const API_URL: &str = "https://www.googleapis.com/calendar/v3";
+
+const SCOPE: &str = "https://www.googleapis.com/auth/calendar.readonly";
+const DEVICE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+const TOKENS_FILE: &str = "google_calendar.json";
+
+/// Access tokens with less than this left are refreshed before use
+const EXPIRY_MARGIN_MS: u64 = 60_000;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the code stays on the frame, in seconds
+const CODE_MESSAGE_DURATION: u32 = 5 * 60;
+
+static TOKENS: OnceLock<Mutex<Option<GoogleTokens>>> = OnceLock::new();
+static PENDING: Mutex<Option<PendingCode>> = Mutex::new(None);
+static LAST_ERROR: Mutex<Option<String>> = Mutex::new(None);
+
+/// Tokens as stored in the config directory
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GoogleTokens {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub expires_at: u64,  // Unix timestamp in milliseconds
+}
+
+struct PendingCode {
+    code: GoogleDeviceCode,
+    expires: Instant,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeviceCodeResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_url: String,
+    pub expires_in: u64,
+    #[serde(default = "default_poll_interval")]
+    pub interval: u64,  // Seconds between polls
+}
+
+fn default_poll_interval() -> u64 {
+    5
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    refresh_token: Option<String>,
+    expires_in: Option<u64>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+/// Where waiting for the owner to enter the code stands
+#[derive(Debug, PartialEq, Eq)]
+pub enum Poll {
+    Pending,
+    SlowDown,
+    Done(GoogleTokens),
+}
+
+#[derive(Deserialize)]
+struct EventList {
+    #[serde(default)]
+    summary: String,  // The calendar's name
+    #[serde(default)]
+    items: Vec<EventItem>,
+}
+
+#[derive(Deserialize)]
+struct EventItem {
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    summary: String,
+    location: Option<String>,
+    start: Option<EventTime>,
+    end: Option<EventTime>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EventTime {
+    date_time: Option<String>,  // RFC 3339, for timed events
+    date: Option<String>,  // YYYY-MM-DD, for all-day events
+}
+
+fn now_ms() -> u64 {
+    chrono::Utc::now().timestamp_millis() as u64
+}
+
+fn tokens() -> &'static Mutex<Option<GoogleTokens>> {
+    TOKENS.get_or_init(|| {
+        let tokens = settings_manager::get_config_file_path(TOKENS_FILE)
+            .ok()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok());
+        Mutex::new(tokens)
+    })
+}
+
+fn save_tokens(new: GoogleTokens) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(&new)
+        .map_err(|e| format!("Failed to serialize Google tokens: {}", e))?;
+    auth::write_secret_file(TOKENS_FILE, &json)?;
+    *tokens()
+        .lock()
+        .map_err(|e| format!("Failed to lock Google tokens: {}", e))? = Some(new);
+    Ok(())
+}
+
+fn set_error(error: Option<String>) {
+    if let Ok(mut last) = LAST_ERROR.lock() {
+        *last = error;
+    }
+}
+
+fn credentials(settings: &GoogleCalendarSettings) -> Result<(&str, &str), String> {
+    match (settings.client_id.as_deref(), settings.client_secret.as_deref()) {
+        (Some(id), Some(secret)) if !id.is_empty() && !secret.is_empty() => Ok((id, secret)),
+        _ => Err("Set calendar.google.client_id and calendar.google.client_secret first".to_string()),
+    }
+}
+
+/// Whether the OAuth client is set up, which connecting needs
+pub fn configured(settings: &GoogleCalendarSettings) -> bool {
+    credentials(settings).is_ok()
+}
+
+/// Ask Google for a code for the owner to enter
+pub async fn request_device_code_from(
+    client: &reqwest::Client,
+    url: &str,
+    settings: &GoogleCalendarSettings,
+) -> Result<DeviceCodeResponse, String> {
+    let (client_id, _) = credentials(settings)?;
+    client
+        .post(url)
+        .form(&[("client_id", client_id), ("scope", SCOPE)])
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to get a code from Google: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse Google's device code: {}", e))
+}
+
+async fn token_request(client: &reqwest::Client, url: &str, form: &[(&str, &str)]) -> Result<TokenResponse, String> {
+    // Refusals come back as JSON with a 4xx status, so the body is read either way
+    client
+        .post(url)
+        .form(form)
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach Google: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse Google's token response: {}", e))
+}
+
+fn refusal(response: &TokenResponse) -> String {
+    let reason = response
+        .error_description
+        .as_deref()
+        .or(response.error.as_deref())
+        .unwrap_or("no reason given");
+    format!("Google refused: {}", reason)
+}
+
+fn expires_at(expires_in: Option<u64>) -> u64 {
+    now_ms() + expires_in.unwrap_or(3600) * 1000
+}
+
+/// Check whether the owner has entered the code yet
+pub async fn poll_token_from(
+    client: &reqwest::Client,
+    url: &str,
+    settings: &GoogleCalendarSettings,
+    device_code: &str,
+) -> Result<Poll, String> {
+    let (client_id, client_secret) = credentials(settings)?;
+    let form = [
+        ("client_id", client_id),
+        ("client_secret", client_secret),
+        ("device_code", device_code),
+        ("grant_type", DEVICE_GRANT),
+    ];
+    let response = token_request(client, url, &form).await?;
+    match (response.error.as_deref(), &response.access_token, &response.refresh_token) {
+        (Some("authorization_pending"), _, _) => Ok(Poll::Pending),
+        (Some("slow_down"), _, _) => Ok(Poll::SlowDown),
+        (None, Some(access_token), Some(refresh_token)) => Ok(Poll::Done(GoogleTokens {
+            access_token: access_token.clone(),
+            refresh_token: refresh_token.clone(),
+            expires_at: expires_at(response.expires_in),
+        })),
+        _ => Err(refusal(&response)),
+    }
+}
+
+/// A new access token for the stored refresh token
+pub async fn refresh_from(
+    client: &reqwest::Client,
+    url: &str,
+    settings: &GoogleCalendarSettings,
+    tokens: &GoogleTokens,
+) -> Result<GoogleTokens, String> {
+    let (client_id, client_secret) = credentials(settings)?;
+    let form = [
+        ("client_id", client_id),
+        ("client_secret", client_secret),
+        ("refresh_token", tokens.refresh_token.as_str()),
+        ("grant_type", "refresh_token"),
+    ];
+    let response = token_request(client, url, &form).await?;
+    match &response.access_token {
+        Some(access_token) if response.error.is_none() => Ok(GoogleTokens {
+            access_token: access_token.clone(),
+            // Google only sends a new refresh token when it rotates it
+            refresh_token: response.refresh_token.clone().unwrap_or_else(|| tokens.refresh_token.clone()),
+            expires_at: expires_at(response.expires_in),
+        }),
+        _ => Err(refusal(&response)),
+    }
+}
+
+/// Start and end in milliseconds, and whether the event lasts all day
+fn timestamp(time: &EventTime) -> Option<(u64, bool)> {
+    if let Some(date_time) = &time.date_time {
+        let ms = DateTime::parse_from_rfc3339(date_time).ok()?.timestamp_millis();
+        return Some((u64::try_from(ms).ok()?, false));
+    }
+    let date = NaiveDate::parse_from_str(time.date.as_deref()?, "%Y-%m-%d").ok()?;
+    let ms = Local.from_local_datetime(&date.and_time(NaiveTime::MIN)).earliest()?.timestamp_millis();
+    Some((u64::try_from(ms).ok()?, true))
+}
+
+/// Events of a Calendar API events list, expanded with `singleEvents`
+pub fn parse_events(json: &str) -> Result<Vec<CalendarEvent>, String> {
+    let list: EventList = serde_json::from_str(json).map_err(|e| format!("Failed to parse Google events: {}", e))?;
+    Ok(list
+        .items
+        .iter()
+        .filter(|item| item.status != "cancelled")
+        .filter_map(|item| {
+            let (start, all_day) = timestamp(item.start.as_ref()?)?;
+            let end = item.end.as_ref().and_then(timestamp).map_or(start, |(end, _)| end);
+            Some(CalendarEvent {
+                title: item.summary.clone(),
+                start,
+                end,
+                all_day,
+                location: item.location.clone().filter(|location| !location.is_empty()),
+                calendar: list.summary.clone(),
+            })
+        })
+        .collect())
+}
+
+/// Events of one calendar between `now` and `window_end`, in milliseconds
+pub async fn fetch_events_from(
+    client: &reqwest::Client,
+    api_url: &str,
+    access_token: &str,
+    calendar_id: &str,
+    now: u64,
+    window_end: u64,
+    max: usize,
+) -> Result<Vec<CalendarEvent>, String> {
+    let rfc3339 = |ms: u64| {
+        DateTime::from_timestamp_millis(ms as i64)
+            .unwrap_or_default()
+            .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+    };
+    let mut url = reqwest::Url::parse(api_url).map_err(|e| format!("Invalid Google API URL: {}", e))?;
+    // Calendar IDs are e-mail addresses, sometimes with a '#'
+    url.path_segments_mut()
+        .map_err(|_| "Invalid Google API URL".to_string())?
+        .extend(["calendars", calendar_id, "events"]);
+
+    let body = client
+        .get(url)
+        .bearer_auth(access_token)
+        .query(&[
+            ("timeMin", rfc3339(now)),
+            ("timeMax", rfc3339(window_end)),
+            ("singleEvents", "true".to_string()),
+            ("orderBy", "startTime".to_string()),
+            ("maxResults", max.to_string()),
+        ])
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to fetch Google calendar {}: {}", calendar_id, e))?
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read Google calendar {}: {}", calendar_id, e))?;
+    parse_events(&body)
+}
+
+/// Whether tokens are stored
+pub fn connected() -> bool {
+    tokens().lock().is_ok_and(|tokens| tokens.is_some())
+}
+
+/// For `GET /api/calendar/google`
+pub fn status() -> GoogleCalendarStatus {
+    let pending = PENDING
+        .lock()
+        .ok()
+        .and_then(|pending| {
+            pending
+                .as_ref()
+                .filter(|pending| pending.expires > Instant::now())
+                .map(|pending| pending.code.clone())
+        });
+    GoogleCalendarStatus {
+        connected: connected(),
+        pending,
+        last_error: LAST_ERROR.lock().ok().and_then(|error| error.clone()),
+    }
+}
+
+/// A usable access token, refreshed first when it is about to expire. None
+/// when no account is connected.
+async fn access_token(client: &reqwest::Client, settings: &GoogleCalendarSettings) -> Result<Option<String>, String> {
+    let Some(current) = tokens().lock().ok().and_then(|tokens| tokens.clone()) else {
+        return Ok(None);
+    };
+    if current.expires_at > now_ms() + EXPIRY_MARGIN_MS {
+        return Ok(Some(current.access_token));
+    }
+    let refreshed = refresh_from(client, TOKEN_URL, settings, &current).await?;
+    save_tokens(refreshed.clone())?;
+    Ok(Some(refreshed.access_token))
+}
+
+/// Events of every configured Google calendar, none when no account is connected
+pub async fn upcoming_events(client: &reqwest::Client, settings: &CalendarSettings) -> Result<Vec<CalendarEvent>, String> {
+    let result = async {
+        let Some(access_token) = access_token(client, &settings.google).await? else {
+            return Ok(Vec::new());
+        };
+        let (now, window_end) = calendar::window(settings);
+        let mut events = Vec::new();
+        for calendar_id in &settings.google.calendars {
+            let fetched = fetch_events_from(client, API_URL, &access_token, calendar_id, now, window_end, settings.max_events);
+            events.extend(fetched.await?);
+        }
+        Ok(events)
+    }
+    .await;
+    set_error(result.as_ref().err().cloned());
+    result
+}
+
+fn is_pending(user_code: &str) -> bool {
+    PENDING
+        .lock()
+        .is_ok_and(|pending| pending.as_ref().is_some_and(|pending| pending.code.user_code == user_code))
+}
+
+fn clear_pending(user_code: &str) {
+    if let Ok(mut pending) = PENDING.lock() {
+        if pending.as_ref().is_some_and(|pending| pending.code.user_code == user_code) {
+            *pending = None;
+        }
+    }
+}
+
+/// Wait for the owner to enter the code, then store the tokens
+async fn wait_for_approval(state: AppState, device: DeviceCodeResponse) {
+    let deadline = Instant::now() + Duration::from_secs(device.expires_in);
+    let mut interval = Duration::from_secs(device.interval.max(1));
+    while Instant::now() < deadline {
+        tokio::time::sleep(interval).await;
+        // Replaced by a newer code, or disconnected
+        if !is_pending(&device.user_code) {
+            return;
+        }
+        let settings = match state.settings_manager.get() {
+            Ok(settings) => settings.calendar.google,
+            Err(e) => {
+                warn!("Google Calendar: failed to read settings: {}", e);
+                continue;
+            }
+        };
+        let result = match poll_token_from(crate::http_client(), TOKEN_URL, &settings, &device.device_code).await {
+            Ok(Poll::Pending) => continue,
+            Ok(Poll::SlowDown) => {
+                interval += Duration::from_secs(5);
+                continue;
+            }
+            Ok(Poll::Done(tokens)) => save_tokens(tokens),
+            Err(e) => Err(e),
+        };
+        clear_pending(&device.user_code);
+        match result {
+            Ok(()) => {
+                info!("Google Calendar: connected");
+                set_error(None);
+                calendar::request_refresh();
+            }
+            Err(e) => {
+                warn!("Google Calendar: {}", e);
+                set_error(Some(e));
+            }
+        }
+        return;
+    }
+    clear_pending(&device.user_code);
+    set_error(Some("The code expired before it was entered".to_string()));
+}
+
+/// Start connecting an account: get a code, show it on the frame, and wait
+/// in the background for the owner to enter it
+pub async fn connect(state: &AppState) -> Result<GoogleDeviceCode, String> {
+    let settings = state.settings_manager.get()?.calendar.google;
+    let device = request_device_code_from(crate::http_client(), DEVICE_CODE_URL, &settings).await?;
+    let code = GoogleDeviceCode {
+        user_code: device.user_code.clone(),
+        verification_url: device.verification_url.clone(),
+        expires_in: device.expires_in,
+    };
+    *PENDING
+        .lock()
+        .map_err(|e| format!("Failed to lock Google device code: {}", e))? = Some(PendingCode {
+        code: code.clone(),
+        expires: Instant::now() + Duration::from_secs(device.expires_in),
+    });
+
+    let message = MessageRequest {
+        text: format!("Google Calendar: go to {} and enter {}", code.verification_url, code.user_code),
+        duration: CODE_MESSAGE_DURATION,
+        priority: MessagePriority::High,
+        sound: false,
+    };
+    if let Err(e) = state.messages.push(message) {
+        warn!("Google Calendar: failed to show the code: {}", e);
+    }
+    tokio::spawn(wait_for_approval(state.clone(), device));
+    Ok(code)
+}
+
+/// Forget the account's tokens and any code waiting to be entered. Returns
+/// whether an account was connected.
+pub fn disconnect() -> Result<bool, String> {
+    if let Ok(mut pending) = PENDING.lock() {
+        *pending = None;
+    }
+    set_error(None);
+    let was_connected = tokens()
+        .lock()
+        .map_err(|e| format!("Failed to lock Google tokens: {}", e))?
+        .take()
+        .is_some();
+    let path = settings_manager::get_config_file_path(TOKENS_FILE)?;
+    if path.exists() {
+        fs::remove_file(&path).map_err(|e| format!("Failed to remove Google tokens: {}", e))?;
+    }
+    calendar::request_refresh();
+    Ok(was_connected)
+}
@@ -0,0 +1,105 @@
+//! The greeting above the clock: "Good morning — cold and clear, 2°C",
+//! composed from the time of day, the current weather and the language.
+
+use crate::settings_manager::GreetingSettings;
+use crate::weather_codes::resolve_language;
+use idleview_api::WeatherData;
+
+/// Words for one language: the greetings by time of day, how the temperature
+/// feels, the sky, and the word joining feel and sky
+struct Phrases {
+    greetings: [&'static str; 4],  // Morning, afternoon, evening, night
+    feels: [&'static str; 6],  // Freezing, cold, cool, mild, warm, hot
+    skies: [&'static str; 5],  // Clear, partly cloudy, cloudy, rainy, snowy
+    and: &'static str,
+}
+
+const ENGLISH: Phrases = Phrases {
+    greetings: ["Good morning", "Good afternoon", "Good evening", "Good night"],
+    feels: ["freezing", "cold", "cool", "mild", "warm", "hot"],
+    skies: ["clear", "partly cloudy", "cloudy", "rainy", "snowy"],
+    and: "and",
+};
+
+const ROMANIAN: Phrases = Phrases {
+    greetings: ["Bună dimineața", "Bună ziua", "Bună seara", "Noapte bună"],
+    feels: ["ger", "frig", "răcoare", "plăcut", "cald", "caniculă"],
+    skies: ["senin", "parțial noros", "înnorat", "ploios", "cu ninsoare"],
+    and: "și",
+};
+
+fn phrases(language: &str) -> &'static Phrases {
+    match resolve_language(language) {
+        "ro" => &ROMANIAN,
+        _ => &ENGLISH,
+    }
+}
+
+/// 0 morning (5-12), 1 afternoon (12-18), 2 evening (18-22), 3 night
+fn part_of_day(hour: u32) -> usize {
+    match hour {
+        5..=11 => 0,
+        12..=17 => 1,
+        18..=21 => 2,
+        _ => 3,
+    }
+}
+
+fn feel(celsius: f64) -> usize {
+    [0.0, 8.0, 15.0, 22.0, 28.0]
+        .iter()
+        .position(|limit| celsius < *limit)
+        .unwrap_or(5)
+}
+
+fn sky(weather: &WeatherData) -> usize {
+    if weather.snowfall > 0.0 {
+        4
+    } else if weather.rain > 0.0 {
+        3
+    } else if weather.cloudcover > 70.0 {
+        2
+    } else if weather.cloudcover > 30.0 {
+        1
+    } else {
+        0
+    }
+}
+
+/// The greeting for the given local hour. Without weather, whatever in the
+/// template would describe it is left out and only the greeting (and name)
+/// remain.
+pub fn compose_greeting(settings: &GreetingSettings, weather: Option<&WeatherData>, hour: u32, language: &str) -> String {
+    let phrases = phrases(language);
+    let greeting = phrases.greetings[part_of_day(hour)];
+    let name = settings.name.as_deref().unwrap_or_default().trim();
+
+    let Some(weather) = weather else {
+        return if name.is_empty() { greeting.to_string() } else { format!("{}, {}", greeting, name) };
+    };
+    let celsius = match weather.temperature_unit.as_str() {
+        "fahrenheit" => (weather.temperature - 32.0) * 5.0 / 9.0,
+        _ => weather.temperature,
+    };
+    let feel = phrases.feels[feel(celsius)];
+    let sky = phrases.skies[sky(weather)];
+    let unit = if weather.temperature_unit == "fahrenheit" { "°F" } else { "°C" };
+
+    // Without a name, take the separator before {name} out with it
+    let template = if name.is_empty() {
+        settings.template.replace(", {name}", "").replace(" {name}", "")
+    } else {
+        settings.template.clone()
+    };
+    // Adding 0.0 turns -0 into 0
+    let temperature = weather.temperature.round() + 0.0;
+    template
+        .replace("{greeting}", greeting)
+        .replace("{name}", name)
+        .replace("{conditions}", &format!("{} {} {}", feel, phrases.and, sky))
+        .replace("{feel}", feel)
+        .replace("{sky}", sky)
+        .replace("{temperature}", &format!("{}{}", temperature, unit))
+        .trim()
+        .to_string()
+}
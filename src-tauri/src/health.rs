@@ -0,0 +1,110 @@
+//! Health summary for automations deciding whether to power-cycle the frame.
+
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+pub use idleview_api::health::{DiskCacheHealth, Health, HealthStatus, ProviderStatus};
+
+use crate::settings_manager;
+use crate::system;
+
+/// Below this much free space the frame is degraded
+pub const LOW_DISK_BYTES: u64 = 50 * 1024 * 1024;
+
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+#[derive(Default)]
+struct ProviderRecord {
+    last_success: Option<u64>,
+    last_error: Option<String>,
+}
+
+static PROVIDERS: Mutex<BTreeMap<&'static str, ProviderRecord>> = Mutex::new(BTreeMap::new());
+
+/// Start counting uptime, if it hasn't started yet
+pub fn mark_started() {
+    STARTED.get_or_init(Instant::now);
+}
+
+/// Seconds since the app started
+pub fn uptime() -> u64 {
+    STARTED.get_or_init(Instant::now).elapsed().as_secs()
+}
+
+/// Remember how the last call to a provider went
+pub fn record<T>(provider: &'static str, result: &Result<T, String>) {
+    let Ok(mut providers) = PROVIDERS.lock() else {
+        return;
+    };
+    let record = providers.entry(provider).or_default();
+    match result {
+        Ok(_) => {
+            record.last_success = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .ok();
+            record.last_error = None;
+        }
+        Err(e) => record.last_error = Some(e.clone()),
+    }
+}
+
+/// Providers called since the app started
+pub fn provider_statuses() -> Vec<ProviderStatus> {
+    let Ok(providers) = PROVIDERS.lock() else {
+        return Vec::new();
+    };
+    providers
+        .iter()
+        .map(|(name, record)| ProviderStatus {
+            name: name.to_string(),
+            status: if record.last_error.is_some() { HealthStatus::Degraded } else { HealthStatus::Healthy },
+            last_success: record.last_success,
+            last_error: record.last_error.clone(),
+        })
+        .collect()
+}
+
+/// Whether settings can still be saved, and the space left for them
+pub fn disk_cache_health() -> DiskCacheHealth {
+    let writable = settings_manager::get_config_file_path(".health_check")
+        .and_then(|probe| {
+            std::fs::write(&probe, b"ok").map_err(|e| e.to_string())?;
+            std::fs::remove_file(&probe).map_err(|e| e.to_string())
+        })
+        .is_ok();
+    DiskCacheHealth {
+        writable,
+        available: system::disk_status().map(|disk| disk.available),
+    }
+}
+
+/// The overall verdict: unhealthy when nothing can be saved or every provider
+/// called is failing, degraded when some are or the disk is nearly full
+pub fn overall_status(providers: &[ProviderStatus], disk: &DiskCacheHealth) -> HealthStatus {
+    let failing = providers.iter().filter(|provider| provider.status != HealthStatus::Healthy).count();
+    if !disk.writable || (failing > 0 && failing == providers.len()) {
+        HealthStatus::Unhealthy
+    } else if failing > 0 || disk.available.is_some_and(|available| available < LOW_DISK_BYTES) {
+        HealthStatus::Degraded
+    } else {
+        HealthStatus::Healthy
+    }
+}
+
+/// Everything but the fetch times, which live in the server state
+pub fn health(last_weather_fetch: Option<u64>, last_photo_fetch: Option<u64>) -> Health {
+    let providers = provider_statuses();
+    let disk_cache = disk_cache_health();
+    Health {
+        status: overall_status(&providers, &disk_cache),
+        service: "idleview-api".to_string(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        uptime: uptime(),
+        last_weather_fetch,
+        last_photo_fetch,
+        providers,
+        disk_cache,
+    }
+}
@@ -1,37 +1,99 @@
 use axum::{
-    extract::State,
-    http::StatusCode,
-    response::{IntoResponse, Response, sse::{Event, KeepAlive, Sse}},
-    routing::{get, patch, post, put},
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        ConnectInfo, MatchedPath, Path, Query, Request, State,
+    },
+    http::{header, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Response, sse::{Event, KeepAlive, Sse}},
+    routing::{delete, get, patch, post, put},
     Json, Router,
 };
-use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use serde::Deserialize;
 use serde_json::json;
-use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::{Arc, Mutex};
 use std::convert::Infallible;
+use std::future::IntoFuture;
 use tauri::{Emitter, Manager};
 use tower::ServiceBuilder;
 use tower_http::{
-    cors::{Any, CorsLayer},
-    services::ServeDir,
+    compression::{
+        predicate::{DefaultPredicate, Predicate},
+        CompressionLayer,
+    },
+    cors::{AllowOrigin, CorsLayer},
     trace::TraceLayer,
 };
+use std::time::Duration;
+use tokio::sync::{oneshot, watch};
 use tracing::{info, error};
-use tokio::sync::broadcast;
+use futures::future::BoxFuture;
 use futures::stream::Stream;
 use async_stream::stream;
 
-use crate::settings_manager::{Settings, SettingsManager};
+pub use idleview_api::{CurrentPhoto, EventEnvelope, ServerEvent, WeatherData, WeatherReport};
+use idleview_api::{API_VERSION, API_VERSION_HEADER};
 
-/// Current photo information
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CurrentPhoto {
-    pub url: String,
-    pub author: String,
-    pub author_url: String,
-}
+use crate::access_log::{self, AccessLogEntry};
+use crate::alarms;
+use crate::auth;
+use crate::battery;
+use crate::connectivity::{self, Connectivity};
+use crate::control_panel;
+use crate::crypto::{self, CryptoReport};
+use crate::brightness::{self, Brightness, BrightnessRequest};
+use crate::burn_in;
+use crate::caldav::{self, CalDavPasswordRequest};
+use crate::calendar::{self, CalendarReport};
+use crate::discovery;
+use crate::disk_space;
+use crate::health::{self, Health, HealthStatus};
+use crate::lastfm;
+use crate::media::{self, MediaDevice};
+use crate::display_power::{self, DisplayPower, PowerState};
+use crate::display_sleep::{self, DisplaySleep};
+use crate::google_calendar::{self, GoogleCalendarStatus, GoogleDeviceCode};
+use crate::event_hub::{ClientStats, EventHub, EventPriority, CLIENT_QUEUE_CAPACITY};
+use crate::openapi;
+use crate::messages::{self, MessageQueue, MessageRequest, QueuedMessage};
+use crate::metrics;
+use crate::monitors::{self, MonitorInfo};
+use crate::mqtt;
+use crate::news::{self, NewsReport};
+use crate::night_mode::{self, NightMode};
+use crate::now_playing::{self, NowPlaying};
+use crate::ntp;
+use crate::owntracks;
+use crate::pairing::{self, PairRequest, PairedDevice, PairingResult, PairingStarted};
+use crate::power::{self, PowerAction};
+use crate::presence::{self, Presence};
+use crate::profiles;
+use crate::quotes::{self, Quote};
+use crate::rate_limit::{RateLimiter, RequestKind};
+use crate::scenes;
+use crate::screenshot;
+use crate::settings_history::{self, ChangeSource, SettingsHistoryEntry};
+use crate::settings_manager::{
+    self, AccessLogLevel, Alarm, Profile, ProfilesSettings, Scene, ServerBind, ServerSettings, Settings, SettingsDefaults, SettingsManager,
+};
+use crate::settings_watcher;
+use crate::spotify::{self, SpotifyAuthorization, SpotifyCallbackRequest, SpotifyStatus};
+use crate::ssdp;
+use crate::sports::{self, SportsReport};
+use crate::stocks::{self, StocksReport};
+use crate::sync::{self, SyncStatus};
+use crate::system::{self, SystemStatus};
+use crate::thermal;
+use crate::tasks::{self, TasksReport};
+use crate::timer::{self, Timer, TimerRequest};
+use crate::tls;
+use crate::updates::{self, UpdateInfo};
+use crate::watchdog::{self, WatchdogStatus};
+use crate::webhooks;
+use crate::weather_codes::{self, WeatherLegend};
+use crate::DebugInfo;
 
 /// Application state shared across handlers
 #[derive(Clone)]
@@ -39,18 +101,174 @@ pub struct AppState {
     pub settings_manager: SettingsManager,
     pub app_handle: tauri::AppHandle,
     pub current_photo: Arc<Mutex<Option<CurrentPhoto>>>,
-    pub event_broadcaster: broadcast::Sender<String>,
+    pub event_hub: Arc<EventHub>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub weather: Arc<Mutex<Option<WeatherReport>>>,
+    pub messages: Arc<MessageQueue>,
+}
+
+impl AppState {
+    /// Wrap an event in the shared envelope and deliver it to the webview and SSE
+    /// clients; private events only go to the webview
+    pub fn publish(&self, event: ServerEvent) {
+        self.publish_from(event, "http");
+    }
+
+    /// Same as `publish`, for events raised by something other than an HTTP request
+    pub fn publish_from(&self, event: ServerEvent, source: &str) {
+        // Webhooks and event streams get settings without their credentials
+        let event = match event {
+            ServerEvent::SettingsUpdated(settings) => match settings_manager::redacted(&settings) {
+                Ok(settings) => ServerEvent::SettingsUpdated(Box::new(settings)),
+                Err(e) => {
+                    error!("Not publishing settings-updated: {}", e);
+                    return;
+                }
+            },
+            event => event,
+        };
+        let priority = EventPriority::of(&event);
+        let envelope = EventEnvelope::new(event, source);
+        let _ = self.app_handle.emit(envelope.event.event_type(), &envelope);
+        if envelope.event.is_private() {
+            return;
+        }
+        if let Ok(event_data) = serde_json::to_string(&envelope) {
+            self.event_hub.publish(event_data, priority);
+        }
+    }
+
+    /// Record the photo the frame is showing and tell clients about it
+    pub fn set_current_photo_from(&self, photo: CurrentPhoto, source: &str) -> Result<(), String> {
+        *self
+            .current_photo
+            .lock()
+            .map_err(|e| format!("Failed to lock photo state: {}", e))? = Some(photo.clone());
+        info!("Current photo updated: {} by {}", photo.url, photo.author);
+        self.publish_from(ServerEvent::PhotoUpdated(photo), source);
+        Ok(())
+    }
+
+    /// Keep the latest weather for `GET /api/weather` and tell clients about it,
+    /// raising a weather alert for conditions the previous weather didn't have
+    pub fn set_weather_from(&self, weather: WeatherData, source: &str) -> WeatherReport {
+        let report = WeatherReport {
+            weather: weather.clone(),
+            fetched_at: chrono::Utc::now().timestamp_millis() as u64,
+            stale: false,
+        };
+        let mut new_alerts = weather.alerts.clone();
+        if let Ok(mut current) = self.weather.lock() {
+            if let Some(previous) = current.as_ref() {
+                new_alerts.retain(|alert| !previous.weather.alerts.contains(alert));
+            }
+            *current = Some(report.clone());
+        }
+        self.publish_from(ServerEvent::WeatherUpdated(Box::new(weather)), source);
+        if !new_alerts.is_empty() {
+            self.publish_from(ServerEvent::WeatherAlert { alerts: new_alerts }, source);
+        }
+        report
+    }
 }
 
+/// How long open requests get to finish when the listener stops
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// Pause before restarting a listener that failed
+const RESTART_DELAY: Duration = Duration::from_secs(2);
+
 /// Custom error type for HTTP responses
-pub struct AppError(String);
+pub struct AppError {
+    status: StatusCode,
+    message: String,
+    problems: Vec<String>,
+}
+
+impl AppError {
+    fn internal(message: impl Into<String>) -> Self {
+        AppError {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: message.into(),
+            problems: Vec::new(),
+        }
+    }
+
+    fn unauthorized(message: impl Into<String>) -> Self {
+        AppError {
+            status: StatusCode::UNAUTHORIZED,
+            message: message.into(),
+            problems: Vec::new(),
+        }
+    }
+
+    fn not_found(message: impl Into<String>) -> Self {
+        AppError {
+            status: StatusCode::NOT_FOUND,
+            message: message.into(),
+            problems: Vec::new(),
+        }
+    }
+
+    fn forbidden(message: impl Into<String>) -> Self {
+        AppError {
+            status: StatusCode::FORBIDDEN,
+            message: message.into(),
+            problems: Vec::new(),
+        }
+    }
+
+    fn conflict(message: impl Into<String>) -> Self {
+        AppError {
+            status: StatusCode::CONFLICT,
+            message: message.into(),
+            problems: Vec::new(),
+        }
+    }
+
+    fn bad_request(message: impl Into<String>) -> Self {
+        AppError {
+            status: StatusCode::BAD_REQUEST,
+            message: message.into(),
+            problems: Vec::new(),
+        }
+    }
+
+    fn too_many_requests(message: impl Into<String>) -> Self {
+        AppError {
+            status: StatusCode::TOO_MANY_REQUESTS,
+            message: message.into(),
+            problems: Vec::new(),
+        }
+    }
+
+    /// 502 for a service the frame depends on failing
+    fn bad_gateway(message: impl Into<String>) -> Self {
+        AppError {
+            status: StatusCode::BAD_GATEWAY,
+            message: message.into(),
+            problems: Vec::new(),
+        }
+    }
+
+    /// 422 listing every invalid value in a request
+    fn unprocessable(problems: Vec<String>) -> Self {
+        AppError {
+            status: StatusCode::UNPROCESSABLE_ENTITY,
+            message: "Invalid settings".to_string(),
+            problems,
+        }
+    }
+}
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let body = json!({
-            "error": self.0
-        });
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(body)).into_response()
+        let body = if self.problems.is_empty() {
+            json!({ "error": self.message })
+        } else {
+            json!({ "error": self.message, "problems": self.problems })
+        };
+        (self.status, Json(body)).into_response()
     }
 }
 
@@ -59,17 +277,17 @@ where
     E: std::error::Error,
 {
     fn from(err: E) -> Self {
-        AppError(err.to_string())
+        AppError::internal(err.to_string())
     }
 }
 
-/// GET /api/settings - Return current settings as JSON
+/// GET /api/settings - Return current settings as JSON, credentials redacted
 async fn get_settings(State(state): State<AppState>) -> Result<Json<Settings>, AppError> {
     match state.settings_manager.get() {
-        Ok(settings) => Ok(Json(settings)),
+        Ok(settings) => settings_manager::redacted(&settings).map(Json).map_err(AppError::internal),
         Err(e) => {
             error!("Failed to get settings: {}", e);
-            Err(AppError(e))
+            Err(AppError::internal(e))
         }
     }
 }
@@ -77,26 +295,28 @@ async fn get_settings(State(state): State<AppState>) -> Result<Json<Settings>, A
 /// PUT /api/settings - Update all settings from JSON body
 async fn update_settings(
     State(state): State<AppState>,
-    Json(settings): Json<Settings>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Json(mut body): Json<serde_json::Value>,
 ) -> Result<Json<Settings>, AppError> {
-    match state.settings_manager.update_all(settings.clone()) {
+    // Credentials sent back redacted are checked as the stored ones
+    let current = serde_json::to_value(state.settings_manager.get().map_err(AppError::internal)?)?;
+    settings_manager::restore_secrets(&mut body, &current, "");
+    let problems = settings_manager::validate_settings_json(&body);
+    if !problems.is_empty() {
+        return Err(AppError::unprocessable(problems));
+    }
+    let settings: Settings = serde_json::from_value(body)?;
+
+    match state.settings_manager.update_all_from(settings.clone(), ChangeSource::http(addr)) {
         Ok(_) => {
             info!("Settings updated successfully");
-            // Emit event to Tauri window
-            let _ = state.app_handle.emit("settings-updated", &settings);
-            
-            // Broadcast settings update via SSE
-            let event_data = serde_json::to_string(&json!({
-                "type": "settings-updated",
-                "settings": settings
-            })).unwrap_or_default();
-            let _ = state.event_broadcaster.send(event_data);
-            
-            Ok(Json(settings))
+            // Notify the Tauri window and SSE clients
+            state.publish(ServerEvent::SettingsUpdated(Box::new(settings.clone())));
+            settings_manager::redacted(&settings).map(Json).map_err(AppError::internal)
         }
         Err(e) => {
             error!("Failed to update settings: {}", e);
-            Err(AppError(e))
+            Err(AppError::internal(e))
         }
     }
 }
@@ -104,215 +324,1886 @@ async fn update_settings(
 /// PATCH /api/settings - Partially update settings from JSON body
 async fn patch_settings(
     State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     Json(updates): Json<serde_json::Value>,
 ) -> Result<Json<Settings>, AppError> {
-    match state.settings_manager.update_partial(updates) {
+    let problems = state.settings_manager.validate_partial(&updates).map_err(AppError::internal)?;
+    if !problems.is_empty() {
+        return Err(AppError::unprocessable(problems));
+    }
+
+    match state.settings_manager.update_partial_from(updates, ChangeSource::http(addr)) {
         Ok(settings) => {
             info!("Settings partially updated successfully");
-            // Emit event to Tauri window
-            let _ = state.app_handle.emit("settings-updated", &settings);
-            Ok(Json(settings))
+            // Notify the Tauri window and SSE clients
+            state.publish(ServerEvent::SettingsUpdated(Box::new(settings.clone())));
+            settings_manager::redacted(&settings).map(Json).map_err(AppError::internal)
         }
         Err(e) => {
             error!("Failed to partially update settings: {}", e);
-            Err(AppError(e))
+            Err(AppError::internal(e))
+        }
+    }
+}
+
+/// GET /api/settings/defaults - Default settings and the allowed values of enum fields
+async fn get_settings_defaults() -> Json<SettingsDefaults> {
+    Json(settings_manager::settings_defaults())
+}
+
+/// GET /api/settings/schema - JSON Schema of the settings document
+async fn get_settings_schema() -> Json<serde_json::Value> {
+    Json(settings_manager::settings_schema())
+}
+
+/// GET /api/settings/:section - Return one settings section (units, display, photos, location, weather or server)
+async fn get_settings_section(
+    State(state): State<AppState>,
+    Path(section): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    match state.settings_manager.get_section(&section) {
+        Ok(Some(mut values)) => {
+            settings_manager::redact_secrets(&mut values, &section);
+            Ok(Json(values))
+        }
+        Ok(None) => Err(AppError::not_found(format!("Unknown settings section: {}", section))),
+        Err(e) => {
+            error!("Failed to get settings section: {}", e);
+            Err(AppError::internal(e))
+        }
+    }
+}
+
+/// PATCH /api/settings/:section - Partially update one settings section
+async fn patch_settings_section(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(section): Path<String>,
+    Json(updates): Json<serde_json::Value>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    if !settings_manager::SECTIONS.contains(&section.as_str()) {
+        return Err(AppError::not_found(format!("Unknown settings section: {}", section)));
+    }
+    let problems = state
+        .settings_manager
+        .validate_partial(&json!({ section.as_str(): updates }))
+        .map_err(AppError::internal)?;
+    if !problems.is_empty() {
+        return Err(AppError::unprocessable(problems));
+    }
+
+    match state.settings_manager.update_section_from(&section, updates, ChangeSource::http(addr)) {
+        Ok(mut values) => {
+            info!("Settings section '{}' updated", section);
+            settings_manager::redact_secrets(&mut values, &section);
+            let settings = state.settings_manager.get().map_err(AppError::internal)?;
+            state.publish(ServerEvent::SettingsUpdated(Box::new(settings)));
+            Ok(Json(values))
+        }
+        Err(e) => {
+            error!("Failed to update settings section: {}", e);
+            Err(AppError::internal(e))
         }
     }
 }
 
 /// POST /api/settings/reset - Reset all settings to defaults
-async fn reset_settings(State(state): State<AppState>) -> Result<Json<Settings>, AppError> {
+async fn reset_settings(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+) -> Result<Json<Settings>, AppError> {
     let default_settings = Settings::default();
     
-    match state.settings_manager.update_all(default_settings.clone()) {
+    match state.settings_manager.update_all_from(default_settings.clone(), ChangeSource::http(addr)) {
         Ok(_) => {
             info!("Settings reset to defaults successfully");
-            // Emit event to Tauri window
-            let _ = state.app_handle.emit("settings-updated", &default_settings);
-            Ok(Json(default_settings))
+            // Notify the Tauri window and SSE clients
+            state.publish(ServerEvent::SettingsUpdated(Box::new(default_settings.clone())));
+            settings_manager::redacted(&default_settings).map(Json).map_err(AppError::internal)
         }
         Err(e) => {
             error!("Failed to reset settings: {}", e);
-            Err(AppError(e))
+            Err(AppError::internal(e))
         }
     }
 }
 
-/// Health check endpoint
-async fn health_check() -> Json<serde_json::Value> {
-    Json(json!({
-        "status": "healthy",
-        "service": "idleview-api"
-    }))
+/// GET /api/settings/backup - Download the settings with backup metadata
+async fn backup_settings(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
+    let settings = state.settings_manager.get().map_err(AppError::internal)?;
+    let backup = settings_manager::create_backup(&settings);
+    let file_name = format!(
+        "idleview-settings-{}.json",
+        chrono::Local::now().format("%Y%m%d-%H%M")
+    );
+
+    Ok((
+        [(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", file_name),
+        )],
+        Json(backup),
+    ))
 }
 
-/// GET /api/photo/current - Return current photo information
-async fn get_current_photo(State(state): State<AppState>) -> Result<Json<Option<CurrentPhoto>>, AppError> {
-    let photo = state.current_photo
-        .lock()
-        .map_err(|e| AppError(format!("Failed to lock photo state: {}", e)))?;
-    Ok(Json(photo.clone()))
+/// POST /api/settings/restore - Validate and apply an uploaded backup
+async fn restore_settings(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Json(backup): Json<serde_json::Value>,
+) -> Result<Json<Settings>, AppError> {
+    let settings = settings_manager::settings_from_backup(&backup).map_err(AppError::unprocessable)?;
+
+    match state.settings_manager.update_all_from(settings.clone(), ChangeSource::http(addr)) {
+        Ok(_) => {
+            info!("Settings restored from backup");
+            state.publish(ServerEvent::SettingsUpdated(Box::new(settings.clone())));
+            settings_manager::redacted(&settings).map(Json).map_err(AppError::internal)
+        }
+        Err(e) => {
+            error!("Failed to restore settings: {}", e);
+            Err(AppError::internal(e))
+        }
+    }
 }
 
-/// POST /api/photo/current - Update current photo information
-async fn update_current_photo(
+/// Query parameters for the settings history
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    pub limit: Option<usize>,
+}
+
+/// GET /api/settings/history - Recent settings changes, newest first
+async fn get_settings_history(Query(query): Query<HistoryQuery>) -> Json<Vec<SettingsHistoryEntry>> {
+    Json(settings_history::recent_changes(query.limit))
+}
+
+/// GET /api/settings/profiles - List profiles, the active one and the schedule
+async fn list_profiles(State(state): State<AppState>) -> Result<Json<ProfilesSettings>, AppError> {
+    let settings = state.settings_manager.get().map_err(AppError::internal)?;
+    settings_manager::redacted(&settings).map(|settings| Json(settings.profiles)).map_err(AppError::internal)
+}
+
+/// GET /api/settings/profiles/:name - Return one profile
+async fn get_profile(
     State(state): State<AppState>,
-    Json(photo): Json<CurrentPhoto>,
-) -> Result<Json<CurrentPhoto>, AppError> {
-    let mut current = state.current_photo
-        .lock()
-        .map_err(|e| AppError(format!("Failed to lock photo state: {}", e)))?;
-    *current = Some(photo.clone());
-    info!("Current photo updated: {} by {}", photo.url, photo.author);
-    
-    // Broadcast photo update event via SSE
-    let event_data = serde_json::to_string(&json!({
-        "type": "photo-updated",
-        "photo": photo
-    })).unwrap_or_default();
-    let _ = state.event_broadcaster.send(event_data);
-    
-    Ok(Json(photo))
+    Path(name): Path<String>,
+) -> Result<Json<Profile>, AppError> {
+    let settings = state.settings_manager.get().map_err(AppError::internal)?;
+    let settings = settings_manager::redacted(&settings).map_err(AppError::internal)?;
+    profiles::find_profile(&settings.profiles, &name)
+        .cloned()
+        .map(Json)
+        .ok_or_else(|| AppError::not_found(format!("Unknown profile: {}", name)))
 }
 
-/// GET /api/events - Server-Sent Events stream for real-time updates
-async fn events_stream(
+/// Request body for replacing a profile's overrides
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ProfileRequest {
+    pub settings: serde_json::Value,
+}
+
+/// Save a profile after checking its overrides would produce valid settings
+fn store_profile(state: &AppState, profile: Profile) -> Result<Json<Profile>, AppError> {
+    let overrides = profiles::profile_overrides(&profile.settings);
+    let problems = state.settings_manager.validate_partial(&overrides).map_err(AppError::internal)?;
+    if !problems.is_empty() {
+        return Err(AppError::unprocessable(problems));
+    }
+
+    // Credentials sent back redacted keep the ones the profile has
+    let mut overrides = overrides;
+    let current = state.settings_manager.get().map_err(AppError::internal)?;
+    if let Some(stored) = profiles::find_profile(&current.profiles, &profile.name) {
+        settings_manager::restore_secrets(&mut overrides, &stored.settings, "");
+    }
+
+    let profile = Profile { settings: overrides, ..profile };
+    match profiles::save_profile(&state.settings_manager, profile.clone()) {
+        Ok(settings) => {
+            info!("Profile '{}' saved", profile.name);
+            state.publish(ServerEvent::SettingsUpdated(Box::new(settings)));
+            let mut profile = profile;
+            settings_manager::redact_secrets(&mut profile.settings, "");
+            Ok(Json(profile))
+        }
+        Err(e) => {
+            error!("Failed to save profile: {}", e);
+            Err(AppError::internal(e))
+        }
+    }
+}
+
+/// POST /api/settings/profiles - Create a profile
+async fn create_profile(
     State(state): State<AppState>,
-) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
-    let mut rx = state.event_broadcaster.subscribe();
-    
-    let stream = stream! {
-        loop {
-            match rx.recv().await {
-                Ok(event_data) => {
-                    yield Ok(Event::default().data(event_data));
-                },
-                Err(broadcast::error::RecvError::Lagged(_)) => {
-                    // Client lagged behind, continue
-                    continue;
-                },
-                Err(_) => {
-                    // Channel closed
-                    break;
-                }
-            }
+    Json(profile): Json<Profile>,
+) -> Result<(StatusCode, Json<Profile>), AppError> {
+    if profile.name.trim().is_empty() {
+        return Err(AppError::unprocessable(vec!["name: must not be empty".to_string()]));
+    }
+    let current = state.settings_manager.get().map_err(AppError::internal)?;
+    if profiles::find_profile(&current.profiles, &profile.name).is_some() {
+        return Err(AppError::conflict(format!("Profile already exists: {}", profile.name)));
+    }
+
+    store_profile(&state, profile).map(|profile| (StatusCode::CREATED, profile))
+}
+
+/// PUT /api/settings/profiles/:name - Replace a profile's settings overrides
+async fn update_profile(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(request): Json<ProfileRequest>,
+) -> Result<Json<Profile>, AppError> {
+    let current = state.settings_manager.get().map_err(AppError::internal)?;
+    if profiles::find_profile(&current.profiles, &name).is_none() {
+        return Err(AppError::not_found(format!("Unknown profile: {}", name)));
+    }
+
+    store_profile(&state, Profile { name, settings: request.settings })
+}
+
+/// DELETE /api/settings/profiles/:name - Delete a profile and its schedule windows
+async fn delete_profile(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let current = state.settings_manager.get().map_err(AppError::internal)?;
+    if profiles::find_profile(&current.profiles, &name).is_none() {
+        return Err(AppError::not_found(format!("Unknown profile: {}", name)));
+    }
+
+    match profiles::delete_profile(&state.settings_manager, &name) {
+        Ok(settings) => {
+            info!("Profile '{}' deleted", name);
+            state.publish(ServerEvent::SettingsUpdated(Box::new(settings)));
+            Ok(StatusCode::NO_CONTENT)
         }
-    };
-    
-    Sse::new(stream).keep_alive(KeepAlive::default())
+        Err(e) => {
+            error!("Failed to delete profile: {}", e);
+            Err(AppError::internal(e))
+        }
+    }
 }
 
-/// Create the router with all routes
-fn create_router(state: AppState, static_dir: PathBuf) -> Router {
-    // API routes
-    let api_routes = Router::new()
-        .route("/settings", get(get_settings))
-        .route("/settings", put(update_settings))
-        .route("/settings", patch(patch_settings))
-        .route("/settings/reset", post(reset_settings))
-        .route("/photo/current", get(get_current_photo))
-        .route("/photo/current", post(update_current_photo))
-        .route("/events", get(events_stream))
-        .route("/health", get(health_check));
+/// POST /api/settings/profiles/:name/activate - Activate a profile and apply its overrides
+async fn activate_profile(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<Settings>, AppError> {
+    let current = state.settings_manager.get().map_err(AppError::internal)?;
+    if profiles::find_profile(&current.profiles, &name).is_none() {
+        return Err(AppError::not_found(format!("Unknown profile: {}", name)));
+    }
 
-    // CORS configuration - allow all origins for development
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
+    match profiles::activate_profile(&state.settings_manager, &name) {
+        Ok(settings) => {
+            info!("Profile '{}' activated", name);
+            state.publish(ServerEvent::SettingsUpdated(Box::new(settings.clone())));
+            settings_manager::redacted(&settings).map(Json).map_err(AppError::internal)
+        }
+        Err(e) => {
+            error!("Failed to activate profile: {}", e);
+            Err(AppError::internal(e))
+        }
+    }
+}
 
-    // Build the main router
-    Router::new()
-        .nest("/api", api_routes)
-        .nest_service("/", ServeDir::new(static_dir))
-        .layer(
-            ServiceBuilder::new()
-                .layer(TraceLayer::new_for_http())
-                .layer(cors),
-        )
-        .with_state(state)
+/// POST /api/settings/profiles/deactivate - Clear the active profile
+async fn deactivate_profile(State(state): State<AppState>) -> Result<Json<Settings>, AppError> {
+    match profiles::deactivate_profile(&state.settings_manager) {
+        Ok(settings) => {
+            info!("Profile deactivated");
+            state.publish(ServerEvent::SettingsUpdated(Box::new(settings.clone())));
+            settings_manager::redacted(&settings).map(Json).map_err(AppError::internal)
+        }
+        Err(e) => {
+            error!("Failed to deactivate profile: {}", e);
+            Err(AppError::internal(e))
+        }
+    }
 }
 
-/// Get local IP addresses for display
-fn get_local_ips() -> Vec<String> {
-    let mut ips = vec!["127.0.0.1".to_string()];
-    
-    if let Ok(local_ip) = local_ip_address::local_ip() {
-        ips.push(local_ip.to_string());
+/// GET /api/alarms - List alarms
+async fn list_alarms(State(state): State<AppState>) -> Result<Json<Vec<Alarm>>, AppError> {
+    let settings = state.settings_manager.get().map_err(AppError::internal)?;
+    Ok(Json(settings.alarms.list))
+}
+
+/// GET /api/alarms/:id - Return one alarm
+async fn get_alarm(State(state): State<AppState>, Path(id): Path<u32>) -> Result<Json<Alarm>, AppError> {
+    let settings = state.settings_manager.get().map_err(AppError::internal)?;
+    alarms::find_alarm(&settings.alarms, id)
+        .cloned()
+        .map(Json)
+        .ok_or_else(|| AppError::not_found(format!("Unknown alarm: {}", id)))
+}
+
+/// Save an alarm after checking it would produce valid settings
+fn store_alarm(state: &AppState, alarm: Alarm) -> Result<Json<Alarm>, AppError> {
+    let problems = state
+        .settings_manager
+        .validate_partial(&json!({ "alarms": { "list": [alarm] } }))
+        .map_err(AppError::internal)?;
+    if !problems.is_empty() {
+        return Err(AppError::unprocessable(problems));
+    }
+
+    match alarms::save_alarm(&state.settings_manager, alarm.clone()) {
+        Ok(settings) => {
+            info!("Alarm {} saved for {}", alarm.id, alarm.time);
+            state.publish(ServerEvent::SettingsUpdated(Box::new(settings)));
+            Ok(Json(alarm))
+        }
+        Err(e) => {
+            error!("Failed to save alarm: {}", e);
+            Err(AppError::internal(e))
+        }
     }
-    
-    ips
 }
 
-/// Start the HTTP server
-pub async fn start_server(port: u16, app_handle: tauri::AppHandle) -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "info".into()),
-        )
-        .init();
+/// POST /api/alarms - Create an alarm; its id is assigned here
+async fn create_alarm(
+    State(state): State<AppState>,
+    Json(alarm): Json<Alarm>,
+) -> Result<(StatusCode, Json<Alarm>), AppError> {
+    let current = state.settings_manager.get().map_err(AppError::internal)?;
+    let alarm = Alarm { id: alarms::next_id(&current.alarms), ..alarm };
+    store_alarm(&state, alarm).map(|alarm| (StatusCode::CREATED, alarm))
+}
+
+/// PUT /api/alarms/:id - Replace an alarm
+async fn update_alarm(
+    State(state): State<AppState>,
+    Path(id): Path<u32>,
+    Json(alarm): Json<Alarm>,
+) -> Result<Json<Alarm>, AppError> {
+    let current = state.settings_manager.get().map_err(AppError::internal)?;
+    if alarms::find_alarm(&current.alarms, id).is_none() {
+        return Err(AppError::not_found(format!("Unknown alarm: {}", id)));
+    }
 
-    // Initialize settings manager
-    let settings_manager = SettingsManager::new()
-        .map_err(|e| format!("Failed to initialize settings manager: {}", e))?;
+    store_alarm(&state, Alarm { id, ..alarm })
+}
 
-    // Create broadcast channel for SSE events (capacity: 100 events)
-    let (event_broadcaster, _) = broadcast::channel(100);
+/// DELETE /api/alarms/:id - Delete an alarm
+async fn delete_alarm(State(state): State<AppState>, Path(id): Path<u32>) -> Result<StatusCode, AppError> {
+    let current = state.settings_manager.get().map_err(AppError::internal)?;
+    if alarms::find_alarm(&current.alarms, id).is_none() {
+        return Err(AppError::not_found(format!("Unknown alarm: {}", id)));
+    }
 
-    let state = AppState { 
-        settings_manager,
-        app_handle: app_handle.clone(),
-        current_photo: Arc::new(Mutex::new(None)),
-        event_broadcaster,
-    };
+    match alarms::delete_alarm(&state.settings_manager, id) {
+        Ok(settings) => {
+            info!("Alarm {} deleted", id);
+            state.publish(ServerEvent::SettingsUpdated(Box::new(settings)));
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Err(e) => {
+            error!("Failed to delete alarm: {}", e);
+            Err(AppError::internal(e))
+        }
+    }
+}
+
+/// GET /api/health - Overall status with uptime, last fetches, providers and disk.
+/// Answers 503 when unhealthy so automations can act on the status code alone.
+async fn health_check(State(state): State<AppState>) -> Result<(StatusCode, Json<Health>), AppError> {
+    let last_weather_fetch = state
+        .weather
+        .lock()
+        .map_err(|e| AppError::internal(format!("Failed to lock weather state: {}", e)))?
+        .as_ref()
+        .map(|report| report.fetched_at);
+    let last_photo_fetch = state
+        .current_photo
+        .lock()
+        .map_err(|e| AppError::internal(format!("Failed to lock photo state: {}", e)))?
+        .as_ref()
+        .and_then(|photo| photo.fetched_at);
 
-    // Determine static files directory
-    let static_dir = if cfg!(debug_assertions) {
-        // Development: use the idleview-control folder inside src-tauri
-        PathBuf::from("idleview-control")
-    } else {
-        // Production: use resource path bundled with the app
-        app_handle
-            .path()
-            .resource_dir()
-            .expect("Failed to get resource directory")
-            .join("idleview-control")
+    // The disk checks touch the filesystem
+    let report = tokio::task::spawn_blocking(move || health::health(last_weather_fetch, last_photo_fetch))
+        .await
+        .map_err(|e| AppError::internal(format!("Failed to check health: {}", e)))?;
+    let status = match report.status {
+        HealthStatus::Unhealthy => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::OK,
     };
+    Ok((status, Json(report)))
+}
 
-    // Create router
-    let app = create_router(state, static_dir);
+/// GET /api/calendar - Upcoming events from the calendar feeds
+async fn get_calendar() -> Json<CalendarReport> {
+    Json(calendar::report())
+}
 
-    // Bind to 0.0.0.0 to accept connections from local network
-    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+/// GET /api/calendar/google - Whether a Google account is connected, and the code to enter while connecting
+async fn get_google_calendar() -> Json<GoogleCalendarStatus> {
+    Json(google_calendar::status())
+}
 
-    info!("🚀 Idleview HTTP Server starting...");
-    info!("📍 Server listening on port {}", port);
-    
-    let ips = get_local_ips();
-    info!("🌐 Access the control panel at:");
-    for ip in ips {
-        info!("   http://{}:{}", ip, port);
+/// POST /api/calendar/google/connect - Get a code to enter at Google, shown on the frame too
+async fn connect_google_calendar(State(state): State<AppState>) -> Result<Json<GoogleDeviceCode>, AppError> {
+    let settings = state.settings_manager.get().map_err(AppError::internal)?;
+    if !google_calendar::configured(&settings.calendar.google) {
+        return Err(AppError::conflict("Set calendar.google.client_id and calendar.google.client_secret first"));
     }
-    
-    info!("📡 API endpoints available at:");
-    info!("   GET    /api/settings");
-    info!("   PUT    /api/settings");
-    info!("   PATCH  /api/settings");
-    info!("   POST   /api/settings/reset");
-    info!("   GET    /api/photo/current");
-    info!("   POST   /api/photo/current");
-    info!("   GET    /api/health");
-
-    // Start the server
-    let listener = tokio::net::TcpListener::bind(addr)
-        .await
-        .map_err(|e| format!("Failed to bind to {}: {}", addr, e))?;
+    let code = google_calendar::connect(&state).await.map_err(AppError::bad_gateway)?;
+    info!("Google Calendar: waiting for code {} to be entered", code.user_code);
+    Ok(Json(code))
+}
 
-    axum::serve(listener, app)
-        .await
-        .map_err(|e| format!("Server error: {}", e).into())
+/// DELETE /api/calendar/google - Forget the connected Google account
+async fn disconnect_google_calendar() -> Result<StatusCode, AppError> {
+    if !google_calendar::disconnect().map_err(AppError::internal)? {
+        return Err(AppError::not_found("No Google account connected"));
+    }
+    info!("Google Calendar: disconnected");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// PUT /api/calendar/caldav/:name/password - Store a CalDAV calendar's password outside the settings
+async fn set_caldav_password(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(request): Json<CalDavPasswordRequest>,
+) -> Result<StatusCode, AppError> {
+    let settings = state.settings_manager.get().map_err(AppError::internal)?;
+    if !settings.calendar.caldav.iter().any(|calendar| calendar.name == name) {
+        return Err(AppError::not_found(format!("Unknown CalDAV calendar: {}", name)));
+    }
+    caldav::set_password(&name, &request.password).map_err(AppError::internal)?;
+    info!("CalDAV password for {} updated", name);
+    calendar::request_refresh();
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/quote - Today's quote
+async fn get_quote(State(state): State<AppState>) -> Result<Json<Quote>, AppError> {
+    let settings = state.settings_manager.get().map_err(AppError::internal)?;
+    Ok(Json(quotes::quote_of_the_day(&settings.quotes).await))
+}
+
+/// GET /api/news - Headlines from the news feeds
+async fn get_news() -> Json<NewsReport> {
+    Json(news::report())
+}
+
+/// GET /api/stocks - Last price and day change of the configured stocks
+async fn get_stocks(State(state): State<AppState>) -> Result<Json<StocksReport>, AppError> {
+    let settings = state.settings_manager.get().map_err(AppError::internal)?;
+    Ok(Json(stocks::report(&settings.stocks).await))
+}
+
+/// GET /api/sports - Live scores, upcoming fixtures and results of the followed teams
+async fn get_sports(State(state): State<AppState>) -> Result<Json<SportsReport>, AppError> {
+    let settings = state.settings_manager.get().map_err(AppError::internal)?;
+    Ok(Json(sports::report(&settings.sports).await))
+}
+
+/// GET /api/tasks - Today's tasks from Todoist and the tasks file
+async fn get_tasks(State(state): State<AppState>) -> Result<Json<TasksReport>, AppError> {
+    let settings = state.settings_manager.get().map_err(AppError::internal)?;
+    Ok(Json(tasks::report(&settings.tasks).await))
+}
+
+/// GET /api/crypto - Prices of the configured coins
+async fn get_crypto(State(state): State<AppState>) -> Result<Json<CryptoReport>, AppError> {
+    let settings = state.settings_manager.get().map_err(AppError::internal)?;
+    Ok(Json(crypto::report(&settings.crypto).await))
+}
+
+/// GET /api/now-playing - The track playing right now, null when nothing is
+async fn get_now_playing() -> Json<Option<NowPlaying>> {
+    Json(now_playing::current())
+}
+
+/// GET /api/media/devices - Chromecasts and Sonos speakers found on the network
+async fn get_media_devices() -> Json<Vec<MediaDevice>> {
+    Json(media::devices())
+}
+
+/// GET /api/spotify - Whether a Spotify account is connected
+async fn get_spotify() -> Json<SpotifyStatus> {
+    Json(spotify::status())
+}
+
+/// POST /api/spotify/connect - Get the page at which to approve the frame
+async fn connect_spotify(State(state): State<AppState>) -> Result<Json<SpotifyAuthorization>, AppError> {
+    let settings = state.settings_manager.get().map_err(AppError::internal)?;
+    if !spotify::configured(&settings) {
+        return Err(AppError::conflict("Set music.spotify.client_id first"));
+    }
+    let authorization = spotify::authorize(&settings).map_err(AppError::internal)?;
+    info!("Spotify: waiting for approval, redirecting to {}", authorization.redirect_uri);
+    Ok(Json(authorization))
+}
+
+/// Query parameters Spotify redirects the browser back with
+#[derive(Debug, Deserialize)]
+pub struct SpotifyCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
+/// GET /api/spotify/callback - Where Spotify sends the browser after approving; open to
+/// everyone as a redirect can't carry a token, the state ties it to the approval asked for
+async fn spotify_callback(State(state): State<AppState>, Query(query): Query<SpotifyCallbackQuery>) -> Response {
+    let result = match (query.error, query.code, query.state) {
+        (Some(error), _, _) => Err(format!("Spotify refused: {}", error)),
+        (None, Some(code), Some(approval)) => match state.settings_manager.get() {
+            Ok(settings) => spotify::finish(&settings, &code, &approval).await,
+            Err(e) => Err(e),
+        },
+        _ => Err("The address has no code and state".to_string()),
+    };
+    let (status, text) = match result {
+        Ok(()) => (StatusCode::OK, "Spotify is connected. You can close this page.".to_string()),
+        Err(e) => (StatusCode::BAD_REQUEST, e.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")),
+    };
+    let page = format!("<!DOCTYPE html><meta charset=\"utf-8\"><title>Idleview</title><p>{}</p>", text);
+    (status, Html(page)).into_response()
+}
+
+/// POST /api/spotify/callback - Finish connecting with the address Spotify redirected to,
+/// when the browser couldn't reach the frame
+async fn post_spotify_callback(
+    State(state): State<AppState>,
+    Json(request): Json<SpotifyCallbackRequest>,
+) -> Result<StatusCode, AppError> {
+    let (code, approval) = spotify::callback_params(&request.url).map_err(AppError::bad_request)?;
+    let settings = state.settings_manager.get().map_err(AppError::internal)?;
+    spotify::finish(&settings, &code, &approval).await.map_err(AppError::bad_request)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// DELETE /api/spotify - Forget the connected Spotify account
+async fn disconnect_spotify() -> Result<StatusCode, AppError> {
+    if !spotify::disconnect().map_err(AppError::internal)? {
+        return Err(AppError::not_found("No Spotify account connected"));
+    }
+    info!("Spotify: disconnected");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/sync - This frame's sync role and, when following, the lead it follows
+async fn get_sync_status(State(state): State<AppState>) -> Result<Json<SyncStatus>, AppError> {
+    let settings = state.settings_manager.get().map_err(AppError::internal)?;
+    Ok(Json(sync::status(settings.sync.role)))
+}
+
+/// GET /api/photo/current - Return current photo information
+async fn get_current_photo(State(state): State<AppState>) -> Result<Json<Option<CurrentPhoto>>, AppError> {
+    let photo = state.current_photo
+        .lock()
+        .map_err(|e| AppError::internal(format!("Failed to lock photo state: {}", e)))?;
+    Ok(Json(photo.clone()))
+}
+
+/// POST /api/photo/current - Update current photo information
+async fn update_current_photo(
+    State(state): State<AppState>,
+    Json(photo): Json<CurrentPhoto>,
+) -> Result<Json<CurrentPhoto>, AppError> {
+    state.set_current_photo_from(photo.clone(), "http").map_err(AppError::internal)?;
+    Ok(Json(photo))
+}
+
+/// POST /api/photo/next - Skip to a new photo. The frame fetches and shows it,
+/// then reports it with a `photo-updated` event.
+async fn next_photo(State(state): State<AppState>) -> StatusCode {
+    state.publish(ServerEvent::RefreshPhoto);
+    info!("Next photo requested");
+    StatusCode::ACCEPTED
+}
+
+/// POST /api/message - Show a short message over the frame, after any already waiting
+async fn post_message(
+    State(state): State<AppState>,
+    Json(request): Json<MessageRequest>,
+) -> Result<(StatusCode, Json<QueuedMessage>), AppError> {
+    let problems = messages::validate_message(&request);
+    if !problems.is_empty() {
+        return Err(AppError::bad_request(problems.join("; ")));
+    }
+    let queued = state.messages.push(request).map_err(AppError::conflict)?;
+    Ok((StatusCode::ACCEPTED, Json(queued)))
+}
+
+/// GET /api/timer - The running timer, null when none runs
+async fn get_timer() -> Json<Option<Timer>> {
+    Json(timer::current())
+}
+
+/// POST /api/timer - Start a timer, cancelling the one running
+async fn start_timer(
+    State(state): State<AppState>,
+    Json(request): Json<TimerRequest>,
+) -> Result<(StatusCode, Json<Timer>), AppError> {
+    let problems = timer::validate_timer(&request);
+    if !problems.is_empty() {
+        return Err(AppError::bad_request(problems.join("; ")));
+    }
+    let started = timer::start(&state, request).map_err(AppError::internal)?;
+    Ok((StatusCode::CREATED, Json(started)))
+}
+
+/// DELETE /api/timer - Stop the running timer
+async fn cancel_timer(State(state): State<AppState>) -> Result<StatusCode, AppError> {
+    match timer::cancel(&state).map_err(AppError::internal)? {
+        Some(_) => Ok(StatusCode::NO_CONTENT),
+        None => Err(AppError::not_found("No timer is running")),
+    }
+}
+
+/// POST /api/refresh - Have the frame re-fetch location, weather and photo now
+async fn refresh_all(State(state): State<AppState>) -> Result<StatusCode, AppError> {
+    state
+        .app_handle
+        .emit("refresh-all", ())
+        .map_err(|e| AppError::internal(format!("Failed to reach the frame: {}", e)))?;
+    info!("Full refresh requested");
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Request body for switching the active saved location
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ActiveLocationRequest {
+    pub name: Option<String>,  // None switches back to auto-detection
+}
+
+/// POST /api/location/active - Switch the active saved location
+async fn set_active_location(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Json(request): Json<ActiveLocationRequest>,
+) -> Result<Json<Settings>, AppError> {
+    let current = state.settings_manager.get().map_err(AppError::internal)?;
+
+    if let Some(name) = &request.name {
+        if !current.location.saved.iter().any(|location| &location.name == name) {
+            return Err(AppError::not_found(format!("Unknown saved location: {}", name)));
+        }
+    }
+
+    let updates = json!({ "location": { "active": request.name } });
+    match state.settings_manager.update_partial_from(updates, ChangeSource::http(addr)) {
+        Ok(settings) => {
+            info!("Active location set to {:?}", settings.location.active);
+            state.publish(ServerEvent::SettingsUpdated(Box::new(settings.clone())));
+            state.publish(ServerEvent::LocationChanged {
+                active: settings.location.active.clone(),
+            });
+            settings_manager::redacted(&settings).map(Json).map_err(AppError::internal)
+        }
+        Err(e) => {
+            error!("Failed to set active location: {}", e);
+            Err(AppError::internal(e))
+        }
+    }
+}
+
+/// Whether weather fetched at `fetched_at` is younger than the refresh interval (in minutes)
+pub fn weather_is_fresh(fetched_at: u64, now_ms: u64, refresh_interval: u64) -> bool {
+    now_ms.saturating_sub(fetched_at) < refresh_interval.saturating_mul(60_000)
+}
+
+/// GET /api/weather - The weather the frame shows, refetched once older than the refresh interval
+async fn get_weather(State(state): State<AppState>) -> Result<Json<WeatherReport>, AppError> {
+    let settings = state.settings_manager.get().map_err(AppError::internal)?;
+    let cached = state
+        .weather
+        .lock()
+        .map_err(|e| AppError::internal(format!("Failed to lock weather state: {}", e)))?
+        .clone();
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+    if let Some(report) = &cached {
+        if weather_is_fresh(report.fetched_at, now, settings.weather.refresh_interval) {
+            return Ok(Json(report.clone()));
+        }
+    }
+
+    let fetched = async {
+        let location = crate::resolve_location(&settings).await?;
+        crate::fetch_weather_for(&settings, location.latitude, location.longitude, location.elevation).await
+    }
+    .await;
+    match (fetched, cached) {
+        (Ok(weather), _) => Ok(Json(state.set_weather_from(weather, "http"))),
+        // An old reading beats none while the network is down
+        (Err(e), Some(report)) => {
+            error!("Failed to refresh weather, serving cached: {}", e);
+            Ok(Json(WeatherReport { stale: true, ..report }))
+        }
+        (Err(e), None) => {
+            error!("Failed to fetch weather: {}", e);
+            Err(AppError::internal(e))
+        }
+    }
+}
+
+/// Query parameters for the weather legend
+#[derive(Debug, Deserialize)]
+pub struct LegendQuery {
+    pub lang: Option<String>,  // BCP-47 tag, defaults to the language setting
+}
+
+/// GET /api/weather/legend - WMO weather code to icon/label mapping
+async fn get_weather_legend(State(state): State<AppState>, Query(query): Query<LegendQuery>) -> Json<WeatherLegend> {
+    let language = query
+        .lang
+        .unwrap_or_else(|| state.settings_manager.get().map(|s| s.language).unwrap_or_else(|_| "en".to_string()));
+    Json(weather_codes::get_weather_legend_impl(&language))
+}
+
+/// Query parameters for listing scenes
+#[derive(Debug, Deserialize)]
+pub struct ScenesQuery {
+    pub tag: Option<String>,
+}
+
+/// GET /api/scenes - List scenes, optionally filtered by tag
+async fn list_scenes(
+    State(state): State<AppState>,
+    Query(query): Query<ScenesQuery>,
+) -> Result<Json<Vec<Scene>>, AppError> {
+    let settings = state.settings_manager.get().map_err(AppError::internal)?;
+    let scenes = scenes::scenes_with_tag(&settings.scenes, query.tag.as_deref())
+        .into_iter()
+        .cloned()
+        .collect();
+    Ok(Json(scenes))
+}
+
+/// POST /api/scenes/:name/activate - Activate a scene and apply its overrides
+async fn activate_scene(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<Settings>, AppError> {
+    let current = state.settings_manager.get().map_err(AppError::internal)?;
+    if scenes::find_scene(&current.scenes, &name).is_none() {
+        return Err(AppError::not_found(format!("Unknown scene: {}", name)));
+    }
+
+    match scenes::activate_scene(&state.settings_manager, &name) {
+        Ok(settings) => {
+            info!("Scene '{}' activated", name);
+            state.publish(ServerEvent::SettingsUpdated(Box::new(settings.clone())));
+            settings_manager::redacted(&settings).map(Json).map_err(AppError::internal)
+        }
+        Err(e) => {
+            error!("Failed to activate scene: {}", e);
+            Err(AppError::internal(e))
+        }
+    }
+}
+
+/// POST /api/scenes/deactivate - Clear the active scene
+async fn deactivate_scene(State(state): State<AppState>) -> Result<Json<Settings>, AppError> {
+    match scenes::deactivate_scene(&state.settings_manager) {
+        Ok(settings) => {
+            info!("Scene deactivated");
+            state.publish(ServerEvent::SettingsUpdated(Box::new(settings.clone())));
+            settings_manager::redacted(&settings).map(Json).map_err(AppError::internal)
+        }
+        Err(e) => {
+            error!("Failed to deactivate scene: {}", e);
+            Err(AppError::internal(e))
+        }
+    }
+}
+
+/// GET /api/events - Server-Sent Events stream for real-time updates.
+/// Carries the same events as `/api/ws` for clients that can't use WebSockets;
+/// both are fed by the event hub in `AppState`.
+async fn events_stream(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    // The subscription lives inside the stream and unregisters when the client disconnects
+    let subscription = state.event_hub.subscribe("sse");
+    
+    let stream = stream! {
+        loop {
+            let event_data = subscription.recv().await;
+            yield Ok(Event::default().data(&*event_data));
+        }
+    };
+    
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// GET /api/ws - WebSocket pushing the same events as `/api/events`
+async fn events_websocket(State(state): State<AppState>, upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(move |socket| forward_events(socket, state))
+}
+
+/// Send every published event to the socket until the client goes away.
+/// Messages from the client are ignored.
+async fn forward_events(mut socket: WebSocket, state: AppState) {
+    let subscription = state.event_hub.subscribe("websocket");
+    loop {
+        tokio::select! {
+            event_data = subscription.recv() => {
+                if socket.send(Message::Text(event_data.to_string())).await.is_err() {
+                    break;
+                }
+            }
+            message = socket.recv() => {
+                match message {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                }
+            }
+        }
+    }
+}
+
+/// GET /api/system - CPU temperature and usage, memory, disk space, uptime and app version
+async fn get_system_status() -> Result<Json<SystemStatus>, AppError> {
+    // Reading sensors and disks touches the filesystem
+    tokio::task::spawn_blocking(system::system_status)
+        .await
+        .map(Json)
+        .map_err(|e| AppError::internal(format!("Failed to read system status: {}", e)))
+}
+
+/// Reboot or shut down the machine, when the settings allow it
+async fn power_action(state: &AppState, action: PowerAction) -> Result<StatusCode, AppError> {
+    let settings = state.settings_manager.get().map_err(AppError::internal)?;
+    if !settings.server.allow_power_control {
+        return Err(AppError::forbidden(
+            "Power control is disabled; enable server.allow_power_control in the settings first",
+        ));
+    }
+
+    // Don't lose a settings change still waiting to be written
+    state.settings_manager.flush();
+    info!("🔌 {} requested via API", action.as_str());
+    tokio::task::spawn_blocking(move || power::request(action))
+        .await
+        .map_err(|e| AppError::internal(format!("Failed to {}: {}", action.as_str(), e)))?
+        .map_err(AppError::internal)?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// POST /api/system/reboot - Restart the machine
+async fn reboot_system(State(state): State<AppState>) -> Result<StatusCode, AppError> {
+    power_action(&state, PowerAction::Reboot).await
+}
+
+/// POST /api/system/shutdown - Turn the machine off
+async fn shutdown_system(State(state): State<AppState>) -> Result<StatusCode, AppError> {
+    power_action(&state, PowerAction::Shutdown).await
+}
+
+/// POST /api/system/check-update - Ask GitHub for the latest release now
+async fn check_update(State(state): State<AppState>) -> Result<Json<UpdateInfo>, AppError> {
+    updates::check_for_update(&state).await.map(Json).map_err(AppError::bad_gateway)
+}
+
+/// GET /api/debug - The debug overlay's details, for troubleshooting from another machine
+async fn get_debug_info(State(state): State<AppState>) -> Result<Json<DebugInfo>, AppError> {
+    let settings = state.settings_manager.get().map_err(AppError::internal)?;
+    let photo = state
+        .current_photo
+        .lock()
+        .map_err(|e| AppError::internal(format!("Failed to lock photo state: {}", e)))?
+        .clone();
+    let weather = state
+        .weather
+        .lock()
+        .map_err(|e| AppError::internal(format!("Failed to lock weather state: {}", e)))?
+        .clone()
+        .map(|report| report.weather);
+
+    Ok(Json(crate::debug_info_impl(
+        &settings,
+        photo.as_ref().and_then(|photo| photo.fetched_at),
+        photo.and_then(|photo| photo.query),
+        weather.as_ref().map(|weather| weather.sunrise.clone()),
+        weather.as_ref().map(|weather| weather.sunset.clone()),
+        weather.as_ref().map(|weather| weather.temperature),
+        weather.as_ref().map(|weather| weather.rain),
+        weather.as_ref().map(|weather| weather.snowfall),
+        weather.as_ref().map(|weather| weather.cloudcover),
+    )))
+}
+
+/// GET /api/display/power - Whether the screen is on
+async fn get_display_power() -> Result<Json<DisplayPower>, AppError> {
+    // Asking the display server runs a command
+    tokio::task::spawn_blocking(|| DisplayPower { power: display_power::power_state() })
+        .await
+        .map(Json)
+        .map_err(|e| AppError::internal(format!("Failed to read display power: {}", e)))
+}
+
+/// POST /api/display/power - Turn the screen on or off
+async fn set_display_power(
+    State(state): State<AppState>,
+    Json(request): Json<DisplayPower>,
+) -> Result<Json<DisplayPower>, AppError> {
+    tokio::task::spawn_blocking(move || display_power::set_power(request.power))
+        .await
+        .map_err(|e| AppError::internal(format!("Failed to set display power: {}", e)))?
+        .map_err(AppError::internal)?;
+    state.publish(ServerEvent::DisplayPowerChanged(request));
+    if request.power == PowerState::On {
+        display_sleep::woken_by_hand(&state);
+    }
+    Ok(Json(request))
+}
+
+/// GET /api/display/sleep - Whether the sleep schedule has the screen off
+async fn get_display_sleep(State(state): State<AppState>) -> Result<Json<DisplaySleep>, AppError> {
+    let settings = state.settings_manager.get().map_err(AppError::internal)?;
+    Ok(Json(display_sleep::status(&settings.display.sleep)))
+}
+
+/// GET /api/monitors - Attached monitors and the frame window on each
+async fn list_monitors(State(state): State<AppState>) -> Result<Json<Vec<MonitorInfo>>, AppError> {
+    monitors::list(&state.app_handle).map(Json).map_err(AppError::internal)
+}
+
+/// GET /api/connectivity - Whether the frame is online, and when it last was
+async fn get_connectivity() -> Json<Connectivity> {
+    Json(connectivity::status())
+}
+
+/// GET /api/watchdog - Frame heartbeats and the times the watchdog had to step in
+async fn get_watchdog() -> Json<WatchdogStatus> {
+    Json(watchdog::status())
+}
+
+/// GET /api/presence - Whether someone is in the room
+async fn get_presence() -> Json<Presence> {
+    Json(presence::status())
+}
+
+/// POST /api/presence - Motion from a smart-home sensor; wakes the screen
+async fn report_presence(State(state): State<AppState>) -> Result<Json<Presence>, AppError> {
+    presence::motion(&state, "http").await.map(Json).map_err(AppError::conflict)
+}
+
+/// GET /api/display/brightness - Screen brightness and whether it's the backlight or software dimming
+async fn get_brightness() -> Result<Json<Brightness>, AppError> {
+    tokio::task::spawn_blocking(brightness::brightness)
+        .await
+        .map(Json)
+        .map_err(|e| AppError::internal(format!("Failed to read brightness: {}", e)))
+}
+
+/// PUT /api/display/brightness - Set the screen brightness in percent
+async fn set_brightness(
+    State(state): State<AppState>,
+    Json(request): Json<BrightnessRequest>,
+) -> Result<Json<Brightness>, AppError> {
+    if request.percent > 100 {
+        return Err(AppError::bad_request("percent must be between 0 and 100"));
+    }
+    let app_handle = state.app_handle.clone();
+    tokio::task::spawn_blocking(move || brightness::set_brightness(&app_handle, request.percent))
+        .await
+        .map_err(|e| AppError::internal(format!("Failed to set brightness: {}", e)))?
+        .map(Json)
+        .map_err(AppError::internal)
+}
+
+/// GET /api/display/night - Whether night mode is on
+async fn get_night_mode(State(state): State<AppState>) -> Result<Json<NightMode>, AppError> {
+    let settings = state.settings_manager.get().map_err(AppError::internal)?;
+    Ok(Json(night_mode::status(&settings.display.night_mode)))
+}
+
+/// Query parameters for a screenshot
+#[derive(Deserialize)]
+pub struct ScreenshotQuery {
+    /// Downscale factor, between 0 and 1
+    scale: Option<f32>,
+}
+
+/// GET /api/screenshot - PNG of what the frame window is showing, optionally downscaled
+async fn get_screenshot(
+    State(state): State<AppState>,
+    Query(query): Query<ScreenshotQuery>,
+) -> Result<Response, AppError> {
+    let scale = query.scale.unwrap_or(1.0);
+    if !(scale > 0.0 && scale <= 1.0) {
+        return Err(AppError::bad_request("scale must be greater than 0 and at most 1"));
+    }
+
+    let rect = screenshot::window_rect(&state.app_handle).map_err(AppError::internal)?;
+    // Screenshot tools and PNG encoding both block
+    let png = tokio::task::spawn_blocking(move || {
+        let png = screenshot::capture(&rect)?;
+        if scale < 1.0 {
+            screenshot::downscale(&png, scale)
+        } else {
+            Ok(png)
+        }
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Failed to capture screenshot: {}", e)))?
+    .map_err(AppError::internal)?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "image/png"), (header::CACHE_CONTROL, "no-store")],
+        png,
+    )
+        .into_response())
+}
+
+/// GET /api/server/clients - Connected event clients with queue/lag statistics
+async fn list_event_clients(State(state): State<AppState>) -> Json<Vec<ClientStats>> {
+    Json(state.event_hub.client_stats())
+}
+
+/// Routes that change something but are open to everyone, as they are how a device gets a token
+const PUBLIC_ROUTES: &[&str] = &["/pair/start", "/pair"];
+
+/// Routes that only read but still need a token, as the screen can show a
+/// pairing PIN and a backup holds the credentials other reads redact
+pub const PROTECTED_READS: &[&str] = &["/screenshot", "/calendar", "/calendar/google", "/tasks", "/settings/backup"];
+
+/// Whether a request to `path` under the API needs a token: anything that
+/// changes something, and the protected reads
+pub fn needs_api_token(method: &Method, path: &str) -> bool {
+    let read_only = matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS);
+    !((read_only && !PROTECTED_READS.contains(&path)) || PUBLIC_ROUTES.contains(&path))
+}
+
+/// Reject requests that change something unless they carry the API token, or
+/// a paired device's token, as a Bearer header
+async fn require_api_token(request: Request, next: Next) -> Result<Response, AppError> {
+    if !needs_api_token(request.method(), request.uri().path()) {
+        return Ok(next.run(request).await);
+    }
+
+    let authorization = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    if !auth::is_authorized(authorization).map_err(AppError::internal)? {
+        return Err(AppError::unauthorized("Missing or invalid API token"));
+    }
+    Ok(next.run(request).await)
+}
+
+/// Count API calls for /metrics, labelled with the route pattern they matched
+async fn track_request(request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_default();
+    let response = next.run(request).await;
+    metrics::global().record_request(&method, &route, response.status().as_u16());
+    response
+}
+
+/// GET /metrics - Prometheus metrics
+async fn get_metrics(State(state): State<AppState>) -> Result<Response, AppError> {
+    let queued_events = state.event_hub.client_stats().iter().map(|client| client.queued).sum();
+    let queued_messages = state.messages.len();
+    // Reading sensors and the config directory touches the filesystem
+    let gauges = tokio::task::spawn_blocking(move || metrics::Gauges {
+        uptime: health::uptime(),
+        cpu_temperature: thermal::cpu_temperature(),
+        gpu_temperature: thermal::gpu_temperature(),
+        memory: Some(system::memory_status()),
+        config_dir_bytes: metrics::config_dir_bytes(),
+        queued_events,
+        queued_messages,
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Failed to collect metrics: {}", e)))?;
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        metrics::global().render(&gauges),
+    )
+        .into_response())
+}
+
+/// Whether a peer may use the server with the given bind setting
+pub fn peer_allowed(bind: ServerBind, peer: IpAddr) -> bool {
+    bind == ServerBind::All || peer.to_canonical().is_loopback()
+}
+
+/// Turn away other machines in localhost-only mode. The listener already
+/// binds to 127.0.0.1, but connections opened before switching stay alive
+/// while the old listener drains.
+async fn localhost_only(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let bind = state.settings_manager.get().map_err(AppError::internal)?.server.bind;
+    if !peer_allowed(bind, addr.ip()) {
+        return Err(AppError::forbidden("The server only accepts connections from this device"));
+    }
+    Ok(next.run(request).await)
+}
+
+/// Record requests in the access log, as verbose as the server settings say
+async fn access_log(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let level = state
+        .settings_manager
+        .get()
+        .map(|settings| settings.server.access_log)
+        .unwrap_or_default();
+    if level == AccessLogLevel::Off {
+        return next.run(request).await;
+    }
+
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let started = std::time::Instant::now();
+    let response = next.run(request).await;
+
+    let read_only = matches!(method, Method::GET | Method::HEAD | Method::OPTIONS);
+    let status = response.status().as_u16();
+    if access_log::should_log(level, read_only, status) {
+        let entry = AccessLogEntry {
+            timestamp: chrono::Utc::now().timestamp_millis() as u64,
+            method: method.to_string(),
+            path,
+            status,
+            latency_ms: started.elapsed().as_millis() as u64,
+            client: addr.ip().to_string(),
+        };
+        // Writing is quick, but don't hold up the runtime on a slow SD card
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = access_log::record(&entry) {
+                error!("{}", e);
+            }
+        });
+    }
+    response
+}
+
+/// Answer 429 once a client IP has used up its requests for the minute
+async fn rate_limit(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let limits = state
+        .settings_manager
+        .get()
+        .map(|settings| settings.server.rate_limit)
+        .unwrap_or_default();
+    if !limits.enabled {
+        return next.run(request).await;
+    }
+
+    let (kind, per_minute) = match *request.method() {
+        Method::GET | Method::HEAD | Method::OPTIONS => (RequestKind::Read, limits.reads_per_minute),
+        _ => (RequestKind::Write, limits.writes_per_minute),
+    };
+    match state.rate_limiter.check(addr.ip(), kind, per_minute, std::time::Instant::now()) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => {
+            info!("Rate limited {} {} from {}", request.method(), request.uri().path(), addr.ip());
+            let retry_after = retry_after.as_secs().max(1);
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after.to_string())],
+                Json(json!({ "error": "Too many requests", "retry_after": retry_after })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Refuse requests asking for an API version this server doesn't speak, and
+/// label every response with the version that answered it
+async fn negotiate_version(request: Request, next: Next) -> Response {
+    if let Some(requested) = request.headers().get(API_VERSION_HEADER) {
+        let requested = requested.to_str().unwrap_or_default().trim().to_string();
+        if requested.parse::<u32>().ok() != Some(API_VERSION) {
+            let body = json!({
+                "error": format!("Unsupported API version: {}", requested),
+                "supported": [API_VERSION],
+            });
+            return (StatusCode::NOT_ACCEPTABLE, Json(body)).into_response();
+        }
+    }
+
+    let mut response = next.run(request).await;
+    response
+        .headers_mut()
+        .insert(HeaderName::from_static("x-api-version"), HeaderValue::from(API_VERSION));
+    response
+}
+
+/// Mark responses on the unversioned `/api` alias as deprecated
+async fn deprecated_alias(request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    response
+        .headers_mut()
+        .insert(HeaderName::from_static("deprecation"), HeaderValue::from_static("true"));
+    response
+}
+
+/// POST /api/pair/start - Show a pairing PIN on the frame
+async fn start_pairing(State(state): State<AppState>) -> Result<Json<PairingStarted>, AppError> {
+    let Some(pin) = pairing::start_pairing().map_err(AppError::internal)? else {
+        return Err(AppError::too_many_requests("Too many wrong PINs, try pairing again later"));
+    };
+    let expires_in = pin.expires_in;
+    // A private event: only the frame's own window gets the PIN, never the event stream
+    state.publish(ServerEvent::PairingPin(pin));
+    info!("Pairing started, PIN shown on the frame");
+    Ok(Json(PairingStarted { expires_in }))
+}
+
+/// POST /api/pair - Exchange the PIN shown on the frame for a device token
+async fn pair_device(
+    State(state): State<AppState>,
+    Json(request): Json<PairRequest>,
+) -> Result<(StatusCode, Json<PairingResult>), AppError> {
+    if !pairing::redeem_pin(&request.pin).map_err(AppError::internal)? {
+        return Err(AppError::unauthorized("Invalid or expired PIN"));
+    }
+
+    let result = pairing::pair_device(request.name).map_err(AppError::internal)?;
+    state.publish(ServerEvent::PairingComplete(result.device.clone()));
+    info!("Device '{}' paired", result.device.name);
+    Ok((StatusCode::CREATED, Json(result)))
+}
+
+/// GET /api/pair - List paired devices
+async fn list_paired_devices() -> Result<Json<Vec<PairedDevice>>, AppError> {
+    pairing::paired_devices().map(Json).map_err(AppError::internal)
+}
+
+/// DELETE /api/pair/:id - Revoke a paired device's token
+async fn unpair_device(Path(id): Path<String>) -> Result<StatusCode, AppError> {
+    if !pairing::unpair_device(&id).map_err(AppError::internal)? {
+        return Err(AppError::not_found(format!("Unknown device: {}", id)));
+    }
+    info!("Device {} unpaired", id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /api/auth/rotate - Replace the API token and return the new one
+async fn rotate_api_token() -> Result<Json<serde_json::Value>, AppError> {
+    let token = auth::rotate_api_token().map_err(AppError::internal)?;
+    info!("API token rotated");
+    Ok(Json(json!({ "token": token })))
+}
+
+/// GET /api/openapi.json - OpenAPI description of this API
+async fn get_openapi() -> Json<serde_json::Value> {
+    Json(openapi::openapi_document())
+}
+
+/// GET /api/docs - Swagger UI for the OpenAPI description
+async fn api_docs() -> Html<&'static str> {
+    Html(openapi::DOCS_PAGE)
+}
+
+/// GET /description.xml - UPnP device description for SSDP discovery
+async fn ssdp_description(State(state): State<AppState>) -> Result<Response, AppError> {
+    let settings = state.settings_manager.get().map_err(AppError::internal)?;
+    let panel = ssdp::panel_url(&settings.server).unwrap_or_default();
+    let description = ssdp::device_description(ssdp::device_uuid(), &format!("{}/", panel));
+    Ok(([(header::CONTENT_TYPE, "text/xml; charset=utf-8")], description).into_response())
+}
+
+/// Create the router with all routes
+fn create_router(state: AppState) -> Router {
+    // API routes
+    let api_routes = Router::new()
+        .route("/settings", get(get_settings))
+        .route("/settings", put(update_settings))
+        .route("/settings", patch(patch_settings))
+        .route("/settings/reset", post(reset_settings))
+        .route("/settings/backup", get(backup_settings))
+        .route("/settings/restore", post(restore_settings))
+        .route("/settings/history", get(get_settings_history))
+        .route("/settings/defaults", get(get_settings_defaults))
+        .route("/settings/schema", get(get_settings_schema))
+        .route("/settings/profiles", get(list_profiles))
+        .route("/settings/profiles", post(create_profile))
+        .route("/settings/profiles/deactivate", post(deactivate_profile))
+        .route("/settings/profiles/:name", get(get_profile))
+        .route("/settings/profiles/:name", put(update_profile))
+        .route("/settings/profiles/:name", delete(delete_profile))
+        .route("/settings/profiles/:name/activate", post(activate_profile))
+        .route("/alarms", get(list_alarms))
+        .route("/alarms", post(create_alarm))
+        .route("/alarms/:id", get(get_alarm))
+        .route("/alarms/:id", put(update_alarm))
+        .route("/alarms/:id", delete(delete_alarm))
+        .route("/settings/:section", get(get_settings_section))
+        .route("/settings/:section", patch(patch_settings_section))
+        .route("/photo/current", get(get_current_photo))
+        .route("/photo/current", post(update_current_photo))
+        .route("/photo/next", post(next_photo))
+        .route("/refresh", post(refresh_all))
+        .route("/message", post(post_message))
+        .route("/timer", get(get_timer))
+        .route("/timer", post(start_timer))
+        .route("/timer", delete(cancel_timer))
+        .route("/location/active", post(set_active_location))
+        .route("/weather", get(get_weather))
+        .route("/weather/legend", get(get_weather_legend))
+        .route("/scenes", get(list_scenes))
+        .route("/scenes/deactivate", post(deactivate_scene))
+        .route("/scenes/:name/activate", post(activate_scene))
+        .route("/events", get(events_stream))
+        .route("/ws", get(events_websocket))
+        .route("/system", get(get_system_status))
+        .route("/system/reboot", post(reboot_system))
+        .route("/system/shutdown", post(shutdown_system))
+        .route("/system/check-update", post(check_update))
+        .route("/debug", get(get_debug_info))
+        .route("/screenshot", get(get_screenshot))
+        .route("/display/power", get(get_display_power))
+        .route("/display/power", post(set_display_power))
+        .route("/display/brightness", get(get_brightness))
+        .route("/display/brightness", put(set_brightness))
+        .route("/display/night", get(get_night_mode))
+        .route("/display/sleep", get(get_display_sleep))
+        .route("/monitors", get(list_monitors))
+        .route("/presence", get(get_presence))
+        .route("/presence", post(report_presence))
+        .route("/server/clients", get(list_event_clients))
+        .route("/auth/rotate", post(rotate_api_token))
+        .route("/pair", get(list_paired_devices))
+        .route("/pair", post(pair_device))
+        .route("/pair/start", post(start_pairing))
+        .route("/pair/:id", delete(unpair_device))
+        .route("/health", get(health_check))
+        .route("/watchdog", get(get_watchdog))
+        .route("/connectivity", get(get_connectivity))
+        .route("/sync", get(get_sync_status))
+        .route("/calendar", get(get_calendar))
+        .route("/calendar/google", get(get_google_calendar))
+        .route("/calendar/google", delete(disconnect_google_calendar))
+        .route("/calendar/google/connect", post(connect_google_calendar))
+        .route("/calendar/caldav/:name/password", put(set_caldav_password))
+        .route("/quote", get(get_quote))
+        .route("/news", get(get_news))
+        .route("/stocks", get(get_stocks))
+        .route("/crypto", get(get_crypto))
+        .route("/sports", get(get_sports))
+        .route("/tasks", get(get_tasks))
+        .route("/now-playing", get(get_now_playing))
+        .route("/media/devices", get(get_media_devices))
+        .route("/spotify", get(get_spotify))
+        .route("/spotify", delete(disconnect_spotify))
+        .route("/spotify/connect", post(connect_spotify))
+        .route("/spotify/callback", get(spotify_callback))
+        .route("/spotify/callback", post(post_spotify_callback))
+        .route("/openapi.json", get(get_openapi))
+        .route("/docs", get(api_docs))
+        .route_layer(middleware::from_fn(track_request))
+        .layer(middleware::from_fn(require_api_token))
+        .layer(middleware::from_fn_with_state(state.clone(), rate_limit))
+        .layer(middleware::from_fn(negotiate_version));
+
+    // CORS follows the server settings at request time, so no restart is needed
+    let settings_manager = state.settings_manager.clone();
+    let cors = CorsLayer::new()
+        .allow_origin(AllowOrigin::predicate(move |origin, _| {
+            let allowed = settings_manager
+                .get()
+                .map(|settings| settings.server.cors_origins)
+                .unwrap_or_default();
+            origin.to_str().is_ok_and(|origin| cors_origin_allowed(origin, &allowed))
+        }))
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE])
+        .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE]);
+
+    // Compression also follows the settings at request time; event streams and
+    // images are never compressed
+    let compression = CompressionLayer::new()
+        .compress_when(DefaultPredicate::new().and(CompressionEnabled(state.settings_manager.clone())));
+
+    // Build the main router
+    Router::new()
+        .nest("/api/v1", api_routes.clone())
+        // Unversioned alias so existing control panels and scripts keep working
+        .nest("/api", api_routes.layer(middleware::from_fn(deprecated_alias)))
+        .route("/description.xml", get(ssdp_description))
+        .route("/metrics", get(get_metrics))
+        // Everything else is the control panel
+        .fallback(control_panel::serve)
+        .layer(
+            ServiceBuilder::new()
+                .layer(TraceLayer::new_for_http())
+                .layer(cors)
+                .layer(compression),
+        )
+        .layer(middleware::from_fn_with_state(state.clone(), localhost_only))
+        .layer(middleware::from_fn_with_state(state.clone(), access_log))
+        .with_state(state)
+}
+
+/// Compress responses only while the server settings allow it
+#[derive(Clone)]
+struct CompressionEnabled(SettingsManager);
+
+impl Predicate for CompressionEnabled {
+    fn should_compress<B>(&self, _response: &axum::http::Response<B>) -> bool
+    where
+        B: axum::body::HttpBody,
+    {
+        self.0.get().map(|settings| settings.server.compression).unwrap_or(true)
+    }
+}
+
+/// Whether a browser origin may call the API. With no configured origins,
+/// pages served from localhost or the local network are allowed.
+pub fn cors_origin_allowed(origin: &str, allowed: &[String]) -> bool {
+    if !allowed.is_empty() {
+        return allowed.iter().any(|entry| entry == "*" || entry.trim_end_matches('/') == origin);
+    }
+
+    let Some(host) = reqwest::Url::parse(origin).ok().and_then(|url| url.host_str().map(str::to_string)) else {
+        return false;
+    };
+    match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => ip.is_private() || ip.is_loopback() || ip.is_link_local(),
+        // Loopback, unique local (fc00::/7) and link-local (fe80::/10)
+        Ok(IpAddr::V6(ip)) => {
+            ip.is_loopback() || (ip.segments()[0] & 0xfe00) == 0xfc00 || (ip.segments()[0] & 0xffc0) == 0xfe80
+        }
+        Err(_) => host == "localhost" || host.ends_with(".local"),
+    }
+}
+
+/// Get local IP addresses for display
+fn get_local_ips() -> Vec<String> {
+    let mut ips = vec!["127.0.0.1".to_string()];
+    
+    if let Ok(local_ip) = local_ip_address::local_ip() {
+        ips.push(local_ip.to_string());
+    }
+    
+    ips
+}
+
+/// The address to listen on for the server settings
+pub fn server_addr(server: &ServerSettings) -> SocketAddr {
+    match server.bind {
+        // 0.0.0.0 accepts connections from the local network
+        ServerBind::All => SocketAddr::from(([0, 0, 0, 0], server.port)),
+        ServerBind::Localhost => SocketAddr::from(([127, 0, 0, 1], server.port)),
+    }
+}
+
+/// Whether the settings the listener was started with changed. CORS and
+/// rate limits are read per request and don't need a restart.
+fn listener_changed(server: &ServerSettings, current: &ServerSettings) -> bool {
+    server.enabled != current.enabled
+        || server.port != current.port
+        || server.bind != current.bind
+        || server.tls != current.tls
+}
+
+/// Wait until the listener's server settings differ from `current`
+pub(crate) async fn server_settings_changed(changes: &mut watch::Receiver<Settings>, current: &ServerSettings) {
+    loop {
+        if changes.changed().await.is_err() {
+            // The settings can no longer change
+            std::future::pending::<()>().await;
+        }
+        if listener_changed(&changes.borrow_and_update().server, current) {
+            return;
+        }
+    }
+}
+
+/// Why the listener is being stopped
+#[derive(Debug, PartialEq, Eq)]
+enum Stop {
+    Restart,
+    Shutdown,
+}
+
+/// Wait until the listener has to restart for new settings, or the app is quitting
+async fn wait_for_stop(
+    changes: &mut watch::Receiver<Settings>,
+    current: &ServerSettings,
+    shutdown: &mut watch::Receiver<bool>,
+) -> Stop {
+    tokio::select! {
+        _ = server_settings_changed(changes, current) => Stop::Restart,
+        _ = shutdown.wait_for(|requested| *requested) => Stop::Shutdown,
+    }
+}
+
+/// Bind, retrying briefly while a previous listener on the same address shuts down
+async fn bind_listener(addr: SocketAddr) -> std::io::Result<tokio::net::TcpListener> {
+    let mut attempts = 0;
+    loop {
+        match tokio::net::TcpListener::bind(addr).await {
+            Err(e) if e.kind() == std::io::ErrorKind::AddrInUse && attempts < 10 => {
+                attempts += 1;
+                tokio::time::sleep(Duration::from_millis(200)).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Start the HTTP server. It listens according to the `server` settings,
+/// restarts its listener when they change and returns once `shutdown` is set.
+pub async fn start_server(
+    app_handle: tauri::AppHandle,
+    settings_manager: SettingsManager,
+    mut shutdown: watch::Receiver<bool>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    health::mark_started();
+
+    // Initialize tracing
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "info".into()),
+        )
+        .init();
+
+    // Event fan-out with a bounded queue per SSE client
+    let event_hub = EventHub::new(CLIENT_QUEUE_CAPACITY);
+
+    let state = AppState { 
+        settings_manager,
+        app_handle: app_handle.clone(),
+        current_photo: Arc::new(Mutex::new(None)),
+        event_hub,
+        rate_limiter: Arc::new(RateLimiter::new()),
+        weather: Arc::new(Mutex::new(None)),
+        messages: MessageQueue::new(messages::QUEUE_CAPACITY),
+    };
+
+    // The frame reports its photo through a command rather than over HTTP(S)
+    app_handle.manage(state.clone());
+
+    // Follow a phone's regions over MQTT when configured
+    owntracks::spawn_listener(state.clone());
+
+    // Let browsers and the companion UI find the frame on the network
+    discovery::spawn_advertiser(state.clone());
+    ssdp::spawn_responder(state.clone());
+
+    // Show messages sent to the frame one after another
+    messages::spawn_presenter(state.clone());
+
+    // Show the lead's photo and settings when this frame follows another
+    sync::spawn_follower(state.clone());
+
+    // Keep the calendar feeds fresh and switch scenes on their events
+    calendar::spawn_refresher(state.clone());
+
+    // Keep the news headlines fresh
+    news::spawn_refresher(state.clone());
+
+    // Follow what's playing on Spotify, once an account is connected
+    spotify::spawn_poller(state.clone());
+
+    // Follow Last.fm scrobbles, once a username and key are set
+    lastfm::spawn_poller(state.clone());
+
+    // Find Chromecasts and Sonos speakers once turned on, and follow what they play
+    media::spawn_watcher(state.clone());
+
+    // Keep fixtures and scores fresh for live scores and match days
+    sports::spawn_refresher(state.clone());
+
+    // Look for a newer release once a day
+    updates::spawn_checker(state.clone());
+
+    // Notice the internet connection going away and coming back
+    connectivity::spawn_checker(state.clone());
+
+    // Compare the system clock with NTP, which matters on a Pi without a clock battery
+    ntp::spawn_checker(state.clone());
+    alarms::spawn_scheduler(state.clone());
+    night_mode::spawn_scheduler(state.clone());
+    display_sleep::spawn_scheduler(state.clone());
+
+    // Wake the screen on motion and turn it off once the room is empty, when enabled
+    presence::spawn_watcher(state.clone());
+
+    // Move the frame now and then and refresh the screen, for OLED and plasma screens
+    burn_in::spawn_scheduler(state.clone());
+
+    // Reload the frame, or open it again, when its heartbeats stop
+    watchdog::spawn_watchdog(state.clone());
+
+    // Publish state to an MQTT broker and take commands from it, when enabled
+    mqtt::spawn_client(state.clone());
+
+    // POST events to the configured webhooks, and watch for a hot CPU and a full disk
+    webhooks::spawn_dispatcher(state.clone());
+    system::spawn_temperature_monitor(state.clone());
+    disk_space::spawn_monitor(state.clone());
+    battery::spawn_monitor(state.clone());
+
+    // Switch settings profiles on their time-of-day schedule
+    profiles::spawn_scheduler(state.clone());
+
+    // Reload settings.json when it is edited by hand or synced by another tool
+    settings_watcher::spawn_watcher(state.clone());
+
+    // Create router
+    let mut changes = state.settings_manager.subscribe();
+    let app = create_router(state);
+
+    info!("🚀 Idleview HTTP Server starting...");
+    match auth::api_token() {
+        Ok(_) => info!("🔑 Changes require the API token as a Bearer header (shown in the frame's debug overlay)"),
+        Err(e) => error!("Failed to load API token, changes will be rejected: {}", e),
+    }
+    info!("📡 API endpoints available at (also unversioned under /api):");
+    info!("   GET    /api/v1/settings");
+    info!("   PUT    /api/v1/settings");
+    info!("   PATCH  /api/v1/settings");
+    info!("   POST   /api/v1/settings/reset");
+    info!("   GET    /api/v1/settings/backup");
+    info!("   POST   /api/v1/settings/restore");
+    info!("   GET    /api/v1/settings/history");
+    info!("   GET    /api/v1/settings/defaults");
+    info!("   GET    /api/v1/settings/schema");
+    info!("   GET    /api/v1/settings/profiles");
+    info!("   POST   /api/v1/settings/profiles");
+    info!("   GET    /api/v1/settings/profiles/:name");
+    info!("   PUT    /api/v1/settings/profiles/:name");
+    info!("   DELETE /api/v1/settings/profiles/:name");
+    info!("   POST   /api/v1/settings/profiles/:name/activate");
+    info!("   POST   /api/v1/settings/profiles/deactivate");
+    info!("   GET    /api/v1/alarms");
+    info!("   POST   /api/v1/alarms");
+    info!("   GET    /api/v1/alarms/:id");
+    info!("   PUT    /api/v1/alarms/:id");
+    info!("   DELETE /api/v1/alarms/:id");
+    info!("   GET    /api/v1/settings/:section");
+    info!("   PATCH  /api/v1/settings/:section");
+    info!("   GET    /api/v1/photo/current");
+    info!("   POST   /api/v1/photo/current");
+    info!("   POST   /api/v1/photo/next");
+    info!("   POST   /api/v1/refresh");
+    info!("   POST   /api/v1/message");
+    info!("   GET    /api/v1/timer");
+    info!("   POST   /api/v1/timer");
+    info!("   DELETE /api/v1/timer");
+    info!("   POST   /api/v1/location/active");
+    info!("   GET    /api/v1/weather");
+    info!("   GET    /api/v1/weather/legend");
+    info!("   GET    /api/v1/scenes");
+    info!("   POST   /api/v1/scenes/:name/activate");
+    info!("   POST   /api/v1/scenes/deactivate");
+    info!("   GET    /api/v1/events");
+    info!("   GET    /api/v1/ws");
+    info!("   GET    /api/v1/system");
+    info!("   POST   /api/v1/system/reboot");
+    info!("   POST   /api/v1/system/shutdown");
+    info!("   POST   /api/v1/system/check-update");
+    info!("   GET    /api/v1/debug");
+    info!("   GET    /api/v1/screenshot");
+    info!("   GET    /api/v1/display/power");
+    info!("   POST   /api/v1/display/power");
+    info!("   GET    /api/v1/display/brightness");
+    info!("   PUT    /api/v1/display/brightness");
+    info!("   GET    /api/v1/display/night");
+    info!("   GET    /api/v1/display/sleep");
+    info!("   GET    /api/v1/monitors");
+    info!("   GET    /api/v1/presence");
+    info!("   POST   /api/v1/presence");
+    info!("   GET    /api/v1/server/clients");
+    info!("   POST   /api/v1/auth/rotate");
+    info!("   GET    /api/v1/pair");
+    info!("   POST   /api/v1/pair");
+    info!("   POST   /api/v1/pair/start");
+    info!("   DELETE /api/v1/pair/:id");
+    info!("   GET    /api/v1/health");
+    info!("   GET    /api/v1/watchdog");
+    info!("   GET    /api/v1/connectivity");
+    info!("   GET    /api/v1/sync");
+    info!("   GET    /api/v1/calendar");
+    info!("   GET    /api/v1/calendar/google");
+    info!("   DELETE /api/v1/calendar/google");
+    info!("   POST   /api/v1/calendar/google/connect");
+    info!("   PUT    /api/v1/calendar/caldav/:name/password");
+    info!("   GET    /api/v1/quote");
+    info!("   GET    /api/v1/news");
+    info!("   GET    /api/v1/stocks");
+    info!("   GET    /api/v1/crypto");
+    info!("   GET    /api/v1/sports");
+    info!("   GET    /api/v1/tasks");
+    info!("   GET    /api/v1/now-playing");
+    info!("   GET    /api/v1/media/devices");
+    info!("   GET    /api/v1/spotify");
+    info!("   DELETE /api/v1/spotify");
+    info!("   POST   /api/v1/spotify/connect");
+    info!("   GET    /api/v1/spotify/callback");
+    info!("   POST   /api/v1/spotify/callback");
+    info!("   GET    /api/v1/openapi.json");
+    info!("   GET    /api/v1/docs");
+    info!("   GET    /description.xml");
+    info!("   GET    /metrics");
+
+    loop {
+        let server = changes.borrow_and_update().server.clone();
+        let addr = server_addr(&server);
+
+        if !server.enabled {
+            info!("⏸️  HTTP server disabled in settings");
+        } else {
+            match bind_listener(addr).await {
+                Ok(listener) => {
+                    // Client addresses are recorded in the settings history
+                    let service = app.clone().into_make_service_with_connect_info::<SocketAddr>();
+                    let (stop, stopped) = oneshot::channel::<()>();
+                    let serve: BoxFuture<'static, std::io::Result<()>> = if server.tls.enabled {
+                        let config = match tls::rustls_config(&server.tls).await {
+                            Ok(config) => config,
+                            Err(e) => {
+                                // Keep running so a corrected certificate takes effect without a restart
+                                error!("{}", e);
+                                if wait_for_stop(&mut changes, &server, &mut shutdown).await == Stop::Shutdown {
+                                    return Ok(());
+                                }
+                                continue;
+                            }
+                        };
+                        let handle = axum_server::Handle::new();
+                        let shutdown_handle = handle.clone();
+                        tokio::spawn(async move {
+                            if stopped.await.is_ok() {
+                                shutdown_handle.graceful_shutdown(Some(SHUTDOWN_GRACE));
+                            }
+                        });
+                        match listener.into_std() {
+                            Ok(listener) => Box::pin(
+                                axum_server::from_tcp_rustls(listener, config)
+                                    .handle(handle)
+                                    .serve(service),
+                            ),
+                            Err(e) => Box::pin(std::future::ready(Err(e))),
+                        }
+                    } else {
+                        Box::pin(
+                            axum::serve(listener, service)
+                                .with_graceful_shutdown(async move {
+                                    let _ = stopped.await;
+                                })
+                                .into_future(),
+                        )
+                    };
+
+                    let scheme = if server.tls.enabled { "https" } else { "http" };
+                    info!("📍 Server listening on {}://{}", scheme, addr);
+                    if server.bind == ServerBind::Localhost {
+                        info!("🔒 Localhost only; reach it from another machine with an SSH tunnel, e.g. ssh -L {0}:127.0.0.1:{0} <user>@<frame>", server.port);
+                    }
+                    info!("🌐 Access the control panel at:");
+                    for ip in get_local_ips() {
+                        if server.bind == ServerBind::All || ip == "127.0.0.1" {
+                            info!("   {}://{}:{}", scheme, ip, server.port);
+                        }
+                    }
+
+                    let mut serve = serve;
+                    let reason = tokio::select! {
+                        result = &mut serve => {
+                            // Supervise rather than give up: a transient accept error shouldn't
+                            // leave the frame without its control panel until the app restarts
+                            match result {
+                                Ok(()) => error!("Server stopped unexpectedly, restarting"),
+                                Err(e) => error!("Server error, restarting: {}", e),
+                            }
+                            tokio::time::sleep(RESTART_DELAY).await;
+                            continue;
+                        }
+                        reason = wait_for_stop(&mut changes, &server, &mut shutdown) => reason,
+                    };
+
+                    // Stop accepting, then give open requests a moment to finish
+                    let _ = stop.send(());
+                    let drain = async move {
+                        if tokio::time::timeout(SHUTDOWN_GRACE, serve).await.is_err() {
+                            info!("Closed connections still open after {:?}", SHUTDOWN_GRACE);
+                        }
+                    };
+                    match reason {
+                        Stop::Restart => {
+                            info!("🔄 Server settings changed, restarting listener");
+                            tokio::spawn(drain);
+                            continue;
+                        }
+                        Stop::Shutdown => {
+                            info!("🛑 HTTP server shutting down");
+                            drain.await;
+                            return Ok(());
+                        }
+                    }
+                }
+                // Keep running so a corrected port takes effect without a restart
+                Err(e) => error!("Failed to bind to {}: {}", addr, e),
+            }
+        }
+
+        if wait_for_stop(&mut changes, &server, &mut shutdown).await == Stop::Shutdown {
+            return Ok(());
+        }
+    }
+}
+
+/// Runs the HTTP server on its own thread and stops it gracefully on request
+pub struct ServerSupervisor {
+    shutdown: watch::Sender<bool>,
+    thread: Mutex<Option<std::thread::JoinHandle<()>>>,
+}
+
+impl ServerSupervisor {
+    /// Stop accepting connections, let open requests finish and wait for the
+    /// server thread to exit
+    pub fn shutdown(&self) {
+        let _ = self.shutdown.send(true);
+        let thread = self.thread.lock().ok().and_then(|mut thread| thread.take());
+        if let Some(thread) = thread {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Start the HTTP server on a thread with its own runtime
+pub fn spawn_server(app_handle: tauri::AppHandle, settings_manager: SettingsManager) -> ServerSupervisor {
+    let (shutdown, shutdown_requested) = watch::channel(false);
+    let thread = std::thread::spawn(move || {
+        let runtime = match tokio::runtime::Runtime::new() {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                eprintln!("Failed to start HTTP server runtime: {}", e);
+                return;
+            }
+        };
+        runtime.block_on(async move {
+            if let Err(e) = start_server(app_handle, settings_manager, shutdown_requested).await {
+                eprintln!("HTTP server error: {}", e);
+            }
+        });
+    });
+    ServerSupervisor {
+        shutdown,
+        thread: Mutex::new(Some(thread)),
+    }
 }
 
 // Tests disabled - requires tauri AppHandle which can't be easily mocked
@@ -327,7 +2218,7 @@ pub async fn start_server(port: u16, app_handle: tauri::AppHandle) -> Result<(),
 //     async fn test_health_check() {
 //         let settings_manager = SettingsManager::new().unwrap();
 //         let state = AppState { settings_manager };
-//         let app = create_router(state, PathBuf::from("dist"));
+//         let app = create_router(state);
 //
 //         let response = app
 //             .oneshot(
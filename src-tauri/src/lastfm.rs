@@ -0,0 +1,142 @@
+//! Last.fm's latest scrobble, for music played anywhere that scrobbles
+//! (Apple Music, a record player with a scrobbler, a car stereo), reported
+//! through the same now-playing state as Spotify.
+
+use serde_json::Value;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::display_sleep;
+use crate::http_server::AppState;
+use crate::now_playing::{self, NowPlaying, NowPlayingSource};
+use crate::settings_manager::LastFmSettings;
+
+pub const API_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Last.fm's grey star, given for tracks it has no cover of
+const PLACEHOLDER_IMAGE: &str = "2a96cbd8b46e442fc41c2b86b821562f";
+
+/// Username and key, when both are set
+fn credentials(settings: &LastFmSettings) -> Option<(&str, &str)> {
+    let username = settings.username.as_deref().map(str::trim).filter(|name| !name.is_empty())?;
+    let api_key = settings.api_key.as_deref().map(str::trim).filter(|key| !key.is_empty())?;
+    Some((username, api_key))
+}
+
+/// The latest track of a user.getRecentTracks answer: the one playing now,
+/// or the last one scrobbled
+pub fn parse_recent_tracks(json: &str) -> Result<Option<NowPlaying>, String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| format!("Failed to parse Last.fm tracks: {}", e))?;
+    if let Some(message) = value.get("message").and_then(Value::as_str) {
+        return Err(format!("Last.fm refused: {}", message));
+    }
+    // A list, or a single object when there is only one track
+    let track = match value.pointer("/recenttracks/track") {
+        Some(Value::Array(tracks)) => tracks.first(),
+        Some(track @ Value::Object(_)) => Some(track),
+        _ => None,
+    };
+    let Some(track) = track else {
+        return Ok(None);
+    };
+    // Names are under "#text", or "name" with extended=1
+    let text = |value: Option<&Value>| {
+        value
+            .and_then(|value| value.as_str().or_else(|| value.get("#text").and_then(Value::as_str)))
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+            .map(str::to_string)
+    };
+    let Some(title) = text(track.get("name")) else {
+        return Ok(None);
+    };
+    let artist = text(track.get("artist"))
+        .or_else(|| text(track.pointer("/artist/name")))
+        .unwrap_or_default();
+    // Sizes go from small to extralarge
+    let album_art_url = track
+        .get("image")
+        .and_then(Value::as_array)
+        .and_then(|images| images.iter().rev().find_map(|image| text(Some(image))))
+        .filter(|url| !url.contains(PLACEHOLDER_IMAGE));
+    Ok(Some(NowPlaying {
+        title,
+        artist,
+        album: text(track.get("album")),
+        album_art_url,
+        is_playing: track.pointer("/@attr/nowplaying").and_then(Value::as_str) == Some("true"),
+        progress_ms: None,
+        duration_ms: None,
+        source: NowPlayingSource::LastFm,
+        device: None,
+    }))
+}
+
+/// The latest track of the configured user
+pub async fn fetch_latest_from(
+    client: &reqwest::Client,
+    url: &str,
+    settings: &LastFmSettings,
+) -> Result<Option<NowPlaying>, String> {
+    let (username, api_key) =
+        credentials(settings).ok_or_else(|| "Set music.lastfm.username and music.lastfm.api_key first".to_string())?;
+    // Errors come back as JSON with a 4xx status, so the body is read either way
+    let body = client
+        .get(url)
+        .query(&[
+            ("method", "user.getrecenttracks"),
+            ("user", username),
+            ("api_key", api_key),
+            ("format", "json"),
+            ("limit", "1"),
+        ])
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach Last.fm: {}", e))?
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read Last.fm tracks: {}", e))?;
+    parse_recent_tracks(&body)
+}
+
+/// Ask Last.fm for the latest scrobble every `poll_interval` seconds while a
+/// username and key are set, for the lifetime of the server
+pub fn spawn_poller(state: AppState) {
+    tokio::spawn(async move {
+        let mut last_error: Option<String> = None;
+        loop {
+            if display_sleep::is_asleep() {
+                tokio::time::sleep(display_sleep::PAUSED_POLL_INTERVAL).await;
+                continue;
+            }
+            let settings = match state.settings_manager.get() {
+                Ok(settings) => settings.music.lastfm,
+                Err(e) => {
+                    warn!("Last.fm: failed to read settings: {}", e);
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    continue;
+                }
+            };
+            if credentials(&settings).is_none() {
+                now_playing::update(&state, NowPlayingSource::LastFm, None);
+            } else {
+                match fetch_latest_from(crate::http_client(), API_URL, &settings).await {
+                    Ok(latest) => {
+                        last_error = None;
+                        now_playing::update(&state, NowPlayingSource::LastFm, latest);
+                    }
+                    // Logged once rather than every poll while it keeps failing
+                    Err(e) if last_error.as_ref() != Some(&e) => {
+                        warn!("Last.fm: {}", e);
+                        last_error = Some(e);
+                    }
+                    Err(_) => {}
+                }
+            }
+            tokio::time::sleep(Duration::from_secs(settings.poll_interval.max(1))).await;
+        }
+    });
+}
@@ -0,0 +1,71 @@
+//! Tolerant field deserializers for third-party API responses.
+//!
+//! Providers occasionally send `null`, numbers as strings or fields of the
+//! wrong type. With these, a bad field becomes `None` (or is skipped in a
+//! list) instead of failing the whole response, and callers decide which
+//! fields they can't do without.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+fn as_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+    .filter(|v: &f64| v.is_finite())
+}
+
+fn as_string(value: Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.trim().is_empty() => Some(s),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// A number, or a numeric string; anything else is `None`
+pub fn f64_or_none<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f64>, D::Error> {
+    Ok(as_f64(&Value::deserialize(deserializer)?))
+}
+
+/// A non-empty string (numbers are converted); anything else is `None`
+pub fn string_or_none<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    Ok(as_string(Value::deserialize(deserializer)?))
+}
+
+/// A boolean, or "true"/"false"; anything else is `None`
+pub fn bool_or_none<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<bool>, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Bool(b) => Some(b),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    })
+}
+
+/// The valid strings of a list; a non-list is empty
+pub fn strings<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Array(items) => items.into_iter().filter_map(as_string).collect(),
+        _ => Vec::new(),
+    })
+}
+
+/// The valid numbers of a list; a non-list is empty
+pub fn f64s<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<f64>, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Array(items) => items.iter().filter_map(as_f64).collect(),
+        _ => Vec::new(),
+    })
+}
+
+/// A nested object; `null` or a value of the wrong shape is the default
+pub fn or_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned + Default,
+{
+    Ok(serde_json::from_value(Value::deserialize(deserializer)?).unwrap_or_default())
+}
@@ -1,14 +1,83 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use chrono::{Datelike, Local};
+use chrono::{Datelike, Local, Timelike};
+use rand::seq::IndexedRandom;
 use std::sync::{Mutex, OnceLock};
 use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{Manager, State};
 
 // HTTP server modules
 pub mod settings_manager;
 pub mod http_server;
+pub mod event_hub;
+pub mod location;
+pub mod weather_codes;
+pub mod scenes;
+pub mod bandwidth;
+pub mod owntracks;
+pub mod lenient;
+pub mod display_state;
+pub mod profiles;
+pub mod settings_watcher;
+pub mod settings_history;
+pub mod write_throttle;
+pub mod auth;
+pub mod pairing;
+pub mod tls;
+pub mod rate_limit;
+pub mod access_log;
+pub mod discovery;
+pub mod ssdp;
+pub mod openapi;
+pub mod system;
+pub mod thermal;
+pub mod screenshot;
+pub mod display_power;
+pub mod brightness;
+pub mod messages;
+pub mod control_panel;
+pub mod health;
+pub mod metrics;
+pub mod power;
+pub mod sync;
+pub mod webhooks;
+pub mod mqtt;
+pub mod updates;
+pub mod calendar;
+pub mod google_calendar;
+pub mod caldav;
+pub mod greeting;
+pub mod quotes;
+pub mod news;
+pub mod stocks;
+pub mod crypto;
+pub mod now_playing;
+pub mod spotify;
+pub mod lastfm;
+pub mod sports;
+pub mod media;
+pub mod tasks;
+pub mod countdowns;
+pub mod ntp;
+pub mod alarms;
+pub mod timer;
+pub mod night_mode;
+pub mod display_sleep;
+pub mod presence;
+pub mod burn_in;
+pub mod monitors;
+pub mod watchdog;
+pub mod disk_space;
+pub mod battery;
+pub mod connectivity;
 
 // Re-export settings types from settings_manager
-use settings_manager::Settings;
+use settings_manager::{
+    DateFormat, PhotoOrientation, PhotoProvider, PhotoQuerySettings, PhotosSettings, QualityWindow, Settings, SettingsManager,
+    TemperatureUnit, TimeFormat, UnitsSettings, WeatherAlertSettings, WeatherProvider, WindSpeedUnit,
+};
+pub use location::Location;
+pub use idleview_api::WeatherData;
 
 // ===== Core functions (public for testing) =====
 
@@ -24,7 +93,13 @@ struct SunTimesCache {
     sunset: chrono::NaiveDateTime,
 }
 
-fn http_client() -> &'static reqwest::Client {
+const OPEN_METEO_FORECAST_URL: &str = "https://api.open-meteo.com/v1/forecast";
+const OPEN_METEO_CUSTOMER_URL: &str = "https://customer-api.open-meteo.com/v1/forecast";
+const OPENWEATHERMAP_URL: &str = "https://api.openweathermap.org/data/2.5/weather";
+const UNSPLASH_API_URL: &str = "https://api.unsplash.com";
+const PEXELS_API_URL: &str = "https://api.pexels.com";
+
+pub(crate) fn http_client() -> &'static reqwest::Client {
     HTTP_CLIENT.get_or_init(reqwest::Client::new)
 }
 
@@ -40,6 +115,20 @@ fn unsplash_access_key() -> &'static str {
         .as_str()
 }
 
+/// The API key for the configured photo provider. Unsplash falls back to the
+/// key from the environment or build.
+fn photo_api_key(photos: &PhotosSettings) -> Result<String, String> {
+    let configured = match photos.provider {
+        PhotoProvider::Unsplash => &photos.api_keys.unsplash,
+        PhotoProvider::Pexels => &photos.api_keys.pexels,
+    };
+    match (configured.as_deref().filter(|key| !key.trim().is_empty()), photos.provider) {
+        (Some(key), _) => Ok(key.trim().to_string()),
+        (None, PhotoProvider::Unsplash) => Ok(unsplash_access_key().to_string()),
+        (None, PhotoProvider::Pexels) => Err("Pexels API key is not set".to_string()),
+    }
+}
+
 fn get_cached_sun_times(
     sunrise_str: &str,
     sunset_str: &str,
@@ -210,28 +299,69 @@ pub fn build_photo_query_impl(
     PhotoQuery { query }
 }
 
-pub fn get_current_time_impl() -> FormattedTime {
+/// The chrono locale for a BCP-47 tag. A bare language ("ro") picks its main
+/// country ("ro_RO"); unknown tags fall back to English.
+pub fn chrono_locale(tag: &str) -> chrono::Locale {
+    let tag = tag.trim().replace('-', "_");
+    let language = tag.split('_').next().unwrap_or_default().to_lowercase();
+    [tag.clone(), format!("{}_{}", language, language.to_uppercase())]
+        .iter()
+        .find_map(|name| chrono::Locale::try_from(name.as_str()).ok())
+        .unwrap_or(chrono::Locale::en_US)
+}
+
+/// The time at `now` in each world clock, in the order given. Clocks with an
+/// unknown timezone are left out; settings validation rejects them anyway.
+pub fn get_world_clocks_impl<Z: chrono::TimeZone>(
+    clocks: &[settings_manager::WorldClock],
+    units: &UnitsSettings,
+    language: &str,
+    now: chrono::DateTime<Z>,
+) -> Vec<WorldClockTime> {
+    let locale = chrono_locale(language);
+    clocks
+        .iter()
+        .filter_map(|clock| {
+            let tz: chrono_tz::Tz = clock.timezone.trim().parse().ok()?;
+            let there = now.with_timezone(&tz);
+            let time = if units.time_format == TimeFormat::H12 {
+                there.format("%-I:%M %p").to_string()
+            } else {
+                there.format("%H:%M").to_string()
+            };
+            // "America/Argentina/Buenos_Aires" -> "Buenos Aires"
+            let city = tz.name().rsplit('/').next().unwrap_or(tz.name()).replace('_', " ");
+            Some(WorldClockTime {
+                label: clock.label.as_deref().map(str::trim).filter(|l| !l.is_empty()).map_or(city, str::to_string),
+                timezone: tz.name().to_string(),
+                time,
+                day_of_week: there.format_localized("%A", locale).to_string().to_uppercase(),
+                utc_offset: there.format("%:z").to_string(),
+                day_offset: (there.date_naive() - now.date_naive()).num_days(),
+            })
+        })
+        .collect()
+}
+
+pub fn get_current_time_impl(units: &UnitsSettings, language: &str) -> FormattedTime {
     let now = Local::now();
-    
-    // Get settings to determine format
-    let settings = settings_manager::read_settings().unwrap_or_default();
+    let locale = chrono_locale(language);
     
     // Format time based on settings
-    let time = if settings.units.time_format == "12h" {
+    let time = if units.time_format == TimeFormat::H12 {
         now.format("%-I:%M %p").to_string()
     } else {
         now.format("%H:%M").to_string()
     };
     
-    // Format date based on settings
-    let date = match settings.units.date_format.as_str() {
-        "mdy" => now.format("%b %d, %Y").to_string(),  // Nov 28, 2025
-        "dmy" => now.format("%d %b %Y").to_string(),   // 28 Nov 2025
-        "ymd" => now.format("%Y %b %d").to_string(),   // 2025 Nov 28
-        _ => now.format("%b %d, %Y").to_string(),      // Default to MDY
+    // Format date based on settings, with month names in the configured language
+    let date = match units.date_format {
+        DateFormat::Mdy => now.format_localized("%b %d, %Y", locale).to_string(),  // Nov 28, 2025
+        DateFormat::Dmy => now.format_localized("%d %b %Y", locale).to_string(),   // 28 Nov 2025
+        DateFormat::Ymd => now.format_localized("%Y %b %d", locale).to_string(),   // 2025 Nov 28
     };
     
-    let day_of_week = now.format("%A").to_string().to_uppercase();
+    let day_of_week = now.format_localized("%A", locale).to_string().to_uppercase();
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
@@ -245,36 +375,44 @@ pub fn get_current_time_impl() -> FormattedTime {
     }
 }
 
-pub fn get_precipitation_display_impl(weather: WeatherData) -> PrecipitationDisplay {
+pub fn get_precipitation_display_impl(weather: WeatherData, language: &str) -> PrecipitationDisplay {
+    let label = |text: &str| weather_codes::localize_label(text, language);
     if weather.snowfall > 0.0 {
         PrecipitationDisplay {
             icon: "snowflake.svg".to_string(),
-            label: "Snow".to_string(),
+            label: label("Snow"),
             value: format!("{:.1} cm", weather.snowfall),
         }
     } else if weather.rain > 0.0 {
         PrecipitationDisplay {
             icon: "droplets.svg".to_string(),
-            label: "Rain".to_string(),
+            label: label("Rain"),
             value: format!("{:.1} mm", weather.rain),
         }
     } else {
         PrecipitationDisplay {
             icon: "umbrella.svg".to_string(),
-            label: "Precip".to_string(),
-            value: "Clear".to_string(),
+            label: label("Precip"),
+            value: label("Clear"),
         }
     }
 }
 
-pub fn is_cache_valid_impl(cache_timestamp: u64) -> bool {
+pub fn is_cache_valid_impl(cache_timestamp: u64, photos: &PhotosSettings) -> bool {
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_millis() as u64;
     
-    let settings = settings_manager::read_settings().unwrap_or_default();
-    let refresh_interval_ms = (settings.photos.refresh_interval as u64) * 60 * 1000;
+    let mut refresh_interval_ms = (photos.refresh_interval as u64) * 60 * 1000;
+    
+    // Refresh less often while the connection is slow
+    if bandwidth::is_degraded() {
+        refresh_interval_ms *= bandwidth::DEGRADED_INTERVAL_FACTOR;
+    }
+
+    // And at night, if night mode asks for it
+    refresh_interval_ms *= night_mode::photo_interval_factor();
     
     let cache_age = now.saturating_sub(cache_timestamp);
     cache_age < refresh_interval_ms
@@ -307,36 +445,219 @@ fn greet(name: &str) -> String {
 }
 
 #[tauri::command]
-fn get_settings() -> Result<Settings, String> {
-    settings_manager::read_settings()
+fn get_settings(manager: State<'_, SettingsManager>) -> Result<Settings, String> {
+    manager.get()
+}
+
+/// Settings for the frame in this window, with its monitor's override merged in
+#[tauri::command]
+fn get_window_settings(window: tauri::WebviewWindow, manager: State<'_, SettingsManager>) -> Result<Settings, String> {
+    monitors::settings_for_window(&window, &manager.get()?)
 }
 
 #[tauri::command]
-fn save_settings(settings: Settings) -> Result<(), String> {
-    settings_manager::write_settings(&settings)
+fn save_settings(manager: State<'_, SettingsManager>, settings: serde_json::Value) -> Result<(), String> {
+    let problems = settings_manager::validate_settings_json(&settings);
+    if !problems.is_empty() {
+        return Err(format!("Invalid settings: {}", problems.join("; ")));
+    }
+
+    let settings: Settings = serde_json::from_value(settings)
+        .map_err(|e| format!("Failed to parse settings: {}", e))?;
+    manager.update_all_from(settings, "tauri")
 }
 
 #[tauri::command]
-fn reset_settings() -> Result<Settings, String> {
+fn backup_settings(manager: State<'_, SettingsManager>) -> Result<settings_manager::SettingsBackup, String> {
+    let settings = manager.get()?;
+    Ok(settings_manager::create_backup(&settings))
+}
+
+#[tauri::command]
+fn restore_settings(manager: State<'_, SettingsManager>, backup: serde_json::Value) -> Result<Settings, String> {
+    let settings = settings_manager::settings_from_backup(&backup)
+        .map_err(|problems| format!("Invalid backup: {}", problems.join("; ")))?;
+    manager.update_all_from(settings.clone(), "tauri")?;
+    Ok(settings)
+}
+
+#[tauri::command]
+fn export_settings(manager: State<'_, SettingsManager>, path: String) -> Result<(), String> {
+    let settings = manager.get()?;
+    settings_manager::export_settings_to(std::path::Path::new(&path), &settings)
+}
+
+#[tauri::command]
+fn import_settings(manager: State<'_, SettingsManager>, path: String) -> Result<Settings, String> {
+    let settings = settings_manager::import_settings_from(std::path::Path::new(&path))?;
+    manager.update_all_from(settings.clone(), "tauri")?;
+    Ok(settings)
+}
+
+#[tauri::command]
+fn reset_settings(manager: State<'_, SettingsManager>) -> Result<Settings, String> {
     let settings = Settings::default();
-    save_settings(settings.clone())?;
+    manager.update_all_from(settings.clone(), "tauri")?;
     Ok(settings)
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Location {
-    pub latitude: f64,
-    pub longitude: f64,
-    pub city: Option<String>,
-    pub country: Option<String>,
+/// The token HTTP clients need for changes, to show when setting one up
+#[tauri::command]
+fn get_api_token() -> Result<String, String> {
+    auth::api_token()
 }
 
-#[derive(Debug, Deserialize)]
-struct IpApiResponse {
-    lat: f64,
-    lon: f64,
-    city: Option<String>,
-    country: Option<String>,
+#[tauri::command]
+fn rotate_api_token() -> Result<String, String> {
+    auth::rotate_api_token()
+}
+
+/// The frame reporting the photo it shows, for the HTTP API and its clients
+#[tauri::command]
+fn set_current_photo(state: State<'_, http_server::AppState>, photo: http_server::CurrentPhoto) -> Result<(), String> {
+    state.set_current_photo_from(photo, "tauri")
+}
+
+/// Start a timer from the frame, cancelling the one running
+#[tauri::command]
+async fn start_timer(
+    state: State<'_, http_server::AppState>,
+    duration: u32,
+    label: Option<String>,
+) -> Result<timer::Timer, String> {
+    let request = timer::TimerRequest { duration, label: label.unwrap_or_default() };
+    let problems = timer::validate_timer(&request);
+    if !problems.is_empty() {
+        return Err(problems.join("; "));
+    }
+    timer::start(&state, request)
+}
+
+/// The running timer, to pick up the countdown after a reload
+#[tauri::command]
+fn get_timer() -> Option<timer::Timer> {
+    timer::current()
+}
+
+#[tauri::command]
+fn cancel_timer(state: State<'_, http_server::AppState>) -> Result<Option<timer::Timer>, String> {
+    timer::cancel(&state)
+}
+
+/// Whether night mode is on, for the frame's theme after a reload
+#[tauri::command]
+fn get_night_mode(manager: State<'_, SettingsManager>) -> night_mode::NightMode {
+    let settings = manager.get().unwrap_or_default();
+    night_mode::status(&settings.display.night_mode)
+}
+
+/// Whether the sleep hours have the screen off, so the frame can skip polling
+#[tauri::command]
+fn get_display_sleep(manager: State<'_, SettingsManager>) -> display_sleep::DisplaySleep {
+    let settings = manager.get().unwrap_or_default();
+    display_sleep::status(&settings.display.sleep)
+}
+
+/// Whether the frame is online, so it can stop retrying and keep its photo while it isn't
+#[tauri::command]
+fn get_connectivity() -> connectivity::Connectivity {
+    connectivity::status()
+}
+
+/// Where burn-in protection has moved the frame, for its place after a reload
+#[tauri::command]
+fn get_pixel_shift() -> burn_in::PixelShift {
+    burn_in::current_shift()
+}
+
+/// The frame is alive; the watchdog reloads it when these stop
+#[tauri::command]
+fn frame_heartbeat(window: tauri::WebviewWindow) {
+    watchdog::heartbeat(window.label());
+}
+
+/// The last update check, for the frame's "update available" hint
+#[tauri::command]
+fn get_update_info() -> Option<updates::UpdateInfo> {
+    updates::last_check()
+}
+
+/// Upcoming events for the frame's agenda
+#[tauri::command]
+fn get_calendar_events() -> Vec<calendar::CalendarEvent> {
+    calendar::report().events
+}
+
+/// The greeting above the clock, for the current hour and weather
+#[tauri::command]
+fn get_greeting(state: State<'_, http_server::AppState>) -> String {
+    let settings = state.settings_manager.get().unwrap_or_default();
+    let weather = state.weather.lock().ok().and_then(|report| report.clone()).map(|report| report.weather);
+    greeting::compose_greeting(&settings.display.greeting, weather.as_ref(), Local::now().hour(), &settings.language)
+}
+
+/// Today's quote, for the corner of the frame
+#[tauri::command]
+async fn get_quote(state: State<'_, http_server::AppState>) -> Result<quotes::Quote, String> {
+    let settings = state.settings_manager.get()?;
+    Ok(quotes::quote_of_the_day(&settings.quotes).await)
+}
+
+/// Headlines from the news feeds, newest first
+#[tauri::command]
+fn get_headlines() -> Vec<news::Headline> {
+    news::report().headlines
+}
+
+/// Last price and day change of the configured stocks
+#[tauri::command]
+async fn get_stocks(state: State<'_, http_server::AppState>) -> Result<stocks::StocksReport, String> {
+    let settings = state.settings_manager.get()?;
+    Ok(stocks::report(&settings.stocks).await)
+}
+
+/// Prices of the configured coins, for the corner of the frame
+#[tauri::command]
+async fn get_crypto_prices(state: State<'_, http_server::AppState>) -> Result<crypto::CryptoReport, String> {
+    let settings = state.settings_manager.get()?;
+    Ok(crypto::report(&settings.crypto).await)
+}
+
+/// Live scores, upcoming fixtures and results of the followed teams and leagues
+#[tauri::command]
+async fn get_sports(state: State<'_, http_server::AppState>) -> Result<sports::SportsReport, String> {
+    let settings = state.settings_manager.get()?;
+    Ok(sports::report(&settings.sports).await)
+}
+
+/// Today's tasks from Todoist and the tasks file
+#[tauri::command]
+async fn get_tasks(state: State<'_, http_server::AppState>) -> Result<tasks::TasksReport, String> {
+    let settings = state.settings_manager.get()?;
+    Ok(tasks::report(&settings.tasks).await)
+}
+
+/// Days left until the user's countdown events, soonest first
+#[tauri::command]
+fn get_countdowns(manager: State<'_, SettingsManager>) -> Vec<countdowns::Countdown> {
+    let settings = manager.get().unwrap_or_default();
+    countdowns::countdowns_impl(&settings.countdowns, Local::now().date_naive())
+}
+
+/// The track playing right now, for the album-art view
+#[tauri::command]
+fn get_now_playing() -> Option<now_playing::NowPlaying> {
+    now_playing::current()
+}
+
+#[tauri::command]
+fn get_brightness() -> brightness::Brightness {
+    brightness::brightness()
+}
+
+#[tauri::command]
+fn set_brightness(app: tauri::AppHandle, percent: u8) -> Result<brightness::Brightness, String> {
+    brightness::set_brightness(&app, percent)
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -347,73 +668,155 @@ pub struct UnsplashPhoto {
     pub download_location: String,
 }
 
+// Provider responses are parsed leniently: a null or mistyped field becomes
+// None and only the fields we can't do without turn into errors.
+
 #[derive(Debug, Deserialize)]
 struct UnsplashApiResponse {
+    #[serde(default, deserialize_with = "lenient::or_default")]
     urls: UnsplashUrls,
+    #[serde(default, deserialize_with = "lenient::or_default")]
     user: UnsplashUser,
+    #[serde(default, deserialize_with = "lenient::or_default")]
     links: UnsplashPhotoLinks,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Default)]
 struct UnsplashPhotoLinks {
-    download_location: String,
+    #[serde(default, deserialize_with = "lenient::string_or_none")]
+    download_location: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Default)]
 struct UnsplashUrls {
-    regular: String,
+    #[serde(default, deserialize_with = "lenient::string_or_none")]
+    regular: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Default)]
 struct UnsplashUser {
-    name: String,
+    #[serde(default, deserialize_with = "lenient::string_or_none")]
+    name: Option<String>,
+    #[serde(default, deserialize_with = "lenient::or_default")]
     links: UnsplashUserLinks,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Default)]
 struct UnsplashUserLinks {
-    html: String,
+    #[serde(default, deserialize_with = "lenient::string_or_none")]
+    html: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct WeatherData {
-    pub temperature: f64,
-    pub temperature_unit: String,
-    pub humidity: f64,
-    pub wind_speed: f64,
-    pub wind_speed_unit: String,
-    pub wind_speed_label: String,
-    pub cloudcover: f64,
-    pub rain: f64,
-    pub snowfall: f64,
-    pub sunrise: String,
-    pub sunset: String,
-    pub timezone: String,
+#[derive(Debug, Deserialize)]
+struct PexelsResponse {
+    // Search results come as "photos", collection contents as "media"
+    #[serde(default, alias = "media", deserialize_with = "lenient::or_default")]
+    photos: Vec<PexelsPhoto>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+struct PexelsPhoto {
+    #[serde(deserialize_with = "lenient::or_default")]
+    src: PexelsPhotoSource,
+    #[serde(deserialize_with = "lenient::string_or_none")]
+    photographer: Option<String>,
+    #[serde(deserialize_with = "lenient::string_or_none")]
+    photographer_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct PexelsPhotoSource {
+    #[serde(default, deserialize_with = "lenient::string_or_none")]
+    original: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct OpenMeteoResponse {
-    current: OpenMeteoCurrentData,
+    #[serde(default, deserialize_with = "lenient::or_default")]
+    current: Option<OpenMeteoCurrentData>,
+    #[serde(default, deserialize_with = "lenient::or_default")]
     daily: OpenMeteoDailyData,
-    timezone: String,
+    #[serde(default, deserialize_with = "lenient::string_or_none")]
+    timezone: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct OpenMeteoCurrentData {
-    temperature_2m: f64,
-    relative_humidity_2m: f64,
-    rain: f64,
-    snowfall: f64,
-    cloudcover: f64,
-    wind_speed_10m: f64,
+    #[serde(default, deserialize_with = "lenient::f64_or_none")]
+    temperature_2m: Option<f64>,
+    #[serde(default, deserialize_with = "lenient::f64_or_none")]
+    relative_humidity_2m: Option<f64>,
+    #[serde(default, deserialize_with = "lenient::f64_or_none")]
+    rain: Option<f64>,
+    #[serde(default, deserialize_with = "lenient::f64_or_none")]
+    snowfall: Option<f64>,
+    #[serde(default, deserialize_with = "lenient::f64_or_none")]
+    cloudcover: Option<f64>,
+    #[serde(default, deserialize_with = "lenient::f64_or_none")]
+    wind_speed_10m: Option<f64>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Default)]
 struct OpenMeteoDailyData {
+    #[serde(default, deserialize_with = "lenient::strings")]
     sunrise: Vec<String>,
+    #[serde(default, deserialize_with = "lenient::strings")]
     sunset: Vec<String>,
 }
 
+#[derive(Debug, Deserialize)]
+struct OpenWeatherMapResponse {
+    #[serde(default, deserialize_with = "lenient::or_default")]
+    main: OpenWeatherMapMain,
+    #[serde(default, deserialize_with = "lenient::or_default")]
+    wind: OpenWeatherMapWind,
+    #[serde(default, deserialize_with = "lenient::or_default")]
+    clouds: OpenWeatherMapClouds,
+    #[serde(default, deserialize_with = "lenient::or_default")]
+    rain: OpenWeatherMapPrecipitation,
+    #[serde(default, deserialize_with = "lenient::or_default")]
+    snow: OpenWeatherMapPrecipitation,
+    #[serde(default, deserialize_with = "lenient::or_default")]
+    sys: OpenWeatherMapSys,
+    #[serde(default, deserialize_with = "lenient::f64_or_none")]
+    timezone: Option<f64>,  // UTC offset in seconds
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct OpenWeatherMapMain {
+    #[serde(default, deserialize_with = "lenient::f64_or_none")]
+    temp: Option<f64>,
+    #[serde(default, deserialize_with = "lenient::f64_or_none")]
+    humidity: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct OpenWeatherMapWind {
+    #[serde(default, deserialize_with = "lenient::f64_or_none")]
+    speed: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct OpenWeatherMapClouds {
+    #[serde(default, deserialize_with = "lenient::f64_or_none")]
+    all: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct OpenWeatherMapPrecipitation {
+    #[serde(rename = "1h", default, deserialize_with = "lenient::f64_or_none")]
+    one_hour: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct OpenWeatherMapSys {
+    #[serde(default, deserialize_with = "lenient::f64_or_none")]
+    sunrise: Option<f64>,
+    #[serde(default, deserialize_with = "lenient::f64_or_none")]
+    sunset: Option<f64>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct TimeOfDay {
     pub time_of_day: String, // "dawn", "day", "dusk", "night"
@@ -443,6 +846,17 @@ pub struct FormattedTime {
     pub timestamp: u64,         // Unix timestamp in milliseconds
 }
 
+/// The time in one of the world clocks
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct WorldClockTime {
+    pub label: String,
+    pub timezone: String,
+    pub time: String,           // HH:MM, or h:MM AM/PM
+    pub day_of_week: String,    // e.g., "FRIDAY"
+    pub utc_offset: String,     // e.g., "-04:00"
+    pub day_offset: i64,        // -1 yesterday, 0 today, 1 tomorrow, from the frame's own date
+}
+
 #[derive(Debug, Serialize)]
 pub struct PhotoCache {
     pub photo: UnsplashPhoto,
@@ -457,7 +871,7 @@ pub struct PrecipitationDisplay {
     pub value: String,     // "5.0 cm", "3.2 mm", "Clear"
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct DebugInfo {
     pub photo_age: String,
     pub query: String,
@@ -471,85 +885,316 @@ pub struct DebugInfo {
     pub snowfall: String,
     pub cloudcover: String,
     pub season: String,
+    pub network_mode: String, // "normal" or "degraded (120 KB/s)"
+    pub clock: String, // "0.2s ahead", "3h 12m behind (drifted)" or "unknown"
 }
 
 #[tauri::command]
-async fn get_location() -> Result<Location, String> {
-    let response = http_client()
-        .get("http://ip-api.com/json/")
+async fn get_location(manager: State<'_, SettingsManager>) -> Result<Location, String> {
+    resolve_location(&manager.get().unwrap_or_default()).await
+}
+
+/// Where the frame is: a manual location, then a saved one (active or
+/// rotating), then detection from the configured source
+pub async fn resolve_location(settings: &Settings) -> Result<Location, String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+
+    let location = match location::manual_location_impl(&settings.location) {
+        Some(manual) => manual,
+        None => match location::resolve_saved_location_impl(&settings.location, now) {
+            Some(saved) => Location::from(saved),
+            None => {
+                let detected = location::fetch_location_from_source(http_client(), &settings.location).await;
+                health::record("location", &detected);
+                detected?
+            }
+        },
+    };
+
+    Ok(location::with_elevation(http_client(), location).await)
+}
+
+#[tauri::command]
+async fn get_weather(
+    app: tauri::AppHandle,
+    manager: State<'_, SettingsManager>,
+    latitude: f64,
+    longitude: f64,
+    elevation: Option<f64>,
+) -> Result<WeatherData, String> {
+    let settings = manager.get().unwrap_or_default();
+    let weather = fetch_weather_for(&settings, latitude, longitude, elevation).await?;
+
+    // Let connected control panels show the same reading as the frame
+    if let Some(state) = app.try_state::<http_server::AppState>() {
+        state.set_weather_from(weather.clone(), "weather");
+    }
+    Ok(weather)
+}
+
+/// Current weather from the configured provider, in the configured units and language
+pub async fn fetch_weather_for(
+    settings: &Settings,
+    latitude: f64,
+    longitude: f64,
+    elevation: Option<f64>,
+) -> Result<WeatherData, String> {
+    let started = std::time::Instant::now();
+    let weather = fetch_provider_weather(settings, latitude, longitude, elevation).await;
+    health::record("weather", &weather);
+    metrics::global().record_fetch("weather", settings.weather.provider.as_str(), weather.is_ok());
+    metrics::global().observe_weather_latency(started.elapsed());
+    weather
+}
+
+async fn fetch_provider_weather(
+    settings: &Settings,
+    latitude: f64,
+    longitude: f64,
+    elevation: Option<f64>,
+) -> Result<WeatherData, String> {
+    // Pass the real elevation so mountain locations aren't corrected against the model grid
+    let elevation = match elevation {
+        Some(elevation) => Some(elevation),
+        None => location::fetch_elevation(http_client(), latitude, longitude).await.ok(),
+    };
+    
+    let weather = match settings.weather.provider {
+        WeatherProvider::OpenMeteo => {
+            let forecast_url = match &settings.weather.api_keys.openmeteo {
+                Some(key) => format!("{}?apikey={}", OPEN_METEO_CUSTOMER_URL, urlencoding::encode(key)),
+                None => OPEN_METEO_FORECAST_URL.to_string(),
+            };
+            fetch_weather_impl(
+                http_client(),
+                &forecast_url,
+                latitude,
+                longitude,
+                elevation,
+                settings.location.timezone.as_deref(),
+                &settings.units,
+            )
+            .await?
+        }
+        WeatherProvider::OpenWeatherMap => {
+            let api_key = settings
+                .weather
+                .api_keys
+                .openweathermap
+                .as_deref()
+                .ok_or_else(|| "OpenWeatherMap API key is not set".to_string())?;
+            fetch_openweathermap_impl(http_client(), OPENWEATHERMAP_URL, api_key, latitude, longitude, &settings.units).await?
+        }
+    };
+
+    Ok(WeatherData {
+        alerts: weather_alerts_impl(&weather, &settings.weather.alerts)
+            .iter()
+            .map(|alert| weather_codes::localize_label(alert, &settings.language))
+            .collect(),
+        ..weather
+    })
+}
+
+/// Fetch the current weather from an Open-Meteo compatible forecast endpoint
+/// and convert it to the configured units. Without a `timezone` the endpoint
+/// picks it from the coordinates.
+pub async fn fetch_weather_impl(
+    client: &reqwest::Client,
+    forecast_url: &str,
+    latitude: f64,
+    longitude: f64,
+    elevation: Option<f64>,
+    timezone: Option<&str>,
+    units: &UnitsSettings,
+) -> Result<WeatherData, String> {
+    // The URL may already carry a query, e.g. an API key
+    let separator = if forecast_url.contains('?') { '&' } else { '?' };
+    let mut url = format!(
+        "{}{}latitude={}&longitude={}&current=temperature_2m,relative_humidity_2m,rain,snowfall,cloudcover,wind_speed_10m&daily=sunrise,sunset&timezone={}",
+        forecast_url,
+        separator,
+        latitude,
+        longitude,
+        urlencoding::encode(timezone.unwrap_or("auto"))
+    );
+    if let Some(elevation) = elevation {
+        url.push_str(&format!("&elevation={}", elevation));
+    }
+    
+    let response = client
+        .get(&url)
         .send()
         .await
-        .map_err(|e| format!("Failed to fetch location: {}", e))?;
+        .map_err(|e| format!("Failed to fetch weather: {}", e))?;
     
-    let data: IpApiResponse = response
-        .json()
+    let body = response
+        .text()
         .await
-        .map_err(|e| format!("Failed to parse location data: {}", e))?;
+        .map_err(|e| format!("Failed to read weather data: {}", e))?;
     
-    Ok(Location {
-        latitude: data.lat,
-        longitude: data.lon,
-        city: data.city,
-        country: data.country,
-    })
+    parse_weather_response_impl(&body, units)
 }
 
-#[tauri::command]
-async fn get_weather(latitude: f64, longitude: f64) -> Result<WeatherData, String> {
-    let settings = get_settings().unwrap_or_default();
+/// Turn an Open-Meteo forecast response into `WeatherData` in the configured units.
+/// Only the temperature is required; other missing values read as zero and a
+/// missing sunrise/sunset as empty, which the time-of-day logic treats as unknown.
+pub fn parse_weather_response_impl(body: &str, units: &UnitsSettings) -> Result<WeatherData, String> {
+    let data: OpenMeteoResponse = serde_json::from_str(body)
+        .map_err(|e| format!("Failed to parse weather data: {}", e))?;
+    let current = data
+        .current
+        .ok_or_else(|| "Weather data has no current conditions".to_string())?;
+    let temperature_c = current
+        .temperature_2m
+        .ok_or_else(|| "Weather data has no current temperature".to_string())?;
+    let wind_speed_kmh = current.wind_speed_10m.unwrap_or_default();
     
+    Ok(WeatherData {
+        temperature: temperature_in_units(temperature_c, units),
+        temperature_unit: units.temperature_unit.as_str().to_string(),
+        humidity: current.relative_humidity_2m.unwrap_or_default(),
+        wind_speed: wind_speed_in_units(wind_speed_kmh, units),
+        wind_speed_unit: units.wind_speed_unit.as_str().to_string(),
+        wind_speed_label: units.wind_speed_unit.label().to_string(),
+        cloudcover: current.cloudcover.unwrap_or_default(),
+        rain: current.rain.unwrap_or_default(),
+        snowfall: current.snowfall.unwrap_or_default(),
+        sunrise: data.daily.sunrise.first().cloned().unwrap_or_default(),
+        sunset: data.daily.sunset.first().cloned().unwrap_or_default(),
+        timezone: data.timezone.unwrap_or_else(|| "UTC".to_string()),
+        alerts: Vec::new(),
+    })
+}
+
+/// Convert a temperature in °C to the configured unit
+fn temperature_in_units(celsius: f64, units: &UnitsSettings) -> f64 {
+    match units.temperature_unit {
+        TemperatureUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+        TemperatureUnit::Celsius => celsius,
+    }
+}
+
+/// Convert a wind speed in km/h to the configured unit
+fn wind_speed_in_units(kmh: f64, units: &UnitsSettings) -> f64 {
+    match units.wind_speed_unit {
+        WindSpeedUnit::Mph => kmh * 0.621371,
+        WindSpeedUnit::Ms => kmh / 3.6,
+        WindSpeedUnit::Kmh => kmh,
+    }
+}
+
+/// Fetch the current weather from OpenWeatherMap and convert it to the configured units
+pub async fn fetch_openweathermap_impl(
+    client: &reqwest::Client,
+    api_url: &str,
+    api_key: &str,
+    latitude: f64,
+    longitude: f64,
+    units: &UnitsSettings,
+) -> Result<WeatherData, String> {
     let url = format!(
-        "https://api.open-meteo.com/v1/forecast?latitude={}&longitude={}&current=temperature_2m,relative_humidity_2m,rain,snowfall,cloudcover,wind_speed_10m&daily=sunrise,sunset&timezone=auto",
-        latitude, longitude
+        "{}?lat={}&lon={}&units=metric&appid={}",
+        api_url,
+        latitude,
+        longitude,
+        urlencoding::encode(api_key)
     );
-    
-    let response = http_client()
+
+    let response = client
         .get(&url)
         .send()
         .await
         .map_err(|e| format!("Failed to fetch weather: {}", e))?;
-    
-    let data: OpenMeteoResponse = response
-        .json()
+
+    let status = response.status();
+    let body = response
+        .text()
         .await
+        .map_err(|e| format!("Failed to read weather data: {}", e))?;
+    if !status.is_success() {
+        return Err(format!("OpenWeatherMap API error ({}): {}", status, body));
+    }
+
+    parse_openweathermap_response_impl(&body, units)
+}
+
+/// Turn an OpenWeatherMap current weather response (metric units) into `WeatherData`.
+/// Sunrise and sunset become local times like Open-Meteo's, using the reported UTC offset.
+pub fn parse_openweathermap_response_impl(body: &str, units: &UnitsSettings) -> Result<WeatherData, String> {
+    let data: OpenWeatherMapResponse = serde_json::from_str(body)
         .map_err(|e| format!("Failed to parse weather data: {}", e))?;
-    
-    // Convert temperature based on user settings
-    let temperature = match settings.units.temperature_unit.as_str() {
-        "fahrenheit" => data.current.temperature_2m * 9.0 / 5.0 + 32.0,
-        _ => data.current.temperature_2m, // celsius is default
-    };
-    
-    // Convert wind speed based on user settings
-    let wind_speed = match settings.units.wind_speed_unit.as_str() {
-        "mph" => data.current.wind_speed_10m * 0.621371,
-        "ms" => data.current.wind_speed_10m / 3.6,
-        _ => data.current.wind_speed_10m, // kmh is default
+    let temperature_c = data
+        .main
+        .temp
+        .ok_or_else(|| "Weather data has no current temperature".to_string())?;
+    // Wind speed is in m/s with metric units
+    let wind_speed_kmh = data.wind.speed.unwrap_or_default() * 3.6;
+    let offset = data.timezone.unwrap_or_default() as i64;
+
+    let local_time = |timestamp: Option<f64>| {
+        timestamp
+            .and_then(|t| chrono::DateTime::from_timestamp(t as i64 + offset, 0))
+            .map(|t| t.naive_utc().format("%Y-%m-%dT%H:%M").to_string())
+            .unwrap_or_default()
     };
-    
-    // Get wind speed label
-    let wind_speed_label = match settings.units.wind_speed_unit.as_str() {
-        "mph" => "mph",
-        "ms" => "m/s",
-        _ => "km/h",
-    }.to_string();
-    
+    let timezone = chrono::FixedOffset::east_opt(offset as i32)
+        .map(|offset| format!("UTC{}", offset))
+        .unwrap_or_else(|| "UTC".to_string());
+
     Ok(WeatherData {
-        temperature,
-        temperature_unit: settings.units.temperature_unit.clone(),
-        humidity: data.current.relative_humidity_2m,
-        wind_speed,
-        wind_speed_unit: settings.units.wind_speed_unit.clone(),
-        wind_speed_label,
-        cloudcover: data.current.cloudcover,
-        rain: data.current.rain,
-        snowfall: data.current.snowfall,
-        sunrise: data.daily.sunrise.get(0).cloned().unwrap_or_default(),
-        sunset: data.daily.sunset.get(0).cloned().unwrap_or_default(),
-        timezone: data.timezone,
+        temperature: temperature_in_units(temperature_c, units),
+        temperature_unit: units.temperature_unit.as_str().to_string(),
+        humidity: data.main.humidity.unwrap_or_default(),
+        wind_speed: wind_speed_in_units(wind_speed_kmh, units),
+        wind_speed_unit: units.wind_speed_unit.as_str().to_string(),
+        wind_speed_label: units.wind_speed_unit.label().to_string(),
+        cloudcover: data.clouds.all.unwrap_or_default(),
+        rain: data.rain.one_hour.unwrap_or_default(),
+        // Snow is reported as water equivalent in mm; roughly 1 mm makes 1 cm of snow
+        snowfall: data.snow.one_hour.unwrap_or_default(),
+        sunrise: local_time(data.sys.sunrise),
+        sunset: local_time(data.sys.sunset),
+        timezone,
+        alerts: Vec::new(),
     })
 }
 
+/// Labels for the conditions worth flagging, per the alert settings
+pub fn weather_alerts_impl(weather: &WeatherData, alerts: &WeatherAlertSettings) -> Vec<String> {
+    if !alerts.enabled {
+        return Vec::new();
+    }
+
+    // Thresholds are metric, so undo the unit conversion
+    let temperature_c = match weather.temperature_unit.as_str() {
+        "fahrenheit" => (weather.temperature - 32.0) * 5.0 / 9.0,
+        _ => weather.temperature,
+    };
+    let wind_speed_kmh = match weather.wind_speed_unit.as_str() {
+        "mph" => weather.wind_speed / 0.621371,
+        "ms" => weather.wind_speed * 3.6,
+        _ => weather.wind_speed,
+    };
+
+    let mut labels = Vec::new();
+    if alerts.wind && wind_speed_kmh >= 50.0 {
+        labels.push("Strong wind".to_string());
+    }
+    if alerts.precipitation && weather.snowfall > 0.0 {
+        labels.push("Snow".to_string());
+    } else if alerts.precipitation && weather.rain >= 4.0 {
+        labels.push("Heavy rain".to_string());
+    }
+    if alerts.frost && temperature_c <= 0.0 {
+        labels.push("Frost".to_string());
+    }
+    labels
+}
+
 #[tauri::command]
 fn get_season() -> Season {
     get_season_impl()
@@ -583,28 +1228,143 @@ fn get_time_of_day(sunrise_iso: Option<String>, sunset_iso: Option<String>) -> T
 
 #[tauri::command]
 fn build_photo_query(
+    window: tauri::WebviewWindow,
+    manager: State<'_, SettingsManager>,
     cloudcover: f64,
     rain: f64,
     snowfall: f64,
     sunrise_iso: Option<String>,
     sunset_iso: Option<String>,
-    enable_festive: Option<bool>,
 ) -> PhotoQuery {
-    build_photo_query_impl(cloudcover, rain, snowfall, sunrise_iso, sunset_iso, enable_festive)
+    // An active scene with query templates replaces the weather-based query;
+    // each monitor may have its own query settings
+    let settings = manager.get().unwrap_or_default();
+    let settings = monitors::settings_for_window(&window, &settings).unwrap_or(settings);
+    let query = match scenes::pick_query_template(&settings.scenes) {
+        Some(template) => {
+            let tod = get_time_of_day_impl(sunrise_iso, sunset_iso);
+            let season = get_season_impl();
+            scenes::render_query_template(template, &season.season, &tod.time_of_day)
+        }
+        None => {
+            let festive = settings.photos.enable_festive_queries;
+            // An imminent countdown event with photo keywords wins over the built-in holidays
+            match countdowns::photo_keywords(&settings.countdowns, Local::now().date_naive()).filter(|_| festive) {
+                Some(keywords) => keywords,
+                None => build_photo_query_impl(cloudcover, rain, snowfall, sunrise_iso, sunset_iso, Some(festive)).query,
+            }
+        }
+    };
+    let query = match settings.sports.match_day_keywords.as_deref().map(str::trim).filter(|k| !k.is_empty()) {
+        Some(keywords) if sports::is_match_day(&settings.sports) => format!("{} {}", query, keywords),
+        _ => query,
+    };
+
+    PhotoQuery {
+        query: customize_photo_query_impl(query, &settings.photos.query),
+    }
+}
+
+/// Apply the query settings to a generated query: a fixed query replaces it
+/// and extra keywords are appended
+pub fn customize_photo_query_impl(query: String, settings: &PhotoQuerySettings) -> String {
+    if let Some(fixed) = settings.fixed.as_deref().map(str::trim).filter(|q| !q.is_empty()) {
+        return fixed.to_string();
+    }
+    match settings.extra_keywords.as_deref().map(str::trim).filter(|k| !k.is_empty()) {
+        Some(keywords) => format!("{} {}", query, keywords),
+        None => query,
+    }
+}
+
+/// Parse a photo quality setting (supports both string numbers like "100" and legacy text like "high")
+pub fn photo_quality_value(quality: &str) -> u32 {
+    match quality {
+        // Legacy string values (backwards compatibility)
+        "low" => 65,
+        "medium" => 80,
+        "high" => 100,
+        "maximum" => 100,
+        // Parse numeric strings directly
+        _ => quality.parse::<u32>().unwrap_or(80)
+    }
+}
+
+/// Find the quality schedule window covering a time of day.
+/// Windows whose end is before their start wrap past midnight.
+pub fn scheduled_quality_impl(schedule: &[QualityWindow], time: chrono::NaiveTime) -> Option<&QualityWindow> {
+    schedule
+        .iter()
+        .find(|window| in_time_window(&window.start, &window.end, time))
+}
+
+/// Whether `time` falls in the "HH:MM" window [start, end), which may wrap past midnight
+pub fn in_time_window(start: &str, end: &str, time: chrono::NaiveTime) -> bool {
+    let (Ok(start), Ok(end)) = (
+        chrono::NaiveTime::parse_from_str(start, "%H:%M"),
+        chrono::NaiveTime::parse_from_str(end, "%H:%M"),
+    ) else {
+        return false;
+    };
+
+    if start <= end {
+        time >= start && time < end
+    } else {
+        time >= start || time < end
+    }
 }
 
 #[tauri::command]
-async fn get_unsplash_photo(width: u32, height: u32, query: String) -> Result<UnsplashPhoto, String> {
-    let url = format!(
-        "https://api.unsplash.com/photos/random?orientation=landscape&query={}&w={}&h={}",
-        urlencoding::encode(&query),
+async fn get_unsplash_photo(
+    manager: State<'_, SettingsManager>,
+    width: u32,
+    height: u32,
+    query: String,
+) -> Result<UnsplashPhoto, String> {
+    let settings = manager.get().unwrap_or_default();
+    let photos = &settings.photos;
+    let api_key = photo_api_key(photos)?;
+    let photo = match photos.provider {
+        PhotoProvider::Unsplash => {
+            fetch_unsplash_photo_impl(http_client(), UNSPLASH_API_URL, &api_key, width, height, &query, photos).await
+        }
+        PhotoProvider::Pexels => {
+            fetch_pexels_photo_impl(http_client(), PEXELS_API_URL, &api_key, width, height, &query, photos).await
+        }
+    };
+    health::record("photos", &photo);
+    metrics::global().record_fetch("photo", photos.provider.as_str(), photo.is_ok());
+    photo
+}
+
+/// Fetch a random photo from an Unsplash compatible API and build the image
+/// URL with the configured quality, schedule and bandwidth adjustments
+pub async fn fetch_unsplash_photo_impl(
+    client: &reqwest::Client,
+    api_url: &str,
+    access_key: &str,
+    width: u32,
+    height: u32,
+    query: &str,
+    photos: &PhotosSettings,
+) -> Result<UnsplashPhoto, String> {
+    let mut url = format!(
+        "{}/photos/random?orientation={}&w={}&h={}",
+        api_url,
+        photos.query.orientation.as_str(),
         width,
         height
     );
+    // Unsplash doesn't accept a search query together with collections
+    if photos.collections.is_empty() {
+        url.push_str(&format!("&query={}", urlencoding::encode(query)));
+    } else {
+        url.push_str(&format!("&collections={}", urlencoding::encode(&photos.collections.join(","))));
+    }
 
-    let response = http_client()
+    let response = client
         .get(&url)
-        .header("Authorization", format!("Client-ID {}", unsplash_access_key()))
+        .header("Authorization", format!("Client-ID {}", access_key))
         .send()
         .await
         .map_err(|e| format!("Failed to fetch photo: {}", e))?;
@@ -616,11 +1376,96 @@ async fn get_unsplash_photo(width: u32, height: u32, query: String) -> Result<Un
         return Err(format!("Unsplash API error ({}): {}", status, error_text));
     }
     
-    let data: UnsplashApiResponse = response
-        .json()
+    let body = response
+        .text()
         .await
-        .map_err(|e| format!("Failed to parse photo data: {}", e))?;
+        .map_err(|e| format!("Failed to read photo data: {}", e))?;
+    let photo = parse_unsplash_response_impl(&body)?;
     
+    Ok(UnsplashPhoto {
+        url: sized_photo_url(&photo.url, width, height, photos),
+        ..photo
+    })
+}
+
+/// Fetch a random photo from a Pexels search, or from one of the configured
+/// collections, and build the image URL like `fetch_unsplash_photo_impl`
+pub async fn fetch_pexels_photo_impl(
+    client: &reqwest::Client,
+    api_url: &str,
+    api_key: &str,
+    width: u32,
+    height: u32,
+    query: &str,
+    photos: &PhotosSettings,
+) -> Result<UnsplashPhoto, String> {
+    let url = match photos.collections.choose(&mut rand::rng()) {
+        Some(collection) => format!(
+            "{}/v1/collections/{}?type=photos&per_page=80",
+            api_url,
+            urlencoding::encode(collection)
+        ),
+        None => {
+            // Pexels calls it "square"
+            let orientation = match photos.query.orientation {
+                PhotoOrientation::Squarish => "square",
+                orientation => orientation.as_str(),
+            };
+            format!(
+                "{}/v1/search?query={}&orientation={}&per_page=80",
+                api_url,
+                urlencoding::encode(query),
+                orientation
+            )
+        }
+    };
+
+    let response = client
+        .get(&url)
+        .header("Authorization", api_key)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch photo: {}", e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+        return Err(format!("Pexels API error ({}): {}", status, error_text));
+    }
+
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read photo data: {}", e))?;
+    let photo = parse_pexels_response_impl(&body)?;
+
+    Ok(UnsplashPhoto {
+        url: sized_photo_url(&photo.url, width, height, photos),
+        ..photo
+    })
+}
+
+/// Pick a random photo from a Pexels search or collection response
+pub fn parse_pexels_response_impl(body: &str) -> Result<UnsplashPhoto, String> {
+    let data: PexelsResponse = serde_json::from_str(body)
+        .map_err(|e| format!("Failed to parse photo data: {}", e))?;
+    let usable: Vec<&PexelsPhoto> = data.photos.iter().filter(|photo| photo.src.original.is_some()).collect();
+    let photo = usable
+        .choose(&mut rand::rng())
+        .ok_or_else(|| "No photos found".to_string())?;
+
+    Ok(UnsplashPhoto {
+        url: photo.src.original.clone().unwrap_or_default(),
+        author: photo.photographer.clone().unwrap_or_else(|| "Unknown".to_string()),
+        author_url: photo.photographer_url.clone().unwrap_or_else(|| "https://www.pexels.com".to_string()),
+        // Pexels has no download tracking
+        download_location: String::new(),
+    })
+}
+
+/// Size and compress a provider image URL according to the photo settings,
+/// the quality schedule and the bandwidth mode
+fn sized_photo_url(url: &str, width: u32, height: u32, photos: &PhotosSettings) -> String {
     // Add cache-busting timestamp to prevent browser/CDN caching
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -628,21 +1473,30 @@ async fn get_unsplash_photo(width: u32, height: u32, query: String) -> Result<Un
         .as_millis();
     
     // Apply photo quality setting
-    let settings = get_settings().unwrap_or_default();
+    let mut quality = photo_quality_value(&photos.photo_quality);
+    let (mut width, mut height) = (width, height);
     
-    // Parse quality as number (supports both string numbers like "100" and legacy text like "high")
-    let quality = match settings.photos.photo_quality.as_str() {
-        // Legacy string values (backwards compatibility)
-        "low" => 65,
-        "medium" => 80,
-        "high" => 100,
-        "maximum" => 100,
-        // Parse numeric strings directly
-        _ => settings.photos.photo_quality.parse::<u32>().unwrap_or(80)
+    // Time-of-day schedule, e.g. low quality overnight when nobody is looking
+    if let Some(window) = scheduled_quality_impl(&photos.quality_schedule, Local::now().time()) {
+        quality = photo_quality_value(&window.quality);
+        let scale = window.resolution_scale.clamp(0.1, 1.0);
+        width = (width as f64 * scale) as u32;
+        height = (height as f64 * scale) as u32;
+    }
+    
+    // Request smaller, more compressed images while the connection is slow
+    let (width, height, quality) = if bandwidth::is_degraded() {
+        (
+            (width as f64 * bandwidth::DEGRADED_RESOLUTION_SCALE) as u32,
+            (height as f64 * bandwidth::DEGRADED_RESOLUTION_SCALE) as u32,
+            quality.min(bandwidth::DEGRADED_MAX_QUALITY),
+        )
+    } else {
+        (width, height, quality)
     };
     
     // Parse the URL and replace existing quality parameter
-    let mut url = data.urls.regular.clone();
+    let mut url = url.to_string();
     
     // Remove existing quality parameter if present
     if let Some(pos) = url.find("&q=") {
@@ -655,21 +1509,44 @@ async fn get_unsplash_photo(width: u32, height: u32, query: String) -> Result<Un
     
     // Add our parameters
     let separator = if url.contains('?') { "&" } else { "?" };
-    let photo_url = format!("{}{}w={}&h={}&fit=crop&q={}&t={}", url, separator, width, height, quality, timestamp);
-    
+    format!("{}{}w={}&h={}&fit=crop&q={}&t={}", url, separator, width, height, quality, timestamp)
+}
+
+/// Read a random-photo response. The image URL is required; missing credit
+/// details fall back to generic Unsplash attribution.
+pub fn parse_unsplash_response_impl(body: &str) -> Result<UnsplashPhoto, String> {
+    let data: UnsplashApiResponse = serde_json::from_str(body)
+        .map_err(|e| format!("Failed to parse photo data: {}", e))?;
+    let url = data
+        .urls
+        .regular
+        .ok_or_else(|| "Photo data has no image URL".to_string())?;
+
     Ok(UnsplashPhoto {
-        url: photo_url,
-        author: data.user.name,
-        author_url: data.user.links.html,
-        download_location: data.links.download_location,
+        url,
+        author: data.user.name.unwrap_or_else(|| "Unknown".to_string()),
+        author_url: data.user.links.html.unwrap_or_else(|| "https://unsplash.com".to_string()),
+        download_location: data.links.download_location.unwrap_or_default(),
     })
 }
 
 #[tauri::command]
-async fn trigger_unsplash_download(download_url: String) -> Result<(), String> {
+async fn trigger_unsplash_download(manager: State<'_, SettingsManager>, download_url: String) -> Result<(), String> {
+    // Nothing to report when the photo data had no download link
+    if download_url.is_empty() {
+        return Ok(());
+    }
+    
+    let settings = manager.get().unwrap_or_default();
+    let access_key = settings
+        .photos
+        .api_keys
+        .unsplash
+        .filter(|key| !key.trim().is_empty())
+        .unwrap_or_else(|| unsplash_access_key().to_string());
     let _response = http_client()
         .get(&download_url)
-        .header("Authorization", format!("Client-ID {}", unsplash_access_key()))
+        .header("Authorization", format!("Client-ID {}", access_key.trim()))
         .send()
         .await
         .map_err(|e| format!("Failed to trigger download: {}", e))?;
@@ -677,72 +1554,123 @@ async fn trigger_unsplash_download(download_url: String) -> Result<(), String> {
     Ok(())
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Default)]
 pub struct CpuTemp {
     pub value: f32,
     pub display: String,
+    pub gpu_value: Option<f32>,  // °C, None when the machine doesn't report it
+    pub gpu_display: String,  // e.g. "58 °C", empty without a GPU reading
 }
 
 #[tauri::command]
-fn get_cpu_temp() -> Result<CpuTemp, String> {
-    #[cfg(target_os = "linux")]
-    {
-        match std::fs::read_to_string("/sys/class/thermal/thermal_zone0/temp") {
-            Ok(contents) => {
-                let temp_millidegrees: i32 = contents.trim()
-                    .parse()
-                    .map_err(|e| format!("Failed to parse temperature: {}", e))?;
-                let temp_celsius = temp_millidegrees as f32 / 1000.0;
-                
-                if temp_celsius <= 0.0 {
-                    return Ok(CpuTemp {
-                        value: 0.0,
-                        display: String::new(),
-                    });
-                }
-                
-                // Get settings for unit conversion
-                let settings = get_settings().unwrap_or_default();
-                let (display_temp, unit) = if settings.units.temperature_unit == "fahrenheit" {
-                    (temp_celsius * 9.0 / 5.0 + 32.0, "°F")
-                } else {
-                    (temp_celsius, "°C")
-                };
-                
-                Ok(CpuTemp {
-                    value: temp_celsius,
-                    display: format!("{} {}", display_temp.round() as i32, unit),
-                })
-            }
-            Err(_) => Ok(CpuTemp {
-                value: 0.0,
-                display: String::new(),
-            })
-        }
-    }
-    
-    #[cfg(not(target_os = "linux"))]
-    {
-        Ok(CpuTemp {
-            value: 0.0,
-            display: String::new(),
-        })
+fn get_cpu_temp(manager: State<'_, SettingsManager>) -> Result<CpuTemp, String> {
+    let settings = manager.get().unwrap_or_default();
+    let mut temp = read_cpu_temp_impl(&settings.units)?;
+    // A Pi can throttle on GPU heat while the CPU zone looks fine
+    if let Some(celsius) = thermal::gpu_temperature() {
+        temp.gpu_value = Some(celsius);
+        temp.gpu_display = format_temperature(celsius, &settings.units);
     }
+    Ok(temp)
 }
 
+/// CPU utilization since the frame last asked, per core and in total, with
+/// the 1/5/15 minute load averages
 #[tauri::command]
-fn get_current_time() -> FormattedTime {
-    get_current_time_impl()
+async fn get_cpu_usage() -> Result<system::CpuUsage, String> {
+    // The first reading sleeps a moment between two samples
+    tauri::async_runtime::spawn_blocking(system::cpu_usage)
+        .await
+        .map_err(|e| format!("Failed to read CPU usage: {}", e))?
+        .ok_or_else(|| "CPU usage is unavailable".to_string())
 }
 
+/// Strength of the Wi-Fi link, None when the frame isn't on Wi-Fi
 #[tauri::command]
-fn get_precipitation_display(weather: WeatherData) -> PrecipitationDisplay {
-    get_precipitation_display_impl(weather)
+fn get_wifi_signal() -> Option<system::WifiSignal> {
+    system::wifi_signal()
 }
 
+/// Charge of the laptop or tablet the frame runs on, None without a battery
 #[tauri::command]
-fn is_cache_valid(cache_timestamp: u64) -> bool {
-    is_cache_valid_impl(cache_timestamp)
+async fn get_battery() -> Result<Option<battery::BatteryStatus>, String> {
+    tauri::async_runtime::spawn_blocking(battery::status)
+        .await
+        .map_err(|e| format!("Failed to read the battery: {}", e))
+}
+
+/// Memory used by the machine and by the app with its webview
+#[tauri::command]
+async fn get_memory() -> Result<system::MemoryStatus, String> {
+    // Measuring the app walks the process list
+    tauri::async_runtime::spawn_blocking(system::memory_status)
+        .await
+        .map_err(|e| format!("Failed to read memory usage: {}", e))
+}
+
+/// A temperature in °C, rounded and in the configured unit: "52 °C", "126 °F"
+pub fn format_temperature(celsius: f32, units: &UnitsSettings) -> String {
+    let (value, unit) = if units.temperature_unit == TemperatureUnit::Fahrenheit {
+        (celsius * 9.0 / 5.0 + 32.0, "°F")
+    } else {
+        (celsius, "°C")
+    };
+    format!("{} {}", value.round() as i32, unit)
+}
+
+/// Read the CPU temperature and format it in the configured unit
+pub fn read_cpu_temp_impl(units: &UnitsSettings) -> Result<CpuTemp, String> {
+    Ok(match thermal::cpu_temperature() {
+        Some(celsius) => CpuTemp {
+            value: celsius,
+            display: format_temperature(celsius, units),
+            ..CpuTemp::default()
+        },
+        None => CpuTemp::default(),
+    })
+}
+
+#[tauri::command]
+fn get_current_time(manager: State<'_, SettingsManager>) -> FormattedTime {
+    let settings = manager.get().unwrap_or_default();
+    get_current_time_impl(&settings.units, &settings.language)
+}
+
+/// The time in each of display.world_clocks, in the frame's time format
+#[tauri::command]
+fn get_world_clocks(manager: State<'_, SettingsManager>) -> Vec<WorldClockTime> {
+    let settings = manager.get().unwrap_or_default();
+    get_world_clocks_impl(&settings.display.world_clocks, &settings.units, &settings.language, Local::now())
+}
+
+#[tauri::command]
+fn get_precipitation_display(manager: State<'_, SettingsManager>, weather: WeatherData) -> PrecipitationDisplay {
+    let settings = manager.get().unwrap_or_default();
+    get_precipitation_display_impl(weather, &settings.language)
+}
+
+#[tauri::command]
+fn is_cache_valid(manager: State<'_, SettingsManager>, cache_timestamp: u64) -> bool {
+    let settings = manager.get().unwrap_or_default();
+    is_cache_valid_impl(cache_timestamp, &settings.photos)
+}
+
+#[tauri::command]
+fn get_display_state(manager: State<'_, SettingsManager>, weather_available: bool) -> display_state::DisplayState {
+    let settings = manager.get().unwrap_or_default();
+    let available = display_state::DataAvailability {
+        weather: weather_available,
+        // Only probe the sensor when the widget is enabled
+        cpu_temp: settings.display.show_cpu_temp && read_cpu_temp_impl(&settings.units).is_ok(),
+        wifi: settings.display.show_wifi && system::wifi_signal().is_some(),
+        battery: settings.display.show_battery && battery::status().is_some(),
+    };
+    display_state::resolve_display_state_impl(&settings.display, available, bandwidth::is_degraded())
+}
+
+#[tauri::command]
+fn report_photo_download(bytes: u64, duration_ms: u64) -> bandwidth::NetworkMode {
+    bandwidth::record_photo_download_impl(bytes, duration_ms)
 }
 
 #[tauri::command]
@@ -751,7 +1679,9 @@ fn format_time_remaining(milliseconds: i64) -> String {
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 fn get_debug_info(
+    manager: State<'_, SettingsManager>,
     cache_timestamp: Option<u64>,
     query: Option<String>,
     sunrise_iso: Option<String>,
@@ -761,6 +1691,34 @@ fn get_debug_info(
     rain: Option<f64>,
     snowfall: Option<f64>,
     cloudcover: Option<f64>,
+) -> DebugInfo {
+    let settings = manager.get().unwrap_or_default();
+    debug_info_impl(
+        &settings,
+        cache_timestamp,
+        query,
+        sunrise_iso,
+        sunset_iso,
+        temperature,
+        rain,
+        snowfall,
+        cloudcover,
+    )
+}
+
+/// Troubleshooting details for the debug overlay and `GET /api/debug`.
+/// API keys are only reported as present or missing.
+#[allow(clippy::too_many_arguments)]
+pub fn debug_info_impl(
+    settings: &Settings,
+    cache_timestamp: Option<u64>,
+    query: Option<String>,
+    sunrise_iso: Option<String>,
+    sunset_iso: Option<String>,
+    temperature: Option<f64>,
+    rain: Option<f64>,
+    snowfall: Option<f64>,
+    cloudcover: Option<f64>,
 ) -> DebugInfo {
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -800,23 +1758,45 @@ fn get_debug_info(
     let season_info = get_season();
     
     // Check API key availability
-    let (api_key_status, api_key_source) = match std::env::var("UNSPLASH_ACCESS_KEY") {
-        Ok(key) if key.len() > 10 && key != "YOUR_UNSPLASH_ACCESS_KEY" => {
-            ("Available".to_string(), "Runtime env".to_string())
-        },
-        _ => {
-            match option_env!("UNSPLASH_ACCESS_KEY") {
-                Some(key) if key.len() > 10 && key != "YOUR_UNSPLASH_ACCESS_KEY" => {
-                    ("Available".to_string(), "Compile-time".to_string())
-                },
-                _ => ("Missing or invalid".to_string(), "None".to_string())
+    let configured_key = match settings.photos.provider {
+        PhotoProvider::Unsplash => &settings.photos.api_keys.unsplash,
+        PhotoProvider::Pexels => &settings.photos.api_keys.pexels,
+    };
+    let (api_key_status, api_key_source) = if configured_key.as_deref().is_some_and(|key| !key.trim().is_empty()) {
+        ("Available".to_string(), "Settings".to_string())
+    } else if settings.photos.provider == PhotoProvider::Pexels {
+        // Only Unsplash has a fallback key
+        ("Missing or invalid".to_string(), "None".to_string())
+    } else {
+        match std::env::var("UNSPLASH_ACCESS_KEY") {
+            Ok(key) if key.len() > 10 && key != "YOUR_UNSPLASH_ACCESS_KEY" => {
+                ("Available".to_string(), "Runtime env".to_string())
+            },
+            _ => {
+                match option_env!("UNSPLASH_ACCESS_KEY") {
+                    Some(key) if key.len() > 10 && key != "YOUR_UNSPLASH_ACCESS_KEY" => {
+                        ("Available".to_string(), "Compile-time".to_string())
+                    },
+                    _ => ("Missing or invalid".to_string(), "None".to_string())
+                }
             }
         }
     };
     
-    // Get settings for temperature unit
-    let settings = get_settings().unwrap_or_default();
-    let temp_unit = settings.units.temperature_unit.as_str();
+    let network = bandwidth::current_mode();
+    let network_mode = match network.throughput_kbps {
+        Some(kbps) => format!("{} ({:.0} KB/s)", network.mode, kbps),
+        None => network.mode,
+    };
+    
+    let clock = match ntp::status() {
+        Some(status) if status.drifted => format!("{} (drifted)", ntp::describe_offset(status.offset_ms)),
+        Some(status) => ntp::describe_offset(status.offset_ms),
+        None => "unknown".to_string(),
+    };
+    
+    // Temperature unit for display
+    let temp_unit = settings.units.temperature_unit;
     
     DebugInfo {
         photo_age,
@@ -826,7 +1806,7 @@ fn get_debug_info(
         api_key_status,
         api_key_source,
         temperature: temperature.map(|t| {
-            if temp_unit == "fahrenheit" {
+            if temp_unit == TemperatureUnit::Fahrenheit {
                 format!("{:.1}°F", t)
             } else {
                 format!("{:.1}°C", t)
@@ -836,6 +1816,8 @@ fn get_debug_info(
         snowfall: snowfall.map(|s| format!("{:.1}cm", s)).unwrap_or_else(|| "n/a".to_string()),
         cloudcover: cloudcover.map(|c| format!("{}%", c as i32)).unwrap_or_else(|| "n/a".to_string()),
         season: season_info.season,
+        network_mode,
+        clock,
     }
 }
 
@@ -849,15 +1831,17 @@ pub fn run() {
         .setup(|app| {
             let app_handle = app.handle().clone();
             
-            // Start HTTP server in a separate thread with app handle
-            std::thread::spawn(move || {
-                let runtime = tokio::runtime::Runtime::new().unwrap();
-                runtime.block_on(async move {
-                    if let Err(e) = http_server::start_server(8737, app_handle).await {
-                        eprintln!("HTTP server error: {}", e);
-                    }
-                });
-            });
+            // One settings store shared by the commands and the HTTP server
+            let settings_manager = SettingsManager::new()?;
+            app.manage(settings_manager.clone());
+            
+            // A frame on every monitor when asked to, beside the main window
+            if let Err(e) = monitors::open_windows(&app_handle, &settings_manager.get()?.monitors) {
+                eprintln!("{}", e);
+            }
+
+            // Start the HTTP server on its own thread, supervised so it can stop gracefully
+            app.manage(http_server::spawn_server(app_handle, settings_manager));
             
             Ok(())
         })
@@ -867,6 +1851,10 @@ pub fn run() {
             get_weather,
             get_unsplash_photo,
             get_cpu_temp,
+            get_cpu_usage,
+            get_memory,
+            get_wifi_signal,
+            get_battery,
             trigger_unsplash_download,
             get_season,
             get_holiday,
@@ -876,11 +1864,51 @@ pub fn run() {
             get_precipitation_display,
             is_cache_valid,
             format_time_remaining,
+            report_photo_download,
+            get_display_state,
             get_debug_info,
             get_settings,
+            get_window_settings,
             save_settings,
             reset_settings,
+            get_api_token,
+            rotate_api_token,
+            set_current_photo,
+            backup_settings,
+            restore_settings,
+            export_settings,
+            import_settings,
+            get_brightness,
+            set_brightness,
+            get_update_info,
+            get_calendar_events,
+            get_greeting,
+            get_quote,
+            get_headlines,
+            get_stocks,
+            get_crypto_prices,
+            get_now_playing,
+            get_sports,
+            get_tasks,
+            get_countdowns,
+            get_world_clocks,
+            start_timer,
+            get_timer,
+            cancel_timer,
+            get_night_mode,
+            get_display_sleep,
+            get_connectivity,
+            get_pixel_shift,
+            frame_heartbeat,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| {
+            // Don't lose a settings change still waiting to be written
+            if let tauri::RunEvent::Exit = event {
+                app_handle.state::<SettingsManager>().flush();
+                // Let requests in flight finish rather than cutting them off
+                app_handle.state::<http_server::ServerSupervisor>().shutdown();
+            }
+        });
 }
\ No newline at end of file
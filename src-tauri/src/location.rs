@@ -0,0 +1,647 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use crate::lenient;
+use crate::settings_manager::{self, LocationMode, LocationSettings, SavedLocation};
+
+pub use idleview_api::Location;
+
+const LAST_LOCATION_FILE: &str = "last_location.json";
+const ELEVATION_CACHE_FILE: &str = "elevation_cache.json";
+const OPEN_METEO_ELEVATION_URL: &str = "https://api.open-meteo.com/v1/elevation";
+
+/// How long a serial GPS gets to report a fix
+#[cfg(target_os = "linux")]
+const GPS_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Pause between reads while the GPS has nothing new
+#[cfg(target_os = "linux")]
+const GPS_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+static ELEVATION_CACHE: OnceLock<Mutex<HashMap<String, f64>>> = OnceLock::new();
+
+/// An IP geolocation service. Providers only describe the endpoint and how
+/// to read its response; the HTTP request and failover live in `fetch_ip_location`.
+pub trait LocationProvider: Sync {
+    /// Recorded as the `source` of locations from this provider
+    fn name(&self) -> &'static str;
+    fn url(&self) -> &'static str;
+    fn parse(&self, body: &str) -> Result<Location, String>;
+}
+
+/// ip-api.com (free tier is HTTP-only and rate-limited)
+pub struct IpApi;
+
+/// ipwho.is
+pub struct IpWhoIs;
+
+/// ipinfo.io
+pub struct IpInfo;
+
+/// Providers in the order they are tried: the HTTPS ones first, and ip-api,
+/// whose free tier sends the location in the clear, only when both fail
+pub static IP_PROVIDERS: &[&dyn LocationProvider] = &[&IpWhoIs, &IpInfo, &IpApi];
+
+// Responses are parsed leniently, see `lenient`; only coordinates are required
+
+#[derive(Debug, Deserialize)]
+struct IpApiResponse {
+    #[serde(default, deserialize_with = "lenient::string_or_none")]
+    status: Option<String>,
+    #[serde(default, deserialize_with = "lenient::string_or_none")]
+    message: Option<String>,
+    #[serde(default, deserialize_with = "lenient::f64_or_none")]
+    lat: Option<f64>,
+    #[serde(default, deserialize_with = "lenient::f64_or_none")]
+    lon: Option<f64>,
+    #[serde(default, deserialize_with = "lenient::string_or_none")]
+    city: Option<String>,
+    #[serde(default, deserialize_with = "lenient::string_or_none")]
+    country: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IpWhoIsResponse {
+    #[serde(default, deserialize_with = "lenient::bool_or_none")]
+    success: Option<bool>,
+    #[serde(default, deserialize_with = "lenient::string_or_none")]
+    message: Option<String>,
+    #[serde(default, deserialize_with = "lenient::f64_or_none")]
+    latitude: Option<f64>,
+    #[serde(default, deserialize_with = "lenient::f64_or_none")]
+    longitude: Option<f64>,
+    #[serde(default, deserialize_with = "lenient::string_or_none")]
+    city: Option<String>,
+    #[serde(default, deserialize_with = "lenient::string_or_none")]
+    country: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IpInfoResponse {
+    #[serde(default, deserialize_with = "lenient::string_or_none")]
+    loc: Option<String>,  // "lat,lon"
+    #[serde(default, deserialize_with = "lenient::string_or_none")]
+    city: Option<String>,
+    #[serde(default, deserialize_with = "lenient::string_or_none")]
+    country: Option<String>,
+}
+
+impl LocationProvider for IpApi {
+    fn name(&self) -> &'static str {
+        "ip-api"
+    }
+
+    fn url(&self) -> &'static str {
+        "http://ip-api.com/json/"
+    }
+
+    fn parse(&self, body: &str) -> Result<Location, String> {
+        let data: IpApiResponse = serde_json::from_str(body)
+            .map_err(|e| format!("Failed to parse location data: {}", e))?;
+        if data.status.as_deref() == Some("fail") {
+            return Err(format!("ip-api lookup failed: {}", data.message.unwrap_or_default()));
+        }
+
+        match (data.lat, data.lon) {
+            (Some(latitude), Some(longitude)) => Ok(Location {
+                latitude,
+                longitude,
+                city: data.city,
+                country: data.country,
+                source: self.name().to_string(),
+                elevation: None,
+            }),
+            _ => Err("ip-api returned no coordinates".to_string()),
+        }
+    }
+}
+
+impl LocationProvider for IpWhoIs {
+    fn name(&self) -> &'static str {
+        "ipwhois"
+    }
+
+    fn url(&self) -> &'static str {
+        "https://ipwho.is/"
+    }
+
+    fn parse(&self, body: &str) -> Result<Location, String> {
+        let data: IpWhoIsResponse = serde_json::from_str(body)
+            .map_err(|e| format!("Failed to parse location data: {}", e))?;
+        if data.success == Some(false) {
+            return Err(format!("ipwho.is lookup failed: {}", data.message.unwrap_or_default()));
+        }
+
+        match (data.latitude, data.longitude) {
+            (Some(latitude), Some(longitude)) => Ok(Location {
+                latitude,
+                longitude,
+                city: data.city,
+                country: data.country,
+                source: self.name().to_string(),
+                elevation: None,
+            }),
+            _ => Err("ipwho.is returned no coordinates".to_string()),
+        }
+    }
+}
+
+impl LocationProvider for IpInfo {
+    fn name(&self) -> &'static str {
+        "ipinfo"
+    }
+
+    fn url(&self) -> &'static str {
+        "https://ipinfo.io/json"
+    }
+
+    fn parse(&self, body: &str) -> Result<Location, String> {
+        let data: IpInfoResponse = serde_json::from_str(body)
+            .map_err(|e| format!("Failed to parse location data: {}", e))?;
+
+        let loc = data.loc.ok_or_else(|| "ipinfo returned no coordinates".to_string())?;
+        let (latitude, longitude) = loc
+            .split_once(',')
+            .and_then(|(lat, lon)| Some((lat.trim().parse().ok()?, lon.trim().parse().ok()?)))
+            .ok_or_else(|| format!("Invalid ipinfo coordinates: {}", loc))?;
+
+        Ok(Location {
+            latitude,
+            longitude,
+            city: data.city,
+            country: data.country,
+            source: self.name().to_string(),
+            elevation: None,
+        })
+    }
+}
+
+/// Pick the saved location that should currently be displayed.
+///
+/// With rotation enabled the saved locations take turns, each shown for
+/// `rotation_interval` minutes. Otherwise the `active` location is used.
+/// Returns `None` when the location should be auto-detected.
+pub fn resolve_saved_location_impl(settings: &LocationSettings, now_ms: u64) -> Option<&SavedLocation> {
+    if settings.saved.is_empty() {
+        return None;
+    }
+
+    if settings.rotate {
+        let interval_ms = settings.rotation_interval.max(1) * 60 * 1000;
+        let index = (now_ms / interval_ms) as usize % settings.saved.len();
+        return settings.saved.get(index);
+    }
+
+    let active = settings.active.as_deref()?;
+    settings.saved.iter().find(|location| location.name == active)
+}
+
+/// The fixed location configured in manual mode, if its coordinates are set
+pub fn manual_location_impl(settings: &LocationSettings) -> Option<Location> {
+    if settings.mode != LocationMode::Manual {
+        return None;
+    }
+
+    Some(Location {
+        latitude: settings.latitude?,
+        longitude: settings.longitude?,
+        city: settings.label.clone(),
+        country: None,
+        source: "manual".to_string(),
+        elevation: None,
+    })
+}
+
+/// Look up the current location from the public IP address, trying each
+/// provider in turn until one answers
+pub async fn fetch_ip_location(client: &reqwest::Client) -> Result<Location, String> {
+    let endpoints: Vec<_> = IP_PROVIDERS
+        .iter()
+        .map(|provider| (*provider, provider.url()))
+        .collect();
+    fetch_ip_location_from(client, &endpoints).await
+}
+
+/// Try providers at the given URLs in order, used directly by tests
+pub async fn fetch_ip_location_from(
+    client: &reqwest::Client,
+    endpoints: &[(&dyn LocationProvider, &str)],
+) -> Result<Location, String> {
+    let mut errors = Vec::new();
+
+    for (provider, url) in endpoints {
+        let result = async {
+            let response = client
+                .get(*url)
+                .timeout(Duration::from_secs(5))
+                .send()
+                .await
+                .map_err(|e| format!("Failed to fetch location: {}", e))?;
+            let body = response
+                .text()
+                .await
+                .map_err(|e| format!("Failed to read location data: {}", e))?;
+            provider.parse(&body)
+        }
+        .await;
+
+        match result {
+            Ok(location) => return Ok(location),
+            Err(e) => errors.push(format!("{}: {}", provider.name(), e)),
+        }
+    }
+
+    Err(format!("All location providers failed ({})", errors.join("; ")))
+}
+
+/// Parse an NMEA `ddmm.mmmm` coordinate with its hemisphere into decimal degrees
+fn parse_nmea_coordinate(value: &str, hemisphere: &str) -> Option<f64> {
+    let raw: f64 = value.parse().ok()?;
+    let degrees = (raw / 100.0).trunc();
+    let minutes = raw - degrees * 100.0;
+    let decimal = degrees + minutes / 60.0;
+
+    match hemisphere {
+        "N" | "E" => Some(decimal),
+        "S" | "W" => Some(-decimal),
+        _ => None,
+    }
+}
+
+/// Extract a position fix from a GGA or RMC NMEA sentence (any talker ID)
+pub fn parse_nmea_position(sentence: &str) -> Option<(f64, f64)> {
+    let sentence = sentence.trim().split('*').next()?;
+    let fields: Vec<&str> = sentence.split(',').collect();
+    let kind = fields.first()?.get(3..6)?;
+
+    let (lat, lat_hemi, lon, lon_hemi) = match kind {
+        // $GPGGA,time,lat,N,lon,E,quality,... (quality 0 = no fix)
+        "GGA" if fields.len() > 6 && fields[6] != "0" && !fields[6].is_empty() => {
+            (fields[2], fields[3], fields[4], fields[5])
+        }
+        // $GPRMC,time,status,lat,N,lon,E,... (status A = valid)
+        "RMC" if fields.len() > 6 && fields[2] == "A" => (fields[3], fields[4], fields[5], fields[6]),
+        _ => return None,
+    };
+
+    Some((
+        parse_nmea_coordinate(lat, lat_hemi)?,
+        parse_nmea_coordinate(lon, lon_hemi)?,
+    ))
+}
+
+/// Read NMEA sentences from a serial GPS device until a position fix arrives
+#[cfg(target_os = "linux")]
+pub async fn fetch_gps_location(device: &str) -> Result<Location, String> {
+    use std::io::{ErrorKind, Read};
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::time::Instant;
+
+    let device = device.to_string();
+    let read_fix = tokio::task::spawn_blocking(move || {
+        // Non-blocking reads against a deadline, so a silent receiver can't
+        // keep this thread, and the device, once the lookup has given up
+        let deadline = Instant::now() + GPS_TIMEOUT;
+        let mut file = fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK | libc::O_NOCTTY)
+            .open(&device)
+            .map_err(|e| format!("Failed to open GPS device {}: {}", device, e))?;
+
+        // A receiver emits several sentences per second, so give it a few hundred lines to get a fix
+        let mut pending = Vec::new();
+        let mut buffer = [0u8; 512];
+        let mut lines = 0;
+        while lines < 500 {
+            if Instant::now() >= deadline {
+                return Err("Timed out waiting for a GPS fix".to_string());
+            }
+            match file.read(&mut buffer) {
+                Ok(0) => std::thread::sleep(GPS_POLL_INTERVAL),
+                Ok(read) => pending.extend_from_slice(&buffer[..read]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => std::thread::sleep(GPS_POLL_INTERVAL),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(format!("Failed to read GPS device: {}", e)),
+            }
+
+            while let Some(end) = pending.iter().position(|&byte| byte == b'\n') {
+                let line: Vec<u8> = pending.drain(..=end).collect();
+                lines += 1;
+                if let Some((latitude, longitude)) = parse_nmea_position(String::from_utf8_lossy(&line).trim_end()) {
+                    return Ok(Location {
+                        latitude,
+                        longitude,
+                        city: None,
+                        country: None,
+                        source: "gps".to_string(),
+                        elevation: None,
+                    });
+                }
+            }
+        }
+
+        Err("GPS device did not report a position fix".to_string())
+    });
+
+    read_fix.await.map_err(|e| format!("GPS reader failed: {}", e))?
+}
+
+#[cfg(target_os = "linux")]
+mod geoclue {
+    use zbus::zvariant::{ObjectPath, OwnedObjectPath};
+
+    #[zbus::proxy(
+        interface = "org.freedesktop.GeoClue2.Manager",
+        default_service = "org.freedesktop.GeoClue2",
+        default_path = "/org/freedesktop/GeoClue2/Manager"
+    )]
+    pub trait Manager {
+        fn get_client(&self) -> zbus::Result<OwnedObjectPath>;
+    }
+
+    #[zbus::proxy(
+        interface = "org.freedesktop.GeoClue2.Client",
+        default_service = "org.freedesktop.GeoClue2"
+    )]
+    pub trait Client {
+        fn start(&self) -> zbus::Result<()>;
+        fn stop(&self) -> zbus::Result<()>;
+
+        #[zbus(property)]
+        fn set_desktop_id(&self, id: &str) -> zbus::Result<()>;
+
+        #[zbus(property)]
+        fn set_requested_accuracy_level(&self, level: u32) -> zbus::Result<()>;
+
+        #[zbus(signal)]
+        fn location_updated(&self, old: ObjectPath<'_>, new: ObjectPath<'_>) -> zbus::Result<()>;
+    }
+
+    #[zbus::proxy(
+        interface = "org.freedesktop.GeoClue2.Location",
+        default_service = "org.freedesktop.GeoClue2"
+    )]
+    pub trait Location {
+        #[zbus(property)]
+        fn latitude(&self) -> zbus::Result<f64>;
+
+        #[zbus(property)]
+        fn longitude(&self) -> zbus::Result<f64>;
+    }
+}
+
+/// Ask the GeoClue2 D-Bus service for the current position
+#[cfg(target_os = "linux")]
+pub async fn fetch_geoclue_location() -> Result<Location, String> {
+    use futures::StreamExt;
+
+    // GeoClue accuracy levels: 4 = city, 8 = exact
+    const ACCURACY_CITY: u32 = 4;
+
+    let connection = zbus::Connection::system()
+        .await
+        .map_err(|e| format!("Failed to connect to system bus: {}", e))?;
+
+    let manager = geoclue::ManagerProxy::new(&connection)
+        .await
+        .map_err(|e| format!("Failed to reach GeoClue: {}", e))?;
+    let client_path = manager
+        .get_client()
+        .await
+        .map_err(|e| format!("Failed to create GeoClue client: {}", e))?;
+
+    let client = geoclue::ClientProxy::builder(&connection)
+        .path(client_path)
+        .map_err(|e| format!("Invalid GeoClue client path: {}", e))?
+        .build()
+        .await
+        .map_err(|e| format!("Failed to create GeoClue client: {}", e))?;
+
+    client
+        .set_desktop_id("idleview")
+        .await
+        .map_err(|e| format!("Failed to register with GeoClue: {}", e))?;
+    client
+        .set_requested_accuracy_level(ACCURACY_CITY)
+        .await
+        .map_err(|e| format!("Failed to set GeoClue accuracy: {}", e))?;
+
+    let mut updates = client
+        .receive_location_updated()
+        .await
+        .map_err(|e| format!("Failed to subscribe to GeoClue updates: {}", e))?;
+    client
+        .start()
+        .await
+        .map_err(|e| format!("Failed to start GeoClue client: {}", e))?;
+
+    let update = tokio::time::timeout(std::time::Duration::from_secs(15), updates.next())
+        .await
+        .map_err(|_| "Timed out waiting for GeoClue location".to_string())?
+        .ok_or_else(|| "GeoClue closed the location stream".to_string())?;
+    let args = update
+        .args()
+        .map_err(|e| format!("Failed to parse GeoClue update: {}", e))?;
+
+    let location = geoclue::LocationProxy::builder(&connection)
+        .path(args.new().to_owned())
+        .map_err(|e| format!("Invalid GeoClue location path: {}", e))?
+        .build()
+        .await
+        .map_err(|e| format!("Failed to read GeoClue location: {}", e))?;
+
+    let latitude = location
+        .latitude()
+        .await
+        .map_err(|e| format!("Failed to read GeoClue latitude: {}", e))?;
+    let longitude = location
+        .longitude()
+        .await
+        .map_err(|e| format!("Failed to read GeoClue longitude: {}", e))?;
+
+    let _ = client.stop().await;
+
+    Ok(Location {
+        latitude,
+        longitude,
+        city: None,
+        country: None,
+        source: "geoclue".to_string(),
+        elevation: None,
+    })
+}
+
+/// Remember a successful lookup so it can be used when offline
+pub fn save_last_location(location: &Location) -> Result<(), String> {
+    settings_manager::ensure_settings_dir()?;
+    let path = settings_manager::get_config_file_path(LAST_LOCATION_FILE)?;
+
+    let json = serde_json::to_string_pretty(location)
+        .map_err(|e| format!("Failed to serialize location: {}", e))?;
+    settings_manager::write_file_atomic(&path, json.as_bytes())
+}
+
+/// Load the last successful lookup, marked as coming from the cache
+pub fn load_last_location() -> Result<Option<Location>, String> {
+    let path = settings_manager::get_config_file_path(LAST_LOCATION_FILE)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read location cache: {}", e))?;
+    let mut location: Location = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse location cache: {}", e))?;
+    location.source = "cache".to_string();
+
+    Ok(Some(location))
+}
+
+#[derive(Deserialize)]
+struct ElevationResponse {
+    #[serde(default, deserialize_with = "lenient::f64s")]
+    elevation: Vec<f64>,
+}
+
+/// Elevation cache key: coordinates rounded to about 100 m
+pub fn elevation_cache_key(latitude: f64, longitude: f64) -> String {
+    format!("{:.3},{:.3}", latitude, longitude)
+}
+
+/// Read the elevation from an Open-Meteo elevation API response
+pub fn parse_elevation_response(body: &str) -> Result<f64, String> {
+    let data: ElevationResponse = serde_json::from_str(body)
+        .map_err(|e| format!("Failed to parse elevation data: {}", e))?;
+    data.elevation
+        .first()
+        .copied()
+        .ok_or_else(|| "Elevation API returned no elevation".to_string())
+}
+
+fn elevation_cache() -> &'static Mutex<HashMap<String, f64>> {
+    ELEVATION_CACHE.get_or_init(|| {
+        let cached = settings_manager::get_config_file_path(ELEVATION_CACHE_FILE)
+            .ok()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Mutex::new(cached)
+    })
+}
+
+pub fn cached_elevation(latitude: f64, longitude: f64) -> Option<f64> {
+    let key = elevation_cache_key(latitude, longitude);
+    elevation_cache().lock().ok()?.get(&key).copied()
+}
+
+fn cache_elevation(latitude: f64, longitude: f64, elevation: f64) -> Result<(), String> {
+    let json = {
+        let mut cache = elevation_cache()
+            .lock()
+            .map_err(|e| format!("Failed to lock elevation cache: {}", e))?;
+        cache.insert(elevation_cache_key(latitude, longitude), elevation);
+        serde_json::to_string_pretty(&*cache)
+            .map_err(|e| format!("Failed to serialize elevation cache: {}", e))?
+    };
+
+    settings_manager::ensure_settings_dir()?;
+    let path = settings_manager::get_config_file_path(ELEVATION_CACHE_FILE)?;
+    settings_manager::write_file_atomic(&path, json.as_bytes())
+}
+
+/// Elevation in meters for a coordinate. A frame doesn't move, so lookups
+/// are cached on disk and the API is only asked once per location.
+pub async fn fetch_elevation(client: &reqwest::Client, latitude: f64, longitude: f64) -> Result<f64, String> {
+    fetch_elevation_from(client, OPEN_METEO_ELEVATION_URL, latitude, longitude).await
+}
+
+/// Same as `fetch_elevation` against an Open-Meteo compatible elevation endpoint
+pub async fn fetch_elevation_from(
+    client: &reqwest::Client,
+    elevation_url: &str,
+    latitude: f64,
+    longitude: f64,
+) -> Result<f64, String> {
+    if let Some(elevation) = cached_elevation(latitude, longitude) {
+        return Ok(elevation);
+    }
+
+    let url = format!("{}?latitude={}&longitude={}", elevation_url, latitude, longitude);
+    let body = client
+        .get(&url)
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch elevation: {}", e))?
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read elevation response: {}", e))?;
+
+    let elevation = parse_elevation_response(&body)?;
+    if let Err(e) = cache_elevation(latitude, longitude, elevation) {
+        eprintln!("Failed to cache elevation: {}", e);
+    }
+    Ok(elevation)
+}
+
+/// Fill in the elevation of a location, leaving it unset when the lookup fails
+pub async fn with_elevation(client: &reqwest::Client, mut location: Location) -> Location {
+    if location.elevation.is_none() {
+        match fetch_elevation(client, location.latitude, location.longitude).await {
+            Ok(elevation) => location.elevation = Some(elevation),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+    location
+}
+
+/// Resolve the location from the configured source, falling back to the
+/// last known location when every live lookup fails
+pub async fn fetch_location_from_source(
+    client: &reqwest::Client,
+    settings: &LocationSettings,
+) -> Result<Location, String> {
+    match fetch_live_location(client, settings).await {
+        Ok(location) => {
+            if let Err(e) = save_last_location(&location) {
+                eprintln!("Failed to cache location: {}", e);
+            }
+            Ok(location)
+        }
+        Err(e) => match load_last_location() {
+            Ok(Some(cached)) => Ok(cached),
+            _ => Err(e),
+        },
+    }
+}
+
+/// Resolve the location using the configured source, falling back to IP lookup
+async fn fetch_live_location(
+    client: &reqwest::Client,
+    settings: &LocationSettings,
+) -> Result<Location, String> {
+    #[cfg(target_os = "linux")]
+    {
+        let result = match settings.source.as_str() {
+            "geoclue" => Some(fetch_geoclue_location().await),
+            "gps" => Some(fetch_gps_location(&settings.gps_device).await),
+            _ => None,
+        };
+
+        match result {
+            Some(Ok(location)) => return Ok(location),
+            Some(Err(e)) => eprintln!("Location source '{}' failed, using IP lookup: {}", settings.source, e),
+            None => {}
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = settings;
+    }
+
+    fetch_ip_location(client).await
+}
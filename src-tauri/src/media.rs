@@ -0,0 +1,537 @@
+//! What Chromecasts and Sonos speakers on the local network play, whatever
+//! app or service plays it, reported through the shared now-playing state.
+//!
+//! Devices are found over mDNS (`_googlecast._tcp`, `_sonos._tcp`) while
+//! their kind is turned on in `music.media`. Sonos speakers answer UPnP SOAP
+//! requests on port 1400. Chromecasts speak the Cast protocol: JSON messages
+//! in length-prefixed protobuf frames over TLS, with a self-signed
+//! certificate. Only the few protobuf fields the protocol needs are encoded
+//! by hand, and the TLS exchange runs on a blocking thread.
+
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, ClientConnection, DigitallySignedStruct, SignatureScheme, StreamOwned};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tracing::{info, warn};
+
+pub use idleview_api::music::{MediaDevice, MediaDeviceKind};
+
+use crate::display_sleep;
+use crate::http_server::AppState;
+use crate::news::{child_text, decode_entities, elements};
+use crate::now_playing::{self, NowPlaying, NowPlayingSource};
+use crate::settings_manager::MediaSettings;
+
+pub const CHROMECAST_SERVICE: &str = "_googlecast._tcp.local.";
+pub const SONOS_SERVICE: &str = "_sonos._tcp.local.";
+
+/// Port of the Sonos UPnP services, whatever port mDNS advertises
+const SONOS_PORT: u16 = 1400;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+const CONNECTION_NAMESPACE: &str = "urn:x-cast:com.google.cast.tp.connection";
+const RECEIVER_NAMESPACE: &str = "urn:x-cast:com.google.cast.receiver";
+const MEDIA_NAMESPACE: &str = "urn:x-cast:com.google.cast.media";
+
+/// Biggest Cast message read; media statuses with a queue are a few kB
+const MAX_CAST_MESSAGE: usize = 1 << 20;
+
+/// Messages read while waiting for an answer, skipping pings and broadcasts
+const MAX_CAST_READS: usize = 16;
+
+/// Devices found, by mDNS full name
+static DEVICES: Mutex<Vec<(String, MediaDevice)>> = Mutex::new(Vec::new());
+
+/// Devices found so far, by name
+pub fn devices() -> Vec<MediaDevice> {
+    let mut devices: Vec<MediaDevice> = DEVICES
+        .lock()
+        .map(|devices| devices.iter().map(|(_, device)| device.clone()).collect())
+        .unwrap_or_default();
+    devices.sort_by(|a, b| a.name.cmp(&b.name));
+    devices
+}
+
+/// The device an mDNS answer describes. Chromecasts put their name in the
+/// "fn" TXT record; Sonos instances are called "RINCON_...@Room".
+fn device_from(kind: MediaDeviceKind, service: &ServiceInfo) -> Option<MediaDevice> {
+    let instance = service.get_fullname().split('.').next().unwrap_or_default();
+    let name = match kind {
+        MediaDeviceKind::Chromecast => service.get_property_val_str("fn").unwrap_or(instance).to_string(),
+        MediaDeviceKind::Sonos => instance.rsplit('@').next().unwrap_or(instance).to_string(),
+    };
+    let ip = service
+        .get_addresses()
+        .iter()
+        .find(|ip| ip.is_ipv4())
+        .or_else(|| service.get_addresses().iter().next())?;
+    let port = match kind {
+        MediaDeviceKind::Chromecast => service.get_port(),
+        MediaDeviceKind::Sonos => SONOS_PORT,
+    };
+    Some(MediaDevice {
+        name,
+        kind,
+        address: SocketAddr::new(*ip, port).to_string(),
+    })
+}
+
+/// Milliseconds of an "H:MM:SS" UPnP time; None for "NOT_IMPLEMENTED"
+fn upnp_time(time: &str) -> Option<u64> {
+    let mut parts = time.trim().split(':').map(|part| part.split('.').next().unwrap_or(part).parse::<u64>());
+    let (hours, minutes, seconds) = (parts.next()?.ok()?, parts.next()?.ok()?, parts.next()?.ok()?);
+    Some((hours * 3600 + minutes * 60 + seconds) * 1000)
+}
+
+/// The track in a Sonos GetPositionInfo answer, None when nothing is queued
+/// or it plays from another speaker of its group. Relative cover addresses
+/// are resolved against `base_url`.
+pub fn parse_sonos_position(xml: &str, is_playing: bool, base_url: &str) -> Option<NowPlaying> {
+    let (_, metadata) = elements(xml, "TrackMetaData").into_iter().next()?;
+    // The DIDL-Lite document is escaped inside the SOAP answer
+    let didl = decode_entities(metadata);
+    // Radio gives the station as the title and the song as stream content
+    let title = child_text(&didl, "streamContent").or_else(|| child_text(&didl, "title"))?;
+    let album_art_url = child_text(&didl, "albumArtURI").map(|uri| {
+        if uri.starts_with("http://") || uri.starts_with("https://") {
+            uri
+        } else {
+            format!("{}{}", base_url.trim_end_matches('/'), uri)
+        }
+    });
+    Some(NowPlaying {
+        title,
+        artist: child_text(&didl, "creator").unwrap_or_default(),
+        album: child_text(&didl, "album"),
+        album_art_url,
+        is_playing,
+        progress_ms: child_text(xml, "RelTime").as_deref().and_then(upnp_time),
+        duration_ms: child_text(xml, "TrackDuration").as_deref().and_then(upnp_time).filter(|ms| *ms > 0),
+        source: NowPlayingSource::Sonos,
+        device: None,
+    })
+}
+
+async fn sonos_action(client: &reqwest::Client, base_url: &str, action: &str) -> Result<String, String> {
+    let body = format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="utf-8"?>"#,
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/" "#,
+            r#"s:encodingStyle="http://schemas.xmlsoap.org/soap/encoding/"><s:Body>"#,
+            r#"<u:{0} xmlns:u="urn:schemas-upnp-org:service:AVTransport:1"><InstanceID>0</InstanceID></u:{0}>"#,
+            r#"</s:Body></s:Envelope>"#
+        ),
+        action
+    );
+    client
+        .post(format!("{}/MediaRenderer/AVTransport/Control", base_url.trim_end_matches('/')))
+        .header(reqwest::header::CONTENT_TYPE, r#"text/xml; charset="utf-8""#)
+        .header("SOAPACTION", format!("\"urn:schemas-upnp-org:service:AVTransport:1#{}\"", action))
+        .body(body)
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to ask Sonos for {}: {}", action, e))?
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read Sonos {}: {}", action, e))
+}
+
+/// What the Sonos speaker at `base_url` (http://ip:1400) plays
+pub async fn fetch_sonos_from(client: &reqwest::Client, base_url: &str) -> Result<Option<NowPlaying>, String> {
+    let transport = sonos_action(client, base_url, "GetTransportInfo").await?;
+    let state = child_text(&transport, "CurrentTransportState").unwrap_or_default();
+    if state == "STOPPED" || state == "NO_MEDIA_PRESENT" {
+        return Ok(None);
+    }
+    let position = sonos_action(client, base_url, "GetPositionInfo").await?;
+    Ok(parse_sonos_position(&position, state == "PLAYING" || state == "TRANSITIONING", base_url))
+}
+
+fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn put_string(out: &mut Vec<u8>, field: u64, value: &str) {
+    put_varint(out, field << 3 | 2);
+    put_varint(out, value.len() as u64);
+    out.extend_from_slice(value.as_bytes());
+}
+
+/// A CastMessage with a JSON payload, as protobuf: protocol_version (1) and
+/// payload_type (5) are both 0, for CASTV2_1_0 and STRING
+pub fn encode_cast_message(source: &str, destination: &str, namespace: &str, payload: &str) -> Vec<u8> {
+    let mut message = Vec::new();
+    put_varint(&mut message, 1 << 3);
+    put_varint(&mut message, 0);
+    put_string(&mut message, 2, source);
+    put_string(&mut message, 3, destination);
+    put_string(&mut message, 4, namespace);
+    put_varint(&mut message, 5 << 3);
+    put_varint(&mut message, 0);
+    put_string(&mut message, 6, payload);
+    message
+}
+
+fn read_varint(bytes: &[u8], at: &mut usize) -> Result<u64, String> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes.get(*at).ok_or("Truncated Cast message")?;
+        *at += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err("Malformed varint in Cast message".to_string())
+}
+
+/// Namespace and JSON payload of a CastMessage
+pub fn decode_cast_message(bytes: &[u8]) -> Result<(String, String), String> {
+    let mut namespace = String::new();
+    let mut payload = String::new();
+    let mut at = 0;
+    while at < bytes.len() {
+        let key = read_varint(bytes, &mut at)?;
+        match key & 7 {
+            0 => {
+                read_varint(bytes, &mut at)?;
+            }
+            1 => at += 8,
+            5 => at += 4,
+            2 => {
+                let len = read_varint(bytes, &mut at)? as usize;
+                let value = bytes.get(at..at + len).ok_or("Truncated Cast message")?;
+                at += len;
+                match key >> 3 {
+                    4 => namespace = String::from_utf8_lossy(value).into_owned(),
+                    6 => payload = String::from_utf8_lossy(value).into_owned(),
+                    _ => {}
+                }
+            }
+            wire_type => return Err(format!("Unknown wire type {} in Cast message", wire_type)),
+        }
+    }
+    Ok((namespace, payload))
+}
+
+/// The track in a Cast MEDIA_STATUS message, None when the player is idle
+pub fn parse_media_status(json: &str) -> Option<NowPlaying> {
+    let value: Value = serde_json::from_str(json).ok()?;
+    let status = value.pointer("/status/0")?;
+    let is_playing = match status.get("playerState").and_then(Value::as_str)? {
+        "PLAYING" | "BUFFERING" => true,
+        "PAUSED" => false,
+        _ => return None,
+    };
+    let metadata = status.pointer("/media/metadata")?;
+    let text = |key: &str| {
+        metadata
+            .get(key)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+            .map(str::to_string)
+    };
+    let millis = |seconds: Option<&Value>| seconds.and_then(Value::as_f64).filter(|s| *s >= 0.0).map(|s| (s * 1000.0) as u64);
+    Some(NowPlaying {
+        title: text("title")?,
+        // Music has an artist, videos a subtitle such as the channel
+        artist: text("artist").or_else(|| text("albumArtist")).or_else(|| text("subtitle")).unwrap_or_default(),
+        album: text("albumName"),
+        album_art_url: metadata.pointer("/images/0/url").and_then(Value::as_str).map(str::to_string),
+        is_playing,
+        progress_ms: millis(status.get("currentTime")),
+        duration_ms: millis(status.pointer("/media/duration")),
+        source: NowPlayingSource::Chromecast,
+        device: None,
+    })
+}
+
+/// Chromecasts have self-signed certificates, so any is accepted; the
+/// handshake signatures are still checked
+#[derive(Debug)]
+struct AnyCertificate(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+fn cast_tls_config() -> Result<Arc<ClientConfig>, String> {
+    static CONFIG: OnceLock<Result<Arc<ClientConfig>, String>> = OnceLock::new();
+    CONFIG
+        .get_or_init(|| {
+            let provider = Arc::new(rustls::crypto::ring::default_provider());
+            let config = ClientConfig::builder_with_provider(provider.clone())
+                .with_safe_default_protocol_versions()
+                .map_err(|e| format!("Failed to set up Cast TLS: {}", e))?
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(AnyCertificate(provider)))
+                .with_no_client_auth();
+            Ok(Arc::new(config))
+        })
+        .clone()
+}
+
+struct CastConnection {
+    stream: StreamOwned<ClientConnection, TcpStream>,
+}
+
+impl CastConnection {
+    const SENDER: &'static str = "sender-0";
+
+    fn open(address: SocketAddr) -> Result<Self, String> {
+        let tcp = TcpStream::connect_timeout(&address, REQUEST_TIMEOUT)
+            .map_err(|e| format!("Failed to connect to {}: {}", address, e))?;
+        tcp.set_read_timeout(Some(REQUEST_TIMEOUT))
+            .and_then(|()| tcp.set_write_timeout(Some(REQUEST_TIMEOUT)))
+            .map_err(|e| format!("Failed to set Cast timeouts: {}", e))?;
+        let connection = ClientConnection::new(cast_tls_config()?, ServerName::IpAddress(address.ip().into()))
+            .map_err(|e| format!("Failed to start Cast TLS: {}", e))?;
+        Ok(CastConnection {
+            stream: StreamOwned::new(connection, tcp),
+        })
+    }
+
+    fn send(&mut self, destination: &str, namespace: &str, payload: Value) -> Result<(), String> {
+        let message = encode_cast_message(Self::SENDER, destination, namespace, &payload.to_string());
+        let mut frame = (message.len() as u32).to_be_bytes().to_vec();
+        frame.extend(message);
+        self.stream
+            .write_all(&frame)
+            .and_then(|()| self.stream.flush())
+            .map_err(|e| format!("Failed to send Cast message: {}", e))
+    }
+
+    /// The next message on `namespace` of type `kind`, skipping the others
+    fn receive(&mut self, namespace: &str, kind: &str) -> Result<String, String> {
+        for _ in 0..MAX_CAST_READS {
+            let mut length = [0u8; 4];
+            self.stream
+                .read_exact(&mut length)
+                .map_err(|e| format!("Failed to read Cast message: {}", e))?;
+            let length = u32::from_be_bytes(length) as usize;
+            if length > MAX_CAST_MESSAGE {
+                return Err(format!("Cast message of {} bytes is too big", length));
+            }
+            let mut message = vec![0u8; length];
+            self.stream
+                .read_exact(&mut message)
+                .map_err(|e| format!("Failed to read Cast message: {}", e))?;
+            let (received_namespace, payload) = decode_cast_message(&message)?;
+            let received_kind = serde_json::from_str::<Value>(&payload)
+                .ok()
+                .and_then(|payload| payload.get("type").and_then(Value::as_str).map(str::to_string));
+            if received_namespace == namespace && received_kind.as_deref() == Some(kind) {
+                return Ok(payload);
+            }
+        }
+        Err(format!("No {} from the Chromecast", kind))
+    }
+}
+
+/// What the Chromecast at `address` plays: ask the receiver which app runs,
+/// then that app's media player for its status. Blocking.
+fn chromecast_now_playing(address: SocketAddr) -> Result<Option<NowPlaying>, String> {
+    let mut cast = CastConnection::open(address)?;
+    cast.send("receiver-0", CONNECTION_NAMESPACE, json!({ "type": "CONNECT" }))?;
+    cast.send("receiver-0", RECEIVER_NAMESPACE, json!({ "type": "GET_STATUS", "requestId": 1 }))?;
+    let status: Value = serde_json::from_str(&cast.receive(RECEIVER_NAMESPACE, "RECEIVER_STATUS")?)
+        .map_err(|e| format!("Failed to parse Chromecast status: {}", e))?;
+    // The backdrop and apps without a media player have nothing to show
+    let media_app = status
+        .pointer("/status/applications")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .find(|app| {
+            app.get("namespaces")
+                .and_then(Value::as_array)
+                .is_some_and(|namespaces| namespaces.iter().any(|ns| ns.get("name").and_then(Value::as_str) == Some(MEDIA_NAMESPACE)))
+        });
+    let Some(transport) = media_app.and_then(|app| app.get("transportId")).and_then(Value::as_str) else {
+        return Ok(None);
+    };
+    cast.send(transport, CONNECTION_NAMESPACE, json!({ "type": "CONNECT" }))?;
+    cast.send(transport, MEDIA_NAMESPACE, json!({ "type": "GET_STATUS", "requestId": 2 }))?;
+    let playing = parse_media_status(&cast.receive(MEDIA_NAMESPACE, "MEDIA_STATUS")?);
+    // Leaving without closing would leave the app a sender it waits on
+    let _ = cast.send(transport, CONNECTION_NAMESPACE, json!({ "type": "CLOSE" }));
+    Ok(playing)
+}
+
+async fn fetch_device(device: &MediaDevice) -> Result<Option<NowPlaying>, String> {
+    let address: SocketAddr = device
+        .address
+        .parse()
+        .map_err(|e| format!("Bad address {}: {}", device.address, e))?;
+    let playing = match device.kind {
+        MediaDeviceKind::Sonos => fetch_sonos_from(crate::http_client(), &format!("http://{}", address)).await?,
+        MediaDeviceKind::Chromecast => tokio::task::spawn_blocking(move || chromecast_now_playing(address))
+            .await
+            .map_err(|e| format!("Chromecast request failed: {}", e))??,
+    };
+    Ok(playing.map(|playing| NowPlaying {
+        device: Some(device.name.clone()),
+        ..playing
+    }))
+}
+
+/// Follow `kind`'s mDNS answers until browsing stops
+fn spawn_browser(daemon: &ServiceDaemon, kind: MediaDeviceKind, service: &'static str) -> Result<(), String> {
+    let events = daemon
+        .browse(service)
+        .map_err(|e| format!("Failed to look for {}: {}", service, e))?;
+    tokio::spawn(async move {
+        while let Ok(event) = events.recv_async().await {
+            let Ok(mut devices) = DEVICES.lock() else {
+                break;
+            };
+            match event {
+                ServiceEvent::ServiceResolved(info) => {
+                    let Some(device) = device_from(kind, &info) else {
+                        continue;
+                    };
+                    let fullname = info.get_fullname().to_string();
+                    if !devices.iter().any(|(known, found)| *known == fullname && *found == device) {
+                        info!("🔈 Found {:?} {} at {}", kind, device.name, device.address);
+                    }
+                    devices.retain(|(known, _)| *known != fullname);
+                    devices.push((fullname, device));
+                }
+                ServiceEvent::ServiceRemoved(_, fullname) => devices.retain(|(known, _)| *known != fullname),
+                ServiceEvent::SearchStopped(_) => break,
+                _ => {}
+            }
+        }
+    });
+    Ok(())
+}
+
+/// What `kind`'s devices play: the first playing, else the first paused
+async fn poll(kind: MediaDeviceKind, settings: &MediaSettings, errors: &mut HashMap<String, String>) -> Option<NowPlaying> {
+    let followed = devices()
+        .into_iter()
+        .filter(|device| device.kind == kind)
+        .filter(|device| settings.devices.is_empty() || settings.devices.iter().any(|name| name.eq_ignore_ascii_case(&device.name)));
+    let mut paused = None;
+    for device in followed {
+        match fetch_device(&device).await {
+            Ok(playing) => {
+                errors.remove(&device.name);
+                match playing {
+                    Some(playing) if playing.is_playing => return Some(playing),
+                    Some(playing) => paused = paused.or(Some(playing)),
+                    None => {}
+                }
+            }
+            // Logged once rather than every poll while it keeps failing
+            Err(e) => {
+                if errors.get(&device.name) != Some(&e) {
+                    warn!("Media: {}: {}", device.name, e);
+                    errors.insert(device.name.clone(), e);
+                }
+            }
+        }
+    }
+    paused
+}
+
+/// Look for the devices turned on in the settings and ask them what they
+/// play every `poll_interval` seconds, for the lifetime of the server
+pub fn spawn_watcher(state: AppState) {
+    tokio::spawn(async move {
+        let mut daemon: Option<ServiceDaemon> = None;
+        let mut browsing: Vec<MediaDeviceKind> = Vec::new();
+        let mut errors = HashMap::new();
+        loop {
+            if display_sleep::is_asleep() {
+                tokio::time::sleep(display_sleep::PAUSED_POLL_INTERVAL).await;
+                continue;
+            }
+            let settings = match state.settings_manager.get() {
+                Ok(settings) => settings.music.media,
+                Err(e) => {
+                    warn!("Media: failed to read settings: {}", e);
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    continue;
+                }
+            };
+            let kinds = [
+                (MediaDeviceKind::Chromecast, CHROMECAST_SERVICE, NowPlayingSource::Chromecast, settings.chromecast),
+                (MediaDeviceKind::Sonos, SONOS_SERVICE, NowPlayingSource::Sonos, settings.sonos),
+            ];
+            for (kind, service, source, enabled) in kinds {
+                let browsed = browsing.contains(&kind);
+                if enabled && !browsed {
+                    // The mDNS daemon only starts once something is turned on
+                    if daemon.is_none() {
+                        daemon = ServiceDaemon::new()
+                            .map_err(|e| warn!("Media: failed to start mDNS: {}", e))
+                            .ok();
+                    }
+                    match daemon.as_ref().map(|daemon| spawn_browser(daemon, kind, service)) {
+                        Some(Ok(())) => browsing.push(kind),
+                        Some(Err(e)) => warn!("Media: {}", e),
+                        None => {}
+                    }
+                } else if !enabled && browsed {
+                    if let Some(daemon) = &daemon {
+                        let _ = daemon.stop_browse(service);
+                    }
+                    browsing.retain(|browsed| *browsed != kind);
+                    if let Ok(mut devices) = DEVICES.lock() {
+                        devices.retain(|(_, device)| device.kind != kind);
+                    }
+                }
+                let playing = if enabled { poll(kind, &settings, &mut errors).await } else { None };
+                now_playing::update(&state, source, playing);
+            }
+            tokio::time::sleep(Duration::from_secs(settings.poll_interval.max(1))).await;
+        }
+    });
+}
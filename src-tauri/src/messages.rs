@@ -0,0 +1,121 @@
+//! Short messages shown over the frame ("Dinner's ready!", "Doorbell"),
+//! queued so each one stays up for its full duration.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+pub use idleview_api::message::{Message, MessagePriority, MessageRequest, QueuedMessage};
+use idleview_api::ServerEvent;
+
+use crate::http_server::AppState;
+
+/// Messages waiting to be shown before new ones are refused
+pub const QUEUE_CAPACITY: usize = 20;
+
+/// Longest text a message may have
+pub const MAX_TEXT_LENGTH: usize = 280;
+
+/// Longest a message may stay on screen, in seconds
+pub const MAX_DURATION: u32 = 3600;
+
+/// Everything wrong with a message request
+pub fn validate_message(request: &MessageRequest) -> Vec<String> {
+    let mut problems = Vec::new();
+    if request.text.trim().is_empty() {
+        problems.push("text must not be empty".to_string());
+    }
+    if request.text.chars().count() > MAX_TEXT_LENGTH {
+        problems.push(format!("text must be at most {} characters", MAX_TEXT_LENGTH));
+    }
+    if request.duration == 0 || request.duration > MAX_DURATION {
+        problems.push(format!("duration must be between 1 and {} seconds", MAX_DURATION));
+    }
+    problems
+}
+
+/// Pending messages, highest priority first and oldest first within a priority
+pub struct MessageQueue {
+    pending: Mutex<VecDeque<Message>>,
+    notify: Notify,
+    next_id: AtomicU64,
+    capacity: usize,
+}
+
+impl MessageQueue {
+    pub fn new(capacity: usize) -> Arc<Self> {
+        Arc::new(MessageQueue {
+            pending: Mutex::new(VecDeque::new()),
+            notify: Notify::new(),
+            next_id: AtomicU64::new(1),
+            capacity: capacity.max(1),
+        })
+    }
+
+    /// Queue a message behind everything of the same or higher priority
+    pub fn push(&self, request: MessageRequest) -> Result<QueuedMessage, String> {
+        let mut pending = self
+            .pending
+            .lock()
+            .map_err(|e| format!("Failed to lock message queue: {}", e))?;
+        if pending.len() >= self.capacity {
+            return Err(format!("{} messages are already waiting", pending.len()));
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let position = pending
+            .iter()
+            .position(|queued| queued.priority < request.priority)
+            .unwrap_or(pending.len());
+        pending.insert(
+            position,
+            Message {
+                id,
+                text: request.text,
+                duration: request.duration,
+                priority: request.priority,
+                sound: request.sound,
+            },
+        );
+        self.notify.notify_one();
+        Ok(QueuedMessage { id, position })
+    }
+
+    /// How many messages are waiting
+    pub fn len(&self) -> usize {
+        self.pending.lock().map(|pending| pending.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The next message to show, if any
+    pub fn pop(&self) -> Option<Message> {
+        self.pending.lock().ok()?.pop_front()
+    }
+
+    /// Wait for the next message
+    pub async fn next(&self) -> Message {
+        loop {
+            if let Some(message) = self.pop() {
+                return message;
+            }
+            self.notify.notified().await;
+        }
+    }
+}
+
+/// Show queued messages on the frame one at a time for the lifetime of the server
+pub fn spawn_presenter(state: AppState) {
+    tokio::spawn(async move {
+        loop {
+            let message = state.messages.next().await;
+            let duration = Duration::from_secs(message.duration as u64);
+            state.publish_from(ServerEvent::ShowMessage(message), "messages");
+            tokio::time::sleep(duration).await;
+        }
+    });
+}
@@ -0,0 +1,202 @@
+//! Prometheus metrics at `/metrics`, for putting frames on a Grafana dashboard.
+//!
+//! The handful of series the frame has are kept in plain maps and rendered
+//! in the text exposition format by hand.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::system::MemoryStatus;
+
+/// Upper bounds of the weather fetch latency histogram, in seconds
+pub const LATENCY_BUCKETS: [f64; 7] = [0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+/// Counters and histograms collected while the app runs
+pub struct Metrics {
+    requests: Mutex<BTreeMap<(String, String, u16), u64>>,  // (method, route, status)
+    fetches: Mutex<BTreeMap<(&'static str, &'static str, bool), u64>>,  // (kind, provider, success)
+    weather_latency: Mutex<Histogram>,
+}
+
+/// Values read at scrape time
+#[derive(Debug, Default)]
+pub struct Gauges {
+    pub uptime: u64,
+    pub cpu_temperature: Option<f32>,
+    pub gpu_temperature: Option<f32>,
+    pub memory: Option<MemoryStatus>,
+    pub config_dir_bytes: u64,
+    pub queued_events: usize,
+    pub queued_messages: usize,
+}
+
+static METRICS: Metrics = Metrics::new();
+
+/// The app-wide metrics
+pub fn global() -> &'static Metrics {
+    &METRICS
+}
+
+/// Quote a label value as the exposition format wants it
+fn label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub const fn new() -> Self {
+        Metrics {
+            requests: Mutex::new(BTreeMap::new()),
+            fetches: Mutex::new(BTreeMap::new()),
+            weather_latency: Mutex::new(Histogram {
+                buckets: [0; LATENCY_BUCKETS.len()],
+                sum: 0.0,
+                count: 0,
+            }),
+        }
+    }
+
+    /// Count an API call by its route pattern, not its path, to keep the series few
+    pub fn record_request(&self, method: &str, route: &str, status: u16) {
+        if let Ok(mut requests) = self.requests.lock() {
+            *requests.entry((method.to_string(), route.to_string(), status)).or_default() += 1;
+        }
+    }
+
+    /// Count a call to an external provider; `kind` is "photo" or "weather"
+    pub fn record_fetch(&self, kind: &'static str, provider: &'static str, success: bool) {
+        if let Ok(mut fetches) = self.fetches.lock() {
+            *fetches.entry((kind, provider, success)).or_default() += 1;
+        }
+    }
+
+    pub fn observe_weather_latency(&self, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        if let Ok(mut histogram) = self.weather_latency.lock() {
+            for (bucket, bound) in histogram.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+                if seconds <= bound {
+                    *bucket += 1;
+                }
+            }
+            histogram.sum += seconds;
+            histogram.count += 1;
+        }
+    }
+
+    /// Everything in the Prometheus text format
+    pub fn render(&self, gauges: &Gauges) -> String {
+        let mut out = String::new();
+
+        let _ = writeln!(out, "# HELP idleview_build_info Version of the running app.");
+        let _ = writeln!(out, "# TYPE idleview_build_info gauge");
+        let _ = writeln!(out, "idleview_build_info{{version=\"{}\"}} 1", env!("CARGO_PKG_VERSION"));
+
+        let _ = writeln!(out, "# HELP idleview_uptime_seconds Seconds since the app started.");
+        let _ = writeln!(out, "# TYPE idleview_uptime_seconds gauge");
+        let _ = writeln!(out, "idleview_uptime_seconds {}", gauges.uptime);
+
+        let _ = writeln!(out, "# HELP idleview_http_requests_total API calls by method, route and status.");
+        let _ = writeln!(out, "# TYPE idleview_http_requests_total counter");
+        if let Ok(requests) = self.requests.lock() {
+            for ((method, route, status), count) in requests.iter() {
+                let _ = writeln!(
+                    out,
+                    "idleview_http_requests_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}",
+                    label(method),
+                    label(route),
+                    status,
+                    count
+                );
+            }
+        }
+
+        for (kind, help) in [("photo", "Photo fetches"), ("weather", "Weather fetches")] {
+            let _ = writeln!(out, "# HELP idleview_{}_fetches_total {} by provider and result.", kind, help);
+            let _ = writeln!(out, "# TYPE idleview_{}_fetches_total counter", kind);
+            if let Ok(fetches) = self.fetches.lock() {
+                for ((_, provider, success), count) in fetches.iter().filter(|((k, _, _), _)| *k == kind) {
+                    let result = if *success { "success" } else { "failure" };
+                    let _ = writeln!(
+                        out,
+                        "idleview_{}_fetches_total{{provider=\"{}\",result=\"{}\"}} {}",
+                        kind, provider, result, count
+                    );
+                }
+            }
+        }
+
+        let _ = writeln!(out, "# HELP idleview_weather_fetch_duration_seconds Time taken by weather fetches.");
+        let _ = writeln!(out, "# TYPE idleview_weather_fetch_duration_seconds histogram");
+        if let Ok(histogram) = self.weather_latency.lock() {
+            for (count, bound) in histogram.buckets.iter().zip(LATENCY_BUCKETS) {
+                let _ = writeln!(out, "idleview_weather_fetch_duration_seconds_bucket{{le=\"{}\"}} {}", bound, count);
+            }
+            let _ = writeln!(out, "idleview_weather_fetch_duration_seconds_bucket{{le=\"+Inf\"}} {}", histogram.count);
+            let _ = writeln!(out, "idleview_weather_fetch_duration_seconds_sum {}", histogram.sum);
+            let _ = writeln!(out, "idleview_weather_fetch_duration_seconds_count {}", histogram.count);
+        }
+
+        if let Some(celsius) = gauges.cpu_temperature {
+            let _ = writeln!(out, "# HELP idleview_cpu_temperature_celsius CPU temperature.");
+            let _ = writeln!(out, "# TYPE idleview_cpu_temperature_celsius gauge");
+            let _ = writeln!(out, "idleview_cpu_temperature_celsius {}", celsius);
+        }
+
+        if let Some(celsius) = gauges.gpu_temperature {
+            let _ = writeln!(out, "# HELP idleview_gpu_temperature_celsius GPU temperature.");
+            let _ = writeln!(out, "# TYPE idleview_gpu_temperature_celsius gauge");
+            let _ = writeln!(out, "idleview_gpu_temperature_celsius {}", celsius);
+        }
+
+        if let Some(memory) = &gauges.memory {
+            let _ = writeln!(out, "# HELP idleview_memory_bytes Memory of the machine, and resident memory of the app.");
+            let _ = writeln!(out, "# TYPE idleview_memory_bytes gauge");
+            let _ = writeln!(out, "idleview_memory_bytes{{memory=\"used\"}} {}", memory.used);
+            let _ = writeln!(out, "idleview_memory_bytes{{memory=\"available\"}} {}", memory.available);
+            let _ = writeln!(out, "idleview_memory_bytes{{memory=\"swap_used\"}} {}", memory.swap_used);
+            if let Some(app) = memory.app {
+                let _ = writeln!(out, "idleview_memory_bytes{{memory=\"app\"}} {}", app);
+            }
+        }
+
+        let _ = writeln!(out, "# HELP idleview_cache_bytes Size of what the app keeps on disk.");
+        let _ = writeln!(out, "# TYPE idleview_cache_bytes gauge");
+        let _ = writeln!(out, "idleview_cache_bytes{{cache=\"config_dir\"}} {}", gauges.config_dir_bytes);
+
+        let _ = writeln!(out, "# HELP idleview_queue_length Items waiting in in-memory queues.");
+        let _ = writeln!(out, "# TYPE idleview_queue_length gauge");
+        let _ = writeln!(out, "idleview_queue_length{{queue=\"events\"}} {}", gauges.queued_events);
+        let _ = writeln!(out, "idleview_queue_length{{queue=\"messages\"}} {}", gauges.queued_messages);
+
+        out
+    }
+}
+
+/// Bytes used by the files in the config directory (settings, history, logs)
+pub fn config_dir_bytes() -> u64 {
+    let Ok(dir) = crate::settings_manager::get_config_file_path("") else {
+        return 0;
+    };
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok()?.metadata().ok())
+                .filter(|metadata| metadata.is_file())
+                .map(|metadata| metadata.len())
+                .sum()
+        })
+        .unwrap_or(0)
+}
@@ -0,0 +1,105 @@
+//! More than one monitor: with `monitors.window_per_monitor` a frame window
+//! opens full screen on every monitor at startup, not only the first. Each
+//! frame reads its settings with the override for its monitor merged in, so
+//! a second screen can show its own photo query, widgets or units.
+//!
+//! Monitors are matched by the name the system gives them (`HDMI-A-1`,
+//! `\\.\DISPLAY2`), as listed by `GET /api/monitors`.
+
+use tauri::{AppHandle, Manager, Monitor, WebviewUrl, WebviewWindow, WebviewWindowBuilder};
+
+pub use idleview_api::display::MonitorInfo;
+
+use crate::settings_manager::{self, MonitorsSettings, Settings};
+
+/// Label of the window from tauri.conf.json
+pub const MAIN_WINDOW: &str = "main";
+
+/// Label of the frame window opened on the `index`th other monitor
+pub fn window_label(index: usize) -> String {
+    format!("monitor-{}", index)
+}
+
+/// Settings for the frame on `monitor`, with its override merged in
+pub fn settings_for(settings: &Settings, monitor: Option<&str>) -> Result<Settings, String> {
+    let overrides = monitor
+        .and_then(|monitor| settings.monitors.overrides.iter().find(|o| o.monitor == monitor))
+        .filter(|o| o.settings.is_object());
+    match overrides {
+        Some(o) => settings_manager::with_overrides(settings, &o.settings),
+        None => Ok(settings.clone()),
+    }
+}
+
+/// Name of the monitor `window` is on
+pub fn monitor_of(window: &WebviewWindow) -> Option<String> {
+    window.current_monitor().ok().flatten().and_then(|monitor| monitor.name().cloned())
+}
+
+/// Settings for the frame in `window`
+pub fn settings_for_window(window: &WebviewWindow, settings: &Settings) -> Result<Settings, String> {
+    settings_for(settings, monitor_of(window).as_deref())
+}
+
+fn same_monitor(a: &Monitor, b: &Monitor) -> bool {
+    a.name() == b.name() && a.position() == b.position()
+}
+
+/// Monitors with the frame window showing on each
+pub fn list(app: &AppHandle) -> Result<Vec<MonitorInfo>, String> {
+    let monitors = app.available_monitors().map_err(|e| format!("Failed to list monitors: {}", e))?;
+    let primary = app.primary_monitor().ok().flatten();
+    let windows: Vec<(String, Monitor)> = app
+        .webview_windows()
+        .into_iter()
+        .filter_map(|(label, window)| Some((label, window.current_monitor().ok()??)))
+        .collect();
+
+    Ok(monitors
+        .iter()
+        .map(|monitor| MonitorInfo {
+            name: monitor.name().cloned(),
+            width: monitor.size().width,
+            height: monitor.size().height,
+            x: monitor.position().x,
+            y: monitor.position().y,
+            scale_factor: monitor.scale_factor(),
+            primary: primary.as_ref().is_some_and(|primary| same_monitor(primary, monitor)),
+            window: windows
+                .iter()
+                .find(|(_, shown_on)| same_monitor(shown_on, monitor))
+                .map(|(label, _)| label.clone()),
+        })
+        .collect())
+}
+
+/// Open a full-screen frame on every monitor the main window isn't on
+pub fn open_windows(app: &AppHandle, settings: &MonitorsSettings) -> Result<(), String> {
+    if !settings.window_per_monitor {
+        return Ok(());
+    }
+    let main = app
+        .get_webview_window(MAIN_WINDOW)
+        .and_then(|window| window.current_monitor().ok().flatten());
+    let monitors = app.available_monitors().map_err(|e| format!("Failed to list monitors: {}", e))?;
+    let others = monitors
+        .iter()
+        .filter(|monitor| !main.as_ref().is_some_and(|main| same_monitor(main, monitor)));
+
+    for (index, monitor) in others.enumerate() {
+        open_frame(app, &window_label(index + 1), Some(monitor)).map_err(|e| {
+            format!("Failed to open a frame on monitor {}: {}", monitor.name().map_or("?", |name| name.as_str()), e)
+        })?;
+    }
+    Ok(())
+}
+
+/// Open a full-screen frame window labelled `label`, on `monitor` if given
+pub fn open_frame(app: &AppHandle, label: &str, monitor: Option<&Monitor>) -> tauri::Result<WebviewWindow> {
+    let mut builder = WebviewWindowBuilder::new(app, label, WebviewUrl::App("index.html".into())).title("idleview");
+    if let Some(monitor) = monitor {
+        let position = monitor.position().to_logical::<f64>(monitor.scale_factor());
+        builder = builder.position(position.x, position.y);
+    }
+    builder.fullscreen(true).build()
+}
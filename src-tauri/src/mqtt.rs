@@ -0,0 +1,246 @@
+//! MQTT integration for smart-home setups.
+//!
+//! State is published, retained, under `mqtt.base_topic`:
+//!
+//! - `<base>/status`: "online", or "offline" (the last will) once the frame is gone
+//! - `<base>/photo` and `<base>/weather`: JSON, as in the HTTP API
+//! - `<base>/display/power`: "on" or "off"
+//! - `<base>/display/brightness`: percent
+//! - `<base>/cpu_temperature`: °C
+//!
+//! Commands are read from `<base>/command/next_photo` (any payload),
+//! `<base>/command/display` ("on" or "off") and `<base>/command/brightness`
+//! (0-100). The connection follows the settings, so changes apply right away.
+
+use rumqttc::{AsyncClient, Event, LastWill, MqttOptions, Packet, QoS};
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use idleview_api::{EventEnvelope, PowerState, ServerEvent};
+
+use crate::brightness;
+use crate::display_power::{self, DisplayPower};
+use crate::http_server::AppState;
+use crate::settings_manager::{MqttSettings, Settings};
+use crate::thermal;
+
+/// How often the readings nothing announces (CPU temperature, brightness) are published
+const STATE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Pause before reconnecting after the broker drops the connection
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+/// A command read from `<base>/command/...`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    NextPhoto,
+    DisplayPower(PowerState),
+    Brightness(u8),
+}
+
+/// Full topic name under the base topic
+pub fn topic(base: &str, name: &str) -> String {
+    format!("{}/{}", base.trim_end_matches('/'), name)
+}
+
+/// The command a message on a command topic asks for; Err for bad payloads
+pub fn parse_command(base: &str, topic_name: &str, payload: &[u8]) -> Result<Command, String> {
+    let name = topic_name
+        .strip_prefix(&topic(base, "command/"))
+        .ok_or_else(|| format!("{} is not a command topic", topic_name))?;
+    let payload = String::from_utf8_lossy(payload).trim().to_lowercase();
+    match name {
+        "next_photo" => Ok(Command::NextPhoto),
+        "display" => match payload.as_str() {
+            "on" => Ok(Command::DisplayPower(PowerState::On)),
+            "off" => Ok(Command::DisplayPower(PowerState::Off)),
+            _ => Err(format!("display: expected \"on\" or \"off\", got {:?}", payload)),
+        },
+        "brightness" => payload
+            .parse::<u8>()
+            .ok()
+            .filter(|percent| *percent <= 100)
+            .map(Command::Brightness)
+            .ok_or_else(|| format!("brightness: expected 0-100, got {:?}", payload)),
+        _ => Err(format!("unknown command {}", name)),
+    }
+}
+
+fn power_payload(power: PowerState) -> String {
+    match power {
+        PowerState::On => "on".to_string(),
+        PowerState::Off => "off".to_string(),
+    }
+}
+
+/// The state topic and payload an event updates, if any
+pub fn state_for_event(event: &ServerEvent) -> Option<(&'static str, String)> {
+    match event {
+        ServerEvent::PhotoUpdated(photo) => serde_json::to_string(photo).ok().map(|json| ("photo", json)),
+        ServerEvent::WeatherUpdated(weather) => serde_json::to_string(weather).ok().map(|json| ("weather", json)),
+        ServerEvent::DisplayPowerChanged(power) => Some(("display/power", power_payload(power.power))),
+        _ => None,
+    }
+}
+
+fn publish(client: &AsyncClient, base: &str, name: &str, payload: String) {
+    // Never wait on the request queue: it is drained by the same loop that calls this
+    if let Err(e) = client.try_publish(topic(base, name), QoS::AtLeastOnce, true, payload) {
+        warn!("MQTT: failed to publish {}: {}", name, e);
+    }
+}
+
+/// Publish everything known now, for a fresh connection
+fn publish_initial_state(state: &AppState, client: &AsyncClient, base: &str) {
+    publish(client, base, "status", "online".to_string());
+    let photo = state.current_photo.lock().ok().and_then(|photo| photo.clone());
+    if let Some(state) = photo.and_then(|photo| state_for_event(&ServerEvent::PhotoUpdated(photo))) {
+        publish(client, base, state.0, state.1);
+    }
+    let weather = state.weather.lock().ok().and_then(|report| report.clone());
+    if let Some(state) = weather.and_then(|report| state_for_event(&ServerEvent::WeatherUpdated(Box::new(report.weather)))) {
+        publish(client, base, state.0, state.1);
+    }
+
+    let client = client.clone();
+    let base = base.to_string();
+    tokio::spawn(async move {
+        if let Ok(power) = tokio::task::spawn_blocking(display_power::power_state).await {
+            publish(&client, &base, "display/power", power_payload(power));
+        }
+    });
+}
+
+/// Readings that change without an event
+fn publish_readings(client: &AsyncClient, base: &str) {
+    let client = client.clone();
+    let base = base.to_string();
+    tokio::spawn(async move {
+        let readings = tokio::task::spawn_blocking(|| (thermal::cpu_temperature(), brightness::brightness())).await;
+        let Ok((cpu_temperature, brightness)) = readings else {
+            return;
+        };
+        if let Some(celsius) = cpu_temperature {
+            publish(&client, &base, "cpu_temperature", format!("{:.1}", celsius));
+        }
+        publish(&client, &base, "display/brightness", brightness.percent.to_string());
+    });
+}
+
+async fn run_command(state: AppState, client: AsyncClient, base: String, command: Command) -> Result<(), String> {
+    match command {
+        Command::NextPhoto => {
+            state.publish_from(ServerEvent::RefreshPhoto, "mqtt");
+            Ok(())
+        }
+        Command::DisplayPower(power) => {
+            tokio::task::spawn_blocking(move || display_power::set_power(power))
+                .await
+                .map_err(|e| format!("Failed to set display power: {}", e))??;
+            // Publishing the event also updates the display/power topic
+            state.publish_from(ServerEvent::DisplayPowerChanged(DisplayPower { power }), "mqtt");
+            Ok(())
+        }
+        Command::Brightness(percent) => {
+            let app_handle = state.app_handle.clone();
+            let brightness = tokio::task::spawn_blocking(move || brightness::set_brightness(&app_handle, percent))
+                .await
+                .map_err(|e| format!("Failed to set brightness: {}", e))??;
+            publish(&client, &base, "display/brightness", brightness.percent.to_string());
+            Ok(())
+        }
+    }
+}
+
+/// Stay connected to the broker, publishing state and running commands
+async fn run(state: &AppState, settings: &MqttSettings) {
+    let base = settings.base_topic.trim_end_matches('/').to_string();
+    let mut options = MqttOptions::new(
+        format!("idleview-control-{}", std::process::id()),
+        settings.host.clone(),
+        settings.port,
+    );
+    options.set_keep_alive(Duration::from_secs(30));
+    options.set_last_will(LastWill::new(topic(&base, "status"), "offline", QoS::AtLeastOnce, true));
+    if let Some(username) = &settings.username {
+        options.set_credentials(username.clone(), settings.password.clone().unwrap_or_default());
+    }
+
+    let (client, mut eventloop) = AsyncClient::new(options, 10);
+    let subscription = state.event_hub.subscribe("mqtt");
+    let mut readings = tokio::time::interval(STATE_INTERVAL);
+    info!("MQTT: connecting to {}:{}", settings.host, settings.port);
+
+    loop {
+        tokio::select! {
+            polled = eventloop.poll() => match polled {
+                // Subscribe on every (re)connect, the broker may not keep the session
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    info!("MQTT: connected, publishing under {}", base);
+                    if let Err(e) = client.try_subscribe(topic(&base, "command/#"), QoS::AtLeastOnce) {
+                        warn!("MQTT: failed to subscribe to commands: {}", e);
+                    }
+                    publish_initial_state(state, &client, &base);
+                }
+                Ok(Event::Incoming(Packet::Publish(message))) => {
+                    match parse_command(&base, &message.topic, &message.payload) {
+                        Ok(command) => {
+                            let run = run_command(state.clone(), client.clone(), base.clone(), command);
+                            tokio::spawn(async move {
+                                if let Err(e) = run.await {
+                                    warn!("MQTT: {:?} failed: {}", command, e);
+                                }
+                            });
+                        }
+                        Err(e) => warn!("MQTT: ignoring command: {}", e),
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("MQTT: connection error: {}", e);
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            },
+            event_data = subscription.recv() => {
+                let update = serde_json::from_str::<EventEnvelope>(&event_data)
+                    .ok()
+                    .and_then(|envelope| state_for_event(&envelope.event));
+                if let Some((name, payload)) = update {
+                    publish(&client, &base, name, payload);
+                }
+            }
+            _ = readings.tick() => publish_readings(&client, &base),
+        }
+    }
+}
+
+async fn mqtt_settings_changed(changes: &mut watch::Receiver<Settings>, current: &MqttSettings) {
+    loop {
+        if changes.changed().await.is_err() {
+            // The settings can no longer change
+            std::future::pending::<()>().await;
+        }
+        if changes.borrow_and_update().mqtt != *current {
+            return;
+        }
+    }
+}
+
+/// Connect to the broker whenever MQTT is enabled, for the lifetime of the server
+pub fn spawn_client(state: AppState) {
+    let mut changes = state.settings_manager.subscribe();
+    tokio::spawn(async move {
+        loop {
+            let mqtt = changes.borrow_and_update().mqtt.clone();
+            if mqtt.enabled {
+                tokio::select! {
+                    _ = run(&state, &mqtt) => {}
+                    _ = mqtt_settings_changed(&mut changes, &mqtt) => {}
+                }
+            } else {
+                mqtt_settings_changed(&mut changes, &mqtt).await;
+            }
+        }
+    });
+}
@@ -0,0 +1,320 @@
+//! Headlines from RSS and Atom feeds, fetched here rather than in the webview
+//! so feeds without CORS headers work too.
+//!
+//! The parser takes what a headline needs from RSS 2.0 `<item>`s and Atom
+//! `<entry>`s: the title, the link and the publication time. Each feed gives
+//! at most its `limit` newest headlines, and the merged list is capped at
+//! `max_headlines`.
+
+use chrono::DateTime;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+pub use idleview_api::news::{Headline, NewsReport};
+use idleview_api::ServerEvent;
+
+use crate::display_sleep;
+use crate::health;
+use crate::http_server::AppState;
+use crate::settings_manager::{NewsFeed, NewsSettings};
+
+/// How often the refresher checks whether the feeds are due
+const TICK_INTERVAL: Duration = Duration::from_secs(60);
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+
+static REPORT: Mutex<NewsReport> = Mutex::new(NewsReport {
+    headlines: Vec::new(),
+    fetched_at: None,
+    errors: Vec::new(),
+});
+
+/// The elements called `name`, whatever their namespace prefix, as their
+/// start tag and inner XML. Self-closing ones have no inner XML.
+pub(crate) fn elements<'a>(xml: &'a str, name: &str) -> Vec<(&'a str, &'a str)> {
+    let mut found = Vec::new();
+    let mut rest = xml;
+    while let Some(open) = rest.find('<') {
+        rest = &rest[open + 1..];
+        let Some(tag_end) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[..tag_end];
+        rest = &rest[tag_end + 1..];
+        let qualified = tag.split_whitespace().next().unwrap_or_default().trim_end_matches('/');
+        let local_name = qualified.rsplit(':').next().unwrap_or(qualified);
+        if local_name != name || qualified.starts_with(['/', '!', '?']) {
+            continue;
+        }
+        if tag.ends_with('/') {
+            found.push((tag, ""));
+            continue;
+        }
+        let closing = format!("</{}>", qualified);
+        let Some(end) = rest.find(&closing) else {
+            break;
+        };
+        found.push((tag, &rest[..end]));
+        rest = &rest[end + closing.len()..];
+    }
+    found
+}
+
+/// An attribute's value in a start tag
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let mut rest = tag;
+    while let Some(at) = rest.find(name) {
+        let before = rest[..at].chars().last();
+        rest = &rest[at + name.len()..];
+        let Some(value) = rest.trim_start().strip_prefix('=') else {
+            continue;
+        };
+        if !before.is_some_and(char::is_whitespace) {
+            continue;
+        }
+        let value = value.trim_start();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let value = &value[1..];
+        return value.find(quote).map(|end| decode_entities(&value[..end]));
+    }
+    None
+}
+
+pub(crate) fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semicolon) = rest.find(';').filter(|end| *end <= 10) else {
+            decoded.push('&');
+            rest = &rest[1..];
+            continue;
+        };
+        let entity = &rest[1..semicolon];
+        let character = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|decimal| decimal.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match character {
+            Some(character) => {
+                decoded.push(character);
+                rest = &rest[semicolon + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// The text of an element: CDATA kept as is, entities decoded, markup
+/// (e.g. in an Atom title of type "html") dropped and whitespace collapsed
+fn text(inner: &str) -> String {
+    let mut unescaped = String::new();
+    let mut rest = inner;
+    while let Some(start) = rest.find("<![CDATA[") {
+        unescaped.push_str(&decode_entities(&rest[..start]));
+        rest = &rest[start + "<![CDATA[".len()..];
+        let end = rest.find("]]>").unwrap_or(rest.len());
+        unescaped.push_str(&rest[..end]);
+        rest = rest.get(end + "]]>".len()..).unwrap_or_default();
+    }
+    unescaped.push_str(&decode_entities(rest));
+
+    // Only "<" starting a tag, so "rates < 5%" keeps its text
+    let mut plain = String::with_capacity(unescaped.len());
+    let mut rest = unescaped.as_str();
+    while let Some(open) = rest.find('<') {
+        plain.push_str(&rest[..open]);
+        rest = &rest[open..];
+        let starts_tag = rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/');
+        match rest.find('>').filter(|_| starts_tag) {
+            Some(close) => {
+                plain.push(' ');
+                rest = &rest[close + 1..];
+            }
+            None => {
+                plain.push('<');
+                rest = &rest[1..];
+            }
+        }
+    }
+    plain.push_str(rest);
+    plain.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Text of the first child element called `name`
+pub(crate) fn child_text(xml: &str, name: &str) -> Option<String> {
+    elements(xml, name)
+        .first()
+        .map(|(_, inner)| text(inner))
+        .filter(|text| !text.is_empty())
+}
+
+/// RSS links are the element's text; Atom's are the href of the
+/// "alternate" link, or of the only one
+fn link(xml: &str) -> Option<String> {
+    let links = elements(xml, "link");
+    links
+        .iter()
+        .find(|(tag, _)| attribute(tag, "rel").is_none_or(|rel| rel == "alternate"))
+        .or(links.first())
+        .and_then(|(tag, inner)| attribute(tag, "href").or_else(|| Some(text(inner))))
+        .filter(|link| !link.is_empty())
+}
+
+/// RFC 2822 in RSS, RFC 3339 in Atom and Dublin Core
+fn published(xml: &str) -> Option<u64> {
+    ["pubDate", "published", "updated", "date"]
+        .iter()
+        .find_map(|name| child_text(xml, name))
+        .and_then(|date| {
+            DateTime::parse_from_rfc2822(&date)
+                .or_else(|_| DateTime::parse_from_rfc3339(&date))
+                .ok()
+        })
+        .map(|date| date.timestamp_millis().max(0) as u64)
+}
+
+/// The first `limit` headlines of an RSS or Atom document
+pub fn parse_feed(xml: &str, feed: &str, limit: usize) -> Vec<Headline> {
+    let mut items = elements(xml, "item");
+    if items.is_empty() {
+        items = elements(xml, "entry");
+    }
+    items
+        .iter()
+        .filter_map(|(_, item)| {
+            Some(Headline {
+                title: child_text(item, "title")?,
+                link: link(item),
+                published: published(item),
+                feed: feed.to_string(),
+            })
+        })
+        .take(limit)
+        .collect()
+}
+
+/// Newest first, headlines without a date after the dated ones in feed
+/// order, at most `max` of them
+pub fn merge_headlines(mut headlines: Vec<Headline>, max: usize) -> Vec<Headline> {
+    headlines.sort_by_key(|headline| std::cmp::Reverse(headline.published));
+    headlines.truncate(max);
+    headlines
+}
+
+/// Headlines of one feed
+pub async fn fetch_feed(client: &reqwest::Client, feed: &NewsFeed) -> Result<Vec<Headline>, String> {
+    let xml = client
+        .get(&feed.url)
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to fetch feed {}: {}", feed.name, e))?
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read feed {}: {}", feed.name, e))?;
+    let headlines = parse_feed(&xml, &feed.name, feed.limit);
+    if headlines.is_empty() && !["<rss", "<feed", ":RDF"].iter().any(|root| xml.contains(root)) {
+        return Err(format!("Feed {} is not RSS or Atom", feed.name));
+    }
+    Ok(headlines)
+}
+
+/// Fetch every feed and merge their headlines. Feeds that fail are listed in
+/// `errors` rather than failing the rest.
+pub async fn fetch_report(client: &reqwest::Client, settings: &NewsSettings) -> NewsReport {
+    let mut headlines = Vec::new();
+    let mut errors = Vec::new();
+    for feed in &settings.feeds {
+        match fetch_feed(client, feed).await {
+            Ok(feed_headlines) => headlines.extend(feed_headlines),
+            Err(e) => errors.push(e),
+        }
+    }
+    NewsReport {
+        headlines: merge_headlines(headlines, settings.max_headlines),
+        fetched_at: Some(chrono::Utc::now().timestamp_millis() as u64),
+        errors,
+    }
+}
+
+/// The headlines of the last refresh
+pub fn report() -> NewsReport {
+    REPORT.lock().map(|report| report.clone()).unwrap_or_default()
+}
+
+async fn refresh(state: &AppState, settings: &NewsSettings) {
+    let report = fetch_report(crate::http_client(), settings).await;
+    for error in &report.errors {
+        warn!("News: {}", error);
+    }
+    health::record::<()>(
+        "news",
+        &if report.errors.is_empty() { Ok(()) } else { Err(report.errors.join("; ")) },
+    );
+    info!("News: {} headlines", report.headlines.len());
+    let headlines = report.headlines.clone();
+    state.publish_from(ServerEvent::NewsUpdated { headlines }, "news");
+    if let Ok(mut current) = REPORT.lock() {
+        *current = report;
+    }
+}
+
+/// Refresh the feeds on their interval, and straight away when they change,
+/// for the lifetime of the server
+pub fn spawn_refresher(state: AppState) {
+    tokio::spawn(async move {
+        let mut refreshed: Option<(Instant, NewsSettings)> = None;
+        let mut interval = tokio::time::interval(TICK_INTERVAL);
+        loop {
+            interval.tick().await;
+            if display_sleep::is_asleep() {
+                continue;
+            }
+            let settings = match state.settings_manager.get() {
+                Ok(settings) => settings.news,
+                Err(e) => {
+                    warn!("News: failed to read settings: {}", e);
+                    continue;
+                }
+            };
+            let due = match &refreshed {
+                Some((at, used)) => {
+                    *used != settings || at.elapsed() >= Duration::from_secs(settings.refresh_interval * 60)
+                }
+                None => true,
+            };
+            if !due {
+                continue;
+            }
+            if settings.enabled && !settings.feeds.is_empty() {
+                refresh(&state, &settings).await;
+            } else {
+                // Turned off or every feed removed: forget their headlines
+                if let Ok(mut current) = REPORT.lock() {
+                    *current = NewsReport::default();
+                }
+                state.publish_from(ServerEvent::NewsUpdated { headlines: Vec::new() }, "news");
+            }
+            refreshed = Some((Instant::now(), settings));
+        }
+    });
+}
@@ -0,0 +1,119 @@
+//! Night mode: between `display.night_mode.start` and `end`, or from sunset
+//! to sunrise, the screen is dimmed to `brightness`, the frame switches to
+//! its dark theme and photos change less often. Leaving it brings back the
+//! brightness from before.
+//!
+//! The schedule is checked every 30 seconds; `night-mode-changed` goes out
+//! when it flips.
+
+use chrono::{Local, NaiveDateTime, NaiveTime};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{info, warn};
+
+pub use idleview_api::display::NightMode;
+use idleview_api::ServerEvent;
+
+use crate::brightness;
+use crate::http_server::AppState;
+use crate::in_time_window;
+use crate::settings_manager::NightModeSettings;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// `photo_interval_factor` while night mode is on, 1 otherwise
+static PHOTO_INTERVAL_FACTOR: AtomicU64 = AtomicU64::new(1);
+
+/// Brightness before night mode dimmed the screen, restored when it ends
+static DAY_BRIGHTNESS: Mutex<Option<u8>> = Mutex::new(None);
+
+/// Whether it's night at `time`. With `follow_sun` the weather's sunset and
+/// sunrise times of day are used when known, the fixed window otherwise.
+pub fn is_night(settings: &NightModeSettings, time: NaiveTime, sun: Option<(NaiveTime, NaiveTime)>) -> bool {
+    if !settings.enabled {
+        return false;
+    }
+    match sun.filter(|_| settings.follow_sun) {
+        Some((sunrise, sunset)) => time >= sunset || time < sunrise,
+        None => in_time_window(&settings.start, &settings.end, time),
+    }
+}
+
+/// Sunrise and sunset times of day from the weather the frame shows
+fn sun_times(state: &AppState) -> Option<(NaiveTime, NaiveTime)> {
+    let weather = state.weather.lock().ok()?.as_ref()?.weather.clone();
+    let parse = |value: &str| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M").ok().map(|time| time.time());
+    Some((parse(&weather.sunrise)?, parse(&weather.sunset)?))
+}
+
+/// Whether night mode is on right now
+pub fn is_active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+/// How many times longer photos stay up right now
+pub fn photo_interval_factor() -> u64 {
+    PHOTO_INTERVAL_FACTOR.load(Ordering::Relaxed)
+}
+
+/// Night mode as the frame should show it
+pub fn status(settings: &NightModeSettings) -> NightMode {
+    let active = is_active();
+    NightMode { active, dark_theme: active && settings.dark_theme }
+}
+
+/// The brightness to go back to in the morning. After a restart at night the
+/// screen may already be at the night level; full brightness beats staying dim.
+pub fn day_brightness(before: u8, night: u8) -> u8 {
+    if before > night { before } else { 100 }
+}
+
+async fn apply_brightness(state: &AppState, percent: u8) {
+    let app_handle = state.app_handle.clone();
+    match tokio::task::spawn_blocking(move || brightness::set_brightness(&app_handle, percent)).await {
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => warn!("Night mode: {}", e),
+        Err(e) => warn!("Night mode: failed to set brightness: {}", e),
+    }
+}
+
+/// Switch night mode on and off on schedule for the lifetime of the server
+pub fn spawn_scheduler(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let settings = match state.settings_manager.get() {
+                Ok(settings) => settings.display.night_mode,
+                Err(e) => {
+                    warn!("Night mode: failed to read settings: {}", e);
+                    continue;
+                }
+            };
+            let night = is_night(&settings, Local::now().time(), sun_times(&state));
+            let factor = if night { settings.photo_interval_factor.max(1) } else { 1 };
+            PHOTO_INTERVAL_FACTOR.store(factor, Ordering::Relaxed);
+            if night == is_active() {
+                continue;
+            }
+            ACTIVE.store(night, Ordering::Relaxed);
+
+            if night {
+                info!("🌙 Night mode on");
+                let before = brightness::brightness().percent;
+                if let Ok(mut day) = DAY_BRIGHTNESS.lock() {
+                    *day = Some(day_brightness(before, settings.brightness));
+                }
+                apply_brightness(&state, settings.brightness).await;
+            } else {
+                info!("☀️ Night mode off");
+                let day = DAY_BRIGHTNESS.lock().ok().and_then(|mut day| day.take());
+                apply_brightness(&state, day.unwrap_or(100)).await;
+            }
+            state.publish_from(ServerEvent::NightModeChanged(status(&settings)), "night-mode");
+        }
+    });
+}
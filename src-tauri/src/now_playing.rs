@@ -0,0 +1,74 @@
+//! The track playing right now, whichever music service reported it, and the
+//! `now-playing-changed` event raised when it changes.
+//!
+//! Each service reports what it sees on its own. A track that is playing wins
+//! over one that isn't. Then Spotify comes first, as its player is what
+//! actually plays, then the Chromecast and Sonos speakers in the room, and
+//! Last.fm last as it only hears about tracks played elsewhere.
+
+use std::sync::Mutex;
+
+pub use idleview_api::music::{NowPlaying, NowPlayingSource};
+use idleview_api::ServerEvent;
+
+use crate::http_server::AppState;
+
+/// Sources in order of preference
+const SOURCES: [NowPlayingSource; 4] = [
+    NowPlayingSource::Spotify,
+    NowPlayingSource::Chromecast,
+    NowPlayingSource::Sonos,
+    NowPlayingSource::LastFm,
+];
+
+/// What each source last reported, in the order of `SOURCES`
+static REPORTED: Mutex<[Option<NowPlaying>; 4]> = Mutex::new([None, None, None, None]);
+
+/// The track to show out of what the sources reported
+pub fn pick(reported: &[Option<NowPlaying>]) -> Option<NowPlaying> {
+    let mut tracks = reported.iter().flatten();
+    tracks
+        .clone()
+        .find(|track| track.is_playing)
+        .or_else(|| tracks.next())
+        .cloned()
+}
+
+/// What's playing, None when nothing is
+pub fn current() -> Option<NowPlaying> {
+    REPORTED.lock().ok().and_then(|reported| pick(&*reported))
+}
+
+/// Whether going from `old` to `new` is worth an event: another track, a
+/// pause or resume, or the music stopping. Progress alone isn't.
+pub fn changed(old: Option<&NowPlaying>, new: Option<&NowPlaying>) -> bool {
+    match (old, new) {
+        (Some(old), Some(new)) => {
+            old.title != new.title
+                || old.artist != new.artist
+                || old.album != new.album
+                || old.is_playing != new.is_playing
+                || old.source != new.source
+                || old.device != new.device
+        }
+        (old, new) => old.is_some() != new.is_some(),
+    }
+}
+
+/// Record what a service reports is playing, raising `now-playing-changed`
+/// when the track to show differs from before
+pub fn update(state: &AppState, source: NowPlayingSource, playing: Option<NowPlaying>) {
+    let Some(index) = SOURCES.iter().position(|known| *known == source) else {
+        return;
+    };
+    let Ok(mut reported) = REPORTED.lock() else {
+        return;
+    };
+    let before = pick(&*reported);
+    reported[index] = playing;
+    let after = pick(&*reported);
+    drop(reported);
+    if changed(before.as_ref(), after.as_ref()) {
+        state.publish_from(ServerEvent::NowPlayingChanged(after), source.as_str());
+    }
+}
@@ -0,0 +1,197 @@
+//! Checking the system clock against an NTP server. A Raspberry Pi has no
+//! battery-backed clock: booted without network it starts from the last
+//! shutdown, and sunrise times, the time of day and cache ages are all wrong
+//! until it syncs. The offset is shown in the debug overlay and
+//! `GET /api/system`, and logged when it grows past `clock.max_drift`.
+//!
+//! This only measures; setting the clock is left to the system's own NTP
+//! client. One SNTP request (RFC 4330) is sent every hour, and every minute
+//! until a server first answers.
+
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tracing::{info, warn};
+
+pub use idleview_api::system::ClockStatus;
+
+use crate::health;
+use crate::http_server::AppState;
+
+/// Seconds from the NTP epoch (1900) to the Unix epoch (1970)
+const NTP_UNIX_OFFSET: i64 = 2_208_988_800;
+
+const NTP_PORT: u16 = 123;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Between checks once a server has answered
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Between checks until a server answers, while the clock is off, and while
+/// checking is off
+const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+static LAST_STATUS: Mutex<Option<ClockStatus>> = Mutex::new(None);
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+/// Unix milliseconds as a 64-bit NTP timestamp
+fn to_ntp(unix_ms: i64) -> [u8; 8] {
+    let seconds = (unix_ms.div_euclid(1000) + NTP_UNIX_OFFSET) as u32;
+    let fraction = ((unix_ms.rem_euclid(1000) as u64) << 32) / 1000;
+    let mut bytes = [0u8; 8];
+    bytes[..4].copy_from_slice(&seconds.to_be_bytes());
+    bytes[4..].copy_from_slice(&(fraction as u32).to_be_bytes());
+    bytes
+}
+
+/// A 64-bit NTP timestamp as Unix milliseconds. Seconds wrap in 2036; small
+/// values are taken to be after that.
+fn from_ntp(bytes: &[u8]) -> i64 {
+    let seconds = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as i64;
+    let fraction = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as i64;
+    let seconds = if seconds < 0x8000_0000 { seconds + (1 << 32) } else { seconds };
+    (seconds - NTP_UNIX_OFFSET) * 1000 + ((fraction * 1000 + (1 << 31)) >> 32)
+}
+
+/// A client request: version 4, mode 3, with the send time as the transmit
+/// timestamp so the answer can be matched to it
+pub fn request_packet(sent_ms: i64) -> [u8; 48] {
+    let mut packet = [0u8; 48];
+    packet[0] = 0x23;
+    packet[40..48].copy_from_slice(&to_ntp(sent_ms));
+    packet
+}
+
+/// How far the system clock is ahead of the server's, in milliseconds, from
+/// an answer to the request sent at `sent_ms` and received at `received_ms`
+/// (both by the system clock). Network delay cancels out when it's the same
+/// both ways.
+pub fn parse_response(packet: &[u8], sent_ms: i64, received_ms: i64) -> Result<i64, String> {
+    if packet.len() < 48 {
+        return Err(format!("NTP answer of {} bytes is too short", packet.len()));
+    }
+    if packet[0] & 0x07 != 4 {
+        return Err("NTP answer isn't from a server".to_string());
+    }
+    if packet[0] >> 6 == 3 || packet[1] == 0 {
+        return Err("NTP server isn't synchronized".to_string());
+    }
+    if packet[24..32] != to_ntp(sent_ms) {
+        return Err("NTP answer doesn't match the request".to_string());
+    }
+    let server_received = from_ntp(&packet[32..40]);
+    let server_sent = from_ntp(&packet[40..48]);
+    let server_minus_system = ((server_received - sent_ms) + (server_sent - received_ms)) / 2;
+    Ok(-server_minus_system)
+}
+
+/// The system clock's offset from the NTP server at `server` ("host" or
+/// "host:port")
+pub async fn query(server: &str) -> Result<i64, String> {
+    let address = if server.contains(':') { server.to_string() } else { format!("{}:{}", server, NTP_PORT) };
+    let socket = UdpSocket::bind("0.0.0.0:0")
+        .await
+        .map_err(|e| format!("Failed to open a socket for NTP: {}", e))?;
+    socket
+        .connect(&address)
+        .await
+        .map_err(|e| format!("Failed to reach NTP server {}: {}", server, e))?;
+    let sent_ms = now_ms();
+    socket
+        .send(&request_packet(sent_ms))
+        .await
+        .map_err(|e| format!("Failed to ask {} for the time: {}", server, e))?;
+    let mut packet = [0u8; 128];
+    let len = tokio::time::timeout(REQUEST_TIMEOUT, socket.recv(&mut packet))
+        .await
+        .map_err(|_| format!("No answer from NTP server {}", server))?
+        .map_err(|e| format!("Failed to read the time from {}: {}", server, e))?;
+    parse_response(&packet[..len], sent_ms, now_ms())
+}
+
+/// The offset for people: "0.3s ahead", "2m 5s behind", "3h 12m behind"
+pub fn describe_offset(offset_ms: i64) -> String {
+    let direction = if offset_ms < 0 { "behind" } else { "ahead" };
+    let ms = offset_ms.unsigned_abs();
+    let seconds = ms / 1000;
+    let amount = if seconds < 60 {
+        format!("{:.1}s", ms as f64 / 1000.0)
+    } else if seconds < 3600 {
+        format!("{}m {}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{}h {}m", seconds / 3600, seconds % 3600 / 60)
+    };
+    format!("{} {}", amount, direction)
+}
+
+/// Result of the last successful check, None before one or while checking is off
+pub fn status() -> Option<ClockStatus> {
+    LAST_STATUS.lock().ok()?.clone()
+}
+
+/// Compare the clock with NTP every hour for the lifetime of the server
+pub fn spawn_checker(state: AppState) {
+    tokio::spawn(async move {
+        let mut last_error = None;
+        loop {
+            let settings = match state.settings_manager.get() {
+                Ok(settings) => settings.clock,
+                Err(e) => {
+                    warn!("Clock: failed to read settings: {}", e);
+                    tokio::time::sleep(RETRY_INTERVAL).await;
+                    continue;
+                }
+            };
+            if !settings.check_drift {
+                if let Ok(mut last) = LAST_STATUS.lock() {
+                    *last = None;
+                }
+                tokio::time::sleep(RETRY_INTERVAL).await;
+                continue;
+            }
+
+            let server = settings.ntp_server.trim().to_string();
+            let result = query(&server).await;
+            health::record("ntp", &result);
+            let next = match result {
+                Ok(offset_ms) => {
+                    last_error = None;
+                    let drifted = offset_ms.unsigned_abs() > settings.max_drift * 1000;
+                    let was_drifted = status().is_some_and(|last| last.drifted);
+                    if drifted && !was_drifted {
+                        warn!(
+                            "🕰️ System clock is {} of {}; sunrise times and cache ages will be off until it syncs",
+                            describe_offset(offset_ms),
+                            server
+                        );
+                    } else if !drifted && was_drifted {
+                        info!("🕰️ System clock is back in sync ({})", describe_offset(offset_ms));
+                    }
+                    if let Ok(mut last) = LAST_STATUS.lock() {
+                        *last = Some(ClockStatus {
+                            offset_ms,
+                            drifted,
+                            server,
+                            checked_at: now_ms().max(0) as u64,
+                        });
+                    }
+                    // Soon after an offline boot the system syncs; see it when it does
+                    if drifted { RETRY_INTERVAL } else { CHECK_INTERVAL }
+                }
+                Err(e) => {
+                    // Offline frames would log it every minute otherwise
+                    if last_error.as_ref() != Some(&e) {
+                        warn!("Clock: {}", e);
+                    }
+                    last_error = Some(e);
+                    if status().is_some() { CHECK_INTERVAL } else { RETRY_INTERVAL }
+                }
+            };
+            tokio::time::sleep(next).await;
+        }
+    });
+}
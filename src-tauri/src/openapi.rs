@@ -0,0 +1,384 @@
+//! OpenAPI description of the control API, for people building Home
+//! Assistant, Node-RED and similar integrations.
+//!
+//! Schemas come from the same `JsonSchema` derives as `/api/settings/schema`,
+//! so they can't drift from the types the server actually uses.
+
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+
+use crate::brightness::{Brightness, BrightnessRequest};
+use crate::caldav::CalDavPasswordRequest;
+use crate::calendar::CalendarReport;
+use crate::connectivity::Connectivity;
+use crate::crypto::CryptoReport;
+use crate::google_calendar::{GoogleCalendarStatus, GoogleDeviceCode};
+use crate::display_power::DisplayPower;
+use crate::display_sleep::DisplaySleep;
+use crate::event_hub::ClientStats;
+use crate::health::Health;
+use crate::http_server::{ActiveLocationRequest, CurrentPhoto, ProfileRequest, WeatherReport, PROTECTED_READS};
+use crate::messages::{MessageRequest, QueuedMessage};
+use crate::monitors::MonitorInfo;
+use crate::news::NewsReport;
+use crate::night_mode::NightMode;
+use crate::media::MediaDevice;
+use crate::now_playing::NowPlaying;
+use crate::pairing::{PairRequest, PairedDevice, PairingResult, PairingStarted};
+use crate::presence::Presence;
+use crate::quotes::Quote;
+use crate::settings_history::SettingsHistoryEntry;
+use crate::settings_manager::{Alarm, Profile, ProfilesSettings, Scene, Settings, SettingsBackup, SettingsDefaults};
+use crate::spotify::{SpotifyAuthorization, SpotifyCallbackRequest, SpotifyStatus};
+use crate::sports::SportsReport;
+use crate::stocks::StocksReport;
+use crate::sync::SyncStatus;
+use crate::system::SystemStatus;
+use crate::tasks::TasksReport;
+use crate::timer::{Timer, TimerRequest};
+use crate::updates::UpdateInfo;
+use crate::watchdog::WatchdogStatus;
+use crate::weather_codes::WeatherLegend;
+use crate::DebugInfo;
+
+/// Routes that change something without needing a token
+const PUBLIC_OPERATIONS: &[(&str, &str)] = &[("post", "/api/v1/pair/start"), ("post", "/api/v1/pair")];
+
+/// One documented route
+struct Operation {
+    method: &'static str,
+    path: &'static str,
+    summary: &'static str,
+    request: Option<Value>,
+    response: Option<Value>,
+    status: u16,
+}
+
+fn operation(method: &'static str, path: &'static str, summary: &'static str) -> Operation {
+    Operation {
+        method,
+        path,
+        summary,
+        request: None,
+        response: None,
+        status: 200,
+    }
+}
+
+impl Operation {
+    fn request(mut self, schema: &Value) -> Self {
+        self.request = Some(schema.clone());
+        self
+    }
+
+    fn response(mut self, schema: &Value) -> Self {
+        self.response = Some(schema.clone());
+        self
+    }
+
+    fn status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+
+    fn to_json(&self) -> Value {
+        let mut responses = Map::new();
+        let mut success = json!({ "description": "Success" });
+        if let Some(schema) = &self.response {
+            success["content"] = json!({ "application/json": { "schema": schema } });
+        }
+        responses.insert(self.status.to_string(), success);
+        responses.insert(
+            "default".to_string(),
+            json!({
+                "description": "Error",
+                "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Error" } } }
+            }),
+        );
+
+        let mut operation = json!({ "summary": self.summary, "responses": responses });
+        let parameters: Vec<Value> = self
+            .path
+            .split('/')
+            .filter_map(|segment| segment.strip_prefix(':'))
+            .map(|name| json!({ "name": name, "in": "path", "required": true, "schema": { "type": "string" } }))
+            .collect();
+        if !parameters.is_empty() {
+            operation["parameters"] = Value::Array(parameters);
+        }
+        if let Some(schema) = &self.request {
+            operation["requestBody"] = json!({
+                "required": true,
+                "content": { "application/json": { "schema": schema } }
+            });
+        }
+        let protected_read = self
+            .path
+            .strip_prefix("/api/v1")
+            .is_some_and(|path| PROTECTED_READS.contains(&path));
+        let needs_token = if self.method == "get" {
+            protected_read
+        } else {
+            !PUBLIC_OPERATIONS.contains(&(self.method, self.path))
+        };
+        if needs_token {
+            operation["security"] = json!([{ "bearerAuth": [] }]);
+        }
+        operation
+    }
+
+    /// `/api/v1/settings/:section` in OpenAPI's `/api/v1/settings/{section}` form
+    fn openapi_path(&self) -> String {
+        self.path
+            .split('/')
+            .map(|segment| match segment.strip_prefix(':') {
+                Some(name) => format!("{{{}}}", name),
+                None => segment.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+fn schema<T: JsonSchema>(gen: &mut SchemaGenerator) -> Value {
+    serde_json::to_value(gen.subschema_for::<T>()).unwrap_or_default()
+}
+
+fn array_of(item: &Value) -> Value {
+    json!({ "type": "array", "items": item })
+}
+
+/// The OpenAPI 3.0 document served at `/api/v1/openapi.json`
+pub fn openapi_document() -> Value {
+    let mut gen = SchemaSettings::openapi3().into_generator();
+    let settings = schema::<Settings>(&mut gen);
+    let defaults = schema::<SettingsDefaults>(&mut gen);
+    let backup = schema::<SettingsBackup>(&mut gen);
+    let history = array_of(&schema::<SettingsHistoryEntry>(&mut gen));
+    let profiles = schema::<ProfilesSettings>(&mut gen);
+    let profile = schema::<Profile>(&mut gen);
+    let profile_request = schema::<ProfileRequest>(&mut gen);
+    let scenes = array_of(&schema::<Scene>(&mut gen));
+    let alarm = schema::<Alarm>(&mut gen);
+    let alarms = array_of(&alarm);
+    let photo = schema::<CurrentPhoto>(&mut gen);
+    let location_request = schema::<ActiveLocationRequest>(&mut gen);
+    let weather = schema::<WeatherReport>(&mut gen);
+    let message_request = schema::<MessageRequest>(&mut gen);
+    let queued_message = schema::<QueuedMessage>(&mut gen);
+    let timer_request = schema::<TimerRequest>(&mut gen);
+    let timer = schema::<Timer>(&mut gen);
+    let nullable_timer = json!({ "allOf": [timer], "nullable": true });
+    let legend = schema::<WeatherLegend>(&mut gen);
+    let system = schema::<SystemStatus>(&mut gen);
+    let update = schema::<UpdateInfo>(&mut gen);
+    let display_power = schema::<DisplayPower>(&mut gen);
+    let brightness = schema::<Brightness>(&mut gen);
+    let brightness_request = schema::<BrightnessRequest>(&mut gen);
+    let night_mode = schema::<NightMode>(&mut gen);
+    let display_sleep = schema::<DisplaySleep>(&mut gen);
+    let presence = schema::<Presence>(&mut gen);
+    let monitors = schema::<Vec<MonitorInfo>>(&mut gen);
+    let watchdog = schema::<WatchdogStatus>(&mut gen);
+    let connectivity = schema::<Connectivity>(&mut gen);
+    let debug = schema::<DebugInfo>(&mut gen);
+    let health = schema::<Health>(&mut gen);
+    let sync_status = schema::<SyncStatus>(&mut gen);
+    let calendar = schema::<CalendarReport>(&mut gen);
+    let google_calendar = schema::<GoogleCalendarStatus>(&mut gen);
+    let google_device_code = schema::<GoogleDeviceCode>(&mut gen);
+    let caldav_password = schema::<CalDavPasswordRequest>(&mut gen);
+    let quote = schema::<Quote>(&mut gen);
+    let news = schema::<NewsReport>(&mut gen);
+    let stocks = schema::<StocksReport>(&mut gen);
+    let crypto = schema::<CryptoReport>(&mut gen);
+    let sports = schema::<SportsReport>(&mut gen);
+    let tasks = schema::<TasksReport>(&mut gen);
+    let now_playing = schema::<Option<NowPlaying>>(&mut gen);
+    let media_devices = schema::<Vec<MediaDevice>>(&mut gen);
+    let spotify = schema::<SpotifyStatus>(&mut gen);
+    let spotify_authorization = schema::<SpotifyAuthorization>(&mut gen);
+    let spotify_callback = schema::<SpotifyCallbackRequest>(&mut gen);
+    let clients = array_of(&schema::<ClientStats>(&mut gen));
+    let pair_request = schema::<PairRequest>(&mut gen);
+    let pairing_result = schema::<PairingResult>(&mut gen);
+    let pairing_started = schema::<PairingStarted>(&mut gen);
+    let devices = array_of(&schema::<PairedDevice>(&mut gen));
+    let object = json!({ "type": "object" });
+    let nullable_photo = json!({ "allOf": [photo], "nullable": true });
+    let token = json!({ "type": "object", "properties": { "token": { "type": "string" } } });
+
+    let operations = [
+        operation("get", "/api/v1/settings", "Current settings").response(&settings),
+        operation("put", "/api/v1/settings", "Replace all settings").request(&settings).response(&settings),
+        operation("patch", "/api/v1/settings", "Merge a partial settings document").request(&object).response(&settings),
+        operation("post", "/api/v1/settings/reset", "Reset all settings to defaults").response(&settings),
+        operation("get", "/api/v1/settings/backup", "Download the settings with backup metadata").response(&backup),
+        operation("post", "/api/v1/settings/restore", "Validate and apply a backup").request(&backup).response(&settings),
+        operation("get", "/api/v1/settings/history", "Recent settings changes, newest first").response(&history),
+        operation("get", "/api/v1/settings/defaults", "Default settings and allowed enum values").response(&defaults),
+        operation("get", "/api/v1/settings/schema", "JSON Schema of the settings document").response(&object),
+        operation("get", "/api/v1/settings/profiles", "Profiles, the active one and the schedule").response(&profiles),
+        operation("post", "/api/v1/settings/profiles", "Create a profile").request(&profile).response(&profile).status(201),
+        operation("post", "/api/v1/settings/profiles/deactivate", "Clear the active profile").response(&settings),
+        operation("get", "/api/v1/settings/profiles/:name", "One profile").response(&profile),
+        operation("put", "/api/v1/settings/profiles/:name", "Replace a profile's overrides")
+            .request(&profile_request)
+            .response(&profile),
+        operation("delete", "/api/v1/settings/profiles/:name", "Delete a profile").status(204),
+        operation("post", "/api/v1/settings/profiles/:name/activate", "Activate a profile").response(&settings),
+        operation("get", "/api/v1/alarms", "Alarms and chimes").response(&alarms),
+        operation("post", "/api/v1/alarms", "Create an alarm; its id is assigned").request(&alarm).response(&alarm).status(201),
+        operation("get", "/api/v1/alarms/:id", "One alarm").response(&alarm),
+        operation("put", "/api/v1/alarms/:id", "Replace an alarm").request(&alarm).response(&alarm),
+        operation("delete", "/api/v1/alarms/:id", "Delete an alarm").status(204),
+        operation("get", "/api/v1/settings/:section", "One settings section").response(&object),
+        operation("patch", "/api/v1/settings/:section", "Merge into one settings section").request(&object).response(&object),
+        operation("get", "/api/v1/photo/current", "The photo on the frame, if any").response(&nullable_photo),
+        operation("post", "/api/v1/photo/current", "Report the photo on the frame").request(&photo).response(&photo),
+        operation("post", "/api/v1/photo/next", "Skip to a new photo").status(202),
+        operation("post", "/api/v1/refresh", "Re-fetch location, weather and photo").status(202),
+        operation("post", "/api/v1/message", "Show a message over the frame; queued behind higher priorities")
+            .request(&message_request)
+            .response(&queued_message)
+            .status(202),
+        operation("get", "/api/v1/timer", "The running timer, null when none runs").response(&nullable_timer),
+        operation("post", "/api/v1/timer", "Start a timer, cancelling the one running")
+            .request(&timer_request)
+            .response(&timer)
+            .status(201),
+        operation("delete", "/api/v1/timer", "Stop the running timer; 404 when none runs").status(204),
+        operation("post", "/api/v1/location/active", "Switch the active saved location")
+            .request(&location_request)
+            .response(&settings),
+        operation("get", "/api/v1/weather", "The weather the frame shows").response(&weather),
+        operation("get", "/api/v1/weather/legend", "Weather code icons and labels").response(&legend),
+        operation("get", "/api/v1/scenes", "Scenes, optionally filtered by tag").response(&scenes),
+        operation("post", "/api/v1/scenes/deactivate", "Clear the active scene").response(&settings),
+        operation("post", "/api/v1/scenes/:name/activate", "Activate a scene").response(&settings),
+        operation("get", "/api/v1/events", "Server-Sent Events stream of live updates"),
+        operation("get", "/api/v1/ws", "WebSocket of live updates"),
+        operation("get", "/api/v1/system", "CPU temperature and usage, memory, disk, uptime and version").response(&system),
+        operation("post", "/api/v1/system/reboot", "Reboot the machine, if server.allow_power_control is on").status(202),
+        operation("post", "/api/v1/system/shutdown", "Shut the machine down, if server.allow_power_control is on").status(202),
+        operation("post", "/api/v1/system/check-update", "Check GitHub for a newer release; 502 when it can't be reached")
+            .response(&update),
+        operation("get", "/api/v1/debug", "Debug overlay details, secrets reduced to present/missing").response(&debug),
+        operation("get", "/api/v1/screenshot", "PNG of the frame window; ?scale=0.5 downscales it"),
+        operation("get", "/api/v1/display/power", "Whether the screen is on").response(&display_power),
+        operation("post", "/api/v1/display/power", "Turn the screen on or off")
+            .request(&display_power)
+            .response(&display_power),
+        operation("get", "/api/v1/display/brightness", "Screen brightness and how it's applied").response(&brightness),
+        operation("put", "/api/v1/display/brightness", "Set the screen brightness, dimming in software without a backlight")
+            .request(&brightness_request)
+            .response(&brightness),
+        operation("get", "/api/v1/display/night", "Whether night mode is on and the frame should be dark").response(&night_mode),
+        operation("get", "/api/v1/display/sleep", "Whether the sleep hours have the screen off, and until when")
+            .response(&display_sleep),
+        operation("get", "/api/v1/monitors", "Attached monitors and the frame window on each").response(&monitors),
+        operation("get", "/api/v1/presence", "Whether someone is in the room, and when motion was last seen").response(&presence),
+        operation("post", "/api/v1/presence", "Report motion, waking the screen; 409 while display.presence is off")
+            .response(&presence),
+        operation("get", "/api/v1/server/clients", "Connected event clients").response(&clients),
+        operation("post", "/api/v1/auth/rotate", "Replace the API token").response(&token),
+        operation("get", "/api/v1/pair", "Paired devices").response(&devices),
+        operation("post", "/api/v1/pair", "Exchange the PIN on the frame for a device token")
+            .request(&pair_request)
+            .response(&pairing_result)
+            .status(201),
+        operation("post", "/api/v1/pair/start", "Show a pairing PIN on the frame, the same until it expires; 429 after 5 wrong PINs")
+            .response(&pairing_started),
+        operation("delete", "/api/v1/pair/:id", "Revoke a paired device").status(204),
+        operation("get", "/api/v1/health", "Overall status, uptime, last fetches, providers and disk; 503 when unhealthy")
+            .response(&health),
+        operation("get", "/api/v1/watchdog", "Frame heartbeats and recent watchdog recoveries").response(&watchdog),
+        operation("get", "/api/v1/connectivity", "Whether the frame is online, and when it last was")
+            .response(&connectivity),
+        operation("get", "/api/v1/sync", "Sync role and the lead this frame follows").response(&sync_status),
+        operation("get", "/api/v1/calendar", "Upcoming events from the calendar feeds").response(&calendar),
+        operation("get", "/api/v1/calendar/google", "Whether a Google account is connected").response(&google_calendar),
+        operation("delete", "/api/v1/calendar/google", "Forget the connected Google account").status(204),
+        operation("post", "/api/v1/calendar/google/connect", "Get a code to enter at Google's device page; 409 without an OAuth client")
+            .response(&google_device_code),
+        operation("put", "/api/v1/calendar/caldav/:name/password", "Store a CalDAV calendar's password; empty removes it")
+            .request(&caldav_password)
+            .status(204),
+        operation("get", "/api/v1/quote", "Today's quote, from the bundled set or quotes.url").response(&quote),
+        operation("get", "/api/v1/news", "Headlines from the news feeds, newest first").response(&news),
+        operation("get", "/api/v1/stocks", "Last price and day change of the configured stocks").response(&stocks),
+        operation("get", "/api/v1/crypto", "Prices of the configured coins from CoinGecko").response(&crypto),
+        operation("get", "/api/v1/sports", "Live scores, upcoming fixtures and results of the followed teams and leagues")
+            .response(&sports),
+        operation("get", "/api/v1/tasks", "Tasks due today or overdue in Todoist, and those of the tasks file").response(&tasks),
+        operation("get", "/api/v1/now-playing", "The track playing right now, null when nothing is").response(&now_playing),
+        operation("get", "/api/v1/media/devices", "Chromecasts and Sonos speakers found on the network")
+            .response(&media_devices),
+        operation("get", "/api/v1/spotify", "Whether a Spotify account is connected").response(&spotify),
+        operation("delete", "/api/v1/spotify", "Forget the connected Spotify account").status(204),
+        operation("post", "/api/v1/spotify/connect", "Get the page at which to approve the frame; 409 without a client ID")
+            .response(&spotify_authorization),
+        operation("get", "/api/v1/spotify/callback", "Where Spotify sends the browser after approving; answers with a page"),
+        operation("post", "/api/v1/spotify/callback", "Finish connecting with the address Spotify redirected to")
+            .request(&spotify_callback)
+            .status(204),
+    ];
+
+    let mut paths = Map::new();
+    for operation in &operations {
+        let item = paths
+            .entry(operation.openapi_path())
+            .or_insert_with(|| Value::Object(Map::new()));
+        item[operation.method] = operation.to_json();
+    }
+
+    let mut schemas: Map<String, Value> = gen
+        .take_definitions()
+        .into_iter()
+        .map(|(name, schema)| (name, serde_json::to_value(schema).unwrap_or_default()))
+        .collect();
+    schemas.insert(
+        "Error".to_string(),
+        json!({
+            "type": "object",
+            "properties": {
+                "error": { "type": "string" },
+                "problems": { "type": "array", "items": { "type": "string" } }
+            },
+            "required": ["error"]
+        }),
+    );
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Idleview control API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Changes need the API token or a paired device's token as a Bearer header."
+        },
+        "paths": paths,
+        "components": {
+            "schemas": schemas,
+            "securitySchemes": { "bearerAuth": { "type": "http", "scheme": "bearer" } }
+        }
+    })
+}
+
+/// Swagger UI for the document, loaded from a CDN so the app doesn't carry it
+pub const DOCS_PAGE: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <title>Idleview API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>
+    SwaggerUIBundle({ url: "/api/v1/openapi.json", dom_id: "#swagger-ui" });
+  </script>
+</body>
+</html>
+"##;
@@ -0,0 +1,153 @@
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use idleview_api::ServerEvent;
+
+use crate::http_server::AppState;
+use crate::settings_manager::{LocationSettings, SavedLocation};
+
+/// The subset of OwnTracks messages geofencing cares about
+#[derive(Debug, Deserialize)]
+#[serde(tag = "_type", rename_all = "lowercase")]
+enum OwnTracksMessage {
+    Location {
+        #[serde(default)]
+        inregions: Vec<String>,
+    },
+    Transition {
+        event: String,  // "enter" or "leave"
+        desc: Option<String>,  // Region name
+    },
+    #[serde(other)]
+    Other,
+}
+
+fn saved_location_for_region<'a>(settings: &'a LocationSettings, region: &str) -> Option<&'a SavedLocation> {
+    settings
+        .saved
+        .iter()
+        .find(|location| location.name.eq_ignore_ascii_case(region.trim()))
+}
+
+/// Work out the active saved location after an OwnTracks message.
+///
+/// Returns `Some(new_active)` when the active location should change, where
+/// `None` inside means falling back to auto-detection after leaving a region.
+/// Messages from phones other than the configured device are ignored.
+pub fn active_location_for_message(
+    settings: &LocationSettings,
+    topic: &str,
+    payload: &[u8],
+) -> Option<Option<String>> {
+    let device = settings.owntracks.device.trim_end_matches('/');
+    // Transitions are published on the device topic's "/event" subtopic
+    if !device.is_empty() && topic != device && !topic.starts_with(&format!("{}/", device)) {
+        return None;
+    }
+
+    let message: OwnTracksMessage = serde_json::from_slice(payload).ok()?;
+    let active = settings.active.as_deref();
+
+    match message {
+        OwnTracksMessage::Transition { event, desc } => {
+            let location = saved_location_for_region(settings, desc.as_deref()?)?;
+            match event.as_str() {
+                "enter" if active != Some(location.name.as_str()) => Some(Some(location.name.clone())),
+                "leave" if active == Some(location.name.as_str()) => Some(None),
+                _ => None,
+            }
+        }
+        OwnTracksMessage::Location { inregions } => {
+            let location = inregions
+                .iter()
+                .find_map(|region| saved_location_for_region(settings, region))?;
+            if active == Some(location.name.as_str()) {
+                None
+            } else {
+                Some(Some(location.name.clone()))
+            }
+        }
+        OwnTracksMessage::Other => None,
+    }
+}
+
+fn handle_message(state: &AppState, topic: &str, payload: &[u8]) {
+    let current = match state.settings_manager.get() {
+        Ok(settings) => settings,
+        Err(e) => {
+            warn!("OwnTracks: failed to read settings: {}", e);
+            return;
+        }
+    };
+
+    let Some(active) = active_location_for_message(&current.location, topic, payload) else {
+        return;
+    };
+
+    let updates = json!({ "location": { "active": active } });
+    match state.settings_manager.update_partial_from(updates, "owntracks") {
+        Ok(settings) => {
+            info!("OwnTracks: active location set to {:?}", settings.location.active);
+            state.publish_from(ServerEvent::SettingsUpdated(Box::new(settings.clone())), "owntracks");
+            state.publish_from(
+                ServerEvent::LocationChanged {
+                    active: settings.location.active.clone(),
+                },
+                "owntracks",
+            );
+        }
+        Err(e) => warn!("OwnTracks: failed to set active location: {}", e),
+    }
+}
+
+/// Start the OwnTracks MQTT listener if it is enabled.
+/// Broker settings are read once, so changing them requires a restart.
+pub fn spawn_listener(state: AppState) {
+    let settings = match state.settings_manager.get() {
+        Ok(settings) => settings.location.owntracks,
+        Err(e) => {
+            warn!("OwnTracks: failed to read settings: {}", e);
+            return;
+        }
+    };
+    if !settings.enabled {
+        return;
+    }
+
+    let mut options = MqttOptions::new(
+        format!("idleview-{}", std::process::id()),
+        settings.host.clone(),
+        settings.port,
+    );
+    options.set_keep_alive(Duration::from_secs(30));
+    if let Some(username) = &settings.username {
+        options.set_credentials(username.clone(), settings.password.clone().unwrap_or_default());
+    }
+
+    let (client, mut eventloop) = AsyncClient::new(options, 10);
+    info!("OwnTracks: connecting to {}:{}", settings.host, settings.port);
+
+    tokio::spawn(async move {
+        loop {
+            match eventloop.poll().await {
+                // Subscribe on every (re)connect, the broker may not keep the session
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    if let Err(e) = client.subscribe(settings.topic.clone(), QoS::AtLeastOnce).await {
+                        warn!("OwnTracks: failed to subscribe to {}: {}", settings.topic, e);
+                    }
+                }
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    handle_message(&state, &publish.topic, &publish.payload);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("OwnTracks: MQTT connection error: {}", e);
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                }
+            }
+        }
+    });
+}
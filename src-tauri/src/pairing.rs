@@ -0,0 +1,204 @@
+//! Pairing control panels with the frame using a PIN shown on screen.
+//!
+//! A panel asks the frame to start pairing, the frame shows a 6-digit PIN for
+//! a few minutes, and posting that PIN back returns a device token that works
+//! like the API token until the device is revoked.
+
+use rand::Rng;
+use std::fs;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub use idleview_api::pairing::{PairRequest, PairedDevice, PairingPin, PairingResult, PairingStarted};
+use serde::{Deserialize, Serialize};
+
+use crate::auth;
+use crate::settings_manager;
+
+static DEVICES: OnceLock<Mutex<Vec<DeviceRecord>>> = OnceLock::new();
+static PAIRING: Mutex<PairingState> = Mutex::new(PairingState { pending: None, failures: 0, locked_until: None });
+
+const DEVICES_FILE: &str = "paired_devices.json";
+/// How long a PIN can be used
+pub const PIN_LIFETIME: Duration = Duration::from_secs(5 * 60);
+/// Wrong guesses, across PINs, before pairing is locked
+const MAX_PIN_ATTEMPTS: u32 = 5;
+/// How long pairing stays locked after too many wrong guesses
+pub const PAIRING_LOCKOUT: Duration = Duration::from_secs(15 * 60);
+
+/// A paired device as stored, with its token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DeviceRecord {
+    #[serde(flatten)]
+    device: PairedDevice,
+    token: String,
+}
+
+struct PendingPin {
+    pin: String,
+    expires: Instant,
+}
+
+/// The PIN being shown and the wrong guesses made. Starting pairing again
+/// keeps both, so restarting doesn't buy more guesses.
+struct PairingState {
+    pending: Option<PendingPin>,
+    failures: u32,
+    locked_until: Option<Instant>,
+}
+
+impl PairingState {
+    fn is_locked(&mut self, now: Instant) -> bool {
+        match self.locked_until {
+            Some(until) if until > now => true,
+            Some(_) => {
+                self.locked_until = None;
+                self.failures = 0;
+                false
+            }
+            None => false,
+        }
+    }
+}
+
+fn devices() -> &'static Mutex<Vec<DeviceRecord>> {
+    DEVICES.get_or_init(|| {
+        let records = settings_manager::get_config_file_path(DEVICES_FILE)
+            .ok()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Mutex::new(records)
+    })
+}
+
+fn save_devices(records: &[DeviceRecord]) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(records)
+        .map_err(|e| format!("Failed to serialize paired devices: {}", e))?;
+    auth::write_secret_file(DEVICES_FILE, &json)
+}
+
+/// Start pairing and return the PIN to show on the frame. While a PIN is
+/// still valid, starting again returns it rather than a new one. None while
+/// pairing is locked after too many wrong guesses.
+pub fn start_pairing() -> Result<Option<PairingPin>, String> {
+    let mut state = PAIRING
+        .lock()
+        .map_err(|e| format!("Failed to lock pairing state: {}", e))?;
+    let now = Instant::now();
+    if state.is_locked(now) {
+        return Ok(None);
+    }
+
+    let pending = match state.pending.take().filter(|pending| pending.expires > now) {
+        Some(pending) => pending,
+        None => PendingPin {
+            pin: format!("{:06}", rand::rng().random_range(0..1_000_000)),
+            expires: now + PIN_LIFETIME,
+        },
+    };
+    let started = PairingPin {
+        pin: pending.pin.clone(),
+        expires_in: pending.expires.saturating_duration_since(now).as_secs(),
+    };
+    state.pending = Some(pending);
+    Ok(Some(started))
+}
+
+/// Check a PIN against the pending one. A correct PIN can only be used once;
+/// too many wrong ones end the pairing attempt and lock pairing for a while.
+pub fn redeem_pin(pin: &str) -> Result<bool, String> {
+    let mut state = PAIRING
+        .lock()
+        .map_err(|e| format!("Failed to lock pairing state: {}", e))?;
+    let now = Instant::now();
+    if state.is_locked(now) {
+        return Ok(false);
+    }
+    let Some(current) = state.pending.as_ref() else {
+        return Ok(false);
+    };
+
+    if current.expires <= now {
+        state.pending = None;
+        return Ok(false);
+    }
+    if auth::constant_time_eq(pin.trim().as_bytes(), current.pin.as_bytes()) {
+        state.pending = None;
+        state.failures = 0;
+        return Ok(true);
+    }
+
+    state.failures += 1;
+    if state.failures >= MAX_PIN_ATTEMPTS {
+        state.pending = None;
+        state.locked_until = Some(now + PAIRING_LOCKOUT);
+    }
+    Ok(false)
+}
+
+/// Pair a device after its PIN was accepted
+pub fn pair_device(name: Option<String>) -> Result<PairingResult, String> {
+    let paired_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let id: String = rand::rng()
+        .random::<[u8; 4]>()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let name = name
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| format!("Device {}", id));
+
+    let record = DeviceRecord {
+        device: PairedDevice { id, name, paired_at },
+        token: auth::generate_token(),
+    };
+
+    let mut records = devices()
+        .lock()
+        .map_err(|e| format!("Failed to lock paired devices: {}", e))?;
+    records.push(record.clone());
+    save_devices(&records)?;
+
+    Ok(PairingResult {
+        device: record.device,
+        token: record.token,
+    })
+}
+
+pub fn paired_devices() -> Result<Vec<PairedDevice>, String> {
+    devices()
+        .lock()
+        .map(|records| records.iter().map(|record| record.device.clone()).collect())
+        .map_err(|e| format!("Failed to lock paired devices: {}", e))
+}
+
+/// Revoke a device's token. Returns false if no device has that id.
+pub fn unpair_device(id: &str) -> Result<bool, String> {
+    let mut records = devices()
+        .lock()
+        .map_err(|e| format!("Failed to lock paired devices: {}", e))?;
+    let before = records.len();
+    records.retain(|record| record.device.id != id);
+    if records.len() == before {
+        return Ok(false);
+    }
+    save_devices(&records)?;
+    Ok(true)
+}
+
+/// Whether a token belongs to a paired device
+pub fn is_device_token(token: &str) -> bool {
+    devices()
+        .lock()
+        .map(|records| {
+            records
+                .iter()
+                .any(|record| auth::constant_time_eq(token.as_bytes(), record.token.as_bytes()))
+        })
+        .unwrap_or(false)
+}
@@ -0,0 +1,71 @@
+//! Rebooting or shutting down the machine, to recover a wedged kiosk frame
+//! without pulling the plug. Only with `server.allow_power_control` on.
+
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerAction {
+    Reboot,
+    Shutdown,
+}
+
+impl PowerAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PowerAction::Reboot => "reboot",
+            PowerAction::Shutdown => "shutdown",
+        }
+    }
+}
+
+/// Commands to try in order. On Linux systemctl asks systemd over D-Bus,
+/// which polkit may refuse; `shutdown` works where the user may run it.
+#[cfg(target_os = "linux")]
+pub fn power_commands(action: PowerAction) -> Vec<(&'static str, Vec<&'static str>)> {
+    match action {
+        PowerAction::Reboot => vec![("systemctl", vec!["reboot"]), ("shutdown", vec!["-r", "now"])],
+        PowerAction::Shutdown => vec![("systemctl", vec!["poweroff"]), ("shutdown", vec!["-h", "now"])],
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn power_commands(action: PowerAction) -> Vec<(&'static str, Vec<&'static str>)> {
+    let script = match action {
+        PowerAction::Reboot => "tell application \"System Events\" to restart",
+        PowerAction::Shutdown => "tell application \"System Events\" to shut down",
+    };
+    vec![("osascript", vec!["-e", script])]
+}
+
+#[cfg(target_os = "windows")]
+pub fn power_commands(action: PowerAction) -> Vec<(&'static str, Vec<&'static str>)> {
+    match action {
+        PowerAction::Reboot => vec![("shutdown", vec!["/r", "/t", "0"])],
+        PowerAction::Shutdown => vec![("shutdown", vec!["/s", "/t", "0"])],
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn power_commands(_action: PowerAction) -> Vec<(&'static str, Vec<&'static str>)> {
+    Vec::new()
+}
+
+/// Ask the operating system to reboot or shut down
+pub fn request(action: PowerAction) -> Result<(), String> {
+    let mut failures = Vec::new();
+    for (program, args) in power_commands(action) {
+        match Command::new(program).args(&args).output() {
+            Ok(output) if output.status.success() => return Ok(()),
+            Ok(output) => failures.push(format!(
+                "{}: {}",
+                program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+            Err(e) => failures.push(format!("{}: {}", program, e)),
+        }
+    }
+    if failures.is_empty() {
+        return Err(format!("Can't {} on this platform", action.as_str()));
+    }
+    Err(format!("Failed to {} ({})", action.as_str(), failures.join("; ")))
+}
@@ -0,0 +1,200 @@
+//! Presence: the screen comes on when someone walks into the room and goes
+//! off once nobody has moved for `display.presence.idle_timeout`. Motion
+//! comes from a PIR sensor on a GPIO pin, read through sysfs, or from a
+//! smart-home motion sensor posting to `POST /api/presence`.
+//!
+//! Sleep hours win: motion during them is noted but doesn't wake the screen.
+//! `presence-changed` goes out when the room fills or empties.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+pub use idleview_api::display::{DisplayPower, PowerState, Presence};
+use idleview_api::ServerEvent;
+
+use crate::display_power;
+use crate::display_sleep;
+use crate::http_server::AppState;
+
+/// Between idle checks and GPIO reads; PIR sensors hold their output high
+/// for a few seconds after motion, so none is missed
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+const GPIO_ROOT: &str = "/sys/class/gpio";
+
+/// Whether someone is in the room, from the motion seen so far
+#[derive(Debug, Clone)]
+pub struct PresenceTracker {
+    present: bool,
+    since: Instant,
+    last_motion: Option<u64>,
+}
+
+impl PresenceTracker {
+    /// Starting out, the room counts as occupied until the idle timeout
+    /// passes without motion
+    pub fn new(now: Instant) -> Self {
+        PresenceTracker { present: true, since: now, last_motion: None }
+    }
+
+    /// Motion seen at `now` (`now_ms` as a Unix timestamp); true when it
+    /// fills an empty room
+    pub fn motion(&mut self, now: Instant, now_ms: u64) -> bool {
+        self.since = now;
+        self.last_motion = Some(now_ms);
+        !std::mem::replace(&mut self.present, true)
+    }
+
+    /// True the first time nobody has moved for `idle_timeout`
+    pub fn check(&mut self, now: Instant, idle_timeout: Duration) -> bool {
+        if self.present && now.saturating_duration_since(self.since) >= idle_timeout {
+            self.present = false;
+            return true;
+        }
+        false
+    }
+
+    pub fn status(&self) -> Presence {
+        Presence { present: self.present, last_motion: self.last_motion }
+    }
+}
+
+/// None while presence is off
+static TRACKER: Mutex<Option<PresenceTracker>> = Mutex::new(None);
+
+fn now_ms() -> u64 {
+    chrono::Utc::now().timestamp_millis().max(0) as u64
+}
+
+/// Whether someone is in the room; nobody while presence is off
+pub fn status() -> Presence {
+    TRACKER.lock().ok().and_then(|tracker| tracker.as_ref().map(PresenceTracker::status)).unwrap_or_default()
+}
+
+async fn set_power(state: &AppState, power: PowerState) {
+    match tokio::task::spawn_blocking(move || display_power::set_power(power)).await {
+        Ok(Ok(())) => state.publish_from(ServerEvent::DisplayPowerChanged(DisplayPower { power }), "presence"),
+        Ok(Err(e)) => warn!("Presence: {}", e),
+        Err(e) => warn!("Presence: failed to set display power: {}", e),
+    }
+}
+
+/// Note motion, waking the screen when it fills an empty room
+pub async fn motion(state: &AppState, source: &str) -> Result<Presence, String> {
+    if !state.settings_manager.get()?.display.presence.enabled {
+        return Err("Presence is off; turn on display.presence.enabled first".to_string());
+    }
+    let (arrived, presence) = {
+        let mut tracker = TRACKER.lock().map_err(|e| format!("Failed to lock presence: {}", e))?;
+        let tracker = tracker.get_or_insert_with(|| PresenceTracker::new(Instant::now()));
+        (tracker.motion(Instant::now(), now_ms()), tracker.status())
+    };
+    if arrived {
+        if display_sleep::is_asleep() {
+            info!("👋 Motion ({}) during sleep hours; the screen stays off", source);
+        } else {
+            info!("👋 Motion ({}); waking the screen", source);
+            set_power(state, PowerState::On).await;
+        }
+        state.publish_from(ServerEvent::PresenceChanged(presence), "presence");
+    }
+    Ok(presence)
+}
+
+fn gpio_dir(pin: u32) -> PathBuf {
+    PathBuf::from(GPIO_ROOT).join(format!("gpio{}", pin))
+}
+
+/// Export `pin` through sysfs, unless it already is, and make it an input
+fn export_gpio(pin: u32) -> Result<(), String> {
+    if !gpio_dir(pin).exists() {
+        std::fs::write(PathBuf::from(GPIO_ROOT).join("export"), pin.to_string())
+            .map_err(|e| format!("Failed to export GPIO {}: {}", pin, e))?;
+    }
+    std::fs::write(gpio_dir(pin).join("direction"), "in")
+        .map_err(|e| format!("Failed to make GPIO {} an input: {}", pin, e))
+}
+
+/// Level from a sysfs GPIO `value` file: true when high
+pub fn parse_gpio_value(value: &str) -> Option<bool> {
+    match value.trim() {
+        "1" => Some(true),
+        "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn read_gpio(pin: u32) -> Result<bool, String> {
+    let value = std::fs::read_to_string(gpio_dir(pin).join("value"))
+        .map_err(|e| format!("Failed to read GPIO {}: {}", pin, e))?;
+    parse_gpio_value(&value).ok_or_else(|| format!("Unexpected GPIO {} value {:?}", pin, value.trim()))
+}
+
+/// Read the PIR sensor and turn the screen off once the room empties, for
+/// the lifetime of the server
+pub fn spawn_watcher(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        let mut exported = None;
+        let mut last_error = None;
+        loop {
+            interval.tick().await;
+            let settings = match state.settings_manager.get() {
+                Ok(settings) => settings.display.presence,
+                Err(e) => {
+                    warn!("Presence: failed to read settings: {}", e);
+                    continue;
+                }
+            };
+            if !settings.enabled {
+                if let Ok(mut tracker) = TRACKER.lock() {
+                    *tracker = None;
+                }
+                continue;
+            }
+
+            if let Some(pin) = settings.gpio_pin {
+                let result = if exported == Some(pin) {
+                    read_gpio(pin)
+                } else {
+                    export_gpio(pin).and_then(|()| {
+                        exported = Some(pin);
+                        read_gpio(pin)
+                    })
+                };
+                match result {
+                    Ok(true) => {
+                        last_error = None;
+                        if let Err(e) = motion(&state, "gpio").await {
+                            warn!("Presence: {}", e);
+                        }
+                    }
+                    Ok(false) => last_error = None,
+                    Err(e) => {
+                        // A missing sensor would log it every second otherwise
+                        if last_error.as_ref() != Some(&e) {
+                            warn!("Presence: {}", e);
+                        }
+                        exported = None;
+                        last_error = Some(e);
+                    }
+                }
+            }
+
+            let idle_timeout = Duration::from_secs(settings.idle_timeout);
+            let left = TRACKER.lock().ok().and_then(|mut tracker| {
+                let tracker = tracker.get_or_insert_with(|| PresenceTracker::new(Instant::now()));
+                tracker.check(Instant::now(), idle_timeout).then(|| tracker.status())
+            });
+            if let Some(presence) = left {
+                if !display_sleep::is_asleep() {
+                    info!("🚪 No motion for {}s; turning the screen off", settings.idle_timeout);
+                    set_power(&state, PowerState::Off).await;
+                }
+                state.publish_from(ServerEvent::PresenceChanged(presence), "presence");
+            }
+        }
+    });
+}
@@ -0,0 +1,127 @@
+use chrono::{Local, NaiveTime};
+use serde_json::json;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use idleview_api::ServerEvent;
+
+use crate::http_server::AppState;
+use crate::in_time_window;
+use crate::settings_manager::{Profile, ProfileWindow, ProfilesSettings, Settings, SettingsManager};
+
+/// How often the profile schedule is checked
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+pub fn find_profile<'a>(profiles: &'a ProfilesSettings, name: &str) -> Option<&'a Profile> {
+    profiles.list.iter().find(|profile| profile.name == name)
+}
+
+/// The overrides a profile applies. Profiles can't redefine the profiles themselves.
+pub fn profile_overrides(settings: &serde_json::Value) -> serde_json::Value {
+    match settings {
+        serde_json::Value::Object(overrides) => {
+            let mut overrides = overrides.clone();
+            overrides.remove("profiles");
+            serde_json::Value::Object(overrides)
+        }
+        _ => json!({}),
+    }
+}
+
+/// Add a profile, or replace the one with the same name
+pub fn save_profile(manager: &SettingsManager, profile: Profile) -> Result<Settings, String> {
+    let mut list = manager.get()?.profiles.list;
+    match list.iter_mut().find(|existing| existing.name == profile.name) {
+        Some(existing) => *existing = profile,
+        None => list.push(profile),
+    }
+    manager.update_partial(json!({ "profiles": { "list": list } }))
+}
+
+/// Remove a profile along with its schedule windows. Its overrides stay applied.
+pub fn delete_profile(manager: &SettingsManager, name: &str) -> Result<Settings, String> {
+    let current = manager.get()?.profiles;
+    let list: Vec<&Profile> = current.list.iter().filter(|profile| profile.name != name).collect();
+    let schedule: Vec<&ProfileWindow> = current.schedule.iter().filter(|window| window.profile != name).collect();
+    let active = current.active.filter(|active| active != name);
+
+    manager.update_partial(json!({ "profiles": { "list": list, "schedule": schedule, "active": active } }))
+}
+
+/// Activate a profile: mark it active and merge its settings overrides
+pub fn activate_profile(manager: &SettingsManager, name: &str) -> Result<Settings, String> {
+    activate_profile_from(manager, name, "http")
+}
+
+/// Same as `activate_profile`, recording `source` in the settings history
+pub fn activate_profile_from(manager: &SettingsManager, name: &str, source: &str) -> Result<Settings, String> {
+    let current = manager.get()?;
+    let profile = find_profile(&current.profiles, name)
+        .ok_or_else(|| format!("Unknown profile: {}", name))?;
+
+    let mut patch = profile_overrides(&profile.settings);
+    patch["profiles"] = json!({ "active": name });
+
+    manager.update_partial_from(patch, source)
+}
+
+/// Clear the active profile. Overrides applied on activation are kept.
+pub fn deactivate_profile(manager: &SettingsManager) -> Result<Settings, String> {
+    manager.update_partial(json!({ "profiles": { "active": null } }))
+}
+
+/// The profile scheduled for a time of day, if any window covers it
+pub fn scheduled_profile(schedule: &[ProfileWindow], time: NaiveTime) -> Option<&str> {
+    schedule
+        .iter()
+        .find(|window| in_time_window(&window.start, &window.end, time))
+        .map(|window| window.profile.as_str())
+}
+
+/// Switch profiles when the schedule moves into a new window.
+///
+/// Only changes of the scheduled profile act, so a profile picked by hand
+/// stays until the next window starts. `last_scheduled` carries the
+/// scheduled profile between calls. Returns the new settings when anything
+/// changed.
+pub fn sync_scheduled_profile(
+    manager: &SettingsManager,
+    time: NaiveTime,
+    last_scheduled: &mut Option<String>,
+) -> Result<Option<Settings>, String> {
+    let current = manager.get()?;
+    let scheduled = scheduled_profile(&current.profiles.schedule, time).map(str::to_string);
+    if scheduled == *last_scheduled {
+        return Ok(None);
+    }
+    *last_scheduled = scheduled.clone();
+
+    match scheduled {
+        Some(name)
+            if current.profiles.active.as_deref() != Some(name.as_str())
+                && find_profile(&current.profiles, &name).is_some() =>
+        {
+            activate_profile_from(manager, &name, "schedule").map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Check the profile schedule periodically for the lifetime of the server
+pub fn spawn_scheduler(state: AppState) {
+    tokio::spawn(async move {
+        let mut last_scheduled = None;
+        let mut interval = tokio::time::interval(SCHEDULE_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            match sync_scheduled_profile(&state.settings_manager, Local::now().time(), &mut last_scheduled) {
+                Ok(Some(settings)) => {
+                    info!("Scheduled profile {:?} activated", settings.profiles.active);
+                    state.publish_from(ServerEvent::SettingsUpdated(Box::new(settings)), "schedule");
+                }
+                Ok(None) => {}
+                Err(e) => warn!("Failed to apply profile schedule: {}", e),
+            }
+        }
+    });
+}
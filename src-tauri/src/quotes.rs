@@ -0,0 +1,136 @@
+//! Quote of the day: from a bundled offline set, or from a remote API with
+//! the bundled set as the fallback. The day's quote is cached so the remote
+//! API is asked once a day.
+
+use chrono::{Datelike, Local, NaiveDate};
+use serde_json::Value;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::warn;
+
+pub use idleview_api::quote::Quote;
+
+use crate::settings_manager::{QuoteSource, QuotesSettings};
+
+/// (text, author)
+const BUNDLED: &[(&str, &str)] = &[
+    ("The only way to do great work is to love what you do.", "Steve Jobs"),
+    ("Nothing in life is to be feared, it is only to be understood.", "Marie Curie"),
+    ("It does not matter how slowly you go as long as you do not stop.", "Confucius"),
+    ("Well done is better than well said.", "Benjamin Franklin"),
+    ("The best time to plant a tree was twenty years ago. The second best time is now.", "Chinese proverb"),
+    ("Simplicity is the ultimate sophistication.", "Leonardo da Vinci"),
+    ("What we think, we become.", "Buddha"),
+    ("Happiness depends upon ourselves.", "Aristotle"),
+    ("Act as if what you do makes a difference. It does.", "William James"),
+    ("The journey of a thousand miles begins with one step.", "Lao Tzu"),
+    ("Turn your face to the sun and the shadows fall behind you.", "Maori proverb"),
+    ("In the middle of difficulty lies opportunity.", "Albert Einstein"),
+    ("The secret of getting ahead is getting started.", "Mark Twain"),
+    ("Adopt the pace of nature: her secret is patience.", "Ralph Waldo Emerson"),
+    ("Whatever you are, be a good one.", "Abraham Lincoln"),
+    ("We are what we repeatedly do.", "Will Durant"),
+    ("Look deep into nature, and then you will understand everything better.", "Albert Einstein"),
+    ("The present moment is filled with joy and happiness. If you are attentive, you will see it.", "Thich Nhat Hanh"),
+    ("If you want to lift yourself up, lift up someone else.", "Booker T. Washington"),
+    ("To live is the rarest thing in the world. Most people exist, that is all.", "Oscar Wilde"),
+    ("Everything has beauty, but not everyone sees it.", "Confucius"),
+    ("He who has a why to live can bear almost any how.", "Friedrich Nietzsche"),
+    ("Do what you can, with what you have, where you are.", "Theodore Roosevelt"),
+    ("Little by little, one travels far.", "J. R. R. Tolkien"),
+    ("Not all those who wander are lost.", "J. R. R. Tolkien"),
+    ("Dwell on the beauty of life. Watch the stars, and see yourself running with them.", "Marcus Aurelius"),
+    ("Very little is needed to make a happy life.", "Marcus Aurelius"),
+    ("Luck is what happens when preparation meets opportunity.", "Seneca"),
+    ("While we wait for life, life passes.", "Seneca"),
+    ("There is no path to happiness: happiness is the path.", "Buddha"),
+    ("One today is worth two tomorrows.", "Benjamin Franklin"),
+    ("Knowing yourself is the beginning of all wisdom.", "Aristotle"),
+    ("The best way out is always through.", "Robert Frost"),
+    ("In every walk with nature one receives far more than he seeks.", "John Muir"),
+    ("Nature does not hurry, yet everything is accomplished.", "Lao Tzu"),
+    ("Where there is love there is life.", "Mahatma Gandhi"),
+    ("Be the change that you wish to see in the world.", "Mahatma Gandhi"),
+];
+
+/// How long the remote API gets to answer
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+static CACHE: Mutex<Option<Quote>> = Mutex::new(None);
+
+/// The bundled quote for a day, going through the set one day at a time
+pub fn bundled_quote(date: NaiveDate) -> Quote {
+    let (text, author) = BUNDLED[date.num_days_from_ce().unsigned_abs() as usize % BUNDLED.len()];
+    Quote {
+        text: text.to_string(),
+        author: Some(author.to_string()),
+        source: QuoteSource::Bundled,
+        date: date.format("%Y-%m-%d").to_string(),
+    }
+}
+
+/// Text and author of a remote answer: ZenQuotes' `[{"q": ..., "a": ...}]`,
+/// or an object (or array of them) with "content", "quote" or "text" and "author"
+pub fn parse_remote_quote(json: &str) -> Result<(String, Option<String>), String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| format!("Failed to parse quote: {}", e))?;
+    let quote = match &value {
+        Value::Array(quotes) => quotes.first().unwrap_or(&Value::Null),
+        _ => &value,
+    };
+    let field = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| quote.get(*name).and_then(Value::as_str))
+            .map(|text| text.trim().to_string())
+            .filter(|text| !text.is_empty())
+    };
+    let text = field(&["q", "content", "quote", "text"]).ok_or_else(|| "No quote in the answer".to_string())?;
+    Ok((text, field(&["a", "author"])))
+}
+
+/// Today's quote from a remote API
+pub async fn fetch_quote_from(client: &reqwest::Client, url: &str, date: NaiveDate) -> Result<Quote, String> {
+    let body = client
+        .get(url)
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to fetch quote: {}", e))?
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read quote: {}", e))?;
+    let (text, author) = parse_remote_quote(&body)?;
+    Ok(Quote {
+        text,
+        author,
+        source: QuoteSource::Remote,
+        date: date.format("%Y-%m-%d").to_string(),
+    })
+}
+
+/// Today's quote, from the cache when it was already picked today
+pub async fn quote_of_the_day(settings: &QuotesSettings) -> Quote {
+    let today = Local::now().date_naive();
+    let date = today.format("%Y-%m-%d").to_string();
+    let cached = CACHE.lock().ok().and_then(|cache| cache.clone());
+    if let Some(quote) = cached.filter(|quote| quote.date == date && quote.source == settings.source) {
+        return quote;
+    }
+
+    let quote = match settings.source {
+        QuoteSource::Bundled => bundled_quote(today),
+        QuoteSource::Remote => match fetch_quote_from(crate::http_client(), &settings.url, today).await {
+            Ok(quote) => quote,
+            Err(e) => {
+                // Not cached, so the remote API is tried again next time
+                warn!("Quotes: {}", e);
+                return bundled_quote(today);
+            }
+        },
+    };
+    if let Ok(mut cache) = CACHE.lock() {
+        *cache = Some(quote.clone());
+    }
+    quote
+}
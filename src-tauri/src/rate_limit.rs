@@ -0,0 +1,72 @@
+//! Per-client rate limiting for the HTTP API.
+//!
+//! Every client IP gets a token bucket for reads and one for writes. A bucket
+//! holds a minute's worth of requests and refills evenly, so short bursts are
+//! fine but a script hammering `PATCH /api/settings` gets 429s instead of
+//! wearing out the SD card.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Clients tracked before idle ones are forgotten
+const MAX_TRACKED_CLIENTS: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RequestKind {
+    Read,
+    Write,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    /// Tokens after refilling since the last update, capped at a minute's worth
+    fn refill(&mut self, per_minute: u32, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * per_minute as f64 / 60.0).min(per_minute as f64);
+        self.updated = now;
+    }
+}
+
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<(IpAddr, RequestKind), Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take one request from the client's bucket. When it is empty, returns how
+    /// long until the next request is allowed.
+    pub fn check(&self, ip: IpAddr, kind: RequestKind, per_minute: u32, now: Instant) -> Result<(), Duration> {
+        let Ok(mut buckets) = self.buckets.lock() else {
+            // Never lock everyone out because of a poisoned lock
+            return Ok(());
+        };
+
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            buckets.retain(|_, bucket| now.saturating_duration_since(bucket.updated) < Duration::from_secs(60));
+        }
+
+        let bucket = buckets.entry((ip, kind)).or_insert(Bucket {
+            tokens: per_minute as f64,
+            updated: now,
+        });
+        bucket.refill(per_minute, now);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            let missing = 1.0 - bucket.tokens;
+            Err(Duration::from_secs_f64(missing * 60.0 / per_minute.max(1) as f64))
+        }
+    }
+}
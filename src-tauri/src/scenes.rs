@@ -0,0 +1,165 @@
+use rand::seq::IndexedRandom;
+use serde_json::json;
+
+use crate::settings_manager::{Scene, ScenesSettings, Settings, SettingsManager};
+
+pub fn find_scene<'a>(scenes: &'a ScenesSettings, name: &str) -> Option<&'a Scene> {
+    scenes.list.iter().find(|scene| scene.name == name)
+}
+
+/// Scenes carrying the given tag (case-insensitive), or all scenes without a tag
+pub fn scenes_with_tag<'a>(scenes: &'a ScenesSettings, tag: Option<&str>) -> Vec<&'a Scene> {
+    scenes
+        .list
+        .iter()
+        .filter(|scene| match tag {
+            Some(tag) => scene.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)),
+            None => true,
+        })
+        .collect()
+}
+
+/// Fill the `{season}` and `{time_of_day}` placeholders of a query template
+pub fn render_query_template(template: &str, season: &str, time_of_day: &str) -> String {
+    template
+        .replace("{season}", season)
+        .replace("{time_of_day}", time_of_day)
+        .trim()
+        .to_string()
+}
+
+/// Pick a random query template from the active scene, if it defines any
+pub fn pick_query_template(scenes: &ScenesSettings) -> Option<&str> {
+    let active = scenes.active.as_deref()?;
+    find_scene(scenes, active)?
+        .query_templates
+        .choose(&mut rand::rng())
+        .map(String::as_str)
+}
+
+/// Activate a scene: mark it active and merge its settings overrides.
+/// Shared by the HTTP API and anything else that switches scenes.
+pub fn activate_scene(manager: &SettingsManager, name: &str) -> Result<Settings, String> {
+    let current = manager.get()?;
+    let scene = find_scene(&current.scenes, name)
+        .ok_or_else(|| format!("Unknown scene: {}", name))?;
+
+    let mut patch = scene_overrides(scene);
+    patch["scenes"] = json!({ "active": name, "scheduled": false, "revert": null });
+
+    manager.update_partial(patch)
+}
+
+/// Clear the active scene. Overrides applied on activation are kept.
+pub fn deactivate_scene(manager: &SettingsManager) -> Result<Settings, String> {
+    manager.update_partial(json!({ "scenes": { "active": null, "scheduled": false, "revert": null } }))
+}
+
+fn scene_overrides(scene: &Scene) -> serde_json::Value {
+    match &scene.settings {
+        serde_json::Value::Object(overrides) => serde_json::Value::Object(overrides.clone()),
+        _ => json!({}),
+    }
+}
+
+/// Copy the current values of every key an override patch would touch
+fn snapshot_overridden(current: &serde_json::Value, overrides: &serde_json::Value) -> serde_json::Value {
+    let mut snapshot = serde_json::Map::new();
+    if let Some(overrides) = overrides.as_object() {
+        for (key, value) in overrides {
+            let existing = current.get(key).cloned().unwrap_or(serde_json::Value::Null);
+            let saved = if value.is_object() && existing.is_object() {
+                snapshot_overridden(&existing, value)
+            } else {
+                existing
+            };
+            snapshot.insert(key.clone(), saved);
+        }
+    }
+    serde_json::Value::Object(snapshot)
+}
+
+/// Add what `source` has and `target` lacks, keeping every value `target` already has
+fn merge_missing(target: &mut serde_json::Value, source: serde_json::Value) {
+    let (Some(target), serde_json::Value::Object(source)) = (target.as_object_mut(), source) else {
+        return;
+    };
+    for (key, value) in source {
+        match target.get_mut(&key) {
+            Some(existing) => merge_missing(existing, value),
+            None => {
+                target.insert(key, value);
+            }
+        }
+    }
+}
+
+/// What to restore once a scheduled scene ends: the `saved` snapshot of the
+/// scene it replaced, if any, plus the current values of keys only this
+/// scene's `overrides` touch. Values already saved are the originals and win.
+pub fn scheduled_revert(
+    saved: &serde_json::Value,
+    current: &serde_json::Value,
+    overrides: &serde_json::Value,
+) -> serde_json::Value {
+    let mut revert = match saved {
+        serde_json::Value::Object(_) => saved.clone(),
+        _ => json!({}),
+    };
+    merge_missing(&mut revert, snapshot_overridden(current, overrides));
+    revert
+}
+
+/// Find the scene whose calendar keywords match an event title (case-insensitive)
+pub fn scene_for_event_title<'a>(scenes: &'a ScenesSettings, title: &str) -> Option<&'a Scene> {
+    let title = title.to_lowercase();
+    scenes.list.iter().find(|scene| {
+        scene
+            .calendar_keywords
+            .iter()
+            .any(|keyword| !keyword.is_empty() && title.contains(&keyword.to_lowercase()))
+    })
+}
+
+/// Bring the active scene in line with the events happening right now.
+///
+/// A matching event activates its scene and remembers the settings it
+/// overrides; once no event matches, the scheduled scene is reverted.
+/// Scenes activated manually are left alone. Returns the new settings when
+/// anything changed.
+pub fn sync_scheduled_scene(manager: &SettingsManager, current_titles: &[String]) -> Result<Option<Settings>, String> {
+    let current = manager.get()?;
+    let scenes = &current.scenes;
+    let matched = current_titles
+        .iter()
+        .find_map(|title| scene_for_event_title(scenes, title));
+
+    match matched {
+        Some(scene) if scenes.active.as_deref() != Some(scene.name.as_str()) => {
+            if scenes.active.is_some() && !scenes.scheduled {
+                return Ok(None);
+            }
+
+            let overrides = scene_overrides(scene);
+            let current_json = serde_json::to_value(&current)
+                .map_err(|e| format!("Failed to serialize current settings: {}", e))?;
+            // Keep the original snapshot when hopping between scheduled scenes,
+            // adding the keys only the new scene overrides
+            let saved = if scenes.scheduled { &scenes.revert } else { &serde_json::Value::Null };
+            let revert = scheduled_revert(saved, &current_json, &overrides);
+
+            let mut patch = overrides;
+            patch["scenes"] = json!({ "active": scene.name, "scheduled": true, "revert": revert });
+            manager.update_partial_from(patch, "calendar").map(Some)
+        }
+        None if scenes.scheduled => {
+            let mut patch = match &scenes.revert {
+                serde_json::Value::Object(_) => scenes.revert.clone(),
+                _ => json!({}),
+            };
+            patch["scenes"] = json!({ "active": null, "scheduled": false, "revert": null });
+            manager.update_partial_from(patch, "calendar").map(Some)
+        }
+        _ => Ok(None),
+    }
+}
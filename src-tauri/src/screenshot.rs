@@ -0,0 +1,155 @@
+//! Screenshots of the frame window, to check from another room what it shows.
+//!
+//! The webview has no capture API of its own, so Tauri supplies the window's
+//! place on screen and the platform's screenshot tool grabs that rectangle.
+
+use image::{imageops::FilterType, ImageFormat};
+use std::io::Cursor;
+use std::path::Path;
+use std::process::Command;
+use tauri::{AppHandle, Manager};
+
+/// Where the frame's webview sits on screen, in physical pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+}
+
+/// The on-screen rectangle of the main window's contents
+pub fn window_rect(app: &AppHandle) -> Result<WindowRect, String> {
+    let window = app
+        .get_webview_window("main")
+        .ok_or_else(|| "The frame window is not open".to_string())?;
+    let position = window
+        .inner_position()
+        .map_err(|e| format!("Failed to read window position: {}", e))?;
+    let size = window
+        .inner_size()
+        .map_err(|e| format!("Failed to read window size: {}", e))?;
+    let scale_factor = window
+        .scale_factor()
+        .map_err(|e| format!("Failed to read window scale: {}", e))?;
+    Ok(WindowRect {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        scale_factor,
+    })
+}
+
+/// Screenshot tools to try in order, each writing a PNG of `rect` to `output`
+#[cfg(target_os = "linux")]
+fn capture_commands(rect: &WindowRect, output: &Path) -> Vec<Command> {
+    let output = output.to_string_lossy().to_string();
+    // grim on Wayland, ImageMagick's import on X11
+    let mut grim = Command::new("grim");
+    grim.args([
+        "-g",
+        &format!("{},{} {}x{}", rect.x, rect.y, rect.width, rect.height),
+        &output,
+    ]);
+    let mut import = Command::new("import");
+    import.args([
+        "-window",
+        "root",
+        "-crop",
+        &format!("{}x{}+{}+{}", rect.width, rect.height, rect.x, rect.y),
+        &output,
+    ]);
+    vec![grim, import]
+}
+
+#[cfg(target_os = "macos")]
+fn capture_commands(rect: &WindowRect, output: &Path) -> Vec<Command> {
+    // screencapture works in points rather than pixels
+    let points = |pixels: f64| (pixels / rect.scale_factor).round() as i64;
+    let mut screencapture = Command::new("screencapture");
+    screencapture.arg("-x").arg(format!(
+        "-R{},{},{},{}",
+        points(rect.x as f64),
+        points(rect.y as f64),
+        points(rect.width as f64),
+        points(rect.height as f64)
+    ));
+    screencapture.arg(output);
+    vec![screencapture]
+}
+
+#[cfg(target_os = "windows")]
+fn capture_commands(rect: &WindowRect, output: &Path) -> Vec<Command> {
+    let script = format!(
+        "Add-Type -AssemblyName System.Drawing; \
+         $bitmap = New-Object System.Drawing.Bitmap {w}, {h}; \
+         $graphics = [System.Drawing.Graphics]::FromImage($bitmap); \
+         $graphics.CopyFromScreen({x}, {y}, 0, 0, $bitmap.Size); \
+         $bitmap.Save('{path}', [System.Drawing.Imaging.ImageFormat]::Png)",
+        w = rect.width,
+        h = rect.height,
+        x = rect.x,
+        y = rect.y,
+        path = output.display()
+    );
+    let mut powershell = Command::new("powershell");
+    powershell.args(["-NoProfile", "-NonInteractive", "-Command", &script]);
+    vec![powershell]
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn capture_commands(_rect: &WindowRect, _output: &Path) -> Vec<Command> {
+    Vec::new()
+}
+
+/// A PNG of what is on screen inside `rect`
+pub fn capture(rect: &WindowRect) -> Result<Vec<u8>, String> {
+    let output = std::env::temp_dir().join(format!("idleview-screenshot-{}.png", std::process::id()));
+    let mut failures = Vec::new();
+
+    for mut command in capture_commands(rect, &output) {
+        let program = command.get_program().to_string_lossy().to_string();
+        match command.output() {
+            Ok(result) if result.status.success() => {
+                let png = std::fs::read(&output)
+                    .map_err(|e| format!("Failed to read screenshot: {}", e));
+                let _ = std::fs::remove_file(&output);
+                return png;
+            }
+            Ok(result) => failures.push(format!(
+                "{}: {}",
+                program,
+                String::from_utf8_lossy(&result.stderr).trim()
+            )),
+            Err(e) => failures.push(format!("{}: {}", program, e)),
+        }
+    }
+
+    let _ = std::fs::remove_file(&output);
+    if failures.is_empty() {
+        return Err("Screenshots are not supported on this platform".to_string());
+    }
+    Err(format!("Failed to capture screenshot ({})", failures.join("; ")))
+}
+
+/// Size of an image scaled by `scale`, never below one pixel
+pub fn scaled_size(width: u32, height: u32, scale: f32) -> (u32, u32) {
+    let scaled = |pixels: u32| ((pixels as f32 * scale).round() as u32).max(1);
+    (scaled(width), scaled(height))
+}
+
+/// Shrink a PNG by `scale` (between 0 and 1), keeping its aspect ratio
+pub fn downscale(png: &[u8], scale: f32) -> Result<Vec<u8>, String> {
+    let image = image::load_from_memory_with_format(png, ImageFormat::Png)
+        .map_err(|e| format!("Failed to decode screenshot: {}", e))?;
+    let (width, height) = scaled_size(image.width(), image.height(), scale);
+    let resized = image.resize_exact(width, height, FilterType::Triangle);
+
+    let mut encoded = Cursor::new(Vec::new());
+    resized
+        .write_to(&mut encoded, ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode screenshot: {}", e))?;
+    Ok(encoded.into_inner())
+}
@@ -0,0 +1,173 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::net::SocketAddr;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+pub use idleview_api::{SettingChange, SettingsHistoryEntry};
+
+use crate::settings_manager::{self, Settings};
+use crate::write_throttle::WriteThrottle;
+
+static HISTORY: OnceLock<Mutex<VecDeque<SettingsHistoryEntry>>> = OnceLock::new();
+static HISTORY_WRITES: OnceLock<WriteThrottle> = OnceLock::new();
+
+/// Oldest entries are dropped beyond this
+const MAX_ENTRIES: usize = 500;
+const HISTORY_FILE: &str = "settings_history.json";
+/// Repeated changes to the same keys by the same client within this window
+/// (a slider being dragged) are folded into one entry
+const MERGE_WINDOW_MS: u64 = 2000;
+
+/// Who changed the settings
+#[derive(Debug, Clone)]
+pub struct ChangeSource {
+    pub source: String,
+    pub client: Option<String>,
+}
+
+impl ChangeSource {
+    /// A change made over the HTTP API by the client at `addr`
+    pub fn http(addr: SocketAddr) -> Self {
+        ChangeSource {
+            source: "http".to_string(),
+            client: Some(addr.ip().to_string()),
+        }
+    }
+}
+
+impl From<&str> for ChangeSource {
+    fn from(source: &str) -> Self {
+        ChangeSource {
+            source: source.to_string(),
+            client: None,
+        }
+    }
+}
+
+/// Every leaf value that differs between two settings documents.
+/// Lists are compared as a whole. Credentials are never written to the log.
+pub fn diff_settings(old: &serde_json::Value, new: &serde_json::Value) -> Vec<SettingChange> {
+    let mut changes = Vec::new();
+    diff_into(old, new, String::new(), &mut changes);
+    changes
+}
+
+fn diff_into(old: &serde_json::Value, new: &serde_json::Value, key: String, changes: &mut Vec<SettingChange>) {
+    if let (Some(old_obj), Some(new_obj)) = (old.as_object(), new.as_object()) {
+        let mut keys: Vec<&String> = old_obj.keys().chain(new_obj.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        for name in keys {
+            let child = if key.is_empty() { name.clone() } else { format!("{}.{}", key, name) };
+            let null = serde_json::Value::Null;
+            diff_into(old_obj.get(name).unwrap_or(&null), new_obj.get(name).unwrap_or(&null), child, changes);
+        }
+        return;
+    }
+
+    if old != new {
+        let secret = settings_manager::is_secret(&key);
+        let redact = |value: &serde_json::Value| match value {
+            serde_json::Value::Null => serde_json::Value::Null,
+            _ if secret => settings_manager::REDACTED.into(),
+            _ => {
+                let mut value = value.clone();
+                settings_manager::redact_secrets(&mut value, &key);
+                value
+            }
+        };
+        changes.push(SettingChange {
+            old: redact(old),
+            new: redact(new),
+            key,
+        });
+    }
+}
+
+fn history() -> &'static Mutex<VecDeque<SettingsHistoryEntry>> {
+    HISTORY.get_or_init(|| {
+        let entries = settings_manager::get_config_file_path(HISTORY_FILE)
+            .ok()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Mutex::new(entries)
+    })
+}
+
+/// Add a mutation to the log. Writes that changed nothing are skipped.
+pub fn record_change(source: &ChangeSource, old: &Settings, new: &Settings) {
+    let (Ok(old), Ok(new)) = (serde_json::to_value(old), serde_json::to_value(new)) else {
+        return;
+    };
+    let changes = diff_settings(&old, &new);
+    if changes.is_empty() {
+        return;
+    }
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let entry = SettingsHistoryEntry {
+        timestamp,
+        source: source.source.clone(),
+        client: source.client.clone(),
+        changes,
+    };
+
+    let Ok(mut entries) = history().lock() else {
+        return;
+    };
+    match entries.back_mut() {
+        Some(last) if continues(last, &entry) => {
+            for (change, newer) in last.changes.iter_mut().zip(entry.changes) {
+                change.new = newer.new;
+            }
+            last.timestamp = entry.timestamp;
+        }
+        _ => entries.push_back(entry),
+    }
+    while entries.len() > MAX_ENTRIES {
+        entries.pop_front();
+    }
+
+    let Ok(json) = serde_json::to_string(&*entries) else {
+        return;
+    };
+    let writes = HISTORY_WRITES.get_or_init(|| WriteThrottle::new(Duration::from_secs(1)));
+    if let Err(e) = writes.submit(move || save_history(&json)) {
+        eprintln!("Failed to save settings history: {}", e);
+    }
+}
+
+/// Whether `entry` carries on the edit logged in `last`
+fn continues(last: &SettingsHistoryEntry, entry: &SettingsHistoryEntry) -> bool {
+    last.source == entry.source
+        && last.client == entry.client
+        && entry.timestamp.saturating_sub(last.timestamp) <= MERGE_WINDOW_MS
+        && last.changes.len() == entry.changes.len()
+        && last.changes.iter().zip(&entry.changes).all(|(a, b)| a.key == b.key)
+}
+
+fn save_history(json: &str) -> Result<(), String> {
+    settings_manager::ensure_settings_dir()?;
+    let path = settings_manager::get_config_file_path(HISTORY_FILE)?;
+    settings_manager::write_file_atomic(&path, json.as_bytes())
+}
+
+/// Logged mutations, newest first
+pub fn recent_changes(limit: Option<usize>) -> Vec<SettingsHistoryEntry> {
+    history()
+        .lock()
+        .map(|entries| {
+            entries
+                .iter()
+                .rev()
+                .take(limit.unwrap_or(MAX_ENTRIES))
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
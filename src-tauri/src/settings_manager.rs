@@ -1,121 +1,37 @@
-use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+use tokio::sync::watch;
 
-static SETTINGS_CACHE: OnceLock<RwLock<Settings>> = OnceLock::new();
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Settings {
-    pub units: UnitsSettings,
-    pub display: DisplaySettings,
-    pub photos: PhotosSettings,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct UnitsSettings {
-    pub temperature_unit: String,  // "celsius" or "fahrenheit"
-    pub time_format: String,        // "24h" or "12h"
-    pub date_format: String,        // "mdy", "dmy", "ymd"
-    pub wind_speed_unit: String,    // "kmh", "mph", "ms"
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct DisplaySettings {
-    pub show_humidity_wind: bool,
-    pub show_precipitation_cloudiness: bool,
-    pub show_sunrise_sunset: bool,
-    pub show_cpu_temp: bool,
-    #[serde(default)]
-    pub show_debug: bool,
-    #[serde(default = "default_debug_position")]
-    pub debug_position: String,  // "left" or "right"
-}
-
-fn default_debug_position() -> String {
-    "right".to_string()
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct PhotosSettings {
-    pub refresh_interval: u64,  // in minutes
-    #[serde(deserialize_with = "deserialize_quality")]
-    pub photo_quality: String,  // Accepts both "85" string or 85 number
-    #[serde(default = "default_enable_festive")]
-    pub enable_festive_queries: bool,  // Enable holiday/festive photo themes
-}
-
-fn default_enable_festive() -> bool {
-    true
-}
+use crate::display_state;
+use crate::settings_history::{self, ChangeSource};
+use crate::webhooks;
+use crate::write_throttle::WriteThrottle;
 
-// Custom deserializer to handle both string and number
-fn deserialize_quality<'de, D>(deserializer: D) -> Result<String, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    use serde::de::{self, Visitor};
-    use std::fmt;
+// Settings types live in the shared API crate so companion tools use the same definitions
+pub use idleview_api::settings::{
+    AccessLogLevel, Alarm, AlarmDay, AlarmsSettings, BurnInRefresh, BurnInSettings, CalDavCalendar, CalendarFeed,
+    CalendarSettings, ConnectivitySettings, CountdownEvent, CountdownsSettings, CryptoSettings, DateFormat,
+    DebugPosition, DisplaySettings, DisplaySleepSettings, GoogleCalendarSettings, GreetingSettings, LastFmSettings,
+    LocationMode, LocationSettings, MediaSettings, MonitorOverride, MonitorsSettings, MqttSettings, MusicSettings,
+    NewsFeed, NewsSettings, NightModeSettings, OwnTracksSettings, PhotoApiKeys, PhotoOrientation, PhotoProvider,
+    PhotoQuerySettings, PhotosSettings, PresenceSettings, Profile, ProfileWindow, ProfilesSettings, QualityWindow,
+    QuoteSource, QuotesSettings, RateLimitSettings, SavedLocation, Scene, ScenesSettings, ServerBind, ServerSettings,
+    Settings, SettingsDefaults, SportsApiKeys, SportsProvider, SportsSettings, SpotifySettings, StockApiKeys,
+    StockProvider, StocksSettings, SyncRole, SyncSettings, TasksSettings, TemperatureUnit, TimeFormat, TlsSettings,
+    UnitsSettings, WatchdogSettings, WeatherAlertSettings, WeatherApiKeys, WeatherProvider, WeatherSettings, Webhook,
+    WebhooksSettings, WindSpeedUnit, WorldClock, settings_schema,
+};
 
-    struct QualityVisitor;
+pub use idleview_api::backup::{SettingsBackup, BACKUP_FORMAT, BACKUP_VERSION};
 
-    impl<'de> Visitor<'de> for QualityVisitor {
-        type Value = String;
-
-        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-            formatter.write_str("a string or number")
-        }
-
-        fn visit_str<E>(self, value: &str) -> Result<String, E>
-        where
-            E: de::Error,
-        {
-            Ok(value.to_string())
-        }
-
-        fn visit_u64<E>(self, value: u64) -> Result<String, E>
-        where
-            E: de::Error,
-        {
-            Ok(value.to_string())
-        }
-
-        fn visit_i64<E>(self, value: i64) -> Result<String, E>
-        where
-            E: de::Error,
-        {
-            Ok(value.to_string())
-        }
-    }
+static SETTINGS_CACHE: OnceLock<RwLock<Settings>> = OnceLock::new();
 
-    deserializer.deserialize_any(QualityVisitor)
-}
-
-impl Default for Settings {
-    fn default() -> Self {
-        Settings {
-            units: UnitsSettings {
-                temperature_unit: "celsius".to_string(),
-                time_format: "24h".to_string(),
-                date_format: "dmy".to_string(),
-                wind_speed_unit: "kmh".to_string(),
-            },
-            display: DisplaySettings {
-                show_humidity_wind: true,
-                show_precipitation_cloudiness: true,
-                show_sunrise_sunset: true,
-                show_cpu_temp: false,
-                show_debug: false,
-                debug_position: "right".to_string(),
-            },
-            photos: PhotosSettings {
-                refresh_interval: 30,
-                photo_quality: "80".to_string(),
-                enable_festive_queries: true,
-            },
-        }
-    }
-}
+/// Minimum time between settings.json writes from the SettingsManager
+const SETTINGS_WRITE_INTERVAL: Duration = Duration::from_secs(1);
 
 /// Get the cross-platform settings file path
 pub fn get_settings_path() -> Result<PathBuf, String> {
@@ -149,8 +65,17 @@ pub fn get_settings_path() -> Result<PathBuf, String> {
     }
 }
 
+/// Get the path of another file stored alongside settings.json
+pub fn get_config_file_path(file_name: &str) -> Result<PathBuf, String> {
+    let settings_path = get_settings_path()?;
+    settings_path
+        .parent()
+        .map(|dir| dir.join(file_name))
+        .ok_or_else(|| "Failed to get settings directory".to_string())
+}
+
 /// Ensure the settings directory exists
-fn ensure_settings_dir() -> Result<(), String> {
+pub fn ensure_settings_dir() -> Result<(), String> {
     let settings_path = get_settings_path()?;
     if let Some(parent) = settings_path.parent() {
         if !parent.exists() {
@@ -174,53 +99,183 @@ pub fn read_settings() -> Result<Settings, String> {
         .map_err(|e| format!("Failed to read settings: {}", e))
 }
 
+/// Write a file so readers only ever see the old or the new contents:
+/// write a temp file beside it, fsync it, then rename it over the target
+pub fn write_file_atomic(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| format!("Invalid file path: {}", path.display()))?;
+    let mut tmp_name = file_name.to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let mut file = fs::File::create(&tmp_path)
+        .map_err(|e| format!("Failed to create {}: {}", tmp_path.display(), e))?;
+    file.write_all(contents)
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("Failed to write {}: {}", tmp_path.display(), e))?;
+    drop(file);
+
+    fs::rename(&tmp_path, path)
+        .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))?;
+
+    // Persist the rename itself (not supported for directories on Windows)
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        if let Ok(dir) = fs::File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+
+    Ok(())
+}
+
+fn backup_path(settings_path: &Path) -> PathBuf {
+    settings_path.with_extension("json.bak")
+}
+
+/// Save settings, keeping the previous good file as settings.json.bak
+fn save_settings_file(settings_path: &Path, settings: &Settings) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+
+    // Only back up a file that parses, so a corrupt file never replaces a good backup
+    if let Ok(previous) = fs::read_to_string(settings_path) {
+        if serde_json::from_str::<Settings>(&previous).is_ok() {
+            write_file_atomic(&backup_path(settings_path), previous.as_bytes())?;
+        }
+    }
+
+    write_file_atomic(settings_path, json.as_bytes())
+}
+
+/// Settings from a document that doesn't deserialize as is, with every enum
+/// field holding a value this version doesn't know (a typo, or one added by
+/// a newer version) reset to its default. None when that isn't enough.
+fn with_unknown_values_reset(mut value: serde_json::Value) -> Option<Settings> {
+    let defaults = serde_json::to_value(Settings::default()).ok()?;
+    for (path, allowed) in settings_defaults().options {
+        let pointer = format!("/{}", path.replace('.', "/"));
+        let Some(field) = value.pointer_mut(&pointer) else {
+            continue;
+        };
+        let known = field.as_str().is_some_and(|name| allowed.iter().any(|option| option == name));
+        if known || field.is_null() || field.is_array() {
+            continue;
+        }
+        let default = defaults.pointer(&pointer).cloned().unwrap_or_default();
+        eprintln!("Unknown {} {} in settings.json; using the default, {}", path, field, default);
+        *field = default;
+    }
+    serde_json::from_value(value).ok()
+}
+
+/// Load settings, falling back to (and restoring from) the backup when the file is unreadable
+fn load_settings_file(settings_path: &Path) -> Result<Settings, String> {
+    let parse = |path: &Path| -> Result<Settings, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read settings file: {}", e))?;
+        let value: serde_json::Value = serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse settings JSON: {}", e))?;
+        match serde_json::from_value(value.clone()) {
+            Ok(settings) => Ok(settings),
+            Err(e) => with_unknown_values_reset(value)
+                .ok_or_else(|| format!("Failed to parse settings JSON: {}", e)),
+        }
+    };
+
+    let error = match parse(settings_path) {
+        Ok(settings) => return Ok(settings),
+        Err(e) => e,
+    };
+
+    let backup = backup_path(settings_path);
+    match parse(&backup) {
+        Ok(settings) => {
+            eprintln!("{}; restored settings from {}", error, backup.display());
+            if let Err(e) = fs::copy(&backup, settings_path) {
+                eprintln!("Failed to restore settings file from backup: {}", e);
+            }
+            Ok(settings)
+        }
+        Err(_) => Err(error),
+    }
+}
+
 /// Write settings to disk
 pub fn write_settings(settings: &Settings) -> Result<(), String> {
     ensure_settings_dir()?;
     let settings_path = get_settings_path()?;
-    
-    let json = serde_json::to_string_pretty(settings)
-        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
-    
-    fs::write(&settings_path, json)
-        .map_err(|e| format!("Failed to write settings file: {}", e))?;
+    save_settings_file(&settings_path, settings)?;
+
+    set_cached_settings(settings);
+    Ok(())
+}
 
+fn set_cached_settings(settings: &Settings) {
     let cache = SETTINGS_CACHE.get_or_init(|| RwLock::new(settings.clone()));
     if let Ok(mut cached) = cache.write() {
         *cached = settings.clone();
     }
-    
-    Ok(())
+}
+
+/// Parse settings.json after it changed on disk. Returns `None` when the
+/// contents match what is already loaded, e.g. after our own writes.
+pub fn reloaded_settings(content: &str, current: &Settings) -> Result<Option<Settings>, String> {
+    let reloaded: Settings = serde_json::from_str(content)
+        .map_err(|e| format!("Failed to parse settings JSON: {}", e))?;
+
+    let unchanged = serde_json::to_value(&reloaded).ok() == serde_json::to_value(current).ok();
+    Ok(if unchanged { None } else { Some(reloaded) })
 }
 
 fn read_settings_from_disk() -> Result<Settings, String> {
     let settings_path = get_settings_path()?;
 
-    if settings_path.exists() {
-        let content = fs::read_to_string(&settings_path)
-            .map_err(|e| format!("Failed to read settings file: {}", e))?;
-
-        serde_json::from_str(&content)
-            .map_err(|e| format!("Failed to parse settings JSON: {}", e))
+    if settings_path.exists() || backup_path(&settings_path).exists() {
+        load_settings_file(&settings_path)
     } else {
         Ok(Settings::default())
     }
 }
 
-/// A thread-safe settings manager
+/// A thread-safe settings manager.
+/// Updates apply in memory at once; disk writes are coalesced to at most one per second.
 #[derive(Clone)]
 pub struct SettingsManager {
     settings: Arc<RwLock<Settings>>,
+    writes: WriteThrottle,
+    changes: Arc<watch::Sender<Settings>>,
 }
 
 impl SettingsManager {
     pub fn new() -> Result<Self, String> {
         let settings = read_settings()?;
+        let (changes, _) = watch::channel(settings.clone());
         Ok(Self {
             settings: Arc::new(RwLock::new(settings)),
+            writes: WriteThrottle::new(SETTINGS_WRITE_INTERVAL),
+            changes: Arc::new(changes),
         })
     }
 
+    /// Receive the settings after every change, whatever made it
+    pub fn subscribe(&self) -> watch::Receiver<Settings> {
+        self.changes.subscribe()
+    }
+
+    fn persist(&self, settings: &Settings) -> Result<(), String> {
+        set_cached_settings(settings);
+        self.changes.send_replace(settings.clone());
+        let settings = settings.clone();
+        self.writes.submit(move || write_settings(&settings))
+    }
+
+    /// Write any coalesced update to disk now, e.g. before exiting
+    pub fn flush(&self) {
+        self.writes.flush();
+    }
+
     pub fn get(&self) -> Result<Settings, String> {
         self.settings
             .read()
@@ -229,16 +284,91 @@ impl SettingsManager {
     }
 
     pub fn update_all(&self, new_settings: Settings) -> Result<(), String> {
-        {
+        self.update_all_from(new_settings, "http")
+    }
+
+    /// Same as `update_all`, recording `source` in the settings history
+    pub fn update_all_from(&self, mut new_settings: Settings, source: impl Into<ChangeSource>) -> Result<(), String> {
+        let old_settings = {
             let mut settings = self.settings
                 .write()
                 .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
-            *settings = new_settings.clone();
+            new_settings = with_secrets_of(new_settings, &settings)?;
+            std::mem::replace(&mut *settings, new_settings.clone())
+        };
+        self.persist(&new_settings)?;
+        settings_history::record_change(&source.into(), &old_settings, &new_settings);
+        Ok(())
+    }
+
+    /// Pick up settings.json after an external edit. Unlike a cold read, an
+    /// unparsable file (often an editor mid-save) is reported and skipped
+    /// rather than replaced by the backup.
+    pub fn reload(&self) -> Result<Option<Settings>, String> {
+        // The file is about to be overwritten with what's in memory anyway
+        if self.writes.is_pending() {
+            return Ok(None);
         }
-        write_settings(&new_settings)
+
+        let settings_path = get_settings_path()?;
+        let content = fs::read_to_string(&settings_path)
+            .map_err(|e| format!("Failed to read settings file: {}", e))?;
+
+        let mut settings = self.settings
+            .write()
+            .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+        let reloaded = reloaded_settings(&content, &settings)?;
+        if let Some(reloaded) = &reloaded {
+            let old_settings = std::mem::replace(&mut *settings, reloaded.clone());
+            set_cached_settings(reloaded);
+            self.changes.send_replace(reloaded.clone());
+            settings_history::record_change(&"file".into(), &old_settings, reloaded);
+        }
+        Ok(reloaded)
+    }
+
+    /// One section of the settings, or `None` for an unknown section name
+    pub fn get_section(&self, section: &str) -> Result<Option<serde_json::Value>, String> {
+        if !SECTIONS.contains(&section) {
+            return Ok(None);
+        }
+        let settings = serde_json::to_value(self.get()?)
+            .map_err(|e| format!("Failed to serialize current settings: {}", e))?;
+        Ok(settings.get(section).cloned())
+    }
+
+    /// Merge a partial update into one section, like `update_partial` scoped to it
+    pub fn update_section_from(
+        &self,
+        section: &str,
+        updates: serde_json::Value,
+        source: impl Into<ChangeSource>,
+    ) -> Result<serde_json::Value, String> {
+        if !SECTIONS.contains(&section) {
+            return Err(format!("Unknown settings section: {}", section));
+        }
+        let settings = self.update_partial_from(serde_json::json!({ section: updates }), source)?;
+        serde_json::to_value(settings)
+            .map(|settings| settings[section].clone())
+            .map_err(|e| format!("Failed to serialize settings: {}", e))
+    }
+
+    /// Problems the settings would have after merging a partial update
+    pub fn validate_partial(&self, updates: &serde_json::Value) -> Result<Vec<String>, String> {
+        let mut merged = serde_json::to_value(self.get()?)
+            .map_err(|e| format!("Failed to serialize current settings: {}", e))?;
+        let stored = merged.clone();
+        merge_json(&mut merged, updates.clone());
+        restore_secrets(&mut merged, &stored, "");
+        Ok(validate_settings_json(&merged))
     }
 
     pub fn update_partial(&self, updates: serde_json::Value) -> Result<Settings, String> {
+        self.update_partial_from(updates, "http")
+    }
+
+    /// Same as `update_partial`, recording `source` in the settings history
+    pub fn update_partial_from(&self, updates: serde_json::Value, source: impl Into<ChangeSource>) -> Result<Settings, String> {
         let mut settings = self.settings
             .write()
             .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
@@ -247,21 +377,766 @@ impl SettingsManager {
         let mut current = serde_json::to_value(&*settings)
             .map_err(|e| format!("Failed to serialize current settings: {}", e))?;
         
-        // Merge the updates
+        // Merge the updates, keeping credentials sent back redacted
+        let stored = current.clone();
         merge_json(&mut current, updates);
+        restore_secrets(&mut current, &stored, "");
         
         // Deserialize back to Settings
         let updated_settings: Settings = serde_json::from_value(current)
             .map_err(|e| format!("Failed to parse updated settings: {}", e))?;
         
-        *settings = updated_settings.clone();
+        let old_settings = std::mem::replace(&mut *settings, updated_settings.clone());
         drop(settings); // Release lock before writing to disk
         
-        write_settings(&updated_settings)?;
+        self.persist(&updated_settings)?;
+        settings_history::record_change(&source.into(), &old_settings, &updated_settings);
         Ok(updated_settings)
     }
 }
 
+/// Top-level settings sections that can be read and patched on their own
+pub const SECTIONS: &[&str] = &["units", "display", "photos", "location", "weather", "server", "sync", "webhooks", "mqtt", "calendar", "quotes", "news", "stocks", "crypto", "music", "sports", "tasks", "countdowns", "clock", "alarms", "monitors", "watchdog", "connectivity"];
+
+const LOCATION_SOURCES: &[&str] = &["ip", "geoclue", "gps"];
+const LEGACY_QUALITIES: &[&str] = &["low", "medium", "high", "maximum"];
+
+/// Shortest presence idle timeout in seconds; a PIR sensor goes low between
+/// pulses even with someone in the room
+const MIN_PRESENCE_IDLE_TIMEOUT: u64 = 10;
+
+/// Furthest burn-in protection may move the frame, in pixels
+const MAX_BURN_IN_SHIFT: u64 = 50;
+
+/// Shortest watchdog timeout in seconds, a few of the frame's 15 second heartbeats
+const MIN_WATCHDOG_TIMEOUT: u64 = 45;
+
+/// Shortest time between connectivity checks in seconds
+const MIN_CONNECTIVITY_INTERVAL: u64 = 5;
+
+/// Default settings with the allowed values of each enum-like field
+pub fn settings_defaults() -> SettingsDefaults {
+    let options = [
+        ("units.temperature_unit", TemperatureUnit::VALUES),
+        ("units.time_format", TimeFormat::VALUES),
+        ("units.date_format", DateFormat::VALUES),
+        ("units.wind_speed_unit", WindSpeedUnit::VALUES),
+        ("display.debug_position", DebugPosition::VALUES),
+        ("display.burn_in.refresh_mode", BurnInRefresh::VALUES),
+        ("location.mode", LocationMode::VALUES),
+        ("location.source", LOCATION_SOURCES),
+        ("photos.provider", PhotoProvider::VALUES),
+        ("photos.query.orientation", PhotoOrientation::VALUES),
+        ("weather.provider", WeatherProvider::VALUES),
+        ("server.bind", ServerBind::VALUES),
+        ("server.access_log", AccessLogLevel::VALUES),
+        ("sync.role", SyncRole::VALUES),
+        ("quotes.source", QuoteSource::VALUES),
+        ("stocks.provider", StockProvider::VALUES),
+        ("sports.provider", SportsProvider::VALUES),
+    ]
+    .into_iter()
+    .map(|(path, values)| (path.to_string(), values.iter().map(|v| v.to_string()).collect()))
+    .chain(std::iter::once((
+        "display.degradation_order".to_string(),
+        display_state::WIDGETS.iter().map(|(id, _)| id.to_string()).collect(),
+    )))
+    .collect();
+
+    SettingsDefaults {
+        defaults: Settings::default(),
+        options,
+    }
+}
+
+/// Check a full settings document, collecting every problem instead of
+/// stopping at the first one like deserialization does
+pub fn validate_settings_json(value: &serde_json::Value) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    check_enum::<TemperatureUnit>(value, "units.temperature_unit", TemperatureUnit::VALUES, &mut problems);
+    check_enum::<TimeFormat>(value, "units.time_format", TimeFormat::VALUES, &mut problems);
+    check_enum::<DateFormat>(value, "units.date_format", DateFormat::VALUES, &mut problems);
+    check_enum::<WindSpeedUnit>(value, "units.wind_speed_unit", WindSpeedUnit::VALUES, &mut problems);
+    check_enum::<DebugPosition>(value, "display.debug_position", DebugPosition::VALUES, &mut problems);
+    check_enum::<BurnInRefresh>(value, "display.burn_in.refresh_mode", BurnInRefresh::VALUES, &mut problems);
+    check_enum::<LocationMode>(value, "location.mode", LocationMode::VALUES, &mut problems);
+    check_enum::<PhotoProvider>(value, "photos.provider", PhotoProvider::VALUES, &mut problems);
+    check_enum::<PhotoOrientation>(value, "photos.query.orientation", PhotoOrientation::VALUES, &mut problems);
+    check_enum::<WeatherProvider>(value, "weather.provider", WeatherProvider::VALUES, &mut problems);
+    check_enum::<ServerBind>(value, "server.bind", ServerBind::VALUES, &mut problems);
+    check_enum::<AccessLogLevel>(value, "server.access_log", AccessLogLevel::VALUES, &mut problems);
+    check_enum::<SyncRole>(value, "sync.role", SyncRole::VALUES, &mut problems);
+    check_enum::<QuoteSource>(value, "quotes.source", QuoteSource::VALUES, &mut problems);
+    check_enum::<StockProvider>(value, "stocks.provider", StockProvider::VALUES, &mut problems);
+    check_enum::<SportsProvider>(value, "sports.provider", SportsProvider::VALUES, &mut problems);
+
+    let has_cert = value.pointer("/server/tls/cert_path").is_some_and(|p| !p.is_null());
+    let has_key = value.pointer("/server/tls/key_path").is_some_and(|p| !p.is_null());
+    if has_cert != has_key {
+        problems.push("server.tls: cert_path and key_path must be set together".to_string());
+    }
+
+    if let Some(origins) = value.pointer("/server/cors_origins").and_then(|o| o.as_array()) {
+        for origin in origins {
+            if !origin.as_str().is_some_and(is_cors_origin) {
+                problems.push(format!(
+                    "server.cors_origins: expected \"*\" or an origin like \"http://panel.local:3000\", got {}",
+                    origin
+                ));
+            }
+        }
+    }
+
+    for key in ["reads_per_minute", "writes_per_minute"] {
+        if let Some(limit) = value.pointer(&format!("/server/rate_limit/{}", key)) {
+            if limit.as_u64().filter(|limit| (1..=u32::MAX as u64).contains(limit)).is_none() {
+                problems.push(format!("server.rate_limit.{}: expected a positive number, got {}", key, limit));
+            }
+        }
+    }
+
+    if let Some(url) = value.pointer("/sync/lead_url").filter(|url| !url.is_null()) {
+        if !url.as_str().is_some_and(|url| url.starts_with("http://") || url.starts_with("https://")) {
+            problems.push(format!("sync.lead_url: expected a URL like \"http://192.168.1.20:8737\", got {}", url));
+        }
+    }
+
+    if let Some(feeds) = value.pointer("/calendar/feeds").and_then(|feeds| feeds.as_array()) {
+        for (i, feed) in feeds.iter().enumerate() {
+            let url = feed.get("url").and_then(|url| url.as_str()).unwrap_or_default();
+            if !["http://", "https://", "webcal://"].iter().any(|scheme| url.starts_with(scheme)) {
+                problems.push(format!("calendar.feeds[{}].url: expected an http(s) or webcal URL, got {:?}", i, url));
+            }
+        }
+    }
+
+    if let Some(calendars) = value.pointer("/calendar/caldav").and_then(|calendars| calendars.as_array()) {
+        for (i, calendar) in calendars.iter().enumerate() {
+            let url = calendar.get("url").and_then(|url| url.as_str()).unwrap_or_default();
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                problems.push(format!("calendar.caldav[{}].url: expected an http(s) URL, got {:?}", i, url));
+            }
+        }
+    }
+
+    if let Some(url) = value.pointer("/quotes/url").and_then(|url| url.as_str()) {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            problems.push(format!("quotes.url: expected an http(s) URL, got {:?}", url));
+        }
+    }
+
+    if let Some(feeds) = value.pointer("/news/feeds").and_then(|feeds| feeds.as_array()) {
+        for (i, feed) in feeds.iter().enumerate() {
+            let url = feed.get("url").and_then(|url| url.as_str()).unwrap_or_default();
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                problems.push(format!("news.feeds[{}].url: expected an http(s) URL, got {:?}", i, url));
+            }
+        }
+    }
+
+    if value.pointer("/stocks/provider").and_then(|p| p.as_str()) == Some("finnhub") {
+        let key = value.pointer("/stocks/api_keys/finnhub").and_then(|k| k.as_str()).unwrap_or_default();
+        if key.trim().is_empty() {
+            problems.push("stocks.api_keys.finnhub: required for the finnhub provider".to_string());
+        }
+    }
+
+    if let Some(currency) = value.pointer("/crypto/currency") {
+        if currency.as_str().filter(|currency| !currency.trim().is_empty()).is_none() {
+            problems.push(format!("crypto.currency: expected a currency like \"usd\", got {}", currency));
+        }
+    }
+
+    if let Some(interval) = value.pointer("/music/spotify/poll_interval") {
+        if interval.as_u64().filter(|interval| *interval > 0).is_none() {
+            problems.push(format!("music.spotify.poll_interval: expected a positive number of seconds, got {}", interval));
+        }
+    }
+
+    if let Some(uri) = value.pointer("/music/spotify/redirect_uri").filter(|uri| !uri.is_null()) {
+        if !uri.as_str().is_some_and(|uri| uri.starts_with("http://") || uri.starts_with("https://")) {
+            problems.push(format!("music.spotify.redirect_uri: expected an http(s) URL, got {}", uri));
+        }
+    }
+
+    if let Some(interval) = value.pointer("/music/lastfm/poll_interval") {
+        if interval.as_u64().filter(|interval| *interval > 0).is_none() {
+            problems.push(format!("music.lastfm.poll_interval: expected a positive number of seconds, got {}", interval));
+        }
+    }
+
+    if value.pointer("/music/lastfm/username").and_then(|u| u.as_str()).is_some_and(|u| !u.trim().is_empty()) {
+        let key = value.pointer("/music/lastfm/api_key").and_then(|k| k.as_str()).unwrap_or_default();
+        if key.trim().is_empty() {
+            problems.push("music.lastfm.api_key: required with music.lastfm.username".to_string());
+        }
+    }
+
+    if let Some(interval) = value.pointer("/music/media/poll_interval") {
+        if interval.as_u64().filter(|interval| *interval > 0).is_none() {
+            problems.push(format!("music.media.poll_interval: expected a positive number of seconds, got {}", interval));
+        }
+    }
+
+    if value.pointer("/sports/provider").and_then(|p| p.as_str()) == Some("football-data") {
+        let key = value.pointer("/sports/api_keys/football_data").and_then(|k| k.as_str()).unwrap_or_default();
+        if key.trim().is_empty() {
+            problems.push("sports.api_keys.football_data: required for the football-data provider".to_string());
+        }
+    }
+
+    if let Some(clocks) = value.pointer("/display/world_clocks").and_then(|clocks| clocks.as_array()) {
+        for (i, clock) in clocks.iter().enumerate() {
+            let timezone = clock.get("timezone").and_then(|timezone| timezone.as_str()).unwrap_or_default();
+            if timezone.parse::<chrono_tz::Tz>().is_err() {
+                problems.push(format!(
+                    "display.world_clocks[{}].timezone: expected an IANA timezone like \"America/Toronto\", got {:?}",
+                    i, timezone
+                ));
+            }
+        }
+    }
+
+    if let Some(alarms) = value.pointer("/alarms/list").and_then(|list| list.as_array()) {
+        let mut ids = Vec::new();
+        for (i, alarm) in alarms.iter().enumerate() {
+            let time = alarm.get("time").and_then(|time| time.as_str()).unwrap_or_default();
+            if chrono::NaiveTime::parse_from_str(time, "%H:%M").is_err() {
+                problems.push(format!("alarms.list[{}].time: expected HH:MM, got {:?}", i, time));
+            }
+            let id = alarm.get("id").and_then(|id| id.as_u64()).unwrap_or_default();
+            if id > 0 && ids.contains(&id) {
+                problems.push(format!("alarms.list[{}].id: {} is used by another alarm", i, id));
+            }
+            ids.push(id);
+        }
+    }
+
+    if let Some(server) = value.pointer("/clock/ntp_server") {
+        if !server.as_str().is_some_and(|server| !server.trim().is_empty()) {
+            problems.push(format!("clock.ntp_server: expected a host name or address, got {}", server));
+        }
+    }
+
+    if let Some(events) = value.pointer("/countdowns/events").and_then(|events| events.as_array()) {
+        for (i, event) in events.iter().enumerate() {
+            let date = event.get("date").and_then(|date| date.as_str()).unwrap_or_default();
+            if chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+                problems.push(format!("countdowns.events[{}].date: expected a YYYY-MM-DD date, got {:?}", i, date));
+            }
+        }
+    }
+
+    if let Some(webhooks) = value.pointer("/webhooks/list").and_then(|list| list.as_array()) {
+        for (i, webhook) in webhooks.iter().enumerate() {
+            let url = webhook.get("url").and_then(|url| url.as_str()).unwrap_or_default();
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                problems.push(format!("webhooks.list[{}].url: expected an http(s) URL, got {:?}", i, url));
+            }
+            for event in webhook.get("events").and_then(|events| events.as_array()).into_iter().flatten() {
+                if !event.as_str().is_some_and(|event| webhooks::EVENTS.contains(&event)) {
+                    problems.push(format!(
+                        "webhooks.list[{}].events: expected any of {}, got {}",
+                        i,
+                        webhooks::EVENTS.join(", "),
+                        event
+                    ));
+                }
+            }
+        }
+    }
+
+    if let Some(port) = value.pointer("/server/port") {
+        if port.as_u64().filter(|port| (1..=65535).contains(port)).is_none() {
+            problems.push(format!("server.port: expected a port between 1 and 65535, got {}", port));
+        }
+    }
+
+    if let Some(language) = value.get("language") {
+        if !language.as_str().is_some_and(is_language_tag) {
+            problems.push(format!("language: expected a BCP-47 tag like \"en\" or \"ro-RO\", got {}", language));
+        }
+    }
+
+    for section in REFRESH_INTERVAL_SECTIONS {
+        check_positive_minutes(value, section, &mut problems);
+    }
+
+    if value.pointer("/weather/provider").and_then(|p| p.as_str()) == Some("openweathermap") {
+        let key = value.pointer("/weather/api_keys/openweathermap").and_then(|k| k.as_str()).unwrap_or_default();
+        if key.trim().is_empty() {
+            problems.push("weather.api_keys.openweathermap: required for the openweathermap provider".to_string());
+        }
+    }
+
+    if value.pointer("/photos/provider").and_then(|p| p.as_str()) == Some("pexels") {
+        let key = value.pointer("/photos/api_keys/pexels").and_then(|k| k.as_str()).unwrap_or_default();
+        if key.trim().is_empty() {
+            problems.push("photos.api_keys.pexels: required for the pexels provider".to_string());
+        }
+    }
+
+    if let Some(quality) = value.pointer("/photos/photo_quality") {
+        check_quality("photos.photo_quality", quality, &mut problems);
+    }
+
+    if let Some(schedule) = value.pointer("/photos/quality_schedule").and_then(|s| s.as_array()) {
+        for (i, window) in schedule.iter().enumerate() {
+            check_window_times(&format!("photos.quality_schedule[{}]", i), window, &mut problems);
+            if let Some(quality) = window.get("quality") {
+                check_quality(&format!("photos.quality_schedule[{}].quality", i), quality, &mut problems);
+            }
+        }
+    }
+
+    if let Some(night) = value.pointer("/display/night_mode") {
+        check_times_if_set("display.night_mode", night, &mut problems);
+        if let Some(brightness) = night.get("brightness") {
+            if !brightness.as_u64().is_some_and(|percent| percent <= 100) {
+                problems.push(format!("display.night_mode.brightness: expected 0-100, got {}", brightness));
+            }
+        }
+        if let Some(factor) = night.get("photo_interval_factor") {
+            if !factor.as_u64().is_some_and(|factor| factor >= 1) {
+                problems.push(format!("display.night_mode.photo_interval_factor: expected 1 or more, got {}", factor));
+            }
+        }
+    }
+
+    if let Some(sleep) = value.pointer("/display/sleep") {
+        check_times_if_set("display.sleep", sleep, &mut problems);
+    }
+
+    if let Some(burn_in) = value.pointer("/display/burn_in") {
+        if let Some(interval) = burn_in.get("shift_interval") {
+            if !interval.as_u64().is_some_and(|seconds| seconds >= 10) {
+                problems.push(format!("display.burn_in.shift_interval: expected at least 10 seconds, got {}", interval));
+            }
+        }
+        if let Some(shift) = burn_in.get("max_shift") {
+            if !shift.as_u64().is_some_and(|pixels| pixels <= MAX_BURN_IN_SHIFT) {
+                problems.push(format!("display.burn_in.max_shift: expected 0-{} pixels, got {}", MAX_BURN_IN_SHIFT, shift));
+            }
+        }
+        if let Some(duration) = burn_in.get("refresh_duration") {
+            if !duration.as_u64().is_some_and(|seconds| (1..=300).contains(&seconds)) {
+                problems.push(format!("display.burn_in.refresh_duration: expected 1-300 seconds, got {}", duration));
+            }
+        }
+    }
+
+    if let Some(warning) = value.pointer("/display/low_battery_warning") {
+        if !warning.as_u64().is_some_and(|percent| percent <= 100) {
+            problems.push(format!("display.low_battery_warning: expected 0-100, got {}", warning));
+        }
+    }
+
+    if let Some(timeout) = value.pointer("/display/presence/idle_timeout") {
+        if !timeout.as_u64().is_some_and(|seconds| seconds >= MIN_PRESENCE_IDLE_TIMEOUT) {
+            problems.push(format!(
+                "display.presence.idle_timeout: expected at least {} seconds, got {}",
+                MIN_PRESENCE_IDLE_TIMEOUT, timeout
+            ));
+        }
+    }
+
+    if let Some(order) = value.pointer("/display/degradation_order").and_then(|o| o.as_array()) {
+        let known: Vec<&str> = display_state::WIDGETS.iter().map(|(id, _)| *id).collect();
+        for id in order {
+            if !id.as_str().is_some_and(|id| known.contains(&id)) {
+                problems.push(format!("display.degradation_order: unknown widget {}, expected one of {}", id, known.join(", ")));
+            }
+        }
+    }
+
+    if let Some(timeout) = value.pointer("/watchdog/timeout") {
+        if !timeout.as_u64().is_some_and(|seconds| seconds >= MIN_WATCHDOG_TIMEOUT) {
+            problems.push(format!("watchdog.timeout: expected at least {} seconds, got {}", MIN_WATCHDOG_TIMEOUT, timeout));
+        }
+    }
+
+    if let Some(url) = value.pointer("/connectivity/check_url") {
+        if !url.as_str().is_some_and(|url| url.starts_with("http://") || url.starts_with("https://")) {
+            problems.push(format!("connectivity.check_url: expected an http:// or https:// URL, got {}", url));
+        }
+    }
+
+    if let Some(interval) = value.pointer("/connectivity/interval") {
+        if !interval.as_u64().is_some_and(|seconds| seconds >= MIN_CONNECTIVITY_INTERVAL) {
+            problems.push(format!(
+                "connectivity.interval: expected at least {} seconds, got {}",
+                MIN_CONNECTIVITY_INTERVAL, interval
+            ));
+        }
+    }
+
+    if let Some(overrides) = value.pointer("/monitors/overrides").and_then(|o| o.as_array()) {
+        for (i, o) in overrides.iter().enumerate() {
+            if !o.get("monitor").and_then(|m| m.as_str()).is_some_and(|m| !m.trim().is_empty()) {
+                problems.push(format!("monitors.overrides[{}].monitor: expected a monitor name", i));
+            }
+            if let Some(settings) = o.get("settings").filter(|s| s.is_object()) {
+                for problem in validate_settings_json(settings) {
+                    problems.push(format!("monitors.overrides[{}].settings.{}", i, problem));
+                }
+            }
+        }
+    }
+
+    if let Some(schedule) = value.pointer("/profiles/schedule").and_then(|s| s.as_array()) {
+        let names: Vec<&str> = value
+            .pointer("/profiles/list")
+            .and_then(|l| l.as_array())
+            .map(|list| list.iter().filter_map(|p| p.get("name")?.as_str()).collect())
+            .unwrap_or_default();
+        for (i, window) in schedule.iter().enumerate() {
+            check_window_times(&format!("profiles.schedule[{}]", i), window, &mut problems);
+            let profile = window.get("profile").and_then(|p| p.as_str()).unwrap_or_default();
+            if !names.contains(&profile) {
+                problems.push(format!("profiles.schedule[{}].profile: unknown profile {:?}", i, profile));
+            }
+        }
+    }
+
+    for (key, limit) in [("latitude", 90.0), ("longitude", 180.0)] {
+        match value.pointer(&format!("/location/{}", key)) {
+            None | Some(serde_json::Value::Null) => {
+                if value.pointer("/location/mode").and_then(|m| m.as_str()) == Some("manual") {
+                    problems.push(format!("location.{}: required in manual mode", key));
+                }
+            }
+            Some(coordinate) => {
+                if !coordinate.as_f64().is_some_and(|c| (-limit..=limit).contains(&c)) {
+                    problems.push(format!("location.{}: expected a number between -{} and {}, got {}", key, limit, limit, coordinate));
+                }
+            }
+        }
+    }
+
+    if let Some(source) = value.pointer("/location/source") {
+        if !source.as_str().is_some_and(|s| LOCATION_SOURCES.contains(&s)) {
+            problems.push(format!("location.source: expected one of {}, got {}", LOCATION_SOURCES.join(", "), source));
+        }
+    }
+
+    // Anything else (missing sections, wrong types) is reported by serde
+    if problems.is_empty() {
+        if let Err(e) = serde_json::from_value::<Settings>(value.clone()) {
+            problems.push(e.to_string());
+        }
+    }
+
+    problems
+}
+
+fn check_enum<T: DeserializeOwned>(value: &serde_json::Value, path: &str, allowed: &[&str], problems: &mut Vec<String>) {
+    let pointer = format!("/{}", path.replace('.', "/"));
+    if let Some(field) = value.pointer(&pointer) {
+        if serde_json::from_value::<T>(field.clone()).is_err() {
+            problems.push(format!("{}: expected one of {}, got {}", path, allowed.join(", "), field));
+        }
+    }
+}
+
+/// Sections with a `refresh_interval` in minutes
+const REFRESH_INTERVAL_SECTIONS: &[&str] = &["calendar", "news", "stocks", "crypto", "sports", "tasks", "photos", "weather"];
+
+fn check_positive_minutes(value: &serde_json::Value, section: &str, problems: &mut Vec<String>) {
+    if let Some(interval) = value.pointer(&format!("/{}/refresh_interval", section)) {
+        if interval.as_u64().filter(|minutes| *minutes > 0).is_none() {
+            problems.push(format!("{}.refresh_interval: expected a positive number of minutes, got {}", section, interval));
+        }
+    }
+}
+
+/// Loose BCP-47 shape check: a 2-3 letter language, then alphanumeric subtags
+fn is_language_tag(tag: &str) -> bool {
+    let mut subtags = tag.split(['-', '_']);
+    let language = subtags.next().unwrap_or_default();
+    (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && subtags.all(|subtag| (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+/// "*" or a scheme://host[:port] origin, as browsers send it
+fn is_cors_origin(origin: &str) -> bool {
+    origin == "*"
+        || reqwest::Url::parse(origin).is_ok_and(|url| {
+            matches!(url.scheme(), "http" | "https") && url.origin().ascii_serialization() == origin.trim_end_matches('/')
+        })
+}
+
+/// Schedule windows need "HH:MM" start and end times
+fn check_window_times(path: &str, window: &serde_json::Value, problems: &mut Vec<String>) {
+    for key in ["start", "end"] {
+        let time = window.get(key).and_then(|t| t.as_str()).unwrap_or_default();
+        if chrono::NaiveTime::parse_from_str(time, "%H:%M").is_err() {
+            problems.push(format!("{}.{}: expected HH:MM, got {:?}", path, key, time));
+        }
+    }
+}
+
+/// Like `check_window_times`, for windows whose times have defaults
+fn check_times_if_set(path: &str, window: &serde_json::Value, problems: &mut Vec<String>) {
+    for key in ["start", "end"] {
+        if let Some(time) = window.get(key) {
+            if !time.as_str().is_some_and(|time| chrono::NaiveTime::parse_from_str(time, "%H:%M").is_ok()) {
+                problems.push(format!("{}.{}: expected HH:MM, got {}", path, key, time));
+            }
+        }
+    }
+}
+
+/// Qualities are 1-100 (as a number or numeric string) or a legacy name like "high"
+fn check_quality(path: &str, quality: &serde_json::Value, problems: &mut Vec<String>) {
+    let valid = match quality {
+        serde_json::Value::Number(n) => n.as_u64().is_some_and(|q| (1..=100).contains(&q)),
+        serde_json::Value::String(s) => {
+            LEGACY_QUALITIES.contains(&s.as_str()) || s.parse::<u64>().is_ok_and(|q| (1..=100).contains(&q))
+        }
+        _ => false,
+    };
+    if !valid {
+        problems.push(format!("{}: expected 1-100 or one of {}, got {}", path, LEGACY_QUALITIES.join(", "), quality));
+    }
+}
+
+/// Wrap settings in a backup with metadata
+pub fn create_backup(settings: &Settings) -> SettingsBackup {
+    let created_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default();
+
+    SettingsBackup {
+        format: BACKUP_FORMAT.to_string(),
+        version: BACKUP_VERSION,
+        created_at,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        settings: settings.clone(),
+    }
+}
+
+/// Bring an older backup up to the current layout. Version 0 is a bare
+/// settings.json copied off a frame, from before backups had a wrapper.
+pub fn migrate_backup(backup: &serde_json::Value) -> serde_json::Value {
+    let mut backup = if backup.get("format").is_none() && backup.get("units").is_some() {
+        serde_json::json!({
+            "format": BACKUP_FORMAT,
+            "version": 0,
+            "created_at": 0,
+            "app_version": "unknown",
+            "settings": backup,
+        })
+    } else {
+        backup.clone()
+    };
+
+    // 0 -> 1: legacy quality names are stored as numbers
+    if backup.get("version").and_then(|v| v.as_u64()) == Some(0) {
+        if let Some(quality) = backup.pointer_mut("/settings/photos/photo_quality") {
+            let legacy = match quality.as_str() {
+                Some("low") => Some("65"),
+                Some("medium") => Some("80"),
+                Some("high") | Some("maximum") => Some("100"),
+                _ => None,
+            };
+            if let Some(numeric) = legacy {
+                *quality = numeric.into();
+            }
+        }
+        backup["version"] = 1.into();
+    }
+
+    backup
+}
+
+/// Check an uploaded backup and return the settings it holds.
+/// Older backups are migrated and sections they lack are filled in with defaults.
+pub fn settings_from_backup(backup: &serde_json::Value) -> Result<Settings, Vec<String>> {
+    let backup = &migrate_backup(backup);
+    let mut problems = Vec::new();
+
+    match backup.get("format").and_then(|f| f.as_str()) {
+        Some(BACKUP_FORMAT) => {}
+        other => problems.push(format!("format: expected {:?}, got {:?}", BACKUP_FORMAT, other)),
+    }
+    match backup.get("version").and_then(|v| v.as_u64()) {
+        Some(version) if version >= 1 && version <= BACKUP_VERSION as u64 => {}
+        Some(version) => problems.push(format!(
+            "version: backup version {} is not supported (expected 1-{})",
+            version, BACKUP_VERSION
+        )),
+        None => problems.push("version: missing".to_string()),
+    }
+    let Some(saved) = backup.get("settings").filter(|s| s.is_object()) else {
+        problems.push("settings: missing".to_string());
+        return Err(problems);
+    };
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+
+    let mut merged = serde_json::to_value(Settings::default()).map_err(|e| vec![e.to_string()])?;
+    merge_json(&mut merged, saved.clone());
+
+    let problems = validate_settings_json(&merged);
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+    serde_json::from_value(merged).map_err(|e| vec![e.to_string()])
+}
+
+/// Write a settings bundle to a file, e.g. on a USB stick
+pub fn export_settings_to(path: &Path, settings: &Settings) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(&create_backup(settings))
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    write_file_atomic(path, json.as_bytes())
+}
+
+/// Read, migrate and validate a settings bundle (or a bare settings.json)
+pub fn import_settings_from(path: &Path) -> Result<Settings, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let bundle: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    settings_from_backup(&bundle)
+        .map_err(|problems| format!("Invalid settings file: {}", problems.join("; ")))
+}
+
+/// `settings` with partial settings merged over them, e.g. a monitor's override
+pub fn with_overrides(settings: &Settings, overrides: &serde_json::Value) -> Result<Settings, String> {
+    let mut merged = serde_json::to_value(settings).map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    merge_json(&mut merged, overrides.clone());
+    serde_json::from_value(merged).map_err(|e| format!("Failed to apply overrides: {}", e))
+}
+
+/// Settings keys holding credentials. Everything at or under them is left
+/// out of what the API returns and the events it sends.
+pub const SECRET_KEYS: &[&str] = &["password", "api_keys", "api_key", "secret", "client_secret", "todoist_token"];
+
+/// Settings holding credentials under a key that isn't one elsewhere, by
+/// their dotted path. A private iCal feed's address works as its password.
+pub const SECRET_FIELDS: &[&str] = &["calendar.feeds.url"];
+
+/// Shown instead of a credential that is set. Writing it back keeps the
+/// stored credential, so a client can save settings it read.
+pub const REDACTED: &str = "***";
+
+/// Fields that tell list entries apart, to match an entry with its stored copy
+const ENTRY_KEYS: &[&str] = &["id", "name"];
+
+/// Whether the setting at a dotted path, e.g. "mqtt.password", is a credential or under one
+pub fn is_secret(path: &str) -> bool {
+    SECRET_FIELDS.contains(&path) || path.split('.').any(|name| SECRET_KEYS.contains(&name))
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) }
+}
+
+/// Replace every credential in settings, or in the part of them at `path`
+/// (e.g. "calendar", or "" for all of them), with `REDACTED`. Unset ones stay
+/// null or empty, so clients can still tell which are missing.
+pub fn redact_secrets(value: &mut serde_json::Value, path: &str) {
+    match value {
+        serde_json::Value::Object(fields) => {
+            for (key, child) in fields.iter_mut() {
+                let path = child_path(path, key);
+                if is_secret(&path) {
+                    mask_secret(child);
+                } else {
+                    redact_secrets(child, &path);
+                }
+            }
+        }
+        serde_json::Value::Array(entries) => entries.iter_mut().for_each(|entry| redact_secrets(entry, path)),
+        _ => {}
+    }
+}
+
+fn mask_secret(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(fields) => fields.values_mut().for_each(mask_secret),
+        serde_json::Value::Array(entries) => entries.iter_mut().for_each(mask_secret),
+        serde_json::Value::Null => {}
+        serde_json::Value::String(secret) if secret.is_empty() => {}
+        _ => *value = REDACTED.into(),
+    }
+}
+
+/// Settings as the API shows them, without credentials
+pub fn redacted(settings: &Settings) -> Result<Settings, String> {
+    let mut value = serde_json::to_value(settings).map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    redact_secrets(&mut value, "");
+    serde_json::from_value(value).map_err(|e| format!("Failed to parse redacted settings: {}", e))
+}
+
+/// Put the stored credentials from `current` back wherever `value`, the
+/// settings or the part of them at `path`, has `REDACTED`
+pub fn restore_secrets(value: &mut serde_json::Value, current: &serde_json::Value, path: &str) {
+    match value {
+        serde_json::Value::Object(fields) => {
+            for (key, child) in fields.iter_mut() {
+                let stored = current.get(key).unwrap_or(&serde_json::Value::Null);
+                let path = child_path(path, key);
+                if SECRET_FIELDS.contains(&path.as_str()) && stored.is_null() {
+                    // A new entry, e.g. a feed the lead has and a follower doesn't, gets no address
+                    unmask_secret(child, &serde_json::Value::String(String::new()));
+                } else if is_secret(&path) {
+                    unmask_secret(child, stored);
+                } else {
+                    restore_secrets(child, stored, &path);
+                }
+            }
+        }
+        serde_json::Value::Array(entries) => {
+            for (index, entry) in entries.iter_mut().enumerate() {
+                let stored = stored_entry(entry, current, index);
+                restore_secrets(entry, stored, path);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn unmask_secret(value: &mut serde_json::Value, stored: &serde_json::Value) {
+    match value {
+        serde_json::Value::Object(fields) => {
+            for (key, child) in fields.iter_mut() {
+                unmask_secret(child, stored.get(key).unwrap_or(&serde_json::Value::Null));
+            }
+        }
+        serde_json::Value::String(secret) if secret == REDACTED => *value = stored.clone(),
+        _ => {}
+    }
+}
+
+/// The stored copy of a list entry: the one with the same id or name, else
+/// for entries with neither (webhooks) the one with the same URL, else the
+/// one at the same position
+fn stored_entry<'a>(entry: &serde_json::Value, current: &'a serde_json::Value, index: usize) -> &'a serde_json::Value {
+    let stored = current.as_array().map(Vec::as_slice).unwrap_or_default();
+    let same = |key: &str, id: &serde_json::Value| stored.iter().find(|stored| stored.get(key) == Some(id));
+    match ENTRY_KEYS.iter().find_map(|key| entry.get(key).filter(|id| !id.is_null()).map(|id| (key, id))) {
+        Some((key, id)) => same(key, id),
+        None => match entry.get("url").filter(|url| url.as_str() != Some(REDACTED)) {
+            Some(url) => same("url", url),
+            None => stored.get(index),
+        },
+    }
+    .unwrap_or(&serde_json::Value::Null)
+}
+
+/// `settings` with the stored credentials kept wherever they hold `REDACTED`
+fn with_secrets_of(settings: Settings, current: &Settings) -> Result<Settings, String> {
+    let mut value = serde_json::to_value(settings).map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    let current = serde_json::to_value(current).map_err(|e| format!("Failed to serialize current settings: {}", e))?;
+    restore_secrets(&mut value, &current, "");
+    serde_json::from_value(value).map_err(|e| format!("Failed to parse updated settings: {}", e))
+}
+
 /// Merge JSON values recursively
 fn merge_json(target: &mut serde_json::Value, source: serde_json::Value) {
     if let (Some(target_obj), Some(source_obj)) = (target.as_object_mut(), source.as_object()) {
@@ -286,7 +1161,7 @@ mod tests {
     #[test]
     fn test_default_settings() {
         let settings = Settings::default();
-        assert_eq!(settings.units.temperature_unit, "celsius");
+        assert_eq!(settings.units.temperature_unit, TemperatureUnit::Celsius);
         assert_eq!(settings.photos.refresh_interval, 30);
     }
 
@@ -314,4 +1189,61 @@ mod tests {
         assert_eq!(target["b"]["d"], 3);
         assert_eq!(target["e"], 10);
     }
+
+    #[test]
+    fn test_settings_recovered_from_backup() {
+        let dir = std::env::temp_dir().join(format!("idleview-settings-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("settings.json");
+
+        let mut settings = Settings::default();
+        save_settings_file(&path, &settings).unwrap();
+        settings.photos.refresh_interval = 45;
+        save_settings_file(&path, &settings).unwrap();
+        assert!(!dir.join("settings.json.tmp").exists());
+
+        // Simulate a truncated write: the backup holds the previous good settings
+        fs::write(&path, "{\"units\": {").unwrap();
+        let recovered = load_settings_file(&path).unwrap();
+        assert_eq!(recovered.photos.refresh_interval, 30);
+        assert!(serde_json::from_str::<Settings>(&fs::read_to_string(&path).unwrap()).is_ok());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reload_ignores_unchanged_and_invalid_files() {
+        let current = Settings::default();
+        let same = serde_json::to_string_pretty(&current).unwrap();
+        assert!(reloaded_settings(&same, &current).unwrap().is_none());
+
+        let mut edited = serde_json::to_value(&current).unwrap();
+        edited["units"]["temperature_unit"] = "fahrenheit".into();
+        let reloaded = reloaded_settings(&edited.to_string(), &current).unwrap().unwrap();
+        assert_eq!(reloaded.units.temperature_unit, TemperatureUnit::Fahrenheit);
+
+        assert!(reloaded_settings("{\"units\": {", &current).is_err());
+    }
+    #[test]
+    fn test_unknown_enum_values_load_as_defaults() {
+        let dir = std::env::temp_dir().join(format!("idleview-settings-lenient-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("settings.json");
+
+        let mut settings = serde_json::to_value(Settings::default()).unwrap();
+        settings["units"]["temperature_unit"] = "farenheit".into();
+        settings["photos"]["refresh_interval"] = 45.into();
+        fs::write(&path, settings.to_string()).unwrap();
+
+        let loaded = load_settings_file(&path).unwrap();
+        assert_eq!(loaded.units.temperature_unit, TemperatureUnit::Celsius);
+        assert_eq!(loaded.photos.refresh_interval, 45);
+
+        // Anything else that doesn't fit is still an error
+        settings["photos"]["refresh_interval"] = "often".into();
+        fs::write(&path, settings.to_string()).unwrap();
+        assert!(load_settings_file(&path).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
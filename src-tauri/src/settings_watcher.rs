@@ -0,0 +1,69 @@
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use idleview_api::ServerEvent;
+
+use crate::http_server::AppState;
+use crate::settings_manager;
+
+/// Editors and sync tools often write in several steps, so wait for the file to settle
+const SETTLE_DELAY: Duration = Duration::from_millis(300);
+
+/// Watch settings.json and reload it when something other than us changes it.
+/// The directory is watched because atomic saves replace the file rather than modify it.
+pub fn spawn_watcher(state: AppState) {
+    let settings_path = match settings_manager::ensure_settings_dir().and_then(|_| settings_manager::get_settings_path()) {
+        Ok(path) => path,
+        Err(e) => {
+            warn!("Settings watcher: {}", e);
+            return;
+        }
+    };
+    let (Some(dir), Some(file_name)) = (settings_path.parent(), settings_path.file_name()) else {
+        return;
+    };
+    let file_name = file_name.to_os_string();
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+        let Ok(event) = result else {
+            return;
+        };
+        let touches_settings = event.paths.iter().any(|path| path.file_name() == Some(file_name.as_os_str()));
+        if touches_settings && matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            let _ = tx.send(());
+        }
+    });
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            warn!("Settings watcher: failed to create watcher: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+        warn!("Settings watcher: failed to watch {}: {}", dir.display(), e);
+        return;
+    }
+    info!("Watching {} for external edits", settings_path.display());
+
+    tokio::spawn(async move {
+        // Dropping the watcher stops it, so it lives as long as this task
+        let _watcher = watcher;
+        while rx.recv().await.is_some() {
+            tokio::time::sleep(SETTLE_DELAY).await;
+            while rx.try_recv().is_ok() {}
+
+            match state.settings_manager.reload() {
+                Ok(Some(settings)) => {
+                    info!("Settings reloaded after an external edit");
+                    state.publish_from(ServerEvent::SettingsUpdated(Box::new(settings)), "file");
+                }
+                Ok(None) => {}
+                Err(e) => warn!("Settings watcher: keeping current settings: {}", e),
+            }
+        }
+    });
+}
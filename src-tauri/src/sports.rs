@@ -0,0 +1,409 @@
+//! Fixtures and scores of followed teams and leagues, from TheSportsDB (any
+//! sport, free key) or football-data.org (football, free key needed).
+//!
+//! Like stocks, the provider is asked at most once per `refresh_interval`
+//! whoever asks, except while a match is on or due to kick off, when the
+//! scores are refreshed every minute. The refresher keeps the cache warm so
+//! the photo query knows about match days without waiting on the network.
+
+use chrono::{DateTime, Local, NaiveDateTime};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+pub use idleview_api::sports::{MatchStatus, SportsMatch, SportsReport};
+
+use crate::display_sleep;
+use crate::health;
+use crate::http_server::AppState;
+use crate::settings_manager::{SportsProvider, SportsSettings};
+
+pub const THESPORTSDB_URL: &str = "https://www.thesportsdb.com/api/v1/json";
+pub const FOOTBALL_DATA_URL: &str = "https://api.football-data.org/v4";
+
+/// TheSportsDB's key for anyone, used when none is set
+const THESPORTSDB_FREE_KEY: &str = "123";
+
+/// Shortest time between two rounds of requests, and the interval while a
+/// match is on
+const MIN_INTERVAL: Duration = Duration::from_secs(60);
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Days of past and coming fixtures asked of football-data.org
+const FOOTBALL_DATA_DAYS: i64 = 14;
+
+struct Cached {
+    at: Instant,
+    settings: SportsSettings,
+    report: SportsReport,
+}
+
+/// Held while fetching, so callers arriving meanwhile wait for that round
+static CACHE: Mutex<Option<Cached>> = Mutex::const_new(None);
+
+/// A team or a league being followed
+#[derive(Clone, Copy)]
+enum Followed<'a> {
+    Team(&'a str),
+    League(&'a str),
+}
+
+fn text(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
+/// A score, which TheSportsDB gives as a string
+fn score(value: Option<&Value>) -> Option<u32> {
+    let value = value?;
+    value
+        .as_u64()
+        .map(|score| score as u32)
+        .or_else(|| value.as_str()?.trim().parse().ok())
+}
+
+/// Milliseconds since the epoch of an RFC 3339 time, or of a UTC one without
+/// an offset as TheSportsDB writes them
+fn timestamp(time: &str) -> Option<u64> {
+    let millis = match DateTime::parse_from_rfc3339(time) {
+        Ok(time) => time.timestamp_millis(),
+        Err(_) => NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M:%S")
+            .or_else(|_| NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S"))
+            .ok()?
+            .and_utc()
+            .timestamp_millis(),
+    };
+    u64::try_from(millis).ok()
+}
+
+fn thesportsdb_status(status: Option<&str>, has_score: bool) -> MatchStatus {
+    match status.map(str::trim).unwrap_or_default() {
+        // Past events often come without a status
+        "" => {
+            if has_score {
+                MatchStatus::Finished
+            } else {
+                MatchStatus::Scheduled
+            }
+        }
+        "NS" | "Not Started" | "TBD" | "Time to be defined" => MatchStatus::Scheduled,
+        "FT" | "AET" | "PEN" | "AOT" | "Match Finished" | "Finished" => MatchStatus::Finished,
+        "PST" | "Postponed" | "CANC" | "Cancelled" | "ABD" | "Abandoned" | "SUSP" | "INT" => MatchStatus::Postponed,
+        // Periods and breaks: 1H, HT, 2H, ET, P, Q1, OT, LIVE...
+        _ => MatchStatus::Live,
+    }
+}
+
+/// Matches in a TheSportsDB answer, under "events" or, for a team's past
+/// ones, "results". No matches come as null.
+pub fn parse_thesportsdb(json: &str) -> Result<Vec<SportsMatch>, String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| format!("Failed to parse fixtures: {}", e))?;
+    let events = value
+        .get("events")
+        .or_else(|| value.get("results"))
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    Ok(events
+        .iter()
+        .filter_map(|event| {
+            let home_score = score(event.get("intHomeScore"));
+            let away_score = score(event.get("intAwayScore"));
+            let starts_at = event
+                .get("strTimestamp")
+                .and_then(Value::as_str)
+                .and_then(timestamp)
+                .or_else(|| {
+                    let date = text(event, "dateEvent")?;
+                    let time = text(event, "strTime").unwrap_or_else(|| "00:00:00".to_string());
+                    timestamp(&format!("{}T{}", date, time))
+                });
+            Some(SportsMatch {
+                id: text(event, "idEvent")?,
+                league: text(event, "strLeague"),
+                home_team: text(event, "strHomeTeam")?,
+                away_team: text(event, "strAwayTeam")?,
+                home_score,
+                away_score,
+                starts_at,
+                status: thesportsdb_status(event.get("strStatus").and_then(Value::as_str), home_score.is_some()),
+            })
+        })
+        .collect())
+}
+
+/// Matches in a football-data.org answer
+pub fn parse_football_data(json: &str) -> Result<Vec<SportsMatch>, String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| format!("Failed to parse fixtures: {}", e))?;
+    let Some(matches) = value.get("matches").and_then(Value::as_array) else {
+        let message = text(&value, "message").unwrap_or_else(|| "no matches in the answer".to_string());
+        return Err(format!("football-data.org refused: {}", message));
+    };
+    let team = |side: &Value| text(side, "shortName").or_else(|| text(side, "name"));
+    Ok(matches
+        .iter()
+        .filter_map(|fixture| {
+            // The full-time score is the current one while the match is on
+            let full_time = fixture.pointer("/score/fullTime");
+            Some(SportsMatch {
+                id: fixture.get("id")?.to_string(),
+                league: fixture.get("competition").and_then(|competition| text(competition, "name")),
+                home_team: team(fixture.get("homeTeam")?)?,
+                away_team: team(fixture.get("awayTeam")?)?,
+                home_score: score(full_time.and_then(|score| score.get("home"))),
+                away_score: score(full_time.and_then(|score| score.get("away"))),
+                starts_at: fixture.get("utcDate").and_then(Value::as_str).and_then(timestamp),
+                status: match fixture.get("status").and_then(Value::as_str).unwrap_or_default() {
+                    "IN_PLAY" | "PAUSED" | "LIVE" => MatchStatus::Live,
+                    "FINISHED" | "AWARDED" => MatchStatus::Finished,
+                    "POSTPONED" | "SUSPENDED" | "CANCELLED" => MatchStatus::Postponed,
+                    _ => MatchStatus::Scheduled,
+                },
+            })
+        })
+        .collect())
+}
+
+/// Sort matches into live ones, upcoming fixtures and results, keeping
+/// `max_matches` of the last two. The same match followed twice (a team and
+/// its league) is listed once; postponed ones aren't listed.
+pub fn arrange(matches: Vec<SportsMatch>, max_matches: usize) -> SportsReport {
+    let mut seen = HashSet::new();
+    let mut live = Vec::new();
+    let mut upcoming = Vec::new();
+    let mut results = Vec::new();
+    for fixture in matches {
+        if !seen.insert(fixture.id.clone()) {
+            continue;
+        }
+        match fixture.status {
+            MatchStatus::Live => live.push(fixture),
+            MatchStatus::Scheduled => upcoming.push(fixture),
+            MatchStatus::Finished => results.push(fixture),
+            MatchStatus::Postponed => {}
+        }
+    }
+    live.sort_by_key(|fixture| fixture.starts_at);
+    // Fixtures without a date yet go last
+    upcoming.sort_by_key(|fixture| fixture.starts_at.unwrap_or(u64::MAX));
+    results.sort_by_key(|fixture| std::cmp::Reverse(fixture.starts_at));
+    upcoming.truncate(max_matches);
+    results.truncate(max_matches);
+    SportsReport {
+        live,
+        upcoming,
+        results,
+        ..SportsReport::default()
+    }
+}
+
+/// The requests that give a team's or a league's past and coming matches
+fn requests(
+    client: &reqwest::Client,
+    settings: &SportsSettings,
+    base_url: &str,
+    followed: Followed,
+) -> Vec<reqwest::RequestBuilder> {
+    match settings.provider {
+        SportsProvider::TheSportsDb => {
+            let key = settings.api_keys.thesportsdb.as_deref().unwrap_or(THESPORTSDB_FREE_KEY);
+            let (id, endpoints) = match followed {
+                Followed::Team(id) => (id, ["eventsnext.php", "eventslast.php"]),
+                Followed::League(id) => (id, ["eventsnextleague.php", "eventspastleague.php"]),
+            };
+            endpoints
+                .iter()
+                .map(|endpoint| client.get(format!("{}/{}/{}", base_url, key, endpoint)).query(&[("id", id)]))
+                .collect()
+        }
+        SportsProvider::FootballData => {
+            let key = settings.api_keys.football_data.as_deref().unwrap_or_default();
+            let url = match followed {
+                Followed::Team(id) => format!("{}/teams/{}/matches", base_url, urlencoding::encode(id)),
+                Followed::League(code) => format!("{}/competitions/{}/matches", base_url, urlencoding::encode(code)),
+            };
+            let today = chrono::Utc::now().date_naive();
+            let days = chrono::Days::new(FOOTBALL_DATA_DAYS as u64);
+            let from = (today - days).format("%Y-%m-%d").to_string();
+            let to = (today + days).format("%Y-%m-%d").to_string();
+            vec![client
+                .get(url)
+                .header("X-Auth-Token", key)
+                .query(&[("dateFrom", from.as_str()), ("dateTo", to.as_str())])]
+        }
+    }
+}
+
+async fn fetch_followed_from(
+    client: &reqwest::Client,
+    settings: &SportsSettings,
+    base_url: &str,
+    followed: Followed<'_>,
+) -> Result<Vec<SportsMatch>, String> {
+    let name = match followed {
+        Followed::Team(id) => format!("team {}", id),
+        Followed::League(id) => format!("league {}", id),
+    };
+    let mut matches = Vec::new();
+    for request in requests(client, settings, base_url, followed) {
+        let body = request
+            .timeout(REQUEST_TIMEOUT)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("Failed to fetch matches of {}: {}", name, e))?
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read matches of {}: {}", name, e))?;
+        let parsed = match settings.provider {
+            SportsProvider::TheSportsDb => parse_thesportsdb(&body),
+            SportsProvider::FootballData => parse_football_data(&body),
+        };
+        matches.extend(parsed.map_err(|e| format!("{} ({})", e, name))?);
+    }
+    Ok(matches)
+}
+
+/// Matches of every followed team and league from the provider at
+/// `base_url`. When all of them fail, the lists of `previous` are kept.
+pub async fn fetch_report_from(
+    client: &reqwest::Client,
+    settings: &SportsSettings,
+    base_url: &str,
+    previous: Option<&SportsReport>,
+) -> SportsReport {
+    let followed = settings
+        .teams
+        .iter()
+        .map(|id| Followed::Team(id))
+        .chain(settings.leagues.iter().map(|id| Followed::League(id)));
+    let mut matches = Vec::new();
+    let mut errors = Vec::new();
+    let mut fetched_any = false;
+    for followed in followed {
+        match fetch_followed_from(client, settings, base_url, followed).await {
+            Ok(found) => {
+                fetched_any = true;
+                matches.extend(found);
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+    if !fetched_any {
+        if let Some(previous) = previous {
+            return SportsReport {
+                errors,
+                ..previous.clone()
+            };
+        }
+    }
+    SportsReport {
+        fetched_at: Some(chrono::Utc::now().timestamp_millis() as u64),
+        errors,
+        ..arrange(matches, settings.max_matches)
+    }
+}
+
+/// Whether a match is on, or one was due to start by now
+fn in_play(report: &SportsReport) -> bool {
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+    !report.live.is_empty() || report.upcoming.iter().any(|fixture| fixture.starts_at.is_some_and(|at| at <= now))
+}
+
+/// The matches, from the cache unless the interval has passed or the
+/// settings changed
+pub async fn report(settings: &SportsSettings) -> SportsReport {
+    if !settings.enabled || (settings.teams.is_empty() && settings.leagues.is_empty()) {
+        return SportsReport::default();
+    }
+    let mut cache = CACHE.lock().await;
+    let fresh = |cached: &Cached| {
+        let age = cached.at.elapsed();
+        let interval = if in_play(&cached.report) {
+            MIN_INTERVAL
+        } else {
+            Duration::from_secs(settings.refresh_interval * 60).max(MIN_INTERVAL)
+        };
+        // Changed settings wait out the shortest interval too
+        age < MIN_INTERVAL || (age < interval && cached.settings == *settings)
+    };
+    if let Some(cached) = cache.as_ref().filter(|cached| fresh(cached)) {
+        return cached.report.clone();
+    }
+    let previous = cache
+        .take()
+        .filter(|cached| cached.settings.provider == settings.provider)
+        .map(|cached| cached.report);
+
+    let base_url = match settings.provider {
+        SportsProvider::TheSportsDb => THESPORTSDB_URL,
+        SportsProvider::FootballData => FOOTBALL_DATA_URL,
+    };
+    let report = fetch_report_from(crate::http_client(), settings, base_url, previous.as_ref()).await;
+    for error in &report.errors {
+        warn!("Sports: {}", error);
+    }
+    health::record::<()>(
+        "sports",
+        &if report.errors.is_empty() { Ok(()) } else { Err(report.errors.join("; ")) },
+    );
+    *cache = Some(Cached {
+        at: Instant::now(),
+        settings: settings.clone(),
+        report: report.clone(),
+    });
+    report
+}
+
+/// Whether a followed team plays today, local time, going by the cached
+/// matches. False until they were first fetched.
+pub fn is_match_day(settings: &SportsSettings) -> bool {
+    if !settings.enabled {
+        return false;
+    }
+    // Not worth waiting for a round of requests in progress
+    let Ok(cache) = CACHE.try_lock() else {
+        return false;
+    };
+    let Some(cached) = cache.as_ref() else {
+        return false;
+    };
+    let today = Local::now().date_naive();
+    let on_today = |fixture: &SportsMatch| {
+        fixture
+            .starts_at
+            .and_then(|at| DateTime::from_timestamp_millis(at as i64))
+            .is_some_and(|at| at.with_timezone(&Local).date_naive() == today)
+    };
+    !cached.report.live.is_empty()
+        || cached.report.upcoming.iter().any(on_today)
+        || cached.report.results.iter().any(on_today)
+}
+
+/// Keep the matches fresh while sports are on, for match days and live
+/// scores, for the lifetime of the server
+pub fn spawn_refresher(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(MIN_INTERVAL);
+        loop {
+            interval.tick().await;
+            if display_sleep::is_asleep() {
+                continue;
+            }
+            match state.settings_manager.get() {
+                // Only asks the provider when the cache is due
+                Ok(settings) => {
+                    report(&settings.sports).await;
+                }
+                Err(e) => warn!("Sports: failed to read settings: {}", e),
+            }
+        }
+    });
+}
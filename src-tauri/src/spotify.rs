@@ -0,0 +1,477 @@
+//! Spotify's currently playing track, for the frame's album-art view.
+//!
+//! Connecting uses the authorization code flow with PKCE, so only the app's
+//! client ID is needed. The owner opens the authorize URL, approves, and
+//! Spotify redirects the browser to the frame's callback route. When the
+//! browser isn't on the frame (the default redirect is 127.0.0.1), the
+//! address it ended up at can be posted to the callback route instead. The
+//! tokens are kept in the config directory and refreshed as they expire.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+pub use idleview_api::music::{SpotifyAuthorization, SpotifyCallbackRequest, SpotifyStatus};
+
+use crate::auth;
+use crate::display_sleep;
+use crate::http_server::AppState;
+use crate::now_playing::{self, NowPlaying, NowPlayingSource};
+use crate::settings_manager::{self, Settings};
+
+pub const AUTHORIZE_URL: &str = "https://accounts.spotify.com/authorize";
+pub const TOKEN_URL: &str = "https://accounts.spotify.com/api/token";
+pub const API_URL: &str = "https://api.spotify.com/v1";
+
+const SCOPE: &str = "user-read-currently-playing user-read-playback-state";
+const TOKENS_FILE: &str = "spotify.json";
+
+/// Access tokens with less than this left are refreshed before use
+const EXPIRY_MARGIN_MS: u64 = 60_000;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the owner has to approve after asking to connect
+const AUTHORIZATION_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+static TOKENS: OnceLock<Mutex<Option<SpotifyTokens>>> = OnceLock::new();
+static PENDING: Mutex<Option<PendingAuthorization>> = Mutex::new(None);
+static LAST_ERROR: Mutex<Option<String>> = Mutex::new(None);
+
+/// Tokens as stored in the config directory
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SpotifyTokens {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub expires_at: u64,  // Unix timestamp in milliseconds
+}
+
+struct PendingAuthorization {
+    state: String,
+    verifier: String,
+    redirect_uri: String,
+    expires: Instant,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    refresh_token: Option<String>,
+    expires_in: Option<u64>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+fn now_ms() -> u64 {
+    chrono::Utc::now().timestamp_millis() as u64
+}
+
+fn tokens() -> &'static Mutex<Option<SpotifyTokens>> {
+    TOKENS.get_or_init(|| {
+        let tokens = settings_manager::get_config_file_path(TOKENS_FILE)
+            .ok()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok());
+        Mutex::new(tokens)
+    })
+}
+
+fn save_tokens(new: SpotifyTokens) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(&new)
+        .map_err(|e| format!("Failed to serialize Spotify tokens: {}", e))?;
+    auth::write_secret_file(TOKENS_FILE, &json)?;
+    *tokens()
+        .lock()
+        .map_err(|e| format!("Failed to lock Spotify tokens: {}", e))? = Some(new);
+    Ok(())
+}
+
+/// Record the outcome of the last request; returns whether the error is new,
+/// so one that repeats every poll is logged once
+fn set_error(error: Option<String>) -> bool {
+    let Ok(mut last) = LAST_ERROR.lock() else {
+        return false;
+    };
+    let new = error.is_some() && *last != error;
+    *last = error;
+    new
+}
+
+/// Unpadded base64url, as PKCE wants the challenge
+fn base64url(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| group | (*byte as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    encoded
+}
+
+/// The S256 code challenge for a PKCE verifier
+pub fn code_challenge(verifier: &str) -> String {
+    base64url(ring::digest::digest(&ring::digest::SHA256, verifier.as_bytes()).as_ref())
+}
+
+fn client_id(settings: &Settings) -> Result<&str, String> {
+    settings
+        .music
+        .spotify
+        .client_id
+        .as_deref()
+        .filter(|id| !id.is_empty())
+        .ok_or_else(|| "Set music.spotify.client_id first".to_string())
+}
+
+/// Whether the app's client ID is set, which connecting needs
+pub fn configured(settings: &Settings) -> bool {
+    client_id(settings).is_ok()
+}
+
+/// Where Spotify sends the browser after approving; it has to be listed in
+/// the Spotify app's settings exactly like this
+pub fn redirect_uri(settings: &Settings) -> String {
+    settings.music.spotify.redirect_uri.clone().unwrap_or_else(|| {
+        let scheme = if settings.server.tls.enabled { "https" } else { "http" };
+        format!("{}://127.0.0.1:{}/api/v1/spotify/callback", scheme, settings.server.port)
+    })
+}
+
+/// The page at which the owner approves the frame
+pub fn authorize_url(base_url: &str, client_id: &str, redirect_uri: &str, state: &str, verifier: &str) -> String {
+    let challenge = code_challenge(verifier);
+    let params = [
+        ("client_id", client_id),
+        ("response_type", "code"),
+        ("redirect_uri", redirect_uri),
+        ("scope", SCOPE),
+        ("state", state),
+        ("code_challenge_method", "S256"),
+        ("code_challenge", challenge.as_str()),
+    ];
+    reqwest::Url::parse_with_params(base_url, &params)
+        .map(String::from)
+        .unwrap_or_default()
+}
+
+/// Start connecting: a new authorize URL, replacing any earlier one
+pub fn authorize(settings: &Settings) -> Result<SpotifyAuthorization, String> {
+    let client_id = client_id(settings)?;
+    let verifier: String = rand::rng()
+        .sample_iter(&rand::distr::Alphanumeric)
+        .take(64)
+        .map(char::from)
+        .collect();
+    let state = auth::generate_token();
+    let redirect_uri = redirect_uri(settings);
+    let authorization = SpotifyAuthorization {
+        authorize_url: authorize_url(AUTHORIZE_URL, client_id, &redirect_uri, &state, &verifier),
+        redirect_uri: redirect_uri.clone(),
+        expires_in: AUTHORIZATION_TIMEOUT.as_secs(),
+    };
+    *PENDING
+        .lock()
+        .map_err(|e| format!("Failed to lock Spotify authorization: {}", e))? = Some(PendingAuthorization {
+        state,
+        verifier,
+        redirect_uri,
+        expires: Instant::now() + AUTHORIZATION_TIMEOUT,
+    });
+    Ok(authorization)
+}
+
+async fn token_request(client: &reqwest::Client, url: &str, form: &[(&str, &str)]) -> Result<TokenResponse, String> {
+    // Refusals come back as JSON with a 4xx status, so the body is read either way
+    client
+        .post(url)
+        .form(form)
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach Spotify: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse Spotify's token response: {}", e))
+}
+
+fn refusal(response: &TokenResponse) -> String {
+    let reason = response
+        .error_description
+        .as_deref()
+        .or(response.error.as_deref())
+        .unwrap_or("no reason given");
+    format!("Spotify refused: {}", reason)
+}
+
+fn expires_at(expires_in: Option<u64>) -> u64 {
+    now_ms() + expires_in.unwrap_or(3600) * 1000
+}
+
+/// Trade the code from the redirect for tokens
+pub async fn exchange_code_from(
+    client: &reqwest::Client,
+    url: &str,
+    client_id: &str,
+    code: &str,
+    redirect_uri: &str,
+    verifier: &str,
+) -> Result<SpotifyTokens, String> {
+    let form = [
+        ("client_id", client_id),
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", redirect_uri),
+        ("code_verifier", verifier),
+    ];
+    let response = token_request(client, url, &form).await?;
+    match (&response.access_token, &response.refresh_token) {
+        (Some(access_token), Some(refresh_token)) if response.error.is_none() => Ok(SpotifyTokens {
+            access_token: access_token.clone(),
+            refresh_token: refresh_token.clone(),
+            expires_at: expires_at(response.expires_in),
+        }),
+        _ => Err(refusal(&response)),
+    }
+}
+
+/// A new access token for the stored refresh token
+pub async fn refresh_from(
+    client: &reqwest::Client,
+    url: &str,
+    client_id: &str,
+    tokens: &SpotifyTokens,
+) -> Result<SpotifyTokens, String> {
+    let form = [
+        ("client_id", client_id),
+        ("grant_type", "refresh_token"),
+        ("refresh_token", tokens.refresh_token.as_str()),
+    ];
+    let response = token_request(client, url, &form).await?;
+    match &response.access_token {
+        Some(access_token) if response.error.is_none() => Ok(SpotifyTokens {
+            access_token: access_token.clone(),
+            // PKCE refresh tokens may be rotated with every refresh
+            refresh_token: response.refresh_token.clone().unwrap_or_else(|| tokens.refresh_token.clone()),
+            expires_at: expires_at(response.expires_in),
+        }),
+        _ => Err(refusal(&response)),
+    }
+}
+
+/// The code and state of a redirect address, or why Spotify didn't give a code
+pub fn callback_params(url: &str) -> Result<(String, String), String> {
+    let url = reqwest::Url::parse(url).map_err(|e| format!("Invalid callback address: {}", e))?;
+    let param = |name: &str| url.query_pairs().find(|(key, _)| key == name).map(|(_, value)| value.into_owned());
+    if let Some(error) = param("error") {
+        return Err(format!("Spotify refused: {}", error));
+    }
+    match (param("code"), param("state")) {
+        (Some(code), Some(state)) => Ok((code, state)),
+        _ => Err("The address has no code and state".to_string()),
+    }
+}
+
+/// Finish connecting with the code and state from the redirect
+pub async fn finish(settings: &Settings, code: &str, state: &str) -> Result<(), String> {
+    let (verifier, redirect_uri) = {
+        let mut pending = PENDING
+            .lock()
+            .map_err(|e| format!("Failed to lock Spotify authorization: {}", e))?;
+        match pending.take() {
+            Some(pending) if pending.state == state && pending.expires > Instant::now() => {
+                (pending.verifier, pending.redirect_uri)
+            }
+            Some(other) if other.state != state => {
+                *pending = Some(other);
+                return Err("This approval is not the latest; connect again".to_string());
+            }
+            _ => return Err("Nothing is waiting for approval; connect again".to_string()),
+        }
+    };
+    let client_id = client_id(settings)?;
+    let result = match exchange_code_from(crate::http_client(), TOKEN_URL, client_id, code, &redirect_uri, &verifier).await {
+        Ok(tokens) => save_tokens(tokens),
+        Err(e) => Err(e),
+    };
+    match &result {
+        Ok(()) => {
+            info!("Spotify: connected");
+            set_error(None);
+        }
+        Err(e) => {
+            warn!("Spotify: {}", e);
+            set_error(Some(e.clone()));
+        }
+    }
+    result
+}
+
+/// The track in a currently-playing answer; None when it's an ad or there is
+/// no item
+pub fn parse_currently_playing(json: &str) -> Result<Option<NowPlaying>, String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| format!("Failed to parse Spotify's player: {}", e))?;
+    let Some(item) = value.get("item").filter(|item| item.is_object()) else {
+        return Ok(None);
+    };
+    let text = |value: Option<&Value>| value.and_then(Value::as_str).map(str::to_string);
+    let Some(title) = text(item.get("name")) else {
+        return Ok(None);
+    };
+    // Tracks have artists and an album, podcast episodes a show
+    let artist = match item.get("artists").and_then(Value::as_array) {
+        Some(artists) => artists
+            .iter()
+            .filter_map(|artist| artist.get("name").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join(", "),
+        None => text(item.pointer("/show/publisher")).unwrap_or_default(),
+    };
+    let album = text(item.pointer("/album/name")).or_else(|| text(item.pointer("/show/name")));
+    let images = item
+        .pointer("/album/images")
+        .or_else(|| item.get("images"))
+        .and_then(Value::as_array);
+    let album_art_url = images.and_then(|images| {
+        images
+            .iter()
+            .max_by_key(|image| image.get("width").and_then(Value::as_u64).unwrap_or(0))
+            .and_then(|image| text(image.get("url")))
+    });
+    Ok(Some(NowPlaying {
+        title,
+        artist,
+        album,
+        album_art_url,
+        is_playing: value.get("is_playing").and_then(Value::as_bool).unwrap_or(false),
+        progress_ms: value.get("progress_ms").and_then(Value::as_u64),
+        duration_ms: item.get("duration_ms").and_then(Value::as_u64),
+        source: NowPlayingSource::Spotify,
+        device: None,
+    }))
+}
+
+/// What the account is playing; None when nothing is
+pub async fn fetch_now_playing_from(
+    client: &reqwest::Client,
+    api_url: &str,
+    access_token: &str,
+) -> Result<Option<NowPlaying>, String> {
+    let response = client
+        .get(format!("{}/me/player/currently-playing", api_url))
+        .query(&[("additional_types", "track,episode")])
+        .bearer_auth(access_token)
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to ask Spotify what's playing: {}", e))?;
+    // 204 when nothing has played for a while
+    if response.status() == reqwest::StatusCode::NO_CONTENT {
+        return Ok(None);
+    }
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read Spotify's player: {}", e))?;
+    if body.trim().is_empty() {
+        return Ok(None);
+    }
+    parse_currently_playing(&body)
+}
+
+/// Whether tokens are stored
+pub fn connected() -> bool {
+    tokens().lock().is_ok_and(|tokens| tokens.is_some())
+}
+
+/// For `GET /api/spotify`
+pub fn status() -> SpotifyStatus {
+    let authorizing = PENDING
+        .lock()
+        .is_ok_and(|pending| pending.as_ref().is_some_and(|pending| pending.expires > Instant::now()));
+    SpotifyStatus {
+        connected: connected(),
+        authorizing,
+        last_error: LAST_ERROR.lock().ok().and_then(|error| error.clone()),
+    }
+}
+
+/// A usable access token, refreshed first when it is about to expire. None
+/// when no account is connected.
+async fn access_token(client: &reqwest::Client, settings: &Settings) -> Result<Option<String>, String> {
+    let Some(current) = tokens().lock().ok().and_then(|tokens| tokens.clone()) else {
+        return Ok(None);
+    };
+    if current.expires_at > now_ms() + EXPIRY_MARGIN_MS {
+        return Ok(Some(current.access_token));
+    }
+    let refreshed = refresh_from(client, TOKEN_URL, client_id(settings)?, &current).await?;
+    save_tokens(refreshed.clone())?;
+    Ok(Some(refreshed.access_token))
+}
+
+/// Forget the account's tokens and any approval in progress. Returns whether
+/// an account was connected.
+pub fn disconnect() -> Result<bool, String> {
+    if let Ok(mut pending) = PENDING.lock() {
+        *pending = None;
+    }
+    set_error(None);
+    let was_connected = tokens()
+        .lock()
+        .map_err(|e| format!("Failed to lock Spotify tokens: {}", e))?
+        .take()
+        .is_some();
+    let path = settings_manager::get_config_file_path(TOKENS_FILE)?;
+    if path.exists() {
+        fs::remove_file(&path).map_err(|e| format!("Failed to remove Spotify tokens: {}", e))?;
+    }
+    Ok(was_connected)
+}
+
+/// Ask Spotify what's playing every `poll_interval` seconds while an account
+/// is connected, for the lifetime of the server
+pub fn spawn_poller(state: AppState) {
+    tokio::spawn(async move {
+        loop {
+            if display_sleep::is_asleep() {
+                tokio::time::sleep(display_sleep::PAUSED_POLL_INTERVAL).await;
+                continue;
+            }
+            let settings = match state.settings_manager.get() {
+                Ok(settings) => settings,
+                Err(e) => {
+                    warn!("Spotify: failed to read settings: {}", e);
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    continue;
+                }
+            };
+            let client = crate::http_client();
+            let result = match access_token(client, &settings).await {
+                Ok(Some(access_token)) => fetch_now_playing_from(client, API_URL, &access_token).await.map(Some),
+                Ok(None) => Ok(None),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(Some(playing)) => {
+                    set_error(None);
+                    now_playing::update(&state, NowPlayingSource::Spotify, playing);
+                }
+                // Not connected (any more): nothing is playing as far as Spotify goes
+                Ok(None) => now_playing::update(&state, NowPlayingSource::Spotify, None),
+                Err(e) => {
+                    if set_error(Some(e.clone())) {
+                        warn!("Spotify: {}", e);
+                    }
+                }
+            }
+            tokio::time::sleep(Duration::from_secs(settings.music.spotify.poll_interval.max(1))).await;
+        }
+    });
+}
@@ -0,0 +1,196 @@
+//! SSDP/UPnP discovery responder.
+//!
+//! Some home networks block mDNS but pass SSDP, and smart-home hubs and
+//! network scanners list devices found this way. The frame answers M-SEARCH
+//! queries with a link to its device description at `/description.xml`.
+
+use rand::Rng;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::fs;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::OnceLock;
+use tracing::{info, warn};
+
+use crate::auth;
+use crate::http_server::AppState;
+use crate::settings_manager::{self, ServerBind, ServerSettings};
+
+const MULTICAST_ADDR: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+const SSDP_PORT: u16 = 1900;
+pub const DEVICE_TYPE: &str = "urn:schemas-upnp-org:device:Basic:1";
+const UUID_FILE: &str = "ssdp_uuid";
+
+static DEVICE_UUID: OnceLock<String> = OnceLock::new();
+
+/// The frame's UPnP UUID, generated once and kept in the config directory
+pub fn device_uuid() -> &'static str {
+    DEVICE_UUID.get_or_init(|| {
+        let stored = settings_manager::get_config_file_path(UUID_FILE)
+            .ok()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|uuid| uuid.trim().to_string())
+            .filter(|uuid| !uuid.is_empty());
+        if let Some(uuid) = stored {
+            return uuid;
+        }
+
+        let bytes: [u8; 16] = rand::rng().random();
+        let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        let uuid = format!("{}-{}-4{}-a{}-{}", &hex[0..8], &hex[8..12], &hex[13..16], &hex[17..20], &hex[20..32]);
+        if let Err(e) = auth::write_secret_file(UUID_FILE, &uuid) {
+            warn!("SSDP: {}", e);
+        }
+        uuid
+    })
+}
+
+/// The search target of an M-SEARCH request, or None for anything else
+pub fn search_target(request: &str) -> Option<String> {
+    let mut lines = request.lines();
+    if !lines.next()?.trim().eq_ignore_ascii_case("M-SEARCH * HTTP/1.1") {
+        return None;
+    }
+
+    let mut target = None;
+    let mut discover = false;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_uppercase().as_str() {
+            "ST" => target = Some(value.to_string()),
+            "MAN" => discover = value.trim_matches('"') == "ssdp:discover",
+            _ => {}
+        }
+    }
+    target.filter(|_| discover)
+}
+
+/// The search targets the frame answers for a query, with the USN of each
+pub fn matching_targets(target: &str, uuid: &str) -> Vec<(String, String)> {
+    let device = format!("uuid:{}", uuid);
+    let all = [
+        ("upnp:rootdevice".to_string(), format!("{}::upnp:rootdevice", device)),
+        (device.clone(), device.clone()),
+        (DEVICE_TYPE.to_string(), format!("{}::{}", device, DEVICE_TYPE)),
+    ];
+    if target == "ssdp:all" {
+        return all.to_vec();
+    }
+    all.into_iter().filter(|(st, _)| st == target).collect()
+}
+
+/// The unicast reply to an M-SEARCH
+pub fn search_response(target: &str, usn: &str, location: &str) -> String {
+    format!(
+        "HTTP/1.1 200 OK\r\n\
+         CACHE-CONTROL: max-age=1800\r\n\
+         EXT:\r\n\
+         LOCATION: {}\r\n\
+         SERVER: Idleview/{} UPnP/1.1\r\n\
+         ST: {}\r\n\
+         USN: {}\r\n\
+         \r\n",
+        location,
+        env!("CARGO_PKG_VERSION"),
+        target,
+        usn
+    )
+}
+
+/// The UPnP device description served at `/description.xml`
+pub fn device_description(uuid: &str, presentation_url: &str) -> String {
+    format!(
+        r#"<?xml version="1.0"?>
+<root xmlns="urn:schemas-upnp-org:device-1-0">
+  <specVersion><major>1</major><minor>0</minor></specVersion>
+  <device>
+    <deviceType>{}</deviceType>
+    <friendlyName>Idleview</friendlyName>
+    <manufacturer>Idleview</manufacturer>
+    <modelName>Idleview</modelName>
+    <modelNumber>{}</modelNumber>
+    <UDN>uuid:{}</UDN>
+    <presentationURL>{}</presentationURL>
+  </device>
+</root>
+"#,
+        DEVICE_TYPE,
+        env!("CARGO_PKG_VERSION"),
+        uuid,
+        presentation_url
+    )
+}
+
+/// The control panel address on the local network, when it can be reached from there
+pub fn panel_url(server: &ServerSettings) -> Option<String> {
+    if !server.enabled || server.bind == ServerBind::Localhost {
+        return None;
+    }
+    let ip = local_ip_address::local_ip().ok()?;
+    let scheme = if server.tls.enabled { "https" } else { "http" };
+    Some(format!("{}://{}:{}", scheme, ip, server.port))
+}
+
+/// A socket on the SSDP port that other UPnP software on the machine can share
+fn bind_socket() -> Result<tokio::net::UdpSocket, String> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))
+        .map_err(|e| format!("Failed to create SSDP socket: {}", e))?;
+    socket
+        .set_reuse_address(true)
+        .map_err(|e| format!("Failed to share SSDP port: {}", e))?;
+    #[cfg(unix)]
+    socket
+        .set_reuse_port(true)
+        .map_err(|e| format!("Failed to share SSDP port: {}", e))?;
+    socket
+        .bind(&SocketAddr::from(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, SSDP_PORT)).into())
+        .map_err(|e| format!("Failed to bind SSDP port {}: {}", SSDP_PORT, e))?;
+    socket
+        .join_multicast_v4(&MULTICAST_ADDR, &Ipv4Addr::UNSPECIFIED)
+        .map_err(|e| format!("Failed to join SSDP multicast group: {}", e))?;
+    socket
+        .set_nonblocking(true)
+        .map_err(|e| format!("Failed to configure SSDP socket: {}", e))?;
+    tokio::net::UdpSocket::from_std(socket.into()).map_err(|e| format!("Failed to configure SSDP socket: {}", e))
+}
+
+/// Answer SSDP searches while the server is reachable from the network
+pub fn spawn_responder(state: AppState) {
+    tokio::spawn(async move {
+        let socket = match bind_socket() {
+            Ok(socket) => socket,
+            Err(e) => {
+                warn!("SSDP: {}", e);
+                return;
+            }
+        };
+        info!("📣 Answering SSDP searches on port {}", SSDP_PORT);
+
+        let mut buffer = [0u8; 2048];
+        loop {
+            let (len, peer) = match socket.recv_from(&mut buffer).await {
+                Ok(received) => received,
+                Err(e) => {
+                    warn!("SSDP: receive failed: {}", e);
+                    continue;
+                }
+            };
+            let Some(target) = search_target(&String::from_utf8_lossy(&buffer[..len])) else {
+                continue;
+            };
+            let Some(panel) = state.settings_manager.get().ok().and_then(|s| panel_url(&s.server)) else {
+                continue;
+            };
+
+            let location = format!("{}/description.xml", panel);
+            for (st, usn) in matching_targets(&target, device_uuid()) {
+                let response = search_response(&st, &usn, &location);
+                if let Err(e) = socket.send_to(response.as_bytes(), peer).await {
+                    warn!("SSDP: failed to answer {}: {}", peer, e);
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,174 @@
+//! Stock prices for a desk display: last price and the day's change of the
+//! configured symbols, from Yahoo Finance (no key) or Finnhub.
+//!
+//! However often the frame or API clients ask, the provider is asked at most
+//! once per `refresh_interval`, and never more than once a minute; callers in
+//! between get the cached report. A symbol that fails keeps its last quote.
+
+use serde_json::Value;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+pub use idleview_api::market::{StockQuote, StocksReport};
+
+use crate::health;
+use crate::settings_manager::{StockProvider, StocksSettings};
+
+pub const YAHOO_URL: &str = "https://query1.finance.yahoo.com/v8/finance/chart";
+pub const FINNHUB_URL: &str = "https://finnhub.io/api/v1/quote";
+
+/// Shortest time between two rounds of requests, whatever the settings say
+const MIN_INTERVAL: Duration = Duration::from_secs(60);
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Cached {
+    at: Instant,
+    settings: StocksSettings,
+    report: StocksReport,
+}
+
+/// Held while fetching, so callers arriving meanwhile wait for that round
+/// instead of starting their own
+static CACHE: Mutex<Option<Cached>> = Mutex::const_new(None);
+
+fn number(value: &Value, key: &str) -> Option<f64> {
+    value.get(key).and_then(Value::as_f64)
+}
+
+/// Quote from Yahoo's chart endpoint: the meta block has the last price and
+/// the previous close
+pub fn parse_yahoo_chart(symbol: &str, json: &str) -> Result<StockQuote, String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| format!("Failed to parse quote for {}: {}", symbol, e))?;
+    let meta = value
+        .pointer("/chart/result/0/meta")
+        .ok_or_else(|| format!("No quote for {}", symbol))?;
+    let price = number(meta, "regularMarketPrice").ok_or_else(|| format!("No price for {}", symbol))?;
+    let previous = number(meta, "chartPreviousClose")
+        .or_else(|| number(meta, "previousClose"))
+        .unwrap_or(price);
+    let change = price - previous;
+    Ok(StockQuote {
+        symbol: symbol.to_string(),
+        price,
+        change,
+        change_percent: if previous != 0.0 { change / previous * 100.0 } else { 0.0 },
+        currency: meta.get("currency").and_then(Value::as_str).map(str::to_string),
+    })
+}
+
+/// Quote from Finnhub, which answers unknown symbols with zeros
+pub fn parse_finnhub_quote(symbol: &str, json: &str) -> Result<StockQuote, String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| format!("Failed to parse quote for {}: {}", symbol, e))?;
+    let price = number(&value, "c")
+        .filter(|price| *price != 0.0)
+        .ok_or_else(|| format!("No price for {}", symbol))?;
+    Ok(StockQuote {
+        symbol: symbol.to_string(),
+        price,
+        change: number(&value, "d").unwrap_or_default(),
+        change_percent: number(&value, "dp").unwrap_or_default(),
+        currency: None,
+    })
+}
+
+/// One symbol's quote from the provider at `base_url`
+pub async fn fetch_quote_from(
+    client: &reqwest::Client,
+    settings: &StocksSettings,
+    base_url: &str,
+    symbol: &str,
+) -> Result<StockQuote, String> {
+    let request = match settings.provider {
+        StockProvider::Yahoo => client
+            .get(format!("{}/{}", base_url, urlencoding::encode(symbol)))
+            .query(&[("range", "1d"), ("interval", "1d")]),
+        StockProvider::Finnhub => {
+            let key = settings.api_keys.finnhub.as_deref().unwrap_or_default();
+            client.get(base_url).query(&[("symbol", symbol), ("token", key)])
+        }
+    };
+    let body = request
+        .header(reqwest::header::USER_AGENT, concat!("Idleview/", env!("CARGO_PKG_VERSION")))
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to fetch quote for {}: {}", symbol, e))?
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read quote for {}: {}", symbol, e))?;
+    match settings.provider {
+        StockProvider::Yahoo => parse_yahoo_chart(symbol, &body),
+        StockProvider::Finnhub => parse_finnhub_quote(symbol, &body),
+    }
+}
+
+/// Quotes of every symbol, in order. Symbols that fail are listed in
+/// `errors` and keep their quote from `previous`, when it has one.
+pub async fn fetch_report_from(
+    client: &reqwest::Client,
+    settings: &StocksSettings,
+    base_url: &str,
+    previous: Option<&StocksReport>,
+) -> StocksReport {
+    let mut quotes = Vec::new();
+    let mut errors = Vec::new();
+    for symbol in &settings.symbols {
+        match fetch_quote_from(client, settings, base_url, symbol).await {
+            Ok(quote) => quotes.push(quote),
+            Err(e) => {
+                let last = previous.and_then(|report| report.quotes.iter().find(|quote| quote.symbol == *symbol));
+                quotes.extend(last.cloned());
+                errors.push(e);
+            }
+        }
+    }
+    StocksReport {
+        quotes,
+        fetched_at: Some(chrono::Utc::now().timestamp_millis() as u64),
+        errors,
+    }
+}
+
+/// The quotes, from the cache unless the interval has passed or the
+/// settings changed
+pub async fn report(settings: &StocksSettings) -> StocksReport {
+    if !settings.enabled || settings.symbols.is_empty() {
+        return StocksReport::default();
+    }
+    let mut cache = CACHE.lock().await;
+    let interval = Duration::from_secs(settings.refresh_interval * 60).max(MIN_INTERVAL);
+    let fresh = |cached: &Cached| {
+        let age = cached.at.elapsed();
+        // Changed settings wait out the shortest interval too
+        age < MIN_INTERVAL || (age < interval && cached.settings == *settings)
+    };
+    if let Some(cached) = cache.as_ref().filter(|cached| fresh(cached)) {
+        return cached.report.clone();
+    }
+    let previous = cache
+        .take()
+        .filter(|cached| cached.settings.provider == settings.provider)
+        .map(|cached| cached.report);
+
+    let base_url = match settings.provider {
+        StockProvider::Yahoo => YAHOO_URL,
+        StockProvider::Finnhub => FINNHUB_URL,
+    };
+    let report = fetch_report_from(crate::http_client(), settings, base_url, previous.as_ref()).await;
+    for error in &report.errors {
+        warn!("Stocks: {}", error);
+    }
+    health::record::<()>(
+        "stocks",
+        &if report.errors.is_empty() { Ok(()) } else { Err(report.errors.join("; ")) },
+    );
+    *cache = Some(Cached {
+        at: Instant::now(),
+        settings: settings.clone(),
+        report: report.clone(),
+    });
+    report
+}
@@ -0,0 +1,242 @@
+//! Lead/follower sync, so every frame in a house shows the same photo.
+//!
+//! A frame set to follow finds the lead (at `sync.lead_url`, or over mDNS),
+//! catches up on its current photo and settings, then takes every change
+//! the lead pushes on its event stream. The rules when both sides change:
+//!
+//! - The lead wins. Its photo replaces the follower's, and a settings change
+//!   on the lead overwrites the synced sections on every follower.
+//! - Changes made on a follower stay until the lead's settings next change,
+//!   or the follower reconnects and catches up.
+//! - `server`, `sync` and the sections in `sync.local_sections` are never
+//!   taken from the lead.
+//! - Credentials aren't either: the lead's API redacts them, so each frame
+//!   keeps its own API keys and passwords.
+//! - While it follows, a frame doesn't pick photos itself. When the lead
+//!   can't be reached it goes back to its own rotation until it can.
+
+use serde_json::json;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use idleview_api::{CurrentPhoto, IdleviewClient, ServerEvent};
+pub use idleview_api::sync::SyncStatus;
+
+use crate::discovery;
+use crate::http_server::AppState;
+use crate::settings_manager::{self, Settings, SyncRole, SyncSettings};
+
+/// Sections that describe the frame itself rather than what it shows
+pub const LOCAL_SECTIONS: &[&str] = &["server", "sync"];
+
+/// Pause before trying the lead again after losing it
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+/// How long to look for a lead over mDNS
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a frame found over mDNS gets to say whether it leads
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+static STATUS: Mutex<SyncStatus> = Mutex::new(SyncStatus {
+    role: SyncRole::Off,
+    lead: None,
+    connected: false,
+    last_sync: None,
+    last_error: None,
+});
+
+/// Where following the lead stands, for `GET /api/sync`
+pub fn status(role: SyncRole) -> SyncStatus {
+    let mut status = STATUS.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    status.role = role;
+    if role != SyncRole::Follow {
+        status.lead = None;
+        status.connected = false;
+    }
+    status
+}
+
+fn update_status(state: &AppState, change: impl FnOnce(&mut SyncStatus)) {
+    let Ok(mut status) = STATUS.lock() else {
+        return;
+    };
+    let was_connected = status.connected;
+    change(&mut status);
+    if status.connected != was_connected {
+        state.publish_from(ServerEvent::SyncStatus(status.clone()), "sync");
+    }
+}
+
+/// The part of the lead's settings a follower takes: every section but the
+/// local ones, as a partial update
+pub fn settings_patch(lead: &Settings, sync: &SyncSettings) -> serde_json::Value {
+    let mut patch = serde_json::to_value(lead).unwrap_or_else(|_| json!({}));
+    if let Some(sections) = patch.as_object_mut() {
+        sections.retain(|section, _| {
+            !LOCAL_SECTIONS.contains(&section.as_str()) && !sync.local_sections.contains(section)
+        });
+    }
+    patch
+}
+
+/// Whether applying the patch would change anything, so unchanged settings
+/// aren't written and recorded in the history again
+pub fn patch_changes(local: &Settings, patch: &serde_json::Value) -> Result<bool, String> {
+    // The lead's credentials arrive redacted and leave the local ones be
+    let local = serde_json::to_value(settings_manager::redacted(local)?)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    Ok(patch
+        .as_object()
+        .is_some_and(|sections| sections.iter().any(|(section, value)| local.get(section) != Some(value))))
+}
+
+fn apply_settings(state: &AppState, sync: &SyncSettings, lead: &Settings) -> Result<(), String> {
+    if !sync.settings {
+        return Ok(());
+    }
+    let patch = settings_patch(lead, sync);
+    if !patch_changes(&state.settings_manager.get()?, &patch)? {
+        return Ok(());
+    }
+    let settings = state.settings_manager.update_partial_from(patch, "sync")?;
+    info!("Sync: took the lead's settings");
+    state.publish_from(ServerEvent::SettingsUpdated(Box::new(settings)), "sync");
+    Ok(())
+}
+
+fn apply_photo(state: &AppState, photo: CurrentPhoto) -> Result<(), String> {
+    let showing = state
+        .current_photo
+        .lock()
+        .map_err(|e| format!("Failed to lock photo state: {}", e))?
+        .as_ref()
+        .is_some_and(|current| current.url == photo.url);
+    if showing {
+        return Ok(());
+    }
+    // The frame reports the photo back once it shows it, like any other
+    state.publish_from(ServerEvent::SyncPhoto(photo), "sync");
+    Ok(())
+}
+
+/// Look for a frame on the network whose sync role is lead
+async fn find_lead() -> Result<String, String> {
+    let daemon = mdns_sd::ServiceDaemon::new().map_err(|e| format!("Failed to start mDNS: {}", e))?;
+    let browser = daemon
+        .browse(discovery::SERVICE_TYPE)
+        .map_err(|e| format!("Failed to browse mDNS: {}", e))?;
+
+    let search = async {
+        while let Ok(event) = browser.recv_async().await {
+            let mdns_sd::ServiceEvent::ServiceResolved(service) = event else {
+                continue;
+            };
+            let scheme = service.get_property_val_str("scheme").unwrap_or("http");
+            for address in service.get_addresses() {
+                let url = format!("{}://{}", scheme, std::net::SocketAddr::new(*address, service.get_port()));
+                let client = IdleviewClient::new(url.as_str());
+                if let Ok(Ok(status)) = tokio::time::timeout(PROBE_TIMEOUT, client.sync_status()).await {
+                    if status.role == SyncRole::Lead {
+                        return Some(url);
+                    }
+                }
+            }
+        }
+        None
+    };
+    let found = tokio::time::timeout(DISCOVERY_TIMEOUT, search).await.ok().flatten();
+    let _ = daemon.shutdown();
+    found.ok_or_else(|| "No lead found on the network".to_string())
+}
+
+/// Catch up with the lead, then take its changes until the connection drops
+async fn follow_lead(state: &AppState, sync: &SyncSettings) -> Result<(), String> {
+    let lead = match &sync.lead_url {
+        Some(url) => url.trim_end_matches('/').to_string(),
+        None => find_lead().await?,
+    };
+    let client = IdleviewClient::new(lead.as_str());
+    if client.sync_status().await?.role != SyncRole::Lead {
+        return Err(format!("{} is not set up as a lead", lead));
+    }
+
+    // Subscribe before catching up so nothing is missed in between
+    let mut events = client.events().await?;
+    apply_settings(state, sync, &client.settings().await?)?;
+    if let Some(photo) = client.current_photo().await? {
+        apply_photo(state, photo)?;
+    }
+    info!("Sync: following {}", lead);
+    update_status(state, |status| {
+        status.lead = Some(lead.clone());
+        status.connected = true;
+        status.last_sync = Some(chrono::Utc::now().timestamp_millis() as u64);
+        status.last_error = None;
+    });
+
+    while let Some(envelope) = events.next().await {
+        let applied = match envelope?.event {
+            ServerEvent::SettingsUpdated(settings) => apply_settings(state, sync, &settings),
+            ServerEvent::PhotoUpdated(photo) => apply_photo(state, photo),
+            _ => continue,
+        };
+        match applied {
+            Ok(()) => update_status(state, |status| {
+                status.last_sync = Some(chrono::Utc::now().timestamp_millis() as u64)
+            }),
+            Err(e) => warn!("Sync: {}", e),
+        }
+    }
+    Err(format!("{} closed the event stream", lead))
+}
+
+/// Follow the lead until the sync settings change
+async fn follow(state: &AppState, sync: &SyncSettings) {
+    loop {
+        if let Err(e) = follow_lead(state, sync).await {
+            warn!("Sync: {}", e);
+            update_status(state, |status| {
+                status.connected = false;
+                status.last_error = Some(e);
+            });
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+async fn sync_settings_changed(changes: &mut watch::Receiver<Settings>, current: &SyncSettings) {
+    loop {
+        if changes.changed().await.is_err() {
+            // The settings can no longer change
+            std::future::pending::<()>().await;
+        }
+        if changes.borrow_and_update().sync != *current {
+            return;
+        }
+    }
+}
+
+/// Follow the lead whenever this frame is set to, for the lifetime of the server
+pub fn spawn_follower(state: AppState) {
+    let mut changes = state.settings_manager.subscribe();
+    tokio::spawn(async move {
+        loop {
+            let sync = changes.borrow_and_update().sync.clone();
+            if sync.role == SyncRole::Follow {
+                tokio::select! {
+                    _ = follow(&state, &sync) => {}
+                    _ = sync_settings_changed(&mut changes, &sync) => {}
+                }
+                update_status(&state, |status| {
+                    status.lead = None;
+                    status.connected = false;
+                });
+            } else {
+                sync_settings_changed(&mut changes, &sync).await;
+            }
+        }
+    });
+}
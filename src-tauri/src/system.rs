@@ -0,0 +1,212 @@
+//! Health of the machine running the frame, for remote monitoring without SSH.
+
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use sysinfo::{Disks, ProcessRefreshKind, ProcessesToUpdate, System};
+use tracing::warn;
+
+pub use idleview_api::system::{CpuUsage, DiskStatus, LoadAverage, MemoryStatus, SystemStatus, WifiSignal};
+use idleview_api::ServerEvent;
+
+use crate::battery;
+use crate::disk_space;
+use crate::http_server::AppState;
+use crate::ntp;
+use crate::settings_manager;
+use crate::thermal;
+use crate::updates;
+
+#[cfg(target_os = "linux")]
+const PROC_NET_WIRELESS: &str = "/proc/net/wireless";
+
+/// Link quality most drivers report for a perfect signal
+const MAX_LINK_QUALITY: f32 = 70.0;
+
+/// How often the CPU temperature is checked against its limit
+const TEMPERATURE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How far the CPU has to cool below the limit before it can be reported hot again
+pub const TEMPERATURE_HYSTERESIS: f32 = 5.0;
+
+/// The first interface in `/proc/net/wireless`, under its two header lines:
+/// "wlan0: 0000   54.  -56.  -256        0 ..." (status, link, level, noise)
+pub fn parse_proc_net_wireless(contents: &str) -> Option<WifiSignal> {
+    let (interface, fields) = contents.lines().skip(2).find_map(|line| line.split_once(':'))?;
+    let mut fields = fields.split_whitespace().skip(1).map(|field| field.trim_end_matches('.').parse::<f32>());
+    let link = fields.next()?.ok()?;
+    let level = fields.next()?.ok()?;
+    Some(WifiSignal {
+        interface: interface.trim().to_string(),
+        quality: (link / MAX_LINK_QUALITY * 100.0).round().clamp(0.0, 100.0) as u8,
+        // Some drivers give a relative level instead of dBm
+        signal_dbm: (level < 0.0).then_some(level as i32),
+    })
+}
+
+/// Strength of the Wi-Fi link, None when the machine isn't on Wi-Fi
+pub(crate) fn wifi_signal() -> Option<WifiSignal> {
+    #[cfg(target_os = "linux")]
+    {
+        parse_proc_net_wireless(&std::fs::read_to_string(PROC_NET_WIRELESS).ok()?)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// Kept between CPU usage readings, since sysinfo measures usage as the
+/// change since its last refresh
+static CPU_SYSTEM: Mutex<Option<System>> = Mutex::new(None);
+
+/// Index of the mount point holding `path`: the longest one that is a prefix of it
+pub fn containing_mount(mount_points: &[&Path], path: &Path) -> Option<usize> {
+    mount_points
+        .iter()
+        .enumerate()
+        .filter(|(_, mount)| path.starts_with(mount))
+        .max_by_key(|(_, mount)| mount.as_os_str().len())
+        .map(|(index, _)| index)
+}
+
+/// Per-core and total CPU utilization since the previous reading, with the
+/// load averages. The first reading waits a moment to have something to compare.
+pub fn cpu_usage() -> Option<CpuUsage> {
+    let mut cpu = CPU_SYSTEM.lock().ok()?;
+    let system = match cpu.as_mut() {
+        Some(system) => {
+            system.refresh_cpu_usage();
+            system
+        }
+        None => {
+            let mut system = System::new();
+            system.refresh_cpu_usage();
+            std::thread::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+            system.refresh_cpu_usage();
+            cpu.insert(system)
+        }
+    };
+    let load = System::load_average();
+    Some(CpuUsage {
+        total: system.global_cpu_usage(),
+        cores: system.cpus().iter().map(|core| core.cpu_usage()).collect(),
+        load_average: LoadAverage { one: load.one, five: load.five, fifteen: load.fifteen },
+    })
+}
+
+/// Memory of `root` and every process below it, from (pid, parent, bytes)
+pub fn process_tree_memory(processes: &[(u32, Option<u32>, u64)], root: u32) -> u64 {
+    let mut tree = vec![root];
+    let mut next = 0;
+    while next < tree.len() {
+        let parent = tree[next];
+        for (pid, _, _) in processes.iter().filter(|(_, of, _)| *of == Some(parent)) {
+            if !tree.contains(pid) {
+                tree.push(*pid);
+            }
+        }
+        next += 1;
+    }
+    processes.iter().filter(|(pid, _, _)| tree.contains(pid)).map(|(_, _, bytes)| bytes).sum()
+}
+
+/// The app with the processes it started. On Linux and Windows those include
+/// the webview's renderers, whose memory creeps up over days of slideshow.
+fn app_memory(system: &mut System) -> Option<u64> {
+    let pid = sysinfo::get_current_pid().ok()?;
+    system.refresh_processes_specifics(ProcessesToUpdate::All, true, ProcessRefreshKind::nothing().with_memory());
+    let processes: Vec<(u32, Option<u32>, u64)> = system
+        .processes()
+        .values()
+        // Threads are listed too, each with its process's memory
+        .filter(|process| process.thread_kind().is_none())
+        .map(|process| (process.pid().as_u32(), process.parent().map(|parent| parent.as_u32()), process.memory()))
+        .collect();
+    Some(process_tree_memory(&processes, pid.as_u32()))
+}
+
+pub(crate) fn memory_status() -> MemoryStatus {
+    let mut system = System::new();
+    system.refresh_memory();
+    MemoryStatus {
+        total: system.total_memory(),
+        used: system.used_memory(),
+        available: system.available_memory(),
+        swap_total: system.total_swap(),
+        swap_used: system.used_swap(),
+        app: app_memory(&mut system),
+    }
+}
+
+/// Space on the disk holding the settings, which is the one that fills up on a Pi
+pub(crate) fn disk_status() -> Option<DiskStatus> {
+    disk_containing(&settings_manager::get_settings_path().ok()?)
+}
+
+/// Space on the disk `path` is on
+pub(crate) fn disk_containing(path: &Path) -> Option<DiskStatus> {
+    let disks = Disks::new_with_refreshed_list();
+    let mount_points: Vec<&Path> = disks.iter().map(|disk| disk.mount_point()).collect();
+    let disk = &disks.list()[containing_mount(&mount_points, path)?];
+    Some(DiskStatus {
+        mount_point: disk.mount_point().display().to_string(),
+        total: disk.total_space(),
+        available: disk.available_space(),
+    })
+}
+
+/// Collect the current system status
+pub fn system_status() -> SystemStatus {
+    SystemStatus {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        uptime: System::uptime(),
+        cpu_temperature: thermal::cpu_temperature(),
+        gpu_temperature: thermal::gpu_temperature(),
+        cpu_usage: cpu_usage(),
+        wifi: wifi_signal(),
+        battery: battery::status(),
+        memory: memory_status(),
+        disk: disk_status(),
+        cache_disk: disk_space::cache_disk(),
+        update: updates::last_check(),
+        clock: ntp::status(),
+    }
+}
+
+/// Whether a reading newly crosses the limit. `hot` carries the state between
+/// readings, so one hot spell is reported once.
+pub fn temperature_crossed(celsius: f32, limit: f32, hot: &mut bool) -> bool {
+    if *hot {
+        *hot = celsius > limit - TEMPERATURE_HYSTERESIS;
+        return false;
+    }
+    *hot = celsius >= limit;
+    *hot
+}
+
+/// Raise `cpu-temperature-high` when the CPU reaches its limit, for the lifetime of the server
+pub fn spawn_temperature_monitor(state: AppState) {
+    tokio::spawn(async move {
+        let mut hot = false;
+        let mut interval = tokio::time::interval(TEMPERATURE_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let Ok(Some(celsius)) = tokio::task::spawn_blocking(thermal::cpu_temperature).await else {
+                continue;
+            };
+            let limit = match state.settings_manager.get() {
+                Ok(settings) => settings.webhooks.cpu_temperature_limit,
+                Err(e) => {
+                    warn!("Failed to read the CPU temperature limit: {}", e);
+                    continue;
+                }
+            };
+            if temperature_crossed(celsius, limit, &mut hot) {
+                warn!("CPU temperature is {:.1} °C, at or above the {:.1} °C limit", celsius, limit);
+                state.publish_from(ServerEvent::CpuTemperatureHigh { celsius }, "system");
+            }
+        }
+    });
+}
@@ -0,0 +1,235 @@
+//! Today's tasks for a kitchen or hallway frame: those due today or overdue
+//! in Todoist, and/or the lines of a local text file such as a grocery list.
+//!
+//! Todoist is asked at most once per `refresh_interval`, and never more than
+//! once a minute; when a request fails its last tasks are kept. The file is
+//! read on every call, so edits show up on the next refresh of the frame.
+//!
+//! The file has a task per line. Blank lines and lines starting with `#` are
+//! skipped, Markdown list markers are dropped, checked items (`- [x]`) are
+//! done, and a line starting with a date (`2026-05-04 Dentist`) shows from
+//! that day on. Lines without a date always show.
+
+use chrono::{Local, NaiveDate};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+pub use idleview_api::tasks::{Task, TaskSource, TasksReport};
+
+use crate::health;
+use crate::settings_manager::{self, TasksSettings};
+
+pub const TODOIST_URL: &str = "https://api.todoist.com/api/v1/tasks/filter";
+
+/// Shortest time between two requests, whatever the settings say
+const MIN_INTERVAL: Duration = Duration::from_secs(60);
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pages of a filter read at most, at 200 tasks each
+const MAX_PAGES: usize = 5;
+
+struct Cached {
+    at: Instant,
+    settings: TasksSettings,
+    tasks: Vec<Task>,
+    fetched_at: u64,
+    error: Option<String>,
+}
+
+/// Held while fetching, so callers arriving meanwhile wait for that request
+static CACHE: Mutex<Option<Cached>> = Mutex::const_new(None);
+
+fn task(content: String, due: Option<NaiveDate>, source: TaskSource, today: NaiveDate) -> Task {
+    Task {
+        content,
+        due: due.map(|due| due.format("%Y-%m-%d").to_string()),
+        overdue: due.is_some_and(|due| due < today),
+        source,
+    }
+}
+
+/// Tasks in a page of Todoist's filter answer, and the cursor of the next
+/// page. Due dates with a time keep only the date.
+pub fn parse_todoist(json: &str, today: NaiveDate) -> Result<(Vec<Task>, Option<String>), String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| format!("Failed to parse Todoist tasks: {}", e))?;
+    let results = value
+        .get("results")
+        .and_then(Value::as_array)
+        .ok_or_else(|| match value.get("error").and_then(Value::as_str) {
+            Some(error) => format!("Todoist: {}", error),
+            None => "No tasks in Todoist's answer".to_string(),
+        })?;
+    let tasks = results
+        .iter()
+        .filter(|item| !item.get("checked").and_then(Value::as_bool).unwrap_or(false))
+        .filter_map(|item| {
+            let content = item.get("content").and_then(Value::as_str)?.trim();
+            let due = item
+                .pointer("/due/date")
+                .and_then(Value::as_str)
+                .and_then(|date| NaiveDate::parse_from_str(date.get(..10).unwrap_or(date), "%Y-%m-%d").ok());
+            (!content.is_empty()).then(|| task(content.to_string(), due, TaskSource::Todoist, today))
+        })
+        .collect();
+    let cursor = value.get("next_cursor").and_then(Value::as_str).map(str::to_string);
+    Ok((tasks, cursor))
+}
+
+/// The tasks of a tasks file due by `today`, in file order
+pub fn parse_tasks_file(content: &str, today: NaiveDate) -> Vec<Task> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let line = line
+                .strip_prefix("- ")
+                .or_else(|| line.strip_prefix("* "))
+                .map(str::trim_start)
+                .unwrap_or(line);
+            let line = match line.get(..3) {
+                Some("[x]") | Some("[X]") => return None,
+                Some("[ ]") => line[3..].trim_start(),
+                _ => line,
+            };
+            let due = line
+                .get(..10)
+                .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok());
+            let content = match due {
+                Some(_) => line[10..].trim(),
+                None => line,
+            };
+            if content.is_empty() || due.is_some_and(|due| due > today) {
+                return None;
+            }
+            Some(task(content.to_string(), due, TaskSource::File, today))
+        })
+        .collect()
+}
+
+/// Tasks matching `todoist_filter` from the filter endpoint at `url`
+pub async fn fetch_todoist_from(
+    client: &reqwest::Client,
+    url: &str,
+    settings: &TasksSettings,
+    today: NaiveDate,
+) -> Result<Vec<Task>, String> {
+    let token = settings
+        .todoist_token
+        .as_deref()
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .ok_or("tasks.todoist_token is not set")?;
+    let mut tasks = Vec::new();
+    let mut cursor: Option<String> = None;
+    for _ in 0..MAX_PAGES {
+        let mut request = client
+            .get(url)
+            .bearer_auth(token)
+            .query(&[("query", settings.todoist_filter.as_str()), ("limit", "200")]);
+        if let Some(cursor) = &cursor {
+            request = request.query(&[("cursor", cursor)]);
+        }
+        let response = request
+            .timeout(REQUEST_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to fetch Todoist tasks: {}", e))?;
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read Todoist tasks: {}", e))?;
+        if !status.is_success() {
+            return Err(format!("Todoist answered {}: {}", status, body.trim()));
+        }
+        let (page, next) = parse_todoist(&body, today)?;
+        tasks.extend(page);
+        cursor = next;
+        if cursor.is_none() {
+            break;
+        }
+    }
+    Ok(tasks)
+}
+
+fn tasks_file_path(file: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(file);
+    if path.is_absolute() {
+        Ok(path)
+    } else {
+        settings_manager::get_config_file_path(file)
+    }
+}
+
+/// The tasks file's tasks, read fresh
+fn read_tasks_file(file: &str, today: NaiveDate) -> Result<Vec<Task>, String> {
+    let path = tasks_file_path(file)?;
+    let content = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(parse_tasks_file(&content, today))
+}
+
+/// Todoist's tasks, from the cache unless the interval has passed or the
+/// settings changed; failures keep the last tasks next to the error
+async fn todoist_tasks(settings: &TasksSettings, today: NaiveDate) -> (Vec<Task>, Option<u64>, Option<String>) {
+    let mut cache = CACHE.lock().await;
+    let interval = Duration::from_secs(settings.refresh_interval * 60).max(MIN_INTERVAL);
+    let fresh = |cached: &Cached| {
+        let age = cached.at.elapsed();
+        age < MIN_INTERVAL || (age < interval && cached.settings == *settings)
+    };
+    if let Some(cached) = cache.as_ref().filter(|cached| fresh(cached)) {
+        return (cached.tasks.clone(), Some(cached.fetched_at), cached.error.clone());
+    }
+    let previous = cache
+        .take()
+        .filter(|cached| cached.settings.todoist_token == settings.todoist_token)
+        .map(|cached| cached.tasks);
+
+    let result = fetch_todoist_from(crate::http_client(), TODOIST_URL, settings, today).await;
+    health::record("tasks", &result);
+    let (tasks, error) = match result {
+        Ok(tasks) => (tasks, None),
+        Err(e) => {
+            warn!("Tasks: {}", e);
+            (previous.unwrap_or_default(), Some(e))
+        }
+    };
+    let fetched_at = chrono::Utc::now().timestamp_millis() as u64;
+    *cache = Some(Cached {
+        at: Instant::now(),
+        settings: settings.clone(),
+        tasks: tasks.clone(),
+        fetched_at,
+        error: error.clone(),
+    });
+    (tasks, Some(fetched_at), error)
+}
+
+/// Today's tasks from Todoist and the tasks file, whichever are set
+pub async fn report(settings: &TasksSettings) -> TasksReport {
+    if !settings.enabled {
+        return TasksReport::default();
+    }
+    let today = Local::now().date_naive();
+    let mut report = TasksReport::default();
+    if settings.todoist_token.as_deref().is_some_and(|token| !token.trim().is_empty()) {
+        let (tasks, fetched_at, error) = todoist_tasks(settings, today).await;
+        report.tasks = tasks;
+        report.fetched_at = fetched_at;
+        report.errors.extend(error);
+    }
+    if let Some(file) = settings.file.as_deref().map(str::trim).filter(|file| !file.is_empty()) {
+        match read_tasks_file(file, today) {
+            Ok(tasks) => report.tasks.extend(tasks),
+            Err(e) => report.errors.push(e),
+        }
+    }
+    report
+}
@@ -0,0 +1,98 @@
+//! CPU and GPU temperatures, read the way each OS allows. Every platform has
+//! a backend of its own, tried first:
+//!
+//! - Linux: `thermal_zone0`, with `vcgencmd` and hwmon for the GPU
+//! - Windows: WMI, from LibreHardwareMonitor or the ACPI thermal zones
+//! - macOS: the SMC, with the keys of Intel Macs and then Apple Silicon's
+//!
+//! When the backend has nothing, the CPU sensors sysinfo knows about are
+//! used instead. Readings are in °C; None when the machine doesn't report one.
+
+use sysinfo::Components;
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+use linux as platform;
+
+#[cfg(target_os = "windows")]
+mod windows;
+#[cfg(target_os = "windows")]
+use windows as platform;
+
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "macos")]
+use macos as platform;
+
+/// Sensor labels that belong to the CPU on the boards the frame runs on
+/// (Raspberry Pi `cpu_thermal`, Intel `coretemp`/`Package id`, AMD `k10temp`)
+const CPU_SENSORS: &[&str] = &["cpu", "package", "coretemp", "k10temp", "tctl", "soc"];
+
+/// Above this a sensor is misreporting rather than measuring
+const MAX_PLAUSIBLE_CELSIUS: f32 = 150.0;
+
+fn plausible(celsius: &f32) -> bool {
+    *celsius > 0.0 && *celsius < MAX_PLAUSIBLE_CELSIUS
+}
+
+/// Whether a sensor with this label or name measures the CPU
+fn is_cpu_sensor(label: &str) -> bool {
+    let label = label.to_lowercase();
+    CPU_SENSORS.iter().any(|sensor| label.contains(sensor))
+}
+
+/// The hottest CPU sensor sysinfo lists
+fn sensors_cpu_temperature() -> Option<f32> {
+    Components::new_with_refreshed_list()
+        .iter()
+        .filter(|component| is_cpu_sensor(component.label()))
+        .filter_map(|component| component.temperature())
+        .filter(plausible)
+        .reduce(f32::max)
+}
+
+/// CPU temperature in °C
+pub fn cpu_temperature() -> Option<f32> {
+    #[cfg(any(target_os = "linux", target_os = "windows", target_os = "macos"))]
+    if let Some(celsius) = platform::cpu_temperature().filter(plausible) {
+        return Some(celsius);
+    }
+
+    sensors_cpu_temperature()
+}
+
+/// GPU temperature in °C
+pub fn gpu_temperature() -> Option<f32> {
+    #[cfg(any(target_os = "linux", target_os = "windows", target_os = "macos"))]
+    {
+        platform::gpu_temperature().filter(plausible)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+    {
+        None
+    }
+}
+
+/// Temperature from `vcgencmd measure_temp`: "temp=48.3'C"
+pub fn parse_vcgencmd_temp(output: &str) -> Option<f32> {
+    output.trim().strip_prefix("temp=")?.trim_end_matches("'C").parse().ok()
+}
+
+/// Temperature from `MSAcpi_ThermalZoneTemperature.CurrentTemperature`, in
+/// tenths of a kelvin; boards that don't really measure report about 0 °C
+pub fn acpi_thermal_zone_celsius(tenths_kelvin: u32) -> Option<f32> {
+    let celsius = tenths_kelvin as f32 / 10.0 - 273.15;
+    (1.0..MAX_PLAUSIBLE_CELSIUS).contains(&celsius).then_some(celsius)
+}
+
+/// Value of an SMC temperature key by its data type: `sp78`, signed 7.8
+/// fixed point on Intel Macs, or `flt `, a little-endian float on Apple Silicon
+pub fn decode_smc_value(data_type: &[u8; 4], bytes: &[u8]) -> Option<f32> {
+    match data_type {
+        b"sp78" => Some(f32::from(i16::from_be_bytes([*bytes.first()?, *bytes.get(1)?])) / 256.0),
+        b"flt " => Some(f32::from_le_bytes(bytes.get(..4)?.try_into().ok()?)),
+        _ => None,
+    }
+}
@@ -0,0 +1,56 @@
+//! Linux: the thermal zones whose type names the CPU (`cpu-thermal` on a
+//! Pi), and for the GPU the Pi's `vcgencmd` or a graphics card's hwmon sensor
+
+use std::path::Path;
+
+use super::{is_cpu_sensor, parse_vcgencmd_temp};
+
+const THERMAL_ROOT: &str = "/sys/class/thermal";
+
+const HWMON_ROOT: &str = "/sys/class/hwmon";
+
+/// hwmon drivers of graphics cards with a sensor of their own
+const GPU_HWMON_NAMES: &[&str] = &["amdgpu", "radeon", "nouveau"];
+
+/// A sysfs temperature, which is in millidegrees
+fn read_millidegrees(path: &Path) -> Option<f32> {
+    let millidegrees: f32 = std::fs::read_to_string(path).ok()?.trim().parse().ok()?;
+    Some(millidegrees / 1000.0)
+}
+
+/// The hottest CPU thermal zone; x86 boards often only have `acpitz`, which
+/// measures the board, and are left to sysinfo's coretemp or k10temp
+pub(super) fn cpu_temperature() -> Option<f32> {
+    std::fs::read_dir(THERMAL_ROOT)
+        .ok()?
+        .flatten()
+        .filter(|entry| {
+            std::fs::read_to_string(entry.path().join("type")).is_ok_and(|zone_type| is_cpu_sensor(zone_type.trim()))
+        })
+        .filter_map(|entry| read_millidegrees(&entry.path().join("temp")))
+        .reduce(f32::max)
+}
+
+/// The Pi's VideoCore GPU, which can throttle while the CPU zone looks fine
+fn vcgencmd_temperature() -> Option<f32> {
+    let output = std::process::Command::new("vcgencmd").arg("measure_temp").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_vcgencmd_temp(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// The first graphics card reporting its temperature through hwmon
+fn hwmon_gpu_temperature() -> Option<f32> {
+    std::fs::read_dir(HWMON_ROOT).ok()?.flatten().find_map(|entry| {
+        let name = std::fs::read_to_string(entry.path().join("name")).ok()?;
+        if !GPU_HWMON_NAMES.contains(&name.trim()) {
+            return None;
+        }
+        read_millidegrees(&entry.path().join("temp1_input"))
+    })
+}
+
+pub(super) fn gpu_temperature() -> Option<f32> {
+    vcgencmd_temperature().or_else(hwmon_gpu_temperature)
+}
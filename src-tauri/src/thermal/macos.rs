@@ -0,0 +1,172 @@
+//! macOS: the System Management Controller, through IOKit's `AppleSMC`
+//! service. Intel Macs have the long-standing `TC0P`-style keys in `sp78`;
+//! Apple Silicon has none of them but per-cluster `Tp..` keys as floats, and
+//! which ones depends on the chip, so the known ones are tried in turn.
+
+use std::ffi::{c_char, c_void};
+
+use super::decode_smc_value;
+
+/// CPU proximity, die and core keys of Intel Macs
+const INTEL_CPU_KEYS: &[&str] = &["TC0P", "TC0D", "TC0E", "TC0F", "TC0H"];
+
+/// GPU keys of Intel Macs with a graphics card of their own
+const INTEL_GPU_KEYS: &[&str] = &["TG0P", "TG0D", "TG0H"];
+
+/// CPU cluster keys of the M1 and M2 generations onwards
+const APPLE_SILICON_CPU_KEYS: &[&str] = &[
+    "Tp01", "Tp05", "Tp09", "Tp0D", "Tp0H", "Tp0L", "Tp0P", "Tp0T", "Tp0X", "Tp0b", "Tp0f", "Tp0j", "Tp1h", "Tp1t",
+    "Tp1p", "Tp1l", "Te05", "Te0L", "Te0P", "Te0S",
+];
+
+const APPLE_SILICON_GPU_KEYS: &[&str] = &["Tg05", "Tg0D", "Tg0L", "Tg0T", "Tg0f", "Tg0j"];
+
+const KERN_SUCCESS: i32 = 0;
+
+/// `IOConnectCallStructMethod` selector of the SMC's user client
+const KERNEL_INDEX_SMC: u32 = 2;
+const SMC_CMD_READ_BYTES: u8 = 5;
+const SMC_CMD_READ_KEYINFO: u8 = 9;
+
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+#[allow(dead_code)]  // Laid out for the SMC; not every field is read
+struct SmcVersion {
+    major: u8,
+    minor: u8,
+    build: u8,
+    reserved: u8,
+    release: u16,
+}
+
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+#[allow(dead_code)]  // Laid out for the SMC; not every field is read
+struct SmcPLimitData {
+    version: u16,
+    length: u16,
+    cpu_p_limit: u32,
+    gpu_p_limit: u32,
+    mem_p_limit: u32,
+}
+
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+#[allow(dead_code)]  // Laid out for the SMC; not every field is read
+struct SmcKeyInfo {
+    data_size: u32,
+    data_type: u32,
+    data_attributes: u8,
+}
+
+/// `SMCKeyData_t`, what goes in and out of the SMC user client
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+#[allow(dead_code)]
+struct SmcKeyData {
+    key: u32,
+    vers: SmcVersion,
+    p_limit_data: SmcPLimitData,
+    key_info: SmcKeyInfo,
+    result: u8,
+    status: u8,
+    data8: u8,
+    data32: u32,
+    bytes: [u8; 32],
+}
+
+#[link(name = "IOKit", kind = "framework")]
+extern "C" {
+    fn IOServiceMatching(name: *const c_char) -> *mut c_void;
+    fn IOServiceGetMatchingService(main_port: u32, matching: *mut c_void) -> u32;
+    fn IOServiceOpen(service: u32, owning_task: u32, connect_type: u32, connect: *mut u32) -> i32;
+    fn IOServiceClose(connect: u32) -> i32;
+    fn IOObjectRelease(object: u32) -> i32;
+    fn IOConnectCallStructMethod(
+        connection: u32,
+        selector: u32,
+        input: *const c_void,
+        input_size: usize,
+        output: *mut c_void,
+        output_size: *mut usize,
+    ) -> i32;
+}
+
+extern "C" {
+    /// What the `mach_task_self()` macro reads
+    static mach_task_self_: u32;
+}
+
+/// An open connection to the SMC, closed on drop
+struct Smc(u32);
+
+impl Smc {
+    fn open() -> Option<Smc> {
+        // SAFETY: IOServiceGetMatchingService takes over the matching
+        // dictionary, and the service is released once opened
+        unsafe {
+            let matching = IOServiceMatching(c"AppleSMC".as_ptr());
+            if matching.is_null() {
+                return None;
+            }
+            let service = IOServiceGetMatchingService(0, matching);
+            if service == 0 {
+                return None;
+            }
+            let mut connection = 0;
+            let result = IOServiceOpen(service, mach_task_self_, 0, &mut connection);
+            IOObjectRelease(service);
+            (result == KERN_SUCCESS).then_some(Smc(connection))
+        }
+    }
+
+    fn call(&self, input: &SmcKeyData) -> Option<SmcKeyData> {
+        let mut output = SmcKeyData::default();
+        let mut output_size = std::mem::size_of::<SmcKeyData>();
+        // SAFETY: both buffers are `SmcKeyData`, the layout the SMC expects
+        let result = unsafe {
+            IOConnectCallStructMethod(
+                self.0,
+                KERNEL_INDEX_SMC,
+                (input as *const SmcKeyData).cast(),
+                std::mem::size_of::<SmcKeyData>(),
+                (&mut output as *mut SmcKeyData).cast(),
+                &mut output_size,
+            )
+        };
+        (result == KERN_SUCCESS && output.result == 0).then_some(output)
+    }
+
+    /// A temperature key's value; None when this Mac doesn't have the key
+    fn read(&self, key: &str) -> Option<f32> {
+        let key = u32::from_be_bytes(key.as_bytes().try_into().ok()?);
+        let info = self.call(&SmcKeyData { key, data8: SMC_CMD_READ_KEYINFO, ..SmcKeyData::default() })?.key_info;
+        let value = self.call(&SmcKeyData { key, key_info: info, data8: SMC_CMD_READ_BYTES, ..SmcKeyData::default() })?;
+        let size = (info.data_size as usize).min(value.bytes.len());
+        decode_smc_value(&info.data_type.to_be_bytes(), &value.bytes[..size])
+    }
+
+    /// The hottest of `keys` this Mac has
+    fn hottest(&self, keys: &[&str]) -> Option<f32> {
+        keys.iter().filter_map(|key| self.read(key)).filter(super::plausible).reduce(f32::max)
+    }
+}
+
+impl Drop for Smc {
+    fn drop(&mut self) {
+        // SAFETY: the connection was opened by `Smc::open` and is closed once
+        unsafe {
+            IOServiceClose(self.0);
+        }
+    }
+}
+
+pub(super) fn cpu_temperature() -> Option<f32> {
+    let smc = Smc::open()?;
+    smc.hottest(INTEL_CPU_KEYS).or_else(|| smc.hottest(APPLE_SILICON_CPU_KEYS))
+}
+
+pub(super) fn gpu_temperature() -> Option<f32> {
+    let smc = Smc::open()?;
+    smc.hottest(INTEL_GPU_KEYS).or_else(|| smc.hottest(APPLE_SILICON_GPU_KEYS))
+}
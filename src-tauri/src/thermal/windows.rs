@@ -0,0 +1,67 @@
+//! Windows: WMI. LibreHardwareMonitor's sensors when it runs, since they
+//! read the CPU itself, else the ACPI thermal zones, which need the app to
+//! run as administrator and aren't there on every board. sysinfo only sees
+//! the ACPI zone, labelled "Computer". The GPU only through LibreHardwareMonitor.
+
+use serde::Deserialize;
+
+use super::{acpi_thermal_zone_celsius, is_cpu_sensor};
+
+#[derive(Deserialize)]
+#[serde(rename = "Sensor", rename_all = "PascalCase")]
+struct LibreHardwareMonitorSensor {
+    name: String,
+    value: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "MSAcpi_ThermalZoneTemperature", rename_all = "PascalCase")]
+struct AcpiThermalZone {
+    current_temperature: u32,
+}
+
+/// Run `read` on a thread of its own: COM is set up per thread, and the
+/// webview's threads already have it their own way
+fn with_com(read: fn(wmi::COMLibrary) -> Option<f32>) -> Option<f32> {
+    std::thread::spawn(move || read(wmi::COMLibrary::new().ok()?)).join().ok().flatten()
+}
+
+/// The hottest of LibreHardwareMonitor's temperature sensors with a matching name
+fn lhm_temperature(com: wmi::COMLibrary, matches: fn(&str) -> bool) -> Option<f32> {
+    wmi::WMIConnection::with_namespace_path("root\\LibreHardwareMonitor", com)
+        .and_then(|wmi| {
+            wmi.raw_query::<LibreHardwareMonitorSensor>("SELECT Name, Value FROM Sensor WHERE SensorType = 'Temperature'")
+        })
+        .ok()?
+        .into_iter()
+        .filter(|sensor| matches(&sensor.name))
+        .map(|sensor| sensor.value)
+        .filter(|celsius| *celsius > 0.0)
+        .reduce(f32::max)
+}
+
+/// LibreHardwareMonitor names graphics card sensors "GPU Core", "GPU Hot Spot"
+/// and so on; AMD's "GPU SoC" would pass for the CPU otherwise
+fn is_gpu_sensor(name: &str) -> bool {
+    name.to_lowercase().starts_with("gpu")
+}
+
+pub(super) fn cpu_temperature() -> Option<f32> {
+    with_com(|com| {
+        lhm_temperature(com, |name| is_cpu_sensor(name) && !is_gpu_sensor(name)).or_else(|| {
+            wmi::WMIConnection::with_namespace_path("root\\WMI", com)
+                .and_then(|wmi| {
+                    wmi.raw_query::<AcpiThermalZone>("SELECT CurrentTemperature FROM MSAcpi_ThermalZoneTemperature")
+                })
+                .ok()?
+                .into_iter()
+                .filter_map(|zone| acpi_thermal_zone_celsius(zone.current_temperature))
+                .reduce(f32::max)
+        })
+    })
+}
+
+/// Only LibreHardwareMonitor reads the graphics card
+pub(super) fn gpu_temperature() -> Option<f32> {
+    with_com(|com| lhm_temperature(com, is_gpu_sensor))
+}
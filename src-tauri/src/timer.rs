@@ -0,0 +1,119 @@
+//! A kitchen timer counted down by the backend, so "10 minutes for the pasta"
+//! set from a phone keeps time even while the webview reloads. One timer runs
+//! at a time; starting another cancels it.
+//!
+//! `timer-tick` goes out every second with the seconds left, then
+//! `timer-finished` once; `timer-cancelled` when it's stopped early.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::info;
+
+pub use idleview_api::timer::{Timer, TimerRequest};
+use idleview_api::ServerEvent;
+
+use crate::http_server::AppState;
+
+/// Longest a timer may run, in seconds
+pub const MAX_DURATION: u32 = 24 * 60 * 60;
+
+/// Longest label a timer may have
+pub const MAX_LABEL_LENGTH: usize = 60;
+
+struct Running {
+    timer: Timer,
+    deadline: Instant,
+    task: JoinHandle<()>,
+}
+
+static RUNNING: Mutex<Option<Running>> = Mutex::new(None);
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Everything wrong with a timer request
+pub fn validate_timer(request: &TimerRequest) -> Vec<String> {
+    let mut problems = Vec::new();
+    if request.duration == 0 || request.duration > MAX_DURATION {
+        problems.push(format!("duration must be between 1 and {} seconds", MAX_DURATION));
+    }
+    if request.label.chars().count() > MAX_LABEL_LENGTH {
+        problems.push(format!("label must be at most {} characters", MAX_LABEL_LENGTH));
+    }
+    problems
+}
+
+/// Whole seconds shown for the time left: 9.2s left still reads 10
+pub fn remaining_seconds(left: Duration) -> u32 {
+    left.as_millis().div_ceil(1000) as u32
+}
+
+fn snapshot(running: &Running) -> Timer {
+    let left = running.deadline.saturating_duration_since(Instant::now());
+    Timer { remaining: remaining_seconds(left), ..running.timer.clone() }
+}
+
+/// The running timer, if any
+pub fn current() -> Option<Timer> {
+    RUNNING.lock().ok()?.as_ref().map(snapshot)
+}
+
+/// Tick every time the count goes down, then finish
+async fn count_down(state: AppState, timer: Timer, deadline: Instant) {
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        let remaining = remaining_seconds(left);
+        if remaining == 0 {
+            break;
+        }
+        state.publish_from(ServerEvent::TimerTick(Timer { remaining, ..timer.clone() }), "timer");
+        tokio::time::sleep(left.saturating_sub(Duration::from_secs(u64::from(remaining - 1)))).await;
+    }
+
+    // A timer replaced right as it ended was already reported cancelled
+    let Ok(mut running) = RUNNING.lock() else { return };
+    if !running.as_ref().is_some_and(|running| running.timer.id == timer.id) {
+        return;
+    }
+    *running = None;
+    drop(running);
+    info!("⏲️ Timer {:?} finished", timer.label);
+    state.publish_from(ServerEvent::TimerFinished(Timer { remaining: 0, ..timer }), "timer");
+}
+
+/// Start a timer, cancelling the one running
+pub fn start(state: &AppState, request: TimerRequest) -> Result<Timer, String> {
+    let mut running = RUNNING.lock().map_err(|e| format!("Failed to lock timer: {}", e))?;
+    if let Some(previous) = running.take() {
+        previous.task.abort();
+        state.publish_from(ServerEvent::TimerCancelled(snapshot(&previous)), "timer");
+    }
+
+    let length = Duration::from_secs(u64::from(request.duration));
+    let timer = Timer {
+        id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        label: request.label.trim().to_string(),
+        duration: request.duration,
+        remaining: request.duration,
+        ends_at: chrono::Utc::now().timestamp_millis() as u64 + u64::from(request.duration) * 1000,
+    };
+    let deadline = Instant::now() + length;
+    let task = tokio::spawn(count_down(state.clone(), timer.clone(), deadline));
+    info!("⏲️ Timer {:?} set for {}s", timer.label, timer.duration);
+    *running = Some(Running { timer: timer.clone(), deadline, task });
+    Ok(timer)
+}
+
+/// Stop the running timer, returning it as it was; None when none runs
+pub fn cancel(state: &AppState) -> Result<Option<Timer>, String> {
+    let previous = RUNNING.lock().map_err(|e| format!("Failed to lock timer: {}", e))?.take();
+    Ok(previous.map(|previous| {
+        previous.task.abort();
+        let timer = snapshot(&previous);
+        info!("⏲️ Timer {:?} cancelled with {}s left", timer.label, timer.remaining);
+        state.publish_from(ServerEvent::TimerCancelled(timer.clone()), "timer");
+        timer
+    }))
+}
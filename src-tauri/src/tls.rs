@@ -0,0 +1,61 @@
+//! HTTPS for the control server: a user-provided certificate, or a
+//! self-signed one generated once and kept in the config directory.
+
+use axum_server::tls_rustls::RustlsConfig;
+use std::path::PathBuf;
+
+use crate::auth;
+use crate::settings_manager::{self, TlsSettings};
+
+const CERT_FILE: &str = "tls_cert.pem";
+const KEY_FILE: &str = "tls_key.pem";
+
+/// Names the self-signed certificate is valid for
+fn self_signed_names() -> Vec<String> {
+    let mut names = vec!["localhost".to_string(), "idleview.local".to_string(), "127.0.0.1".to_string()];
+    if let Ok(ip) = local_ip_address::local_ip() {
+        names.push(ip.to_string());
+    }
+    names
+}
+
+/// A new self-signed certificate and private key, both PEM encoded
+pub fn generate_self_signed(names: Vec<String>) -> Result<(String, String), String> {
+    let certified = rcgen::generate_simple_self_signed(names)
+        .map_err(|e| format!("Failed to generate TLS certificate: {}", e))?;
+    Ok((certified.cert.pem(), certified.key_pair.serialize_pem()))
+}
+
+/// The self-signed certificate and key, generated on first use
+fn self_signed_paths() -> Result<(PathBuf, PathBuf), String> {
+    let cert_path = settings_manager::get_config_file_path(CERT_FILE)?;
+    let key_path = settings_manager::get_config_file_path(KEY_FILE)?;
+    if cert_path.exists() && key_path.exists() {
+        return Ok((cert_path, key_path));
+    }
+
+    let (cert, key) = generate_self_signed(self_signed_names())?;
+    auth::write_secret_file(KEY_FILE, &key)?;
+    settings_manager::write_file_atomic(&cert_path, cert.as_bytes())?;
+    Ok((cert_path, key_path))
+}
+
+/// Certificate and key files for the TLS settings
+pub fn certificate_paths(tls: &TlsSettings) -> Result<(PathBuf, PathBuf), String> {
+    match (&tls.cert_path, &tls.key_path) {
+        (Some(cert), Some(key)) => Ok((PathBuf::from(cert), PathBuf::from(key))),
+        (None, None) => self_signed_paths(),
+        _ => Err("A custom TLS certificate needs both cert_path and key_path".to_string()),
+    }
+}
+
+/// The rustls configuration to serve HTTPS with
+pub async fn rustls_config(tls: &TlsSettings) -> Result<RustlsConfig, String> {
+    // ring is the only crypto provider compiled in; installing twice is harmless
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    let (cert_path, key_path) = certificate_paths(tls)?;
+    RustlsConfig::from_pem_file(&cert_path, &key_path)
+        .await
+        .map_err(|e| format!("Failed to load TLS certificate {}: {}", cert_path.display(), e))
+}
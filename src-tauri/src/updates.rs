@@ -0,0 +1,106 @@
+//! Checking GitHub for a newer release, so frames that aren't managed by a
+//! package manager can tell their owner an update is out.
+
+use serde::Deserialize;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{info, warn};
+
+pub use idleview_api::system::UpdateInfo;
+use idleview_api::ServerEvent;
+
+use crate::http_server::AppState;
+
+pub const RELEASES_URL: &str = "https://api.github.com/repos/raduandreigorcea/Idleview/releases/latest";
+
+/// How often the background task checks
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+static LAST_CHECK: Mutex<Option<UpdateInfo>> = Mutex::new(None);
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    html_url: String,
+}
+
+/// Numeric parts of a version like "v1.2.3" or "1.2.3-beta.1", pre-release suffix dropped
+fn version_parts(version: &str) -> Vec<u64> {
+    version
+        .trim()
+        .trim_start_matches(['v', 'V'])
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+/// Whether `latest` is a later version than `current`
+pub fn is_newer(latest: &str, current: &str) -> bool {
+    let mut latest = version_parts(latest);
+    let mut current = version_parts(current);
+    let len = latest.len().max(current.len());
+    latest.resize(len, 0);
+    current.resize(len, 0);
+    latest > current
+}
+
+/// The latest release at a GitHub releases API URL, used directly by tests
+pub async fn check_for_update_from(client: &reqwest::Client, url: &str) -> Result<UpdateInfo, String> {
+    let release: Release = client
+        .get(url)
+        .header(reqwest::header::USER_AGENT, concat!("Idleview/", env!("CARGO_PKG_VERSION")))
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to check for updates: {}", e))?
+        .error_for_status()
+        .map_err(|e| format!("Failed to check for updates: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse release: {}", e))?;
+
+    let current = env!("CARGO_PKG_VERSION").to_string();
+    let latest = release.tag_name.trim_start_matches(['v', 'V']).to_string();
+    Ok(UpdateInfo {
+        update_available: is_newer(&latest, &current),
+        current,
+        latest,
+        url: release.html_url,
+        checked_at: chrono::Utc::now().timestamp_millis() as u64,
+    })
+}
+
+/// Result of the last successful check
+pub fn last_check() -> Option<UpdateInfo> {
+    LAST_CHECK.lock().ok()?.clone()
+}
+
+/// Check GitHub now, remember the result and tell the frame when an update is out
+pub async fn check_for_update(state: &AppState) -> Result<UpdateInfo, String> {
+    let update = check_for_update_from(crate::http_client(), RELEASES_URL).await?;
+    if let Ok(mut last) = LAST_CHECK.lock() {
+        *last = Some(update.clone());
+    }
+    if update.update_available {
+        info!("Update available: {} (running {})", update.latest, update.current);
+        state.publish_from(ServerEvent::UpdateAvailable(update.clone()), "updates");
+    }
+    Ok(update)
+}
+
+/// Check for updates once a day for the lifetime of the server
+pub fn spawn_checker(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = check_for_update(&state).await {
+                warn!("{}", e);
+            }
+        }
+    });
+}
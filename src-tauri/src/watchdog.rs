@@ -0,0 +1,182 @@
+//! Watchdog for the frame's webview. Every frame window sends a heartbeat
+//! every few seconds; when one goes quiet for `watchdog.timeout` seconds,
+//! because the renderer hung or crashed, the page is reloaded. If that
+//! doesn't bring it back the window is closed and opened again, and as a
+//! last resort the app restarts. Each step waits another timeout for a
+//! heartbeat before the next.
+//!
+//! Incidents are logged, kept for `GET /api/watchdog` and reported as the
+//! "watchdog" provider in the health summary until the frame recovers.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tracing::{info, warn};
+
+pub use idleview_api::system::{FrameHeartbeat, WatchdogAction, WatchdogIncident, WatchdogStatus};
+
+use crate::health;
+use crate::http_server::AppState;
+use crate::monitors;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Incidents kept for the API
+const MAX_INCIDENTS: usize = 20;
+
+/// Time for a destroyed window to be gone before one with its label opens
+const RECREATE_DELAY: Duration = Duration::from_secs(1);
+
+/// One frame window as the watchdog sees it
+struct Watch {
+    last_heartbeat: Instant,
+    last_heartbeat_ms: u64,
+    attempts: u32,
+    last_action: Option<Instant>,
+}
+
+static WINDOWS: Mutex<BTreeMap<String, Watch>> = Mutex::new(BTreeMap::new());
+
+static INCIDENTS: Mutex<VecDeque<WatchdogIncident>> = Mutex::new(VecDeque::new());
+
+fn now_ms() -> u64 {
+    chrono::Utc::now().timestamp_millis().max(0) as u64
+}
+
+/// What to do about a frame `silent_for` this long, `since_action` after the
+/// last step taken and with `attempts` steps taken so far; None to wait
+pub fn next_action(
+    silent_for: Duration,
+    since_action: Option<Duration>,
+    attempts: u32,
+    timeout: Duration,
+) -> Option<WatchdogAction> {
+    if silent_for < timeout || since_action.is_some_and(|since| since < timeout) {
+        return None;
+    }
+    Some(match attempts {
+        0 => WatchdogAction::Reload,
+        1 => WatchdogAction::Recreate,
+        _ => WatchdogAction::Restart,
+    })
+}
+
+/// A frame window is alive; windows are watched from their first heartbeat
+pub fn heartbeat(window: &str) {
+    let Ok(mut windows) = WINDOWS.lock() else { return };
+    let recovered = windows.get(window).is_some_and(|watch| watch.attempts > 0);
+    windows.insert(
+        window.to_string(),
+        Watch { last_heartbeat: Instant::now(), last_heartbeat_ms: now_ms(), attempts: 0, last_action: None },
+    );
+    drop(windows);
+    if recovered {
+        info!("🐕 Frame {} is responding again", window);
+        health::record::<()>("watchdog", &Ok(()));
+    }
+}
+
+/// Frame windows' last heartbeats and recent incidents
+pub fn status() -> WatchdogStatus {
+    let windows = WINDOWS
+        .lock()
+        .map(|windows| {
+            windows
+                .iter()
+                .map(|(window, watch)| FrameHeartbeat { window: window.clone(), last_heartbeat: watch.last_heartbeat_ms })
+                .collect()
+        })
+        .unwrap_or_default();
+    let incidents = INCIDENTS.lock().map(|incidents| incidents.iter().cloned().collect()).unwrap_or_default();
+    WatchdogStatus { windows, incidents }
+}
+
+fn record_incident(incident: WatchdogIncident) {
+    warn!(
+        "🐕 Frame {} sent no heartbeat for {}s; {:?}",
+        incident.window, incident.silent_for, incident.action
+    );
+    health::record::<()>(
+        "watchdog",
+        &Err(format!("Frame {} stopped responding for {}s", incident.window, incident.silent_for)),
+    );
+    if let Ok(mut incidents) = INCIDENTS.lock() {
+        if incidents.len() == MAX_INCIDENTS {
+            incidents.pop_front();
+        }
+        incidents.push_back(incident);
+    }
+}
+
+/// Close the window and open a new one with its label on the same monitor
+async fn recreate(app: &AppHandle, label: &str) -> Result<(), String> {
+    let window = app.get_webview_window(label);
+    let monitor = window.as_ref().and_then(|window| window.current_monitor().ok().flatten());
+    if let Some(window) = window {
+        window.destroy().map_err(|e| format!("Failed to close frame {}: {}", label, e))?;
+        tokio::time::sleep(RECREATE_DELAY).await;
+    }
+    monitors::open_frame(app, label, monitor.as_ref())
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open frame {}: {}", label, e))
+}
+
+async fn recover(app: &AppHandle, label: &str, action: WatchdogAction) -> Result<(), String> {
+    match action {
+        WatchdogAction::Reload => match app.get_webview_window(label) {
+            Some(window) => window.reload().map_err(|e| format!("Failed to reload frame {}: {}", label, e)),
+            None => recreate(app, label).await,
+        },
+        WatchdogAction::Recreate => recreate(app, label).await,
+        WatchdogAction::Restart => app.restart(),
+    }
+}
+
+/// Check the frames' heartbeats for the lifetime of the server
+pub fn spawn_watchdog(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let settings = match state.settings_manager.get() {
+                Ok(settings) => settings.watchdog,
+                Err(e) => {
+                    warn!("Watchdog: failed to read settings: {}", e);
+                    continue;
+                }
+            };
+            if !settings.enabled {
+                continue;
+            }
+
+            let timeout = Duration::from_secs(settings.timeout);
+            let due: Vec<(String, WatchdogAction, Duration)> = match WINDOWS.lock() {
+                Ok(mut windows) => windows
+                    .iter_mut()
+                    .filter_map(|(label, watch)| {
+                        let silent_for = watch.last_heartbeat.elapsed();
+                        let since_action = watch.last_action.map(|at| at.elapsed());
+                        let action = next_action(silent_for, since_action, watch.attempts, timeout)?;
+                        watch.attempts += 1;
+                        watch.last_action = Some(Instant::now());
+                        Some((label.clone(), action, silent_for))
+                    })
+                    .collect(),
+                Err(_) => continue,
+            };
+
+            for (label, action, silent_for) in due {
+                record_incident(WatchdogIncident {
+                    window: label.clone(),
+                    action,
+                    silent_for: silent_for.as_secs(),
+                    at: now_ms(),
+                });
+                if let Err(e) = recover(&state.app_handle, &label, action).await {
+                    warn!("Watchdog: {}", e);
+                }
+            }
+        }
+    });
+}
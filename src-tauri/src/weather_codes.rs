@@ -0,0 +1,122 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+/// Languages with translated weather labels; anything else falls back to English
+pub const SUPPORTED_LANGUAGES: &[&str] = &["en", "ro"];
+
+/// WMO weather interpretation codes as returned by Open-Meteo:
+/// (code, icon, English label, Romanian label)
+const WMO_CODES: &[(u8, &str, &str, &str)] = &[
+    (0, "clear", "Clear sky", "Cer senin"),
+    (1, "mostly-clear", "Mainly clear", "Predominant senin"),
+    (2, "partly-cloudy", "Partly cloudy", "Parțial noros"),
+    (3, "cloudy", "Overcast", "Înnorat"),
+    (45, "fog", "Fog", "Ceață"),
+    (48, "fog", "Depositing rime fog", "Ceață cu chiciură"),
+    (51, "drizzle", "Light drizzle", "Burniță slabă"),
+    (53, "drizzle", "Drizzle", "Burniță"),
+    (55, "drizzle", "Dense drizzle", "Burniță densă"),
+    (56, "freezing-rain", "Light freezing drizzle", "Burniță înghețată slabă"),
+    (57, "freezing-rain", "Freezing drizzle", "Burniță înghețată"),
+    (61, "rain", "Light rain", "Ploaie slabă"),
+    (63, "rain", "Rain", "Ploaie"),
+    (65, "rain", "Heavy rain", "Ploaie torențială"),
+    (66, "freezing-rain", "Light freezing rain", "Ploaie înghețată slabă"),
+    (67, "freezing-rain", "Freezing rain", "Ploaie înghețată"),
+    (71, "snow", "Light snow", "Ninsoare slabă"),
+    (73, "snow", "Snow", "Ninsoare"),
+    (75, "snow", "Heavy snow", "Ninsoare abundentă"),
+    (77, "snow", "Snow grains", "Grăunțe de zăpadă"),
+    (80, "showers", "Light showers", "Averse slabe"),
+    (81, "showers", "Showers", "Averse"),
+    (82, "showers", "Violent showers", "Averse violente"),
+    (85, "snow", "Light snow showers", "Averse slabe de ninsoare"),
+    (86, "snow", "Snow showers", "Averse de ninsoare"),
+    (95, "thunderstorm", "Thunderstorm", "Furtună"),
+    (96, "thunderstorm", "Thunderstorm with hail", "Furtună cu grindină"),
+    (99, "thunderstorm", "Severe thunderstorm with hail", "Furtună puternică cu grindină"),
+];
+
+/// Other fixed weather labels shown on screen: (English, Romanian)
+const LABELS: &[(&str, &str)] = &[
+    ("Snow", "Ninsoare"),
+    ("Rain", "Ploaie"),
+    ("Precip", "Precip."),
+    ("Clear", "Senin"),
+    ("Strong wind", "Vânt puternic"),
+    ("Heavy rain", "Ploaie torențială"),
+    ("Frost", "Îngheț"),
+];
+
+#[derive(Debug, Serialize, JsonSchema, Clone)]
+pub struct WeatherCodeInfo {
+    pub code: u8,
+    pub icon: String,
+    pub label: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct WeatherLegend {
+    pub language: String,
+    pub codes: Vec<WeatherCodeInfo>,
+}
+
+/// Reduce a BCP-47 tag ("ro-RO") to a supported language ("ro"), defaulting to English
+pub fn resolve_language(tag: &str) -> &'static str {
+    let primary = tag.split(['-', '_']).next().unwrap_or("").to_lowercase();
+    SUPPORTED_LANGUAGES
+        .iter()
+        .find(|lang| **lang == primary)
+        .copied()
+        .unwrap_or("en")
+}
+
+fn label_for(entry: &(u8, &str, &str, &str), language: &str) -> String {
+    match language {
+        "ro" => entry.3,
+        _ => entry.2,
+    }
+    .to_string()
+}
+
+/// Translate one of the fixed English weather labels; other text is returned as is
+pub fn localize_label(label: &str, language: &str) -> String {
+    match resolve_language(language) {
+        "ro" => LABELS
+            .iter()
+            .find(|(english, _)| *english == label)
+            .map(|(_, romanian)| *romanian)
+            .unwrap_or(label),
+        _ => label,
+    }
+    .to_string()
+}
+
+/// Describe a single WMO code, or None for codes outside the table
+pub fn describe_weather_code_impl(code: u8, language: &str) -> Option<WeatherCodeInfo> {
+    let language = resolve_language(language);
+    WMO_CODES
+        .iter()
+        .find(|entry| entry.0 == code)
+        .map(|entry| WeatherCodeInfo {
+            code: entry.0,
+            icon: entry.1.to_string(),
+            label: label_for(entry, language),
+        })
+}
+
+/// Full code → icon/label mapping in the requested language
+pub fn get_weather_legend_impl(language: &str) -> WeatherLegend {
+    let language = resolve_language(language);
+    WeatherLegend {
+        language: language.to_string(),
+        codes: WMO_CODES
+            .iter()
+            .map(|entry| WeatherCodeInfo {
+                code: entry.0,
+                icon: entry.1.to_string(),
+                label: label_for(entry, language),
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,128 @@
+//! Outbound webhooks: every event a webhook asks for is POSTed to its URL as
+//! the same envelope SSE clients get, signed when the webhook has a secret.
+//!
+//! Deliveries that fail on the network or with a 5xx/429 are retried a few
+//! times with growing pauses; other 4xx answers are final.
+
+use reqwest::StatusCode;
+use ring::hmac;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::http_server::AppState;
+use crate::settings_manager::Webhook;
+
+/// Event types a webhook can subscribe to
+pub const EVENTS: &[&str] = &[
+    "photo-updated",
+    "settings-updated",
+    "location-changed",
+    "weather-updated",
+    "weather-alert",
+    "display-power-changed",
+    "cpu-temperature-high",
+    "disk-space-low",
+    "battery-low",
+    "connectivity-changed",
+    "now-playing-changed",
+    "alarm-fired",
+    "timer-finished",
+    "night-mode-changed",
+    "display-sleep-changed",
+    "presence-changed",
+    "show-message",
+    "update-available",
+    "pairing-complete",
+];
+
+/// Header carrying `sha256=<hex HMAC of the body>` when the webhook has a secret
+pub const SIGNATURE_HEADER: &str = "X-Idleview-Signature";
+
+/// Header carrying the event type, so receivers can route without parsing the body
+pub const EVENT_HEADER: &str = "X-Idleview-Event";
+
+/// Pauses before each retry of a failed delivery
+const RETRY_DELAYS: [Duration; 3] = [Duration::from_secs(2), Duration::from_secs(10), Duration::from_secs(30)];
+
+/// How long a webhook receiver gets to answer
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Whether the webhook asked for this event type
+pub fn wants(webhook: &Webhook, event_type: &str) -> bool {
+    webhook.events.is_empty() || webhook.events.iter().any(|wanted| wanted == event_type)
+}
+
+/// Signature of a body, as sent in the signature header
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let tag = hmac::sign(&key, body);
+    let hex: String = tag.as_ref().iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256={}", hex)
+}
+
+/// Whether a failed delivery is worth trying again; `None` is a network error
+pub fn should_retry(status: Option<StatusCode>) -> bool {
+    status.is_none_or(|status| status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS)
+}
+
+/// POST one event to one webhook, retrying on failures that may pass
+async fn deliver(http: reqwest::Client, webhook: Webhook, event_type: String, body: String) {
+    let signature = webhook.secret.as_deref().map(|secret| sign(secret, body.as_bytes()));
+    let mut delays = RETRY_DELAYS.iter();
+    loop {
+        let mut request = http
+            .post(&webhook.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, &event_type)
+            .body(body.clone());
+        if let Some(signature) = &signature {
+            request = request.header(SIGNATURE_HEADER, signature);
+        }
+
+        let (status, error) = match request.send().await {
+            Ok(response) if response.status().is_success() => return,
+            Ok(response) => (Some(response.status()), format!("answered {}", response.status())),
+            Err(e) => (None, e.to_string()),
+        };
+        match delays.next() {
+            Some(delay) if should_retry(status) => tokio::time::sleep(*delay).await,
+            _ => {
+                warn!("Webhooks: failed to send {} to {}: {}", event_type, webhook.url, error);
+                return;
+            }
+        }
+    }
+}
+
+/// POST events to the configured webhooks for the lifetime of the server
+pub fn spawn_dispatcher(state: AppState) {
+    let http = match reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build() {
+        Ok(http) => http,
+        Err(e) => {
+            warn!("Webhooks: failed to create HTTP client: {}", e);
+            return;
+        }
+    };
+    let subscription = state.event_hub.subscribe("webhook");
+
+    tokio::spawn(async move {
+        loop {
+            let event_data = subscription.recv().await;
+            let Ok(settings) = state.settings_manager.get() else {
+                continue;
+            };
+            if settings.webhooks.list.is_empty() {
+                continue;
+            }
+            let event_type = serde_json::from_str::<serde_json::Value>(&event_data)
+                .ok()
+                .and_then(|envelope| envelope["type"].as_str().map(str::to_string))
+                .unwrap_or_default();
+
+            // Each delivery retries on its own, so a dead receiver doesn't hold up the rest
+            for webhook in settings.webhooks.list.into_iter().filter(|webhook| wants(webhook, &event_type)) {
+                tokio::spawn(deliver(http.clone(), webhook, event_type.clone(), event_data.to_string()));
+            }
+        }
+    });
+}
@@ -0,0 +1,82 @@
+//! Coalesces bursts of file writes.
+//!
+//! The first write after a quiet period runs straight away. Writes submitted
+//! within `interval` of it wait, and only the newest of them runs once the
+//! interval is up, so a slider dragged across the control panel costs two
+//! disk writes instead of one per tick.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+type WriteJob = Box<dyn FnOnce() -> Result<(), String> + Send>;
+
+#[derive(Default)]
+struct WriteState {
+    pending: Option<WriteJob>,
+    last_write: Option<Instant>,
+    flush_scheduled: bool,
+}
+
+#[derive(Clone)]
+pub struct WriteThrottle {
+    state: Arc<Mutex<WriteState>>,
+    interval: Duration,
+}
+
+impl WriteThrottle {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(WriteState::default())),
+            interval,
+        }
+    }
+
+    /// Run `write` now if the last write was long enough ago, otherwise
+    /// replace any waiting write with it. Only immediate writes report errors.
+    pub fn submit(&self, write: impl FnOnce() -> Result<(), String> + Send + 'static) -> Result<(), String> {
+        // The lock is held while writing so writes always land in order
+        let mut state = self.state
+            .lock()
+            .map_err(|e| format!("Failed to lock write state: {}", e))?;
+
+        let since_last = state.last_write.map(|last| last.elapsed());
+        if !state.flush_scheduled && since_last.is_none_or(|elapsed| elapsed >= self.interval) {
+            state.last_write = Some(Instant::now());
+            return write();
+        }
+
+        state.pending = Some(Box::new(write));
+        if !state.flush_scheduled {
+            state.flush_scheduled = true;
+            let wait = self.interval.saturating_sub(since_last.unwrap_or_default());
+            let throttle = self.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(wait);
+                throttle.flush();
+            });
+        }
+        Ok(())
+    }
+
+    /// Whether a write is waiting for the interval to pass
+    pub fn is_pending(&self) -> bool {
+        self.state
+            .lock()
+            .map(|state| state.pending.is_some())
+            .unwrap_or(false)
+    }
+
+    /// Run the waiting write, if any, right away
+    pub fn flush(&self) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        state.flush_scheduled = false;
+        if let Some(write) = state.pending.take() {
+            state.last_write = Some(Instant::now());
+            if let Err(e) = write() {
+                eprintln!("Deferred write failed: {}", e);
+            }
+        }
+    }
+}
@@ -9,13 +9,21 @@ let prefetchedPhoto = null;
 let userSettings = null;
 let lastCacheValid = null;
 let lastPhotoFetchError = null;
+let followingLead = false;
 let timeInterval = null;
 let timeTimeout = null;
+let weatherInterval = null;
 let lastTimeHtml = null;
 let lastDateHtml = null;
 let lastDateKey = null;
 let sunriseSunsetTimeFormat = null;
 let sunriseSunsetIs12h = false;
+let headlineIndex = 0;
+let nowPlaying = null;
+let displayAsleep = false;  // Sleep hours: the screen is off, so skip polling
+let online = true;  // From the backend's connectivity checks; offline, nothing is retried
+let systemWidgets = { cpu_temp: false, cpu_usage: false, memory: false, wifi: false, battery: false };
+let lowBatteryWarning = 15;  // Percent, 0 = no warning
 
 // Simple element setters
 const setText = (id, value) => {
@@ -36,6 +44,11 @@ function updateWeatherDisplay(weather) {
     setText('wind', `${Math.round(weather.wind_speed)} ${weather.wind_speed_label}`);
     setText('cloudiness', `${weather.cloudcover}%`);
 
+    const alerts = weather.alerts || [];
+    const labelEl = document.querySelector('.current-weather-label');
+    const label = alerts.length ? `⚠ ${alerts.join(' · ')}` : 'Current Weather';
+    if (labelEl && labelEl.textContent !== label) labelEl.textContent = label;
+
     invoke('get_precipitation_display', { weather }).then(precip => {
         setText('precipitation', precip.value);
         
@@ -64,13 +77,31 @@ function updateWeatherDisplay(weather) {
     
     setText('sunrise', sunriseText);
     setText('sunset', sunsetText);
+    const hadWeather = !!currentWeather;
     currentWeather = weather;
+    if (!hadWeather) applyDisplaySettings();
+    updateGreeting();
 }
 
-// Retry helper with exponential backoff
+// "Good morning — cold and clear, 2°C" above the clock, composed in Rust
+async function updateGreeting() {
+    const greetingEl = document.getElementById('greeting');
+    const enabled = !!userSettings?.display?.greeting?.enabled;
+    greetingEl.classList.toggle('hidden', !enabled);
+    if (!enabled) return;
+    try {
+        greetingEl.textContent = await invoke('get_greeting');
+    } catch (error) {
+        console.error('Failed to compose greeting:', error);
+    }
+}
+
+// Retry helper with exponential backoff; gives up at once while offline,
+// since the connectivity-changed listener catches up when the connection is back
 async function retryWithBackoff(fn, maxRetries = 5, baseDelayMs = 2000) {
     let lastError;
     for (let attempt = 0; attempt < maxRetries; attempt++) {
+        if (!online) throw lastError ?? new Error('Offline');
         try {
             return await fn();
         } catch (error) {
@@ -83,14 +114,19 @@ async function retryWithBackoff(fn, maxRetries = 5, baseDelayMs = 2000) {
     throw lastError;
 }
 
+// Display location and load its weather
+async function applyLocation(location) {
+    const locationText = location.city || `${location.latitude.toFixed(2)}°, ${location.longitude.toFixed(2)}°`;
+    setText('location', locationText);
+    window.userLocation = location;
+    await updateWeather(location);
+}
+
 // Fetch and display location
 async function fetchLocation() {
     try {
         const location = await retryWithBackoff(() => invoke('get_location'));
-        const locationText = location.city || `${location.latitude.toFixed(2)}°, ${location.longitude.toFixed(2)}°`;
-        setText('location', locationText);
-        window.userLocation = location;
-        await updateWeather(location);
+        await applyLocation(location);
     } catch (error) {
         console.error('Failed to fetch location after retries:', error);
         setText('location', 'Unknown');
@@ -99,15 +135,31 @@ async function fetchLocation() {
     }
 }
 
+// Switch to the next saved location when rotation is enabled
+async function checkLocationRotation() {
+    if (!userSettings?.location?.rotate || displayAsleep) return;
+
+    try {
+        const location = await invoke('get_location');
+        const current = window.userLocation;
+        if (!current || location.latitude !== current.latitude || location.longitude !== current.longitude) {
+            await applyLocation(location);
+        }
+    } catch (error) {
+        console.error('Failed to rotate location:', error);
+    }
+}
+
 // Update weather data
 async function updateWeather(location) {
     try {
         const weather = await retryWithBackoff(() => invoke('get_weather', {
             latitude: location.latitude,
-            longitude: location.longitude
+            longitude: location.longitude,
+            elevation: location.elevation ?? null
         }));
         updateWeatherDisplay(weather);
-        await fetchUnsplashPhoto();
+        if (!followingLead) await fetchUnsplashPhoto();
     } catch (error) {
         console.error('Failed to fetch weather after retries:', error);
         // Schedule another attempt in 30 seconds
@@ -149,6 +201,7 @@ async function updateTimeAndDate() {
     } catch (error) {
         console.error('Failed to update time:', error);
     }
+    updateGreeting();
 }
 
 function updateTimeFormatCache() {
@@ -161,7 +214,8 @@ function updateTimeFormatCache() {
 // Load and apply user settings
 async function loadSettings() {
     try {
-        userSettings = await invoke('get_settings');
+        // With this monitor's overrides when the frame spans several
+        userSettings = await invoke('get_window_settings');
         console.log('Settings loaded:', userSettings);
         updateTimeFormatCache();
         applyDisplaySettings();
@@ -184,6 +238,10 @@ async function reloadSettings() {
         await updateWeather(window.userLocation);
     }
     await updateTimeAndDate();
+    startWeatherPolling();
+    updateQuote();
+    updateCrypto();
+    renderNowPlaying();
     console.log('✅ Settings reloaded and UI updated!');
 }
 
@@ -206,13 +264,40 @@ function startTimeTicker() {
     }, Math.max(0, msUntilNextMinute));
 }
 
-// Apply display settings
-function applyDisplaySettings() {
+// Refresh weather on the interval from the weather settings
+function startWeatherPolling() {
+    if (weatherInterval) {
+        clearInterval(weatherInterval);
+    }
+    const minutes = userSettings?.weather?.refresh_interval ?? 15;
+    weatherInterval = setInterval(() => {
+        if (window.userLocation && !displayAsleep) {
+            updateWeather(window.userLocation);
+        }
+    }, minutes * 60 * 1000);
+}
+
+// Apply display settings, with widget visibility resolved by the backend
+async function applyDisplaySettings() {
     if (!userSettings) return;
     
-    const showSunriseSunset = userSettings.display.show_sunrise_sunset !== false;
-    const showPrecipCloud = userSettings.display.show_precipitation_cloudiness !== false;
-    const showHumidityWind = userSettings.display.show_humidity_wind !== false;
+    let widgets = {};
+    try {
+        const state = await invoke('get_display_state', { weatherAvailable: !!currentWeather });
+        state.widgets.forEach(widget => { widgets[widget.id] = widget.visible; });
+    } catch (error) {
+        console.error('Failed to resolve display state:', error);
+        widgets = {
+            weather: true,
+            sunrise_sunset: userSettings.display.show_sunrise_sunset !== false,
+            precipitation_cloudiness: userSettings.display.show_precipitation_cloudiness !== false,
+            humidity_wind: userSettings.display.show_humidity_wind !== false
+        };
+    }
+    
+    const showSunriseSunset = !!widgets.sunrise_sunset;
+    const showPrecipCloud = !!widgets.precipitation_cloudiness;
+    const showHumidityWind = !!widgets.humidity_wind;
     const anyMetricsVisible = showSunriseSunset || showPrecipCloud || showHumidityWind;
     
     const metricsMap = {
@@ -233,20 +318,39 @@ function applyDisplaySettings() {
     const mainWeatherStatus = document.querySelector('.main-weather-status');
     
     if (metricsGrid) metricsGrid.style.display = anyMetricsVisible ? 'grid' : 'none';
-    if (mainWeatherStatus) mainWeatherStatus.classList.toggle('no-metrics', !anyMetricsVisible);
+    if (mainWeatherStatus) {
+        mainWeatherStatus.classList.toggle('no-metrics', !anyMetricsVisible);
+        mainWeatherStatus.style.display = widgets.weather ? '' : 'none';
+    }
     
+    systemWidgets = {
+        cpu_temp: !!widgets.cpu_temp,
+        cpu_usage: !!widgets.cpu_usage,
+        memory: !!widgets.memory,
+        wifi: !!widgets.wifi,
+        battery: !!widgets.battery,
+    };
+    updateSystemStats();
+    lowBatteryWarning = userSettings.display.low_battery_warning ?? 15;
+    updateBatteryWarning();
+
     // Debug panel position
     const debugEl = document.getElementById('debug');
     if (debugEl) {
         const position = userSettings.display.debug_position || 'right';
         debugEl.dataset.position = position;
     }
+
+    updateGreeting();
 }
 
-// Cache helpers
+// Cache helpers; each monitor's frame keeps its own photo
+const windowLabel = window.__TAURI__.window.getCurrentWindow().label;
+const photoCacheKey = windowLabel === 'main' ? 'unsplash_photo_cache' : `unsplash_photo_cache_${windowLabel}`;
+
 function getCachedPhoto() {
     try {
-        const cachedData = localStorage.getItem('unsplash_photo_cache');
+        const cachedData = localStorage.getItem(photoCacheKey);
         return cachedData ? JSON.parse(cachedData) : null;
     } catch (error) {
         return null;
@@ -254,7 +358,7 @@ function getCachedPhoto() {
 }
 
 function cachePhoto(photo, query) {
-    localStorage.setItem('unsplash_photo_cache', JSON.stringify({
+    localStorage.setItem(photoCacheKey, JSON.stringify({
         photo, query, timestamp: Date.now()
     }));
 }
@@ -272,8 +376,7 @@ function buildPhotoQueryParams() {
         rain: currentWeather.rain,
         snowfall: currentWeather.snowfall,
         sunriseIso: currentWeather.sunrise,
-        sunsetIso: currentWeather.sunset,
-        enableFestive: userSettings?.photos?.enable_festive_queries ?? true
+        sunsetIso: currentWeather.sunset
     };
 }
 
@@ -286,17 +389,36 @@ async function fetchPhotoWithQuery(query) {
     });
 }
 
+// Report download throughput so the backend can switch to degraded mode on slow links
+function reportPhotoDownload(url) {
+    const entry = performance.getEntriesByName(url).pop();
+    // transferSize is 0 for cache hits and when the CDN hides timing details
+    if (!entry || !entry.transferSize || entry.duration <= 0) return;
+    
+    invoke('report_photo_download', {
+        bytes: entry.transferSize,
+        durationMs: Math.round(entry.duration)
+    }).catch(err => console.error('Failed to report photo download:', err));
+}
+
 // Display photo
 async function displayPhoto(photo, timestamp = null, query = null) {
     currentPhotoUrl = photo.url;
     
     // Preload image
     const img = new Image();
-    await new Promise((resolve, reject) => {
+    const loaded = await new Promise((resolve, reject) => {
         img.onload = () => img.decode ? img.decode().then(resolve).catch(resolve) : resolve();
         img.onerror = reject;
         img.src = photo.url;
-    }).catch(err => console.error('Failed to load photo:', err));
+    }).then(() => true).catch(err => {
+        console.error('Failed to load photo:', err);
+        return false;
+    });
+    
+    if (loaded) {
+        reportPhotoDownload(photo.url);
+    }
     
     // Apply background
     document.body.style.backgroundImage = `url('${photo.url}')`;
@@ -325,10 +447,14 @@ async function displayPhoto(photo, timestamp = null, query = null) {
     }
 
     // Update HTTP API (fire-and-forget)
-    fetch('http://localhost:8737/api/photo/current', {
-        method: 'POST',
-        headers: { 'Content-Type': 'application/json' },
-        body: JSON.stringify({ url: photo.url, author: photo.author, author_url: photo.author_url })
+    invoke('set_current_photo', {
+        photo: {
+            url: photo.url,
+            author: photo.author,
+            author_url: photo.author_url,
+            query: query ?? undefined,
+            fetched_at: timestamp ?? undefined
+        }
     }).catch(() => {});
 
     // Debug display
@@ -342,6 +468,7 @@ async function displayPhoto(photo, timestamp = null, query = null) {
             const renderDebug = async () => {
                 try {
                     const cached = getCachedPhoto();
+                    const apiToken = await invoke('get_api_token').catch(() => 'unavailable');
                     const debugInfo = await invoke('get_debug_info', {
                         cacheTimestamp: cached?.timestamp,
                         query: cached?.query,
@@ -367,6 +494,9 @@ async function displayPhoto(photo, timestamp = null, query = null) {
                         <div>Time: ${debugInfo.time_of_day} (${debugInfo.time_source})</div>
                         <div>Season: ${debugInfo.season}</div>
                         <div>API Key: ${debugInfo.api_key_status} (${debugInfo.api_key_source})</div>
+                        <div>Network: ${debugInfo.network_mode}</div>
+                        <div>Clock: ${debugInfo.clock}</div>
+                        <div>HTTP API token: ${apiToken}</div>
                         <div>Cache valid: ${lastCacheValid === null ? 'N/A' : lastCacheValid ? 'Yes' : 'No'}</div>
                         <div>Next refresh: ${nextRefreshDisplay}</div>
                         <div style="margin-top:8px; border-top:1px dashed currentColor; padding-top:8px;">
@@ -395,6 +525,12 @@ async function fetchUnsplashPhoto(forceRefresh = false) {
     try {
         const cached = getCachedPhoto();
         
+        // Offline: keep the cached photo, however old, until the connection is back
+        if (!online && cached) {
+            if (!currentPhotoUrl) await displayPhoto(cached.photo, cached.timestamp, cached.query);
+            return;
+        }
+
         if (!forceRefresh && cached) {
             lastCacheValid = await invoke('is_cache_valid', { cacheTimestamp: cached.timestamp });
             if (lastCacheValid) {
@@ -459,7 +595,7 @@ async function prefetchNextPhoto() {
 // Check if photo needs refresh
 async function checkPhotoContext() {
     const cached = getCachedPhoto();
-    if (!cached) return;
+    if (!cached || followingLead || displayAsleep || !online) return;
     
     try {
         const cacheAge = Date.now() - cached.timestamp;
@@ -484,6 +620,22 @@ async function checkPhotoContext() {
 (async function init() {
     await loadSettings();
     
+    // Connectivity: stop retrying while offline and catch up once the connection is back
+    online = (await invoke('get_connectivity')).online;
+    await window.__TAURI__.event.listen('connectivity-changed', async (event) => {
+        const wasOnline = online;
+        online = event.payload.payload.online;
+        console.log(online ? '🌐 Back online' : '🌐 Offline, keeping the cached photo');
+        if (online && !wasOnline) {
+            if (window.userLocation) {
+                await updateWeather(window.userLocation);
+            } else {
+                await fetchLocation();
+            }
+            await checkPhotoContext();
+        }
+    });
+
     // Show cached photo immediately
     const cached = getCachedPhoto();
     if (cached) {
@@ -499,26 +651,403 @@ async function checkPhotoContext() {
     await fetchLocation();
     
     // Periodic weather refresh
-    setInterval(() => {
-        if (window.userLocation) {
-            updateWeather(window.userLocation);
-        }
-    }, 15 * 60 * 1000);
+    startWeatherPolling();
+
+    // Saved location rotation check
+    setInterval(checkLocationRotation, 60 * 1000);
 
     // Photo refresh check
     checkPhotoContext();
     setInterval(checkPhotoContext, 5 * 60 * 1000);
     
+    // Show the pairing PIN requested from the control panel until it's used or expires
+    let pairingTimeout = null;
+    const pairingEl = document.getElementById('pairing-pin');
+    const hidePairingPin = () => {
+        if (pairingTimeout) clearTimeout(pairingTimeout);
+        pairingEl.classList.add('hidden');
+    };
+    await window.__TAURI__.event.listen('pairing-pin', (event) => {
+        const pairing = event.payload.payload;
+        pairingEl.innerHTML = `<div>Pairing PIN</div><div class="pin">${pairing.pin}</div>`;
+        pairingEl.classList.remove('hidden');
+        if (pairingTimeout) clearTimeout(pairingTimeout);
+        pairingTimeout = setTimeout(hidePairingPin, pairing.expires_in * 1000);
+    });
+    await window.__TAURI__.event.listen('pairing-complete', hidePairingPin);
+
+    // Messages sent from the control panel or automations; the backend queues them
+    let messageTimeout = null;
+    const messageEl = document.getElementById('message');
+    await window.__TAURI__.event.listen('show-message', (event) => {
+        const message = event.payload.payload;
+        messageEl.textContent = message.text;
+        messageEl.dataset.priority = message.priority;
+        messageEl.classList.remove('hidden');
+        if (message.sound) playChime();
+        if (messageTimeout) clearTimeout(messageTimeout);
+        messageTimeout = setTimeout(() => messageEl.classList.add('hidden'), message.duration * 1000);
+    });
+
+    // Alarms fire from the backend's scheduler; show the label for a minute
+    await window.__TAURI__.event.listen('alarm-fired', (event) => {
+        const alarm = event.payload.payload;
+        messageEl.textContent = alarm.label || `⏰ ${alarm.time}`;
+        messageEl.dataset.priority = 'high';
+        messageEl.classList.remove('hidden');
+        playChime();
+        if (messageTimeout) clearTimeout(messageTimeout);
+        messageTimeout = setTimeout(() => messageEl.classList.add('hidden'), 60 * 1000);
+    });
+
+    // Tell the backend's watchdog the frame is alive; it reloads the page when these stop
+    const sendHeartbeat = () => invoke('frame_heartbeat').catch((error) => console.warn('Heartbeat failed:', error));
+    sendHeartbeat();
+    setInterval(sendHeartbeat, 15 * 1000);
+
+    // Sleep hours: the backend turns the screen off; catch up once it's back
+    displayAsleep = (await invoke('get_display_sleep')).asleep;
+    await window.__TAURI__.event.listen('display-sleep-changed', async (event) => {
+        const wasAsleep = displayAsleep;
+        displayAsleep = event.payload.payload.asleep;
+        if (wasAsleep && !displayAsleep) {
+            if (window.userLocation) await updateWeather(window.userLocation);
+            await checkPhotoContext();
+        }
+    });
+
+    // Night mode is scheduled by the backend; the frame only switches theme
+    const applyNightMode = (night) => document.body.classList.toggle('night', night.dark_theme);
+    applyNightMode(await invoke('get_night_mode'));
+    await window.__TAURI__.event.listen('night-mode-changed', (event) => applyNightMode(event.payload.payload));
+
+    // Kitchen timer counted down by the backend; ticks carry the seconds left
+    const timerEl = document.getElementById('timer');
+    const showTimer = (timer) => {
+        if (!timer) {
+            timerEl.classList.add('hidden');
+            return;
+        }
+        const minutes = Math.floor(timer.remaining / 60);
+        const seconds = String(timer.remaining % 60).padStart(2, '0');
+        timerEl.textContent = `${timer.label ? `${timer.label} ` : ''}${minutes}:${seconds}`;
+        timerEl.classList.remove('hidden');
+    };
+    showTimer(await invoke('get_timer'));
+    await window.__TAURI__.event.listen('timer-tick', (event) => showTimer(event.payload.payload));
+    await window.__TAURI__.event.listen('timer-cancelled', () => showTimer(null));
+    await window.__TAURI__.event.listen('timer-finished', (event) => {
+        const timer = event.payload.payload;
+        showTimer(null);
+        messageEl.textContent = `⏲️ ${timer.label || "Time's up"}`;
+        messageEl.dataset.priority = 'high';
+        messageEl.classList.remove('hidden');
+        playChime();
+        if (messageTimeout) clearTimeout(messageTimeout);
+        messageTimeout = setTimeout(() => messageEl.classList.add('hidden'), 60 * 1000);
+    });
+
+    // Burn-in protection: the backend moves the frame a few pixels now and then
+    // and asks for a short dim or invert over the whole screen
+    const applyPixelShift = (shift) => {
+        document.body.style.setProperty('--shift-x', `${shift.x}px`);
+        document.body.style.setProperty('--shift-y', `${shift.y}px`);
+    };
+    applyPixelShift(await invoke('get_pixel_shift'));
+    await window.__TAURI__.event.listen('pixel-shift', (event) => applyPixelShift(event.payload.payload));
+    const refreshEl = document.getElementById('burn-in-refresh');
+    let refreshTimeout = null;
+    await window.__TAURI__.event.listen('screen-refresh', (event) => {
+        const refresh = event.payload.payload;
+        refreshEl.dataset.mode = refresh.mode;
+        refreshEl.classList.remove('hidden');
+        if (refreshTimeout) clearTimeout(refreshTimeout);
+        refreshTimeout = setTimeout(() => refreshEl.classList.add('hidden'), refresh.duration * 1000);
+    });
+
+    // Dim the frame itself when brightness can't go through the backlight
+    const dimmerEl = document.getElementById('dimmer');
+    const applyBrightness = (brightness) => {
+        const percent = brightness.method === 'software' ? brightness.percent : 100;
+        dimmerEl.style.opacity = String(1 - percent / 100);
+    };
+    applyBrightness(await invoke('get_brightness'));
+    await window.__TAURI__.event.listen('software-brightness', (event) => applyBrightness(event.payload));
+
+    // Show the lead's photo while following another frame; rotate our own otherwise
+    await window.__TAURI__.event.listen('sync-status', (event) => {
+        const status = event.payload.payload;
+        followingLead = status.connected;
+        console.log(followingLead ? `🔗 Following ${status.lead}` : '🔗 Lost the lead, rotating our own photos');
+    });
+    await window.__TAURI__.event.listen('sync-photo', async (event) => {
+        const lead = event.payload.payload;
+        const photo = { ...lead, download_location: '' };
+        const timestamp = lead.fetched_at ?? Date.now();
+        cachePhoto(photo, lead.query ?? null);
+        await displayPhoto(photo, timestamp, lead.query ?? null);
+    });
+
+    // Quiet hint once GitHub has a newer release
+    const updateEl = document.getElementById('update');
+    const showUpdate = (update) => {
+        if (!update?.update_available) return;
+        updateEl.textContent = `Update available: v${update.latest}`;
+        updateEl.classList.remove('hidden');
+    };
+    showUpdate(await invoke('get_update_info'));
+    await window.__TAURI__.event.listen('update-available', (event) => showUpdate(event.payload.payload));
+
+    // Next few calendar events under the date; re-rendered each minute so
+    // events drop out once they end
+    let calendarEvents = await invoke('get_calendar_events');
+    renderAgenda(calendarEvents);
+    setInterval(() => renderAgenda(calendarEvents), 60000);
+    await window.__TAURI__.event.listen('calendar-updated', (event) => {
+        calendarEvents = event.payload.payload;
+        renderAgenda(calendarEvents);
+    });
+
+    // One news headline at a time at the top, cycling through the feeds' newest
+    let headlines = await invoke('get_headlines');
+    showHeadline(headlines);
+    setInterval(() => showHeadline(headlines), 12000);
+    await window.__TAURI__.event.listen('news-updated', (event) => {
+        headlines = event.payload.payload.headlines;
+        headlineIndex = 0;
+        showHeadline(headlines);
+    });
+
+    // Album art instead of the photo while music plays
+    nowPlaying = await invoke('get_now_playing');
+    renderNowPlaying();
+    await window.__TAURI__.event.listen('now-playing-changed', (event) => {
+        nowPlaying = event.payload.payload;
+        renderNowPlaying();
+    });
+
+    // Coin prices in the top-right corner; the backend caches and rate limits,
+    // so asking every minute is cheap
+    updateCrypto();
+    setInterval(updateCrypto, 60000);
+
+    // CPU temperature and usage, memory, Wi-Fi and battery in the bottom-right corner, when turned on
+    setInterval(updateSystemStats, 5000);
+
+    // Low battery warning, up until the frame is plugged in
+    setInterval(updateBatteryWarning, 60000);
+    await window.__TAURI__.event.listen('battery-low', updateBatteryWarning);
+
+    // Quote of the day in the corner; checked hourly so it changes with the day
+    updateQuote();
+    setInterval(updateQuote, 60 * 60 * 1000);
+
     // Listen for settings updates from HTTP API
     await window.__TAURI__.event.listen('settings-updated', async () => {
         console.log('⚡ Settings updated via API');
         await reloadSettings();
     });
 
+    // Listen for active location changes from HTTP API
+    await window.__TAURI__.event.listen('location-changed', async () => {
+        console.log('📍 Active location changed via API');
+        await fetchLocation();
+    });
+
+    // Re-fetch everything when asked from the control panel, e.g. after a network outage
+    await window.__TAURI__.event.listen('refresh-all', async () => {
+        console.log('🔄 Full refresh requested via API');
+        await fetchLocation();
+        await fetchUnsplashPhoto(true);
+    });
+
     document.addEventListener('contextmenu', e => e.preventDefault());
     setTimeout(applyDisplaySettings, 100);
 })();
 
+function showHeadline(headlines) {
+    const newsEl = document.getElementById('news');
+    newsEl.classList.toggle('hidden', !headlines || headlines.length === 0);
+    if (!headlines || headlines.length === 0) return;
+    const headline = headlines[headlineIndex % headlines.length];
+    headlineIndex = (headlineIndex + 1) % headlines.length;
+    newsEl.querySelector('.news-feed').textContent = headline.feed;
+    newsEl.querySelector('.news-title').textContent = headline.title;
+}
+
+function renderNowPlaying() {
+    const albumArtEl = document.getElementById('album-art');
+    const show = (userSettings?.music?.album_art_view ?? true)
+        && nowPlaying?.is_playing && !!nowPlaying.album_art_url;
+    albumArtEl.classList.toggle('hidden', !show);
+    document.body.classList.toggle('album-art-view', !!show);
+    if (!show) return;
+    albumArtEl.querySelector('.album-art-backdrop').style.backgroundImage = `url('${nowPlaying.album_art_url}')`;
+    albumArtEl.querySelector('.album-art-cover').src = nowPlaying.album_art_url;
+    albumArtEl.querySelector('.album-art-title').textContent = nowPlaying.title;
+    albumArtEl.querySelector('.album-art-artist').textContent = nowPlaying.artist;
+}
+
+// "Bitcoin  $64,210  +1.2%", one line per coin
+async function updateCrypto() {
+    if (displayAsleep) return;
+    const cryptoEl = document.getElementById('crypto');
+    if (!userSettings?.crypto?.enabled) {
+        cryptoEl.classList.add('hidden');
+        return;
+    }
+    try {
+        const report = await invoke('get_crypto_prices');
+        let format;
+        try {
+            format = new Intl.NumberFormat(undefined, { style: 'currency', currency: report.currency.toUpperCase() });
+        } catch {
+            format = { format: price => `${price.toLocaleString()} ${report.currency.toUpperCase()}` };
+        }
+        cryptoEl.replaceChildren(...report.prices.map(coin => {
+            const item = document.createElement('li');
+            const name = document.createElement('span');
+            name.textContent = coin.coin.charAt(0).toUpperCase() + coin.coin.slice(1).replace(/-/g, ' ');
+            const price = document.createElement('span');
+            price.textContent = format.format(coin.price);
+            item.append(name, price);
+            if (coin.change_24h_percent != null) {
+                const change = document.createElement('span');
+                change.className = coin.change_24h_percent >= 0 ? 'crypto-up' : 'crypto-down';
+                change.textContent = `${coin.change_24h_percent >= 0 ? '+' : ''}${coin.change_24h_percent.toFixed(1)}%`;
+                item.appendChild(change);
+            }
+            return item;
+        }));
+        cryptoEl.classList.toggle('hidden', report.prices.length === 0);
+    } catch (error) {
+        console.error('Failed to load crypto prices:', error);
+    }
+}
+
+// "52 °C · CPU 12% · load 0.42 0.35 0.30 · RAM 41% · app 312 MB"
+async function updateSystemStats() {
+    const statsEl = document.getElementById('system-stats');
+    if (displayAsleep || !Object.values(systemWidgets).some(Boolean)) {
+        statsEl.classList.add('hidden');
+        return;
+    }
+    const parts = [];
+    try {
+        if (systemWidgets.cpu_temp) {
+            const temp = await invoke('get_cpu_temp');
+            if (temp.display) parts.push(temp.display);
+        }
+        if (systemWidgets.cpu_usage) {
+            const usage = await invoke('get_cpu_usage');
+            const load = usage.load_average;
+            parts.push(`CPU ${Math.round(usage.total)}%`);
+            if (load.one > 0) parts.push(`load ${[load.one, load.five, load.fifteen].map(l => l.toFixed(2)).join(' ')}`);
+        }
+        if (systemWidgets.memory) {
+            const memory = await invoke('get_memory');
+            if (memory.total > 0) parts.push(`RAM ${Math.round(memory.used / memory.total * 100)}%`);
+            if (memory.swap_used > 0) parts.push(`swap ${Math.round(memory.swap_used / 1048576)} MB`);
+            if (memory.app != null) parts.push(`app ${Math.round(memory.app / 1048576)} MB`);
+        }
+        if (systemWidgets.wifi) {
+            const wifi = await invoke('get_wifi_signal');
+            if (wifi) parts.push(wifi.signal_dbm != null ? `Wi-Fi ${wifi.quality}% (${wifi.signal_dbm} dBm)` : `Wi-Fi ${wifi.quality}%`);
+        }
+        if (systemWidgets.battery) {
+            const battery = await invoke('get_battery');
+            if (battery) {
+                const charging = battery.state === 'charging' ? ' ⚡' : '';
+                const left = battery.time_remaining;
+                const time = left ? ` ${Math.floor(left / 3600)}h ${String(Math.floor(left % 3600 / 60)).padStart(2, '0')}m` : '';
+                parts.push(`🔋 ${Math.round(battery.percent)}%${charging}${time}`);
+            }
+        }
+    } catch (error) {
+        console.error('Failed to read system stats:', error);
+    }
+    statsEl.textContent = parts.join(' · ');
+    statsEl.classList.toggle('hidden', parts.length === 0);
+}
+
+async function updateBatteryWarning() {
+    const warningEl = document.getElementById('battery-warning');
+    let battery = null;
+    if (lowBatteryWarning > 0) {
+        try {
+            battery = await invoke('get_battery');
+        } catch (error) {
+            console.error('Failed to read the battery:', error);
+        }
+    }
+    const low = battery?.state === 'discharging' && battery.percent <= lowBatteryWarning;
+    if (low) warningEl.textContent = `🪫 Battery at ${Math.round(battery.percent)}%, plug in the frame`;
+    warningEl.classList.toggle('hidden', !low);
+}
+
+async function updateQuote() {
+    const quoteEl = document.getElementById('quote');
+    const enabled = !!userSettings?.quotes?.enabled;
+    quoteEl.classList.toggle('hidden', !enabled);
+    if (!enabled) return;
+    try {
+        const quote = await invoke('get_quote');
+        quoteEl.querySelector('blockquote').textContent = quote.text;
+        quoteEl.querySelector('figcaption').textContent = quote.author ? `— ${quote.author}` : '';
+    } catch (error) {
+        console.error('Failed to load quote:', error);
+    }
+}
+
+// "15:30 Dentist", prefixed with the day when it isn't today
+function renderAgenda(events) {
+    const agendaEl = document.getElementById('agenda');
+    const now = Date.now();
+    const upcoming = (events || []).filter(event => event.end > now || event.start >= now).slice(0, 3);
+    agendaEl.replaceChildren();
+    agendaEl.classList.toggle('hidden', upcoming.length === 0);
+    if (!sunriseSunsetTimeFormat) updateTimeFormatCache();
+
+    const today = new Date().toDateString();
+    const tomorrow = new Date(now + 86400000).toDateString();
+    upcoming.forEach(event => {
+        const start = new Date(event.start);
+        const day = start.toDateString();
+        let when = day === today || event.start < now ? '' : day === tomorrow ? 'Tomorrow'
+            : start.toLocaleDateString('en-US', { weekday: 'short' });
+        if (!event.all_day) {
+            when = `${when} ${start.toLocaleTimeString('en-US', sunriseSunsetTimeFormat)}`.trim();
+        }
+
+        const item = document.createElement('li');
+        if (when) {
+            const time = document.createElement('span');
+            time.className = 'agenda-time';
+            time.textContent = when;
+            item.appendChild(time);
+        }
+        item.appendChild(document.createTextNode(event.title));
+        agendaEl.appendChild(item);
+    });
+}
+
+// Two-tone chime for messages that ask for a sound
+function playChime() {
+    const audio = new AudioContext();
+    [880, 660].forEach((frequency, i) => {
+        const oscillator = audio.createOscillator();
+        const gain = audio.createGain();
+        const start = audio.currentTime + i * 0.25;
+        oscillator.frequency.value = frequency;
+        gain.gain.setValueAtTime(0.3, start);
+        gain.gain.exponentialRampToValueAtTime(0.001, start + 0.6);
+        oscillator.connect(gain).connect(audio.destination);
+        oscillator.start(start);
+        oscillator.stop(start + 0.6);
+    });
+    setTimeout(() => audio.close(), 1500);
+}
+
 // Console commands
 window.refreshPhoto = async function() {
     console.log('🔄 Manually refreshing photo...');
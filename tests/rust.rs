@@ -0,0 +1,2423 @@
+use idleview_lib::location::resolve_saved_location_impl;
+use idleview_lib::settings_manager::{LocationSettings, SavedLocation};
+
+fn saved(name: &str, latitude: f64, longitude: f64) -> SavedLocation {
+    SavedLocation {
+        name: name.to_string(),
+        latitude,
+        longitude,
+    }
+}
+
+#[test]
+fn test_saved_location_auto_when_none_active() {
+    let settings = LocationSettings {
+        saved: vec![saved("Home", 44.43, 26.10)],
+        ..LocationSettings::default()
+    };
+    assert!(resolve_saved_location_impl(&settings, 0).is_none());
+}
+
+#[test]
+fn test_saved_location_active_by_name() {
+    let settings = LocationSettings {
+        saved: vec![saved("Home", 44.43, 26.10), saved("Parents", 46.77, 23.59)],
+        active: Some("Parents".to_string()),
+        ..LocationSettings::default()
+    };
+    let location = resolve_saved_location_impl(&settings, 0).unwrap();
+    assert_eq!(location.name, "Parents");
+}
+
+#[test]
+fn test_saved_location_rotation() {
+    let settings = LocationSettings {
+        saved: vec![saved("Home", 44.43, 26.10), saved("Parents", 46.77, 23.59)],
+        rotate: true,
+        rotation_interval: 5,
+        ..LocationSettings::default()
+    };
+    let five_minutes = 5 * 60 * 1000;
+    assert_eq!(resolve_saved_location_impl(&settings, 0).unwrap().name, "Home");
+    assert_eq!(resolve_saved_location_impl(&settings, five_minutes).unwrap().name, "Parents");
+    assert_eq!(resolve_saved_location_impl(&settings, 2 * five_minutes).unwrap().name, "Home");
+}
+
+#[test]
+fn test_manual_location_mode() {
+    use idleview_lib::location::manual_location_impl;
+    use idleview_lib::settings_manager::{validate_settings_json, LocationMode, Settings};
+
+    let mut settings = LocationSettings {
+        mode: LocationMode::Manual,
+        latitude: Some(45.64),
+        longitude: Some(25.59),
+        label: Some("Brasov".to_string()),
+        ..LocationSettings::default()
+    };
+    let location = manual_location_impl(&settings).unwrap();
+    assert_eq!(location.source, "manual");
+    assert_eq!(location.city.as_deref(), Some("Brasov"));
+
+    settings.mode = LocationMode::Auto;
+    assert!(manual_location_impl(&settings).is_none());
+
+    let mut value = serde_json::to_value(Settings::default()).unwrap();
+    value["location"]["mode"] = "manual".into();
+    value["location"]["latitude"] = 95.0.into();
+    let problems = validate_settings_json(&value);
+    assert_eq!(problems.len(), 2, "{:?}", problems);
+    assert!(problems[0].starts_with("location.latitude: expected a number between -90 and 90"));
+    assert_eq!(problems[1], "location.longitude: required in manual mode");
+}
+
+#[test]
+fn test_weather_legend_localized() {
+    use idleview_lib::weather_codes::{describe_weather_code_impl, get_weather_legend_impl};
+
+    let legend = get_weather_legend_impl("ro-RO");
+    assert_eq!(legend.language, "ro");
+    assert!(legend.codes.iter().any(|c| c.code == 0 && c.label == "Cer senin"));
+
+    // Unsupported languages fall back to English
+    assert_eq!(get_weather_legend_impl("xx").language, "en");
+    assert_eq!(describe_weather_code_impl(95, "en").unwrap().icon, "thunderstorm");
+    assert!(describe_weather_code_impl(42, "en").is_none());
+}
+
+#[test]
+fn test_language_setting() {
+    use idleview_lib::settings_manager::{validate_settings_json, Settings};
+    use idleview_lib::{chrono_locale, get_precipitation_display_impl};
+
+    let friday = chrono::NaiveDate::from_ymd_opt(2025, 11, 28).unwrap();
+    assert_eq!(friday.format_localized("%A", chrono_locale("ro")).to_string().to_uppercase(), "VINERI");
+    assert_eq!(friday.format_localized("%b", chrono_locale("de-DE")).to_string(), "Nov");
+    assert_eq!(chrono_locale("xx-YY"), chrono::Locale::en_US);
+
+    let mut weather = serde_json::from_value::<idleview_lib::WeatherData>(serde_json::json!({
+        "temperature": 1.0, "temperature_unit": "celsius", "humidity": 80.0, "wind_speed": 5.0,
+        "wind_speed_unit": "kmh", "wind_speed_label": "km/h", "cloudcover": 90.0, "rain": 0.0,
+        "snowfall": 0.0, "sunrise": "", "sunset": "", "timezone": "UTC"
+    }))
+    .unwrap();
+    assert_eq!(get_precipitation_display_impl(weather.clone(), "ro").value, "Senin");
+    weather.snowfall = 2.0;
+    assert_eq!(get_precipitation_display_impl(weather, "en").label, "Snow");
+
+    let mut settings = serde_json::to_value(Settings::default()).unwrap();
+    assert_eq!(settings["language"], "en");
+    settings["language"] = "ro-RO".into();
+    assert!(validate_settings_json(&settings).is_empty());
+    settings["language"] = "romanian!".into();
+    assert_eq!(validate_settings_json(&settings).len(), 1);
+}
+
+#[test]
+fn test_parse_nmea_position() {
+    use idleview_lib::location::parse_nmea_position;
+
+    let (lat, lon) = parse_nmea_position("$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47").unwrap();
+    assert!((lat - 48.1173).abs() < 1e-4);
+    assert!((lon - 11.5167).abs() < 1e-4);
+
+    let (lat, lon) = parse_nmea_position("$GNRMC,123519,A,3351.000,S,15112.000,E,022.4,084.4,230394,003.1,W*6A").unwrap();
+    assert!((lat + 33.85).abs() < 1e-4);
+    assert!((lon - 151.2).abs() < 1e-4);
+
+    // No fix yet
+    assert!(parse_nmea_position("$GPGGA,123519,,,,,0,00,,,M,,M,,*66").is_none());
+    assert!(parse_nmea_position("$GPRMC,123519,V,,,,,,,230394,,*22").is_none());
+    assert!(parse_nmea_position("$GPGSV,3,1,11,03,03,111,00*74").is_none());
+}
+
+#[test]
+fn test_scene_tags_and_templates() {
+    use idleview_lib::scenes::{render_query_template, scenes_with_tag};
+    use idleview_lib::settings_manager::{Scene, ScenesSettings};
+
+    let scene = |name: &str, tags: &[&str]| Scene {
+        name: name.to_string(),
+        tags: tags.iter().map(|t| t.to_string()).collect(),
+        query_templates: vec![],
+        settings: serde_json::Value::Null,
+        calendar_keywords: vec![name.to_lowercase()],
+    };
+    let scenes = ScenesSettings {
+        list: vec![scene("Cozy evening", &["evening", "calm"]), scene("Party", &["fun"])],
+        ..ScenesSettings::default()
+    };
+
+    let calm: Vec<_> = scenes_with_tag(&scenes, Some("Calm")).iter().map(|s| s.name.clone()).collect();
+    assert_eq!(calm, vec!["Cozy evening"]);
+    assert_eq!(scenes_with_tag(&scenes, None).len(), 2);
+
+    assert_eq!(render_query_template("{season} {time_of_day} fireplace", "winter", "night"), "winter night fireplace");
+
+    let matched = idleview_lib::scenes::scene_for_event_title(&scenes, "Birthday PARTY at home").unwrap();
+    assert_eq!(matched.name, "Party");
+    assert!(idleview_lib::scenes::scene_for_event_title(&scenes, "Dentist").is_none());
+}
+
+#[test]
+fn test_ip_location_providers_parse() {
+    use idleview_lib::location::{IpApi, IpInfo, IpWhoIs, LocationProvider};
+
+    let location = IpApi
+        .parse(r#"{"status":"success","lat":44.43,"lon":26.1,"city":"Bucharest","country":"Romania"}"#)
+        .unwrap();
+    assert_eq!(location.source, "ip-api");
+    assert_eq!(location.city.as_deref(), Some("Bucharest"));
+    assert!(IpApi.parse(r#"{"status":"fail","message":"private range","query":"10.0.0.1"}"#).is_err());
+
+    let location = IpWhoIs
+        .parse(r#"{"success":true,"latitude":46.77,"longitude":23.59,"city":"Cluj-Napoca","country":"Romania"}"#)
+        .unwrap();
+    assert_eq!(location.source, "ipwhois");
+    assert!(IpWhoIs.parse(r#"{"success":false,"message":"Reserved range"}"#).is_err());
+
+    let location = IpInfo
+        .parse(r#"{"ip":"1.2.3.4","city":"Iasi","country":"RO","loc":"47.1585,27.6014"}"#)
+        .unwrap();
+    assert_eq!(location.source, "ipinfo");
+    assert!((location.latitude - 47.1585).abs() < 1e-6);
+    assert!(IpInfo.parse(r#"{"loc":"nowhere"}"#).is_err());
+}
+
+#[test]
+fn test_event_envelope_schema() {
+    use idleview_api::events::{EventEnvelope, ServerEvent, EVENT_SCHEMA_VERSION};
+    use idleview_api::pairing::PairingPin;
+
+    let envelope = EventEnvelope::new(ServerEvent::LocationChanged { active: Some("Home".to_string()) }, "http");
+    let json = serde_json::to_value(&envelope).unwrap();
+    assert_eq!(json["version"], EVENT_SCHEMA_VERSION);
+    assert_eq!(json["type"], "location-changed");
+    assert_eq!(json["source"], "http");
+    assert_eq!(json["payload"]["active"], "Home");
+
+    // Event types added later still parse, so old consumers keep working
+    let future: EventEnvelope = serde_json::from_str(
+        r#"{"version":1,"timestamp":0,"source":"mqtt","type":"doorbell","payload":{"room":"hall"}}"#,
+    )
+    .unwrap();
+    assert!(matches!(future.event, ServerEvent::Unknown));
+
+    let parsed: EventEnvelope = serde_json::from_value(json).unwrap();
+    assert!(matches!(parsed.event, ServerEvent::LocationChanged { active: Some(_) }));
+
+    // Events without a payload round-trip too
+    let json = serde_json::to_string(&EventEnvelope::new(ServerEvent::RefreshPhoto, "mqtt")).unwrap();
+    let parsed: EventEnvelope = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.event.event_type(), "refresh-photo");
+
+    // Calendar events and the pairing PIN stay on the frame
+    assert!(ServerEvent::CalendarUpdated(Vec::new()).is_private());
+    let pin = PairingPin { pin: "123456".to_string(), expires_in: 120 };
+    assert!(ServerEvent::PairingPin(pin).is_private());
+    assert!(!ServerEvent::RefreshPhoto.is_private());
+}
+
+#[test]
+fn test_event_hub_drops_low_priority_first() {
+    use idleview_lib::event_hub::{EventHub, EventPriority};
+
+    let hub = EventHub::new(3);
+    let fast = hub.subscribe("sse");
+    let slow = hub.subscribe("sse");
+
+    hub.publish("settings".to_string(), EventPriority::Normal);
+    assert_eq!(&*fast.try_recv().unwrap(), "settings");
+
+    hub.publish("tick-1".to_string(), EventPriority::Low);
+    hub.publish("photo".to_string(), EventPriority::Normal);
+    hub.publish("tick-2".to_string(), EventPriority::Low);
+
+    // The slow client's queue is full: the oldest low-priority event goes
+    let received: Vec<String> = std::iter::from_fn(|| slow.try_recv()).map(|e| e.to_string()).collect();
+    assert_eq!(received, vec!["settings", "photo", "tick-2"]);
+
+    let stats = hub.client_stats();
+    assert_eq!(stats.len(), 2);
+    assert_eq!(stats[1].dropped, 1);
+    assert_eq!(stats[1].delivered, 3);
+
+    drop(fast);
+    assert_eq!(hub.client_stats().len(), 1);
+}
+
+#[test]
+fn test_bandwidth_monitor_hysteresis() {
+    use idleview_lib::bandwidth::BandwidthMonitor;
+
+    let mut monitor = BandwidthMonitor::default();
+
+    // A single slow download isn't sustained low bandwidth
+    assert!(!monitor.record(100 * 1024, 1000));
+    assert!(!monitor.record(100 * 1024, 1000));
+    assert!(!monitor.is_degraded());
+
+    assert!(monitor.record(100 * 1024, 1000));
+    assert!(monitor.is_degraded());
+    assert_eq!(monitor.mode().mode, "degraded");
+
+    // Throughput between the thresholds keeps the current mode
+    monitor.record(400 * 1024, 1000);
+    monitor.record(400 * 1024, 1000);
+    monitor.record(400 * 1024, 1000);
+    assert!(monitor.is_degraded());
+
+    monitor.record(1000 * 1024, 1000);
+    monitor.record(1000 * 1024, 1000);
+    monitor.record(1000 * 1024, 1000);
+    assert!(!monitor.is_degraded());
+    assert_eq!(monitor.mode().mode, "normal");
+
+    // Cache hits report no bytes and are ignored
+    assert!(!monitor.record(0, 50));
+}
+
+#[test]
+fn test_parse_elevation_response() {
+    use idleview_lib::location::{elevation_cache_key, parse_elevation_response};
+
+    assert_eq!(parse_elevation_response(r#"{"elevation":[1764.0]}"#), Ok(1764.0));
+    assert!(parse_elevation_response(r#"{"elevation":[]}"#).is_err());
+    assert!(parse_elevation_response(r#"{"error":true,"reason":"Latitude must be in range"}"#).is_err());
+
+    // Nearby fixes of the same frame share a cache entry
+    assert_eq!(elevation_cache_key(45.64271, 25.58812), elevation_cache_key(45.64269, 25.58808));
+}
+
+#[test]
+fn test_owntracks_region_switching() {
+    use idleview_lib::owntracks::active_location_for_message;
+
+    let mut settings = LocationSettings {
+        saved: vec![saved("Home", 44.43, 26.10), saved("Cabin", 45.64, 25.59)],
+        ..LocationSettings::default()
+    };
+    settings.owntracks.device = "owntracks/ana/pixel".to_string();
+
+    let enter = br#"{"_type":"transition","event":"enter","desc":"cabin","lat":45.64,"lon":25.59}"#;
+    assert_eq!(
+        active_location_for_message(&settings, "owntracks/ana/pixel/event", enter),
+        Some(Some("Cabin".to_string()))
+    );
+    // Other phones are ignored
+    assert_eq!(active_location_for_message(&settings, "owntracks/dan/iphone/event", enter), None);
+
+    settings.active = Some("Cabin".to_string());
+    let leave = br#"{"_type":"transition","event":"leave","desc":"Cabin"}"#;
+    assert_eq!(active_location_for_message(&settings, "owntracks/ana/pixel/event", leave), Some(None));
+
+    let location = br#"{"_type":"location","lat":44.43,"lon":26.10,"inregions":["Home"]}"#;
+    assert_eq!(
+        active_location_for_message(&settings, "owntracks/ana/pixel", location),
+        Some(Some("Home".to_string()))
+    );
+    assert_eq!(active_location_for_message(&settings, "owntracks/ana/pixel", br#"{"_type":"lwt"}"#), None);
+}
+
+#[test]
+fn test_quality_schedule_wraps_midnight() {
+    use chrono::NaiveTime;
+    use idleview_lib::settings_manager::QualityWindow;
+    use idleview_lib::{photo_quality_value, scheduled_quality_impl};
+
+    let window = |start: &str, end: &str, quality: &str| QualityWindow {
+        start: start.to_string(),
+        end: end.to_string(),
+        quality: quality.to_string(),
+        resolution_scale: 0.5,
+    };
+    let schedule = vec![window("23:00", "06:30", "40"), window("07:00", "22:00", "high")];
+    let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+
+    assert_eq!(scheduled_quality_impl(&schedule, at(3, 0)).map(|w| w.quality.as_str()), Some("40"));
+    assert_eq!(scheduled_quality_impl(&schedule, at(23, 30)).map(|w| w.quality.as_str()), Some("40"));
+    assert_eq!(scheduled_quality_impl(&schedule, at(12, 0)).map(|w| w.quality.as_str()), Some("high"));
+    assert!(scheduled_quality_impl(&schedule, at(6, 45)).is_none());
+
+    assert_eq!(photo_quality_value("high"), 100);
+    assert_eq!(photo_quality_value("40"), 40);
+}
+
+#[test]
+fn test_validate_settings_lists_every_problem() {
+    use idleview_lib::settings_manager::{validate_settings_json, Settings};
+
+    let mut settings = serde_json::to_value(Settings::default()).unwrap();
+    assert!(validate_settings_json(&settings).is_empty());
+
+    settings["units"]["temperature_unit"] = "farenheit".into();
+    settings["units"]["time_format"] = "24".into();
+    settings["photos"]["photo_quality"] = "250".into();
+    let problems = validate_settings_json(&settings);
+    assert_eq!(problems.len(), 3, "{:?}", problems);
+    assert!(problems[0].starts_with("units.temperature_unit: expected one of celsius, fahrenheit"));
+
+    // Legacy quality names are still accepted
+    let mut settings = serde_json::to_value(Settings::default()).unwrap();
+    settings["photos"]["photo_quality"] = "high".into();
+    assert!(validate_settings_json(&settings).is_empty());
+}
+
+/// JSON pointers of every leaf in a document
+fn leaf_pointers(value: &serde_json::Value, prefix: String, out: &mut Vec<String>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, child) in map {
+                leaf_pointers(child, format!("{}/{}", prefix, key), out);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for (i, child) in items.iter().enumerate() {
+                leaf_pointers(child, format!("{}/{}", prefix, i), out);
+            }
+        }
+        _ => out.push(prefix),
+    }
+}
+
+/// Every truncation of the body, plus copies with each leaf replaced by a
+/// null, a wrong-typed value or an unexpected string
+fn malformed_variants(body: &serde_json::Value) -> Vec<String> {
+    let text = body.to_string();
+    let mut variants: Vec<String> = (0..text.len()).map(|end| text[..end].to_string()).collect();
+
+    let mut pointers = Vec::new();
+    leaf_pointers(body, String::new(), &mut pointers);
+    for pointer in pointers {
+        for replacement in [serde_json::Value::Null, serde_json::json!("¿unexpected?"), serde_json::json!([1, 2]), serde_json::json!({})] {
+            let mut mutated = body.clone();
+            *mutated.pointer_mut(&pointer).unwrap() = replacement;
+            variants.push(mutated.to_string());
+        }
+    }
+    variants
+}
+
+#[test]
+fn test_weather_response_survives_malformed_input() {
+    use idleview_lib::parse_weather_response_impl;
+    use idleview_lib::settings_manager::Settings;
+
+    let units = Settings::default().units;
+    let body = serde_json::json!({
+        "timezone": "Europe/Bucharest",
+        "current": { "temperature_2m": 4.5, "relative_humidity_2m": 80, "rain": 0.2, "snowfall": 0.0, "cloudcover": 90, "wind_speed_10m": 12.0 },
+        "daily": { "sunrise": ["2025-11-28T07:25"], "sunset": ["2025-11-28T16:40"] }
+    });
+    assert!(parse_weather_response_impl(&body.to_string(), &units).is_ok());
+
+    for variant in malformed_variants(&body) {
+        let _ = parse_weather_response_impl(&variant, &units);
+    }
+
+    // Optional values degrade to partial data
+    let mut partial = body.clone();
+    partial["current"]["rain"] = serde_json::Value::Null;
+    partial["current"]["cloudcover"] = serde_json::json!("90");
+    partial["daily"]["sunrise"] = serde_json::json!([null]);
+    partial.as_object_mut().unwrap().remove("timezone");
+    let weather = parse_weather_response_impl(&partial.to_string(), &units).unwrap();
+    assert_eq!(weather.rain, 0.0);
+    assert_eq!(weather.cloudcover, 90.0);
+    assert_eq!(weather.sunrise, "");
+    assert_eq!(weather.sunset, "2025-11-28T16:40");
+    assert_eq!(weather.timezone, "UTC");
+
+    // The temperature can't be made up
+    let mut missing = body.clone();
+    missing["current"]["temperature_2m"] = serde_json::Value::Null;
+    assert_eq!(
+        parse_weather_response_impl(&missing.to_string(), &units).unwrap_err(),
+        "Weather data has no current temperature"
+    );
+    assert!(parse_weather_response_impl("{\"current\":{\"temp", &units).unwrap_err().starts_with("Failed to parse weather data"));
+}
+
+#[test]
+fn test_unsplash_response_survives_malformed_input() {
+    use idleview_lib::parse_unsplash_response_impl;
+
+    let body = serde_json::json!({
+        "urls": { "regular": "https://images.unsplash.com/photo-1?q=80" },
+        "user": { "name": "Ana Pop", "links": { "html": "https://unsplash.com/@anapop" } },
+        "links": { "download_location": "https://api.unsplash.com/photos/1/download" }
+    });
+    assert!(parse_unsplash_response_impl(&body.to_string()).is_ok());
+
+    for variant in malformed_variants(&body) {
+        let _ = parse_unsplash_response_impl(&variant);
+    }
+
+    let mut no_credit = body.clone();
+    no_credit["user"] = serde_json::Value::Null;
+    no_credit["links"]["download_location"] = serde_json::json!(42);
+    let photo = parse_unsplash_response_impl(&no_credit.to_string()).unwrap();
+    assert_eq!(photo.author, "Unknown");
+    assert_eq!(photo.author_url, "https://unsplash.com");
+
+    let mut no_url = body.clone();
+    no_url["urls"]["regular"] = serde_json::Value::Null;
+    assert_eq!(parse_unsplash_response_impl(&no_url.to_string()).unwrap_err(), "Photo data has no image URL");
+}
+
+#[test]
+fn test_location_responses_survive_malformed_input() {
+    use idleview_lib::location::{parse_elevation_response, IP_PROVIDERS};
+
+    let bodies = [
+        serde_json::json!({ "success": true, "latitude": 44.43, "longitude": 26.1, "city": "Bucharest", "country": "Romania" }),
+        serde_json::json!({ "loc": "44.43,26.10", "city": "Bucharest", "country": "RO" }),
+        serde_json::json!({ "status": "success", "lat": 44.43, "lon": 26.1, "city": "Bucharest", "country": "Romania" }),
+    ];
+    for (provider, body) in IP_PROVIDERS.iter().zip(&bodies) {
+        assert!(provider.parse(&body.to_string()).is_ok(), "{}", provider.name());
+        for variant in malformed_variants(body) {
+            let _ = provider.parse(&variant);
+        }
+    }
+
+    // Numbers as strings and a null city are fine
+    let location = IP_PROVIDERS[0]
+        .parse(r#"{"success":"true","latitude":"44.43","longitude":26.1,"city":null}"#)
+        .unwrap();
+    assert_eq!(location.latitude, 44.43);
+    assert_eq!(location.city, None);
+
+    // Plain HTTP only as the last resort
+    let (last, https) = IP_PROVIDERS.split_last().unwrap();
+    assert!(https.iter().all(|provider| provider.url().starts_with("https://")));
+    assert_eq!(last.name(), "ip-api");
+
+    let body = serde_json::json!({ "elevation": [1764.0] });
+    for variant in malformed_variants(&body) {
+        let _ = parse_elevation_response(&variant);
+    }
+    assert_eq!(parse_elevation_response(r#"{"elevation":[null, 12.0]}"#), Ok(12.0));
+}
+
+#[test]
+fn test_settings_backup_round_trip() {
+    use idleview_lib::settings_manager::{create_backup, settings_from_backup, Settings};
+
+    let mut settings = Settings::default();
+    settings.photos.refresh_interval = 15;
+    let backup = serde_json::to_value(create_backup(&settings)).unwrap();
+    assert_eq!(backup["format"], "idleview-settings");
+    assert_eq!(settings_from_backup(&backup).unwrap().photos.refresh_interval, 15);
+
+    // Older backups without the newer sections still restore
+    let mut old = backup.clone();
+    old["settings"].as_object_mut().unwrap().remove("scenes");
+    assert!(settings_from_backup(&old).is_ok());
+
+    let mut bad = backup.clone();
+    bad["version"] = 99.into();
+    bad["settings"]["units"]["date_format"] = "dd/mm".into();
+    assert_eq!(settings_from_backup(&bad).unwrap_err().len(), 1);
+    bad["version"] = 1.into();
+    assert!(settings_from_backup(&bad).unwrap_err()[0].starts_with("units.date_format"));
+
+    assert!(settings_from_backup(&serde_json::json!({ "hello": "world" })).is_err());
+}
+
+#[test]
+fn test_display_state_degradation_order() {
+    use idleview_lib::display_state::{resolve_display_state_impl, DataAvailability};
+    use idleview_lib::settings_manager::Settings;
+
+    let mut display = Settings::default().display;
+    display.show_cpu_temp = true;
+    let visible = |state: &idleview_lib::display_state::DisplayState| -> Vec<String> {
+        state.widgets.iter().filter(|w| w.visible).map(|w| w.id.clone()).collect()
+    };
+
+    let all = DataAvailability { weather: true, cpu_temp: true, wifi: false, battery: false };
+    assert_eq!(visible(&resolve_display_state_impl(&display, all, false)).len(), 5);
+
+    // Degraded: the first two of the order go, the rest stay
+    let state = resolve_display_state_impl(&display, all, true);
+    assert_eq!(state.mode, "degraded");
+    assert_eq!(visible(&state), vec!["weather", "humidity_wind", "precipitation_cloudiness"]);
+
+    // Widgets that are already gone don't use up the drop budget
+    let no_cpu = DataAvailability { weather: true, cpu_temp: false, wifi: false, battery: false };
+    let state = resolve_display_state_impl(&display, no_cpu, true);
+    assert_eq!(visible(&state), vec!["weather", "humidity_wind"]);
+    assert_eq!(state.widgets[4].reason.as_deref(), Some("unavailable"));
+
+    // No weather: its widgets are hidden instead of showing placeholders
+    let available = DataAvailability { weather: false, cpu_temp: true, wifi: false, battery: false };
+    let state = resolve_display_state_impl(&display, available, false);
+    assert_eq!(visible(&state), vec!["cpu_temp"]);
+}
+
+#[test]
+fn test_settings_export_import_and_migration() {
+    use idleview_lib::settings_manager::{export_settings_to, import_settings_from, Settings};
+
+    let dir = std::env::temp_dir().join(format!("idleview-export-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut settings = Settings::default();
+    settings.photos.refresh_interval = 10;
+    let bundle = dir.join("frame.json");
+    export_settings_to(&bundle, &settings).unwrap();
+    assert_eq!(import_settings_from(&bundle).unwrap().photos.refresh_interval, 10);
+
+    // A bare settings.json from an older frame is migrated
+    let mut legacy = serde_json::to_value(Settings::default()).unwrap();
+    legacy["photos"]["photo_quality"] = "high".into();
+    legacy.as_object_mut().unwrap().remove("location");
+    let bare = dir.join("settings.json");
+    std::fs::write(&bare, legacy.to_string()).unwrap();
+    assert_eq!(import_settings_from(&bare).unwrap().photos.photo_quality, "100");
+
+    std::fs::write(&bare, "{\"units\": {\"temperature_unit\": \"kelvin\"}}").unwrap();
+    assert!(import_settings_from(&bare).unwrap_err().contains("units.temperature_unit"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_profile_schedule_and_validation() {
+    use chrono::NaiveTime;
+    use idleview_lib::profiles::{profile_overrides, scheduled_profile};
+    use idleview_lib::settings_manager::{validate_settings_json, ProfileWindow, Settings};
+    use serde_json::json;
+
+    let window = |start: &str, end: &str, profile: &str| ProfileWindow {
+        start: start.to_string(),
+        end: end.to_string(),
+        profile: profile.to_string(),
+    };
+    let schedule = vec![window("07:00", "21:00", "day"), window("21:00", "07:00", "night")];
+    let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+
+    assert_eq!(scheduled_profile(&schedule, at(12, 0)), Some("day"));
+    assert_eq!(scheduled_profile(&schedule, at(21, 0)), Some("night"));
+    assert_eq!(scheduled_profile(&schedule, at(2, 30)), Some("night"));
+    assert_eq!(scheduled_profile(&[], at(2, 30)), None);
+
+    // A profile can't carry other profiles along
+    let overrides = profile_overrides(&json!({ "display": { "show_cpu_temp": false }, "profiles": { "active": "x" } }));
+    assert_eq!(overrides, json!({ "display": { "show_cpu_temp": false } }));
+    assert_eq!(profile_overrides(&json!("night")), json!({}));
+
+    let mut settings = serde_json::to_value(Settings::default()).unwrap();
+    settings["profiles"] = json!({
+        "list": [{ "name": "day", "settings": {} }],
+        "schedule": [
+            { "start": "07:00", "end": "21:00", "profile": "day" },
+            { "start": "21:00", "end": "7", "profile": "night" }
+        ]
+    });
+    let problems = validate_settings_json(&settings);
+    assert_eq!(problems.len(), 2, "{:?}", problems);
+    assert!(problems[0].starts_with("profiles.schedule[1].end: expected HH:MM"));
+    assert!(problems[1].starts_with("profiles.schedule[1].profile: unknown profile \"night\""));
+}
+
+#[test]
+fn test_settings_history_diff() {
+    use idleview_lib::settings_history::diff_settings;
+    use idleview_lib::settings_manager::Settings;
+
+    let old = serde_json::to_value(Settings::default()).unwrap();
+    assert!(diff_settings(&old, &old).is_empty());
+
+    let mut new = old.clone();
+    new["units"]["temperature_unit"] = "fahrenheit".into();
+    new["location"]["owntracks"]["password"] = "hunter2".into();
+    let changes = diff_settings(&old, &new);
+
+    assert_eq!(changes.len(), 2, "{:?}", changes);
+    assert_eq!(changes[0].key, "location.owntracks.password");
+    assert_eq!(changes[0].new, "***");
+    assert_eq!(changes[1].key, "units.temperature_unit");
+    assert_eq!(changes[1].old, "celsius");
+    assert_eq!(changes[1].new, "fahrenheit");
+}
+
+#[test]
+fn test_write_throttle_coalesces_bursts() {
+    use idleview_lib::write_throttle::WriteThrottle;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    let written = Arc::new(Mutex::new(Vec::new()));
+    let throttle = WriteThrottle::new(Duration::from_millis(100));
+    for value in 0..10 {
+        let written = written.clone();
+        throttle
+            .submit(move || {
+                written.lock().unwrap().push(value);
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    // The first write goes straight through, the rest wait for the interval
+    assert_eq!(*written.lock().unwrap(), vec![0]);
+    assert!(throttle.is_pending());
+
+    std::thread::sleep(Duration::from_millis(300));
+    assert_eq!(*written.lock().unwrap(), vec![0, 9]);
+    assert!(!throttle.is_pending());
+}
+
+#[test]
+fn test_time_and_cache_use_passed_settings() {
+    use idleview_lib::settings_manager::{Settings, TimeFormat};
+    use idleview_lib::{get_current_time_impl, is_cache_valid_impl};
+
+    let mut settings = Settings::default();
+    settings.units.time_format = TimeFormat::H12;
+    let time = get_current_time_impl(&settings.units, &settings.language);
+    assert!(time.time.ends_with("AM") || time.time.ends_with("PM"), "{}", time.time);
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    settings.photos.refresh_interval = 30;
+    assert!(is_cache_valid_impl(now - 10 * 60 * 1000, &settings.photos));
+    assert!(!is_cache_valid_impl(now - 90 * 60 * 1000, &settings.photos));
+}
+
+#[test]
+fn test_settings_defaults_options() {
+    use idleview_lib::settings_manager::{settings_defaults, validate_settings_json};
+
+    let defaults = settings_defaults();
+    assert_eq!(defaults.options["units.time_format"], vec!["24h", "12h"]);
+    assert!(defaults.options["display.degradation_order"].contains(&"cpu_temp".to_string()));
+
+    // Every advertised option is accepted by validation (manual location mode needs
+    // coordinates, OpenWeatherMap, Pexels, Finnhub and football-data.org need keys)
+    let mut base = serde_json::to_value(&defaults.defaults).unwrap();
+    base["location"]["latitude"] = 44.43.into();
+    base["location"]["longitude"] = 26.10.into();
+    base["weather"]["api_keys"]["openweathermap"] = "key".into();
+    base["photos"]["api_keys"]["pexels"] = "key".into();
+    base["stocks"]["api_keys"]["finnhub"] = "key".into();
+    base["sports"]["api_keys"]["football_data"] = "key".into();
+    for (path, values) in &defaults.options {
+        for value in values {
+            let mut settings = base.clone();
+            let pointer = format!("/{}", path.replace('.', "/"));
+            let field = settings.pointer_mut(&pointer).unwrap();
+            *field = if field.is_array() { serde_json::json!([value]) } else { serde_json::json!(value) };
+            assert!(validate_settings_json(&settings).is_empty(), "{} = {}", path, value);
+        }
+    }
+}
+
+#[test]
+fn test_photo_query_customization() {
+    use idleview_lib::customize_photo_query_impl;
+    use idleview_lib::settings_manager::{validate_settings_json, PhotoQuerySettings};
+
+    let mut query = PhotoQuerySettings::default();
+    assert_eq!(customize_photo_query_impl("winter night".to_string(), &query), "winter night");
+    query.extra_keywords = Some(" mountains ".to_string());
+    assert_eq!(customize_photo_query_impl("winter night".to_string(), &query), "winter night mountains");
+    query.fixed = Some("lighthouse".to_string());
+    assert_eq!(customize_photo_query_impl("winter night".to_string(), &query), "lighthouse");
+
+    let problems = validate_settings_json(&serde_json::json!({
+        "photos": { "provider": "flickr", "query": { "orientation": "wide" } }
+    }));
+    assert_eq!(problems.len(), 2, "{:?}", problems);
+    let problems = validate_settings_json(&serde_json::json!({ "photos": { "provider": "pexels" } }));
+    assert_eq!(problems, vec!["photos.api_keys.pexels: required for the pexels provider"]);
+}
+
+#[test]
+fn test_server_settings() {
+    use idleview_lib::http_server::server_addr;
+    use idleview_lib::settings_manager::{validate_settings_json, ServerBind, Settings};
+
+    let mut settings = Settings::default();
+    assert!(settings.server.enabled);
+    assert_eq!(server_addr(&settings.server).to_string(), "0.0.0.0:8737");
+    settings.server.bind = ServerBind::Localhost;
+    settings.server.port = 9000;
+    assert_eq!(server_addr(&settings.server).to_string(), "127.0.0.1:9000");
+
+    let mut value = serde_json::to_value(&settings).unwrap();
+    value["server"]["port"] = 70000.into();
+    value["server"]["bind"] = "lan".into();
+    let problems = validate_settings_json(&value);
+    assert_eq!(problems.len(), 2, "{:?}", problems);
+}
+
+#[test]
+fn test_settings_schema() {
+    use idleview_lib::settings_manager::settings_schema;
+
+    let schema = settings_schema();
+    assert_eq!(schema["title"], "Settings");
+    let required: Vec<&str> = schema["required"].as_array().unwrap().iter().filter_map(|r| r.as_str()).collect();
+    assert!(required.contains(&"units") && !required.contains(&"weather"));
+    assert_eq!(schema["definitions"]["TimeFormat"]["enum"], serde_json::json!(["24h", "12h"]));
+    assert_eq!(
+        schema["definitions"]["PhotosSettings"]["properties"]["photo_quality"]["type"],
+        serde_json::json!(["string", "integer"])
+    );
+}
+
+#[test]
+fn test_api_token_bearer_check() {
+    use idleview_lib::auth::bearer_matches;
+
+    assert!(bearer_matches(Some("Bearer abc123"), "abc123"));
+    assert!(!bearer_matches(Some("Bearer abc124"), "abc123"));
+    assert!(!bearer_matches(Some("Bearer abc"), "abc123"));
+    assert!(!bearer_matches(Some("abc123"), "abc123"));
+    assert!(!bearer_matches(None, "abc123"));
+}
+
+#[test]
+fn test_pairing_pin_is_single_use() {
+    use idleview_lib::pairing::{redeem_pin, start_pairing};
+
+    let pin = start_pairing().unwrap().unwrap().pin;
+    assert_eq!(pin.len(), 6);
+    assert!(pin.chars().all(|c| c.is_ascii_digit()));
+    assert!(redeem_pin(&pin).unwrap());
+    assert!(!redeem_pin(&pin).unwrap());
+
+    // Starting again while a PIN is shown keeps it, and the wrong guesses made
+    let pin = start_pairing().unwrap().unwrap().pin;
+    let wrong = if pin == "000000" { "000001" } else { "000000" };
+    for _ in 0..4 {
+        assert!(!redeem_pin(wrong).unwrap());
+    }
+    assert_eq!(start_pairing().unwrap().unwrap().pin, pin);
+
+    // Too many wrong guesses end the attempt and lock pairing
+    assert!(!redeem_pin(wrong).unwrap());
+    assert!(!redeem_pin(&pin).unwrap());
+    assert!(start_pairing().unwrap().is_none());
+}
+
+#[test]
+fn test_tls_self_signed_certificate() {
+    use idleview_lib::settings_manager::TlsSettings;
+    use idleview_lib::tls::{certificate_paths, generate_self_signed};
+
+    let (cert, key) = generate_self_signed(vec!["idleview.local".to_string()]).unwrap();
+    assert!(cert.starts_with("-----BEGIN CERTIFICATE-----"));
+    assert!(key.contains("PRIVATE KEY-----"));
+
+    // A custom certificate needs its key too
+    let tls = TlsSettings {
+        enabled: true,
+        cert_path: Some("/etc/idleview/cert.pem".to_string()),
+        key_path: None,
+    };
+    assert!(certificate_paths(&tls).is_err());
+}
+
+#[test]
+fn test_cors_origin_allowlist() {
+    use idleview_lib::http_server::cors_origin_allowed;
+
+    // Default: only pages on this machine or the local network
+    assert!(cors_origin_allowed("http://192.168.1.20:3000", &[]));
+    assert!(cors_origin_allowed("http://localhost:5173", &[]));
+    assert!(cors_origin_allowed("http://panel.local", &[]));
+    assert!(!cors_origin_allowed("https://example.com", &[]));
+    assert!(!cors_origin_allowed("http://8.8.8.8", &[]));
+
+    let allowed = vec!["https://panel.example.com/".to_string()];
+    assert!(cors_origin_allowed("https://panel.example.com", &allowed));
+    assert!(!cors_origin_allowed("http://192.168.1.20:3000", &allowed));
+    assert!(cors_origin_allowed("https://example.com", &["*".to_string()]));
+}
+
+#[test]
+fn test_rate_limiter_refills_per_client() {
+    use idleview_lib::rate_limit::{RateLimiter, RequestKind};
+    use std::net::IpAddr;
+    use std::time::{Duration, Instant};
+
+    let limiter = RateLimiter::new();
+    let dashboard: IpAddr = "192.168.1.50".parse().unwrap();
+    let phone: IpAddr = "192.168.1.51".parse().unwrap();
+    let start = Instant::now();
+
+    for _ in 0..3 {
+        assert!(limiter.check(dashboard, RequestKind::Write, 3, start).is_ok());
+    }
+    let retry_after = limiter.check(dashboard, RequestKind::Write, 3, start).unwrap_err();
+    assert_eq!(retry_after, Duration::from_secs(20));
+
+    // Other clients and reads have their own buckets
+    assert!(limiter.check(phone, RequestKind::Write, 3, start).is_ok());
+    assert!(limiter.check(dashboard, RequestKind::Read, 3, start).is_ok());
+
+    // One request comes back every 20 seconds at 3 per minute
+    assert!(limiter.check(dashboard, RequestKind::Write, 3, start + Duration::from_secs(20)).is_ok());
+    assert!(limiter.check(dashboard, RequestKind::Write, 3, start + Duration::from_secs(20)).is_err());
+}
+
+#[test]
+fn test_access_log_levels_and_rotation() {
+    use idleview_lib::access_log::{append_line, should_log};
+    use idleview_lib::settings_manager::AccessLogLevel;
+
+    assert!(!should_log(AccessLogLevel::Off, false, 500));
+    assert!(should_log(AccessLogLevel::Errors, true, 404));
+    assert!(!should_log(AccessLogLevel::Errors, false, 200));
+    assert!(should_log(AccessLogLevel::Changes, false, 200));
+    assert!(!should_log(AccessLogLevel::Changes, true, 200));
+    assert!(should_log(AccessLogLevel::All, true, 200));
+
+    let dir = std::env::temp_dir().join(format!("idleview-access-log-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("access.log");
+    append_line(&path, "first", 10).unwrap();
+    append_line(&path, "second", 10).unwrap();
+    append_line(&path, "third", 10).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "third\n");
+    assert_eq!(std::fs::read_to_string(dir.join("access.log.1")).unwrap(), "second\n");
+    assert_eq!(std::fs::read_to_string(dir.join("access.log.2")).unwrap(), "first\n");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_mdns_service_follows_server_settings() {
+    use idleview_lib::discovery::{service_info, SERVICE_TYPE};
+    use idleview_lib::settings_manager::{ServerBind, ServerSettings};
+
+    let mut server = ServerSettings::default();
+    server.tls.enabled = true;
+    let service = service_info(&server).unwrap().unwrap();
+    assert_eq!(service.get_type(), SERVICE_TYPE);
+    assert_eq!(service.get_port(), 8737);
+    assert_eq!(service.get_property_val_str("scheme"), Some("https"));
+
+    // Nothing to advertise when only this machine can connect
+    server.bind = ServerBind::Localhost;
+    assert!(service_info(&server).unwrap().is_none());
+}
+
+#[test]
+fn test_ssdp_answers_matching_searches() {
+    use idleview_lib::ssdp::{matching_targets, search_response, search_target, DEVICE_TYPE};
+
+    let request = "M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: ssdp:all\r\n\r\n";
+    assert_eq!(search_target(request).as_deref(), Some("ssdp:all"));
+    assert!(search_target("NOTIFY * HTTP/1.1\r\nNT: upnp:rootdevice\r\n\r\n").is_none());
+
+    let uuid = "4a1b2c3d-0000-4000-a000-000000000001";
+    assert_eq!(matching_targets("ssdp:all", uuid).len(), 3);
+    assert_eq!(matching_targets("urn:schemas-sonos-com:device:ZonePlayer:1", uuid).len(), 0);
+    let matched = matching_targets(DEVICE_TYPE, uuid);
+    assert_eq!(matched[0].1, format!("uuid:{}::{}", uuid, DEVICE_TYPE));
+
+    let response = search_response(&matched[0].0, &matched[0].1, "http://192.168.1.20:8737/description.xml");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("LOCATION: http://192.168.1.20:8737/description.xml\r\n"));
+    assert!(response.ends_with("\r\n\r\n"));
+}
+
+#[test]
+fn test_weather_updated_event() {
+    use idleview_api::events::{EventEnvelope, ServerEvent};
+    use idleview_lib::event_hub::EventPriority;
+
+    let weather: idleview_api::WeatherData = serde_json::from_value(serde_json::json!({
+        "temperature": 21.5, "temperature_unit": "°C", "humidity": 40.0,
+        "wind_speed": 12.0, "wind_speed_unit": "km/h", "wind_speed_label": "Light breeze",
+        "cloudcover": 20.0, "rain": 0.0, "snowfall": 0.0,
+        "sunrise": "06:45", "sunset": "19:10", "timezone": "Europe/Bucharest"
+    }))
+    .unwrap();
+    let event = ServerEvent::WeatherUpdated(Box::new(weather));
+    // Periodic refreshes are the first to go when a client falls behind
+    assert_eq!(EventPriority::of(&event), EventPriority::Low);
+
+    let json = serde_json::to_value(EventEnvelope::new(event, "weather")).unwrap();
+    assert_eq!(json["type"], "weather-updated");
+    assert_eq!(json["payload"]["temperature"], 21.5);
+}
+
+#[test]
+fn test_event_hub_feeds_sse_and_websocket_alike() {
+    use idleview_lib::event_hub::{EventHub, EventPriority};
+
+    let hub = EventHub::new(8);
+    let sse = hub.subscribe("sse");
+    let websocket = hub.subscribe("websocket");
+
+    hub.publish("settings-updated".to_string(), EventPriority::Normal);
+    hub.publish("weather-updated".to_string(), EventPriority::Low);
+
+    for subscription in [&sse, &websocket] {
+        let received: Vec<String> = std::iter::from_fn(|| subscription.try_recv()).map(|e| e.to_string()).collect();
+        assert_eq!(received, vec!["settings-updated", "weather-updated"]);
+    }
+    let transports: Vec<String> = hub.client_stats().into_iter().map(|stats| stats.transport).collect();
+    assert_eq!(transports, vec!["sse", "websocket"]);
+}
+
+#[test]
+fn test_openapi_document_describes_routes() {
+    use idleview_lib::openapi::openapi_document;
+
+    let document = openapi_document();
+    assert_eq!(document["openapi"], "3.0.3");
+
+    let section = &document["paths"]["/api/v1/settings/{section}"];
+    assert_eq!(section["get"]["parameters"][0]["name"], "section");
+    assert!(section["patch"]["security"].is_array());
+
+    // Pairing is how a device gets a token, so it can't require one
+    assert!(document["paths"]["/api/v1/pair"]["post"]["security"].is_null());
+    assert!(document["paths"]["/api/v1/pair"]["post"]["responses"]["201"].is_object());
+
+    // Every referenced schema is defined
+    let text = document.to_string();
+    for reference in text.split("\"$ref\":\"#/components/schemas/").skip(1) {
+        let name = reference.split('"').next().unwrap();
+        assert!(document["components"]["schemas"][name].is_object(), "missing schema {}", name);
+    }
+}
+
+#[test]
+fn test_cached_weather_freshness() {
+    use idleview_lib::http_server::weather_is_fresh;
+
+    let fetched_at = 1_700_000_000_000;
+    assert!(weather_is_fresh(fetched_at, fetched_at + 14 * 60_000, 15));
+    assert!(!weather_is_fresh(fetched_at, fetched_at + 15 * 60_000, 15));
+    // A clock that went backwards doesn't make the cache look ancient
+    assert!(weather_is_fresh(fetched_at, fetched_at - 1000, 15));
+}
+
+#[test]
+fn test_system_status_finds_settings_disk() {
+    use idleview_lib::system::{containing_mount, system_status};
+    use std::path::Path;
+
+    let mounts = [Path::new("/"), Path::new("/boot"), Path::new("/home")];
+    assert_eq!(containing_mount(&mounts, Path::new("/home/pi/.config/idleview/settings.json")), Some(2));
+    assert_eq!(containing_mount(&mounts, Path::new("/var/log")), Some(0));
+    assert_eq!(containing_mount(&mounts[1..], Path::new("/var/log")), None);
+
+    let status = system_status();
+    assert_eq!(status.app_version, env!("CARGO_PKG_VERSION"));
+    assert!(status.memory.total >= status.memory.available);
+}
+
+#[test]
+fn test_cpu_usage() {
+    use idleview_lib::display_state::{resolve_display_state_impl, DataAvailability};
+    use idleview_lib::settings_manager::Settings;
+    use idleview_lib::system::cpu_usage;
+
+    let usage = cpu_usage().unwrap();
+    assert!(!usage.cores.is_empty());
+    assert!((0.0..=100.0).contains(&usage.total));
+    assert!(usage.load_average.one >= 0.0);
+    // Later readings compare with the last one instead of waiting
+    assert_eq!(cpu_usage().unwrap().cores.len(), usage.cores.len());
+
+    // Needs no sensor, unlike the temperature next to it
+    let mut display = Settings::default().display;
+    display.show_cpu_usage = true;
+    let available = DataAvailability { weather: true, cpu_temp: false, wifi: false, battery: false };
+    let state = resolve_display_state_impl(&display, available, false);
+    let widget = |id: &str| state.widgets.iter().find(|w| w.id == id).unwrap().clone();
+    assert!(widget("cpu_usage").visible);
+    assert!(!widget("cpu_temp").visible);
+}
+
+#[test]
+fn test_debug_info_redacts_api_keys() {
+    use idleview_lib::debug_info_impl;
+    use idleview_lib::settings_manager::Settings;
+
+    let mut settings = Settings::default();
+    settings.photos.api_keys.unsplash = Some("sk-very-secret-unsplash-key".to_string());
+
+    let info = debug_info_impl(
+        &settings,
+        None,
+        Some("forest autumn".to_string()),
+        None,
+        None,
+        Some(12.5),
+        None,
+        None,
+        Some(40.0),
+    );
+    assert_eq!(info.api_key_status, "Available");
+    assert_eq!(info.query, "forest autumn");
+    assert_eq!(info.photo_age, "unknown");
+    assert_eq!(info.temperature, "12.5°C");
+    assert!(!serde_json::to_string(&info).unwrap().contains("sk-very-secret"));
+}
+
+#[test]
+fn test_screenshot_downscale_keeps_aspect_ratio() {
+    use idleview_lib::screenshot::{downscale, scaled_size};
+    use std::io::Cursor;
+
+    assert_eq!(scaled_size(1920, 1080, 0.5), (960, 540));
+    assert_eq!(scaled_size(1920, 1080, 0.0001), (1, 1));
+
+    let mut png = Cursor::new(Vec::new());
+    image::RgbImage::new(64, 36)
+        .write_to(&mut png, image::ImageFormat::Png)
+        .unwrap();
+    let smaller = downscale(png.get_ref(), 0.25).unwrap();
+    let decoded = image::load_from_memory(&smaller).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (16, 9));
+    assert!(downscale(b"not a png", 0.5).is_err());
+}
+
+#[test]
+fn test_display_power_parses_tool_output() {
+    use idleview_lib::display_power::{parse_vcgencmd, parse_wlr_randr, parse_xset_query, PowerState};
+
+    let xset = "DPMS (Energy Star):\n  Standby: 600    Suspend: 600    Off: 600\n  DPMS is Enabled\n  Monitor is Off\n";
+    assert_eq!(parse_xset_query(xset), Some(PowerState::Off));
+    assert_eq!(parse_xset_query("  DPMS is Disabled\n"), None);
+
+    let wlr = "HDMI-A-1 \"Dell Inc. DELL P2419H\"\n  Enabled: yes\n  Modes:\n    1920x1080 px\nDSI-1 \"Unknown\"\n  Enabled: no\n";
+    assert_eq!(
+        parse_wlr_randr(wlr),
+        vec![("HDMI-A-1".to_string(), true), ("DSI-1".to_string(), false)]
+    );
+
+    assert_eq!(parse_vcgencmd("display_power=1\n"), Some(PowerState::On));
+    assert_eq!(parse_vcgencmd("display_power=-1"), None);
+}
+
+#[test]
+fn test_brightness_reads_and_writes_sysfs_backlight() {
+    use idleview_lib::brightness::{backlight_percent, find_backlight, set_backlight_percent, to_percent, to_raw};
+
+    let root = std::env::temp_dir().join(format!("idleview-backlight-{}", std::process::id()));
+    let device = root.join("rpi_backlight");
+    std::fs::create_dir_all(&device).unwrap();
+    std::fs::write(device.join("max_brightness"), "255\n").unwrap();
+    std::fs::write(device.join("brightness"), "255\n").unwrap();
+    // A device without a maximum is skipped
+    std::fs::create_dir_all(root.join("acpi_video0")).unwrap();
+
+    assert_eq!(find_backlight(&root), Some(device.clone()));
+    assert_eq!(backlight_percent(&device), Ok(100));
+    set_backlight_percent(&device, 40).unwrap();
+    assert_eq!(std::fs::read_to_string(device.join("brightness")).unwrap(), "102");
+    assert_eq!(backlight_percent(&device), Ok(40));
+
+    assert_eq!(to_raw(100, 7), 7);
+    assert_eq!(to_percent(300, 255), 100);
+    assert_eq!(find_backlight(&root.join("missing")), None);
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_message_queue_orders_by_priority() {
+    use idleview_lib::messages::{validate_message, MessagePriority, MessageQueue, MessageRequest};
+
+    let request = |text: &str, priority| MessageRequest {
+        text: text.to_string(),
+        duration: 10,
+        priority,
+        sound: false,
+    };
+    let queue = MessageQueue::new(3);
+    assert_eq!(queue.push(request("laundry", MessagePriority::Low)).unwrap().position, 0);
+    assert_eq!(queue.push(request("dinner", MessagePriority::Normal)).unwrap().position, 0);
+    assert_eq!(queue.push(request("doorbell", MessagePriority::High)).unwrap().position, 0);
+    assert!(queue.push(request("one too many", MessagePriority::High)).is_err());
+
+    let order: Vec<String> = std::iter::from_fn(|| queue.pop()).map(|message| message.text).collect();
+    assert_eq!(order, vec!["doorbell", "dinner", "laundry"]);
+
+    assert!(validate_message(&request("Dinner's ready!", MessagePriority::Normal)).is_empty());
+    let invalid = MessageRequest { duration: 0, ..request("  ", MessagePriority::Normal) };
+    assert_eq!(validate_message(&invalid).len(), 2);
+}
+
+#[test]
+fn test_localhost_only_rejects_other_machines() {
+    use idleview_lib::http_server::peer_allowed;
+    use idleview_lib::settings_manager::ServerBind;
+    use std::net::IpAddr;
+
+    let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+    assert!(peer_allowed(ServerBind::All, ip("192.168.1.20")));
+    assert!(peer_allowed(ServerBind::Localhost, ip("127.0.0.1")));
+    assert!(peer_allowed(ServerBind::Localhost, ip("::1")));
+    assert!(peer_allowed(ServerBind::Localhost, ip("::ffff:127.0.0.1")));
+    assert!(!peer_allowed(ServerBind::Localhost, ip("192.168.1.20")));
+    assert!(!peer_allowed(ServerBind::Localhost, ip("::ffff:192.168.1.20")));
+}
+
+#[test]
+fn test_control_panel_is_embedded() {
+    use idleview_lib::control_panel::{asset, asset_path};
+
+    assert_eq!(asset_path("/"), "index.html");
+    assert_eq!(asset_path("/assets/"), "assets/index.html");
+    assert_eq!(asset_path("/favicon.ico"), "favicon.ico");
+
+    let index = asset("index.html").expect("index.html is embedded");
+    assert_eq!(index.content_type, "text/html");
+    assert!(String::from_utf8_lossy(&index.data).contains("<html"));
+    assert!(asset("missing.js").is_none());
+}
+
+#[test]
+fn test_control_panel_caching_headers() {
+    use axum::http::{header, HeaderMap, HeaderValue};
+    use idleview_lib::control_panel::{
+        asset, cache_control, is_fingerprinted, not_modified, version, version_references, CACHE_IMMUTABLE,
+        CACHE_REVALIDATE,
+    };
+
+    assert!(is_fingerprinted("assets/index-CCkbbDEr.js"));
+    assert!(!is_fingerprinted("favicon.ico"));
+    assert!(!is_fingerprinted("index.html"));
+
+    // Files without a hash in their name get one in the query instead
+    let favicon = version("favicon.ico").unwrap();
+    let html = version_references(r#"<link href="/favicon.ico"><script src="/assets/index-CCkbbDEr.js">"#);
+    assert_eq!(
+        html,
+        format!(r#"<link href="/favicon.ico?v={}"><script src="/assets/index-CCkbbDEr.js">"#, favicon)
+    );
+    assert_eq!(cache_control("favicon.ico", Some(&favicon)), CACHE_IMMUTABLE);
+    assert_eq!(cache_control("favicon.ico", Some("stale")), CACHE_REVALIDATE);
+    assert_eq!(cache_control("index.html", None), CACHE_REVALIDATE);
+
+    let index = asset("index.html").unwrap();
+    assert!(String::from_utf8_lossy(&index.data).contains(&format!("/favicon.ico?v={}", favicon)));
+    let mut headers = HeaderMap::new();
+    headers.insert(header::IF_NONE_MATCH, HeaderValue::from_str(&index.etag).unwrap());
+    assert!(not_modified(&headers, &index.etag, None));
+    headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"outdated\""));
+    assert!(!not_modified(&headers, &index.etag, None));
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::IF_MODIFIED_SINCE, HeaderValue::from_static("Sat, 17 Oct 2026 10:00:00 GMT"));
+    assert!(not_modified(&headers, "\"etag\"", Some(1_792_224_000)));
+    assert!(!not_modified(&headers, "\"etag\"", Some(1_792_260_001)));
+}
+
+#[test]
+fn test_compression_defaults_on_for_existing_settings() {
+    use idleview_lib::settings_manager::{validate_settings_json, ServerSettings};
+
+    // Settings saved before the option existed keep compressing
+    let server: ServerSettings = serde_json::from_value(serde_json::json!({ "port": 8737 })).unwrap();
+    assert!(server.compression);
+    assert!(ServerSettings::default().compression);
+
+    let problems = validate_settings_json(&serde_json::json!({ "server": { "compression": "yes" } }));
+    assert_eq!(problems.len(), 1, "{:?}", problems);
+}
+
+#[test]
+fn test_health_overall_status() {
+    use idleview_lib::health::{overall_status, DiskCacheHealth, HealthStatus, ProviderStatus, LOW_DISK_BYTES};
+
+    let provider = |name: &str, failing: bool| ProviderStatus {
+        name: name.to_string(),
+        status: if failing { HealthStatus::Degraded } else { HealthStatus::Healthy },
+        last_success: Some(1_700_000_000_000),
+        last_error: failing.then(|| "Failed to fetch weather: timed out".to_string()),
+    };
+    let disk = |writable, available| DiskCacheHealth { writable, available };
+
+    // Nothing called yet is healthy
+    assert_eq!(overall_status(&[], &disk(true, None)), HealthStatus::Healthy);
+    let mixed = [provider("photos", false), provider("weather", true)];
+    assert_eq!(overall_status(&mixed, &disk(true, Some(u64::MAX))), HealthStatus::Degraded);
+    let down = [provider("photos", true), provider("weather", true)];
+    assert_eq!(overall_status(&down, &disk(true, Some(u64::MAX))), HealthStatus::Unhealthy);
+
+    let fine = [provider("photos", false)];
+    assert_eq!(overall_status(&fine, &disk(true, Some(LOW_DISK_BYTES - 1))), HealthStatus::Degraded);
+    assert_eq!(overall_status(&fine, &disk(false, Some(u64::MAX))), HealthStatus::Unhealthy);
+}
+
+#[test]
+fn test_metrics_render_prometheus_text() {
+    use idleview_lib::metrics::{Gauges, Metrics};
+    use std::time::Duration;
+
+    let metrics = Metrics::new();
+    metrics.record_request("GET", "/api/v1/settings", 200);
+    metrics.record_request("GET", "/api/v1/settings", 200);
+    metrics.record_fetch("photo", "unsplash", true);
+    metrics.record_fetch("photo", "pexels", false);
+    metrics.record_fetch("weather", "openmeteo", true);
+    metrics.observe_weather_latency(Duration::from_millis(300));
+
+    let text = metrics.render(&Gauges { cpu_temperature: Some(48.5), ..Default::default() });
+    assert!(text.contains("idleview_http_requests_total{method=\"GET\",route=\"/api/v1/settings\",status=\"200\"} 2"));
+    assert!(text.contains("idleview_photo_fetches_total{provider=\"pexels\",result=\"failure\"} 1"));
+    assert!(text.contains("idleview_weather_fetches_total{provider=\"openmeteo\",result=\"success\"} 1"));
+    assert!(!text.contains("idleview_weather_fetches_total{provider=\"unsplash\""));
+    assert!(text.contains("idleview_weather_fetch_duration_seconds_bucket{le=\"0.25\"} 0"));
+    assert!(text.contains("idleview_weather_fetch_duration_seconds_bucket{le=\"0.5\"} 1"));
+    assert!(text.contains("idleview_weather_fetch_duration_seconds_count 1"));
+    assert!(text.contains("idleview_cpu_temperature_celsius 48.5"));
+}
+
+#[test]
+fn test_power_control_is_opt_in() {
+    use idleview_lib::power::{power_commands, PowerAction};
+    use idleview_lib::settings_manager::ServerSettings;
+
+    assert!(!ServerSettings::default().allow_power_control);
+    let server: ServerSettings = serde_json::from_value(serde_json::json!({ "port": 8737 })).unwrap();
+    assert!(!server.allow_power_control);
+
+    if cfg!(target_os = "linux") {
+        let reboot = power_commands(PowerAction::Reboot);
+        assert_eq!(reboot[0], ("systemctl", vec!["reboot"]));
+        assert_eq!(reboot[1], ("shutdown", vec!["-r", "now"]));
+        assert_eq!(power_commands(PowerAction::Shutdown)[0], ("systemctl", vec!["poweroff"]));
+    }
+}
+
+#[test]
+fn test_sync_takes_lead_settings_except_local_sections() {
+    use idleview_lib::settings_manager::{Settings, SyncSettings};
+    use idleview_lib::sync::{patch_changes, settings_patch};
+
+    let mut lead = Settings { language: "ro-RO".to_string(), ..Default::default() };
+    lead.server.port = 9000;
+    let sync = SyncSettings { local_sections: vec!["display".to_string()], ..Default::default() };
+
+    let patch = settings_patch(&lead, &sync);
+    assert_eq!(patch["language"], "ro-RO");
+    assert!(patch.get("photos").is_some());
+    assert!(patch.get("server").is_none());
+    assert!(patch.get("sync").is_none());
+    assert!(patch.get("display").is_none());
+
+    let mut local = Settings::default();
+    assert!(patch_changes(&local, &patch).unwrap());
+    local.language = "ro-RO".to_string();
+    assert!(!patch_changes(&local, &patch).unwrap());
+}
+
+#[test]
+fn test_sse_parser_splits_events_across_chunks() {
+    use idleview_api::events::SseParser;
+
+    let mut parser = SseParser::default();
+    assert!(parser.push(b": keep-alive\n\ndata: {\"a\"").is_empty());
+    assert_eq!(parser.push(b":1}\n\ndata: two\r\n\r\n"), vec!["{\"a\":1}", "two"]);
+}
+
+#[test]
+fn test_webhook_signing_and_filters() {
+    use idleview_lib::settings_manager::Webhook;
+    use idleview_lib::webhooks::{should_retry, sign, wants};
+    use reqwest::StatusCode;
+
+    // RFC 4231, test case 2
+    assert_eq!(
+        sign("Jefe", b"what do ya want for nothing?"),
+        "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+
+    let mut webhook = Webhook { url: "http://hooks.local/idleview".to_string(), events: Vec::new(), secret: None };
+    assert!(wants(&webhook, "photo-updated"));
+    webhook.events = vec!["weather-alert".to_string()];
+    assert!(wants(&webhook, "weather-alert"));
+    assert!(!wants(&webhook, "photo-updated"));
+
+    assert!(should_retry(None));
+    assert!(should_retry(Some(StatusCode::BAD_GATEWAY)));
+    assert!(should_retry(Some(StatusCode::TOO_MANY_REQUESTS)));
+    assert!(!should_retry(Some(StatusCode::NOT_FOUND)));
+}
+
+#[test]
+fn test_cpu_temperature_reported_once_per_hot_spell() {
+    use idleview_lib::system::temperature_crossed;
+
+    let mut hot = false;
+    assert!(!temperature_crossed(70.0, 80.0, &mut hot));
+    assert!(temperature_crossed(81.0, 80.0, &mut hot));
+    assert!(!temperature_crossed(82.0, 80.0, &mut hot));
+    assert!(!temperature_crossed(77.0, 80.0, &mut hot));
+    assert!(!temperature_crossed(74.0, 80.0, &mut hot));
+    assert!(temperature_crossed(80.0, 80.0, &mut hot));
+}
+
+#[test]
+fn test_mqtt_commands_and_state_topics() {
+    use idleview_api::{DisplayPower, PowerState, ServerEvent};
+    use idleview_lib::mqtt::{parse_command, state_for_event, topic, Command};
+
+    assert_eq!(topic("home/frame/", "status"), "home/frame/status");
+    assert_eq!(parse_command("idleview", "idleview/command/next_photo", b""), Ok(Command::NextPhoto));
+    assert_eq!(
+        parse_command("idleview", "idleview/command/display", b"OFF"),
+        Ok(Command::DisplayPower(PowerState::Off))
+    );
+    assert_eq!(parse_command("idleview", "idleview/command/brightness", b" 40\n"), Ok(Command::Brightness(40)));
+    assert!(parse_command("idleview", "idleview/command/brightness", b"140").is_err());
+    assert!(parse_command("idleview", "idleview/command/reboot", b"").is_err());
+    assert!(parse_command("idleview", "other/command/next_photo", b"").is_err());
+
+    let power = ServerEvent::DisplayPowerChanged(DisplayPower { power: PowerState::On });
+    assert_eq!(state_for_event(&power), Some(("display/power", "on".to_string())));
+    assert_eq!(state_for_event(&ServerEvent::LocationChanged { active: None }), None);
+}
+
+#[test]
+fn test_ical_parses_and_expands_events() {
+    use idleview_lib::calendar::{current_titles, merge_events, parse_ical};
+
+    let ical = "BEGIN:VCALENDAR\r\n\
+BEGIN:VEVENT\r\n\
+UID:standup\r\n\
+SUMMARY:Stand\r\n  up\\, daily\r\n\
+DTSTART:20300107T090000Z\r\n\
+DURATION:PT15M\r\n\
+RRULE:FREQ=WEEKLY;BYDAY=MO,WE;COUNT=4\r\n\
+EXDATE:20300109T090000Z\r\n\
+BEGIN:VALARM\r\n\
+TRIGGER:-PT5M\r\n\
+SUMMARY:Reminder\r\n\
+END:VALARM\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+UID:standup\r\n\
+RECURRENCE-ID:20300114T090000Z\r\n\
+SUMMARY:Stand up\\, moved\r\n\
+DTSTART:20300114T100000Z\r\n\
+DTEND:20300114T101500Z\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+SUMMARY:Cancelled\r\n\
+STATUS:CANCELLED\r\n\
+DTSTART:20300108T090000Z\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+SUMMARY:Holiday\r\n\
+DTSTART;VALUE=DATE:20300110\r\n\
+END:VEVENT\r\n\
+END:VCALENDAR\r\n";
+
+    let ms = |rfc3339: &str| chrono::DateTime::parse_from_rfc3339(rfc3339).unwrap().timestamp_millis() as u64;
+    let now = ms("2030-01-01T00:00:00Z");
+    let events = merge_events(parse_ical(ical, "Work", now, ms("2030-02-01T00:00:00Z")), 10);
+
+    let summary: Vec<(&str, u64)> = events
+        .iter()
+        .filter(|event| !event.all_day)
+        .map(|event| (event.title.as_str(), event.start))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("Stand up, daily", ms("2030-01-07T09:00:00Z")),
+            ("Stand up, moved", ms("2030-01-14T10:00:00Z")),
+            ("Stand up, daily", ms("2030-01-16T09:00:00Z")),
+        ]
+    );
+    assert_eq!(events[0].end - events[0].start, 15 * 60 * 1000);
+    assert_eq!(events[0].calendar, "Work");
+
+    let holiday = events.iter().find(|event| event.all_day).unwrap();
+    assert_eq!(holiday.title, "Holiday");
+    assert_eq!(holiday.end - holiday.start, 24 * 60 * 60 * 1000);
+
+    assert_eq!(current_titles(&events, ms("2030-01-07T09:05:00Z")), vec!["Stand up, daily".to_string()]);
+    assert_eq!(merge_events(events, 2).len(), 2);
+
+    // Ended events drop out
+    let later = parse_ical(ical, "Work", ms("2030-01-15T00:00:00Z"), ms("2030-02-01T00:00:00Z"));
+    assert_eq!(later.len(), 1);
+}
+
+#[test]
+fn test_greeting_from_time_weather_and_language() {
+    use idleview_api::WeatherData;
+    use idleview_lib::greeting::compose_greeting;
+    use idleview_lib::settings_manager::GreetingSettings;
+
+    let weather = WeatherData {
+        temperature: 1.6,
+        temperature_unit: "celsius".to_string(),
+        humidity: 60.0,
+        wind_speed: 5.0,
+        wind_speed_unit: "kmh".to_string(),
+        wind_speed_label: "km/h".to_string(),
+        cloudcover: 10.0,
+        rain: 0.0,
+        snowfall: 0.0,
+        sunrise: String::new(),
+        sunset: String::new(),
+        timezone: "Europe/Bucharest".to_string(),
+        alerts: Vec::new(),
+    };
+    let settings = GreetingSettings::default();
+    assert_eq!(compose_greeting(&settings, Some(&weather), 8, "en"), "Good morning — cold and clear, 2°C");
+    assert_eq!(compose_greeting(&settings, Some(&weather), 20, "ro-RO"), "Bună seara — frig și senin, 2°C");
+    assert_eq!(compose_greeting(&settings, None, 23, "en"), "Good night");
+
+    let rainy = WeatherData { temperature: 77.0, temperature_unit: "fahrenheit".to_string(), rain: 1.2, ..weather };
+    let custom = GreetingSettings {
+        template: "{greeting}, {name}! It's {feel} out, {temperature}.".to_string(),
+        name: Some("Ana".to_string()),
+        ..GreetingSettings::default()
+    };
+    assert_eq!(compose_greeting(&custom, Some(&rainy), 14, "en"), "Good afternoon, Ana! It's warm out, 77°F.");
+    let nameless = GreetingSettings { name: None, ..custom };
+    assert_eq!(compose_greeting(&nameless, Some(&rainy), 14, "en"), "Good afternoon! It's warm out, 77°F.");
+}
+
+#[test]
+fn test_quote_of_the_day_bundled_and_remote_formats() {
+    use chrono::NaiveDate;
+    use idleview_lib::quotes::{bundled_quote, parse_remote_quote};
+    use idleview_lib::settings_manager::QuoteSource;
+
+    let day = NaiveDate::from_ymd_opt(2026, 3, 14).unwrap();
+    let quote = bundled_quote(day);
+    assert_eq!(quote, bundled_quote(day));
+    assert_eq!(quote.source, QuoteSource::Bundled);
+    assert_eq!(quote.date, "2026-03-14");
+    assert!(quote.author.is_some());
+    assert_ne!(quote.text, bundled_quote(day.succ_opt().unwrap()).text);
+
+    let zen = r#"[{"q": "Well begun is half done. ", "a": "Aristotle", "h": "<blockquote>...</blockquote>"}]"#;
+    assert_eq!(parse_remote_quote(zen).unwrap(), ("Well begun is half done.".to_string(), Some("Aristotle".to_string())));
+    let quotable = r#"{"content": "Stay hungry.", "author": ""}"#;
+    assert_eq!(parse_remote_quote(quotable).unwrap(), ("Stay hungry.".to_string(), None));
+    assert!(parse_remote_quote(r#"[]"#).is_err());
+    assert!(parse_remote_quote("<html>").is_err());
+}
+
+#[test]
+fn test_news_feeds_parse_rss_and_atom() {
+    use idleview_lib::news::{merge_headlines, parse_feed};
+
+    let rss = r#"<?xml version="1.0"?>
+<rss version="2.0"><channel><title>Local News</title><link>https://news.example.com/</link>
+<item><title><![CDATA[Bridge reopens <b>today</b>]]></title><link>https://news.example.com/bridge</link>
+<pubDate>Tue, 10 Mar 2026 08:00:00 +0000</pubDate></item>
+<item><title>Rates &lt; 5% &amp; falling &#8212; again</title><link>https://news.example.com/rates</link>
+<pubDate>Mon, 09 Mar 2026 18:30:00 +0100</pubDate></item>
+<item><title>Third</title></item>
+</channel></rss>"#;
+    let headlines = parse_feed(rss, "Local", 2);
+    assert_eq!(headlines.len(), 2);
+    assert_eq!(headlines[0].title, "Bridge reopens today");
+    assert_eq!(headlines[0].link.as_deref(), Some("https://news.example.com/bridge"));
+    assert_eq!(headlines[0].published, Some(1773129600000));
+    assert_eq!(headlines[1].title, "Rates < 5% & falling \u{2014} again");
+    assert_eq!(headlines[1].feed, "Local");
+
+    let atom = r#"<feed xmlns="http://www.w3.org/2005/Atom"><title>Blog</title>
+<entry><title type="html">Release &lt;em&gt;2.0&lt;/em&gt;</title>
+<link rel="self" href="https://blog.example.com/feed/1"/><link rel="alternate" href="https://blog.example.com/2-0"/>
+<updated>2026-03-10T09:00:00Z</updated></entry>
+</feed>"#;
+    let entries = parse_feed(atom, "Blog", 5);
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].title, "Release 2.0");
+    assert_eq!(entries[0].link.as_deref(), Some("https://blog.example.com/2-0"));
+
+    let merged = merge_headlines(parse_feed(rss, "Local", 5).into_iter().chain(entries).collect(), 3);
+    let titles: Vec<_> = merged.iter().map(|headline| headline.title.as_str()).collect();
+    assert_eq!(titles, ["Release 2.0", "Bridge reopens today", "Rates < 5% & falling \u{2014} again"]);
+}
+
+#[test]
+fn test_stock_quotes_from_yahoo_and_finnhub() {
+    use idleview_lib::stocks::{parse_finnhub_quote, parse_yahoo_chart};
+
+    let chart = r#"{"chart": {"result": [{"meta": {"currency": "USD", "symbol": "AAPL",
+        "regularMarketPrice": 210.0, "chartPreviousClose": 200.0}}], "error": null}}"#;
+    let quote = parse_yahoo_chart("AAPL", chart).unwrap();
+    assert_eq!(quote.price, 210.0);
+    assert_eq!(quote.change, 10.0);
+    assert_eq!(quote.change_percent, 5.0);
+    assert_eq!(quote.currency.as_deref(), Some("USD"));
+    assert!(parse_yahoo_chart("NOPE", r#"{"chart": {"result": null, "error": {"code": "Not Found"}}}"#).is_err());
+
+    let finnhub = parse_finnhub_quote("MSFT", r#"{"c": 401.5, "d": -3.5, "dp": -0.8642, "pc": 405.0}"#).unwrap();
+    assert_eq!(finnhub.price, 401.5);
+    assert_eq!(finnhub.change, -3.5);
+    assert_eq!(finnhub.change_percent, -0.8642);
+    assert!(parse_finnhub_quote("NOPE", r#"{"c": 0, "d": null, "dp": null, "pc": 0}"#).is_err());
+}
+
+#[test]
+fn test_crypto_prices_in_coin_order() {
+    use idleview_lib::crypto::parse_prices;
+
+    let coins = ["ethereum", "bitcoin", "not-a-coin"].map(String::from);
+    let json = r#"{"bitcoin": {"eur": 58000.5, "eur_24h_change": -1.25}, "ethereum": {"eur": 3100}}"#;
+    let prices = parse_prices(json, &coins, "EUR").unwrap();
+    assert_eq!(prices.len(), 2);
+    assert_eq!(prices[0].coin, "ethereum");
+    assert_eq!(prices[0].price, 3100.0);
+    assert_eq!(prices[0].change_24h_percent, None);
+    assert_eq!(prices[1].coin, "bitcoin");
+    assert_eq!(prices[1].change_24h_percent, Some(-1.25));
+    assert!(parse_prices("rate limited", &coins, "eur").is_err());
+}
+
+#[test]
+fn test_sports_matches_from_both_providers() {
+    use idleview_lib::sports::{arrange, parse_football_data, parse_thesportsdb, MatchStatus};
+
+    let thesportsdb = parse_thesportsdb(
+        r#"{"events": [
+            {"idEvent": "1", "strLeague": "English Premier League", "strHomeTeam": "Arsenal", "strAwayTeam": "Chelsea",
+             "intHomeScore": "2", "intAwayScore": "1", "strTimestamp": "2024-05-04T14:00:00", "strStatus": "2H"},
+            {"idEvent": "2", "strHomeTeam": "Spurs", "strAwayTeam": "Arsenal", "intHomeScore": null, "intAwayScore": null,
+             "dateEvent": "2024-05-11", "strTime": "16:30:00", "strStatus": "NS"}]}"#,
+    )
+    .unwrap();
+    assert_eq!(thesportsdb[0].status, MatchStatus::Live);
+    assert_eq!((thesportsdb[0].home_score, thesportsdb[0].away_score), (Some(2), Some(1)));
+    assert_eq!(thesportsdb[0].starts_at, Some(1_714_831_200_000));
+    assert_eq!(thesportsdb[1].status, MatchStatus::Scheduled);
+    assert_eq!(thesportsdb[1].starts_at, Some(1_715_445_000_000));
+    // A team's past matches come under "results", often without a status
+    let results = parse_thesportsdb(
+        r#"{"results": [{"idEvent": "0", "strHomeTeam": "Arsenal", "strAwayTeam": "Bournemouth",
+            "intHomeScore": "3", "intAwayScore": "0", "strTimestamp": "2024-04-27T11:30:00+00:00"}]}"#,
+    )
+    .unwrap();
+    assert_eq!(results[0].status, MatchStatus::Finished);
+    assert!(parse_thesportsdb(r#"{"events": null}"#).unwrap().is_empty());
+
+    let football_data = parse_football_data(
+        r#"{"matches": [{"id": 436, "utcDate": "2024-05-04T14:00:00Z", "status": "PAUSED",
+            "competition": {"name": "Premier League"},
+            "homeTeam": {"name": "Arsenal FC", "shortName": "Arsenal"}, "awayTeam": {"name": "Chelsea FC"},
+            "score": {"fullTime": {"home": 1, "away": 1}}}]}"#,
+    )
+    .unwrap();
+    assert_eq!(football_data[0].id, "436");
+    assert_eq!((football_data[0].home_team.as_str(), football_data[0].away_team.as_str()), ("Arsenal", "Chelsea FC"));
+    assert_eq!(football_data[0].status, MatchStatus::Live);
+    assert_eq!(football_data[0].home_score, Some(1));
+    assert!(parse_football_data(r#"{"message": "Your API token is invalid.", "errorCode": 400}"#)
+        .unwrap_err()
+        .contains("token is invalid"));
+
+    // A match followed through both its team and its league is listed once
+    let mut matches = thesportsdb.clone();
+    matches.extend(results);
+    matches.extend(thesportsdb);
+    let report = arrange(matches, 5);
+    assert_eq!(report.live.len(), 1);
+    assert_eq!(report.upcoming.len(), 1);
+    assert_eq!(report.results[0].away_team, "Bournemouth");
+    assert!(arrange(report.upcoming.clone(), 0).upcoming.is_empty());
+}
+
+#[test]
+fn test_spotify_pkce_and_currently_playing() {
+    use idleview_lib::now_playing::{changed, NowPlayingSource};
+    use idleview_lib::spotify::{authorize_url, callback_params, code_challenge, parse_currently_playing};
+
+    assert_eq!(code_challenge("a"), "ypeBEsobvcr6wjGzmiPcTaeG7_gUfE5yuYB3ha_uSLs");
+    let url = authorize_url("https://accounts.example.com/authorize", "id", "http://127.0.0.1:8737/cb", "xyz", "a");
+    assert!(url.contains("code_challenge=ypeBEsobvcr6wjGzmiPcTaeG7_gUfE5yuYB3ha_uSLs"));
+    assert!(url.contains("redirect_uri=http%3A%2F%2F127.0.0.1%3A8737%2Fcb"));
+    assert!(url.contains("code_challenge_method=S256"));
+
+    let (code, state) = callback_params("http://127.0.0.1:8737/api/v1/spotify/callback?code=abc&state=xyz").unwrap();
+    assert_eq!((code.as_str(), state.as_str()), ("abc", "xyz"));
+    assert!(callback_params("http://127.0.0.1:8737/api/v1/spotify/callback?error=access_denied&state=xyz")
+        .unwrap_err()
+        .contains("access_denied"));
+
+    let json = r#"{"is_playing": true, "progress_ms": 42000, "currently_playing_type": "track", "item": {
+        "name": "Teardrop", "duration_ms": 330000,
+        "artists": [{"name": "Massive Attack"}, {"name": "Elizabeth Fraser"}],
+        "album": {"name": "Mezzanine", "images": [
+            {"url": "https://i.example.com/small", "width": 64},
+            {"url": "https://i.example.com/large", "width": 640}]}}}"#;
+    let playing = parse_currently_playing(json).unwrap().unwrap();
+    assert_eq!(playing.title, "Teardrop");
+    assert_eq!(playing.artist, "Massive Attack, Elizabeth Fraser");
+    assert_eq!(playing.album.as_deref(), Some("Mezzanine"));
+    assert_eq!(playing.album_art_url.as_deref(), Some("https://i.example.com/large"));
+    assert_eq!(playing.progress_ms, Some(42000));
+    assert_eq!(playing.source, NowPlayingSource::Spotify);
+    assert_eq!(parse_currently_playing(r#"{"is_playing": true, "item": null, "currently_playing_type": "ad"}"#).unwrap(), None);
+
+    // Progress alone isn't a change, pausing is
+    let later = idleview_lib::now_playing::NowPlaying { progress_ms: Some(47000), ..playing.clone() };
+    assert!(!changed(Some(&playing), Some(&later)));
+    let paused = idleview_lib::now_playing::NowPlaying { is_playing: false, ..playing.clone() };
+    assert!(changed(Some(&playing), Some(&paused)));
+    assert!(changed(Some(&playing), None));
+    assert!(!changed(None, None));
+}
+
+#[test]
+fn test_lastfm_recent_track_and_source_priority() {
+    use idleview_lib::lastfm::parse_recent_tracks;
+    use idleview_lib::now_playing::{pick, NowPlayingSource};
+
+    let json = r##"{"recenttracks": {"track": [
+        {"name": "Windowlicker", "artist": {"#text": "Aphex Twin"}, "album": {"#text": "Windowlicker"},
+         "image": [{"#text": "https://i.example.com/34s.png", "size": "small"},
+                   {"#text": "https://i.example.com/300x300.png", "size": "extralarge"}],
+         "@attr": {"nowplaying": "true"}},
+        {"name": "Older", "artist": {"#text": "Someone"}, "date": {"uts": "1700000000"}}],
+        "@attr": {"user": "rj", "total": "2"}}}"##;
+    let latest = parse_recent_tracks(json).unwrap().unwrap();
+    assert_eq!(latest.title, "Windowlicker");
+    assert_eq!(latest.artist, "Aphex Twin");
+    assert_eq!(latest.album.as_deref(), Some("Windowlicker"));
+    assert_eq!(latest.album_art_url.as_deref(), Some("https://i.example.com/300x300.png"));
+    assert!(latest.is_playing);
+    assert_eq!(latest.source, NowPlayingSource::LastFm);
+
+    // A single track comes as an object, and the grey star isn't a cover
+    let scrobbled = parse_recent_tracks(
+        r##"{"recenttracks": {"track": {"name": "Song", "artist": {"#text": "Band"}, "album": {"#text": ""},
+            "image": [{"#text": "https://i.example.com/2a96cbd8b46e442fc41c2b86b821562f.png"}]}}}"##,
+    )
+    .unwrap()
+    .unwrap();
+    assert!(!scrobbled.is_playing);
+    assert_eq!(scrobbled.album, None);
+    assert_eq!(scrobbled.album_art_url, None);
+    assert_eq!(parse_recent_tracks(r#"{"recenttracks": {"track": []}}"#).unwrap(), None);
+    assert!(parse_recent_tracks(r#"{"error": 10, "message": "Invalid API key"}"#)
+        .unwrap_err()
+        .contains("Invalid API key"));
+
+    // Spotify first, but a playing scrobble beats a paused Spotify track
+    let spotify = idleview_lib::now_playing::NowPlaying {
+        source: NowPlayingSource::Spotify,
+        ..latest.clone()
+    };
+    assert_eq!(pick(&[Some(spotify.clone()), Some(latest.clone())]).unwrap().source, NowPlayingSource::Spotify);
+    let paused = idleview_lib::now_playing::NowPlaying { is_playing: false, ..spotify };
+    assert_eq!(pick(&[Some(paused.clone()), Some(latest)]).unwrap().source, NowPlayingSource::LastFm);
+    assert_eq!(pick(&[Some(paused), Some(scrobbled)]).unwrap().source, NowPlayingSource::Spotify);
+    assert_eq!(pick(&[None, None]), None);
+}
+
+#[test]
+fn test_media_sonos_and_cast_messages() {
+    use idleview_lib::media::{decode_cast_message, encode_cast_message, parse_media_status, parse_sonos_position};
+    use idleview_lib::now_playing::{pick, NowPlayingSource};
+
+    let position = r#"<s:Envelope><s:Body><u:GetPositionInfoResponse>
+        <TrackDuration>0:04:10</TrackDuration>
+        <TrackMetaData>&lt;DIDL-Lite&gt;&lt;item&gt;&lt;dc:title&gt;Teardrop&lt;/dc:title&gt;&lt;dc:creator&gt;Massive Attack&lt;/dc:creator&gt;&lt;upnp:album&gt;Mezzanine&lt;/upnp:album&gt;&lt;upnp:albumArtURI&gt;/getaa?s=1&amp;amp;u=x&lt;/upnp:albumArtURI&gt;&lt;/item&gt;&lt;/DIDL-Lite&gt;</TrackMetaData>
+        <RelTime>0:01:05</RelTime></u:GetPositionInfoResponse></s:Body></s:Envelope>"#;
+    let sonos = parse_sonos_position(position, true, "http://192.168.1.20:1400").unwrap();
+    assert_eq!((sonos.title.as_str(), sonos.artist.as_str()), ("Teardrop", "Massive Attack"));
+    assert_eq!(sonos.album.as_deref(), Some("Mezzanine"));
+    assert_eq!(sonos.album_art_url.as_deref(), Some("http://192.168.1.20:1400/getaa?s=1&u=x"));
+    assert_eq!((sonos.progress_ms, sonos.duration_ms), (Some(65_000), Some(250_000)));
+    assert_eq!(sonos.source, NowPlayingSource::Sonos);
+    // Radio has no duration and the song in the stream content
+    let radio = r#"<TrackDuration>0:00:00</TrackDuration><TrackMetaData>&lt;item&gt;&lt;dc:title&gt;x-sonosapi-stream&lt;/dc:title&gt;&lt;r:streamContent&gt;Song - Band&lt;/r:streamContent&gt;&lt;/item&gt;</TrackMetaData>"#;
+    let radio = parse_sonos_position(radio, true, "http://192.168.1.20:1400").unwrap();
+    assert_eq!(radio.title, "Song - Band");
+    assert_eq!(radio.duration_ms, None);
+    assert_eq!(parse_sonos_position("<TrackMetaData>NOT_IMPLEMENTED</TrackMetaData>", true, ""), None);
+
+    let message = encode_cast_message("sender-0", "receiver-0", "urn:x-cast:com.google.cast.receiver", r#"{"type":"GET_STATUS"}"#);
+    let (namespace, payload) = decode_cast_message(&message).unwrap();
+    assert_eq!(namespace, "urn:x-cast:com.google.cast.receiver");
+    assert_eq!(payload, r#"{"type":"GET_STATUS"}"#);
+    assert!(decode_cast_message(&message[..message.len() - 1]).is_err());
+
+    let cast = parse_media_status(
+        r#"{"type": "MEDIA_STATUS", "status": [{"playerState": "PAUSED", "currentTime": 12.5,
+            "media": {"duration": 200.0, "metadata": {"title": "Song", "artist": "Band", "albumName": "Album",
+            "images": [{"url": "https://i.example.com/cover.jpg"}]}}}]}"#,
+    )
+    .unwrap();
+    assert!(!cast.is_playing);
+    assert_eq!((cast.progress_ms, cast.duration_ms), (Some(12_500), Some(200_000)));
+    assert_eq!(cast.album_art_url.as_deref(), Some("https://i.example.com/cover.jpg"));
+    assert_eq!(cast.source, NowPlayingSource::Chromecast);
+    assert_eq!(parse_media_status(r#"{"status": [{"playerState": "IDLE"}]}"#), None);
+    assert_eq!(parse_media_status(r#"{"status": []}"#), None);
+
+    // A speaker playing in the room beats a paused Cast session
+    assert_eq!(pick(&[None, Some(cast), Some(sonos), None]).unwrap().source, NowPlayingSource::Sonos);
+}
+
+#[test]
+fn test_tasks_from_todoist_and_file() {
+    use chrono::NaiveDate;
+    use idleview_lib::tasks::{parse_tasks_file, parse_todoist, TaskSource};
+
+    let today = NaiveDate::from_ymd_opt(2026, 5, 4).unwrap();
+    let (tasks, cursor) = parse_todoist(
+        r#"{"results": [
+            {"id": "1", "content": "Pay rent", "checked": false, "due": {"date": "2026-05-01"}},
+            {"id": "2", "content": "Call mum", "due": {"date": "2026-05-04T18:00:00"}},
+            {"id": "3", "content": "Done already", "checked": true, "due": {"date": "2026-05-04"}},
+            {"id": "4", "content": "  "}],
+            "next_cursor": "abc"}"#,
+        today,
+    )
+    .unwrap();
+    assert_eq!(tasks.len(), 2);
+    assert_eq!((tasks[0].content.as_str(), tasks[0].due.as_deref()), ("Pay rent", Some("2026-05-01")));
+    assert!(tasks[0].overdue);
+    assert_eq!(tasks[1].due.as_deref(), Some("2026-05-04"));
+    assert!(!tasks[1].overdue);
+    assert_eq!(tasks[1].source, TaskSource::Todoist);
+    assert_eq!(cursor.as_deref(), Some("abc"));
+    assert!(parse_todoist(r#"{"error": "Forbidden"}"#, today).unwrap_err().contains("Forbidden"));
+
+    let file = "# Groceries\n- [ ] Milk\n- [x] Eggs\n* Bread\n\n2026-05-03 Return library books\n2026-05-10 Dentist\nCoffee filters\n";
+    let tasks = parse_tasks_file(file, today);
+    let contents: Vec<&str> = tasks.iter().map(|task| task.content.as_str()).collect();
+    assert_eq!(contents, ["Milk", "Bread", "Return library books", "Coffee filters"]);
+    assert_eq!(tasks[0].due, None);
+    assert!(tasks[2].overdue);
+    assert!(tasks.iter().all(|task| task.source == TaskSource::File));
+}
+
+#[test]
+fn test_countdowns_days_remaining() {
+    use chrono::NaiveDate;
+    use idleview_lib::countdowns::{countdowns_impl, photo_keywords};
+    use idleview_lib::settings_manager::{validate_settings_json, CountdownEvent, CountdownsSettings};
+
+    let event = |name: &str, date: &str, yearly: bool, photo_keywords: Option<&str>| CountdownEvent {
+        name: name.to_string(),
+        date: date.to_string(),
+        yearly,
+        photo_keywords: photo_keywords.map(str::to_string),
+    };
+    let mut settings = CountdownsSettings {
+        events: vec![
+            event("Exam", "2026-06-20", false, None),
+            event("Vacation", "2026-06-03", false, Some("beach")),
+            event("Birthday", "1990-02-29", true, Some("balloons")),
+            event("Moved in", "2026-05-01", false, None),
+        ],
+        ..CountdownsSettings::default()
+    };
+    let today = NaiveDate::from_ymd_opt(2026, 5, 30).unwrap();
+    let countdowns = countdowns_impl(&settings, today);
+    let names: Vec<&str> = countdowns.iter().map(|countdown| countdown.name.as_str()).collect();
+    assert_eq!(names, ["Vacation", "Exam", "Birthday"]);
+    assert_eq!((countdowns[0].days_remaining, countdowns[0].imminent), (4, true));
+    assert_eq!((countdowns[1].days_remaining, countdowns[1].imminent), (21, false));
+    // February 29th falls on the 28th in 2027
+    assert_eq!(countdowns[2].date, "2027-02-28");
+    assert_eq!(photo_keywords(&settings, today).as_deref(), Some("beach"));
+
+    // On the day itself, then gone the day after
+    let on_the_day = NaiveDate::from_ymd_opt(2026, 6, 3).unwrap();
+    assert_eq!(countdowns_impl(&settings, on_the_day)[0].days_remaining, 0);
+    let after = NaiveDate::from_ymd_opt(2026, 6, 4).unwrap();
+    assert!(countdowns_impl(&settings, after).iter().all(|countdown| countdown.name != "Vacation"));
+    assert_eq!(photo_keywords(&settings, after), None);
+    settings.imminent_days = 0;
+    assert_eq!(photo_keywords(&settings, today), None);
+
+    let problems = validate_settings_json(&serde_json::json!({
+        "countdowns": { "events": [{ "name": "Exam", "date": "20/06/2026" }] }
+    }));
+    assert!(problems.iter().any(|problem| problem.starts_with("countdowns.events[0].date")));
+}
+
+#[test]
+fn test_world_clocks() {
+    use chrono::{TimeZone, Utc};
+    use idleview_lib::get_world_clocks_impl;
+    use idleview_lib::settings_manager::{validate_settings_json, Settings, TimeFormat, UnitsSettings, WorldClock};
+
+    let clocks = vec![
+        WorldClock { timezone: "Asia/Tokyo".to_string(), label: Some("Ana".to_string()) },
+        WorldClock { timezone: "America/Argentina/Buenos_Aires".to_string(), label: None },
+        WorldClock { timezone: "Mars/Olympus_Mons".to_string(), label: None },
+    ];
+    let mut units: UnitsSettings = Settings::default().units;
+    units.time_format = TimeFormat::H24;
+    let now = Utc.with_ymd_and_hms(2026, 5, 4, 20, 5, 0).unwrap();
+    let times = get_world_clocks_impl(&clocks, &units, "en", now);
+    assert_eq!(times.len(), 2);
+    assert_eq!((times[0].label.as_str(), times[0].time.as_str()), ("Ana", "05:05"));
+    assert_eq!((times[0].utc_offset.as_str(), times[0].day_offset), ("+09:00", 1));
+    assert_eq!(times[0].day_of_week, "TUESDAY");
+    assert_eq!((times[1].label.as_str(), times[1].time.as_str()), ("Buenos Aires", "17:05"));
+    assert_eq!(times[1].day_offset, 0);
+
+    units.time_format = TimeFormat::H12;
+    assert_eq!(get_world_clocks_impl(&clocks, &units, "ro", now)[0].time, "5:05 AM");
+    assert_eq!(get_world_clocks_impl(&clocks, &units, "ro", now)[0].day_of_week, "MARȚI");
+
+    let problems = validate_settings_json(&serde_json::json!({
+        "display": { "world_clocks": [{ "timezone": "Europe/Bucharest" }, { "timezone": "Europe/Atlantis" }] }
+    }));
+    assert_eq!(problems.len(), 1);
+    assert!(problems[0].starts_with("display.world_clocks[1].timezone"));
+}
+
+#[test]
+fn test_ntp_offset_from_answer() {
+    use idleview_lib::ntp::{describe_offset, parse_response, request_packet};
+
+    let sent = 1_777_000_000_123;
+    let request = request_packet(sent);
+    assert_eq!(request[0], 0x23);
+
+    // A server 5 s behind the system clock, 40 ms away each way
+    let answer = |server_received: i64, server_sent: i64| {
+        let mut packet = [0u8; 48];
+        packet[0] = 0x24;
+        packet[1] = 2;
+        packet[24..32].copy_from_slice(&request[40..48]);
+        packet[32..40].copy_from_slice(&request_packet(server_received)[40..48]);
+        packet[40..48].copy_from_slice(&request_packet(server_sent)[40..48]);
+        packet
+    };
+    let packet = answer(sent + 40 - 5000, sent + 41 - 5000);
+    assert_eq!(parse_response(&packet, sent, sent + 81), Ok(5000));
+    assert_eq!(parse_response(&answer(sent + 3_600_040, sent + 3_600_041), sent, sent + 81), Ok(-3_600_000));
+
+    // Answers to another request, from unsynchronized servers or clients are refused
+    assert!(parse_response(&packet, sent + 1000, sent + 1081).is_err());
+    let mut unsynchronized = packet;
+    unsynchronized[1] = 0;
+    assert!(parse_response(&unsynchronized, sent, sent + 81).is_err());
+    assert!(parse_response(&request, sent, sent + 81).is_err());
+    assert!(parse_response(&packet[..40], sent, sent + 81).is_err());
+
+    assert_eq!(describe_offset(300), "0.3s ahead");
+    assert_eq!(describe_offset(-125_000), "2m 5s behind");
+    assert_eq!(describe_offset(-11_520_000), "3h 12m behind");
+}
+
+#[test]
+fn test_due_alarms() {
+    use chrono::NaiveDate;
+    use idleview_lib::alarms::{due_alarms, next_id};
+    use idleview_lib::settings_manager::{validate_settings_json, Alarm, AlarmDay, AlarmsSettings};
+
+    let alarm = |id: u32, time: &str, days: Vec<AlarmDay>, enabled: bool| Alarm {
+        id,
+        time: time.to_string(),
+        days,
+        label: format!("Alarm {}", id),
+        enabled,
+    };
+    let list = vec![
+        alarm(1, "07:30", vec![AlarmDay::Mon, AlarmDay::Tue], true),
+        alarm(2, "07:30", Vec::new(), true),
+        alarm(3, "07:30", Vec::new(), false),
+        alarm(5, "12:00", vec![AlarmDay::Sat], true),
+    ];
+    // 2026-06-01 is a Monday
+    let monday = NaiveDate::from_ymd_opt(2026, 6, 1).unwrap();
+    let ids = |now| due_alarms(&list, now).iter().map(|alarm| alarm.id).collect::<Vec<_>>();
+    assert_eq!(ids(monday.and_hms_opt(7, 30, 0).unwrap()), [1, 2]);
+    assert_eq!(ids(monday.and_hms_opt(7, 30, 59).unwrap()), [1, 2]);
+    assert!(ids(monday.and_hms_opt(7, 31, 0).unwrap()).is_empty());
+    assert_eq!(ids(NaiveDate::from_ymd_opt(2026, 6, 3).unwrap().and_hms_opt(7, 30, 0).unwrap()), [2]);
+    assert_eq!(ids(NaiveDate::from_ymd_opt(2026, 6, 6).unwrap().and_hms_opt(12, 0, 0).unwrap()), [5]);
+
+    assert_eq!(next_id(&AlarmsSettings { list: list.clone() }), 6);
+    assert_eq!(next_id(&AlarmsSettings::default()), 1);
+
+    let parsed: Alarm = serde_json::from_value(serde_json::json!({ "time": "06:45", "days": ["sat", "sun"] })).unwrap();
+    assert_eq!(parsed.days, [AlarmDay::Sat, AlarmDay::Sun]);
+    assert!(parsed.enabled);
+
+    let problems = validate_settings_json(&serde_json::json!({
+        "alarms": { "list": [{ "id": 1, "time": "7:30pm" }, { "id": 1, "time": "19:30" }] }
+    }));
+    assert!(problems.iter().any(|problem| problem.starts_with("alarms.list[0].time")));
+    assert!(problems.iter().any(|problem| problem.starts_with("alarms.list[1].id")));
+}
+
+#[test]
+fn test_timer_request_and_remaining() {
+    use idleview_lib::timer::{remaining_seconds, validate_timer, TimerRequest, MAX_DURATION};
+    use std::time::Duration;
+
+    let request = |duration: u32, label: &str| TimerRequest { duration, label: label.to_string() };
+    assert!(validate_timer(&request(600, "Pasta")).is_empty());
+    assert_eq!(validate_timer(&request(0, "")).len(), 1);
+    assert_eq!(validate_timer(&request(MAX_DURATION + 1, &"x".repeat(61))).len(), 2);
+
+    let parsed: TimerRequest = serde_json::from_value(serde_json::json!({ "duration": 90 })).unwrap();
+    assert_eq!((parsed.duration, parsed.label.as_str()), (90, ""));
+
+    // Counts show whole seconds rounded up, reaching 0 only at the end
+    assert_eq!(remaining_seconds(Duration::from_millis(9_200)), 10);
+    assert_eq!(remaining_seconds(Duration::from_secs(10)), 10);
+    assert_eq!(remaining_seconds(Duration::from_millis(1)), 1);
+    assert_eq!(remaining_seconds(Duration::ZERO), 0);
+}
+
+#[test]
+fn test_night_mode_schedule() {
+    use chrono::NaiveTime;
+    use idleview_lib::night_mode::{day_brightness, is_night};
+    use idleview_lib::settings_manager::{validate_settings_json, NightModeSettings};
+
+    let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+    let mut settings = NightModeSettings::default();
+    assert!(!is_night(&settings, at(23, 30), None));
+
+    settings.enabled = true;
+    assert!(is_night(&settings, at(23, 0), None));
+    assert!(is_night(&settings, at(3, 0), None));
+    assert!(!is_night(&settings, at(7, 0), None));
+    assert!(!is_night(&settings, at(12, 0), None));
+
+    // Sun times only count with follow_sun, and the window stands in until they're known
+    let sun = Some((at(6, 10), at(20, 45)));
+    assert!(!is_night(&settings, at(21, 0), sun));
+    settings.follow_sun = true;
+    assert!(is_night(&settings, at(21, 0), sun));
+    assert!(!is_night(&settings, at(6, 30), sun));
+    assert!(!is_night(&settings, at(21, 0), None));
+
+    assert_eq!(day_brightness(80, 10), 80);
+    assert_eq!(day_brightness(10, 10), 100);
+
+    let problems = validate_settings_json(&serde_json::json!({
+        "display": { "night_mode": { "start": "11pm", "end": "07:00", "brightness": 150, "photo_interval_factor": 0 } }
+    }));
+    assert!(problems.iter().any(|problem| problem.starts_with("display.night_mode.start")));
+    assert!(problems.iter().any(|problem| problem.starts_with("display.night_mode.brightness")));
+    assert!(problems.iter().any(|problem| problem.starts_with("display.night_mode.photo_interval_factor")));
+}
+
+#[test]
+fn test_display_sleep_window() {
+    use chrono::NaiveTime;
+    use idleview_lib::display_sleep::{in_sleep_window, is_asleep};
+    use idleview_lib::settings_manager::{validate_settings_json, DisplaySleepSettings};
+
+    let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+    let mut settings = DisplaySleepSettings::default();
+    assert!(!in_sleep_window(&settings, at(3, 0)));
+    settings.enabled = true;
+    assert!(in_sleep_window(&settings, at(0, 0)));
+    assert!(in_sleep_window(&settings, at(6, 29)));
+    assert!(!in_sleep_window(&settings, at(6, 30)));
+    settings.start = "22:30".to_string();
+    assert!(in_sleep_window(&settings, at(23, 0)));
+    assert!(!in_sleep_window(&settings, at(12, 0)));
+    // Nothing sleeps until the scheduler runs
+    assert!(!is_asleep());
+
+    let problems = validate_settings_json(&serde_json::json!({
+        "display": { "sleep": { "enabled": true, "end": "6:30am" } }
+    }));
+    assert!(problems.iter().any(|problem| problem.starts_with("display.sleep.end")));
+    assert!(!problems.iter().any(|problem| problem.starts_with("display.sleep.start")));
+}
+
+#[test]
+fn test_presence_tracker() {
+    use idleview_lib::presence::{parse_gpio_value, PresenceTracker};
+    use idleview_lib::settings_manager::validate_settings_json;
+    use std::time::{Duration, Instant};
+
+    let start = Instant::now();
+    let timeout = Duration::from_secs(60);
+    let mut tracker = PresenceTracker::new(start);
+    // The room counts as occupied at first, without any motion seen
+    assert!(tracker.status().present);
+    assert_eq!(tracker.status().last_motion, None);
+    assert!(!tracker.check(start + Duration::from_secs(59), timeout));
+
+    // Motion while present doesn't count as arriving, but restarts the timeout
+    assert!(!tracker.motion(start + Duration::from_secs(30), 1_000));
+    assert!(!tracker.check(start + Duration::from_secs(80), timeout));
+    assert!(tracker.check(start + Duration::from_secs(90), timeout));
+    assert!(!tracker.status().present);
+    // Leaving is reported once
+    assert!(!tracker.check(start + Duration::from_secs(120), timeout));
+
+    assert!(tracker.motion(start + Duration::from_secs(200), 2_000));
+    assert!(tracker.status().present);
+    assert_eq!(tracker.status().last_motion, Some(2_000));
+
+    assert_eq!(parse_gpio_value("1\n"), Some(true));
+    assert_eq!(parse_gpio_value("0\n"), Some(false));
+    assert_eq!(parse_gpio_value(""), None);
+
+    let problems = validate_settings_json(&serde_json::json!({ "display": { "presence": { "idle_timeout": 5 } } }));
+    assert!(problems.iter().any(|problem| problem.starts_with("display.presence.idle_timeout")));
+    let problems = validate_settings_json(&serde_json::json!({ "display": { "presence": { "idle_timeout": 300 } } }));
+    assert!(problems.is_empty());
+}
+
+#[test]
+fn test_burn_in_pixel_shift() {
+    use idleview_lib::burn_in::{pixel_shift, refresh_due, PixelShift};
+    use idleview_lib::settings_manager::validate_settings_json;
+    use std::time::Duration;
+
+    assert_eq!(pixel_shift(5, 0), PixelShift { x: 0, y: 0 });
+    // Once round at one pixel, then at two, then back to one
+    assert_eq!(pixel_shift(0, 2), PixelShift { x: -1, y: -1 });
+    assert_eq!(pixel_shift(3, 2), PixelShift { x: 1, y: 0 });
+    assert_eq!(pixel_shift(8, 2), PixelShift { x: -2, y: -2 });
+    assert_eq!(pixel_shift(16, 2), PixelShift { x: -1, y: -1 });
+    for step in 0..100 {
+        let shift = pixel_shift(step, 6);
+        assert!(shift.x.abs() <= 6 && shift.y.abs() <= 6);
+        assert_ne!(shift, PixelShift { x: 0, y: 0 });
+    }
+
+    assert!(!refresh_due(Duration::from_secs(59 * 60), 60));
+    assert!(refresh_due(Duration::from_secs(60 * 60), 60));
+    assert!(!refresh_due(Duration::from_secs(24 * 60 * 60), 0));
+
+    let problems = validate_settings_json(&serde_json::json!({
+        "display": { "burn_in": { "refresh_mode": "flash", "max_shift": 200, "shift_interval": 120 } }
+    }));
+    assert!(problems.iter().any(|problem| problem.starts_with("display.burn_in.refresh_mode")));
+    assert!(problems.iter().any(|problem| problem.starts_with("display.burn_in.max_shift")));
+    assert!(!problems.iter().any(|problem| problem.starts_with("display.burn_in.shift_interval")));
+}
+
+#[test]
+fn test_monitor_overrides() {
+    use idleview_lib::monitors::{settings_for, window_label};
+    use idleview_lib::settings_manager::{validate_settings_json, MonitorOverride, Settings};
+
+    let mut settings = Settings::default();
+    settings.monitors.overrides.push(MonitorOverride {
+        monitor: "HDMI-A-2".to_string(),
+        settings: serde_json::json!({ "photos": { "query": { "fixed": "mountains" } } }),
+    });
+
+    let second = settings_for(&settings, Some("HDMI-A-2")).unwrap();
+    assert_eq!(second.photos.query.fixed.as_deref(), Some("mountains"));
+    // Everything the override leaves out stays as it is
+    assert_eq!(second.photos.refresh_interval, settings.photos.refresh_interval);
+    assert_eq!(settings_for(&settings, Some("HDMI-A-1")).unwrap().photos.query.fixed, None);
+    assert_eq!(settings_for(&settings, None).unwrap().photos.query.fixed, None);
+
+    assert_eq!(window_label(1), "monitor-1");
+
+    let problems = validate_settings_json(&serde_json::json!({
+        "monitors": { "overrides": [
+            { "monitor": "", "settings": {} },
+            { "monitor": "DP-1", "settings": { "units": { "temperature_unit": "kelvin" } } }
+        ] }
+    }));
+    assert!(problems.iter().any(|problem| problem.starts_with("monitors.overrides[0].monitor")));
+    assert!(problems.iter().any(|problem| problem.starts_with("monitors.overrides[1].settings.units.temperature_unit")));
+}
+
+#[test]
+fn test_watchdog_escalation() {
+    use idleview_lib::settings_manager::validate_settings_json;
+    use idleview_lib::watchdog::{next_action, WatchdogAction};
+    use std::time::Duration;
+
+    let timeout = Duration::from_secs(90);
+    let secs = Duration::from_secs;
+    assert_eq!(next_action(secs(30), None, 0, timeout), None);
+    assert_eq!(next_action(secs(90), None, 0, timeout), Some(WatchdogAction::Reload));
+    // Each step gets a timeout to bring the heartbeats back before the next
+    assert_eq!(next_action(secs(120), Some(secs(30)), 1, timeout), None);
+    assert_eq!(next_action(secs(180), Some(secs(90)), 1, timeout), Some(WatchdogAction::Recreate));
+    assert_eq!(next_action(secs(270), Some(secs(90)), 2, timeout), Some(WatchdogAction::Restart));
+
+    let problems = validate_settings_json(&serde_json::json!({ "watchdog": { "timeout": 10 } }));
+    assert!(problems.iter().any(|problem| problem.starts_with("watchdog.timeout")));
+}
+
+#[test]
+fn test_gpu_temperature() {
+    use idleview_lib::format_temperature;
+    use idleview_lib::metrics::{Gauges, Metrics};
+    use idleview_lib::settings_manager::{Settings, TemperatureUnit};
+    use idleview_lib::thermal::parse_vcgencmd_temp;
+
+    assert_eq!(parse_vcgencmd_temp("temp=48.3'C\n"), Some(48.3));
+    assert_eq!(parse_vcgencmd_temp("error=1 error_msg=\"Command not registered\""), None);
+
+    let mut units = Settings::default().units;
+    assert_eq!(format_temperature(57.6, &units), "58 °C");
+    units.temperature_unit = TemperatureUnit::Fahrenheit;
+    assert_eq!(format_temperature(50.0, &units), "122 °F");
+
+    let text = Metrics::new().render(&Gauges { gpu_temperature: Some(61.2), ..Default::default() });
+    assert!(text.contains("idleview_gpu_temperature_celsius 61.2"));
+    assert!(!text.contains("idleview_cpu_temperature_celsius"));
+}
+
+#[test]
+fn test_app_memory() {
+    use idleview_lib::metrics::{Gauges, Metrics};
+    use idleview_lib::system::{process_tree_memory, system_status};
+
+    // The app (10) with a webview (11) that started a renderer (12); 20 is someone else's
+    let processes = [(1, None, 5), (10, Some(1), 100), (11, Some(10), 40), (12, Some(11), 300), (20, Some(1), 7)];
+    assert_eq!(process_tree_memory(&processes, 10), 440);
+    assert_eq!(process_tree_memory(&processes, 12), 300);
+    assert_eq!(process_tree_memory(&processes, 99), 0);
+
+    let memory = system_status().memory;
+    assert!(memory.swap_total >= memory.swap_used);
+    assert!(memory.app.is_some_and(|app| app > 0));
+
+    let text = Metrics::new().render(&Gauges { memory: Some(memory), ..Default::default() });
+    assert!(text.contains("idleview_memory_bytes{memory=\"app\"}"));
+}
+
+#[test]
+fn test_low_disk_space() {
+    use idleview_api::ServerEvent;
+    use idleview_lib::access_log::{append_line, remove_rotated};
+    use idleview_lib::disk_space::{space_crossed, DiskStatus};
+
+    const MB: u64 = 1024 * 1024;
+    let mut low = false;
+    assert!(!space_crossed(500 * MB, 200 * MB, &mut low));
+    assert!(space_crossed(150 * MB, 200 * MB, &mut low));
+    assert!(!space_crossed(100 * MB, 200 * MB, &mut low));
+    // Freeing a little isn't enough to count as a new shortage
+    assert!(!space_crossed(220 * MB, 200 * MB, &mut low));
+    assert!(!space_crossed(180 * MB, 200 * MB, &mut low));
+    assert!(!space_crossed(300 * MB, 200 * MB, &mut low));
+    assert!(space_crossed(199 * MB, 200 * MB, &mut low));
+
+    let disk = DiskStatus { mount_point: "/".to_string(), total: 8000 * MB, available: 150 * MB };
+    let event = ServerEvent::DiskSpaceLow(disk);
+    assert_eq!(event.event_type(), "disk-space-low");
+
+    let dir = std::env::temp_dir().join(format!("idleview-disk-space-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("access.log");
+    for line in ["first", "second", "third"] {
+        append_line(&path, line, 10).unwrap();
+    }
+    assert_eq!(remove_rotated(&path), Ok(2));
+    assert!(!dir.join("access.log.1").exists());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "third\n");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_connectivity_tracker() {
+    use idleview_lib::connectivity::ConnectivityTracker;
+    use idleview_lib::settings_manager::validate_settings_json;
+
+    let mut tracker = ConnectivityTracker::default();
+    assert!(tracker.status().online);
+    assert!(!tracker.record(true, 1000));
+    assert_eq!(tracker.status().last_online, Some(1000));
+
+    // One failed check is a hiccup, two are an outage
+    assert!(!tracker.record(false, 2000));
+    assert!(tracker.status().online && tracker.retrying());
+    assert!(tracker.record(false, 3000));
+    assert!(!tracker.status().online);
+    assert_eq!(tracker.status().last_change, Some(3000));
+    assert!(!tracker.record(false, 4000));
+
+    assert!(tracker.record(true, 5000));
+    let status = tracker.status();
+    assert!(status.online && !tracker.retrying());
+    assert_eq!((status.last_online, status.last_change), (Some(5000), Some(5000)));
+
+    let problems = validate_settings_json(&serde_json::json!({
+        "connectivity": { "check_url": "example.com", "interval": 1 }
+    }));
+    assert!(problems.iter().any(|problem| problem.starts_with("connectivity.check_url")));
+    assert!(problems.iter().any(|problem| problem.starts_with("connectivity.interval")));
+}
+
+#[test]
+fn test_wifi_signal() {
+    use idleview_lib::system::parse_proc_net_wireless;
+
+    let wireless = "Inter-| sta-|   Quality        |   Discarded packets               | Missed | WE\n \
+                    face | tus | link level noise |  nwid  crypt   frag  retry   misc | beacon | 22\n \
+                    wlan0: 0000   49.  -61.  -256        0      0      0      0     12        0\n";
+    let signal = parse_proc_net_wireless(wireless).unwrap();
+    assert_eq!(signal.interface, "wlan0");
+    assert_eq!(signal.quality, 70);
+    assert_eq!(signal.signal_dbm, Some(-61));
+
+    // Drivers without dBm give a relative level
+    let relative = parse_proc_net_wireless(&wireless.replace("-61.", "80.")).unwrap();
+    assert_eq!(relative.signal_dbm, None);
+
+    // Headers alone: no wireless interface
+    assert!(parse_proc_net_wireless(&wireless.lines().take(2).collect::<Vec<_>>().join("\n")).is_none());
+}
+
+#[test]
+fn test_low_battery() {
+    use idleview_lib::battery::{battery_crossed, is_low, BatteryState, BatteryStatus};
+    use idleview_lib::settings_manager::validate_settings_json;
+
+    let battery = |percent: f32, state: BatteryState| BatteryStatus { percent, state, time_remaining: None };
+    assert!(is_low(&battery(15.0, BatteryState::Discharging), 15));
+    assert!(!is_low(&battery(10.0, BatteryState::Charging), 15));
+    assert!(!is_low(&battery(5.0, BatteryState::Discharging), 0));
+
+    let mut low = false;
+    assert!(!battery_crossed(&battery(40.0, BatteryState::Discharging), 15, &mut low));
+    assert!(battery_crossed(&battery(14.0, BatteryState::Discharging), 15, &mut low));
+    // Reported once per discharge, even with the charge bobbing around the limit
+    assert!(!battery_crossed(&battery(16.0, BatteryState::Discharging), 15, &mut low));
+    assert!(!battery_crossed(&battery(15.0, BatteryState::Discharging), 15, &mut low));
+    assert!(low);
+    // Plugging in ends it
+    assert!(!battery_crossed(&battery(15.0, BatteryState::Charging), 15, &mut low));
+    assert!(!low);
+    assert!(battery_crossed(&battery(14.0, BatteryState::Discharging), 15, &mut low));
+
+    let problems = validate_settings_json(&serde_json::json!({ "display": { "low_battery_warning": 150 } }));
+    assert!(problems.iter().any(|problem| problem.starts_with("display.low_battery_warning")));
+}
+
+#[test]
+fn test_acpi_thermal_zone_celsius() {
+    use idleview_lib::thermal::acpi_thermal_zone_celsius;
+
+    // Tenths of a kelvin
+    let celsius = acpi_thermal_zone_celsius(3132).unwrap();
+    assert!((celsius - 40.05).abs() < 0.01);
+    // Boards without a real sensor report about 0 °C, or nothing sensible
+    assert_eq!(acpi_thermal_zone_celsius(2732), None);
+    assert_eq!(acpi_thermal_zone_celsius(0), None);
+    assert_eq!(acpi_thermal_zone_celsius(6000), None);
+}
+
+#[test]
+fn test_smc_values() {
+    use idleview_lib::thermal::decode_smc_value;
+
+    // Intel: signed 7.8 fixed point
+    assert_eq!(decode_smc_value(b"sp78", &[0x30, 0x80]), Some(48.5));
+    assert_eq!(decode_smc_value(b"sp78", &[0x30]), None);
+    // Apple Silicon: little-endian floats
+    assert_eq!(decode_smc_value(b"flt ", &42.25f32.to_le_bytes()), Some(42.25));
+    assert_eq!(decode_smc_value(b"ui8 ", &[1]), None);
+}
+
+#[test]
+fn test_settings_reads_redact_credentials() {
+    use axum::http::Method;
+    use idleview_lib::http_server::needs_api_token;
+    use idleview_lib::settings_manager::{redacted, restore_secrets, Settings, Webhook, REDACTED};
+
+    let mut settings = Settings::default();
+    settings.weather.api_keys.openweathermap = Some("owm-key".to_string());
+    settings.location.owntracks.password = Some("broker-password".to_string());
+    settings.photos.api_keys.pexels = Some("pexels-key".to_string());
+    settings.webhooks.list.push(Webhook { url: "http://hooks.lan/frame".to_string(), events: Vec::new(), secret: Some("hmac-secret".to_string()) });
+    settings.mqtt.password = Some("mqtt-password".to_string());
+    settings.calendar.google.client_secret = Some("google-secret".to_string());
+    settings.stocks.api_keys.finnhub = Some("finnhub-key".to_string());
+    settings.music.lastfm.api_key = Some("lastfm-key".to_string());
+    settings.sports.api_keys.football_data = Some("football-key".to_string());
+
+    // GET /api/v1/settings is open to anyone on the network, and gets the keys redacted
+    assert!(!needs_api_token(&Method::GET, "/settings"));
+    let body = serde_json::to_value(redacted(&settings).unwrap()).unwrap();
+    assert_eq!(body["weather"]["api_keys"]["openweathermap"], REDACTED);
+    assert!(body["weather"]["api_keys"]["openmeteo"].is_null());
+    assert_eq!(body["location"]["owntracks"]["password"], REDACTED);
+    assert_eq!(body["photos"]["api_keys"]["pexels"], REDACTED);
+    assert_eq!(body["webhooks"]["list"][0]["secret"], REDACTED);
+    assert_eq!(body["mqtt"]["password"], REDACTED);
+    assert_eq!(body["calendar"]["google"]["client_secret"], REDACTED);
+    assert_eq!(body["stocks"]["api_keys"]["finnhub"], REDACTED);
+    assert_eq!(body["music"]["lastfm"]["api_key"], REDACTED);
+    assert_eq!(body["sports"]["api_keys"]["football_data"], REDACTED);
+    for secret in ["owm-key", "broker-password", "pexels-key", "hmac-secret", "mqtt-password", "google-secret", "finnhub-key", "lastfm-key", "football-key"] {
+        assert!(!body.to_string().contains(secret), "{} leaked", secret);
+    }
+
+    // The backup has them in full, so it needs the token
+    assert!(needs_api_token(&Method::GET, "/settings/backup"));
+
+    // Saving what was read keeps the stored key
+    let mut saved = body.clone();
+    restore_secrets(&mut saved, &serde_json::to_value(&settings).unwrap(), "");
+    assert_eq!(saved["weather"]["api_keys"]["openweathermap"], "owm-key");
+    assert_eq!(saved["webhooks"]["list"][0]["secret"], "hmac-secret");
+}
+
+#[test]
+fn test_scheduled_scene_hop_reverts_every_override() {
+    use idleview_lib::scenes::scheduled_revert;
+    use idleview_lib::settings_manager::{with_overrides, Settings, TemperatureUnit};
+    use serde_json::json;
+
+    let original = Settings::default();
+    let movie = json!({ "photos": { "refresh_interval": 5 } });
+    let party = json!({ "photos": { "refresh_interval": 10 }, "units": { "temperature_unit": "fahrenheit" } });
+
+    // None -> movie night
+    let revert = scheduled_revert(&serde_json::Value::Null, &serde_json::to_value(&original).unwrap(), &movie);
+    let during_movie = with_overrides(&original, &movie).unwrap();
+
+    // Movie night -> party: the refresh interval saved for movie night is the original one
+    let revert = scheduled_revert(&revert, &serde_json::to_value(&during_movie).unwrap(), &party);
+    let during_party = with_overrides(&during_movie, &party).unwrap();
+    assert_eq!(during_party.units.temperature_unit, TemperatureUnit::Fahrenheit);
+
+    // Party -> none: both the shared key and the key only the party overrides come back
+    let reverted = with_overrides(&during_party, &revert).unwrap();
+    assert_eq!(reverted.photos.refresh_interval, original.photos.refresh_interval);
+    assert_eq!(reverted.units.temperature_unit, TemperatureUnit::Celsius);
+}
+
+#[test]
+fn test_private_calendar_feed_urls_are_redacted() {
+    use idleview_lib::settings_manager::{redacted, restore_secrets, with_overrides, CalendarFeed, NewsFeed, Settings, REDACTED};
+    use serde_json::json;
+
+    let secret_url = "https://calendar.google.com/calendar/ical/family%40gmail.com/private-0123abcd/basic.ics";
+    let mut settings = Settings::default();
+    settings.calendar.feeds.push(CalendarFeed { name: "Family".to_string(), url: secret_url.to_string() });
+    settings.news.feeds.push(NewsFeed { name: "BBC".to_string(), url: "https://feeds.bbci.co.uk/news/rss.xml".to_string(), limit: 5 });
+
+    let body = serde_json::to_value(redacted(&settings).unwrap()).unwrap();
+    assert_eq!(body["calendar"]["feeds"][0]["url"], REDACTED);
+    assert_eq!(body["calendar"]["feeds"][0]["name"], "Family");
+    assert!(!body.to_string().contains("private-0123abcd"));
+    // Public feed URLs elsewhere aren't credentials
+    assert_eq!(body["news"]["feeds"][0]["url"], "https://feeds.bbci.co.uk/news/rss.xml");
+
+    // A PATCH sending the feeds back, with a new one in front, keeps the stored URL by the feed's name
+    let stored = serde_json::to_value(&settings).unwrap();
+    let patch = json!({ "calendar": { "feeds": [
+        { "name": "Work", "url": "https://work.example.com/team.ics" },
+        { "name": "Family", "url": REDACTED },
+    ] } });
+    let mut patched = serde_json::to_value(with_overrides(&settings, &patch).unwrap()).unwrap();
+    restore_secrets(&mut patched, &stored, "");
+    assert_eq!(patched["calendar"]["feeds"][0]["url"], "https://work.example.com/team.ics");
+    assert_eq!(patched["calendar"]["feeds"][1]["url"], secret_url);
+}